@@ -0,0 +1,356 @@
+//! Compact AST for large sources.
+//!
+//! [`ArenaAst`] holds every item of a program in a few flat vectors, with names and
+//! text interned once in an [`Interner`] and referred to by [`Symbol`]. Building one
+//! allocates per distinct string rather than per item, cloning it copies a handful
+//! of vectors, and comparing names compares integers.
+//!
+//! Comments, blank lines and [`NodeId`](crate::NodeId)s aren't kept: it's meant for
+//! analysing big programs, not for rewriting them. [`ArenaAst::to_element`] gives the
+//! regular tree when needed.
+
+use std::collections::HashMap;
+use std::hash::{BuildHasherDefault, Hasher};
+use std::num::NonZeroU32;
+use std::sync::Arc;
+
+use crate::{assign_ids, Element, Node, NodeId, Span, Trivia};
+
+/// An interned string; only meaningful with the [`Interner`] that made it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Symbol(NonZeroU32);
+
+impl Symbol {
+    fn index(self) -> usize {
+        self.0.get() as usize - 1
+    }
+}
+
+/// Deduplicated storage for strings, each kept once and handed out as a [`Symbol`]
+#[derive(Debug, Clone, Default)]
+pub struct Interner {
+    strings: Vec<Arc<str>>,
+    symbols: HashMap<Arc<str>, Symbol, BuildHasherDefault<FxHasher>>,
+}
+
+impl Interner {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The symbol for `text`, storing it if it's new
+    pub fn intern(&mut self, text: &str) -> Symbol {
+        if let Some(symbol) = self.symbols.get(text) {
+            return *symbol;
+        }
+        let text: Arc<str> = Arc::from(text);
+        let symbol = Symbol(NonZeroU32::new(self.strings.len() as u32 + 1).expect("too many interned strings"));
+        self.strings.push(text.clone());
+        self.symbols.insert(text, symbol);
+        symbol
+    }
+
+    /// The symbol for `text` if it has been interned
+    pub fn get(&self, text: &str) -> Option<Symbol> {
+        self.symbols.get(text).copied()
+    }
+
+    pub fn resolve(&self, symbol: Symbol) -> &str {
+        &self.strings[symbol.index()]
+    }
+
+    /// Number of distinct strings
+    pub fn len(&self) -> usize {
+        self.strings.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.strings.is_empty()
+    }
+}
+
+/// Multiply-rotate hash as used in rustc: much faster than the default SipHash on the
+/// short identifiers that make up most of a program, and not exposed to untrusted keys
+#[derive(Default)]
+struct FxHasher {
+    hash: u64,
+}
+
+impl FxHasher {
+    const SEED: u64 = 0x51_7c_c1_b7_27_22_0a_95;
+
+    fn add(&mut self, word: u64) {
+        self.hash = (self.hash.rotate_left(5) ^ word).wrapping_mul(Self::SEED);
+    }
+}
+
+impl Hasher for FxHasher {
+    fn write(&mut self, bytes: &[u8]) {
+        let mut chunks = bytes.chunks_exact(8);
+        for chunk in &mut chunks {
+            self.add(u64::from_le_bytes(chunk.try_into().unwrap()));
+        }
+        for byte in chunks.remainder() {
+            self.add(u64::from(*byte));
+        }
+    }
+
+    fn write_u8(&mut self, byte: u8) {
+        self.add(u64::from(byte));
+    }
+
+    fn finish(&self) -> u64 {
+        self.hash
+    }
+}
+
+/// Index of an item in its [`ArenaAst`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ItemId(u32);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ItemKind {
+    /// A block with children, like [`Element`]
+    Block,
+    /// [`Node::ChildLine`]
+    Line,
+    /// [`Node::KeyValue`]
+    Property,
+    /// [`Node::Const`]
+    Const,
+}
+
+/// One item of an [`ArenaAst`]; its annotations and children are in the AST
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Item {
+    pub kind: ItemKind,
+    /// Block name (`next:WebApp` for targets), line id, property key or constant name
+    pub name: Symbol,
+    /// Keyword before a line's id, e.g. `page`
+    pub modifier: Option<Symbol>,
+    /// Header text of a block or line, e.g. `(id: string)`
+    pub signature: Option<Symbol>,
+    /// Raw value of a property or constant
+    pub value: Option<Symbol>,
+    pub span: Span,
+    annotations: Range,
+    children: Range,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+struct Range {
+    start: u32,
+    len: u32,
+}
+
+impl Range {
+    fn of<T>(items: &[T], start: usize) -> Range {
+        Range { start: start as u32, len: (items.len() - start) as u32 }
+    }
+
+    fn slice<T>(self, items: &[T]) -> &[T] {
+        &items[self.start as usize..(self.start + self.len) as usize]
+    }
+}
+
+/// `@name(args)` on an item of an [`ArenaAst`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Annotation {
+    pub name: Symbol,
+    pub args: Option<Symbol>,
+}
+
+/// A program stored flat; see the [module docs](self)
+///
+/// Items are added children first, and the last block added is the root.
+#[derive(Debug, Clone, Default)]
+pub struct ArenaAst {
+    strings: Interner,
+    items: Vec<Item>,
+    annotations: Vec<Annotation>,
+    /// Children of every block, each block's contiguous
+    children: Vec<ItemId>,
+    root: Option<ItemId>,
+}
+
+impl ArenaAst {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// An AST with room for about `items` items, to spare regrowing while building
+    pub fn with_capacity(items: usize) -> Self {
+        Self {
+            items: Vec::with_capacity(items),
+            children: Vec::with_capacity(items),
+            ..Self::default()
+        }
+    }
+
+    pub fn intern(&mut self, text: &str) -> Symbol {
+        self.strings.intern(text)
+    }
+
+    pub fn strings(&self) -> &Interner {
+        &self.strings
+    }
+
+    /// Text of a symbol of this AST
+    pub fn str(&self, symbol: Symbol) -> &str {
+        self.strings.resolve(symbol)
+    }
+
+    /// The symbol for `text`, or `None` when no item uses it, in which case nothing
+    /// in the AST can match it
+    pub fn symbol(&self, text: &str) -> Option<Symbol> {
+        self.strings.get(text)
+    }
+
+    /// Add a block whose children were added before it
+    pub fn block(&mut self, name: Symbol, signature: Option<Symbol>, annotations: &[Annotation], children: &[ItemId], span: Span) -> ItemId {
+        let start = self.children.len();
+        self.children.extend_from_slice(children);
+        let children = Range::of(&self.children, start);
+        let id = self.push(ItemKind::Block, name, None, signature, None, annotations, span);
+        self.items[id.0 as usize].children = children;
+        self.root = Some(id);
+        id
+    }
+
+    pub fn line(&mut self, modifier: Option<Symbol>, id: Symbol, signature: Option<Symbol>, annotations: &[Annotation], span: Span) -> ItemId {
+        self.push(ItemKind::Line, id, modifier, signature, None, annotations, span)
+    }
+
+    pub fn property(&mut self, key: Symbol, value: Symbol, span: Span) -> ItemId {
+        self.push(ItemKind::Property, key, None, None, Some(value), &[], span)
+    }
+
+    pub fn constant(&mut self, name: Symbol, value: Symbol, span: Span) -> ItemId {
+        self.push(ItemKind::Const, name, None, None, Some(value), &[], span)
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn push(
+        &mut self,
+        kind: ItemKind,
+        name: Symbol,
+        modifier: Option<Symbol>,
+        signature: Option<Symbol>,
+        value: Option<Symbol>,
+        annotations: &[Annotation],
+        span: Span,
+    ) -> ItemId {
+        let start = self.annotations.len();
+        self.annotations.extend_from_slice(annotations);
+        let id = ItemId(self.items.len() as u32);
+        self.items.push(Item {
+            kind,
+            name,
+            modifier,
+            signature,
+            value,
+            span,
+            annotations: Range::of(&self.annotations, start),
+            children: Range::default(),
+        });
+        id
+    }
+
+    /// The program block, once one has been added
+    pub fn root(&self) -> Option<ItemId> {
+        self.root
+    }
+
+    pub fn item(&self, id: ItemId) -> &Item {
+        &self.items[id.0 as usize]
+    }
+
+    /// Children of a block, in source order; empty for other items
+    pub fn children(&self, id: ItemId) -> &[ItemId] {
+        self.item(id).children.slice(&self.children)
+    }
+
+    pub fn annotations(&self, id: ItemId) -> &[Annotation] {
+        self.item(id).annotations.slice(&self.annotations)
+    }
+
+    /// Every item, children before their block; use [`children`](Self::children) from
+    /// the [`root`](Self::root) for source order
+    pub fn items(&self) -> impl Iterator<Item = (ItemId, &Item)> {
+        self.items.iter().enumerate().map(|(index, item)| (ItemId(index as u32), item))
+    }
+
+    /// Number of items, the program included
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    /// The regular tree below the root, with ids assigned; empty when there's no root
+    pub fn to_element(&self) -> Element {
+        let mut program = match self.root {
+            Some(root) => self.element(root),
+            None => Element {
+                name: "Program".to_string(),
+                annotations: Vec::new(),
+                children: Vec::new(),
+                signature: None,
+                span: Span::default(),
+                id: NodeId::default(),
+                trivia: Trivia::default(),
+                body_trivia: Trivia::default(),
+            },
+        };
+        assign_ids(&mut program);
+        program
+    }
+
+    fn element(&self, id: ItemId) -> Element {
+        let item = self.item(id);
+        Element {
+            name: self.str(item.name).to_string(),
+            annotations: self.owned_annotations(id),
+            children: self.children(id).iter().map(|child| self.node(*child)).collect(),
+            signature: self.owned(item.signature),
+            span: item.span,
+            id: NodeId::default(),
+            trivia: Trivia::default(),
+            body_trivia: Trivia::default(),
+        }
+    }
+
+    fn node(&self, id: ItemId) -> Node {
+        let item = self.item(id);
+        let name = || self.str(item.name).to_string();
+        let value = || self.owned(item.value).unwrap_or_default();
+        match item.kind {
+            ItemKind::Block => Node::Element(self.element(id)),
+            ItemKind::Line => Node::ChildLine {
+                modifier: self.owned(item.modifier),
+                id: name(),
+                signature: self.owned(item.signature),
+                annotations: self.owned_annotations(id),
+                span: item.span,
+                trivia: Trivia::default(),
+            },
+            ItemKind::Property => Node::KeyValue { key: name(), value: value(), span: item.span, trivia: Trivia::default() },
+            ItemKind::Const => Node::Const { name: name(), value: value(), span: item.span, trivia: Trivia::default() },
+        }
+    }
+
+    fn owned(&self, symbol: Option<Symbol>) -> Option<String> {
+        symbol.map(|symbol| self.str(symbol).to_string())
+    }
+
+    fn owned_annotations(&self, id: ItemId) -> Vec<crate::Annotation> {
+        self.annotations(id).iter()
+            .map(|annotation| crate::Annotation {
+                name: self.str(annotation.name).to_string(),
+                args: self.owned(annotation.args),
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,196 @@
+//! Fluent construction of ASTs from code, for code-mod tools and tests.
+//!
+//! Built nodes have empty spans and no trivia. `==` compares spans too, so compare a
+//! built tree with a parsed one through [`diff`](crate::diff) instead.
+//!
+//! ```ignore
+//! let program = ElementBuilder::program()
+//!     .child(ElementBuilder::target("next", "WebApp")
+//!         .child(ElementBuilder::new("Routes")
+//!             .line("home")
+//!             .child(LineBuilder::new("dashboard").annotation("auth"))))
+//!     .build();
+//! ```
+
+use crate::{assign_ids, Annotation, Element, Node, NodeId, Span, Trivia, TriviaPiece};
+
+/// Builds an [`Element`]
+#[derive(Debug, Clone)]
+pub struct ElementBuilder {
+    element: Element,
+    /// Whether this is the program, whose ids are assigned on [`build`](Self::build)
+    program: bool,
+}
+
+impl ElementBuilder {
+    /// A block named `name`, e.g. `Routes`
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            element: Element {
+                name: name.into(),
+                annotations: Vec::new(),
+                children: Vec::new(),
+                signature: None,
+                span: Span::default(),
+                id: NodeId::default(),
+                trivia: Trivia::default(),
+                body_trivia: Trivia::default(),
+            },
+            program: false,
+        }
+    }
+
+    /// A top-level target block such as `next WebApp { ... }`, named `kind:name` as the parser names it
+    pub fn target(kind: &str, name: &str) -> Self {
+        Self::new(format!("{}:{}", kind, name))
+    }
+
+    /// The program, holding the top-level blocks
+    pub fn program() -> Self {
+        Self { program: true, ..Self::new("Program") }
+    }
+
+    /// Header text between the name and the opening brace, e.g. `(database: postgres)`
+    pub fn signature(mut self, signature: impl Into<String>) -> Self {
+        self.element.signature = Some(signature.into());
+        self
+    }
+
+    /// Add `@name` to the block
+    pub fn annotation(mut self, name: impl Into<String>) -> Self {
+        self.element.annotations.push(Annotation { name: name.into(), args: None });
+        self
+    }
+
+    /// Add `@name(args)` to the block
+    pub fn annotation_with(mut self, name: impl Into<String>, args: impl Into<String>) -> Self {
+        self.element.annotations.push(Annotation { name: name.into(), args: Some(args.into()) });
+        self
+    }
+
+    /// Add a `// text` comment line above the block
+    pub fn comment(mut self, text: &str) -> Self {
+        self.element.trivia.leading.push(TriviaPiece::Comment(format!("// {}", text)));
+        self
+    }
+
+    /// Append a child: a nested block, a line or any other node
+    pub fn child(mut self, child: impl Into<Node>) -> Self {
+        self.element.children.push(child.into());
+        self
+    }
+
+    pub fn children<I>(mut self, children: I) -> Self
+    where
+        I: IntoIterator,
+        I::Item: Into<Node>,
+    {
+        self.element.children.extend(children.into_iter().map(Into::into));
+        self
+    }
+
+    /// Append a plain line such as `home`
+    pub fn line(self, id: impl Into<String>) -> Self {
+        self.child(LineBuilder::new(id))
+    }
+
+    /// Append a `key: value` property
+    pub fn property(self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.child(Node::KeyValue { key: key.into(), value: value.into(), span: Span::default(), trivia: Trivia::default() })
+    }
+
+    /// Append `const name = value`; `value` is the raw text, quotes included
+    pub fn constant(self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.child(Node::Const { name: name.into(), value: value.into(), span: Span::default(), trivia: Trivia::default() })
+    }
+
+    /// The element. Ids are only assigned for the [program](Self::program), since a
+    /// block's id depends on where it ends up; see [`assign_ids`].
+    pub fn build(self) -> Element {
+        let mut element = self.element;
+        if self.program {
+            assign_ids(&mut element);
+        }
+        element
+    }
+}
+
+impl From<ElementBuilder> for Node {
+    fn from(builder: ElementBuilder) -> Self {
+        Node::Element(builder.build())
+    }
+}
+
+impl From<Element> for Node {
+    fn from(element: Element) -> Self {
+        Node::Element(element)
+    }
+}
+
+/// Builds a [`Node::ChildLine`], e.g. `page dashboard(id: string) @auth`
+#[derive(Debug, Clone)]
+pub struct LineBuilder {
+    modifier: Option<String>,
+    id: String,
+    signature: Option<String>,
+    annotations: Vec<Annotation>,
+    trivia: Trivia,
+}
+
+impl LineBuilder {
+    pub fn new(id: impl Into<String>) -> Self {
+        Self { modifier: None, id: id.into(), signature: None, annotations: Vec::new(), trivia: Trivia::default() }
+    }
+
+    /// Keyword before the id, e.g. `page`
+    pub fn modifier(mut self, modifier: impl Into<String>) -> Self {
+        self.modifier = Some(modifier.into());
+        self
+    }
+
+    /// Text after the id, e.g. `(id: string)`
+    pub fn signature(mut self, signature: impl Into<String>) -> Self {
+        self.signature = Some(signature.into());
+        self
+    }
+
+    pub fn annotation(mut self, name: impl Into<String>) -> Self {
+        self.annotations.push(Annotation { name: name.into(), args: None });
+        self
+    }
+
+    pub fn annotation_with(mut self, name: impl Into<String>, args: impl Into<String>) -> Self {
+        self.annotations.push(Annotation { name: name.into(), args: Some(args.into()) });
+        self
+    }
+
+    /// Add a `// text` comment after the line
+    pub fn comment(mut self, text: &str) -> Self {
+        self.trivia.trailing = Some(format!("// {}", text));
+        self
+    }
+
+    pub fn build(self) -> Node {
+        Node::ChildLine {
+            modifier: self.modifier,
+            id: self.id,
+            signature: self.signature,
+            annotations: self.annotations,
+            span: Span::default(),
+            trivia: self.trivia,
+        }
+    }
+}
+
+impl From<LineBuilder> for Node {
+    fn from(builder: LineBuilder) -> Self {
+        builder.build()
+    }
+}
+
+impl Element {
+    /// Start building a block named `name`; see [`ElementBuilder`]
+    pub fn builder(name: impl Into<String>) -> ElementBuilder {
+        ElementBuilder::new(name)
+    }
+}
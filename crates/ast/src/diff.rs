@@ -0,0 +1,153 @@
+//! Structural comparison of two versions of a program.
+//!
+//! Changes are reported per block. Children are matched by identity (the element
+//! name, a line's modifier and id, a property's key or a constant's name) rather
+//! than by position, so moving a block around isn't a change. A block whose own
+//! header or whose lines, properties or constants differ is `Modified`; nested
+//! blocks are compared on their own. Spans, comments and blank lines are ignored.
+
+use std::collections::HashMap;
+
+use crate::{Element, Node, NodeId, Span};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChangeKind {
+    Added,
+    Removed,
+    Modified,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Change {
+    pub kind: ChangeKind,
+    /// Names of the block and its ancestors below the program, outermost first,
+    /// e.g. `["next:WebApp", "Routes"]`. Empty for the program itself.
+    pub path: Vec<String>,
+    /// The block's [`NodeId`]: from the new program, or the old one when removed
+    pub id: NodeId,
+    /// Where the block is in the old source; `None` when added
+    pub old_span: Option<Span>,
+    /// Where the block is in the new source; `None` when removed
+    pub new_span: Option<Span>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AstDiff {
+    /// Block by block in the order of the new program; a block's own change comes
+    /// before those nested in it, and removed blocks after their surviving siblings
+    pub changes: Vec<Change>,
+}
+
+impl AstDiff {
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+
+    /// Whether anything changed in the block at `path` or below it, e.g.
+    /// `affects(&["workspace:shop", "next:WebApp"])`. The program's own lines count
+    /// for every path since they (constants in particular) can reach any block.
+    pub fn affects(&self, path: &[&str]) -> bool {
+        self.changes.iter().any(|change| {
+            change.path.is_empty() || change.path.iter().zip(path).all(|(changed, wanted)| changed == wanted)
+        })
+    }
+}
+
+/// Compare two parsed programs
+pub fn diff(old: &Element, new: &Element) -> AstDiff {
+    let mut diff = AstDiff::default();
+    let mut path = Vec::new();
+    diff_element(old, new, &mut path, &mut diff.changes);
+    diff
+}
+
+/// What identifies a child among its siblings
+fn key(node: &Node) -> (u8, &str, &str) {
+    match node {
+        Node::Element(element) => (0, &element.name, ""),
+        Node::ChildLine { modifier, id, .. } => (1, modifier.as_deref().unwrap_or(""), id),
+        Node::KeyValue { key, .. } => (2, key, ""),
+        Node::Const { name, .. } => (3, name, ""),
+        Node::Expansion { template, .. } => (4, template, ""),
+    }
+}
+
+fn diff_element(old: &Element, new: &Element, path: &mut Vec<String>, changes: &mut Vec<Change>) {
+    // Pair each new child with the next unused old child of the same key
+    let mut unmatched: HashMap<(u8, &str, &str), Vec<&Node>> = HashMap::new();
+    for node in old.children.iter().rev() {
+        unmatched.entry(key(node)).or_default().push(node);
+    }
+    let pairs: Vec<(Option<&Node>, &Node)> = new.children.iter()
+        .map(|node| (unmatched.get_mut(&key(node)).and_then(Vec::pop), node))
+        .collect();
+
+    let mut modified = old.signature != new.signature || old.annotations != new.annotations;
+    let mut nested = Vec::new();
+    for (old_node, new_node) in pairs {
+        match (old_node, new_node) {
+            (Some(Node::Element(old_child)), Node::Element(new_child)) => nested.push((Some(old_child), new_child)),
+            (None, Node::Element(new_child)) => nested.push((None, new_child)),
+            (Some(old_node), new_node) => modified |= !same_leaf(old_node, new_node),
+            (None, _) => modified = true,
+        }
+    }
+    let removed: Vec<&Node> = old.children.iter()
+        .filter(|node| unmatched.get(&key(node)).is_some_and(|left| left.iter().any(|left| std::ptr::eq(*left, *node))))
+        .collect();
+    modified |= removed.iter().any(|node| !matches!(node, Node::Element(_)));
+
+    if modified {
+        changes.push(Change {
+            kind: ChangeKind::Modified,
+            path: path.clone(),
+            id: new.id,
+            old_span: Some(old.span),
+            new_span: Some(new.span),
+        });
+    }
+
+    for (old_child, new_child) in nested {
+        path.push(new_child.name.clone());
+        match old_child {
+            Some(old_child) => diff_element(old_child, new_child, path, changes),
+            None => changes.push(Change {
+                kind: ChangeKind::Added,
+                path: path.clone(),
+                id: new_child.id,
+                old_span: None,
+                new_span: Some(new_child.span),
+            }),
+        }
+        path.pop();
+    }
+
+    for node in removed {
+        if let Node::Element(old_child) = node {
+            path.push(old_child.name.clone());
+            changes.push(Change {
+                kind: ChangeKind::Removed,
+                path: path.clone(),
+                id: old_child.id,
+                old_span: Some(old_child.span),
+                new_span: None,
+            });
+            path.pop();
+        }
+    }
+}
+
+/// Equality of two non-block nodes with the same key, ignoring spans and trivia
+fn same_leaf(old: &Node, new: &Node) -> bool {
+    match (old, new) {
+        (
+            Node::ChildLine { signature: old_signature, annotations: old_annotations, .. },
+            Node::ChildLine { signature: new_signature, annotations: new_annotations, .. },
+        ) => old_signature == new_signature && old_annotations == new_annotations,
+        (Node::KeyValue { value: old_value, .. }, Node::KeyValue { value: new_value, .. })
+        | (Node::Const { value: old_value, .. }, Node::Const { value: new_value, .. }) => old_value == new_value,
+        // A changed template body shows up where the template is defined
+        (Node::Expansion { args: old_args, .. }, Node::Expansion { args: new_args, .. }) => old_args == new_args,
+        _ => false,
+    }
+}
@@ -0,0 +1,199 @@
+//! CBOR (RFC 8949) for the JSON data model: integers, floats, text, arrays, maps,
+//! booleans and null. Lengths are always definite when writing; reading also skips
+//! tags and accepts half and single precision floats.
+
+use serde_json::{Map, Number, Value};
+
+const UNSIGNED: u8 = 0;
+const NEGATIVE: u8 = 1;
+const BYTES: u8 = 2;
+const TEXT: u8 = 3;
+const ARRAY: u8 = 4;
+const MAP: u8 = 5;
+const TAG: u8 = 6;
+const SIMPLE: u8 = 7;
+
+pub(super) fn to_vec(value: &Value) -> Vec<u8> {
+    let mut out = Vec::new();
+    write(value, &mut out);
+    out
+}
+
+fn write(value: &Value, out: &mut Vec<u8>) {
+    match value {
+        Value::Null => out.push(0xf6),
+        Value::Bool(false) => out.push(0xf4),
+        Value::Bool(true) => out.push(0xf5),
+        Value::Number(number) => {
+            if let Some(n) = number.as_u64() {
+                head(UNSIGNED, n, out);
+            } else if let Some(n) = number.as_i64() {
+                head(NEGATIVE, !(n as u64), out);
+            } else {
+                out.push(0xfb);
+                out.extend_from_slice(&number.as_f64().unwrap_or_default().to_be_bytes());
+            }
+        }
+        Value::String(text) => {
+            head(TEXT, text.len() as u64, out);
+            out.extend_from_slice(text.as_bytes());
+        }
+        Value::Array(items) => {
+            head(ARRAY, items.len() as u64, out);
+            items.iter().for_each(|item| write(item, out));
+        }
+        Value::Object(entries) => {
+            head(MAP, entries.len() as u64, out);
+            for (key, value) in entries {
+                head(TEXT, key.len() as u64, out);
+                out.extend_from_slice(key.as_bytes());
+                write(value, out);
+            }
+        }
+    }
+}
+
+/// Major type and argument, in the shortest encoding
+fn head(major: u8, argument: u64, out: &mut Vec<u8>) {
+    let major = major << 5;
+    match argument {
+        0..=23 => out.push(major | argument as u8),
+        24..=0xff => out.extend_from_slice(&[major | 24, argument as u8]),
+        0x100..=0xffff => {
+            out.push(major | 25);
+            out.extend_from_slice(&(argument as u16).to_be_bytes());
+        }
+        0x1_0000..=0xffff_ffff => {
+            out.push(major | 26);
+            out.extend_from_slice(&(argument as u32).to_be_bytes());
+        }
+        _ => {
+            out.push(major | 27);
+            out.extend_from_slice(&argument.to_be_bytes());
+        }
+    }
+}
+
+pub(super) fn from_slice(bytes: &[u8]) -> Result<Value, String> {
+    let mut reader = Reader { bytes, pos: 0, depth: 0 };
+    let value = reader.value()?;
+    if reader.pos != bytes.len() {
+        return Err(format!("invalid CBOR: {} unexpected bytes at the end", bytes.len() - reader.pos));
+    }
+    Ok(value)
+}
+
+/// Deepest nesting accepted, well past what the parser produces
+const MAX_DEPTH: usize = 1024;
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+    depth: usize,
+}
+
+impl Reader<'_> {
+    fn take(&mut self, len: usize) -> Result<&[u8], String> {
+        let end = self.pos.checked_add(len).filter(|end| *end <= self.bytes.len())
+            .ok_or_else(|| "invalid CBOR: unexpected end of data".to_string())?;
+        let taken = &self.bytes[self.pos..end];
+        self.pos = end;
+        Ok(taken)
+    }
+
+    /// Argument of a head whose low five bits are `info`
+    fn argument(&mut self, info: u8) -> Result<u64, String> {
+        Ok(match info {
+            0..=23 => u64::from(info),
+            24 => u64::from(self.take(1)?[0]),
+            25 => u64::from(u16::from_be_bytes(self.take(2)?.try_into().unwrap())),
+            26 => u64::from(u32::from_be_bytes(self.take(4)?.try_into().unwrap())),
+            27 => u64::from_be_bytes(self.take(8)?.try_into().unwrap()),
+            31 => return Err("invalid CBOR: indefinite lengths aren't supported".to_string()),
+            _ => return Err(format!("invalid CBOR: reserved additional information {}", info)),
+        })
+    }
+
+    /// A length, bounded by what's left so a corrupt one can't allocate wildly
+    fn length(&mut self, info: u8) -> Result<usize, String> {
+        let len = self.argument(info)?;
+        if len > (self.bytes.len() - self.pos) as u64 {
+            return Err("invalid CBOR: length past the end of data".to_string());
+        }
+        Ok(len as usize)
+    }
+
+    fn text(&mut self, info: u8) -> Result<String, String> {
+        let len = self.length(info)?;
+        String::from_utf8(self.take(len)?.to_vec()).map_err(|_| "invalid CBOR: text isn't UTF-8".to_string())
+    }
+
+    fn value(&mut self) -> Result<Value, String> {
+        let initial = self.take(1)?[0];
+        let (major, info) = (initial >> 5, initial & 0x1f);
+        match major {
+            UNSIGNED => Ok(Value::from(self.argument(info)?)),
+            NEGATIVE => {
+                let n = self.argument(info)?;
+                i64::try_from(n).map(|n| Value::from(-1 - n)).map_err(|_| "invalid CBOR: integer out of range".to_string())
+            }
+            BYTES => Err("invalid CBOR: byte strings aren't part of the AST".to_string()),
+            TEXT => self.text(info).map(Value::String),
+            ARRAY | MAP => {
+                self.depth += 1;
+                if self.depth > MAX_DEPTH {
+                    return Err("invalid CBOR: nested too deeply".to_string());
+                }
+                let len = self.length(info)?;
+                let value = if major == ARRAY {
+                    (0..len).map(|_| self.value()).collect::<Result<Vec<_>, _>>().map(Value::Array)?
+                } else {
+                    let mut entries = Map::new();
+                    for _ in 0..len {
+                        let key = self.take(1)?[0];
+                        if key >> 5 != TEXT {
+                            return Err("invalid CBOR: map keys must be text".to_string());
+                        }
+                        let key = self.text(key & 0x1f)?;
+                        entries.insert(key, self.value()?);
+                    }
+                    Value::Object(entries)
+                };
+                self.depth -= 1;
+                Ok(value)
+            }
+            TAG => {
+                self.argument(info)?;
+                self.value()
+            }
+            SIMPLE => match info {
+                20 => Ok(Value::Bool(false)),
+                21 => Ok(Value::Bool(true)),
+                22 | 23 => Ok(Value::Null),
+                25 => Ok(float(half(u16::from_be_bytes(self.take(2)?.try_into().unwrap())))),
+                26 => Ok(float(f64::from(f32::from_be_bytes(self.take(4)?.try_into().unwrap())))),
+                27 => Ok(float(f64::from_be_bytes(self.take(8)?.try_into().unwrap()))),
+                _ => Err(format!("invalid CBOR: unsupported simple value {}", info)),
+            },
+            _ => unreachable!("major types are three bits"),
+        }
+    }
+}
+
+/// JSON has no NaN or infinity; they read as null
+fn float(value: f64) -> Value {
+    Number::from_f64(value).map_or(Value::Null, Value::Number)
+}
+
+/// IEEE 754 half precision to f64
+fn half(bits: u16) -> f64 {
+    let sign = if bits & 0x8000 != 0 { -1.0 } else { 1.0 };
+    let exponent = i32::from((bits >> 10) & 0x1f);
+    let mantissa = f64::from(bits & 0x3ff);
+    sign * match exponent {
+        0 => mantissa * 2f64.powi(-24),
+        31 if mantissa == 0.0 => f64::INFINITY,
+        31 => f64::NAN,
+        _ => (1.0 + mantissa / 1024.0) * 2f64.powi(exponent - 15),
+    }
+}
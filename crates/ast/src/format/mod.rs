@@ -0,0 +1,144 @@
+//! Stable external formats for the AST.
+//!
+//! Tools outside the compiler (editors, CI analyzers) read programs through a
+//! versioned document rather than the bare serde output:
+//!
+//! ```json
+//! { "format": "z-ast", "version": 1, "program": { "name": "Program", ... } }
+//! ```
+//!
+//! [`JSON_SCHEMA`] describes it. The same document can be written as JSON, YAML or
+//! CBOR. [`FORMAT_VERSION`] goes up whenever a change could break a reader, and
+//! readers here refuse documents newer than the version they were built with.
+
+use std::fmt;
+use std::str::FromStr;
+
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::Element;
+
+mod cbor;
+mod yaml;
+
+/// Version of the document layout written by this crate
+pub const FORMAT_VERSION: u64 = 1;
+
+/// JSON Schema of the document, for validating it outside Rust
+pub const JSON_SCHEMA: &str = include_str!("../../../../shared/ast.schema.json");
+
+/// Value of the document's `format` field
+const FORMAT_NAME: &str = "z-ast";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Json,
+    Yaml,
+    /// Compact binary, RFC 8949
+    Cbor,
+}
+
+impl Format {
+    /// Usual file extension, without the dot
+    pub fn extension(self) -> &'static str {
+        match self {
+            Format::Json => "json",
+            Format::Yaml => "yaml",
+            Format::Cbor => "cbor",
+        }
+    }
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "json" => Ok(Format::Json),
+            "yaml" | "yml" => Ok(Format::Yaml),
+            "cbor" => Ok(Format::Cbor),
+            _ => Err(format!("invalid AST format '{}' (expected 'json', 'yaml' or 'cbor')", s)),
+        }
+    }
+}
+
+impl fmt::Display for Format {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.extension())
+    }
+}
+
+/// `program` as a document in `format`
+pub fn serialize(program: &Element, format: Format) -> Vec<u8> {
+    let document = Document { format: FORMAT_NAME, version: FORMAT_VERSION, program };
+    match format {
+        // Straight from the structs, so fields keep their declaration order
+        Format::Json => (serde_json::to_string_pretty(&document).expect("AST serializes") + "\n").into_bytes(),
+        Format::Yaml => yaml::to_string(&serde_json::to_value(&document).expect("AST serializes")).into_bytes(),
+        Format::Cbor => cbor::to_vec(&serde_json::to_value(&document).expect("AST serializes")),
+    }
+}
+
+/// The program in a document written in `format`
+pub fn deserialize(bytes: &[u8], format: Format) -> Result<Element, String> {
+    let document = match format {
+        Format::Json => serde_json::from_slice(bytes).map_err(|e| format!("invalid JSON: {}", e))?,
+        Format::Yaml => {
+            let text = std::str::from_utf8(bytes).map_err(|_| "YAML isn't valid UTF-8".to_string())?;
+            yaml::from_str(text)?
+        }
+        Format::Cbor => cbor::from_slice(bytes)?,
+    };
+    program(document)
+}
+
+pub fn to_json(program: &Element) -> String {
+    String::from_utf8(serialize(program, Format::Json)).expect("JSON is UTF-8")
+}
+
+pub fn from_json(text: &str) -> Result<Element, String> {
+    deserialize(text.as_bytes(), Format::Json)
+}
+
+pub fn to_yaml(program: &Element) -> String {
+    String::from_utf8(serialize(program, Format::Yaml)).expect("YAML is UTF-8")
+}
+
+pub fn from_yaml(text: &str) -> Result<Element, String> {
+    deserialize(text.as_bytes(), Format::Yaml)
+}
+
+/// Any JSON value as YAML, written the way documents are
+pub fn value_to_yaml(value: &Value) -> String {
+    yaml::to_string(value)
+}
+
+pub fn to_cbor(program: &Element) -> Vec<u8> {
+    serialize(program, Format::Cbor)
+}
+
+pub fn from_cbor(bytes: &[u8]) -> Result<Element, String> {
+    deserialize(bytes, Format::Cbor)
+}
+
+#[derive(Serialize)]
+struct Document<'a> {
+    format: &'a str,
+    version: u64,
+    program: &'a Element,
+}
+
+fn program(mut document: Value) -> Result<Element, String> {
+    if document["format"] != FORMAT_NAME {
+        return Err(format!("not a Z AST document (expected \"format\": \"{}\")", FORMAT_NAME));
+    }
+    match document["version"].as_u64() {
+        Some(version) if version > FORMAT_VERSION => {
+            return Err(format!("AST format version {} is newer than this reader's ({})", version, FORMAT_VERSION));
+        }
+        Some(_) => {}
+        None => return Err("AST document has no version".to_string()),
+    }
+    serde_json::from_value(document["program"].take()).map_err(|e| format!("invalid AST: {}", e))
+}
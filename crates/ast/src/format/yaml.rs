@@ -0,0 +1,257 @@
+//! YAML for the JSON data model.
+//!
+//! Writing uses block style with two-space indentation and double-quoted strings,
+//! which YAML reads exactly like JSON strings. Reading takes the same block subset:
+//! mappings, `- ` sequences, quoted or plain scalars, `[]`, `{}` and `#` comments.
+//! Anchors, tags, flow collections with items and multi-line scalars aren't
+//! supported.
+
+use serde_json::{Map, Value};
+
+pub(super) fn to_string(value: &Value) -> String {
+    let mut out = String::new();
+    match value {
+        Value::Object(entries) if !entries.is_empty() => mapping(entries, 0, &mut out),
+        Value::Array(items) if !items.is_empty() => sequence(items, 0, &mut out),
+        scalar => {
+            out.push_str(&inline(scalar));
+            out.push('\n');
+        }
+    }
+    out
+}
+
+fn mapping(entries: &Map<String, Value>, indent: usize, out: &mut String) {
+    for (i, (key, value)) in entries.iter().enumerate() {
+        // The first entry of a sequence item follows its `- `
+        if i > 0 || out.is_empty() || out.ends_with('\n') {
+            out.push_str(&" ".repeat(indent));
+        }
+        out.push_str(&yaml_key(key));
+        out.push(':');
+        nested(value, indent, out);
+    }
+}
+
+fn sequence(items: &[Value], indent: usize, out: &mut String) {
+    for item in items {
+        out.push_str(&" ".repeat(indent));
+        out.push_str("- ");
+        match item {
+            Value::Object(entries) if !entries.is_empty() => mapping(entries, indent + 2, out),
+            Value::Array(inner) if !inner.is_empty() => {
+                out.push('\n');
+                sequence(inner, indent + 2, out);
+            }
+            scalar => {
+                out.push_str(&inline(scalar));
+                out.push('\n');
+            }
+        }
+    }
+}
+
+/// The value after `key:`
+fn nested(value: &Value, indent: usize, out: &mut String) {
+    match value {
+        Value::Object(entries) if !entries.is_empty() => {
+            out.push('\n');
+            mapping(entries, indent + 2, out);
+        }
+        Value::Array(items) if !items.is_empty() => {
+            out.push('\n');
+            sequence(items, indent + 2, out);
+        }
+        scalar => {
+            out.push(' ');
+            out.push_str(&inline(scalar));
+            out.push('\n');
+        }
+    }
+}
+
+fn inline(value: &Value) -> String {
+    match value {
+        Value::Array(_) => "[]".to_string(),
+        Value::Object(_) => "{}".to_string(),
+        // JSON text is valid YAML for scalars
+        scalar => scalar.to_string(),
+    }
+}
+
+fn yaml_key(key: &str) -> String {
+    let plain = key.chars().next().is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+    if plain {
+        key.to_string()
+    } else {
+        Value::String(key.to_string()).to_string()
+    }
+}
+
+pub(super) fn from_str(text: &str) -> Result<Value, String> {
+    let mut lines = Vec::new();
+    for (number, raw) in text.lines().enumerate() {
+        let content = raw.trim_end();
+        let trimmed = content.trim_start();
+        if trimmed.is_empty() || trimmed.starts_with('#') || trimmed == "---" {
+            continue;
+        }
+        if content.starts_with('\t') {
+            return Err(format!("YAML line {}: tabs can't indent", number + 1));
+        }
+        lines.push(Line { number: number + 1, indent: content.len() - trimmed.len(), text: trimmed.to_string() });
+    }
+    if lines.is_empty() {
+        return Ok(Value::Null);
+    }
+    let mut reader = Reader { lines, pos: 0 };
+    let indent = reader.lines[0].indent;
+    let value = reader.block(indent)?;
+    match reader.lines.get(reader.pos) {
+        Some(line) => Err(reader.error(line, "unexpected indentation")),
+        None => Ok(value),
+    }
+}
+
+struct Line {
+    number: usize,
+    indent: usize,
+    text: String,
+}
+
+struct Reader {
+    lines: Vec<Line>,
+    pos: usize,
+}
+
+impl Reader {
+    fn error(&self, line: &Line, message: &str) -> String {
+        format!("YAML line {}: {}", line.number, message)
+    }
+
+    /// The mapping, sequence or scalar starting at the current line, at `indent`
+    fn block(&mut self, indent: usize) -> Result<Value, String> {
+        let line = &self.lines[self.pos];
+        if line.text == "-" || line.text.starts_with("- ") {
+            self.sequence(indent)
+        } else if split_key(&line.text).is_some() {
+            self.mapping(indent)
+        } else {
+            let value = scalar(&line.text).map_err(|e| self.error(line, &e))?;
+            self.pos += 1;
+            Ok(value)
+        }
+    }
+
+    fn sequence(&mut self, indent: usize) -> Result<Value, String> {
+        let mut items = Vec::new();
+        while let Some(line) = self.lines.get(self.pos) {
+            if line.indent != indent || !(line.text == "-" || line.text.starts_with("- ")) {
+                break;
+            }
+            let rest = line.text[1..].trim_start().to_string();
+            if rest.is_empty() {
+                // The item is the block on the following lines
+                self.pos += 1;
+                match self.lines.get(self.pos) {
+                    Some(next) if next.indent > indent => {
+                        let next_indent = next.indent;
+                        items.push(self.block(next_indent)?);
+                    }
+                    _ => items.push(Value::Null),
+                }
+            } else {
+                // What follows `- ` reads as if it were on its own line, two columns in
+                let item_indent = indent + (line.text.len() - rest.len());
+                let number = line.number;
+                self.lines[self.pos] = Line { number, indent: item_indent, text: rest };
+                items.push(self.block(item_indent)?);
+            }
+        }
+        Ok(Value::Array(items))
+    }
+
+    fn mapping(&mut self, indent: usize) -> Result<Value, String> {
+        let mut entries = Map::new();
+        while let Some(line) = self.lines.get(self.pos) {
+            if line.indent != indent {
+                break;
+            }
+            let Some((key, rest)) = split_key(&line.text) else {
+                return Err(self.error(line, "expected 'key: value'"));
+            };
+            let key = match key.strip_prefix('"') {
+                Some(_) => serde_json::from_str::<String>(key).map_err(|_| self.error(line, "invalid quoted key"))?,
+                None => key.to_string(),
+            };
+            let value = if rest.is_empty() {
+                self.pos += 1;
+                match self.lines.get(self.pos) {
+                    // A sequence may sit at the same indentation as its key
+                    Some(next) if next.indent > indent || (next.indent == indent && next.text.starts_with('-')) => {
+                        let next_indent = next.indent;
+                        self.block(next_indent)?
+                    }
+                    _ => Value::Null,
+                }
+            } else {
+                let value = scalar(rest).map_err(|e| self.error(line, &e))?;
+                self.pos += 1;
+                value
+            };
+            if entries.insert(key.clone(), value).is_some() {
+                let line = &self.lines[self.pos - 1];
+                return Err(self.error(line, &format!("'{}' appears twice", key)));
+            }
+        }
+        Ok(Value::Object(entries))
+    }
+}
+
+/// `key` and the rest of a `key: value` or `key:` line
+fn split_key(text: &str) -> Option<(&str, &str)> {
+    let end = if let Some(quoted) = text.strip_prefix('"') {
+        let mut escaped = false;
+        let close = quoted.char_indices().find(|(_, c)| {
+            let found = *c == '"' && !escaped;
+            escaped = *c == '\\' && !escaped;
+            found
+        })?;
+        let end = close.0 + 2;
+        text[end..].starts_with(':').then_some(end)?
+    } else {
+        text.find(": ").or_else(|| text.strip_suffix(':').map(str::len))?
+    };
+    Some((&text[..end], text[end + 1..].trim()))
+}
+
+fn scalar(text: &str) -> Result<Value, String> {
+    let text = match text.find(" #") {
+        Some(at) if !text.starts_with(['"', '\'']) => text[..at].trim_end(),
+        _ => text,
+    };
+    Ok(match text {
+        "null" | "~" => Value::Null,
+        "true" => Value::Bool(true),
+        "false" => Value::Bool(false),
+        "[]" => Value::Array(Vec::new()),
+        "{}" => Value::Object(Map::new()),
+        _ if text.starts_with('"') => serde_json::from_str(text).map_err(|_| format!("invalid quoted string {}", text))?,
+        _ if text.starts_with('\'') => {
+            let inner = text.strip_prefix('\'').and_then(|rest| rest.strip_suffix('\''))
+                .ok_or_else(|| format!("unterminated string {}", text))?;
+            Value::String(inner.replace("''", "'"))
+        }
+        _ if text.starts_with(['[', '{', '&', '*', '!', '|', '>']) => {
+            return Err(format!("unsupported YAML '{}'", text));
+        }
+        _ => match text.parse::<i64>() {
+            Ok(n) => Value::from(n),
+            Err(_) => match text.parse::<f64>().ok().and_then(serde_json::Number::from_f64) {
+                Some(n) => Value::Number(n),
+                None => Value::String(text.to_string()),
+            },
+        },
+    })
+}
@@ -0,0 +1,130 @@
+//! Stable identifiers for blocks.
+//!
+//! A block's [`NodeId`] is derived from the names on its path from the program, so it
+//! survives re-parsing: edits elsewhere in the file, even ones that move the block
+//! to another line, keep its id. Renaming the block or one of its parents gives it a
+//! new one. Blocks sharing a name under one parent are told apart by their order
+//! among those namesakes. Blocks an expanded template added count as children of
+//! the block the template was used in.
+
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{Element, Node};
+
+/// Identifier of a block, written as 16 hex digits
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(into = "String", try_from = "String")]
+pub struct NodeId(u64);
+
+const FNV_OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+impl NodeId {
+    /// Id of the program itself
+    pub const ROOT: NodeId = NodeId(FNV_OFFSET);
+
+    /// Id of the block at `path` below the program, e.g. `["next:WebApp", "Routes"]`.
+    /// The second and later namesakes under one parent take a `#n` suffix (`Routes#1`).
+    pub fn from_path(path: &[&str]) -> NodeId {
+        path.iter().fold(NodeId::ROOT, |parent, segment| parent.child(segment))
+    }
+
+    /// Id of the block named `segment` directly below this one
+    pub fn child(self, segment: &str) -> NodeId {
+        // FNV-1a over the parent id and the segment: stable across platforms and Rust versions
+        let hash = self.0.to_le_bytes().iter().chain(segment.as_bytes())
+            .fold(FNV_OFFSET, |hash, byte| (hash ^ u64::from(*byte)).wrapping_mul(FNV_PRIME));
+        NodeId(hash)
+    }
+
+    /// Whether no id has been assigned, as for elements built by hand or deserialized
+    /// without one; see [`assign_ids`]
+    pub fn is_unset(&self) -> bool {
+        self.0 == 0
+    }
+}
+
+impl fmt::Display for NodeId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:016x}", self.0)
+    }
+}
+
+impl std::str::FromStr for NodeId {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.len() != 16 {
+            return Err(format!("invalid node id '{}' (expected 16 hex digits)", s));
+        }
+        u64::from_str_radix(s, 16).map(NodeId).map_err(|_| format!("invalid node id '{}' (expected 16 hex digits)", s))
+    }
+}
+
+impl From<NodeId> for String {
+    fn from(id: NodeId) -> Self {
+        id.to_string()
+    }
+}
+
+impl TryFrom<String> for NodeId {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+/// Give `program` and every block in it its [`NodeId`]. The parser does this already;
+/// call it after building or rearranging a tree by hand.
+pub fn assign_ids(program: &mut Element) {
+    assign(program, NodeId::ROOT);
+}
+
+fn assign(element: &mut Element, id: NodeId) {
+    element.id = id;
+    let mut seen: Vec<&str> = Vec::new();
+    let mut ids = Vec::new();
+    for node in element.items() {
+        if let Node::Element(child) = node {
+            let namesakes = seen.iter().filter(|name| **name == child.name).count();
+            seen.push(&child.name);
+            ids.push(match namesakes {
+                0 => id.child(&child.name),
+                n => id.child(&format!("{}#{}", child.name, n)),
+            });
+        }
+    }
+
+    let mut children = Vec::new();
+    blocks_mut(&mut element.children, &mut children);
+    for (child, id) in children.into_iter().zip(ids) {
+        assign(child, id);
+    }
+}
+
+/// The blocks among `nodes`, looking through expansions like [`Element::items`]
+fn blocks_mut<'a>(nodes: &'a mut [Node], blocks: &mut Vec<&'a mut Element>) {
+    for node in nodes {
+        match node {
+            Node::Element(child) => blocks.push(child),
+            Node::Expansion { children, .. } => blocks_mut(children, blocks),
+            _ => {}
+        }
+    }
+}
+
+impl Element {
+    /// The block with the given id at or below this one
+    pub fn find(&self, id: NodeId) -> Option<&Element> {
+        if self.id == id {
+            return Some(self);
+        }
+        self.items().find_map(|node| match node {
+            Node::Element(child) => child.find(id),
+            _ => None,
+        })
+    }
+}
@@ -1,21 +1,212 @@
 use serde::{Serialize, Deserialize};
 
-#[derive(Debug, Serialize, Deserialize)]
+pub mod arena;
+pub mod builder;
+pub mod diff;
+pub mod format;
+pub mod id;
+pub mod print;
+pub mod query;
+pub mod visit;
+pub use arena::{ArenaAst, Interner, Symbol};
+pub use builder::{ElementBuilder, LineBuilder};
+pub use diff::{diff, AstDiff, Change, ChangeKind};
+pub use format::Format;
+pub use id::{assign_ids, NodeId};
+pub use print::to_source;
+pub use query::Selector;
+pub use visit::{walk_element, walk_node, Visitor};
+
+/// Byte range `[start, end)` into the source text an AST node was parsed from
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    pub fn new(start: usize, end: usize) -> Self {
+        Self { start, end }
+    }
+
+    pub fn len(&self) -> usize {
+        self.end - self.start
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.start == self.end
+    }
+
+    /// True when the two ranges overlap or share a boundary
+    pub fn touches(&self, other: &Span) -> bool {
+        self.start <= other.end && other.start <= self.end
+    }
+}
+
+/// A comment or blank line that carries no meaning but should survive rewriting the file
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", content = "text")]
+pub enum TriviaPiece {
+    /// `// ...` on a line of its own, including the slashes
+    Comment(String),
+    BlankLine,
+}
+
+/// Comments and blank lines attached to a node
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Trivia {
+    /// Lines above the node, in source order (above its leading annotations, if any)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub leading: Vec<TriviaPiece>,
+    /// `// ...` comment after the node on its last line
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub trailing: Option<String>,
+}
+
+impl Trivia {
+    pub fn is_empty(&self) -> bool {
+        self.leading.is_empty() && self.trailing.is_none()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Element {
     pub name: String,
     pub annotations: Vec<Annotation>,
     pub children: Vec<Node>,
+    /// Raw header text between the name and the opening brace, e.g. `(database: postgres)`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signature: Option<String>,
+    #[serde(default)]
+    pub span: Span,
+    /// Stable identity of the block across compiles; see [`NodeId`]
+    #[serde(default, skip_serializing_if = "NodeId::is_unset")]
+    pub id: NodeId,
+    /// Comments and blank lines before the block, and the comment after its closing brace
+    #[serde(default, skip_serializing_if = "Trivia::is_empty")]
+    pub trivia: Trivia,
+    /// Inside the braces: `trailing` is the comment after the opening brace and `leading`
+    /// holds the lines after the last child. For the program, the end of the file.
+    #[serde(default, skip_serializing_if = "Trivia::is_empty")]
+    pub body_trivia: Trivia,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Annotation {
     pub name: String,
+    /// Raw text between the parentheses of `@name(...)`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub args: Option<String>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(tag = "kind")]
 pub enum Node {
     Element(Element),
-    ChildLine { modifier: Option<String>, id: String },
-    KeyValue { key: String, value: String },
-}
\ No newline at end of file
+    ChildLine {
+        modifier: Option<String>,
+        id: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        signature: Option<String>,
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        annotations: Vec<Annotation>,
+        #[serde(default)]
+        span: Span,
+        #[serde(default, skip_serializing_if = "Trivia::is_empty")]
+        trivia: Trivia,
+    },
+    KeyValue {
+        key: String,
+        value: String,
+        #[serde(default)]
+        span: Span,
+        #[serde(default, skip_serializing_if = "Trivia::is_empty")]
+        trivia: Trivia,
+    },
+    /// `const name = value`, referenced as `$name` by the rest of its block
+    Const {
+        name: String,
+        /// Raw value text, e.g. `"#3b82f6"` including the quotes
+        value: String,
+        #[serde(default)]
+        span: Span,
+        #[serde(default, skip_serializing_if = "Trivia::is_empty")]
+        trivia: Trivia,
+    },
+    /// `use Name(args)` invoking a `template`, with the nodes it expanded to. The source
+    /// writes it as a plain line; expanding templates turns that line into this.
+    Expansion {
+        template: String,
+        /// Raw text between the parentheses
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        args: Option<String>,
+        /// The template body with the arguments substituted. Every node spans the
+        /// invocation, so errors in it point at the call site.
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        children: Vec<Node>,
+        #[serde(default)]
+        span: Span,
+        #[serde(default, skip_serializing_if = "Trivia::is_empty")]
+        trivia: Trivia,
+    },
+}
+
+impl Node {
+    /// Source range the node was parsed from
+    pub fn span(&self) -> Span {
+        match self {
+            Node::Element(element) => element.span,
+            Node::ChildLine { span, .. } | Node::KeyValue { span, .. } | Node::Const { span, .. } | Node::Expansion { span, .. } => *span,
+        }
+    }
+
+    /// Comments and blank lines attached to the node
+    pub fn trivia(&self) -> &Trivia {
+        match self {
+            Node::Element(element) => &element.trivia,
+            Node::ChildLine { trivia, .. }
+            | Node::KeyValue { trivia, .. }
+            | Node::Const { trivia, .. }
+            | Node::Expansion { trivia, .. } => trivia,
+        }
+    }
+
+    pub fn trivia_mut(&mut self) -> &mut Trivia {
+        match self {
+            Node::Element(element) => &mut element.trivia,
+            Node::ChildLine { trivia, .. }
+            | Node::KeyValue { trivia, .. }
+            | Node::Const { trivia, .. }
+            | Node::Expansion { trivia, .. } => trivia,
+        }
+    }
+}
+
+impl Element {
+    /// Children in source order, with the nodes an [`Node::Expansion`] expanded to in
+    /// its place. Lowering and analysis read blocks through this.
+    pub fn items(&self) -> Items<'_> {
+        Items { stack: vec![self.children.iter()] }
+    }
+}
+
+/// Iterator returned by [`Element::items`]
+pub struct Items<'a> {
+    stack: Vec<std::slice::Iter<'a, Node>>,
+}
+
+impl<'a> Iterator for Items<'a> {
+    type Item = &'a Node;
+
+    fn next(&mut self) -> Option<&'a Node> {
+        loop {
+            match self.stack.last_mut()?.next() {
+                Some(Node::Expansion { children, .. }) => self.stack.push(children.iter()),
+                Some(node) => return Some(node),
+                None => {
+                    self.stack.pop();
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,178 @@
+//! Printing an AST back to `.z` source.
+//!
+//! [`to_source`] lays the tree out canonically: two-space indentation, one item per
+//! line, annotations of a block on the lines above it and those of a line after it.
+//! Comments are kept where the parser attached them. Blank lines are kept too, at
+//! most one in a row, and never at the start or end of a block. Top-level blocks are
+//! separated by a blank line.
+//!
+//! Parsing the output gives back the same tree, apart from spans.
+
+use crate::{Annotation, Element, Node, Trivia, TriviaPiece};
+
+const INDENT: &str = "  ";
+
+/// Name the parser gives the element holding a whole file
+const PROGRAM: &str = "Program";
+
+/// Format `element` as `.z` source. The program prints as the file it was parsed
+/// from; any other block prints as itself.
+pub fn to_source(element: &Element) -> String {
+    let mut out = String::new();
+    if element.name == PROGRAM && element.signature.is_none() && element.annotations.is_empty() {
+        print_items(&element.children, &element.body_trivia.leading, 0, true, &mut out);
+    } else {
+        print_element(element, 0, &mut out);
+    }
+    out
+}
+
+/// Print `items` at `depth`, followed by the comments after the last of them
+fn print_items(items: &[Node], dangling: &[TriviaPiece], depth: usize, top_level: bool, out: &mut String) {
+    let mut first = true;
+    for node in items {
+        let separate = top_level && !first && matches!(node, Node::Element(_));
+        print_leading(&node.trivia().leading, depth, first, separate, out);
+        match node {
+            Node::Element(element) => print_element(element, depth, out),
+            Node::ChildLine { modifier, id, signature, annotations, trivia, .. } => {
+                indent(depth, out);
+                if let Some(modifier) = modifier {
+                    out.push_str(modifier);
+                    out.push(' ');
+                }
+                out.push_str(id);
+                push_signature(signature.as_deref(), out);
+                for annotation in annotations {
+                    out.push(' ');
+                    push_annotation(annotation, out);
+                }
+                end_line(trivia, out);
+            }
+            Node::KeyValue { key, value, trivia, .. } => {
+                indent(depth, out);
+                out.push_str(key);
+                out.push(':');
+                if !value.is_empty() {
+                    out.push(' ');
+                    out.push_str(value);
+                }
+                end_line(trivia, out);
+            }
+            Node::Const { name, value, trivia, .. } => {
+                indent(depth, out);
+                out.push_str(&format!("const {} = {}", name, value));
+                end_line(trivia, out);
+            }
+            // The invocation as written; the template is printed where it's defined
+            Node::Expansion { template, args, trivia, .. } => {
+                indent(depth, out);
+                out.push_str("use ");
+                out.push_str(template);
+                if let Some(args) = args {
+                    out.push_str(&format!("({})", args));
+                }
+                end_line(trivia, out);
+            }
+        }
+        first = false;
+    }
+
+    // Comments after the last item; blank lines only matter between comments
+    let last_comment = dangling.iter().rposition(|piece| matches!(piece, TriviaPiece::Comment(_)));
+    if let Some(last) = last_comment {
+        print_leading(&dangling[..=last], depth, first, false, out);
+    }
+}
+
+fn print_element(element: &Element, depth: usize, out: &mut String) {
+    for annotation in &element.annotations {
+        indent(depth, out);
+        push_annotation(annotation, out);
+        out.push('\n');
+    }
+
+    indent(depth, out);
+    match element.name.split_once(':') {
+        Some((kind, name)) => out.push_str(&format!("{} {}", kind, name)),
+        None => out.push_str(&element.name),
+    }
+    push_signature(element.signature.as_deref(), out);
+
+    let body = &element.body_trivia;
+    let has_comments = body.leading.iter().any(|piece| matches!(piece, TriviaPiece::Comment(_)));
+    if element.children.is_empty() && body.trailing.is_none() && !has_comments {
+        out.push_str(" {}");
+    } else {
+        out.push_str(" {");
+        if let Some(comment) = &body.trailing {
+            out.push(' ');
+            out.push_str(comment);
+        }
+        out.push('\n');
+        print_items(&element.children, &body.leading, depth + 1, false, out);
+        indent(depth, out);
+        out.push('}');
+    }
+    end_line(&element.trivia, out);
+}
+
+/// Comments and blank lines above an item. Runs of blank lines collapse into one;
+/// there are none before the first item of a block, and there's always one before
+/// a `separate`d item.
+fn print_leading(pieces: &[TriviaPiece], depth: usize, first: bool, separate: bool, out: &mut String) {
+    let mut blank = separate;
+    let mut at_start = first;
+    for piece in pieces {
+        match piece {
+            TriviaPiece::BlankLine => blank = true,
+            TriviaPiece::Comment(comment) => {
+                if blank && !at_start {
+                    out.push('\n');
+                }
+                indent(depth, out);
+                out.push_str(comment);
+                out.push('\n');
+                blank = false;
+                at_start = false;
+            }
+        }
+    }
+    if blank && !at_start {
+        out.push('\n');
+    }
+}
+
+/// Text after a name: attached when it opens with a bracket, as in `page(id: string)`,
+/// and after a space otherwise, as in `for product in products`
+fn push_signature(signature: Option<&str>, out: &mut String) {
+    if let Some(signature) = signature {
+        if !signature.starts_with(['(', '[', '<']) {
+            out.push(' ');
+        }
+        out.push_str(signature);
+    }
+}
+
+fn push_annotation(annotation: &Annotation, out: &mut String) {
+    out.push('@');
+    out.push_str(&annotation.name);
+    if let Some(args) = &annotation.args {
+        out.push_str(&format!("({})", args));
+    }
+}
+
+/// Finish an item's last line with its trailing comment, if any
+fn end_line(trivia: &Trivia, out: &mut String) {
+    if let Some(comment) = &trivia.trailing {
+        out.push(' ');
+        out.push_str(comment);
+    }
+    out.push('\n');
+}
+
+fn indent(depth: usize, out: &mut String) {
+    for _ in 0..depth {
+        out.push_str(INDENT);
+    }
+}
@@ -0,0 +1,250 @@
+//! Declarative lookup of nodes, in the spirit of CSS selectors.
+//!
+//! A selector is a chain of patterns separated by `>` (direct child) or whitespace
+//! (any depth below):
+//!
+//! - `next:*` matches a node by kind and name: `next WebApp { ... }` or a line like
+//!   `page home`. Either part may use `*` as a wildcard, e.g. `table:User*`.
+//! - `Routes` (no colon) matches a node whose name or kind is `Routes`
+//! - `*` matches any node
+//! - `@auth` after a pattern requires that annotation, e.g. `API > *@auth`
+//!
+//! Properties match by key and constants by name. The first pattern is looked for at
+//! any depth, so `next:* > Routes` also finds apps inside workspaces; start with `>`
+//! to only match top-level blocks.
+//!
+//! ```ignore
+//! let pages = program.query("next:* > Routes > *")?;
+//! let secured = Selector::new().descendant("API").child("*").annotated("auth");
+//! ```
+
+use std::collections::HashSet;
+
+use crate::{Annotation, Element, Node};
+
+/// A parsed selector; see the [module docs](self) for the syntax
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Selector {
+    steps: Vec<Step>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Combinator {
+    Child,
+    Descendant,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Step {
+    combinator: Combinator,
+    /// `Some` for `kind:name` patterns; a bare pattern is matched against either part
+    kind: Option<String>,
+    name: String,
+    annotations: Vec<String>,
+}
+
+impl Selector {
+    /// The empty selector, which matches nothing until steps are added
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn parse(selector: &str) -> Result<Self, String> {
+        let mut steps = Vec::new();
+        let mut combinator = Combinator::Descendant;
+        let mut explicit = false;
+        let mut rest = selector.trim();
+        if rest.is_empty() {
+            return Err("empty selector".to_string());
+        }
+
+        while !rest.is_empty() {
+            if let Some(after) = rest.strip_prefix('>') {
+                if explicit {
+                    return Err(format!("two '>' in a row in '{}'", selector));
+                }
+                combinator = Combinator::Child;
+                explicit = true;
+                rest = after.trim_start();
+                continue;
+            }
+            let end = rest.find(|c: char| c.is_whitespace() || c == '>').unwrap_or(rest.len());
+            steps.push(Step::parse(&rest[..end], combinator)?);
+            combinator = Combinator::Descendant;
+            explicit = false;
+            rest = rest[end..].trim_start();
+        }
+        if explicit {
+            return Err(format!("'{}' ends with '>'", selector));
+        }
+        Ok(Self { steps })
+    }
+
+    /// Add a pattern matching direct children of what the selector matched so far
+    ///
+    /// # Panics
+    ///
+    /// If `pattern` isn't a valid single pattern such as `next:*` or `Routes@auth`
+    pub fn child(self, pattern: &str) -> Self {
+        self.push(pattern, Combinator::Child)
+    }
+
+    /// Add a pattern matching nodes at any depth below what the selector matched so far
+    ///
+    /// # Panics
+    ///
+    /// If `pattern` isn't a valid single pattern such as `next:*` or `Routes@auth`
+    pub fn descendant(self, pattern: &str) -> Self {
+        self.push(pattern, Combinator::Descendant)
+    }
+
+    /// Require an annotation on the nodes matched by the last pattern
+    pub fn annotated(mut self, annotation: &str) -> Self {
+        if let Some(step) = self.steps.last_mut() {
+            step.annotations.push(annotation.to_string());
+        }
+        self
+    }
+
+    /// Nodes below `root` matching the selector, in document order
+    pub fn select<'a>(&self, root: &'a Element) -> Vec<&'a Node> {
+        let Some((last, steps)) = self.steps.split_last() else {
+            return Vec::new();
+        };
+
+        // Blocks matched so far, starting from the root itself
+        let mut scopes = vec![root];
+        for step in steps {
+            let mut next = Vec::new();
+            for scope in scopes {
+                step.collect(scope, &mut |node| {
+                    if let Node::Element(element) = node {
+                        next.push(element);
+                    }
+                });
+            }
+            dedup(&mut next);
+            scopes = next;
+        }
+
+        let mut matched = HashSet::new();
+        for scope in scopes {
+            last.collect(scope, &mut |node| {
+                matched.insert(node as *const Node);
+            });
+        }
+        let mut nodes = Vec::new();
+        in_document_order(root, &matched, &mut nodes);
+        nodes
+    }
+
+    fn push(mut self, pattern: &str, combinator: Combinator) -> Self {
+        let step = Step::parse(pattern, combinator).unwrap_or_else(|e| panic!("{}", e));
+        self.steps.push(step);
+        self
+    }
+}
+
+impl std::str::FromStr for Selector {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Selector::parse(s)
+    }
+}
+
+impl Element {
+    /// Nodes below this block matching `selector`, e.g. `next:* > Routes > *`
+    pub fn query(&self, selector: &str) -> Result<Vec<&Node>, String> {
+        Ok(Selector::parse(selector)?.select(self))
+    }
+}
+
+impl Step {
+    fn parse(pattern: &str, combinator: Combinator) -> Result<Self, String> {
+        let mut parts = pattern.split('@');
+        let name = parts.next().unwrap_or_default();
+        let annotations: Vec<String> = parts.map(str::to_string).collect();
+        if name.is_empty() {
+            return Err(format!("'{}' needs a name or '*' before its annotations", pattern));
+        }
+        if annotations.iter().any(String::is_empty) {
+            return Err(format!("empty annotation name in '{}'", pattern));
+        }
+
+        let (kind, name) = match name.split_once(':') {
+            Some((kind, name)) if kind.is_empty() || name.is_empty() => {
+                return Err(format!("'{}' needs both a kind and a name around ':'", pattern));
+            }
+            Some((kind, name)) => (Some(kind.to_string()), name.to_string()),
+            None => (None, name.to_string()),
+        };
+        Ok(Self { combinator, kind, name, annotations })
+    }
+
+    /// Call `found` with every node matching this step below `scope`
+    fn collect<'a>(&self, scope: &'a Element, found: &mut dyn FnMut(&'a Node)) {
+        for node in scope.items() {
+            if self.matches(node) {
+                found(node);
+            }
+            if let (Combinator::Descendant, Node::Element(element)) = (self.combinator, node) {
+                self.collect(element, found);
+            }
+        }
+    }
+
+    fn matches(&self, node: &Node) -> bool {
+        let (kind, name, annotations): (Option<&str>, &str, &[Annotation]) = match node {
+            Node::Element(element) => match element.name.split_once(':') {
+                Some((kind, name)) => (Some(kind), name, &element.annotations),
+                None => (None, &element.name, &element.annotations),
+            },
+            Node::ChildLine { modifier, id, annotations, .. } => (modifier.as_deref(), id, annotations),
+            Node::KeyValue { key, .. } => (None, key, &[]),
+            Node::Const { name, .. } => (None, name, &[]),
+            Node::Expansion { template, .. } => (Some("use"), template, &[]),
+        };
+
+        let named = match &self.kind {
+            Some(pattern) => kind.is_some_and(|kind| glob(pattern, kind)) && glob(&self.name, name),
+            None => glob(&self.name, name) || kind.is_some_and(|kind| glob(&self.name, kind)),
+        };
+        named && self.annotations.iter().all(|wanted| annotations.iter().any(|annotation| &annotation.name == wanted))
+    }
+}
+
+/// Whether `text` matches `pattern`, where `*` stands for any run of characters
+fn glob(pattern: &str, text: &str) -> bool {
+    let Some((head, rest)) = pattern.split_once('*') else {
+        return pattern == text;
+    };
+    let Some(mut text) = text.strip_prefix(head) else {
+        return false;
+    };
+    let mut pieces: Vec<&str> = rest.split('*').collect();
+    let tail = pieces.pop().unwrap_or_default();
+    for piece in pieces {
+        match text.find(piece) {
+            Some(at) => text = &text[at + piece.len()..],
+            None => return false,
+        }
+    }
+    text.len() >= tail.len() && text.ends_with(tail)
+}
+
+fn dedup(elements: &mut Vec<&Element>) {
+    let mut seen = HashSet::new();
+    elements.retain(|element| seen.insert(*element as *const Element));
+}
+
+fn in_document_order<'a>(element: &'a Element, matched: &HashSet<*const Node>, nodes: &mut Vec<&'a Node>) {
+    for node in element.items() {
+        if matched.contains(&(node as *const Node)) {
+            nodes.push(node);
+        }
+        if let Node::Element(child) = node {
+            in_document_order(child, matched, nodes);
+        }
+    }
+}
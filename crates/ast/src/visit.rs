@@ -0,0 +1,79 @@
+//! Read-only traversal of the AST.
+//!
+//! Implement [`Visitor`] and override only the methods for the node kinds you care
+//! about. The defaults recurse through the `walk_*` functions, so an override that
+//! still wants to visit what's below calls the matching `walk_*` itself.
+
+use crate::{Annotation, Element, Node, Span};
+
+/// Borrowed view of a [`Node::ChildLine`]
+#[derive(Debug, Clone, Copy)]
+pub struct ChildLine<'a> {
+    pub modifier: Option<&'a str>,
+    pub id: &'a str,
+    pub signature: Option<&'a str>,
+    pub annotations: &'a [Annotation],
+    pub span: Span,
+}
+
+pub trait Visitor {
+    /// Called for every block; the default visits its annotations, then its children in order
+    fn visit_element(&mut self, element: &Element) {
+        walk_element(self, element);
+    }
+
+    /// Called for every node before dispatching on its kind
+    fn visit_node(&mut self, node: &Node) {
+        walk_node(self, node);
+    }
+
+    fn visit_child_line(&mut self, line: ChildLine<'_>) {
+        walk_child_line(self, line);
+    }
+
+    fn visit_key_value(&mut self, _key: &str, _value: &str, _span: Span) {}
+
+    fn visit_const(&mut self, _name: &str, _value: &str, _span: Span) {}
+
+    /// Called for every expanded template invocation; the default visits what it expanded to
+    fn visit_expansion(&mut self, _template: &str, _args: Option<&str>, children: &[Node], _span: Span) {
+        for child in children {
+            self.visit_node(child);
+        }
+    }
+
+    fn visit_annotation(&mut self, _annotation: &Annotation) {}
+}
+
+pub fn walk_element<V: Visitor + ?Sized>(visitor: &mut V, element: &Element) {
+    for annotation in &element.annotations {
+        visitor.visit_annotation(annotation);
+    }
+    for child in &element.children {
+        visitor.visit_node(child);
+    }
+}
+
+pub fn walk_node<V: Visitor + ?Sized>(visitor: &mut V, node: &Node) {
+    match node {
+        Node::Element(element) => visitor.visit_element(element),
+        Node::ChildLine { modifier, id, signature, annotations, span, .. } => visitor.visit_child_line(ChildLine {
+            modifier: modifier.as_deref(),
+            id,
+            signature: signature.as_deref(),
+            annotations,
+            span: *span,
+        }),
+        Node::KeyValue { key, value, span, .. } => visitor.visit_key_value(key, value, *span),
+        Node::Const { name, value, span, .. } => visitor.visit_const(name, value, *span),
+        Node::Expansion { template, args, children, span, .. } => {
+            visitor.visit_expansion(template, args.as_deref(), children, *span)
+        }
+    }
+}
+
+pub fn walk_child_line<V: Visitor + ?Sized>(visitor: &mut V, line: ChildLine<'_>) {
+    for annotation in line.annotations {
+        visitor.visit_annotation(annotation);
+    }
+}
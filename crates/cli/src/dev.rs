@@ -0,0 +1,200 @@
+//! Running the dev servers of several targets at once for `z dev`: each in its generated
+//! project, with the tool `z pm dev` would use, and every line they print prefixed with
+//! the target's name, in a color of its own.
+//!
+//! On Unix each server runs in a process group of its own, so that Ctrl-C reaches `z`
+//! alone, which passes it on to every server, with what they started, and gives them a
+//! few seconds to stop before killing them. A second Ctrl-C kills them at once.
+
+use std::io::{BufRead, BufReader, Read};
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Stdio};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+use z_compiler_core::{tr, Runtime};
+
+const COLORS: [&str; 6] = ["\x1b[36m", "\x1b[35m", "\x1b[32m", "\x1b[33m", "\x1b[34m", "\x1b[31m"];
+const RESET: &str = "\x1b[0m";
+
+/// How often the servers are looked at
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// How long the servers have to stop after Ctrl-C before they're killed
+const GRACE_PERIOD: Duration = Duration::from_secs(5);
+
+/// A target whose dev server to run
+pub struct Server {
+    pub kind: String,
+    pub name: String,
+    pub project_dir: PathBuf,
+}
+
+struct Running {
+    /// What the lines of the server start with
+    prefix: String,
+    child: Child,
+}
+
+/// Times Ctrl-C was pressed, or `z` was asked to stop, since the servers started
+static INTERRUPTS: AtomicUsize = AtomicUsize::new(0);
+
+/// Start the dev server of each of `servers` and wait for them all to exit, or for Ctrl-C.
+/// Returns whether every server that exited on its own did so successfully.
+pub fn run(servers: &[Server]) -> bool {
+    let width = servers.iter().map(|server| server.name.len()).max().unwrap_or(0);
+    let colored = crate::frame::colored();
+    interrupt::catch();
+
+    println!("🚀 {}", tr!("dev-starting", count = servers.len()));
+    let mut failed = false;
+    let mut running = Vec::new();
+    for (index, server) in servers.iter().enumerate() {
+        let prefix = match colored {
+            true => format!("{}{:width$} |{} ", COLORS[index % COLORS.len()], server.name, RESET),
+            false => format!("{:width$} | ", server.name),
+        };
+        let Some((program, args)) = command(&server.kind, &server.project_dir) else {
+            println!("{}ℹ️  {}", prefix, tr!("no-package-manager", name = server.name, kind = server.kind));
+            continue;
+        };
+        println!("{}{}", prefix, tr!("running-command", command = format!("{} {}", program, args.join(" ")), name = server.name, kind = server.kind));
+        let mut command = Command::new(program);
+        command.args(&args).current_dir(&server.project_dir).stdin(Stdio::null()).stdout(Stdio::piped()).stderr(Stdio::piped());
+        #[cfg(unix)]
+        std::os::unix::process::CommandExt::process_group(&mut command, 0);
+        let mut child = match command.spawn() {
+            Ok(child) => child,
+            Err(e) => {
+                eprintln!("{}❌ {}", prefix, tr!("command-exec-failed", tool = program, name = server.name, error = e));
+                eprintln!("{}   {}", prefix, tr!("tool-missing-hint", tool = program));
+                failed = true;
+                continue;
+            }
+        };
+        if let Some(stdout) = child.stdout.take() {
+            forward(stdout, prefix.clone(), false);
+        }
+        if let Some(stderr) = child.stderr.take() {
+            forward(stderr, prefix.clone(), true);
+        }
+        running.push(Running { prefix, child });
+    }
+
+    let mut stopping: Option<Instant> = None;
+    while !running.is_empty() {
+        std::thread::sleep(POLL_INTERVAL);
+        running.retain_mut(|server| match server.child.try_wait() {
+            Ok(None) => true,
+            Ok(Some(status)) => {
+                if stopping.is_none() && !status.success() {
+                    failed = true;
+                    eprintln!("{}❌ {}", server.prefix, tr!("dev-server-exited", code = format!("{:?}", status.code())));
+                }
+                false
+            }
+            Err(_) => false,
+        });
+        let interrupts = INTERRUPTS.load(Ordering::SeqCst);
+        match stopping {
+            None if interrupts > 0 => {
+                println!("\n🛑 {}", tr!("dev-stopping", count = running.len()));
+                running.iter().for_each(|server| interrupt::stop(&server.child));
+                stopping = Some(Instant::now());
+            }
+            Some(since) if interrupts > 1 || since.elapsed() > GRACE_PERIOD => {
+                for server in &mut running {
+                    interrupt::kill(&mut server.child);
+                    let _ = server.child.wait();
+                }
+                running.clear();
+            }
+            _ => {}
+        }
+    }
+    !failed
+}
+
+/// Program and arguments that start the dev server of a target of type `kind`, as
+/// `z pm dev` runs it; None for types without one
+fn command(kind: &str, project_dir: &Path) -> Option<(&'static str, Vec<String>)> {
+    let dev = || vec!["dev".to_string()];
+    match kind {
+        "next" => Some(Runtime::detect(project_dir).command(project_dir, &dev())),
+        "deno" => Some(("deno", vec!["task".to_string(), "dev".to_string()])),
+        "tauri" => Some(("pnpm", vec!["tauri".to_string(), "dev".to_string()])),
+        "rust" => Some(("cargo", vec!["run".to_string()])),
+        _ => None,
+    }
+}
+
+/// Print what `output` gives, line by line with `prefix`, from a thread of its own
+fn forward(output: impl Read + Send + 'static, prefix: String, to_stderr: bool) {
+    std::thread::spawn(move || {
+        let mut reader = BufReader::new(output);
+        let mut line = Vec::new();
+        while reader.read_until(b'\n', &mut line).is_ok_and(|read| read > 0) {
+            let text = String::from_utf8_lossy(&line);
+            let text = text.trim_end_matches(['\n', '\r']);
+            match to_stderr {
+                true => eprintln!("{}{}", prefix, text),
+                false => println!("{}{}", prefix, text),
+            }
+            line.clear();
+        }
+    });
+}
+
+#[cfg(unix)]
+mod interrupt {
+    use std::process::{Child, Command, Stdio};
+    use std::sync::atomic::Ordering;
+
+    const SIGINT: i32 = 2;
+    const SIGTERM: i32 = 15;
+
+    extern "C" {
+        fn signal(signum: i32, handler: extern "C" fn(i32)) -> usize;
+    }
+
+    extern "C" fn on_interrupt(_: i32) {
+        super::INTERRUPTS.fetch_add(1, Ordering::SeqCst);
+    }
+
+    /// Count Ctrl-C and termination requests instead of exiting on them
+    pub fn catch() {
+        // Safety: the handler only touches an atomic, which is async-signal-safe
+        unsafe {
+            signal(SIGINT, on_interrupt);
+            signal(SIGTERM, on_interrupt);
+        }
+    }
+
+    /// Pass Ctrl-C on to the process group of `child`
+    pub fn stop(child: &Child) {
+        group("-INT", child);
+    }
+
+    pub fn kill(child: &mut Child) {
+        group("-KILL", child);
+    }
+
+    fn group(signal: &str, child: &Child) {
+        let _ = Command::new("kill").args([signal, "--", &format!("-{}", child.id())])
+            .stdout(Stdio::null()).stderr(Stdio::null()).status();
+    }
+}
+
+#[cfg(not(unix))]
+mod interrupt {
+    use std::process::Child;
+
+    /// Ctrl-C reaches every process of the console, the servers included
+    pub fn catch() {}
+
+    pub fn stop(_: &Child) {}
+
+    pub fn kill(child: &mut Child) {
+        let _ = child.kill();
+    }
+}
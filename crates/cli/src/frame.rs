@@ -0,0 +1,132 @@
+//! Diagnostics for people: the message with its code, the line of the `.z` source it
+//! points to with the offending part underlined, and how to fix it.
+//!
+//! ```text
+//!   ❌ error[schema]: Routes isn't available in swift targets
+//!     --> examples/ValidationDemo/main.z:68:3
+//!      |
+//!   68 |   Routes {
+//!      |   ^^^^^^^^
+//!      = help: Move it into a next target
+//! ```
+//!
+//! Colors are plain ANSI escapes, used when stderr is a terminal that isn't `dumb` and
+//! `$NO_COLOR` isn't set, or when `--color always` asks for them. A diagnostic whose
+//! source can't be read, or that points nowhere in particular, is printed without a frame.
+
+use std::io::IsTerminal;
+use std::path::Path;
+use std::sync::OnceLock;
+
+use z_compiler_core::{tr, Diagnostic, Severity};
+
+const BOLD: &str = "\x1b[1m";
+const RED: &str = "\x1b[31m";
+const YELLOW: &str = "\x1b[33m";
+const BLUE: &str = "\x1b[34m";
+const CYAN: &str = "\x1b[36m";
+const RESET: &str = "\x1b[0m";
+
+/// Whether to color the output
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum ColorChoice {
+    /// When stderr is a terminal that can show colors
+    Auto,
+    Always,
+    Never,
+}
+
+/// Whether diagnostics are colored, decided once per run
+static COLOR: OnceLock<bool> = OnceLock::new();
+
+pub fn set_color(choice: ColorChoice) {
+    let _ = COLOR.set(match choice {
+        ColorChoice::Auto => colors_supported(),
+        ColorChoice::Always => true,
+        ColorChoice::Never => false,
+    });
+}
+
+/// Whether what's printed for people is colored
+pub fn colored() -> bool {
+    *COLOR.get_or_init(colors_supported)
+}
+
+fn colors_supported() -> bool {
+    std::io::stderr().is_terminal()
+        && std::env::var_os("NO_COLOR").is_none_or(|value| value.is_empty())
+        && std::env::var("TERM").map_or(true, |term| term != "dumb")
+}
+
+/// `diagnostic` as printed on stderr, a line per element; `file` is where its source is,
+/// if it's on disk
+pub fn render(diagnostic: &Diagnostic, file: &Path) -> Vec<String> {
+    let paint = |style: &str, text: &str| match colored() {
+        true => format!("{}{}{}", style, text, RESET),
+        false => text.to_string(),
+    };
+    let (icon, severity, style) = match diagnostic.severity {
+        Severity::Error => ("❌", tr!("diagnostic-error"), RED),
+        Severity::Warning => ("⚠️ ", tr!("diagnostic-warning"), YELLOW),
+    };
+    let heading = paint(&format!("{}{}", BOLD, style), &format!("{}[{}]", severity, diagnostic.code));
+    let mut lines = vec![format!("  {} {}: {}", icon, heading, paint(BOLD, &diagnostic.message))];
+
+    let source = diagnostic.span.and_then(|_| std::fs::read_to_string(file).ok());
+    match diagnostic.span.zip(source.as_deref()).and_then(|(span, source)| locate(source, span.start, span.end)) {
+        Some(snippet) => {
+            let number = snippet.line.to_string();
+            let gutter = " ".repeat(number.len());
+            lines.push(format!("  {}{} {}:{}:{}", gutter, paint(BLUE, "-->"), file.display(), snippet.line, snippet.column));
+            lines.push(format!("  {} {}", gutter, paint(BLUE, "|")));
+            lines.push(format!("  {} {} {}", paint(BLUE, &number), paint(BLUE, "|"), snippet.text));
+            lines.push(format!("  {} {} {}{}", gutter, paint(BLUE, "|"), snippet.indent, paint(&format!("{}{}", BOLD, style), &"^".repeat(snippet.width))));
+            if let Some(help) = &diagnostic.help {
+                lines.push(format!("  {} {} {}: {}", gutter, paint(BLUE, "="), paint(BOLD, &tr!("diagnostic-help")), help));
+            }
+        }
+        None => {
+            // Without the source, say where it is as the message always did
+            match (&diagnostic.file, diagnostic.line) {
+                (_, Some(line)) => lines.push(format!("     {} {}:{}", paint(BLUE, "-->"), file.display(), line)),
+                (Some(_), None) => lines.push(format!("     {} {}", paint(BLUE, "-->"), file.display())),
+                (None, None) => {}
+            }
+            if let Some(help) = &diagnostic.help {
+                lines.push(format!("     💡 {}", paint(CYAN, help)));
+            }
+        }
+    }
+    lines
+}
+
+/// The line of a span, and where in it the span is
+struct Snippet<'a> {
+    line: usize,
+    /// 1-based, in characters
+    column: usize,
+    text: &'a str,
+    /// What goes under the line before the underline: its tabs, and spaces for the rest
+    indent: String,
+    /// Characters underlined, up to the end of the line for a span over several
+    width: usize,
+}
+
+fn locate(source: &str, start: usize, end: usize) -> Option<Snippet<'_>> {
+    if start > source.len() || !source.is_char_boundary(start) {
+        return None;
+    }
+    let line_start = source[..start].rfind('\n').map_or(0, |newline| newline + 1);
+    let line_end = source[start..].find('\n').map_or(source.len(), |newline| start + newline);
+    let text = source[line_start..line_end].trim_end_matches('\r');
+    let before = &source[line_start..start];
+    let end = end.min(line_start + text.len()).max(start);
+    let width = source.get(start..end).map_or(0, |span| span.chars().count());
+    Some(Snippet {
+        line: source[..line_start].matches('\n').count() + 1,
+        column: before.chars().count() + 1,
+        text,
+        indent: before.chars().map(|c| if c == '\t' { '\t' } else { ' ' }).collect(),
+        width: width.max(1),
+    })
+}
@@ -1,143 +1,2251 @@
-use clap::Parser;
-use regex::Regex;
+use clap::{CommandFactory, Parser, Subcommand};
+use z_compiler_core::report::{Change, PlannedChange, Status, TargetReport, REPORT_FILE};
+use z_compiler_core::style::{Indent, Quotes};
+use z_compiler_core::{
+    tr, ArchiveFormat, Bump, CacheMode, CodeStyle, CompileOptions, CompileResult, DependencyOverrides, DeployRequest, DevEnv, Diagnostic,
+    Environments, Hooks, Layout, Limits, Locale, Observers, Pipeline, Plugins, PublishOptions, RegistryLayers, RemoteCache, Runtime, TargetOptions, Templates, UserCache,
+};
+use z_compiler_core::inspect::DumpFormat;
+use z_compiler_core::structure::GraphFormat;
+use z_compiler_core::members::{Member, Members};
+use z_compiler_core::settings::{self, Settings};
+use z_compiler_core::user_cache;
+use z_compiler_core::sandbox::{self, SandboxPolicy};
+use z_compiler_core::version::{ReleaseOptions, Version};
+
+mod dev;
+mod frame;
+mod trace;
+mod ui;
+
+use trace::Level;
+
+/// Exit status of a command that did what it was asked
+const EXIT_OK: i32 = 0;
+
+/// Exit status when the program has errors, or a command couldn't do its job
+const EXIT_ERRORS: i32 = 1;
+
+/// Exit status when the command line, or the project it points to, can't be used: flags
+/// clap rejects, a source or target that isn't there, a z.toml that doesn't parse
+const EXIT_USAGE: i32 = 2;
+
+/// Exit status when a tool run for a target, e.g. pnpm or cargo, couldn't be started or was
+/// killed; a tool that exits with a status of its own passes it on instead
+const EXIT_TOOL: i32 = 3;
 
 /// Z language compiler CLI
 #[derive(Parser)]
-#[command(author, version, about = "Z language compiler CLI")]
-struct Args {
+#[command(name = "z", bin_name = "z", author, version, about = "Z language compiler CLI")]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+
+    #[command(flatten)]
+    verbosity: Verbosity,
+
+    /// Language for messages (en, es). Defaults to $Z_LOCALE, then $LC_ALL/$LC_MESSAGES/$LANG
+    #[arg(long, global = true)]
+    locale: Option<Locale>,
+}
+
+/// How much is printed besides diagnostics, which always are
+#[derive(clap::Args)]
+struct Verbosity {
+    /// Only print diagnostics and errors
+    #[arg(short, long, global = true, conflicts_with = "verbose")]
+    quiet: bool,
+
+    /// Print more: -v adds how long each target took and how many files it has, -vv the files
+    #[arg(short, long, global = true, action = clap::ArgAction::Count)]
+    verbose: u8,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    Build(BuildArgs),
+    Check(CheckArgs),
+    Dev(DevArgs),
+    Test(TestArgs),
+    New(NewArgs),
+    Pm(PmArgs),
+    Fmt(FmtArgs),
+    Clean(CleanArgs),
+    Add(AddArgs),
+    Ast(AstArgs),
+    Graph(GraphArgs),
+    Explain(ExplainArgs),
+    Registry(RegistryArgs),
+    PublishOutput(PublishArgs),
+    Deploy(DeployArgs),
+    Version(VersionArgs),
+    Trust(TrustArgs),
+    Preview(PreviewArgs),
+    Examples(ExamplesArgs),
+    Cache(CacheArgs),
+    Telemetry(TelemetryArgs),
+    Internals(InternalsArgs),
+}
+
+/// Compile a program into a project for each of its targets
+#[derive(Parser)]
+struct BuildArgs {
+    /// Source file to compile, a directory whose z.toml lists its members, a glob of source
+    /// files, or `-` to read the program from stdin. Defaults to the entry of the project the
+    /// current directory is in
+    source: Option<String>,
+
+    #[command(flatten)]
+    build: BuildFlags,
+}
+
+/// Parse and validate a program and its targets, printing the diagnostics, without generating
+/// anything; exits with status 1 on errors
+#[derive(Parser)]
+struct CheckArgs {
+    /// Source file to check. Defaults to the entry of the project the current directory is in
+    source: Option<String>,
+
+    /// Settings of this [profile.NAME] of z.toml. Defaults to $Z_PROFILE
+    #[arg(long, value_name = "NAME", env = "Z_PROFILE")]
+    profile: Option<String>,
+
+    /// Report unknown target types, and targets or sections their compiler ignores, as errors
+    #[arg(long)]
+    strict: bool,
+
+    /// Report any warning not silenced by an `@allow(...)` annotation as an error
+    #[arg(long)]
+    deny_warnings: bool,
+
+    /// Environment to check for, e.g. prod. Defaults to $Z_ENV
+    #[arg(long, value_name = "NAME", env = "Z_ENV")]
+    env: Option<String>,
+
+
+    /// How to print diagnostics: for people, or as one JSON object per line on stdout
+    #[arg(long, value_name = "FORMAT", default_value = "human")]
+    message_format: MessageFormat,
+
+    /// When to color diagnostics: auto (on a terminal, unless $NO_COLOR or TERM=dumb), always or never
+    #[arg(long, value_name = "WHEN", default_value = "auto")]
+    color: frame::ColorChoice,
+
+    #[command(flatten)]
+    limits: LimitFlags,
+}
+
+/// Compile a program, then start the dev servers of its targets, compiling again as the
+/// program changes
+#[derive(Parser)]
+struct DevArgs {
+    /// Targets to run, by app name (`WebApp`) or with their type (`next:WebApp`); all of them
+    /// when left out
+    targets: Vec<String>,
+
+    /// Source file declaring the target. Defaults to the entry of the project the current
+    /// directory is in
+    #[arg(long)]
+    source: Option<String>,
+
+    /// Don't compile again when the source or a file it imports changes
+    #[arg(long)]
+    no_watch: bool,
+
+    #[command(flatten)]
+    compile: CompileFlags,
+}
+
+/// Compile a program, then run the tests of its targets' generated projects (vitest for
+/// next, `cargo test` for rust, `swift test`, `deno test`) and sum them up; exits with
+/// status 1 if any failed
+#[derive(Parser)]
+struct TestArgs {
+    /// Targets to test, by app name (`WebApp`) or with their type (`next:WebApp`); all of
+    /// them when left out
+    targets: Vec<String>,
+
+    /// Source file declaring the targets. Defaults to the entry of the project the current
+    /// directory is in
+    #[arg(long)]
+    source: Option<String>,
+
+    #[command(flatten)]
+    compile: CompileFlags,
+}
+
+/// Start a project: a directory with a main.z to build on, taken from one of the examples
+#[derive(Parser)]
+struct NewArgs {
+    /// Directory to create the project in
+    dir: std::path::PathBuf,
+
+    /// Example to start from (see `z examples list`)
+    #[arg(long, value_name = "NAME", default_value = "todo")]
+    from: String,
+
+    /// Replace an existing main.z
+    #[arg(short, long)]
+    force: bool,
+
+}
+
+/// Run a package manager command, e.g. `install` or `dev`, in the generated project of each target
+#[derive(Parser)]
+struct PmArgs {
+    /// Command and arguments for the package manager of each project
+    #[arg(required = true, trailing_var_arg = true, allow_hyphen_values = true)]
+    command: Vec<String>,
+
+    /// Source file declaring the targets. Defaults to the entry of the project the current
+    /// directory is in
+    #[arg(long)]
+    source: Option<String>,
+
+    /// Directory holding the generated projects (defaults to out/ next to the source, or the
+    /// project's `out`)
+    #[arg(short, long)]
+    out: Option<String>,
+
+    /// Only run in these targets, by app name
+    #[arg(long = "target", value_name = "NAME", value_delimiter = ',')]
+    targets: Vec<String>,
+
+}
+
+/// Remove the generated projects, or those of the targets named, leaving files of your own
+#[derive(Parser)]
+struct CleanArgs {
+    /// Targets to clean, by app name (`WebApp`) or with their type (`next:WebApp`); all of them
+    /// when left out
+    targets: Vec<String>,
+
+    /// Source file declaring the targets. Defaults to the entry of the project the current
+    /// directory is in
+    #[arg(long)]
+    source: Option<String>,
+
+    /// Directory holding the generated projects (defaults to out/ next to the source, or the
+    /// project's `out`)
+    #[arg(short, long)]
+    out: Option<String>,
+
+    /// Remove dependencies and build products too: node_modules, target and .next
+    #[arg(long)]
+    deps: bool,
+
+    /// Remove the compile cache too, so the next build generates every target
+    #[arg(long)]
+    cache: bool,
+
+    /// Remove generated files edited since they were generated too
+    #[arg(long)]
+    overwrite: bool,
+
+}
+
+/// Add a target, route, component or section to the program, rewriting it formatted
+#[derive(Parser)]
+struct AddArgs {
+    #[command(subcommand)]
+    what: AddCommand,
+
+    /// Source file to add to. Defaults to the entry of the project the current directory is in
+    #[arg(long, global = true)]
+    source: Option<String>,
+
+    /// Print the diff of the change instead of writing it
+    #[arg(long, global = true)]
+    dry_run: bool,
+
+}
+
+#[derive(Subcommand)]
+enum AddCommand {
+    /// A target block, e.g. `z add target rust Api`
+    Target {
+        /// Target type, e.g. next or rust
+        kind: String,
+        name: String,
+    },
+    /// A route in the Routes of a target, e.g. `z add route /pricing --to MySite`
+    Route {
+        /// Path of the route; missing routes on the way are added too, e.g. /customers/[id]
+        path: String,
+        /// Target to add to, by app name or with its type; may be left out when the program has one
+        #[arg(long, value_name = "TARGET")]
+        to: Option<String>,
+    },
+    /// A component in the Components of a target
+    Component {
+        name: String,
+        /// Target to add to, by app name or with its type; may be left out when the program has one
+        #[arg(long, value_name = "TARGET")]
+        to: Option<String>,
+    },
+    /// An empty section of a target, e.g. `z add section API --to MySite`
+    Section {
+        name: String,
+        /// Target to add to, by app name or with its type; may be left out when the program has one
+        #[arg(long, value_name = "TARGET")]
+        to: Option<String>,
+    },
+}
+
+/// Print the tree the parser reads the program as, or the typed model of its targets, to see
+/// why a block isn't picked up
+#[derive(Parser)]
+struct AstArgs {
+    /// Source file to read. Defaults to the entry of the project the current directory is in
+    source: Option<String>,
+
+    /// How to print it: json, yaml, or tree, an outline with where each node is
+    #[arg(long, value_name = "FORMAT", default_value = "tree")]
+    format: DumpFormat,
+
+    /// Print the typed model the target compilers get instead of the AST
+    #[arg(long)]
+    semantic: bool,
+
+    /// Only print the nodes this selector matches, e.g. "next:MySite > Routes", with what's
+    /// under them
+    #[arg(long, value_name = "SELECTOR")]
+    query: Option<String>,
+
+    /// Environment to build the model for, e.g. prod. Defaults to $Z_ENV
+    #[arg(long, value_name = "NAME", env = "Z_ENV")]
+    env: Option<String>,
+
+}
+
+/// Draw the targets of a program, their sections, components and routes, and what refers to
+/// what, as a Graphviz or Mermaid graph; components nothing renders stand out
+#[derive(Parser)]
+struct GraphArgs {
+    /// Source file to read. Defaults to the entry of the project the current directory is in
+    source: Option<String>,
+
+    /// Graph language: dot, for Graphviz (`z graph | dot -Tsvg > app.svg`), or mermaid
+    #[arg(long, value_name = "FORMAT", default_value = "dot")]
+    format: GraphFormat,
+
+    /// Environment to build the model for, e.g. prod. Defaults to $Z_ENV
+    #[arg(long, value_name = "NAME", env = "Z_ENV")]
+    env: Option<String>,
+
+}
+
+/// Show the target types, sections and options z knows: the built-in registry, with the
+/// registry files and plugins of the project over it
+#[derive(Parser)]
+struct RegistryArgs {
+    #[command(subcommand)]
+    command: RegistryCommand,
+
+    /// Project directory whose registry files and plugins to include
+    #[arg(long, global = true, value_name = "DIR", default_value = ".")]
+    project: std::path::PathBuf,
+
+    /// Print the registry entries as JSON
+    #[arg(long, global = true)]
+    json: bool,
+
+}
+
+#[derive(Subcommand)]
+enum RegistryCommand {
+    /// List the target types, with their aliases, and the sections
+    List,
+    /// Show a target type, by name or alias, with its sections and options, or a section,
+    /// with what goes in it and the target types it's in
+    Show {
+        /// Target type, alias or section, e.g. next or Routes
+        name: String,
+    },
+}
+
+/// Explain a diagnostic code, e.g. `z explain unresolved`, with a program that gets it and
+/// the same program fixed; lists the codes when left out
+#[derive(Parser)]
+struct ExplainArgs {
+    /// Code to explain, as printed in brackets after error or warning
+    code: Option<String>,
+
+}
+
+/// Format .z files: two-space indentation, `{` on the line of its block, `key: value`
+#[derive(Parser)]
+struct FmtArgs {
+    /// Files to format, directories to format every .z file under, or - to format stdin
+    /// to stdout
+    #[arg(default_value = ".")]
+    paths: Vec<std::path::PathBuf>,
+
+    /// Don't write anything; print what formatting would change, and exit with status 1
+    /// if any file isn't formatted
+    #[arg(long)]
+    check: bool,
+
+}
+
+/// `z <source> [flags]` and `z <project> <command>`, from before there were subcommands
+#[derive(Parser)]
+#[command(name = "z", bin_name = "z", author, version, about = "Z language compiler CLI")]
+struct LegacyArgs {
     /// First argument: either a source file to compile or a project name for package manager commands
     #[arg(value_name = "SOURCE_OR_PROJECT")]
     first_arg: String,
 
-    /// Additional arguments: for compilation this is ignored, for project commands these are passed to the package manager
-    #[arg(trailing_var_arg = true, allow_hyphen_values = true)]
-    additional_args: Vec<String>,
+    /// Additional arguments: for compilation this is ignored, for project commands these are passed to the package manager
+    #[arg(trailing_var_arg = true, allow_hyphen_values = true)]
+    additional_args: Vec<String>,
+
+    #[command(flatten)]
+    build: BuildFlags,
+
+    #[command(flatten)]
+    verbosity: Verbosity,
+
+    /// Language for messages (en, es). Defaults to $Z_LOCALE, then $LC_ALL/$LC_MESSAGES/$LANG
+    #[arg(long)]
+    locale: Option<Locale>,
+}
+
+/// What `z build` takes besides the flags of every compile
+#[derive(clap::Args)]
+struct BuildFlags {
+    /// Report the files each target would create, update or delete, with their diffs, without writing anything
+    #[arg(long)]
+    dry_run: bool,
+
+    /// Pack the generated projects into FILE (.zip, .tar or .tar.gz) instead of writing them to the output directory
+    #[arg(long, value_name = "FILE")]
+    archive: Option<std::path::PathBuf>,
+
+    /// Compile twice, each time into a scratch directory, and report the files that came out
+    /// different; exits with status 1 if any did
+    #[arg(long)]
+    check_determinism: bool,
+
+    /// Show a live dashboard of the build, then offer to open the output or start a dev server
+    #[arg(long)]
+    ui: bool,
+
+    /// Pick the targets to build from a list first, then show the build as `--ui` does
+    #[arg(long)]
+    interactive: bool,
+
+    /// Where to write the machine-readable build report (defaults to <out>/z-report.json)
+    #[arg(long, value_name = "PATH")]
+    report: Option<std::path::PathBuf>,
+
+    /// Print the generated code on stdout instead of writing it to the output directory
+    /// (`--emit -`), for a target that generates a single file, like swift
+    #[arg(long, value_name = "DEST", value_parser = ["-"], conflicts_with_all = ["dry_run", "archive", "ui", "interactive", "check_determinism", "message_format"])]
+    emit: Option<String>,
+
+    #[command(flatten)]
+    compile: CompileFlags,
+}
+
+/// Flags of every command that compiles
+#[derive(clap::Args)]
+struct CompileFlags {
+    /// Output directory (defaults to out/ next to the source, or the `out` of the project's z.toml)
+    #[arg(short, long)]
+    out: Option<String>,
+
+    /// Settings of this [profile.NAME] of z.toml, e.g. ci. Defaults to $Z_PROFILE
+    #[arg(long, value_name = "NAME", env = "Z_PROFILE")]
+    profile: Option<String>,
+
+    /// Only generate these targets, by app name (`MySite`) or with their type (`next:MySite`);
+    /// the others are still checked
+    #[arg(long = "target", value_name = "NAME", value_delimiter = ',')]
+    only: Vec<String>,
+
+    /// Don't generate these targets, by app name or with their type
+    #[arg(long, value_name = "NAME", value_delimiter = ',')]
+    exclude: Vec<String>,
+
+    /// Indentation of generated code: a number of spaces or "tab"
+    #[arg(long)]
+    indent: Option<Indent>,
+
+    /// Quote style for generated TypeScript/JavaScript strings (single or double)
+    #[arg(long)]
+    quotes: Option<Quotes>,
+
+    /// Whether generated TypeScript/JavaScript statements end with semicolons
+    #[arg(long)]
+    semicolons: Option<bool>,
+
+    /// Whether multi-line TypeScript/JavaScript object and array literals get trailing commas
+    #[arg(long)]
+    trailing_commas: Option<bool>,
+
+    /// Also emit development environment files pinning each project's toolchain (nix, devcontainer)
+    #[arg(long, value_name = "KIND", value_delimiter = ',')]
+    dev_env: Vec<DevEnv>,
+
+    /// Shared cache of generated targets: an http(s) URL, s3://bucket/prefix, or a directory.
+    /// Defaults to $Z_REMOTE_CACHE
+    #[arg(long, value_name = "LOCATION", env = "Z_REMOTE_CACHE")]
+    remote_cache: Option<String>,
+
+    /// Whether to only restore from the remote cache (read) or also upload new targets (read-write)
+    #[arg(long, value_name = "MODE", default_value = "read")]
+    remote_cache_mode: CacheMode,
+
+    /// Don't restore targets from, or keep them in, the cache shared by your projects
+    /// ($Z_CACHE_DIR, by default ~/.cache/z)
+    #[arg(long)]
+    no_user_cache: bool,
+
+    /// Regenerate every target, even those whose inputs haven't changed since the last compile
+    #[arg(long)]
+    force: bool,
+
+    /// Replace generated files that were edited by hand since, instead of keeping them
+    #[arg(long)]
+    overwrite: bool,
+
+    /// Don't run the commands targets list in `after_generate` once they're generated
+    #[arg(long)]
+    no_hooks: bool,
+
+    /// Fail the build on unknown target types, and on targets or sections their compiler ignores
+    #[arg(long)]
+    strict: bool,
+
+    /// Fail the build on any warning not silenced by an `@allow(...)` annotation
+    #[arg(long)]
+    deny_warnings: bool,
+
+    /// Environment to compile for, e.g. prod: its values from `env` blocks and z.toml replace the
+    /// program's constants. Defaults to $Z_ENV
+    #[arg(long, value_name = "NAME", env = "Z_ENV")]
+    env: Option<String>,
+
+
+    /// How to print diagnostics: for people, or as one JSON object per line on stdout
+    #[arg(long, value_name = "FORMAT", default_value = "human")]
+    message_format: MessageFormat,
+
+    /// When to color diagnostics: auto (on a terminal, unless $NO_COLOR or TERM=dumb), always or never
+    #[arg(long, value_name = "WHEN", default_value = "auto")]
+    color: frame::ColorChoice,
+
+    #[command(flatten)]
+    limits: LimitFlags,
+}
+
+/// Bounds on the sources a command reads
+#[derive(clap::Args)]
+struct LimitFlags {
+    /// Largest source accepted, in bytes (default 8 MiB)
+    #[arg(long, value_name = "BYTES")]
+    max_file_size: Option<usize>,
+
+    /// Deepest nesting of blocks accepted (default 128)
+    #[arg(long, value_name = "LEVELS")]
+    max_depth: Option<usize>,
+
+    /// Most blocks, lines, properties and constants accepted in a source (default 200000)
+    #[arg(long, value_name = "COUNT")]
+    max_nodes: Option<usize>,
+}
+
+impl LimitFlags {
+    /// The limits given, and the default ones for the others
+    fn limits(&self) -> Limits {
+        let defaults = Limits::default();
+        Limits {
+            max_bytes: self.max_file_size.unwrap_or(defaults.max_bytes),
+            max_depth: self.max_depth.unwrap_or(defaults.max_depth),
+            max_nodes: self.max_nodes.unwrap_or(defaults.max_nodes),
+        }
+    }
+}
+
+/// Commit generated projects to a branch (or another repository) so they can be used without running the compiler
+#[derive(Parser)]
+struct PublishArgs {
+    /// Directory holding the generated projects
+    #[arg(default_value = "out")]
+    out: std::path::PathBuf,
+
+    /// Branch to commit to; with --split, the prefix of the per-target branches
+    #[arg(long, default_value = "generated")]
+    branch: String,
+
+    /// Repository to push the branch to instead of committing to the current one
+    #[arg(long, value_name = "URL")]
+    repo: Option<String>,
+
+    /// Push the branch to origin after committing to the current repository
+    #[arg(long)]
+    push: bool,
+
+    /// Only publish these targets, by app name
+    #[arg(long = "target", value_name = "NAME", value_delimiter = ',')]
+    targets: Vec<String>,
+
+    /// Give every target its own branch, <branch>-<name>, with the project at its root
+    #[arg(long)]
+    split: bool,
+
+    /// Commit message (defaults to one naming the current commit)
+    #[arg(short, long)]
+    message: Option<String>,
+
+}
+
+/// Deploy a generated project with the provider named in its target's `Deploy` section
+#[derive(Parser)]
+struct DeployArgs {
+    /// Target to deploy, by app name (`WebApp`) or with its type (`next:WebApp`)
+    target: String,
+
+    /// Source file declaring the target
+    #[arg(long, default_value = "main.z")]
+    source: std::path::PathBuf,
+
+    /// Directory holding the generated projects (defaults to out/ next to the source)
+    #[arg(short, long)]
+    out: Option<std::path::PathBuf>,
+
+    /// Environment to deploy to, e.g. preview or production
+    #[arg(long = "env", value_name = "ENVIRONMENT", default_value = "preview")]
+    environment: String,
+
+    /// Promote an existing deployment (URL, id or release tag) to production instead of deploying
+    #[arg(long, value_name = "DEPLOYMENT", conflicts_with = "environment")]
+    promote: Option<String>,
+
+}
+
+/// Show or bump the version of the generated apps, kept in a VERSION file next to the source
+#[derive(Parser)]
+struct VersionArgs {
+    #[command(subcommand)]
+    command: Option<VersionCommand>,
+
+    /// Source file of the apps
+    #[arg(long, default_value = "main.z", global = true)]
+    source: std::path::PathBuf,
+
+}
+
+/// Approve the sandbox permissions a project's z.toml asks for its commands
+#[derive(Parser)]
+struct TrustArgs {
+    /// Source file of the project; z.toml is read from its directory
+    #[arg(long, default_value = "main.z")]
+    source: std::path::PathBuf,
+
+    /// Approve without asking
+    #[arg(short, long)]
+    yes: bool,
+
+}
+
+/// Print, as JSON, the targets of a program or the code generated for the declaration at a position,
+/// for editors to show next to the source
+#[derive(Parser)]
+struct PreviewArgs {
+    /// Source file of the program
+    #[arg(default_value = "main.z")]
+    source: std::path::PathBuf,
+
+    /// Read the program from stdin, e.g. unsaved editor contents, instead of from the source file
+    #[arg(long)]
+    stdin: bool,
+
+    /// List the targets instead of generating one
+    #[arg(long)]
+    list: bool,
+
+    /// 1-based line of the declaration to preview
+    #[arg(long, default_value_t = 1)]
+    line: usize,
+
+    /// 1-based column of the declaration to preview
+    #[arg(long, default_value_t = 1)]
+    column: usize,
+
+    /// Target to generate, as name or kind:name (defaults to the one around the position)
+    #[arg(long)]
+    target: Option<String>,
+
+}
+
+/// Example programs that come with the compiler: list them, start a project from one, or compile them
+#[derive(Parser)]
+struct ExamplesArgs {
+    #[command(subcommand)]
+    command: ExamplesCommand,
+
+}
+
+#[derive(Subcommand)]
+enum ExamplesCommand {
+    /// List the examples with what each one shows
+    List,
+    /// Copy an example into a directory as its main.z
+    Copy {
+        /// Example to copy
+        name: String,
+
+        /// Directory to copy it into (defaults to one named after the example)
+        dir: Option<std::path::PathBuf>,
+
+        /// Replace an existing main.z
+        #[arg(short, long)]
+        force: bool,
+    },
+    /// Compile an example and show its source followed by the files generated for each target
+    Run {
+        /// Example to compile
+        name: String,
+
+        /// Directory to keep the generated projects in (by default they're removed afterwards)
+        #[arg(short, long)]
+        out: Option<std::path::PathBuf>,
+    },
+    /// Compile every example and report the ones that fail
+    Check,
+}
+
+/// Inspect what the compiler is built with
+#[derive(Parser)]
+struct InternalsArgs {
+    #[command(subcommand)]
+    command: InternalsCommand,
+
+}
+
+#[derive(Subcommand)]
+enum InternalsCommand {
+    /// Write the registry, templates, message catalogs and examples embedded in the compiler to a directory
+    DumpAssets {
+        /// Directory to write them to
+        #[arg(default_value = "z-assets")]
+        dir: std::path::PathBuf,
+    },
+    /// Check that the compiler of a target type (built-in, or a plugin of the project) generates sound projects
+    #[cfg(feature = "conformance")]
+    Conformance {
+        /// Target type whose compiler to check
+        kind: String,
+
+        /// Fail unless this file is generated, relative to the project (repeatable)
+        #[arg(long, value_name = "PATH")]
+        require: Vec<std::path::PathBuf>,
+
+        /// Project directory whose z.toml, registry files and templates to use
+        #[arg(long, default_value = ".")]
+        project: std::path::PathBuf,
+    },
+}
+
+/// Share anonymous usage metrics (target types, build times, diagnostic codes) to help decide what to improve
+#[derive(Parser)]
+struct TelemetryArgs {
+    #[command(subcommand)]
+    command: TelemetryCommand,
+
+}
+
+#[derive(Subcommand)]
+enum TelemetryCommand {
+    /// Start recording a metrics event for every compile
+    On {
+        /// URL to post batches of events to (defaults to $Z_TELEMETRY_ENDPOINT; without one they're only queued)
+        #[arg(long, value_name = "URL")]
+        endpoint: Option<String>,
+    },
+    /// Stop recording and delete the events not sent yet
+    Off,
+    /// Show whether metrics are recorded and the events waiting to be sent
+    Show,
+}
+
+/// The cache of generated targets shared by your projects ($Z_CACHE_DIR, by default ~/.cache/z)
+#[derive(Parser)]
+struct CacheArgs {
+    #[command(subcommand)]
+    command: CacheCommand,
+
+}
+
+#[derive(Subcommand)]
+enum CacheCommand {
+    /// Show where the cache is and how much it holds
+    Show,
+    /// Delete the cached targets
+    Clean {
+        /// Only delete the targets restored least recently, until the rest fit in this size, e.g. 500M
+        #[arg(long, value_name = "SIZE", value_parser = user_cache::parse_size)]
+        max_size: Option<u64>,
+    },
+}
+
+#[derive(Subcommand)]
+enum VersionCommand {
+    /// Bump the version in the generated manifests, add a changelog entry from conventional commits and tag the release
+    Bump {
+        /// Part of the version to increment
+        #[arg(value_name = "major|minor|patch")]
+        level: Bump,
+
+        /// Directory holding the generated projects (defaults to out/ next to the source)
+        #[arg(short, long)]
+        out: Option<std::path::PathBuf>,
+
+        /// Only update the files; don't commit or tag
+        #[arg(long)]
+        no_tag: bool,
+    },
+}
+
+fn main() {
+    install_crash_reporter();
+
+    // `z main.z` and `z <project> <command>` predate the subcommands, and keep working
+    // as `z build main.z` and `z pm`
+    if let Some(first) = std::env::args().nth(1) {
+        if !first.starts_with('-') && first != "help" && Cli::command().find_subcommand(&first).is_none() {
+            return handle_legacy(LegacyArgs::parse());
+        }
+    }
+
+    let cli = Cli::parse();
+    z_compiler_core::i18n::set_locale(cli.locale.or_else(Locale::from_env).unwrap_or_default());
+    set_verbosity(&cli.verbosity);
+    match cli.command {
+        Command::Build(args) => handle_build(args.source.as_deref(), args.build),
+        Command::Check(args) => handle_check(args),
+        Command::Dev(args) => handle_dev(args),
+        Command::Test(args) => handle_test(args),
+        Command::New(args) => handle_new(args),
+        Command::Pm(args) => handle_pm(args),
+        Command::Fmt(args) => handle_fmt(args),
+        Command::Clean(args) => handle_clean(args),
+        Command::Add(args) => handle_add(args),
+        Command::Ast(args) => handle_ast(args),
+        Command::Graph(args) => handle_graph(args),
+        Command::Explain(args) => handle_explain(args),
+        Command::Registry(args) => handle_registry(args),
+        Command::PublishOutput(args) => handle_publish(args),
+        Command::Deploy(args) => handle_deploy(args),
+        Command::Version(args) => handle_version(args),
+        Command::Trust(args) => handle_trust(args),
+        Command::Preview(args) => handle_preview(args),
+        Command::Examples(args) => handle_examples(args),
+        Command::Cache(args) => handle_cache(args),
+        Command::Telemetry(args) => handle_telemetry(args),
+        Command::Internals(args) => handle_internals(args),
+    }
+}
+
+fn handle_legacy(args: LegacyArgs) {
+    z_compiler_core::i18n::set_locale(args.locale.or_else(Locale::from_env).unwrap_or_default());
+    set_verbosity(&args.verbosity);
+
+    // A project directory followed by a command runs it with each project's package manager
+    let dir = std::path::Path::new(&args.first_arg);
+    if !args.additional_args.is_empty() && dir.is_dir() {
+        let project = Located::find(None, dir, None).unwrap_or_else(|e| {
+            eprintln!("❌ {}", e);
+            std::process::exit(EXIT_USAGE);
+        });
+        eprintln!("ℹ️  {}", tr!("legacy-project-command", source = project.entry.display(), command = args.additional_args.join(" ")));
+        run_in_projects(&project, None, &[], &args.additional_args);
+    }
+    handle_build(Some(&args.first_arg), args.build);
+}
+
+/// The program a command works on, and the project it's in
+struct Located {
+    entry: std::path::PathBuf,
+    /// The nearest directory from the entry's up with a z.toml, or the entry's own
+    root: std::path::PathBuf,
+    settings: Settings,
+    /// Whether the project's z.toml lists members to compile
+    has_members: bool,
+}
+
+impl Located {
+    /// `source`, or else the entry of the project `dir` is in, with the settings of that
+    /// project and of its `profile`
+    fn find(source: Option<&str>, dir: &std::path::Path, profile: Option<&str>) -> Result<Self, String> {
+        let root = project_root(source.map_or(dir, |source| parent_dir(std::path::Path::new(source))));
+        let config = z_compiler_core::toml::load_config(&root)?;
+        let in_config = |e: String| format!("{}: {}", root.join(z_compiler_core::toml::CONFIG_FILE).display(), e);
+        let settings = Settings::from_config(&config, profile).map_err(in_config)?;
+        let has_members = !Members::from_config(&config).map_err(in_config)?.is_empty();
+        let entry = source.map_or_else(|| in_dir(&root, settings.entry()), std::path::PathBuf::from);
+        Ok(Self { entry, root, settings, has_members })
+    }
+
+    /// Where to compile to: `-o`, or else the project's `out`, or else out/ next to the entry
+    fn out_dir(&self, flag: Option<&str>) -> String {
+        match (flag, &self.settings.out) {
+            (Some(out), _) => out.to_string(),
+            (None, Some(out)) => in_dir(&self.root, out).display().to_string(),
+            (None, None) => "out".to_string(),
+        }
+    }
+}
+
+/// The nearest directory from `dir` up with a z.toml, from the current directory where it's
+/// under it, or else `dir`
+fn project_root(dir: &std::path::Path) -> std::path::PathBuf {
+    let Some(root) = settings::find_root(dir) else {
+        return dir.to_path_buf();
+    };
+    let cwd = std::env::current_dir().unwrap_or_default();
+    match root.strip_prefix(&cwd) {
+        Ok(relative) if relative.as_os_str().is_empty() => std::path::PathBuf::from("."),
+        Ok(relative) => relative.to_path_buf(),
+        Err(_) => root,
+    }
+}
+
+/// `path` in `dir`, written without a leading `./`
+fn in_dir(dir: &std::path::Path, path: &std::path::Path) -> std::path::PathBuf {
+    if dir == std::path::Path::new(".") { path.to_path_buf() } else { dir.join(path) }
+}
+
+fn parent_dir(path: &std::path::Path) -> &std::path::Path {
+    path.parent().filter(|dir| !dir.as_os_str().is_empty()).unwrap_or(std::path::Path::new("."))
+}
+
+/// `options` with what the project the source in `src_dir` is in gives: its z.toml, VERSION
+/// and registry and template overrides
+fn project_options(options: CompileOptions, src_dir: &std::path::Path) -> Result<CompileOptions, String> {
+    let options = options.in_project(&project_root(src_dir))?;
+    Ok(CompileOptions { source_dir: Some(src_dir.to_path_buf()), ..options })
+}
+
+/// Compile `source`, or else the project the current directory is in: one program, or
+/// several when it's a directory or a glob, or the project lists its members
+fn handle_build(source: Option<&str>, flags: BuildFlags) {
+    set_message_format(flags.compile.message_format);
+    frame::set_color(flags.compile.color);
+    let archive = flags.archive.as_deref().map(|path| ArchiveFormat::of_file(path).unwrap_or_else(|| {
+        eprintln!("❌ {}", tr!("archive-format-unknown", path = path.display()));
+        std::process::exit(EXIT_USAGE);
+    }));
+    let options = CompileOptions { dry_run: flags.dry_run, archive, in_memory: flags.emit.is_some(), ..compile_options(&flags.compile) };
+    let outputs = Outputs { report: flags.report.as_deref(), archive: flags.archive.as_deref() };
+    let ui = flags.ui || flags.interactive;
+    let unsupported = ui || flags.check_determinism || flags.emit.is_some();
+    if flags.emit.is_some() {
+        let _ = EMIT_STDOUT.set(true);
+    }
+    // The dashboard and the determinism check read the program from its file again
+    if source == Some("-") && (ui || flags.check_determinism) {
+        eprintln!("❌ {}", tr!("stdin-unsupported"));
+        std::process::exit(EXIT_USAGE);
+    }
+    // A directory or a glob names several programs, which compile together
+    if let Some(source) = source.filter(|source| source.contains(['*', '?']) || std::path::Path::new(source).is_dir()) {
+        return handle_members(source, flags.compile.out.as_deref().unwrap_or("out"), outputs, unsupported, &options);
+    }
+    let project = Located::find(source, std::path::Path::new("."), flags.compile.profile.as_deref()).unwrap_or_else(|e| {
+        eprintln!("❌ {}", e);
+        std::process::exit(EXIT_USAGE);
+    });
+    let options = project.settings.apply(options);
+    // So does a project that lists its members instead of having an entry of its own
+    if source.is_none() && project.has_members && project.settings.entry.is_none() && !project.entry.exists() {
+        let root = project.root.display().to_string();
+        return handle_members(&root, flags.compile.out.as_deref().unwrap_or("out"), outputs, unsupported, &options);
+    }
+    let out = project.out_dir(flags.compile.out.as_deref());
+    let result = handle_compilation(&project.entry.display().to_string(), &out, outputs, ui, flags.interactive, flags.check_determinism, &options);
+    if result.is_some_and(|result| result.has_errors()) {
+        std::process::exit(EXIT_ERRORS);
+    }
+}
+
+/// The options of a compile with `flags`, exiting with status 2 on a cache that can't be used
+fn compile_options(flags: &CompileFlags) -> CompileOptions {
+    let remote_cache = flags.remote_cache.as_deref()
+        .map(|location| RemoteCache::new(location, flags.remote_cache_mode))
+        .transpose()
+        .unwrap_or_else(|e| {
+            eprintln!("❌ {}", e);
+            std::process::exit(EXIT_USAGE);
+        });
+    let user_cache = if flags.no_user_cache { Ok(None) } else { UserCache::from_env() }.unwrap_or_else(|e| {
+        eprintln!("❌ {}", e);
+        std::process::exit(EXIT_USAGE);
+    });
+    CompileOptions {
+        style: CodeStyle {
+            indent: flags.indent,
+            quotes: flags.quotes,
+            semicolons: flags.semicolons,
+            trailing_commas: flags.trailing_commas,
+        },
+        dev_env: flags.dev_env.clone(),
+        remote_cache,
+        user_cache,
+        source_dir: None,
+        pipeline: Pipeline::standard(),
+        observers: Observers::default(),
+        version: None,
+        limits: flags.limits.limits(),
+        dependencies: DependencyOverrides::default(),
+        force: flags.force,
+        overwrite: flags.overwrite,
+        strict: flags.strict,
+        deny_warnings: flags.deny_warnings,
+        progress: None,
+        dry_run: false,
+        check_only: false,
+        targets: flags.only.clone(),
+        excluded: flags.exclude.clone(),
+        environment: flags.env.clone(),
+        environments: Environments::default(),
+        plugins: Plugins::default(),
+        registry: RegistryLayers::default(),
+        templates: Templates::default(),
+        target_options: TargetOptions::default(),
+        hooks: Hooks::default(),
+        run_hooks: !flags.no_hooks,
+        layout: Layout::default(),
+        archive: None,
+        in_memory: false,
+    }
+}
+
+/// Check `source` and its targets without generating them, printing only the diagnostics
+fn handle_check(args: CheckArgs) {
+    set_message_format(args.message_format);
+    frame::set_color(args.color);
+    let fail = |message: String| -> ! {
+        eprintln!("❌ {}", message);
+        std::process::exit(EXIT_ERRORS);
+    };
+
+    let project = Located::find(args.source.as_deref(), std::path::Path::new("."), args.profile.as_deref()).unwrap_or_else(|e| {
+        eprintln!("❌ {}", e);
+        std::process::exit(EXIT_USAGE);
+    });
+    let src_path = project.entry.as_path();
+    let options = CompileOptions {
+        limits: args.limits.limits(),
+        strict: args.strict,
+        deny_warnings: args.deny_warnings,
+        environment: args.env,
+        ..CompileOptions::default()
+    };
+    let options = project_options(project.settings.apply(options), parent_dir(src_path)).unwrap_or_else(|e| fail(e));
+    let result = z_compiler_core::check_project(src_path, &options);
+    print_diagnostics(&result.diagnostics, src_path);
+    let errors = result.diagnostics.iter().filter(|diagnostic| diagnostic.is_error()).count();
+    let warnings = result.diagnostics.len() - errors;
+    if errors > 0 {
+        fail(tr!("check-failed", source = src_path.display(), errors = errors, warnings = warnings));
+    }
+    say(format!("✅ {}", tr!("check-passed", source = src_path.display(), targets = result.targets.len(), warnings = warnings)));
+}
+
+/// Compile the program, then run the dev servers of the targets asked for: in the
+/// terminal for one, side by side with their output prefixed for several
+fn handle_dev(args: DevArgs) {
+    let fail = |message: String| -> ! {
+        eprintln!("❌ {}", message);
+        std::process::exit(EXIT_USAGE);
+    };
+
+    let flags = BuildFlags { dry_run: false, archive: None, check_determinism: false, ui: false, interactive: false, report: None, emit: None, compile: args.compile };
+    set_message_format(flags.compile.message_format);
+    frame::set_color(flags.compile.color);
+    let project = Located::find(args.source.as_deref(), std::path::Path::new("."), flags.compile.profile.as_deref()).unwrap_or_else(|e| fail(e));
+    let options = project.settings.apply(compile_options(&flags.compile));
+    let source = project.entry.display().to_string();
+    let out = project.out_dir(flags.compile.out.as_deref());
+    let outputs = Outputs { report: None, archive: None };
+    let Some(result) = handle_compilation(&source, &out, outputs, false, false, false, &options) else {
+        return;
+    };
+    let built: Vec<_> = result.artifacts.iter().filter(|artifact| artifact.status == Status::Success).collect();
+    let names = || built.iter().map(|artifact| format!("{}:{}", artifact.kind, artifact.name)).collect::<Vec<_>>().join(", ");
+    let targets: Vec<_> = match args.targets.as_slice() {
+        // Nothing to run because the program has errors
+        [] if built.is_empty() => {
+            eprintln!("❌ {}", tr!("dev-no-targets", source = source));
+            std::process::exit(EXIT_ERRORS);
+        }
+        [] => built.clone(),
+        wanted => wanted.iter()
+            .map(|wanted| *built.iter()
+                .find(|artifact| artifact.name == *wanted || format!("{}:{}", artifact.kind, artifact.name) == *wanted)
+                .unwrap_or_else(|| fail(tr!("dev-target-unknown", name = wanted, targets = names()))))
+            .collect(),
+    };
+    if !args.no_watch {
+        watch(project.entry.clone(), output_dir(&project.entry, &out), &result, options);
+    }
+    if let [target] = targets.as_slice() {
+        std::process::exit(run_project_command(&target.kind, &target.output_dir, &["dev".to_string()], &target.name));
+    }
+    let servers: Vec<dev::Server> = targets.iter()
+        .map(|target| dev::Server { kind: target.kind.clone(), name: target.name.clone(), project_dir: target.output_dir.clone() })
+        .collect();
+    if !dev::run(&servers) {
+        std::process::exit(EXIT_TOOL);
+    }
+}
+
+/// How the tests of a target went
+enum TestOutcome {
+    Passed(std::time::Duration),
+    /// With the runner's exit code, if it wasn't killed by a signal
+    Failed(Option<i32>),
+    /// The runner couldn't be started
+    NotRun,
+    /// The project has no tests to run
+    NoTests,
+    /// The target failed to compile
+    NotBuilt,
+}
+
+/// Compile the program, then run the tests of each target's project, printing a line per
+/// target at the end. Exits with status 1 if a target failed to compile or its tests
+/// failed, or else 3 if a test runner couldn't be started.
+fn handle_test(args: TestArgs) {
+    use z_compiler_core::testing;
+
+    set_message_format(args.compile.message_format);
+    frame::set_color(args.compile.color);
+    let fail = |message: String| -> ! {
+        eprintln!("❌ {}", message);
+        std::process::exit(EXIT_USAGE);
+    };
+
+    let project = Located::find(args.source.as_deref(), std::path::Path::new("."), args.compile.profile.as_deref()).unwrap_or_else(|e| fail(e));
+    let options = project.settings.apply(compile_options(&args.compile));
+    let source = project.entry.display().to_string();
+    let out = project.out_dir(args.compile.out.as_deref());
+    let outputs = Outputs { report: None, archive: None };
+    let Some(result) = handle_compilation(&source, &out, outputs, false, false, false, &options) else {
+        return;
+    };
+    let qualified = |artifact: &TargetReport| format!("{}:{}", artifact.kind, artifact.name);
+    let artifacts: Vec<&TargetReport> = match args.targets.as_slice() {
+        [] => result.artifacts.iter().collect(),
+        wanted => wanted.iter()
+            .map(|wanted| result.artifacts.iter()
+                .find(|artifact| artifact.name == *wanted || qualified(artifact) == *wanted)
+                .unwrap_or_else(|| {
+                    let names: Vec<String> = result.artifacts.iter().map(qualified).collect();
+                    fail(tr!("dev-target-unknown", name = wanted, targets = names.join(", ")))
+                }))
+            .collect(),
+    };
+    // Nothing to test because the program has errors
+    if artifacts.is_empty() {
+        eprintln!("❌ {}", tr!("dev-no-targets", source = source));
+        std::process::exit(EXIT_ERRORS);
+    }
+
+    let mut outcomes = Vec::new();
+    for artifact in artifacts {
+        let outcome = if artifact.status != Status::Success {
+            TestOutcome::NotBuilt
+        } else if let Some(command) = testing::test_command(&artifact.kind, &artifact.output_dir) {
+            status(format!("🧪 {}", tr!("running-command", command = command, name = artifact.name, kind = artifact.kind)));
+            let started = std::time::Instant::now();
+            match command.command().status() {
+                Ok(exit) if exit.success() => TestOutcome::Passed(started.elapsed()),
+                Ok(exit) => TestOutcome::Failed(exit.code()),
+                Err(e) => {
+                    eprintln!("❌ {}", tr!("command-exec-failed", tool = command.program, name = artifact.name, error = e));
+                    eprintln!("   {}", tr!("tool-missing-hint", tool = command.program));
+                    TestOutcome::NotRun
+                }
+            }
+        } else {
+            TestOutcome::NoTests
+        };
+        outcomes.push((qualified(artifact), outcome));
+    }
+
+    let width = outcomes.iter().map(|(name, _)| name.len()).max().unwrap_or(0);
+    status(String::new());
+    status(tr!("test-results"));
+    for (name, outcome) in &outcomes {
+        let (icon, line) = match outcome {
+            TestOutcome::Passed(duration) => ("✅", tr!("test-passed", duration = format!("{:.1}s", duration.as_secs_f64()))),
+            TestOutcome::Failed(Some(code)) => ("❌", tr!("test-failed", code = code)),
+            TestOutcome::Failed(None) => ("❌", tr!("test-killed")),
+            TestOutcome::NotRun => ("❌", tr!("test-not-run")),
+            TestOutcome::NoTests => ("⏭️ ", tr!("test-none")),
+            TestOutcome::NotBuilt => ("❌", tr!("test-not-built")),
+        };
+        status(format!("  {} {:width$}  {}", icon, name, line));
+    }
+    let count = |matches: fn(&TestOutcome) -> bool| outcomes.iter().filter(|(_, outcome)| matches(outcome)).count();
+    let passed = count(|outcome| matches!(outcome, TestOutcome::Passed(_)));
+    let failed = count(|outcome| matches!(outcome, TestOutcome::Failed(_) | TestOutcome::NotBuilt));
+    let not_run = count(|outcome| matches!(outcome, TestOutcome::NotRun));
+    let summary = tr!("test-summary", passed = passed, failed = failed + not_run, skipped = count(|outcome| matches!(outcome, TestOutcome::NoTests)));
+    if failed > 0 || not_run > 0 {
+        eprintln!("❌ {}", summary);
+        std::process::exit(if failed > 0 { EXIT_ERRORS } else { EXIT_TOOL });
+    }
+    status(format!("✅ {}", summary));
+}
+
+/// Format the .z files named, or check that they are
+fn handle_fmt(args: FmtArgs) {
+    use std::io::{Read, Write};
+    use z_compiler_core::formatter;
+
+    let fail = |message: String| -> ! {
+        eprintln!("❌ {}", message);
+        std::process::exit(EXIT_ERRORS);
+    };
+
+    let limits = Limits::default();
+    if args.paths.iter().any(|path| path.as_os_str() == "-") {
+        let mut source = String::new();
+        std::io::stdin().read_to_string(&mut source).unwrap_or_else(|e| fail(tr!("read-source-failed", path = "-", error = e)));
+        let formatted = formatter::format(&source, &limits).unwrap_or_else(|e| fail(tr!("fmt-failed", path = "-", error = e)));
+        if args.check {
+            print!("{}", formatter::changes(&source, &formatted, "-"));
+            if formatted != source {
+                std::process::exit(EXIT_ERRORS);
+            }
+        } else {
+            let _ = std::io::stdout().write_all(formatted.as_bytes());
+        }
+        return;
+    }
+
+    // A directory stands for the .z files under it, apart from generated projects
+    let files: Vec<std::path::PathBuf> = args.paths.iter()
+        .flat_map(|path| if path.is_dir() { z_compiler_core::members::glob(path, "**/*.z") } else { vec![path.clone()] })
+        .collect();
+    let (mut changed, mut failed) = (0, 0);
+    for file in &files {
+        let source = match std::fs::read_to_string(file) {
+            Ok(source) => source,
+            Err(e) => {
+                eprintln!("❌ {}", tr!("read-source-failed", path = file.display(), error = e));
+                failed += 1;
+                continue;
+            }
+        };
+        let formatted = match formatter::format(&source, &limits) {
+            Ok(formatted) => formatted,
+            Err(e) => {
+                eprintln!("❌ {}", tr!("fmt-failed", path = file.display(), error = e));
+                failed += 1;
+                continue;
+            }
+        };
+        if formatted == source {
+            continue;
+        }
+        changed += 1;
+        if args.check {
+            print!("{}", formatter::changes(&source, &formatted, &file.display().to_string()));
+        } else if let Err(e) = std::fs::write(file, &formatted) {
+            eprintln!("❌ {}", tr!("write-failed", path = file.display(), error = e));
+            failed += 1;
+        } else {
+            println!("✏️  {}", file.display());
+        }
+    }
+    if args.check {
+        println!("{}", tr!("fmt-checked", files = files.len(), unformatted = changed));
+    } else {
+        println!("{}", tr!("fmt-done", files = files.len(), changed = changed));
+    }
+    if failed > 0 || (args.check && changed > 0) {
+        std::process::exit(EXIT_ERRORS);
+    }
+}
+
+/// Add blocks to the program and write it back formatted
+fn handle_add(args: AddArgs) {
+    use z_compiler_core::codemod::{self, Addition};
+
+    let fail = |message: String| -> ! {
+        eprintln!("❌ {}", message);
+        std::process::exit(EXIT_ERRORS);
+    };
+
+    let project = Located::find(args.source.as_deref(), std::path::Path::new("."), None).unwrap_or_else(|e| fail(e));
+    let src_path = &project.entry;
+    let source = std::fs::read_to_string(src_path)
+        .unwrap_or_else(|e| fail(tr!("read-source-failed", path = src_path.display(), error = e)));
+    let options = project_options(CompileOptions::default(), parent_dir(src_path)).unwrap_or_else(|e| fail(e));
+    let addition = match args.what {
+        AddCommand::Target { kind, name } => Addition::Target { kind, name },
+        AddCommand::Route { path, to } => Addition::Route { path, target: to },
+        AddCommand::Component { name, to } => Addition::Component { name, target: to },
+        AddCommand::Section { name, to } => Addition::Section { name, target: to },
+    };
+    let added = codemod::add(&source, &addition, &options).unwrap_or_else(|e| fail(e));
+    if args.dry_run {
+        print!("{}", z_compiler_core::formatter::changes(&source, &added, &src_path.display().to_string()));
+        return;
+    }
+    std::fs::write(src_path, &added).unwrap_or_else(|e| fail(tr!("write-failed", path = src_path.display(), error = e)));
+    println!("✅ {}", tr!("add-done", path = src_path.display()));
+}
+
+/// Print the AST or the typed model of the program, or the parts the query selects
+fn handle_ast(args: AstArgs) {
+    use z_compiler_core::inspect::{self, Tree};
+
+    let fail = |message: String| -> ! {
+        eprintln!("❌ {}", message);
+        std::process::exit(EXIT_ERRORS);
+    };
+
+    let project = Located::find(args.source.as_deref(), std::path::Path::new("."), None).unwrap_or_else(|e| {
+        eprintln!("❌ {}", e);
+        std::process::exit(EXIT_USAGE);
+    });
+    let src_path = &project.entry;
+    let source = std::fs::read_to_string(src_path).unwrap_or_else(|e| {
+        eprintln!("❌ {}", tr!("read-source-failed", path = src_path.display(), error = e));
+        std::process::exit(EXIT_USAGE);
+    });
+    let options = CompileOptions { environment: args.env, ..CompileOptions::default() };
+    let options = project_options(project.settings.apply(options), parent_dir(src_path)).unwrap_or_else(|e| fail(e));
+    let tree = if args.semantic { Tree::Model } else { Tree::Ast };
+    let dumped = inspect::dump(&source, tree, args.format, args.query.as_deref(), &options).unwrap_or_else(|e| fail(e));
+    print!("{}", dumped);
+}
+
+/// Print the structure of the program as a graph
+fn handle_graph(args: GraphArgs) {
+    let fail = |message: String| -> ! {
+        eprintln!("❌ {}", message);
+        std::process::exit(EXIT_ERRORS);
+    };
+
+    let project = Located::find(args.source.as_deref(), std::path::Path::new("."), None).unwrap_or_else(|e| {
+        eprintln!("❌ {}", e);
+        std::process::exit(EXIT_USAGE);
+    });
+    let src_path = &project.entry;
+    let source = std::fs::read_to_string(src_path).unwrap_or_else(|e| {
+        eprintln!("❌ {}", tr!("read-source-failed", path = src_path.display(), error = e));
+        std::process::exit(EXIT_USAGE);
+    });
+    let options = CompileOptions { environment: args.env, ..CompileOptions::default() };
+    let options = project_options(project.settings.apply(options), parent_dir(src_path)).unwrap_or_else(|e| fail(e));
+    let graph = z_compiler_core::structure::render(&source, args.format, &options).unwrap_or_else(|e| fail(e));
+    print!("{}", graph);
+}
+
+/// List or show what the registry defines
+fn handle_registry(args: RegistryArgs) {
+    use z_compiler_core::registry::{self, Entry};
+
+    let fail = |message: String| -> ! {
+        eprintln!("❌ {}", message);
+        std::process::exit(EXIT_ERRORS);
+    };
+
+    let project = args.project.as_path();
+    let config = z_compiler_core::toml::load_config(project).unwrap_or_else(|e| fail(e));
+    let plugins = Plugins::from_config(&config, project).unwrap_or_else(|e| fail(e));
+    let layers = RegistryLayers::load(project).unwrap_or_else(|e| fail(e));
+    let options = CompileOptions { plugins, registry: layers, ..CompileOptions::default() };
+    let registry = z_compiler_core::load_registry(&options);
+
+    let text = |value: &serde_json::Value| value.as_str().unwrap_or_default().to_string();
+    let names = |value: &serde_json::Value| -> Vec<String> {
+        value.as_array().into_iter().flatten().filter_map(serde_json::Value::as_str).map(str::to_string).collect()
+    };
+    let table = |rows: Vec<(String, String)>| {
+        let width = rows.iter().map(|(name, _)| name.chars().count()).max().unwrap_or(0);
+        for (name, description) in rows {
+            println!("  {:width$}  {}", name, description);
+        }
+    };
+    // Other names of target type `kind`, the deprecated ones last
+    let aliases = |kind: &str| -> Vec<String> {
+        let mut aliases: Vec<(&String, bool)> = registry["targetAliases"].as_object().into_iter().flatten()
+            .filter(|(_, alias)| alias["target"] == kind)
+            .map(|(name, alias)| (name, alias["deprecated"] == true))
+            .collect();
+        aliases.sort_by_key(|(_, deprecated)| *deprecated);
+        aliases.into_iter()
+            .map(|(name, deprecated)| if deprecated { tr!("registry-deprecated", name = name) } else { name.clone() })
+            .collect()
+    };
+    let kinds: Vec<(&String, &serde_json::Value)> = registry["targets"].as_object().into_iter().flatten()
+        .filter(|(kind, _)| *kind != "workspace")
+        .collect();
+
+    let name = match args.command {
+        RegistryCommand::List if args.json => return println!("{}", serde_json::to_string_pretty(&registry).unwrap_or_default()),
+        RegistryCommand::List => {
+            println!("{}", tr!("registry-targets"));
+            table(kinds.iter().map(|(kind, target)| {
+                let mut description = text(&target["description"]);
+                let aliases = aliases(kind);
+                if !aliases.is_empty() {
+                    description.push_str(&format!("; {}", tr!("registry-also", names = aliases.join(", "))));
+                }
+                if !z_compiler_core::is_built_in(kind) && options.plugins.get(kind).is_none() {
+                    description.push_str(&format!(" [{}]", tr!("registry-not-generated")));
+                }
+                (kind.to_string(), description)
+            }).collect());
+            println!();
+            println!("{}", tr!("registry-sections"));
+            table(registry["namespaces"].as_object().into_iter().flatten()
+                .map(|(namespace, section)| (namespace.clone(), text(&section["description"])))
+                .collect());
+            return;
+        }
+        RegistryCommand::Show { name } => name,
+    };
+
+    let entry = registry::lookup(&registry, &name).unwrap_or_else(|e| {
+        eprintln!("❌ {}", e);
+        std::process::exit(EXIT_USAGE);
+    });
+    match entry {
+        Entry::Target(_, target) | Entry::Section(_, target) if args.json => {
+            println!("{}", serde_json::to_string_pretty(target).unwrap_or_default());
+        }
+        Entry::Target(kind, target) => {
+            println!("{} - {}", kind, text(&target["description"]));
+            // A workspace only groups targets
+            let generator = match options.plugins.get(kind) {
+                _ if kind == "workspace" => None,
+                Some(plugin) => Some(tr!("registry-plugin", command = plugin.command)),
+                None if z_compiler_core::is_built_in(kind) => Some(tr!("registry-built-in")),
+                None => Some(tr!("registry-not-generated")),
+            };
+            if let Some(generator) = generator {
+                println!("{}", tr!("registry-generator", generator = generator));
+            }
+            let aliases = aliases(kind);
+            if !aliases.is_empty() {
+                println!("{}", tr!("registry-aliases", names = aliases.join(", ")));
+            }
+            let sections = names(&target["allowedChildren"]);
+            if !sections.is_empty() {
+                println!();
+                println!("{}", tr!("registry-sections"));
+                table(sections.into_iter().map(|section| {
+                    let description = registry["namespaces"][&section]["description"].as_str()
+                        .or_else(|| registry["targets"][&section]["description"].as_str())
+                        .unwrap_or_default()
+                        .to_string();
+                    (section, description)
+                }).collect());
+            }
+            let options_of = |fields: &serde_json::Value| -> Vec<(String, String)> {
+                fields.as_object().into_iter().flatten().map(|(field, description)| (field.clone(), text(description))).collect()
+            };
+            let fields = options_of(&target["fields"]);
+            if !fields.is_empty() {
+                println!();
+                println!("{}", tr!("registry-options"));
+                table(fields);
+            }
+            if kind != "workspace" {
+                println!();
+                println!("{}", tr!("registry-common-options"));
+                table(options_of(&registry["targetFields"]));
+            }
+        }
+        Entry::Section(namespace, section) => {
+            println!("{} - {}", namespace, text(&section["description"]));
+            let children = names(&section["allowedChildren"]);
+            if !children.is_empty() {
+                println!("{}", tr!("registry-children", names = children.join(", ")));
+            }
+            let targets: Vec<&str> = kinds.iter()
+                .filter(|(_, target)| names(&target["allowedChildren"]).contains(&namespace.to_string()))
+                .map(|(kind, _)| kind.as_str())
+                .collect();
+            println!("{}", tr!("registry-in-targets", names = if targets.is_empty() { "-".to_string() } else { targets.join(", ") }));
+        }
+    }
+}
+
+/// Print what a diagnostic code means, or every code with the first sentence of its explanation
+fn handle_explain(args: ExplainArgs) {
+    use z_compiler_core::diagnostic::{self, EXPLANATIONS, WARNINGS};
+
+    let severity = |code: &str| if WARNINGS.contains(&code) { tr!("diagnostic-warning") } else { tr!("diagnostic-error") };
+    let Some(code) = args.code else {
+        let header = [tr!("explain-column-code"), tr!("explain-column-severity"), tr!("explain-column-summary")];
+        let rows: Vec<[String; 3]> = EXPLANATIONS.iter()
+            .map(|explanation| [explanation.code.to_string(), severity(explanation.code), explanation.summary.to_string()])
+            .collect();
+        let width = |column: usize| rows.iter().chain([&header]).map(|row| row[column].chars().count()).max().unwrap_or(0);
+        let (code_width, severity_width) = (width(0), width(1));
+        for [code, severity, summary] in [&header].into_iter().chain(&rows) {
+            println!("{:code_width$}  {:severity_width$}  {}", code, severity, summary);
+        }
+        return;
+    };
+    // Accept the code as printed, e.g. `error[schema]`
+    let code = code.rsplit('[').next().unwrap_or_default().trim_end_matches(']');
+    let Some(explanation) = diagnostic::explain(code) else {
+        eprintln!("❌ {}", tr!("explain-unknown", code = code));
+        std::process::exit(EXIT_USAGE);
+    };
+
+    println!("{}[{}]: {}\n", severity(explanation.code), explanation.code, explanation.summary);
+    println!("{}", explanation.description);
+    for (heading, program) in [(tr!("explain-wrong"), explanation.wrong), (tr!("explain-fixed"), explanation.fixed)] {
+        if let Some(program) = program {
+            println!("\n{}:\n", heading);
+            for line in program.lines() {
+                println!("{}", if line.is_empty() { String::new() } else { format!("    {}", line) });
+            }
+        }
+    }
+}
+
+/// Remove what compiling the program generated, as the manifest of each target lists it
+fn handle_clean(args: CleanArgs) {
+    use z_compiler_core::clean::{self, CleanOptions};
+
+    let fail = |message: String| -> ! {
+        eprintln!("❌ {}", message);
+        std::process::exit(EXIT_ERRORS);
+    };
+
+    let project = Located::find(args.source.as_deref(), std::path::Path::new("."), None).unwrap_or_else(|e| fail(e));
+    let source = std::fs::read_to_string(&project.entry)
+        .unwrap_or_else(|e| fail(tr!("read-source-failed", path = project.entry.display(), error = e)));
+    let program = z_compiler_core::load_program(&source, &CompileOptions::default()).unwrap_or_else(|e| fail(e));
+    let layout = z_compiler_core::toml::load_config(&project.root)
+        .and_then(|config| Layout::from_config(&config))
+        .unwrap_or_else(|e| fail(e));
+    let out = output_dir(&project.entry, &project.out_dir(args.out.as_deref()));
+    let options = CleanOptions { targets: args.targets, dependencies: args.deps, cache: args.cache, overwrite: args.overwrite };
+    let cleaned = clean::clean(&program, &out, &layout, &options).unwrap_or_else(|e| fail(e));
+
+    // Targets never generated, or cleaned already, have nothing to say
+    let touched = cleaned.targets.iter().filter(|target| !(target.removed.is_empty() && target.kept.is_empty() && target.dependencies.is_empty()));
+    for target in touched {
+        println!("🧹 {}", tr!("clean-target", target = target.target, files = target.removed.len(), path = target.output_dir.display()));
+        for dir in &target.dependencies {
+            println!("   🗑️  {}", dir.display());
+        }
+        for file in &target.kept {
+            println!("   ⚠️  {}", tr!("clean-kept", path = file.display()));
+        }
+    }
+    if cleaned.cache {
+        println!("🧹 {}", tr!("clean-cache", path = out.join(z_compiler_core::incremental::CACHE_DIR).display()));
+    }
+    println!("✅ {}", tr!("clean-done", count = cleaned.targets.len()));
+}
+
+/// Compile the program in `entry` again whenever one of its files changes, from another
+/// thread, for as long as the dev server runs. Targets whose inputs didn't change are left
+/// alone, so the server only sees the files that were regenerated, and the files are kept
+/// between compiles so each is only parsed again around what changed in it.
+fn watch(entry: std::path::PathBuf, out_dir: std::path::PathBuf, result: &CompileResult, options: CompileOptions) {
+    use z_compiler_core::modules::{Project, Sources};
+    use z_compiler_core::watch::{self, Watcher};
+
+    let src_dir = parent_dir(&entry).to_path_buf();
+    let mut watcher = Watcher::new([entry.clone(), project_root(&src_dir).join(z_compiler_core::toml::CONFIG_FILE)]);
+    watcher.watch(watch::inputs(&entry, result));
+    say(format!("👀 {}", tr!("watching", count = watcher.files().count(), source = entry.display())));
+    std::thread::spawn(move || {
+        let mut sources = Sources::new();
+        // Problems with the program were reported by the first compile
+        let _ = Project::load_with(&entry, &options.limits, &mut sources);
+        loop {
+            let changed = watcher.wait();
+            let names: Vec<String> = changed.iter().map(|file| file.display().to_string()).collect();
+            say(format!("\n🔄 {}", tr!("watch-changed", files = names.join(", "))));
+            // z.toml may be among them, so the project's settings are read again
+            let result = match project_options(options.clone(), &src_dir) {
+                Ok(options) => {
+                    sources.refresh(&options.limits);
+                    z_compiler_core::compile_project_with(&entry, &out_dir, &options, &mut sources)
+                }
+                Err(e) => {
+                    eprintln!("❌ {}", e);
+                    continue;
+                }
+            };
+            // Diagnostics in the format asked for, like the first compile's
+            print_result(&result, options.dry_run, &entry);
+            if let Err(e) = result.report().write(&out_dir.join(REPORT_FILE)) {
+                eprintln!("❌ {}", tr!("write-failed", path = out_dir.join(REPORT_FILE).display(), error = e));
+            }
+            watcher.watch(watch::inputs(&entry, &result));
+            say(tr!("compiled", source = entry.display(), out = out_dir.display()));
+        }
+    });
+}
+
+/// Write the main.z of an example to a new project directory
+fn handle_new(args: NewArgs) {
+    use z_compiler_core::gallery::{self, EXAMPLES};
+
+    let fail = |message: String| -> ! {
+        eprintln!("❌ {}", message);
+        std::process::exit(EXIT_ERRORS);
+    };
+
+    let example = gallery::find(&args.from).unwrap_or_else(|| {
+        let names: Vec<&str> = EXAMPLES.iter().map(|example| example.name).collect();
+        fail(tr!("example-unknown", name = args.from, examples = names.join(", ")))
+    });
+    let path = args.dir.join("main.z");
+    if path.exists() && !args.force {
+        fail(tr!("example-exists", path = path.display()));
+    }
+    std::fs::create_dir_all(&args.dir)
+        .unwrap_or_else(|e| fail(tr!("create-dir-failed", path = args.dir.display(), error = e)));
+    std::fs::write(&path, example.source())
+        .unwrap_or_else(|e| fail(tr!("write-failed", path = path.display(), error = e)));
+    println!("✅ {}", tr!("new-created", name = example.name, dir = args.dir.display(), path = path.display()));
+}
+
+/// Run a package manager command in the generated project of each target
+fn handle_pm(args: PmArgs) {
+    let project = Located::find(args.source.as_deref(), std::path::Path::new("."), None).unwrap_or_else(|e| {
+        eprintln!("❌ {}", e);
+        std::process::exit(EXIT_USAGE);
+    });
+    run_in_projects(&project, args.out.as_deref(), &args.targets, &args.command);
+}
+
+/// Run `command` in the generated project of each of `targets` of `project`, or of every
+/// target, then exit with the status of the last that failed
+fn run_in_projects(project: &Located, out: Option<&str>, targets: &[String], command: &[String]) -> ! {
+    let fail = |message: String| -> ! {
+        eprintln!("❌ {}", message);
+        std::process::exit(EXIT_ERRORS);
+    };
+
+    let source = std::fs::read_to_string(&project.entry)
+        .unwrap_or_else(|e| fail(tr!("read-source-failed", path = project.entry.display(), error = e)));
+    // The project's plugins and registry files may declare target types of its own
+    let options = project_options(CompileOptions::default(), parent_dir(&project.entry)).unwrap_or_else(|e| fail(e));
+    let program = z_compiler_core::load_program(&source, &options).unwrap_or_else(|e| fail(e));
+    for name in targets {
+        if !program.targets.iter().any(|target| target.name == *name) {
+            let names: Vec<&str> = program.targets.iter().map(|target| target.name.as_str()).collect();
+            eprintln!("❌ {}", tr!("pm-unknown-target", name = name, path = project.entry.display(), targets = names.join(", ")));
+            std::process::exit(EXIT_USAGE);
+        }
+    }
+
+    let out = output_dir(&project.entry, &project.out_dir(out));
+    let layout = z_compiler_core::toml::load_config(&project.root)
+        .and_then(|config| Layout::from_config(&config))
+        .unwrap_or_else(|e| fail(e));
+    let mut exit_status = EXIT_OK;
+    for target in program.targets.iter().filter(|target| targets.is_empty() || targets.contains(&target.name)) {
+        let project_dir = out.join(layout.dir(target).unwrap_or_else(|e| fail(e)));
+        if !project_dir.exists() {
+            println!("⚠️  {}", tr!("project-missing", name = target.name));
+            continue;
+        }
+        match run_project_command(&target.kind, &project_dir, command, &target.name) {
+            EXIT_OK => {}
+            code => exit_status = code,
+        }
+    }
+    std::process::exit(exit_status);
+}
+
+fn handle_publish(args: PublishArgs) {
+
+    let options = PublishOptions {
+        branch: args.branch,
+        repository: args.repo,
+        push: args.push,
+        targets: args.targets,
+        split: args.split,
+        message: args.message,
+    };
+    match z_compiler_core::publish_output(&args.out, &options) {
+        Ok(published) => {
+            for branch in published {
+                match &branch.commit {
+                    Some(commit) => println!("✅ {}", tr!("publish-committed", branch = branch.branch, files = branch.files, commit = &commit[..commit.len().min(12)])),
+                    None => println!("ℹ️  {}", tr!("publish-up-to-date", branch = branch.branch)),
+                }
+                if let Some(remote) = &branch.remote {
+                    println!("🚀 {}", tr!("publish-pushed", branch = branch.branch, remote = remote));
+                }
+            }
+        }
+        Err(e) => {
+            eprintln!("❌ {}", e);
+            std::process::exit(EXIT_ERRORS);
+        }
+    }
+}
+
+fn handle_deploy(args: DeployArgs) {
+    let fail = |message: String| -> ! {
+        eprintln!("❌ {}", message);
+        std::process::exit(EXIT_ERRORS);
+    };
+
+    let source = std::fs::read_to_string(&args.source)
+        .unwrap_or_else(|e| fail(tr!("read-source-failed", path = args.source.display(), error = e)));
+    let program = z_compiler_core::load_program(&source, &CompileOptions::default()).unwrap_or_else(|e| fail(e));
+    let target = program.targets.iter()
+        .find(|target| target.name == args.target || target.qualified_name() == args.target)
+        .unwrap_or_else(|| fail(tr!("deploy-unknown-target", name = args.target, path = args.source.display())));
+
+    let source_dir = args.source.parent().unwrap_or(std::path::Path::new("."));
+    let out = args.out.unwrap_or_else(|| source_dir.join("out"));
+    let layout = z_compiler_core::toml::load_config(source_dir)
+        .and_then(|config| Layout::from_config(&config))
+        .unwrap_or_else(|e| fail(e));
+    let project_dir = out.join(layout.dir(target).unwrap_or_else(|e| fail(e)));
+    if !project_dir.exists() {
+        fail(tr!("project-missing", name = target.name));
+    }
 
-    /// Output directory (defaults to ./out) - only used for compilation
-    #[arg(short, long, default_value = "out")]
-    out: String,
+    let request = DeployRequest { environment: args.environment, promote: args.promote };
+    let steps = z_compiler_core::deploy_steps(target, &project_dir, &request).unwrap_or_else(|e| fail(e));
+    let provider = target.deployment().map_or("", |deployment| deployment.provider.as_str());
+    for step in steps {
+        println!("🚀 {}", tr!("running-command", command = step, name = target.name, kind = provider));
+        match step.command().status() {
+            Ok(status) if status.success() => {}
+            Ok(status) => {
+                eprintln!("❌ {}", tr!("command-failed", name = target.name, code = format!("{:?}", status.code())));
+                std::process::exit(status.code().unwrap_or(EXIT_TOOL));
+            }
+            Err(e) => {
+                eprintln!("   {}", tr!("tool-missing-hint", tool = step.program));
+                eprintln!("❌ {}", tr!("command-exec-failed", tool = step.program, name = target.name, error = e));
+                std::process::exit(EXIT_TOOL);
+            }
+        }
+    }
+    println!("✅ {}", tr!("command-succeeded", name = target.name));
 }
 
-fn main() {
-    let args = Args::parse();
+fn handle_version(args: VersionArgs) {
+    let fail = |message: String| -> ! {
+        eprintln!("❌ {}", message);
+        std::process::exit(EXIT_ERRORS);
+    };
+    let source_dir = args.source.parent().unwrap_or(std::path::Path::new("."));
+
+    let Some(VersionCommand::Bump { level, out, no_tag }) = args.command else {
+        let version = z_compiler_core::version::read_version(source_dir).unwrap_or_else(|e| fail(e));
+        println!("{}", version.unwrap_or(Version::INITIAL));
+        return;
+    };
 
-    // Check if the first argument is a project directory (for package manager commands)
-    let examples_project_path = std::path::Path::new("../examples").join(&args.first_arg);
-    let current_project_path = std::path::Path::new("examples").join(&args.first_arg);
+    let source = std::fs::read_to_string(&args.source)
+        .unwrap_or_else(|e| fail(tr!("read-source-failed", path = args.source.display(), error = e)));
+    let program = z_compiler_core::load_program(&source, &CompileOptions::default()).unwrap_or_else(|e| fail(e));
+    let layout = z_compiler_core::toml::load_config(source_dir)
+        .and_then(|config| Layout::from_config(&config))
+        .unwrap_or_else(|e| fail(e));
+    let options = ReleaseOptions {
+        bump: level,
+        output_dir: out.unwrap_or_else(|| source_dir.join("out")),
+        layout,
+        tag: !no_tag,
+    };
+    let release = z_compiler_core::version::release(&program, source_dir, &options).unwrap_or_else(|e| fail(e));
 
-    // Try both relative paths (from compiler/ and from root)
-    let project_path = if examples_project_path.exists() {
-        Some(examples_project_path)
-    } else if current_project_path.exists() {
-        Some(current_project_path)
-    } else {
-        None
+    println!("✅ {}", tr!("version-bumped", from = release.previous, to = release.version));
+    for path in &release.updated {
+        println!("   {}", tr!("version-updated", path = path.display()));
+    }
+    println!("📝 {}", tr!("version-changelog", count = release.changes, path = release.changelog.display()));
+    if let Some(tag) = &release.tag {
+        println!("🏷️  {}", tr!("version-tagged", tag = tag));
+    }
+}
+
+fn handle_trust(args: TrustArgs) {
+    use std::io::{BufRead, IsTerminal, Write};
+
+    let fail = |message: String| -> ! {
+        eprintln!("❌ {}", message);
+        std::process::exit(EXIT_ERRORS);
     };
+    let project_dir = args.source.parent().filter(|dir| !dir.as_os_str().is_empty()).unwrap_or(std::path::Path::new("."));
+    let config = z_compiler_core::toml::load_config(project_dir).unwrap_or_else(|e| fail(e));
+    let policy = SandboxPolicy::from_config(&config, project_dir).unwrap_or_else(|e| fail(e));
+
+    let grants = policy.grants();
+    if grants.is_empty() {
+        println!("{}", tr!("trust-nothing"));
+        return;
+    }
+    if sandbox::is_trusted(project_dir, &policy) {
+        println!("{}", tr!("trust-already"));
+        return;
+    }
+    println!("{}", tr!("trust-grants", path = project_dir.display()));
+    for grant in &grants {
+        println!("   • {}", grant);
+    }
 
-    if let Some(project_dir) = project_path {
-        if !args.additional_args.is_empty() {
-            // This is a project management command
-            handle_project_command(&project_dir, &args.additional_args);
+    if !args.yes {
+        if !std::io::stdin().is_terminal() {
+            fail(tr!("trust-needs-yes"));
+        }
+        print!("{} ", tr!("trust-prompt"));
+        let _ = std::io::stdout().flush();
+        let mut answer = String::new();
+        let _ = std::io::stdin().lock().read_line(&mut answer);
+        if !matches!(answer.trim().to_ascii_lowercase().as_str(), "y" | "yes" | "s" | "si" | "sí") {
             return;
         }
     }
+    sandbox::trust(project_dir, &policy).unwrap_or_else(|e| fail(e));
+    println!("✅ {}", tr!("trust-recorded"));
+}
+
+fn handle_preview(args: PreviewArgs) {
+    use std::io::Read;
+
+    let fail = |message: String| -> ! {
+        eprintln!("❌ {}", message);
+        std::process::exit(EXIT_ERRORS);
+    };
+
+    let source = if args.stdin {
+        let mut source = String::new();
+        std::io::stdin().read_to_string(&mut source)
+            .unwrap_or_else(|e| fail(tr!("read-source-failed", path = "stdin", error = e)));
+        source
+    } else {
+        std::fs::read_to_string(&args.source)
+            .unwrap_or_else(|e| fail(tr!("read-source-failed", path = args.source.display(), error = e)))
+    };
+    let source_dir = args.source.parent().filter(|dir| !dir.as_os_str().is_empty()).unwrap_or(std::path::Path::new("."));
+    let config = z_compiler_core::toml::load_config(source_dir).unwrap_or_else(|e| fail(e));
+    let plugins = Plugins::from_config(&config, source_dir).unwrap_or_else(|e| fail(e));
+    let target_options = TargetOptions::from_config(&config)
+        .unwrap_or_else(|e| fail(format!("{}: {}", source_dir.join(z_compiler_core::toml::CONFIG_FILE).display(), e)));
+    let registry = RegistryLayers::load(source_dir).unwrap_or_else(|e| fail(e));
+    let templates = Templates::load(source_dir).unwrap_or_else(|e| fail(e));
+    let options = CompileOptions {
+        source_dir: Some(source_dir.to_path_buf()),
+        plugins,
+        registry,
+        templates,
+        target_options,
+        ..CompileOptions::default()
+    };
 
-    // This is a compilation command
-    handle_compilation(&args.first_arg, &args.out);
+    let json = if args.list {
+        let targets = z_compiler_core::preview::list_targets(&source, &options).unwrap_or_else(|e| fail(e));
+        serde_json::to_string(&targets)
+    } else {
+        let offset = offset_of(&source, args.line, args.column);
+        let preview = z_compiler_core::preview::generate_preview(&source, offset, args.target.as_deref(), &options)
+            .unwrap_or_else(|e| fail(e));
+        serde_json::to_string(&preview)
+    };
+    println!("{}", json.expect("preview is serializable"));
 }
 
-fn handle_project_command(project_dir: &std::path::Path, command_args: &[String]) {
-    println!("🔧 Running command in project: {}", project_dir.display());
+fn handle_examples(args: ExamplesArgs) {
+    use z_compiler_core::gallery::{self, EXAMPLES};
 
-    // Detect project types and their corresponding package managers
-    let project_types = detect_project_types(project_dir);
+    let fail = |message: String| -> ! {
+        eprintln!("❌ {}", message);
+        std::process::exit(EXIT_ERRORS);
+    };
+    let find = |name: &str| gallery::find(name).unwrap_or_else(|| {
+        let names: Vec<&str> = EXAMPLES.iter().map(|example| example.name).collect();
+        fail(tr!("example-unknown", name = name, examples = names.join(", ")))
+    });
 
-    if project_types.is_empty() {
-        eprintln!("❌ No recognized project types found in {}", project_dir.display());
-        return;
+    match args.command {
+        ExamplesCommand::List => {
+            let width = EXAMPLES.iter().map(|example| example.name.len()).max().unwrap_or(0);
+            for example in EXAMPLES {
+                println!("{:width$}  {}", example.name, example.description(), width = width);
+            }
+        }
+        ExamplesCommand::Copy { name, dir, force } => {
+            let example = find(&name);
+            let dir = dir.unwrap_or_else(|| std::path::PathBuf::from(example.name));
+            let path = dir.join("main.z");
+            if path.exists() && !force {
+                fail(tr!("example-exists", path = path.display()));
+            }
+            std::fs::create_dir_all(&dir)
+                .unwrap_or_else(|e| fail(tr!("create-dir-failed", path = dir.display(), error = e)));
+            std::fs::write(&path, example.source())
+                .unwrap_or_else(|e| fail(tr!("write-failed", path = path.display(), error = e)));
+            println!("✅ {}", tr!("example-copied", name = example.name, path = path.display()));
+        }
+        ExamplesCommand::Run { name, out } => {
+            let example = find(&name);
+            println!("📄 {}.z", example.name);
+            for (number, line) in example.source().lines().enumerate() {
+                println!("{:4} │ {}", number + 1, line);
+            }
+            println!();
+
+            let scratch = std::env::temp_dir().join(format!("z-example-{}", std::process::id()));
+            let out_dir = out.clone().unwrap_or_else(|| scratch.join(example.name));
+            std::fs::create_dir_all(&out_dir)
+                .unwrap_or_else(|e| fail(tr!("create-dir-failed", path = out_dir.display(), error = e)));
+            let result = z_compiler_core::compile(example.source(), &out_dir, &CompileOptions::default());
+            print_result(&result, false, std::path::Path::new(example.name));
+            for artifact in result.artifacts.iter().filter(|artifact| artifact.status == Status::Success) {
+                println!("\n📦 {}:{} ({})", artifact.kind, artifact.name, tr!("example-files", count = artifact.outputs.len()));
+                for file in &artifact.outputs {
+                    println!("     {}  {}", file.path.display(), file.bytes);
+                }
+            }
+            if out.is_none() {
+                let _ = std::fs::remove_dir_all(&scratch);
+            }
+            if result.has_errors() {
+                std::process::exit(EXIT_ERRORS);
+            }
+        }
+        ExamplesCommand::Check => {
+            let scratch = std::env::temp_dir().join(format!("z-examples-{}", std::process::id()));
+            let mut failed = 0;
+            for example in EXAMPLES {
+                let out_dir = scratch.join(example.name);
+                let result = std::fs::create_dir_all(&out_dir)
+                    .map(|_| z_compiler_core::compile(example.source(), &out_dir, &CompileOptions::default()));
+                match result {
+                    Ok(result) if !result.has_errors() => println!("✅ {}", example.name),
+                    Ok(result) => {
+                        failed += 1;
+                        println!("❌ {}", example.name);
+                        for diagnostic in result.diagnostics.iter().filter(|diagnostic| diagnostic.is_error()) {
+                            print_diagnostic(diagnostic, std::path::Path::new(example.name));
+                        }
+                    }
+                    Err(e) => {
+                        failed += 1;
+                        println!("❌ {}", example.name);
+                        eprintln!("  ❌ {}", tr!("create-dir-failed", path = out_dir.display(), error = e));
+                    }
+                }
+            }
+            let _ = std::fs::remove_dir_all(&scratch);
+            println!("{}", tr!("examples-checked", passed = EXAMPLES.len() - failed, total = EXAMPLES.len()));
+            if failed > 0 {
+                std::process::exit(EXIT_ERRORS);
+            }
+        }
     }
+}
 
-    // For each project type, run the appropriate package manager command
-    for (project_name, project_type) in project_types {
-        let project_path = project_dir.join("out").join(&project_name);
+fn handle_telemetry(args: TelemetryArgs) {
+    use z_compiler_core::telemetry::{self, Settings};
 
-        if !project_path.exists() {
-            println!("⚠️  Project {} does not exist yet. Run compilation first.", project_name);
-            continue;
+    let save = |settings: Settings| settings.save().unwrap_or_else(|e| {
+        eprintln!("❌ {}", e);
+        std::process::exit(EXIT_ERRORS);
+    });
+
+    match args.command {
+        TelemetryCommand::On { endpoint } => {
+            let endpoint = endpoint.or(Settings::load().endpoint);
+            save(Settings { enabled: true, endpoint });
+            println!("✅ {}", tr!("telemetry-on"));
+            println!("   {}", tr!("telemetry-collects"));
+        }
+        TelemetryCommand::Off => {
+            save(Settings { enabled: false, ..Settings::load() });
+            println!("✅ {}", tr!("telemetry-off"));
+        }
+        TelemetryCommand::Show => {
+            let settings = Settings::load();
+            match (settings.enabled, telemetry::disabled_by_environment()) {
+                (false, _) => println!("{}", tr!("telemetry-status-off")),
+                (true, Some(variable)) => println!("{}", tr!("telemetry-status-overridden", variable = variable)),
+                (true, None) => println!("{}", tr!("telemetry-status-on")),
+            }
+            match settings.endpoint() {
+                Some(endpoint) => println!("{}", tr!("telemetry-endpoint", endpoint = endpoint, batch = telemetry::BATCH_SIZE)),
+                None => println!("{}", tr!("telemetry-no-endpoint")),
+            }
+            let events = telemetry::queued();
+            if events.is_empty() {
+                println!("{}", tr!("telemetry-queue-empty"));
+                return;
+            }
+            let queue = telemetry::queue_file().unwrap_or_default();
+            println!("{}", tr!("telemetry-queue", count = events.len(), path = queue.display()));
+            for event in events {
+                println!("{}", serde_json::to_string_pretty(&event).expect("events are JSON"));
+            }
         }
+    }
+}
+
+fn handle_cache(args: CacheArgs) {
+    let cache = UserCache::from_env().unwrap_or_else(|e| {
+        eprintln!("❌ {}", e);
+        std::process::exit(EXIT_ERRORS);
+    }).unwrap_or_else(|| {
+        eprintln!("❌ {}", tr!("user-cache-none", variable = user_cache::DIR_ENV));
+        std::process::exit(EXIT_ERRORS);
+    });
 
-        match project_type.as_str() {
-            "next" => run_pnpm_command(&project_path, command_args, &project_name),
-            "tauri" => run_tauri_command(&project_path, command_args, &project_name),
-            "rust" => run_cargo_command(&project_path, command_args, &project_name),
-            _ => println!("ℹ️  No package manager configured for {} ({})", project_name, project_type),
+    match args.command {
+        CacheCommand::Show => {
+            let usage = cache.usage();
+            println!("{}", tr!("user-cache-summary", entries = usage.entries, size = format_size(usage.bytes), max = format_size(cache.max_bytes), path = cache.dir().display()));
         }
+        CacheCommand::Clean { max_size } => {
+            let deleted = cache.collect_garbage(max_size.unwrap_or(0)).unwrap_or_else(|e| {
+                eprintln!("❌ {}", e);
+                std::process::exit(EXIT_ERRORS);
+            });
+            println!("✅ {}", tr!("user-cache-cleaned", entries = deleted.entries, size = format_size(deleted.bytes), path = cache.dir().display()));
+        }
+    }
+}
+
+/// `bytes` in the largest unit it makes at least one of, e.g. `1.5 GiB`
+fn format_size(bytes: u64) -> String {
+    let units = ["B", "KiB", "MiB", "GiB", "TiB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < units.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} {}", bytes, units[0])
+    } else {
+        format!("{:.1} {}", size, units[unit])
     }
 }
 
-fn detect_project_types(project_dir: &std::path::Path) -> Vec<(String, String)> {
-    let mut project_types = Vec::new();
+fn handle_internals(args: InternalsArgs) {
+    use z_compiler_core::assets;
 
-    // Read the main.z file to detect project types
-    let main_z_path = project_dir.join("main.z");
-    if let Ok(content) = std::fs::read_to_string(&main_z_path) {
-        let mut brace_depth = 0;
+    match args.command {
+        InternalsCommand::DumpAssets { dir } => {
+            let count = assets::dump(&dir).unwrap_or_else(|e| {
+                eprintln!("❌ {}", e);
+                std::process::exit(EXIT_ERRORS);
+            });
+            println!("✅ {}", tr!("assets-dumped", count = count, path = dir.display(), variable = assets::ASSETS_ENV));
+        }
+        #[cfg(feature = "conformance")]
+        InternalsCommand::Conformance { kind, require, project } => handle_conformance(&kind, require, &project),
+    }
+}
 
-        for line in content.lines() {
-            let trimmed = line.trim();
+#[cfg(feature = "conformance")]
+fn handle_conformance(kind: &str, require: Vec<std::path::PathBuf>, project: &std::path::Path) {
+    use z_compiler_testkit::{Conformance, Verdict};
 
-            // Check for top-level project declarations BEFORE updating brace count
-            if brace_depth == 0 {
-                if let Some(caps) = Regex::new(r"^([a-z]+)\s+([A-Za-z0-9_]+)\s*\{")
-                    .unwrap()
-                    .captures(trimmed)
-                {
-                    let target_type = caps[1].to_string();
-                    let app_name = caps[2].to_string();
-                    project_types.push((app_name, target_type));
-                }
-            }
+    let fail = |message: String| -> ! {
+        eprintln!("❌ {}", message);
+        std::process::exit(EXIT_ERRORS);
+    };
+    let config = z_compiler_core::toml::load_config(project).unwrap_or_else(|e| fail(e));
+    let plugins = Plugins::from_config(&config, project).unwrap_or_else(|e| fail(e));
+    let target_options = TargetOptions::from_config(&config)
+        .unwrap_or_else(|e| fail(format!("{}: {}", project.join(z_compiler_core::toml::CONFIG_FILE).display(), e)));
+    let registry = RegistryLayers::load(project).unwrap_or_else(|e| fail(e));
+    let templates = Templates::load(project).unwrap_or_else(|e| fail(e));
+    let options = CompileOptions {
+        source_dir: Some(project.to_path_buf()),
+        plugins,
+        registry,
+        templates,
+        target_options,
+        ..CompileOptions::default()
+    };
 
-            // Count braces to track nesting depth
-            for char in trimmed.chars() {
-                match char {
-                    '{' => brace_depth += 1,
-                    '}' => brace_depth -= 1,
-                    _ => {}
+    let conformance = require.into_iter().fold(Conformance::new(kind, &options), Conformance::require);
+    let (mut passed, mut skipped, mut failed) = (0, 0, 0);
+    for outcome in conformance.run() {
+        match outcome.verdict {
+            Verdict::Passed { files } => {
+                passed += 1;
+                println!("✅ {}", tr!("conformance-passed", fixture = outcome.fixture, count = files));
+            }
+            Verdict::Skipped => {
+                skipped += 1;
+                println!("⏭️  {}", tr!("conformance-skipped", fixture = outcome.fixture, kind = kind));
+            }
+            Verdict::Failed(failures) => {
+                failed += 1;
+                println!("❌ {}", tr!("conformance-failed", fixture = outcome.fixture));
+                for failure in failures {
+                    println!("   • {}", failure);
                 }
             }
         }
     }
+    println!("\n{}", tr!("conformance-summary", kind = kind, passed = passed, skipped = skipped, failed = failed));
+    if failed > 0 {
+        std::process::exit(EXIT_ERRORS);
+    }
+}
 
-    project_types
+/// Byte offset of a 1-based line and column (in characters) of `source`
+fn offset_of(source: &str, line: usize, column: usize) -> usize {
+    let start: usize = source.split_inclusive('\n').take(line.saturating_sub(1)).map(str::len).sum();
+    let rest = &source[start..];
+    start + rest.char_indices().nth(column.saturating_sub(1)).map_or(rest.len(), |(index, _)| index)
 }
 
-fn run_pnpm_command(project_path: &std::path::Path, args: &[String], project_name: &str) {
-    println!("📦 Running pnpm {} in {} (Next.js)", args.join(" "), project_name);
+/// Replace the bare panic message with an explanation and an offer to write a crash
+/// report bundle. Runs in the panic hook, before anything unwinds.
+/// Panics of a target's compiler are left alone: they fail the target, with a diagnostic.
+fn install_crash_reporter() {
+    std::panic::set_hook(Box::new(|info| {
+        if z_compiler_core::crash::is_isolated() {
+            return;
+        }
+        let message = z_compiler_core::crash::panic_message(info.payload());
+        eprintln!("\n💥 {}", tr!("crash-header"));
+        eprintln!("   {}", message);
 
-    let mut cmd = std::process::Command::new("pnpm");
-    cmd.current_dir(project_path);
-    cmd.args(args);
+        let command: Vec<String> = std::env::args().collect();
+        let mut inputs: Vec<std::path::PathBuf> = command.iter().skip(1)
+            .map(std::path::PathBuf::from)
+            .filter(|path| path.extension().is_some_and(|extension| extension == "z") && path.is_file())
+            .collect();
+        if inputs.is_empty() && std::path::Path::new("main.z").is_file() {
+            inputs.push("main.z".into());
+        }
+        let report = z_compiler_core::crash::CrashReport {
+            message,
+            location: info.location().map(|location| format!("{}:{}:{}", location.file(), location.line(), location.column())),
+            backtrace: std::backtrace::Backtrace::force_capture().to_string(),
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            command,
+            inputs,
+        };
 
-    match cmd.status() {
-        Ok(status) => {
-            if status.success() {
-                println!("✅ Command completed successfully for {}", project_name);
-            } else {
-                eprintln!("❌ Command failed for {} with exit code: {:?}", project_name, status.code());
-            }
+        let seconds = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).map_or(0, |elapsed| elapsed.as_secs());
+        let path = std::path::PathBuf::from(format!("z-crash-{}.zip", seconds));
+        if !wants_crash_report(&path) {
+            return;
+        }
+        match report.write_bundle(&path) {
+            Ok(()) => eprintln!("📦 {}", tr!("crash-written", path = path.display(), url = concat!(env!("CARGO_PKG_REPOSITORY"), "/issues"))),
+            Err(e) => eprintln!("❌ {}", e),
+        }
+    }));
+}
+
+/// `Z_CRASH_REPORT=1` writes the bundle and `Z_CRASH_REPORT=0` skips it; otherwise
+/// ask, if there's a terminal to ask on
+fn wants_crash_report(path: &std::path::Path) -> bool {
+    use std::io::{BufRead, IsTerminal, Write};
+
+    match std::env::var("Z_CRASH_REPORT").as_deref() {
+        Ok("1" | "true" | "yes") => return true,
+        Ok("0" | "false" | "no") => return false,
+        _ => {}
+    }
+    if !std::io::stdin().is_terminal() || !std::io::stderr().is_terminal() {
+        eprintln!("   {}", tr!("crash-hint"));
+        return false;
+    }
+    eprint!("   {} ", tr!("crash-prompt", path = path.display()));
+    let _ = std::io::stderr().flush();
+    let mut answer = String::new();
+    if std::io::stdin().lock().read_line(&mut answer).is_err() {
+        return false;
+    }
+    !matches!(answer.trim().to_ascii_lowercase().as_str(), "n" | "no")
+}
+
+/// Run a command in a generated project with the tools of its target type, returning the
+/// status to exit with: the command's own, or [`EXIT_TOOL`] if it couldn't be run
+fn run_project_command(kind: &str, project_path: &std::path::Path, args: &[String], project_name: &str) -> i32 {
+    match kind {
+        "next" => run_package_command(project_path, args, project_name),
+        "deno" => run_deno_command(project_path, args, project_name),
+        "tauri" => run_tauri_command(project_path, args, project_name),
+        "rust" => run_cargo_command(project_path, args, project_name),
+        _ => {
+            status(format!("ℹ️  {}", tr!("no-package-manager", name = project_name, kind = kind)));
+            EXIT_OK
+        }
+    }
+}
+
+/// Report how a command run in a generated project went, and the status to exit with
+fn command_status(result: std::io::Result<std::process::ExitStatus>, tool: &str, package_manager: &str, project_name: &str) -> i32 {
+    match result {
+        Ok(exit) if exit.success() => {
+            status(format!("✅ {}", tr!("command-succeeded", name = project_name)));
+            EXIT_OK
+        }
+        Ok(exit) => {
+            eprintln!("❌ {}", tr!("command-failed", name = project_name, code = format!("{:?}", exit.code())));
+            // Killed by a signal, it has no code of its own
+            exit.code().unwrap_or(EXIT_TOOL)
         }
         Err(e) => {
-            eprintln!("❌ Failed to execute pnpm command for {}: {}", project_name, e);
-            eprintln!("   Make sure pnpm is installed and available in your PATH");
+            eprintln!("❌ {}", tr!("command-exec-failed", tool = tool, name = project_name, error = e));
+            eprintln!("   {}", tr!("tool-missing-hint", tool = package_manager));
+            EXIT_TOOL
         }
     }
 }
 
-fn run_tauri_command(project_path: &std::path::Path, args: &[String], project_name: &str) {
+/// Run a command in a Next.js project through its runtime's package manager
+fn run_package_command(project_path: &std::path::Path, args: &[String], project_name: &str) -> i32 {
+    let runtime = Runtime::detect(project_path);
+    let (program, args) = runtime.command(project_path, args);
+    status(format!("📦 {}", tr!("running-command", command = format!("{} {}", program, args.join(" ")), name = project_name, kind = "Next.js")));
+
+    let mut cmd = std::process::Command::new(program);
+    cmd.current_dir(project_path);
+    cmd.args(&args);
+
+    command_status(cmd.status(), program, runtime.package_manager(), project_name)
+}
+
+fn run_deno_command(project_path: &std::path::Path, args: &[String], project_name: &str) -> i32 {
+    // Deno's own subcommands run as they are; anything else is a task from deno.json
+    let effective_args = match args.first().map(String::as_str) {
+        Some("add" | "install" | "remove" | "outdated" | "fmt" | "lint" | "check" | "test" | "task") => args.to_vec(),
+        _ => [&["task".to_string()], args].concat(),
+    };
+
+    status(format!("🦕 {}", tr!("running-command", command = format!("deno {}", effective_args.join(" ")), name = project_name, kind = "Deno")));
+
+    let mut cmd = std::process::Command::new("deno");
+    cmd.current_dir(project_path);
+    cmd.args(&effective_args);
+
+    command_status(cmd.status(), "deno", "deno", project_name)
+}
+
+fn run_tauri_command(project_path: &std::path::Path, args: &[String], project_name: &str) -> i32 {
     if args.is_empty() {
-        println!("📱 No command provided for Tauri project {}", project_name);
-        return;
+        status(format!("📱 {}", tr!("no-command", kind = "Tauri", name = project_name)));
+        return EXIT_OK;
     }
 
     let first_arg = &args[0];
@@ -163,28 +2271,16 @@ fn run_tauri_command(project_path: &std::path::Path, args: &[String], project_na
     let mut all_args = effective_args;
     all_args.extend(remaining_args.iter().map(|s| s.to_string()));
 
-    println!("📱 Running {} {} in {} (Tauri)", base_cmd, all_args.join(" "), project_name);
+    status(format!("📱 {}", tr!("running-command", command = format!("{} {}", base_cmd, all_args.join(" ")), name = project_name, kind = "Tauri")));
 
     let mut cmd = std::process::Command::new(base_cmd);
     cmd.current_dir(project_path);
     cmd.args(&all_args);
 
-    match cmd.status() {
-        Ok(status) => {
-            if status.success() {
-                println!("✅ Command completed successfully for {}", project_name);
-            } else {
-                eprintln!("❌ Command failed for {} with exit code: {:?}", project_name, status.code());
-            }
-        }
-        Err(e) => {
-            eprintln!("❌ Failed to execute {} command for {}: {}", base_cmd, project_name, e);
-            eprintln!("   Make sure {} is installed and available in your PATH", base_cmd);
-        }
-    }
+    command_status(cmd.status(), base_cmd, base_cmd, project_name)
 }
 
-fn run_cargo_command(project_path: &std::path::Path, args: &[String], project_name: &str) {
+fn run_cargo_command(project_path: &std::path::Path, args: &[String], project_name: &str) -> i32 {
     // Map common commands to appropriate cargo equivalents
     let effective_args = if args.is_empty() {
         vec!["build".to_string()]
@@ -204,52 +2300,477 @@ fn run_cargo_command(project_path: &std::path::Path, args: &[String], project_na
         mapped_args
     };
 
-    println!("🦀 Running cargo {} in {} (Rust)", effective_args.join(" "), project_name);
+    status(format!("🦀 {}", tr!("running-command", command = format!("cargo {}", effective_args.join(" ")), name = project_name, kind = "Rust")));
 
     let mut cmd = std::process::Command::new("cargo");
     cmd.current_dir(project_path);
     cmd.args(&effective_args);
 
-    match cmd.status() {
-        Ok(status) => {
-            if status.success() {
-                println!("✅ Command completed successfully for {}", project_name);
+    command_status(cmd.status(), "cargo", "cargo", project_name)
+}
+
+/// How diagnostics are printed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+enum MessageFormat {
+    /// With icons, on stderr, between the progress of the build on stdout
+    Human,
+    /// One JSON object per line on stdout, as in the build report but with `file` always
+    /// set, from the current directory; everything else goes to stderr
+    Json,
+}
+
+/// The format chosen with --message-format, for every thread printing
+static MESSAGE_FORMAT: std::sync::OnceLock<MessageFormat> = std::sync::OnceLock::new();
+
+fn set_message_format(format: MessageFormat) {
+    let _ = MESSAGE_FORMAT.set(format);
+}
+
+fn json_messages() -> bool {
+    MESSAGE_FORMAT.get() == Some(&MessageFormat::Json)
+}
+
+fn set_verbosity(verbosity: &Verbosity) {
+    trace::set_max_level(verbosity.quiet, verbosity.verbose);
+}
+
+/// Whether stdout is kept for the generated code, with `--emit -`
+static EMIT_STDOUT: std::sync::OnceLock<bool> = std::sync::OnceLock::new();
+
+/// Print a line about how a command is going, unless asked to be quiet; on stderr when
+/// stdout has the generated code
+fn status(line: String) {
+    if !trace::enabled(Level::Info) {
+        return;
+    }
+    if EMIT_STDOUT.get() == Some(&true) {
+        eprintln!("{}", line);
+    } else {
+        println!("{}", line);
+    }
+}
+
+/// Print a line about how the build went: on stdout, unless that's kept for JSON messages
+/// or the generated code
+fn say(line: String) {
+    if !trace::enabled(Level::Info) {
+        return;
+    }
+    if json_messages() || EMIT_STDOUT.get() == Some(&true) {
+        eprintln!("{}", line);
+    } else {
+        println!("{}", line);
+    }
+}
+
+/// Files a compile writes besides the generated projects, where asked to
+struct Outputs<'a> {
+    /// The build report, instead of `<out>/z-report.json`
+    report: Option<&'a std::path::Path>,
+    /// The generated projects, packed into one file
+    archive: Option<&'a std::path::Path>,
+}
+
+/// Compile `src_file`, returning how it went unless the compile was only checked for determinism
+fn handle_compilation(src_file: &str, out_dir: &str, outputs: Outputs, ui: bool, interactive: bool, check_determinism: bool, options: &CompileOptions) -> Option<CompileResult> {
+    let _span = trace::span(tr!("span-compile", source = src_file));
+    // `-` is a program piped on stdin, which can't import other files; the current
+    // directory stands in for the one it would be in
+    let piped = (src_file == "-").then(|| read_stdin(options.limits.max_bytes));
+    let src_path = std::path::Path::new(if piped.is_some() { STDIN } else { src_file });
+
+    // Refuse oversized sources before reading them into memory; files it imports are
+    // checked as they're read
+    if piped.is_none() {
+        let size = std::fs::metadata(src_path).map(|metadata| metadata.len()).unwrap_or_else(|e| {
+            eprintln!("❌ {}", tr!("read-source-failed", path = src_path.display(), error = e));
+            std::process::exit(EXIT_USAGE);
+        });
+        if size > options.limits.max_bytes as u64 {
+            eprintln!("❌ {}", tr!("source-too-large", path = src_path.display(), size = size, limit = options.limits.max_bytes));
+            std::process::exit(EXIT_ERRORS);
+        }
+    }
+
+    // Get the directory containing the source file
+    let src_dir = if piped.is_some() { std::path::Path::new(".") } else { src_path.parent().unwrap_or(std::path::Path::new(".")) };
+    let effective_out_dir = output_dir(src_path, out_dir);
+
+    // Ensure output directory exists
+    if !options.dry_run && options.archive.is_none() && !options.in_memory && !check_determinism {
+        std::fs::create_dir_all(&effective_out_dir).unwrap_or_else(|e| {
+            eprintln!("❌ {}", tr!("create-dir-failed", path = effective_out_dir.display(), error = e));
+            std::process::exit(EXIT_ERRORS);
+        });
+    }
+
+    let options = project_options(options.clone(), src_dir).unwrap_or_else(|e| {
+        eprintln!("❌ {}", e);
+        std::process::exit(EXIT_ERRORS);
+    });
+    if check_determinism {
+        report_determinism(src_path, &options);
+        return None;
+    }
+    // Without a terminal to draw on, the dashboard gives way to the regular output
+    let mut dashboard = if ui && !json_messages() { ui::Dashboard::open(src_path, &effective_out_dir) } else { None };
+    // With --interactive, the targets to build are picked first, out of those the
+    // program declares; the ones picked replace --target and --exclude
+    let options = match &mut dashboard {
+        Some(dashboard) if interactive => {
+            let checked = z_compiler_core::check_project(src_path, &options);
+            let registry = z_compiler_core::load_registry(&options);
+            let targets: Vec<(String, String)> = checked.targets.iter().map(|target| {
+                let kind = target.split_once(':').map_or(target.as_str(), |(kind, _)| kind);
+                (target.clone(), registry["targets"][kind]["description"].as_str().unwrap_or_default().to_string())
+            }).collect();
+            let picked = |target: &str| {
+                let selects = |name: &String| name == target || target.split_once(':').is_some_and(|(_, rest)| name == rest);
+                (options.targets.is_empty() || options.targets.iter().any(selects)) && !options.excluded.iter().any(selects)
+            };
+            // With nothing to pick from, the build reports why
+            if targets.is_empty() {
+                options
             } else {
-                eprintln!("❌ Command failed for {} with exit code: {:?}", project_name, status.code());
+                let chosen = dashboard.choose(&targets, picked)?;
+                CompileOptions { targets: chosen, excluded: Vec::new(), ..options }
             }
         }
-        Err(e) => {
-            eprintln!("❌ Failed to execute cargo command for {}: {}", project_name, e);
-            eprintln!("   Make sure cargo is installed and available in your PATH");
+        _ => options,
+    };
+    let result = match &mut dashboard {
+        Some(dashboard) => dashboard.build(src_path, &effective_out_dir, &options),
+        None => {
+            let result = match &piped {
+                Some(source) => z_compiler_core::compile(source, &effective_out_dir, &options),
+                None => z_compiler_core::compile_project(src_path, &effective_out_dir, &options),
+            };
+            print_result(&result, options.dry_run, src_path);
+            result
+        }
+    };
+    // Only when the user opted in; metrics are never worth failing a build over
+    let _ = z_compiler_core::telemetry::record(&result);
+    let report = result.report();
+
+    if let (Some(path), Some(archive)) = (outputs.archive, &result.archive) {
+        if let Err(e) = std::fs::write(path, archive) {
+            eprintln!("❌ {}", tr!("write-failed", path = path.display(), error = e));
+            std::process::exit(EXIT_ERRORS);
+        }
+    }
+
+    // A dry run only writes the report when asked to, since it's the one place with every
+    // diff, and a compile into an archive or onto stdout writes nothing to the output directory
+    let report_path = outputs.report.map(std::path::Path::to_path_buf)
+        .or_else(|| (!options.dry_run && options.archive.is_none() && !options.in_memory).then(|| effective_out_dir.join(REPORT_FILE)));
+    if let Some(report_path) = report_path {
+        if let Err(e) = report.write(&report_path) {
+            eprintln!("❌ {}", tr!("write-failed", path = report_path.display(), error = e));
+        }
+    }
+
+    // What's in an archive can't be run from here
+    let dev = dashboard.and_then(ui::Dashboard::finish).filter(|_| options.archive.is_none());
+    // Warnings were promoted to errors so that CI catches them
+    if (options.strict || options.deny_warnings) && result.has_errors() {
+        if options.deny_warnings {
+            eprintln!("❌ {}", tr!("warnings-denied", source = src_path.display()));
+        } else {
+            eprintln!("❌ {}", tr!("strict-failed", source = src_path.display()));
+        }
+        std::process::exit(EXIT_ERRORS);
+    }
+    if options.dry_run {
+        let changes: usize = result.artifacts.iter().map(|artifact| artifact.planned.len()).sum();
+        say(tr!("dry-run-done", source = src_path.display(), changes = changes, out = effective_out_dir.display()));
+        return Some(result);
+    }
+    let out = outputs.archive.unwrap_or(&effective_out_dir);
+    if result.has_errors() {
+        eprintln!("❌ {}", tr!("compiled-with-errors", source = src_path.display(), errors = result.diagnostics.iter().filter(|diagnostic| diagnostic.is_error()).count()));
+        return Some(result);
+    }
+    if options.in_memory {
+        emit_stdout(&result);
+        return Some(result);
+    }
+    say(tr!("compiled", source = src_path.display(), out = out.display()));
+    if let Some(target) = dev {
+        match run_project_command(&target.kind, &target.output_dir, &["dev".to_string()], &target.name) {
+            EXIT_OK => {}
+            code => std::process::exit(code),
         }
     }
+    Some(result)
 }
 
-fn handle_compilation(src_file: &str, out_dir: &str) {
-    let src_path = std::path::Path::new(src_file);
-    let out_path = std::path::Path::new(out_dir);
+/// How diagnostics name a program read from stdin
+const STDIN: &str = "<stdin>";
 
-    let src_code = std::fs::read_to_string(src_path)
-        .unwrap_or_else(|_| panic!("failed to read source {}", src_path.display()));
+/// The program piped on stdin, exiting with status 1 if it's over `limit` bytes
+fn read_stdin(limit: usize) -> String {
+    use std::io::Read;
 
-    // Get the directory containing the source file
-    let src_dir = src_path.parent().unwrap_or(std::path::Path::new("."));
+    let mut source = String::new();
+    if let Err(e) = std::io::stdin().take(limit as u64 + 1).read_to_string(&mut source) {
+        eprintln!("❌ {}", tr!("read-source-failed", path = STDIN, error = e));
+        std::process::exit(EXIT_USAGE);
+    }
+    if source.len() > limit {
+        eprintln!("❌ {}", tr!("stdin-too-large", limit = limit));
+        std::process::exit(EXIT_ERRORS);
+    }
+    source
+}
+
+/// Write the file a compile kept in memory to stdout, for `--emit -`; several files would
+/// run together, so exits with status 2 listing them instead
+fn emit_stdout(result: &CompileResult) {
+    use std::io::Write;
+
+    let contents = match result.files.values().collect::<Vec<_>>().as_slice() {
+        [] => return,
+        [contents] => *contents,
+        _ => {
+            let files: Vec<String> = result.files.keys().map(|path| path.display().to_string()).collect();
+            eprintln!("❌ {}", tr!("emit-several-files", count = files.len(), files = files.join(", ")));
+            std::process::exit(EXIT_USAGE);
+        }
+    };
+    let mut stdout = std::io::stdout().lock();
+    match stdout.write_all(contents).and_then(|_| stdout.flush()) {
+        // Whatever read it had what it wanted, like `head`
+        Err(e) if e.kind() == std::io::ErrorKind::BrokenPipe => {}
+        Err(e) => {
+            eprintln!("❌ {}", tr!("write-failed", path = "stdout", error = e));
+            std::process::exit(EXIT_ERRORS);
+        }
+        Ok(()) => {}
+    }
+}
 
-    // If output directory is relative and matches default, use source directory
-    let effective_out_dir = if out_dir == "out" {
-        src_dir.join("out")
+/// Where the program in `src_path` compiles to with `-o out_dir`: the default `out` is next
+/// to the source rather than in the current directory
+fn output_dir(src_path: &std::path::Path, out_dir: &str) -> std::path::PathBuf {
+    if out_dir == "out" {
+        src_path.parent().unwrap_or(std::path::Path::new(".")).join("out")
     } else {
-        out_path.to_path_buf()
+        std::path::PathBuf::from(out_dir)
+    }
+}
+
+/// Compile the programs `members` names: the entry files a glob matches, or those the
+/// `members` of a directory's `z.toml` lists. Each goes in its own directory's `out`, or
+/// with `-o`, in the output directory as it is under the root.
+fn handle_members(pattern: &str, out_dir: &str, outputs: Outputs, unsupported: bool, options: &CompileOptions) {
+    let fail = |message: String| -> ! {
+        eprintln!("❌ {}", message);
+        std::process::exit(EXIT_ERRORS);
+    };
+    let usage = |message: String| -> ! {
+        eprintln!("❌ {}", message);
+        std::process::exit(EXIT_USAGE);
+    };
+    if unsupported || outputs.archive.is_some() {
+        usage(tr!("members-unsupported"));
+    }
+    let is_glob = pattern.contains(['*', '?']);
+    let root = if is_glob { std::path::Path::new(".") } else { std::path::Path::new(pattern) };
+    let entries = if is_glob {
+        let entries: Vec<_> = z_compiler_core::members::glob(root, pattern).into_iter()
+            .map(|entry| entry.strip_prefix(root).map(std::path::Path::to_path_buf).unwrap_or(entry))
+            .collect();
+        if entries.is_empty() {
+            usage(tr!("members-no-match", pattern = pattern));
+        }
+        entries
+    } else {
+        let config = z_compiler_core::toml::load_config(root).unwrap_or_else(|e| fail(e));
+        let listed = Members::from_config(&config)
+            .unwrap_or_else(|e| fail(format!("{}: {}", root.join(z_compiler_core::toml::CONFIG_FILE).display(), e)));
+        if listed.is_empty() {
+            fail(tr!("members-none", dir = root.display()));
+        }
+        listed.entries(root).unwrap_or_else(|e| fail(e))
     };
+    let members: Vec<Member> = entries.into_iter()
+        .map(|entry| {
+            let dir = entry.parent().unwrap_or(std::path::Path::new(""));
+            let output_dir = if out_dir == "out" {
+                dir.join("out")
+            } else {
+                std::path::Path::new(out_dir).join(dir.strip_prefix(root).unwrap_or(dir))
+            };
+            Member { entry, output_dir }
+        })
+        .collect();
 
-    // Ensure output directory exists
-    std::fs::create_dir_all(&effective_out_dir).expect("failed to create output directory");
+    let options = options.clone().in_project(root).unwrap_or_else(|e| fail(e));
+    let compiled = z_compiler_core::members::compile_members(&members, &options);
+    for (member, result) in &compiled.members {
+        say(tr!("member-compiling", source = member.entry.display()));
+        print_result(result, options.dry_run, &member.entry);
+        let _ = z_compiler_core::telemetry::record(result);
+        if !options.dry_run {
+            let report_path = member.output_dir.join(REPORT_FILE);
+            if let Err(e) = result.report().write(&report_path) {
+                eprintln!("❌ {}", tr!("write-failed", path = report_path.display(), error = e));
+            }
+        }
+    }
+
+    // Every program's report in one, next to the z.toml listing them
+    let report_path = outputs.report.map(std::path::Path::to_path_buf)
+        .or_else(|| (!options.dry_run).then(|| root.join(REPORT_FILE)));
+    if let Some(report_path) = report_path {
+        if let Err(e) = compiled.report().write(&report_path) {
+            eprintln!("❌ {}", tr!("write-failed", path = report_path.display(), error = e));
+        }
+    }
+
+    if (options.strict || options.deny_warnings) && compiled.has_errors() {
+        if options.deny_warnings {
+            fail(tr!("warnings-denied", source = pattern));
+        }
+        fail(tr!("strict-failed", source = pattern));
+    }
+    if options.dry_run {
+        let changes: usize = compiled.members.iter()
+            .flat_map(|(_, result)| &result.artifacts)
+            .map(|artifact| artifact.planned.len())
+            .sum();
+        say(tr!("dry-run-members-done", count = compiled.members.len(), changes = changes));
+        if compiled.has_errors() {
+            std::process::exit(EXIT_ERRORS);
+        }
+        return;
+    }
+    say(tr!("compiled-members", count = compiled.members.len(), duration = compiled.duration.as_millis()));
+    if compiled.has_errors() {
+        std::process::exit(EXIT_ERRORS);
+    }
+}
+
+/// Compile `src_path` twice and show the files that came out different, exiting with
+/// status 1 if any did
+fn report_determinism(src_path: &std::path::Path, options: &CompileOptions) {
+    let (result, differences) = z_compiler_core::determinism::check(src_path, options);
+    // Targets that failed weren't compared, so say why
+    for diagnostic in result.diagnostics.iter().filter(|diagnostic| diagnostic.is_error()) {
+        print_diagnostic(diagnostic, src_path);
+    }
+    for difference in &differences {
+        println!("  📝 {}", tr!("determinism-differs", path = difference.path.display()));
+        for line in difference.diff.iter().flat_map(|diff| diff.lines()) {
+            println!("      {}", line);
+        }
+    }
+    if !differences.is_empty() {
+        eprintln!("❌ {}", tr!("determinism-failed", source = src_path.display(), count = differences.len()));
+        std::process::exit(EXIT_ERRORS);
+    }
+    println!("✅ {}", tr!("determinism-ok", source = src_path.display()));
+}
+
+/// Show the outcome of a compilation: problems in the source first, then each target
+/// with its own diagnostics, or in a dry run, the files it would change
+fn print_result(result: &CompileResult, dry_run: bool, source: &std::path::Path) {
+    if json_messages() {
+        return print_diagnostics(&result.diagnostics, source);
+    }
+    for diagnostic in result.diagnostics.iter().filter(|diagnostic| diagnostic.target.is_none()) {
+        print_diagnostic(diagnostic, source);
+    }
+    if !result.targets.is_empty() {
+        status(tr!("detected-targets", targets = result.targets.join(", ")));
+    }
+
+    for name in &result.targets {
+        let diagnostics = result.diagnostics.iter().filter(|diagnostic| diagnostic.target.as_ref() == Some(name));
+        let Some(artifact) = result.artifacts.iter().find(|artifact| format!("{}:{}", artifact.kind, artifact.name) == *name) else {
+            // Targets that couldn't be compiled at all only have diagnostics
+            diagnostics.for_each(|diagnostic| print_diagnostic(diagnostic, source));
+            continue;
+        };
+
+        status(format!("  {} {} - {}", artifact.kind, artifact.name, artifact.description));
+        diagnostics.for_each(|diagnostic| print_diagnostic(diagnostic, source));
+        if artifact.status == Status::Success {
+            if artifact.unchanged {
+                status(format!("  ⏭️  {}", tr!("target-unchanged", target = artifact.kind, name = artifact.name)));
+                continue;
+            } else if dry_run {
+                print_planned(&artifact.planned);
+                continue;
+            } else if artifact.cached {
+                status(format!("  ♻️  {}", tr!("cache-restored", target = artifact.kind, name = artifact.name)));
+            } else {
+                status(format!("  📁 {}", tr!("project-created", path = artifact.output_dir.display())));
+            }
+            for hook in &artifact.hooks {
+                status(format!("  🪝 {}", tr!("hook-ran", command = hook.command, duration = hook.duration_ms)));
+            }
+            status(format!("  ✅ {}", tr!("compile-success", target = artifact.kind, name = artifact.name, compiler = artifact.compiler)));
+            print_outputs(artifact);
+        }
+    }
+}
+
+/// With -v, how long `artifact` took and what it holds; with -vv, its files too
+fn print_outputs(artifact: &TargetReport) {
+    if !trace::enabled(Level::Debug) {
+        return;
+    }
+    let bytes: u64 = artifact.outputs.iter().map(|file| file.bytes).sum();
+    println!("     ⏱️  {}", tr!("target-stats", duration = artifact.duration_ms, files = artifact.outputs.len(), bytes = bytes));
+    if trace::enabled(Level::Trace) {
+        for file in &artifact.outputs {
+            println!("        {} ({} B)", file.path.display(), file.bytes);
+        }
+    }
+}
+
+/// The files a dry run would change; only updates show their diff, since for new and
+/// deleted files it's the whole file
+fn print_planned(planned: &[PlannedChange]) {
+    if planned.is_empty() {
+        status(format!("  ✅ {}", tr!("dry-run-no-changes")));
+    }
+    for change in planned {
+        match change.change {
+            Change::Create => status(format!("  ➕ {}", tr!("dry-run-create", path = change.path.display()))),
+            Change::Delete => status(format!("  ➖ {}", tr!("dry-run-delete", path = change.path.display()))),
+            Change::Update => {
+                status(format!("  📝 {}", tr!("dry-run-update", path = change.path.display())));
+                for line in change.diff.iter().flat_map(|diff| diff.lines()) {
+                    status(format!("      {}", line));
+                }
+            }
+        }
+    }
+}
 
-    z_compiler_core::compile(&src_code, &effective_out_dir);
+/// Print the diagnostics of the compile of `source`, in the format chosen
+fn print_diagnostics(diagnostics: &[Diagnostic], source: &std::path::Path) {
+    if !json_messages() {
+        return diagnostics.iter().for_each(|diagnostic| print_diagnostic(diagnostic, source));
+    }
+    let dir = source.parent().unwrap_or(std::path::Path::new(""));
+    for diagnostic in diagnostics {
+        let file = diagnostic.file.as_ref().map_or_else(|| source.to_path_buf(), |file| dir.join(file));
+        let diagnostic = Diagnostic { file: Some(file), ..diagnostic.clone() };
+        println!("{}", serde_json::to_string(&diagnostic).unwrap_or_default());
+    }
+}
 
-    println!(
-        "Compiled {} -> {}",
-        src_path.display(),
-        effective_out_dir.display()
-    );
-}
\ No newline at end of file
+/// Print a diagnostic of the compile of `source` with the line it points to
+fn print_diagnostic(diagnostic: &Diagnostic, source: &std::path::Path) {
+    let file = match &diagnostic.file {
+        Some(file) => source.parent().unwrap_or(std::path::Path::new("")).join(file),
+        None => source.to_path_buf(),
+    };
+    for line in frame::render(diagnostic, &file) {
+        eprintln!("{}", line);
+    }
+}
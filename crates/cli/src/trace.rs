@@ -0,0 +1,63 @@
+//! How much `z-cli` says about what it's doing, set once from `-q` and `-v`.
+//!
+//! Progress lines are events at a [`Level`], printed when the level is enabled, and a
+//! [`Span`] times a piece of work, reporting how long it took when it closes at `-vv`.
+//! The names follow `tracing`, which isn't a dependency yet: switching means replacing
+//! [`enabled`] with its macros and [`set_max_level`] with a subscriber's level filter.
+
+use std::sync::OnceLock;
+use std::time::Instant;
+
+use z_compiler_core::tr;
+
+/// How detailed an event is; a level is printed when it's at most the max level
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Level {
+    /// Diagnostics and errors, printed even with `-q`
+    Warn,
+    /// What a command did, printed unless `-q`
+    Info,
+    /// How long each target took and what it holds, with `-v`
+    Debug,
+    /// Every file and span, with `-vv`
+    Trace,
+}
+
+/// The most detailed level printed, for every thread printing
+static MAX_LEVEL: OnceLock<Level> = OnceLock::new();
+
+/// Set the max level, from `-q` or the number of `-v`; only the first call counts
+pub fn set_max_level(quiet: bool, verbose: u8) {
+    let level = match (quiet, verbose) {
+        (true, _) => Level::Warn,
+        (false, 0) => Level::Info,
+        (false, 1) => Level::Debug,
+        (false, _) => Level::Trace,
+    };
+    let _ = MAX_LEVEL.set(level);
+}
+
+/// Whether events at `level` are printed
+pub fn enabled(level: Level) -> bool {
+    level <= MAX_LEVEL.get().copied().unwrap_or(Level::Info)
+}
+
+/// A piece of work being timed, closed when dropped
+pub struct Span {
+    name: String,
+    started: Instant,
+}
+
+/// Open a span called `name`
+pub fn span(name: impl Into<String>) -> Span {
+    Span { name: name.into(), started: Instant::now() }
+}
+
+impl Drop for Span {
+    fn drop(&mut self) {
+        // On stderr, so it never mixes with generated code or JSON messages on stdout
+        if enabled(Level::Trace) {
+            eprintln!("     ⏱️  {}", tr!("span-closed", name = self.name, duration = self.started.elapsed().as_millis()));
+        }
+    }
+}
@@ -0,0 +1,554 @@
+//! Live build dashboard for `--ui`: each target's progress, a log of what happened so
+//! far and the diagnostics grouped by where they point, redrawn as the compile goes.
+//! Once the build is done, the selected target's output can be opened or its dev
+//! server started.
+//!
+//! With `--interactive`, the dashboard first lists the program's targets to pick the
+//! ones to build, and the diagnostics can be scrolled through on their own.
+//!
+//! The terminal is driven with plain ANSI escapes and `stty`, so there's nothing to
+//! install; without a terminal, `--ui` falls back to the regular output.
+
+use std::collections::VecDeque;
+use std::io::{IsTerminal, Read, Write};
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use z_compiler_core::report::Status;
+use z_compiler_core::{tr, CompileOptions, CompileResult, Diagnostic, Progress, Severity, TargetReport};
+
+const SPINNER: [char; 10] = ['⠋', '⠙', '⠹', '⠸', '⠼', '⠴', '⠦', '⠧', '⠇', '⠏'];
+const FRAME: Duration = Duration::from_millis(80);
+/// Log lines kept for scrolling back; older ones are dropped
+const LOG_LIMIT: usize = 500;
+
+const BOLD: &str = "\x1b[1m";
+const DIM: &str = "\x1b[2m";
+const RED: &str = "\x1b[31m";
+const GREEN: &str = "\x1b[32m";
+const YELLOW: &str = "\x1b[33m";
+const CYAN: &str = "\x1b[36m";
+const RESET: &str = "\x1b[0m";
+
+enum State {
+    Pending,
+    Running(Instant),
+    /// Done, with how it went unless it couldn't be compiled at all
+    Done(Option<TargetReport>),
+    /// Only checked, as it wasn't among the targets picked to build
+    Skipped,
+}
+
+/// The part of the dashboard ↑/↓ move in
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Focus {
+    Targets,
+    Diagnostics,
+}
+
+enum Key {
+    Up,
+    Down,
+    Open,
+    Dev,
+    /// Space, picking a target or leaving it out
+    Toggle,
+    /// Picking every target, or none if they all were
+    All,
+    /// Enter
+    Confirm,
+    /// Tab, moving between the targets and the diagnostics
+    Focus,
+    Quit,
+    /// Ctrl-C, which doesn't interrupt on its own while the dashboard owns the terminal
+    Interrupt,
+}
+
+/// The dashboard, owning the terminal until it's dropped
+pub struct Dashboard {
+    terminal: Terminal,
+    keys: Receiver<Key>,
+    title: String,
+    started: Instant,
+    finished: Option<Duration>,
+    targets: Vec<(String, State)>,
+    /// Targets to build, by name or `kind:name`; all of them when empty
+    chosen: Vec<String>,
+    selected: usize,
+    focus: Focus,
+    diagnostics: Vec<Diagnostic>,
+    /// Diagnostic lines scrolled past
+    scroll: usize,
+    source: String,
+    log: VecDeque<String>,
+    /// One-off message for the footer, such as a failure to open the output
+    notice: Option<String>,
+}
+
+impl Dashboard {
+    /// Take over the terminal, if there is one to take over
+    pub fn open(source: &Path, out: &Path) -> Option<Self> {
+        if !std::io::stdout().is_terminal() || !std::io::stdin().is_terminal() {
+            return None;
+        }
+        let terminal = Terminal::enter()?;
+        let keys = read_keys(terminal.stop.clone());
+        Some(Self {
+            terminal,
+            keys,
+            title: format!("z  {} → {}", source.display(), out.display()),
+            started: Instant::now(),
+            finished: None,
+            targets: Vec::new(),
+            chosen: Vec::new(),
+            selected: 0,
+            focus: Focus::Targets,
+            diagnostics: Vec::new(),
+            scroll: 0,
+            source: source.display().to_string(),
+            log: VecDeque::new(),
+            notice: None,
+        })
+    }
+
+    /// Let the user pick which of the `targets`, `kind:name` each with a description, to
+    /// build, starting with those `picked`. Returns their names, or `None` if they quit.
+    pub fn choose(&mut self, targets: &[(String, String)], picked: impl Fn(&str) -> bool) -> Option<Vec<String>> {
+        let mut picks: Vec<bool> = targets.iter().map(|(name, _)| picked(name)).collect();
+        loop {
+            while let Ok(key) = self.keys.try_recv() {
+                match key {
+                    Key::Up => self.selected = self.selected.saturating_sub(1),
+                    Key::Down => self.selected = (self.selected + 1).min(targets.len().saturating_sub(1)),
+                    Key::Toggle => {
+                        if let Some(pick) = picks.get_mut(self.selected) {
+                            *pick = !*pick;
+                        }
+                    }
+                    Key::All => {
+                        let all = !picks.iter().all(|pick| *pick);
+                        picks.fill(all);
+                    }
+                    // Building none would build them all
+                    Key::Confirm if !picks.contains(&true) => self.notice = Some(tr!("ui-choose-none")),
+                    Key::Confirm => {
+                        self.notice = None;
+                        self.selected = 0;
+                        self.started = Instant::now();
+                        let chosen = targets.iter().zip(&picks).filter(|(_, pick)| **pick).map(|((name, _), _)| name.clone());
+                        return Some(chosen.collect());
+                    }
+                    Key::Quit => return None,
+                    Key::Interrupt => self.interrupt(),
+                    _ => {}
+                }
+            }
+            self.draw_choice(targets, &picks);
+            std::thread::sleep(FRAME);
+        }
+    }
+
+    /// Compile the program in `entry` into `out`, showing the progress until it's done
+    pub fn build(&mut self, entry: &Path, out: &Path, options: &CompileOptions) -> CompileResult {
+        self.chosen = options.targets.clone();
+        let (sender, progress) = mpsc::channel();
+        let options = CompileOptions { progress: Some(sender.into()), ..options.clone() };
+        std::thread::scope(|scope| {
+            let compile = scope.spawn(|| z_compiler_core::compile_project(entry, out, &options));
+            while !compile.is_finished() {
+                self.update(&progress);
+                self.handle_keys(false);
+                self.draw();
+                std::thread::sleep(FRAME);
+            }
+            self.update(&progress);
+            self.finished = Some(self.started.elapsed());
+            self.draw();
+            compile.join().unwrap_or_else(|panic| std::panic::resume_unwind(panic))
+        })
+    }
+
+    /// Wait for the user to pick a post-build action. Returns the target whose dev
+    /// server to start, if that's what they picked; either way the terminal is given back.
+    pub fn finish(mut self) -> Option<TargetReport> {
+        loop {
+            if let Some(target) = self.handle_keys(true) {
+                return target;
+            }
+            self.draw();
+            std::thread::sleep(FRAME);
+        }
+    }
+
+    fn update(&mut self, progress: &Receiver<Progress>) {
+        for event in progress.try_iter() {
+            match event {
+                Progress::Checked { targets, diagnostics } => {
+                    if !targets.is_empty() {
+                        self.push_log(tr!("detected-targets", targets = targets.join(", ")));
+                    }
+                    self.targets = targets.into_iter().map(|target| (target, State::Pending)).collect();
+                    self.add_diagnostics(diagnostics);
+                }
+                // Targets left out are only checked, so there's nothing to show of them yet
+                Progress::Started(target) if !self.is_chosen(&target) => {}
+                Progress::Started(target) => {
+                    self.push_log(tr!("ui-target-started", target = target));
+                    self.set_state(&target, State::Running(Instant::now()));
+                }
+                Progress::Finished { target, report, diagnostics } => {
+                    self.add_diagnostics(diagnostics);
+                    if let Some(report) = report.as_ref().filter(|report| report.status == Status::Success) {
+                        self.push_log(if report.unchanged {
+                            tr!("target-unchanged", target = report.kind, name = report.name)
+                        } else if report.cached {
+                            tr!("cache-restored", target = report.kind, name = report.name)
+                        } else {
+                            tr!("compile-success", target = report.kind, name = report.name, compiler = report.compiler)
+                        });
+                    }
+                    let state = match report {
+                        None if !self.is_chosen(&target) => State::Skipped,
+                        report => State::Done(report.map(|report| *report)),
+                    };
+                    self.set_state(&target, state);
+                }
+            }
+        }
+    }
+
+    fn set_state(&mut self, target: &str, state: State) {
+        if let Some((_, current)) = self.targets.iter_mut().find(|(name, _)| name == target) {
+            *current = state;
+        }
+    }
+
+    /// Whether the target `kind:name` was among those to build
+    fn is_chosen(&self, target: &str) -> bool {
+        let name = target.split_once(':').map_or(target, |(_, name)| name);
+        self.chosen.is_empty() || self.chosen.iter().any(|chosen| chosen == target || chosen == name)
+    }
+
+    fn add_diagnostics(&mut self, diagnostics: Vec<Diagnostic>) {
+        for diagnostic in diagnostics {
+            let icon = match diagnostic.severity {
+                Severity::Error => "✗",
+                Severity::Warning => "!",
+            };
+            self.push_log(format!("{} {}", icon, diagnostic.message));
+            self.diagnostics.push(diagnostic);
+        }
+    }
+
+    fn push_log(&mut self, line: String) {
+        if self.log.len() == LOG_LIMIT {
+            self.log.pop_front();
+        }
+        self.log.push_back(line);
+    }
+
+    /// Act on the keys pressed since the last frame. Post-build actions only work once
+    /// the build is `done`; a dev server to start ends the dashboard with its target.
+    fn handle_keys(&mut self, done: bool) -> Option<Option<TargetReport>> {
+        while let Ok(key) = self.keys.try_recv() {
+            match key {
+                Key::Up if self.focus == Focus::Diagnostics => self.scroll = self.scroll.saturating_sub(1),
+                Key::Down if self.focus == Focus::Diagnostics => self.scroll += 1,
+                Key::Up => self.selected = self.selected.saturating_sub(1),
+                Key::Down => self.selected = (self.selected + 1).min(self.targets.len().saturating_sub(1)),
+                Key::Focus if self.focus == Focus::Targets && !self.diagnostics.is_empty() => self.focus = Focus::Diagnostics,
+                Key::Focus => self.focus = Focus::Targets,
+                Key::Interrupt => self.interrupt(),
+                Key::Quit if done => return Some(None),
+                Key::Open if done => {
+                    let dir = self.selected_report().map(|report| report.output_dir.clone());
+                    if let Some(Err(e)) = dir.map(|dir| open(&dir)) {
+                        self.notice = Some(e);
+                    }
+                }
+                Key::Dev if done => match self.selected_report() {
+                    Some(report) if report.status == Status::Success => return Some(Some(report.clone())),
+                    _ => self.notice = Some(tr!("ui-dev-unavailable")),
+                },
+                _ => {}
+            }
+        }
+        None
+    }
+
+    fn interrupt(&mut self) -> ! {
+        drop(std::mem::take(&mut self.terminal));
+        std::process::exit(130);
+    }
+
+    fn selected_report(&self) -> Option<&TargetReport> {
+        match self.targets.get(self.selected) {
+            Some((_, State::Done(report))) => report.as_ref(),
+            _ => None,
+        }
+    }
+
+    fn draw(&mut self) {
+        let (height, width) = self.terminal.size;
+        let elapsed = self.finished.unwrap_or_else(|| self.started.elapsed());
+        let spinner = SPINNER[(elapsed.as_millis() / FRAME.as_millis()) as usize % SPINNER.len()];
+
+        let mut lines = Vec::new();
+        let status = match self.finished {
+            None => format!("{}{}{} {}", CYAN, spinner, RESET, tr!("ui-building", seconds = format!("{:.1}", elapsed.as_secs_f32()))),
+            Some(_) if self.diagnostics.iter().any(Diagnostic::is_error) => {
+                format!("{}✗{} {}", RED, RESET, tr!("ui-failed", seconds = format!("{:.1}", elapsed.as_secs_f32())))
+            }
+            Some(_) => format!("{}✓{} {}", GREEN, RESET, tr!("ui-done", seconds = format!("{:.1}", elapsed.as_secs_f32()))),
+        };
+        lines.push(format!("{}{}{}   {}", BOLD, fit(&self.title, width.saturating_sub(30)), RESET, status));
+        lines.push(String::new());
+
+        lines.push(format!("{}{}{}", BOLD, tr!("ui-targets"), RESET));
+        for (index, (name, state)) in self.targets.iter().enumerate() {
+            let marker = if index == self.selected { "❯" } else { " " };
+            let (icon, detail) = match state {
+                State::Pending => (format!("{}·{}", DIM, RESET), String::new()),
+                State::Running(since) => (format!("{}{}{}", CYAN, spinner, RESET), format!("{:.1}s", since.elapsed().as_secs_f32())),
+                State::Done(None) => (format!("{}✗{}", RED, RESET), String::new()),
+                State::Skipped => (format!("{}-{}", DIM, RESET), tr!("ui-skipped")),
+                State::Done(Some(report)) if report.status == Status::Failed => {
+                    (format!("{}✗{}", RED, RESET), format!("{}ms", report.duration_ms))
+                }
+                State::Done(Some(report)) if report.unchanged => (format!("{}↷{}", DIM, RESET), tr!("ui-unchanged")),
+                State::Done(Some(report)) => (
+                    format!("{}✓{}", GREEN, RESET),
+                    format!("{}  {}ms  {}", report.compiler, report.duration_ms, report.output_dir.display()),
+                ),
+            };
+            lines.push(format!(" {} {} {:<24}  {}{}{}", marker, icon, fit(name, 24), DIM, fit(&detail, width.saturating_sub(32)), RESET));
+        }
+        lines.push(String::new());
+
+        // What's left is shared by the diagnostics and the log, keeping the footer's two
+        // lines; the diagnostics take most of it while they're being scrolled through
+        let available = height.saturating_sub(lines.len() + 2);
+        let diagnostics = self.diagnostic_lines(width);
+        let diagnostic_rows = match self.focus {
+            Focus::Targets => diagnostics.len().min(available / 2),
+            Focus::Diagnostics => (diagnostics.len() + 1).min(available.saturating_sub(4)),
+        };
+        if diagnostic_rows > 0 {
+            self.scroll = self.scroll.min(diagnostics.len().saturating_sub(diagnostic_rows - 1));
+            let heading = match self.focus {
+                Focus::Targets => format!("{}{}{}", BOLD, tr!("ui-diagnostics"), RESET),
+                Focus::Diagnostics => format!(
+                    "{}{}{} {}{}–{}/{}{}",
+                    BOLD,
+                    tr!("ui-diagnostics"),
+                    RESET,
+                    DIM,
+                    self.scroll + 1,
+                    (self.scroll + diagnostic_rows - 1).min(diagnostics.len()),
+                    diagnostics.len(),
+                    RESET
+                ),
+            };
+            lines.push(heading);
+            lines.extend(diagnostics.into_iter().skip(self.scroll).take(diagnostic_rows - 1));
+        }
+        let log_rows = available.saturating_sub(diagnostic_rows + 1);
+        lines.push(format!("{}{}{}", BOLD, tr!("ui-log"), RESET));
+        let skipped = self.log.len().saturating_sub(log_rows);
+        lines.extend(self.log.iter().skip(skipped).map(|line| format!(" {}", fit(line, width.saturating_sub(1)))));
+
+        while lines.len() < height.saturating_sub(1) {
+            lines.push(String::new());
+        }
+        let footer = match (&self.notice, self.finished) {
+            (Some(notice), _) => format!("{}{}{}", YELLOW, fit(notice, width), RESET),
+            (None, None) => format!("{}{}{}", DIM, tr!("ui-keys-building"), RESET),
+            (None, Some(_)) => format!("{}{}{}", DIM, tr!("ui-keys-done"), RESET),
+        };
+        self.show(lines, footer);
+    }
+
+    /// The list of targets to pick from, with the picked ones checked
+    fn draw_choice(&mut self, targets: &[(String, String)], picks: &[bool]) {
+        let (height, width) = self.terminal.size;
+        let mut lines = vec![format!("{}{}{}", BOLD, fit(&self.title, width), RESET), String::new()];
+        lines.push(format!("{}{}{}", BOLD, tr!("ui-choose", picked = picks.iter().filter(|pick| **pick).count(), total = picks.len()), RESET));
+        // Scrolled so that the selected target stays in view
+        let rows = height.saturating_sub(lines.len() + 2).max(1);
+        let first = (self.selected + 1).saturating_sub(rows);
+        for (index, ((name, description), pick)) in targets.iter().zip(picks).enumerate().skip(first).take(rows) {
+            let marker = if index == self.selected { "❯" } else { " " };
+            let check = if *pick { format!("{}[x]{}", GREEN, RESET) } else { "[ ]".to_string() };
+            lines.push(format!(" {} {} {:<24}  {}{}{}", marker, check, fit(name, 24), DIM, fit(description, width.saturating_sub(34)), RESET));
+        }
+        while lines.len() < height.saturating_sub(1) {
+            lines.push(String::new());
+        }
+        let footer = match &self.notice {
+            Some(notice) => format!("{}{}{}", YELLOW, fit(notice, width), RESET),
+            None => format!("{}{}{}", DIM, tr!("ui-keys-choose"), RESET),
+        };
+        self.show(lines, footer);
+    }
+
+    /// Replace what's on the screen with `lines`, cut to its height, and the `footer` last
+    fn show(&self, mut lines: Vec<String>, footer: String) {
+        let (height, _) = self.terminal.size;
+        lines.truncate(height.saturating_sub(1));
+        lines.push(footer);
+
+        let mut stdout = std::io::stdout().lock();
+        let _ = write!(stdout, "\x1b[H{}\x1b[J", lines.join("\x1b[K\r\n"));
+        let _ = stdout.flush();
+    }
+
+    /// Diagnostics grouped by where they point: the source file for those with a
+    /// line, then each target for the rest
+    fn diagnostic_lines(&self, width: usize) -> Vec<String> {
+        let mut groups: Vec<(String, Vec<&Diagnostic>)> = Vec::new();
+        for diagnostic in &self.diagnostics {
+            let group = match (&diagnostic.line, &diagnostic.target) {
+                (Some(_), _) | (None, None) => self.source.clone(),
+                (None, Some(target)) => target.clone(),
+            };
+            match groups.iter_mut().find(|(name, _)| *name == group) {
+                Some((_, diagnostics)) => diagnostics.push(diagnostic),
+                None => groups.push((group, vec![diagnostic])),
+            }
+        }
+
+        let mut lines = Vec::new();
+        for (group, diagnostics) in groups {
+            lines.push(format!(" {}{}{}", CYAN, fit(&group, width.saturating_sub(1)), RESET));
+            for diagnostic in diagnostics {
+                let (color, icon) = match diagnostic.severity {
+                    Severity::Error => (RED, "✗"),
+                    Severity::Warning => (YELLOW, "!"),
+                };
+                lines.push(format!("   {}{}{} {}", color, icon, RESET, fit(&diagnostic.to_string(), width.saturating_sub(5))));
+                if let Some(help) = &diagnostic.help {
+                    lines.push(format!("     {}{}{}", DIM, fit(help, width.saturating_sub(5)), RESET));
+                }
+            }
+        }
+        lines
+    }
+}
+
+/// `text` cut to `width` characters, ending in `…` if it didn't fit
+fn fit(text: &str, width: usize) -> String {
+    if text.chars().count() <= width {
+        return text.to_string();
+    }
+    let mut fitted: String = text.chars().take(width.saturating_sub(1)).collect();
+    if width > 0 {
+        fitted.push('…');
+    }
+    fitted
+}
+
+/// Open a directory in the system's file manager
+fn open(dir: &Path) -> Result<(), String> {
+    let program = if cfg!(target_os = "macos") {
+        "open"
+    } else if cfg!(windows) {
+        "explorer"
+    } else {
+        "xdg-open"
+    };
+    Command::new(program)
+        .arg(dir)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .map(drop)
+        .map_err(|e| tr!("command-exec-failed", tool = program, name = dir.display(), error = e))
+}
+
+/// Keys as they're pressed, read on their own thread until `stop` is set
+fn read_keys(stop: Arc<AtomicBool>) -> Receiver<Key> {
+    let (sender, keys) = mpsc::channel();
+    std::thread::spawn(move || {
+        let mut stdin = std::io::stdin();
+        let mut buffer = [0u8; 16];
+        while !stop.load(Ordering::Relaxed) {
+            // Returns empty every tenth of a second (see `Terminal::enter`), to notice `stop`
+            let Ok(read) = stdin.read(&mut buffer) else {
+                return;
+            };
+            let key = match &buffer[..read] {
+                [3] => Key::Interrupt,
+                [b'q'] | [b'Q'] | [27] => Key::Quit,
+                [b' '] => Key::Toggle,
+                [b'a'] | [b'A'] => Key::All,
+                [b'\r'] | [b'\n'] => Key::Confirm,
+                [b'\t'] => Key::Focus,
+                [b'o'] | [b'O'] => Key::Open,
+                [b'd'] | [b'D'] => Key::Dev,
+                [b'k'] | [27, b'[', b'A'] => Key::Up,
+                [b'j'] | [27, b'[', b'B'] => Key::Down,
+                _ => continue,
+            };
+            if sender.send(key).is_err() {
+                return;
+            }
+        }
+    });
+    keys
+}
+
+/// The terminal in the state the dashboard needs: alternate screen, no cursor, keys
+/// read as they're pressed. Restored as it was when dropped.
+#[derive(Default)]
+struct Terminal {
+    /// What `stty -g` printed before, to restore; `None` once restored
+    saved: Option<String>,
+    /// Rows and columns
+    size: (usize, usize),
+    stop: Arc<AtomicBool>,
+}
+
+impl Terminal {
+    fn enter() -> Option<Self> {
+        let saved = stty(&["-g"])?;
+        // No line buffering, echo or signals; reads wait at most a tenth of a second
+        stty(&["-icanon", "-echo", "-isig", "min", "0", "time", "1"])?;
+        print!("\x1b[?1049h\x1b[?25l");
+        Some(Self { saved: Some(saved.trim().to_string()), size: size(), stop: Arc::default() })
+    }
+}
+
+impl Drop for Terminal {
+    fn drop(&mut self) {
+        let Some(saved) = self.saved.take() else {
+            return;
+        };
+        self.stop.store(true, Ordering::Relaxed);
+        print!("\x1b[?25h\x1b[?1049l");
+        let _ = std::io::stdout().flush();
+        stty(&[saved.as_str()]);
+    }
+}
+
+/// Run `stty` on the terminal, returning what it printed
+fn stty(args: &[&str]) -> Option<String> {
+    let output = Command::new("stty").args(args).stdin(Stdio::inherit()).stderr(Stdio::null()).output().ok()?;
+    output.status.success().then(|| String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// Rows and columns of the terminal, from `stty size`, `$LINES`/`$COLUMNS`, or 24×80
+fn size() -> (usize, usize) {
+    let from_stty = stty(&["size"]).and_then(|size| {
+        let (rows, columns) = size.trim().split_once(' ')?;
+        Some((rows.parse().ok()?, columns.parse().ok()?))
+    });
+    from_stty.unwrap_or_else(|| {
+        let variable = |name: &str, default| std::env::var(name).ok().and_then(|value| value.parse().ok()).unwrap_or(default);
+        (variable("LINES", 24), variable("COLUMNS", 80))
+    })
+}
@@ -0,0 +1,35 @@
+//! The conformance checks of `z-compiler-testkit`, for every built-in target compiler.
+
+use std::process::Command;
+
+use z_compiler_core::CompileOptions;
+use z_compiler_testkit::{assert_conformance, Conformance};
+
+#[test]
+fn next() {
+    assert_conformance(&Conformance::new("next", &CompileOptions::default()).require("package.json"));
+}
+
+#[test]
+fn deno() {
+    assert_conformance(&Conformance::new("deno", &CompileOptions::default()).require("deno.json"));
+}
+
+#[test]
+fn swift() {
+    assert_conformance(&Conformance::new("swift", &CompileOptions::default()).require("generated.swift"));
+}
+
+#[test]
+fn rust() {
+    assert_conformance(&Conformance::new("rust", &CompileOptions::default()).require("Cargo.toml"));
+}
+
+#[test]
+fn tauri() {
+    // Scaffolded with `pnpm create tauri-app`, which not every machine has
+    if !Command::new("pnpm").arg("--version").output().is_ok_and(|output| output.status.success()) {
+        return;
+    }
+    assert_conformance(&Conformance::new("tauri", &CompileOptions::default()).require("src-tauri/Cargo.toml"));
+}
@@ -0,0 +1,54 @@
+//! Other names for target types, so target naming can change without breaking
+//! existing sources.
+//!
+//! The registry's `targetAliases` maps each other name to the target type it stands
+//! for: `nextjs web {}` compiles as `next web {}`. An alias marked `deprecated` is an
+//! old name kept working while sources move off it, and every use of it is warned
+//! about with the name to use instead.
+
+use serde_json::Value;
+use z_ast::{Element, Node, Span};
+
+/// A target whose type was written with an alias
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Renamed {
+    /// The alias, e.g. `NextJS`
+    pub alias: String,
+    /// The target type it stands for, e.g. `next`
+    pub target: String,
+    pub deprecated: bool,
+    pub span: Span,
+}
+
+/// Replace the aliases of target types in `program` with the types they stand for
+pub(crate) fn resolve_aliases(program: &mut Element, registry: &Value) -> Vec<Renamed> {
+    let mut renamed = Vec::new();
+    rename(&mut program.children, &registry["targetAliases"], &mut renamed);
+    renamed
+}
+
+/// Targets are at the top level or in a workspace
+fn rename(nodes: &mut [Node], aliases: &Value, renamed: &mut Vec<Renamed>) {
+    for node in nodes {
+        let Node::Element(element) = node else {
+            continue;
+        };
+        let Some((kind, name)) = element.name.split_once(':') else {
+            continue;
+        };
+        if kind == "workspace" {
+            rename(&mut element.children, aliases, renamed);
+            continue;
+        }
+        let Some(target) = aliases[kind]["target"].as_str() else {
+            continue;
+        };
+        renamed.push(Renamed {
+            alias: kind.to_string(),
+            target: target.to_string(),
+            deprecated: aliases[kind]["deprecated"] == true,
+            span: element.span,
+        });
+        element.name = format!("{}:{}", target, name);
+    }
+}
@@ -0,0 +1,265 @@
+//! Writers of the archives the compiler produces: crash report bundles and projects
+//! emitted as a single file (see [`ArchiveSink`](crate::sink::ArchiveSink)). Both
+//! store entries as they are, with fixed dates, so the same files make the same archive;
+//! a tar can then be compressed with [`gzip`].
+
+/// Minimal zip archive writer; entries are stored uncompressed
+#[derive(Default)]
+pub(crate) struct Zip {
+    data: Vec<u8>,
+    central_directory: Vec<u8>,
+    entries: u16,
+}
+
+impl Zip {
+    /// MS-DOS date of 1980-01-01, the earliest a zip entry can have; keeps bundles reproducible
+    const DATE: u16 = (1 << 5) | 1;
+
+    pub fn add(&mut self, name: &str, contents: &[u8]) {
+        let offset = self.data.len() as u32;
+        let crc = crc32(contents);
+        let size = contents.len() as u32;
+
+        // Local file header
+        self.data.extend(0x0403_4b50u32.to_le_bytes());
+        self.data.extend(20u16.to_le_bytes()); // version needed
+        self.data.extend(0u16.to_le_bytes()); // flags
+        self.data.extend(0u16.to_le_bytes()); // stored
+        self.data.extend(0u16.to_le_bytes()); // time
+        self.data.extend(Self::DATE.to_le_bytes());
+        self.data.extend(crc.to_le_bytes());
+        self.data.extend(size.to_le_bytes());
+        self.data.extend(size.to_le_bytes());
+        self.data.extend((name.len() as u16).to_le_bytes());
+        self.data.extend(0u16.to_le_bytes()); // extra field length
+        self.data.extend(name.as_bytes());
+        self.data.extend(contents);
+
+        // Central directory record
+        let record = &mut self.central_directory;
+        record.extend(0x0201_4b50u32.to_le_bytes());
+        record.extend(20u16.to_le_bytes()); // version made by
+        record.extend(20u16.to_le_bytes()); // version needed
+        record.extend(0u16.to_le_bytes()); // flags
+        record.extend(0u16.to_le_bytes()); // stored
+        record.extend(0u16.to_le_bytes()); // time
+        record.extend(Self::DATE.to_le_bytes());
+        record.extend(crc.to_le_bytes());
+        record.extend(size.to_le_bytes());
+        record.extend(size.to_le_bytes());
+        record.extend((name.len() as u16).to_le_bytes());
+        record.extend([0u8; 12]); // extra, comment, disk, internal and external attributes
+        record.extend(offset.to_le_bytes());
+        record.extend(name.as_bytes());
+        self.entries += 1;
+    }
+
+    pub fn finish(mut self) -> Vec<u8> {
+        let directory_offset = self.data.len() as u32;
+        let directory_size = self.central_directory.len() as u32;
+        self.data.append(&mut self.central_directory);
+
+        self.data.extend(0x0605_4b50u32.to_le_bytes());
+        self.data.extend([0u8; 4]); // disk numbers
+        self.data.extend(self.entries.to_le_bytes());
+        self.data.extend(self.entries.to_le_bytes());
+        self.data.extend(directory_size.to_le_bytes());
+        self.data.extend(directory_offset.to_le_bytes());
+        self.data.extend(0u16.to_le_bytes()); // comment length
+        self.data
+    }
+}
+
+/// Minimal tar (ustar) archive writer
+#[derive(Default)]
+pub(crate) struct Tar {
+    data: Vec<u8>,
+}
+
+impl Tar {
+    const BLOCK: usize = 512;
+
+    /// Add a file; names longer than ustar allows are cut short
+    pub fn add(&mut self, name: &str, contents: &[u8]) {
+        // Names over 100 bytes go partly in the prefix field, split at a `/`
+        let (prefix, name) = match name.len() > 100 {
+            true => name.char_indices()
+                .filter(|(index, c)| *c == '/' && *index <= 155 && name.len() - index - 1 <= 100)
+                .map(|(index, _)| (&name[..index], &name[index + 1..]))
+                .next()
+                .unwrap_or(("", name)),
+            false => ("", name),
+        };
+        let mut header = [0u8; Self::BLOCK];
+        let mut field = |offset: usize, length: usize, value: &[u8]| {
+            let length = value.len().min(length);
+            header[offset..offset + length].copy_from_slice(&value[..length]);
+        };
+        field(0, 100, name.as_bytes());
+        field(100, 8, b"0000644\0"); // mode
+        field(108, 8, b"0000000\0"); // owner
+        field(116, 8, b"0000000\0"); // group
+        field(124, 12, format!("{:011o}\0", contents.len()).as_bytes());
+        field(136, 12, b"00000000000\0"); // modification time
+        field(148, 8, b"        "); // checksum, counted as spaces
+        field(156, 1, b"0"); // regular file
+        field(257, 8, b"ustar\x0000"); // magic and version
+        field(345, 155, prefix.as_bytes());
+        let checksum: u32 = header.iter().map(|byte| u32::from(*byte)).sum();
+        header[148..156].copy_from_slice(format!("{:06o}\0 ", checksum).as_bytes());
+
+        self.data.extend(header);
+        self.data.extend(contents);
+        self.data.resize(self.data.len().next_multiple_of(Self::BLOCK), 0);
+    }
+
+    pub fn finish(mut self) -> Vec<u8> {
+        // Two empty blocks end the archive
+        self.data.resize(self.data.len() + 2 * Self::BLOCK, 0);
+        self.data
+    }
+}
+
+/// `data` compressed as a gzip file, with no name or date so the same data makes the
+/// same file. The compression is plain deflate with fixed codes: generated projects are
+/// mostly text, which it shrinks to around a third.
+pub(crate) fn gzip(data: &[u8]) -> Vec<u8> {
+    let mut out = vec![0x1f, 0x8b, 8, 0, 0, 0, 0, 0, 0, 255];
+    out.extend(deflate(data));
+    out.extend(crc32(data).to_le_bytes());
+    out.extend((data.len() as u32).to_le_bytes());
+    out
+}
+
+/// Lengths and distances of deflate's length and distance codes: the smallest each
+/// code stands for, and how many extra bits follow it
+const LENGTHS: [(u16, u8); 29] = [
+    (3, 0), (4, 0), (5, 0), (6, 0), (7, 0), (8, 0), (9, 0), (10, 0), (11, 1), (13, 1), (15, 1), (17, 1), (19, 2), (23, 2), (27, 2),
+    (31, 2), (35, 3), (43, 3), (51, 3), (59, 3), (67, 4), (83, 4), (99, 4), (115, 4), (131, 5), (163, 5), (195, 5), (227, 5), (258, 0),
+];
+const DISTANCES: [(u16, u8); 30] = [
+    (1, 0), (2, 0), (3, 0), (4, 0), (5, 1), (7, 1), (9, 2), (13, 2), (17, 3), (25, 3), (33, 4), (49, 4), (65, 5), (97, 5), (129, 6),
+    (193, 6), (257, 7), (385, 7), (513, 8), (769, 8), (1025, 9), (1537, 9), (2049, 10), (3073, 10), (4097, 11), (6145, 11),
+    (8193, 12), (12289, 12), (16385, 13), (24577, 13),
+];
+
+/// `data` as one deflate block with the fixed Huffman codes, repeats found by hashing
+/// every three bytes
+fn deflate(data: &[u8]) -> Vec<u8> {
+    const WINDOW: usize = 32 * 1024;
+    const HASH_BITS: u32 = 15;
+    const CHAIN: usize = 64;
+    const MAX_LENGTH: usize = 258;
+
+    let mut bits = Bits::default();
+    bits.push(1, 1); // last block
+    bits.push(1, 2); // fixed codes
+    let hash = |at: usize| (u32::from(data[at]) << 16 | u32::from(data[at + 1]) << 8 | u32::from(data[at + 2])).wrapping_mul(0x9e37_79b1) >> (32 - HASH_BITS);
+    let mut head = vec![usize::MAX; 1 << HASH_BITS];
+    // The position before each one in the window with the same hash
+    let mut previous = vec![usize::MAX; WINDOW];
+    let insert = |at: usize, head: &mut [usize], previous: &mut [usize]| {
+        if at + 3 <= data.len() {
+            let key = hash(at) as usize;
+            previous[at % WINDOW] = head[key];
+            head[key] = at;
+        }
+    };
+
+    let mut at = 0;
+    while at < data.len() {
+        let mut best = (0, 0);
+        if at + 3 <= data.len() {
+            let mut candidate = head[hash(at) as usize];
+            for _ in 0..CHAIN {
+                if candidate == usize::MAX || at - candidate >= WINDOW {
+                    break;
+                }
+                let length = data[candidate..].iter().zip(&data[at..]).take(MAX_LENGTH).take_while(|(a, b)| a == b).count();
+                if length > best.0 {
+                    best = (length, at - candidate);
+                }
+                // What's left from before the window was overwritten by later positions
+                let next = previous[candidate % WINDOW];
+                if next >= candidate {
+                    break;
+                }
+                candidate = next;
+            }
+        }
+        if best.0 >= 3 {
+            let (length, distance) = best;
+            let code = LENGTHS.iter().rposition(|(base, _)| usize::from(*base) <= length).unwrap_or(0);
+            literal(&mut bits, 257 + code as u16);
+            bits.push(length as u32 - u32::from(LENGTHS[code].0), LENGTHS[code].1);
+            let code = DISTANCES.iter().rposition(|(base, _)| usize::from(*base) <= distance).unwrap_or(0);
+            bits.push_code(code as u32, 5);
+            bits.push(distance as u32 - u32::from(DISTANCES[code].0), DISTANCES[code].1);
+            for skipped in at..at + length {
+                insert(skipped, &mut head, &mut previous);
+            }
+            at += length;
+        } else {
+            literal(&mut bits, u16::from(data[at]));
+            insert(at, &mut head, &mut previous);
+            at += 1;
+        }
+    }
+    literal(&mut bits, 256); // end of block
+    bits.finish()
+}
+
+/// Write the fixed Huffman code of a literal, length or end-of-block `symbol`
+fn literal(bits: &mut Bits, symbol: u16) {
+    let symbol = u32::from(symbol);
+    match symbol {
+        0..=143 => bits.push_code(0x30 + symbol, 8),
+        144..=255 => bits.push_code(0x190 + symbol - 144, 9),
+        256..=279 => bits.push_code(symbol - 256, 7),
+        _ => bits.push_code(0xc0 + symbol - 280, 8),
+    }
+}
+
+/// Bits packed from the least significant bit of each byte up, as deflate wants them
+#[derive(Default)]
+struct Bits {
+    bytes: Vec<u8>,
+    pending: u32,
+    count: u8,
+}
+
+impl Bits {
+    /// The low `count` bits of `value`, least significant first
+    fn push(&mut self, value: u32, count: u8) {
+        for bit in 0..count {
+            self.pending |= ((value >> bit) & 1) << self.count;
+            self.count += 1;
+            if self.count == 8 {
+                self.bytes.push(self.pending as u8);
+                self.pending = 0;
+                self.count = 0;
+            }
+        }
+    }
+
+    /// A Huffman code of `count` bits, which goes most significant bit first
+    fn push_code(&mut self, code: u32, count: u8) {
+        self.push(code.reverse_bits() >> (32 - u32::from(count)), count);
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        if self.count > 0 {
+            self.bytes.push(self.pending as u8);
+        }
+        self.bytes
+    }
+}
+
+/// CRC-32 (IEEE) as zip and gzip use it
+fn crc32(bytes: &[u8]) -> u32 {
+    !bytes.iter().fold(!0u32, |crc, byte| {
+        (0..8).fold(crc ^ u32::from(*byte), |crc, _| {
+            if crc & 1 == 1 { (crc >> 1) ^ 0xedb8_8320 } else { crc >> 1 }
+        })
+    })
+}
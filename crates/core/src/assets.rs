@@ -0,0 +1,75 @@
+//! The files the compiler reads at run time, embedded in the binary so it works on
+//! its own, on any machine and from any directory: the registry and its schema, the
+//! AST schema, the message catalogs, the output templates and the example gallery.
+//!
+//! Each asset has the path it has under `shared/` in the repository. Pointing
+//! [`ASSETS_ENV`] at a directory laid out the same way reads the assets there
+//! instead, those it has, so changes to them can be tried without rebuilding:
+//! `Z_ASSETS_DIR=shared cargo run -- main.z`. `z internals dump-assets` writes out
+//! the embedded ones, to start such a directory from or to see what a binary ships.
+
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::{Mutex, OnceLock};
+
+use crate::i18n::Locale;
+use crate::store::{create_dir, write_file};
+use crate::{gallery, hash, registry, templates};
+
+/// Environment variable naming a directory whose assets replace the embedded ones
+pub const ASSETS_ENV: &str = "Z_ASSETS_DIR";
+
+/// Every embedded asset, by path
+pub fn embedded() -> Vec<(String, &'static str)> {
+    let mut assets = vec![
+        ("registry.json".to_string(), registry::BUILT_IN),
+        ("registry.schema.json".to_string(), registry::SCHEMA),
+        ("ast.schema.json".to_string(), z_ast::format::JSON_SCHEMA),
+    ];
+    assets.extend(Locale::ALL.iter().map(|locale| (format!("locales/{}.ftl", locale.code()), locale.embedded())));
+    assets.extend(templates::BUILT_IN.iter().map(|(name, source)| (format!("templates/{}", name), *source)));
+    assets.extend(gallery::EXAMPLES.iter().map(|example| (format!("examples/{}.z", example.name), example.embedded)));
+    assets
+}
+
+/// The asset at `path`: the file in [`ASSETS_ENV`]'s directory if there is one, or else
+/// the embedded one
+pub fn get(path: &str) -> Option<&'static str> {
+    override_of(path).or_else(|| embedded().into_iter().find(|(embedded, _)| embedded == path).map(|(_, source)| source))
+}
+
+/// A digest of every asset as it's read, so outputs generated from other assets
+/// aren't taken for current
+pub fn fingerprint() -> &'static str {
+    static FINGERPRINT: OnceLock<String> = OnceLock::new();
+    FINGERPRINT.get_or_init(|| {
+        let assets: String = embedded().iter()
+            .map(|(path, _)| format!("{}\n{}\n", path, get(path).unwrap_or_default()))
+            .collect();
+        hash::sha256_hex(assets.as_bytes())
+    })
+}
+
+/// Write every embedded asset under `dir`, returning how many there are
+pub fn dump(dir: &Path) -> Result<usize, String> {
+    let assets = embedded();
+    for (path, source) in &assets {
+        let path = dir.join(path);
+        if let Some(parent) = path.parent() {
+            create_dir(parent)?;
+        }
+        write_file(&path, source)?;
+    }
+    Ok(assets.len())
+}
+
+/// The replacement of the asset at `path`, read once and kept for the rest of the run
+fn override_of(path: &str) -> Option<&'static str> {
+    static READ: OnceLock<Mutex<HashMap<String, Option<&'static str>>>> = OnceLock::new();
+    let dir = std::env::var_os(ASSETS_ENV).filter(|dir| !dir.is_empty())?;
+    let mut read = READ.get_or_init(Mutex::default).lock().unwrap_or_else(|e| e.into_inner());
+    *read.entry(path.to_string()).or_insert_with(|| {
+        let source = std::fs::read_to_string(Path::new(&dir).join(path)).ok()?;
+        Some(Box::leak(source.into_boxed_str()))
+    })
+}
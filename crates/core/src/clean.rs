@@ -0,0 +1,122 @@
+//! Removing what compiles generated, for `z clean`.
+//!
+//! Only the files listed in the `.z-manifest.json` of a target are removed, so
+//! files of your own in a generated project stay, and so do generated files edited since,
+//! unless asked otherwise. Dependencies and build products (`node_modules`, `target`,
+//! `.next`) are only removed when asked for. A cleaned target's key is forgotten, so the
+//! next compile generates it again; the rest of the compile cache, `.z-cache/`, goes too
+//! when asked for.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use z_semantic::{Program, Target};
+
+use crate::incremental::{self, CACHE_DIR};
+use crate::layout::Layout;
+use crate::report::REPORT_FILE;
+use crate::{emit, selects, tr};
+
+/// Directories of dependencies and build products in generated projects
+pub const DEPENDENCY_DIRS: &[&str] = &["node_modules", "target", ".next"];
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CleanOptions {
+    /// Targets to clean, by name or `kind:name`; all of them when empty
+    pub targets: Vec<String>,
+    /// Remove the dependencies and build products of the targets too
+    pub dependencies: bool,
+    /// Remove the whole compile cache too
+    pub cache: bool,
+    /// Remove generated files edited since they were generated too
+    pub overwrite: bool,
+}
+
+/// What cleaning removed
+#[derive(Debug, Default)]
+pub struct Cleaned {
+    pub targets: Vec<CleanedTarget>,
+    /// Whether the compile cache was removed
+    pub cache: bool,
+}
+
+#[derive(Debug)]
+pub struct CleanedTarget {
+    /// `kind:name`
+    pub target: String,
+    pub output_dir: PathBuf,
+    /// Generated files removed
+    pub removed: Vec<PathBuf>,
+    /// Generated files kept because they were edited since
+    pub kept: Vec<PathBuf>,
+    /// Dependency and build directories removed
+    pub dependencies: Vec<PathBuf>,
+}
+
+/// Remove what compiling `program` generated into `out_dir`, where `layout` put each
+/// target. Without targets named, the build report goes too, and `out_dir` if that
+/// leaves it empty.
+pub fn clean(program: &Program, out_dir: &Path, layout: &Layout, options: &CleanOptions) -> Result<Cleaned, String> {
+    let names: Vec<String> = program.targets.iter().map(Target::qualified_name).collect();
+    if let Some(name) = options.targets.iter().find(|name| !program.targets.iter().any(|target| selects(name, target))) {
+        return Err(tr!("clean-target-unknown", name = name, targets = names.join(", ")));
+    }
+
+    let mut cleaned = Cleaned::default();
+    let selected = program.targets.iter().filter(|target| options.targets.is_empty() || options.targets.iter().any(|name| selects(name, target)));
+    for target in selected {
+        let output_dir = out_dir.join(layout.dir(target)?);
+        let dependencies = match options.dependencies {
+            true => remove_dependencies(&output_dir)?,
+            false => Vec::new(),
+        };
+        let (removed, kept) = emit::remove(&output_dir, options.overwrite)?;
+        // Gone only if nothing of anyone else's is left in it
+        let _ = fs::remove_dir(&output_dir);
+        incremental::forget(out_dir, &target.qualified_name());
+        cleaned.targets.push(CleanedTarget { target: target.qualified_name(), output_dir, removed, kept, dependencies });
+    }
+
+    let cache = out_dir.join(CACHE_DIR);
+    if options.cache && cache.exists() {
+        fs::remove_dir_all(&cache).map_err(|e| tr!("remove-failed", path = cache.display(), error = e))?;
+        cleaned.cache = true;
+    }
+    remove_empty_dirs(&cache);
+    if options.targets.is_empty() {
+        let _ = fs::remove_file(out_dir.join(REPORT_FILE));
+        let _ = fs::remove_dir(out_dir);
+    }
+    Ok(cleaned)
+}
+
+/// Remove the [`DEPENDENCY_DIRS`] under `dir`, wherever they are, as Tauri's
+/// `src-tauri/target` is
+fn remove_dependencies(dir: &Path) -> Result<Vec<PathBuf>, String> {
+    let mut removed = Vec::new();
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Ok(removed);
+    };
+    let mut entries: Vec<_> = entries.flatten().filter(|entry| entry.file_type().is_ok_and(|kind| kind.is_dir())).collect();
+    entries.sort_by_key(|entry| entry.file_name());
+    for entry in entries {
+        let path = entry.path();
+        if DEPENDENCY_DIRS.iter().any(|name| entry.file_name() == *name) {
+            fs::remove_dir_all(&path).map_err(|e| tr!("remove-failed", path = path.display(), error = e))?;
+            removed.push(path);
+        } else {
+            removed.extend(remove_dependencies(&path)?);
+        }
+    }
+    Ok(removed)
+}
+
+/// Remove `dir` and the directories under it, as long as they hold no files
+fn remove_empty_dirs(dir: &Path) {
+    if let Ok(entries) = fs::read_dir(dir) {
+        for entry in entries.flatten().filter(|entry| entry.file_type().is_ok_and(|kind| kind.is_dir())) {
+            remove_empty_dirs(&entry.path());
+        }
+    }
+    let _ = fs::remove_dir(dir);
+}
@@ -0,0 +1,226 @@
+//! Adding blocks to a program, for `z add`.
+//!
+//! The file is parsed as written, with its comments and blank lines, the blocks are
+//! inserted into the tree, and the tree is printed back by [`z_ast::to_source`], so the
+//! file comes out [formatted](crate::formatter). A route is a path of nested routes:
+//! `/customers/[id]` adds `[id]` under `customers` in `Routes`, turning `customers` into
+//! a block if it was a line, and adding what's missing on the way.
+
+use serde_json::Value;
+use z_ast::{Element, ElementBuilder, LineBuilder, Node, TriviaPiece};
+
+use crate::{load_registry, tr, CompileOptions};
+
+/// Section routes go in
+const ROUTES: &str = "Routes";
+
+/// Section components go in
+const COMPONENTS: &str = "Components";
+
+/// Target type of a block that holds targets of its own
+const WORKSPACE: &str = "workspace";
+
+/// What to add to a program
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Addition {
+    /// `kind name {}`, after the rest of the program
+    Target { kind: String, name: String },
+    /// A route at `path`, e.g. `/pricing` or `/customers/[id]`, in the `Routes` of a target
+    Route { path: String, target: Option<String> },
+    /// A component in the `Components` of a target
+    Component { name: String, target: Option<String> },
+    /// An empty section, e.g. `API`, in a target
+    Section { name: String, target: Option<String> },
+}
+
+/// `source` with `addition` made. A target left out is the program's only one. Fails
+/// when the source doesn't parse, when what's added is there already, or when the
+/// registry of `options` has no such target type or the target takes no such section.
+pub fn add(source: &str, addition: &Addition, options: &CompileOptions) -> Result<String, String> {
+    let mut program = z_parser::parse_source_with(source, &options.limits)?;
+    let registry = load_registry(options);
+    match addition {
+        Addition::Target { kind, name } => {
+            let Some(known) = registry["targets"].as_object() else {
+                return Err(tr!("unknown-target", target = kind));
+            };
+            if !known.contains_key(kind) || kind == WORKSPACE {
+                let kinds: Vec<&str> = known.keys().map(String::as_str).filter(|known| *known != WORKSPACE).collect();
+                return Err(format!("{}. {}", tr!("unknown-target", target = kind), tr!("unknown-target-help", targets = kinds.join(", "))));
+            }
+            if targets(&program, &registry).iter().any(|target| target_name(target).1 == name) {
+                return Err(tr!("add-target-exists", name = name));
+            }
+            program.children.push(ElementBuilder::target(kind, name).into());
+        }
+        Addition::Route { path, target } => {
+            let segments: Vec<&str> = path.split('/').filter(|segment| !segment.is_empty()).collect();
+            if segments.is_empty() {
+                return Err(tr!("add-route-empty", path = path));
+            }
+            let routes = section(&mut program, &registry, target.as_deref(), ROUTES)?;
+            add_route(routes, &segments, path)?;
+        }
+        Addition::Component { name, target } => {
+            let components = section(&mut program, &registry, target.as_deref(), COMPONENTS)?;
+            if components.children.iter().any(|node| declares(node, name)) {
+                return Err(tr!("add-exists", what = name, target = COMPONENTS));
+            }
+            components.children.push(LineBuilder::new(name).into());
+        }
+        Addition::Section { name, target } => {
+            let target = find_target(&mut program, &registry, target.as_deref())?;
+            if target.children.iter().any(|node| declares(node, name)) {
+                return Err(tr!("add-exists", what = name, target = target_name(target).1));
+            }
+            allowed(target, &registry, name)?;
+            push_section(target, name);
+        }
+    }
+
+    let added = z_ast::to_source(&program);
+    z_parser::parse_source_with(&added, &options.limits)
+        .map_err(|e| format!("adding it would make the file unparsable ({}); it was left as it is", e))?;
+    Ok(added)
+}
+
+/// The target blocks of `program`, those of workspaces included
+fn targets<'a>(program: &'a Element, registry: &Value) -> Vec<&'a Element> {
+    let mut found = Vec::new();
+    for node in &program.children {
+        if let Node::Element(element) = node {
+            match target_name(element) {
+                (Some(WORKSPACE), _) => found.extend(targets(element, registry)),
+                (Some(kind), _) if registry["targets"].get(kind).is_some() || registry["targetAliases"].get(kind).is_some() => found.push(element),
+                _ => {}
+            }
+        }
+    }
+    found
+}
+
+/// `kind` and name of a block named `kind:name`
+fn target_name(element: &Element) -> (Option<&str>, &str) {
+    match element.name.split_once(':') {
+        Some((kind, name)) => (Some(kind), name),
+        None => (None, &element.name),
+    }
+}
+
+/// The target named `wanted`, by name or `kind:name`, or the only target if it's None
+fn find_target<'a>(program: &'a mut Element, registry: &Value, wanted: Option<&str>) -> Result<&'a mut Element, String> {
+    let names: Vec<String> = targets(program, registry).iter().map(|target| target.name.clone()).collect();
+    let index = match wanted {
+        Some(wanted) => names.iter().position(|name| name == wanted || target_name_of(name) == wanted),
+        None if names.len() == 1 => Some(0),
+        None if names.is_empty() => return Err(tr!("add-no-targets")),
+        None => return Err(tr!("add-target-ambiguous", targets = names.join(", "))),
+    };
+    let Some(index) = index else {
+        return Err(tr!("selected-target-unknown", name = wanted.unwrap_or_default(), targets = names.join(", ")));
+    };
+    Ok(target_mut(program, &names[index]).expect("the target was just found"))
+}
+
+fn target_name_of(name: &str) -> &str {
+    name.split_once(':').map_or(name, |(_, name)| name)
+}
+
+fn target_mut<'a>(parent: &'a mut Element, name: &str) -> Option<&'a mut Element> {
+    for node in &mut parent.children {
+        if let Node::Element(element) = node {
+            if element.name == name {
+                return Some(element);
+            }
+            if target_name(element).0 == Some(WORKSPACE) {
+                if let Some(found) = target_mut(element, name) {
+                    return Some(found);
+                }
+            }
+        }
+    }
+    None
+}
+
+/// The section `name` of a target, added if it has none yet
+fn section<'a>(program: &'a mut Element, registry: &Value, target: Option<&str>, name: &str) -> Result<&'a mut Element, String> {
+    let target = find_target(program, registry, target)?;
+    allowed(target, registry, name)?;
+    let index = match target.children.iter().position(|node| matches!(node, Node::Element(element) if element.name == name)) {
+        Some(index) => index,
+        None => {
+            push_section(target, name);
+            target.children.len() - 1
+        }
+    };
+    match &mut target.children[index] {
+        Node::Element(section) => Ok(section),
+        _ => unreachable!("the section is a block"),
+    }
+}
+
+/// Add an empty section `name` to `target`, after a blank line if it isn't the first
+fn push_section(target: &mut Element, name: &str) {
+    let mut section = ElementBuilder::new(name).build();
+    if !target.children.is_empty() {
+        section.trivia.leading.push(TriviaPiece::BlankLine);
+    }
+    target.children.push(section.into());
+}
+
+/// Fail if targets of the type of `target` take no section `name`
+fn allowed(target: &Element, registry: &Value, name: &str) -> Result<(), String> {
+    let (Some(kind), app) = target_name(target) else {
+        return Ok(());
+    };
+    let kind = registry["targetAliases"][kind].as_str().unwrap_or(kind);
+    let Some(sections) = registry["targets"][kind]["allowedChildren"].as_array() else {
+        return Ok(());
+    };
+    let sections: Vec<&str> = sections.iter().filter_map(Value::as_str).collect();
+    match sections.contains(&name) {
+        true => Ok(()),
+        false => Err(tr!("add-section-unsupported", section = name, target = app, kind = kind, sections = sections.join(", "))),
+    }
+}
+
+/// Whether `node` is the line or block `name`
+fn declares(node: &Node, name: &str) -> bool {
+    match node {
+        Node::Element(element) => element.name == name,
+        Node::ChildLine { id, .. } => id == name,
+        _ => false,
+    }
+}
+
+/// Add the route `segments` under `parent`
+fn add_route(parent: &mut Element, segments: &[&str], path: &str) -> Result<(), String> {
+    let (segment, rest) = segments.split_first().expect("a route has a segment");
+    let index = parent.children.iter().position(|node| declares(node, segment));
+    if rest.is_empty() {
+        return match index {
+            Some(_) => Err(tr!("add-exists", what = path, target = ROUTES)),
+            None => {
+                parent.children.push(LineBuilder::new(*segment).into());
+                Ok(())
+            }
+        };
+    }
+    let index = index.unwrap_or_else(|| {
+        parent.children.push(ElementBuilder::new(*segment).into());
+        parent.children.len() - 1
+    });
+    // A route with routes under it is a block
+    if let Node::ChildLine { modifier: None, id, signature, annotations, span, trivia } = &parent.children[index] {
+        let mut element = ElementBuilder::new(id.as_str()).build();
+        element.signature = signature.clone();
+        element.annotations = annotations.clone();
+        element.span = *span;
+        element.trivia = trivia.clone();
+        parent.children[index] = Node::Element(element);
+    }
+    match &mut parent.children[index] {
+        Node::Element(element) => add_route(element, rest, path),
+        _ => Err(tr!("add-exists", what = segment, target = ROUTES)),
+    }
+}
@@ -0,0 +1,105 @@
+//! The compiler as a library, for programs other than `z-cli` such as a web service or
+//! a GUI.
+//!
+//! ```no_run
+//! use z_compiler_core::{CompileOptions, Compiler, Progress};
+//!
+//! let options = CompileOptions::default().in_project("app".as_ref())?;
+//! let compiler = Compiler::new(options)
+//!     .output_dir("app/out")
+//!     .on_progress(|progress| if let Progress::Started(target) = progress {
+//!         println!("generating {}", target);
+//!     });
+//! let result = compiler.compile_file("app/main.z");
+//! for diagnostic in &result.diagnostics {
+//!     eprintln!("{}", diagnostic);
+//! }
+//! # Ok::<(), String>(())
+//! ```
+//!
+//! Nothing is printed: what happened comes back in the [`CompileResult`], and as
+//! [`Progress`] while it happens.
+
+use std::path::{Path, PathBuf};
+
+use crate::toml::{load_config, CONFIG_FILE};
+use crate::{
+    compile, compile_project, version, ArchiveFormat, CompileOptions, CompileResult, DependencyOverrides, Environments, Hooks, Layout, Plugins,
+    Progress, ProgressListener, RegistryLayers, TargetOptions, Templates,
+};
+
+/// Compiles programs with the same options into the same output directory
+#[derive(Debug, Clone)]
+pub struct Compiler {
+    options: CompileOptions,
+    output_dir: PathBuf,
+}
+
+impl Compiler {
+    /// A compiler generating into `out`, as `z-cli` does unless told otherwise
+    pub fn new(options: CompileOptions) -> Self {
+        Self { options, output_dir: PathBuf::from("out") }
+    }
+
+    /// Generate each target into a directory of its own under `dir`
+    pub fn output_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.output_dir = dir.into();
+        self
+    }
+
+    /// Pack the generated projects into one file of `format`, returned in
+    /// [`CompileResult::archive`], rather than write them into the output directory
+    pub fn archive(mut self, format: ArchiveFormat) -> Self {
+        self.options.archive = Some(format);
+        self
+    }
+
+    /// Keep the generated files in [`CompileResult::files`] rather than write them into the
+    /// output directory
+    pub fn in_memory(mut self) -> Self {
+        self.options.in_memory = true;
+        self
+    }
+
+    /// Call `listener` with each step of a compile as it happens, on the thread compiling
+    pub fn on_progress(mut self, listener: impl Fn(Progress) + Send + Sync + 'static) -> Self {
+        self.options.progress = Some(ProgressListener::new(listener));
+        self
+    }
+
+    pub fn options(&self) -> &CompileOptions {
+        &self.options
+    }
+
+    /// Compile `source`, which can't import other files
+    pub fn compile_str(&self, source: &str) -> CompileResult {
+        compile(source, &self.output_dir, &self.options)
+    }
+
+    /// Compile the program in `path` and the files it imports
+    pub fn compile_file(&self, path: impl AsRef<Path>) -> CompileResult {
+        compile_project(path.as_ref(), &self.output_dir, &self.options)
+    }
+}
+
+impl CompileOptions {
+    /// These options with what the project in `dir` sets, as `z-cli` reads it: its
+    /// `VERSION` file, its `z.toml`, and its registry and template overrides
+    pub fn in_project(self, dir: &Path) -> Result<Self, String> {
+        let config = load_config(dir)?;
+        let in_config = |e: String| format!("{}: {}", dir.join(CONFIG_FILE).display(), e);
+        Ok(Self {
+            source_dir: Some(dir.to_path_buf()),
+            version: version::read_version(dir)?,
+            dependencies: DependencyOverrides::from_config(&config).map_err(in_config)?,
+            target_options: TargetOptions::from_config(&config).map_err(in_config)?,
+            hooks: Hooks::from_config(&config).map_err(in_config)?,
+            layout: Layout::from_config(&config).map_err(in_config)?,
+            environments: Environments::from_config(&config).map_err(in_config)?,
+            plugins: Plugins::from_config(&config, dir)?,
+            registry: RegistryLayers::load(dir)?,
+            templates: Templates::load(dir)?,
+            ..self
+        })
+    }
+}
@@ -0,0 +1,530 @@
+//! Fresh projects for Deno.
+//!
+//! Pages come from the `Routes` section, request handlers from `API`, Preact
+//! components from `Components`, and the models in `Schema` get a typed Deno KV
+//! store in `lib/db.ts`. An API route with `@model(Name)` serves that model from KV.
+
+use std::path::Path;
+
+use serde_json::json;
+use z_semantic::{Annotated, Component, Endpoint, Model, Namespace, Profile, Route, SectionBody, Target};
+
+use super::output::Output;
+use super::{base_type, is_stored, key, pascal, plural, ts_access, ts_property, Capabilities, Outputs, TargetCompiler};
+use crate::naming::{Naming, Role};
+use crate::sink::FileSink;
+use crate::style::CodeStyle;
+use crate::target_options::CompilerOptions;
+use crate::templates::{context, Templates};
+
+/// Fresh and the libraries its project template pins
+const FRESH: &str = "https://deno.land/x/fresh@1.7.3/";
+const PREACT: &str = "10.22.0";
+const STD: &str = "https://deno.land/std@0.216.0/";
+const TAILWIND: &str = "3.4.1";
+
+pub struct DenoCompiler {
+    style: CodeStyle,
+    templates: Templates,
+}
+
+impl DenoCompiler {
+    pub fn new(style: CodeStyle, templates: Templates) -> Self {
+        Self { style, templates }
+    }
+}
+
+impl TargetCompiler for DenoCompiler {
+    fn compile(&self, _target: &Target, _options: &CompilerOptions) -> Result<String, String> {
+        Ok("Fresh project files generated successfully".to_string())
+    }
+
+    fn target_name(&self) -> &str {
+        "Deno"
+    }
+
+    fn file_extension(&self) -> &str {
+        "ts"
+    }
+
+    fn compile_to_directory(&self, target: &Target, options: &CompilerOptions, sink: &dyn FileSink) -> Option<Result<(), String>> {
+        Some(self.create_fresh_project(target, options, sink))
+    }
+
+    fn capabilities(&self, _options: &CompilerOptions) -> Option<Capabilities> {
+        Some(Capabilities::sections([Namespace::Routes, Namespace::Api, Namespace::Components, Namespace::Schema]))
+    }
+
+    fn outputs(&self, _options: &CompilerOptions) -> Outputs {
+        // Fresh renders on the server, so there are no static files to hand over
+        Outputs { dist: None, dev_url: Some("http://localhost:8000".to_string()) }
+    }
+}
+
+/// The parts of a target the generated files are made from
+struct Project<'a> {
+    target: &'a Target,
+    routes: Vec<&'a Route>,
+    endpoints: Vec<&'a Endpoint>,
+    components: Vec<&'a Component>,
+    models: Vec<&'a Model>,
+    naming: Naming,
+}
+
+impl<'a> Project<'a> {
+    fn new(target: &'a Target, naming: Naming) -> Self {
+        let mut project = Project { target, routes: Vec::new(), endpoints: Vec::new(), components: Vec::new(), models: Vec::new(), naming };
+        for section in &target.sections {
+            match &section.body {
+                SectionBody::Routes(routes) => project.routes.extend(routes),
+                SectionBody::Endpoints(endpoints) => project.endpoints.extend(endpoints),
+                SectionBody::Components(components) => project.components.extend(components),
+                SectionBody::Models(models) => project.models.extend(models),
+                _ => {}
+            }
+        }
+        project
+    }
+
+    fn stored(&self) -> impl Iterator<Item = &'a Model> + '_ {
+        self.models.iter().copied().filter(|model| is_stored(model))
+    }
+
+    fn model(&self, name: &str) -> Option<&'a Model> {
+        self.stored().find(|model| model.name == name)
+    }
+}
+
+impl DenoCompiler {
+    fn create_fresh_project(&self, target: &Target, options: &CompilerOptions, sink: &dyn FileSink) -> Result<(), String> {
+        let mut out = Output::new(sink, &self.style);
+        self.create_files(target, Naming::of(&target.kind, options)?, &mut out)?;
+        out.write()
+    }
+
+    fn create_files(&self, target: &Target, naming: Naming, out: &mut Output) -> Result<(), String> {
+        let project = Project::new(target, naming);
+        let profile = target.profile;
+        // Every module under routes/, for the manifest
+        let mut modules = vec!["routes/_app.tsx".to_string(), "routes/index.tsx".to_string()];
+
+        let context = context(target);
+        out.file("deno.json", deno_json(&project));
+        out.file("main.ts", self.templates.render("deno/main.ts", &context)?);
+        out.file("dev.ts", self.templates.render("deno/dev.ts", &context)?);
+        out.file("fresh.config.ts", if profile >= Profile::Standard { TAILWIND_CONFIG } else { PLAIN_CONFIG });
+        if profile >= Profile::Standard {
+            out.file("tailwind.config.ts", TAILWIND_CONFIG_TS);
+        }
+        out.file("static/styles.css", if profile >= Profile::Standard { TAILWIND_CSS } else { PLAIN_CSS });
+
+        out.file("routes/_app.tsx", self.app_tsx(target)?);
+        out.file("routes/index.tsx", index_tsx(&project, profile));
+        if profile >= Profile::Full {
+            out.file("routes/_404.tsx", self.templates.render("deno/_404.tsx", &context)?);
+            modules.push("routes/_404.tsx".to_string());
+        }
+
+        for route in &project.routes {
+            self.create_route(out, route, "", &project, &mut modules);
+        }
+
+        // Methods of the same path share a handler module
+        let mut paths: Vec<&str> = Vec::new();
+        for endpoint in &project.endpoints {
+            if !paths.contains(&endpoint.name.as_str()) {
+                paths.push(&endpoint.name);
+            }
+        }
+        for path in paths {
+            let endpoints: Vec<&Endpoint> = project.endpoints.iter().copied().filter(|endpoint| endpoint.name == path).collect();
+            let name = format!("routes/api/{}.ts", path);
+            out.file(name.clone(), handler_ts(&name, &endpoints, &project));
+            modules.push(name);
+        }
+
+        for component in &project.components {
+            out.file(format!("components/{}.tsx", project.naming.name(Role::Component, &component.name)), component_tsx(component));
+        }
+        if project.stored().next().is_some() || project.models.iter().any(|model| model.kind.as_deref() == Some("enum")) {
+            out.file("lib/db.ts", db_ts(&project));
+        }
+
+        out.file("fresh.gen.ts", manifest(&modules));
+        Ok(())
+    }
+
+    fn create_route(
+        &self,
+        out: &mut Output,
+        route: &Route,
+        parent: &str,
+        project: &Project,
+        modules: &mut Vec<String>,
+    ) {
+        let directory = if route.dynamic {
+            format!("{}/[{}]", parent, route.segment)
+        } else {
+            format!("{}/{}", parent, project.naming.name(Role::Route, &route.segment))
+        };
+
+        let name = format!("routes{}/index.tsx", directory);
+        out.file(name.clone(), route_tsx(route, &directory, project));
+        modules.push(name);
+
+        for child in &route.children {
+            self.create_route(out, child, &directory, project, modules);
+        }
+    }
+
+    fn app_tsx(&self, target: &Target) -> Result<String, String> {
+        // The icon from the app's `Meta` block is copied into static/
+        let mut context = context(target);
+        if let Some(extension) = target.meta.as_ref().and_then(|meta| meta.icon.as_deref()).and_then(|icon| Path::new(icon).extension()) {
+            context["favicon"] = json!(format!("/favicon.{}", extension.to_string_lossy().to_lowercase()));
+        }
+        self.templates.render("deno/_app.tsx", &context)
+    }
+}
+
+fn deno_json(project: &Project) -> String {
+    let profile = project.target.profile;
+    let mut tasks = vec![
+        ("start", "deno run -A --watch=static/,routes/ dev.ts".to_string()),
+        ("build", "deno run -A dev.ts build".to_string()),
+        ("preview", "deno run -A main.ts".to_string()),
+        ("update", "deno run -A -r https://fresh.deno.dev/update .".to_string()),
+    ];
+    if profile >= Profile::Full {
+        tasks.insert(0, ("check", "deno fmt --check && deno lint && deno check **/*.ts && deno check **/*.tsx".to_string()));
+    }
+    let mut imports = vec![
+        ("$fresh/", FRESH.to_string()),
+        ("preact", format!("https://esm.sh/preact@{}", PREACT)),
+        ("preact/", format!("https://esm.sh/preact@{}/", PREACT)),
+        ("@preact/signals", "https://esm.sh/*@preact/signals@1.2.2".to_string()),
+        ("@preact/signals-core", "https://esm.sh/*@preact/signals-core@1.5.1".to_string()),
+        ("$std/", STD.to_string()),
+    ];
+    if profile >= Profile::Standard {
+        imports.extend([
+            ("tailwindcss", format!("npm:tailwindcss@{}", TAILWIND)),
+            ("tailwindcss/", format!("npm:/tailwindcss@{}/", TAILWIND)),
+            ("tailwindcss/plugin", format!("npm:/tailwindcss@{}/plugin.js", TAILWIND)),
+        ]);
+    }
+
+    let mut json = String::from("{\n");
+    if let Some(version) = project.target.meta.as_ref().and_then(|meta| meta.version.as_deref()) {
+        json.push_str(&format!("  \"version\": \"{}\",\n", version));
+    }
+    json.push_str("  \"lock\": false,\n");
+    json.push_str(&format!("  \"tasks\": {{\n{}\n  }},\n", entries(&tasks)));
+    if profile >= Profile::Full {
+        json.push_str("  \"lint\": {\n    \"rules\": {\n      \"tags\": [\"fresh\", \"recommended\"]\n    }\n  },\n");
+    }
+    json.push_str("  \"exclude\": [\"**/_fresh/*\"],\n");
+    json.push_str(&format!("  \"imports\": {{\n{}\n  }},\n", entries(&imports)));
+    // Deno Deploy has KV built in; the CLI still keeps it behind a flag
+    if project.stored().next().is_some() {
+        json.push_str("  \"unstable\": [\"kv\"],\n");
+    }
+    json.push_str("  \"compilerOptions\": {\n    \"jsx\": \"react-jsx\",\n    \"jsxImportSource\": \"preact\"\n  }\n}\n");
+    json
+}
+
+/// `"key": "value"` lines for the body of a JSON object
+fn entries(entries: &[(&str, String)]) -> String {
+    entries.iter()
+        .map(|(key, value)| format!("    \"{}\": \"{}\"", key, value))
+        .collect::<Vec<_>>()
+        .join(",\n")
+}
+
+/// `fresh.gen.ts`, which Fresh rewrites on `deno task start` and `build` but which
+/// `main.ts` needs before either has run
+fn manifest(modules: &[String]) -> String {
+    let mut manifest = String::from("// DO NOT EDIT. This file is generated by Fresh.\n// This file SHOULD be checked into source version control.\n\n");
+    for (i, module) in modules.iter().enumerate() {
+        manifest.push_str(&format!("import * as $routes{} from \"./{}\";\n", i, module));
+    }
+    manifest.push_str("\nimport { type Manifest } from \"$fresh/server.ts\";\n\nconst manifest = {\n  routes: {\n");
+    for (i, module) in modules.iter().enumerate() {
+        manifest.push_str(&format!("    \"./{}\": $routes{},\n", module, i));
+    }
+    manifest.push_str("  },\n  islands: {},\n  baseUrl: import.meta.url,\n} satisfies Manifest;\n\nexport default manifest;\n");
+    manifest
+}
+
+/// Home page linking to every route without dynamic segments
+fn index_tsx(project: &Project, profile: Profile) -> String {
+    fn links(routes: &[Route], parent: &str, naming: &Naming, out: &mut Vec<String>) {
+        for route in routes.iter().filter(|route| !route.dynamic) {
+            let path = format!("{}/{}", parent, naming.name(Role::Route, &route.segment));
+            out.push(path.clone());
+            links(&route.children, &path, naming, out);
+        }
+    }
+    let mut paths = Vec::new();
+    for route in &project.routes {
+        links(std::slice::from_ref(*route), "", &project.naming, &mut paths);
+    }
+
+    let (main, h1) = if profile >= Profile::Standard {
+        (" class=\"mx-auto max-w-screen-md px-4 py-8\"", " class=\"text-4xl font-bold\"")
+    } else {
+        ("", "")
+    };
+    let mut page = String::from("export default function Home() {\n  return (\n");
+    page.push_str(&format!("    <main{}>\n", main));
+    page.push_str(&format!("      <h1{}>{}</h1>\n", h1, project.target.name));
+    if !paths.is_empty() {
+        page.push_str("      <ul>\n");
+        for path in paths {
+            page.push_str(&format!("        <li>\n          <a href=\"{0}\">{0}</a>\n        </li>\n", path));
+        }
+        page.push_str("      </ul>\n");
+    }
+    page.push_str("    </main>\n  );\n}\n");
+    page
+}
+
+/// The page of the route at `directory` (`/todos/[id]`), rendering its
+/// `@component` when it has one
+fn route_tsx(route: &Route, directory: &str, project: &Project) -> String {
+    let component = route.annotation("component")
+        .and_then(|annotation| annotation.args.as_deref())
+        .map(str::trim)
+        .filter(|name| project.components.iter().any(|component| component.name == *name));
+    let depth = directory.matches('/').count() + 1;
+
+    let mut page = String::from("import { type PageProps } from \"$fresh/server.ts\";\n");
+    if let Some(component) = component {
+        let file = project.naming.name(Role::Component, component);
+        page.push_str(&format!("import {} from \"{}components/{}.tsx\";\n", pascal(component), "../".repeat(depth), file));
+    }
+
+    let mut heading = String::new();
+    for segment in directory.split('/').skip(1) {
+        heading.push('/');
+        match segment.strip_prefix('[').and_then(|segment| segment.strip_suffix(']')) {
+            Some(param) => {
+                heading.push_str(&format!("{{props.params{}}}", ts_access(param)));
+            }
+            None => heading.push_str(segment),
+        }
+    }
+
+    page.push_str("\nexport default function Page(props: PageProps) {\n  return (\n    <main>\n");
+    page.push_str(&format!("      <h1>{}</h1>\n", heading));
+    if let Some(component) = component {
+        page.push_str(&format!("      <{} />\n", pascal(component)));
+    }
+    page.push_str("    </main>\n  );\n}\n");
+    page
+}
+
+/// Handlers for the API routes at one path. A route with `@model(Name)` reads and
+/// writes that model in KV; others answer 501 until they're implemented.
+fn handler_ts(name: &str, endpoints: &[&Endpoint], project: &Project) -> String {
+    let depth = name.matches('/').count();
+
+    let mut imports = Vec::new();
+    let mut methods = Vec::new();
+    for endpoint in endpoints {
+        let method = endpoint.method.as_deref().unwrap_or("get").to_ascii_uppercase();
+        let model = endpoint.annotation("model")
+            .and_then(|annotation| annotation.args.as_deref())
+            .and_then(|model| project.model(model.trim()));
+        let body = match (method.as_str(), model) {
+            ("GET", Some(model)) => {
+                imports.push(format!("list{}", plural(&model.name)));
+                format!("    return Response.json(await list{}());", plural(&model.name))
+            }
+            ("POST" | "PUT" | "PATCH", Some(model)) => {
+                imports.extend([format!("save{}", model.name), format!("type {}", model.name)]);
+                let (key, key_type) = key(model);
+                let value = if key_type == "string" && method == "POST" {
+                    format!("{{ {}: crypto.randomUUID(), ...(await req.json()) }}", ts_property(&key))
+                } else {
+                    "await req.json()".to_string()
+                };
+                let status = if method == "POST" { ", { status: 201 }" } else { "" };
+                format!(
+                    "    const {0}: {1} = {2};\n    await save{1}({0});\n    return Response.json({0}{3});",
+                    project.naming.name(Role::Field, &model.name), model.name, value, status,
+                )
+            }
+            ("DELETE", Some(model)) => {
+                imports.push(format!("delete{}", model.name));
+                let (key, key_type) = key(model);
+                let id = if key_type == "number" { "Number(id)" } else { "id" };
+                format!(
+                    "    const id = new URL(req.url).searchParams.get(\"{}\");\n    if (id === null) {{\n      return new Response(\"Missing {}\", {{ status: 400 }});\n    }}\n    await delete{}({});\n    return new Response(null, {{ status: 204 }});",
+                    key, key, model.name, id,
+                )
+            }
+            _ => "    return new Response(\"Not implemented\", { status: 501 });".to_string(),
+        };
+        let uses_request = body.contains("req");
+        let asynchronous = if body.contains("await") { "async " } else { "" };
+        methods.push(format!("  {}{}({}) {{\n{}\n  }},", asynchronous, method, if uses_request { "req" } else { "_req" }, body));
+    }
+    imports.sort();
+    imports.dedup();
+
+    let mut handler = String::from("import { type Handlers } from \"$fresh/server.ts\";\n");
+    if !imports.is_empty() {
+        handler.push_str(&format!("import {{ {} }} from \"{}lib/db.ts\";\n", imports.join(", "), "../".repeat(depth)));
+    }
+    handler.push_str(&format!("\nexport const handler: Handlers = {{\n{}\n}};\n", methods.join("\n")));
+    handler
+}
+
+/// The component, named in PascalCase as JSX takes it, whatever its file is called
+fn component_tsx(component: &Component) -> String {
+    let name = pascal(&component.name);
+    let props = component.props.as_deref()
+        .map(|props| props.trim().trim_start_matches('(').trim_end_matches(')'))
+        .filter(|props| !props.trim().is_empty());
+    match props {
+        Some(props) => {
+            let fields = props.split(',').map(str::trim).filter(|field| !field.is_empty()).collect::<Vec<_>>().join(";\n  ");
+            format!(
+                "export interface {0}Props {{\n  {1};\n}}\n\nexport default function {0}(props: {0}Props) {{\n  return <div>{0}</div>;\n}}\n",
+                name, fields,
+            )
+        }
+        None => format!("export default function {0}() {{\n  return <div>{0}</div>;\n}}\n", name),
+    }
+}
+
+/// Interfaces for the schema and, for every stored model, functions that keep it in
+/// Deno KV under `[Name, key]`
+fn db_ts(project: &Project) -> String {
+    let mut db = String::new();
+    if project.stored().next().is_some() {
+        db.push_str("/// <reference lib=\"deno.unstable\" />\n\nexport const kv = await Deno.openKv();\n");
+    }
+    for model in &project.models {
+        db.push('\n');
+        if model.kind.as_deref() == Some("enum") {
+            let values = model.values.iter().map(|value| format!("\"{}\"", value)).collect::<Vec<_>>();
+            let values = if values.is_empty() { "never".to_string() } else { values.join(" | ") };
+            db.push_str(&format!("export type {} = {};\n", model.name, values));
+            continue;
+        }
+
+        db.push_str(&format!("export interface {} {{\n", model.name));
+        if is_stored(model) && !model.fields.iter().any(|field| field.name == key(model).0) {
+            db.push_str("  id: string;\n");
+        }
+        for field in &model.fields {
+            let (ty, optional) = match field.ty.trim().strip_suffix('?') {
+                Some(ty) => (ty, "?"),
+                None => (field.ty.trim(), ""),
+            };
+            db.push_str(&format!("  {}{}: {};\n", ts_property(&field.name), optional, ts_type(ty, project)));
+        }
+        db.push_str("}\n");
+
+        if is_stored(model) {
+            let (key, key_type) = key(model);
+            let name = &model.name;
+            db.push_str(&format!(r#"
+export async function list{plural}(): Promise<{name}[]> {{
+  const {values}: {name}[] = [];
+  for await (const entry of kv.list<{name}>({{ prefix: ["{name}"] }})) {{
+    {values}.push(entry.value);
+  }}
+  return {values};
+}}
+
+export async function get{name}({key}: {key_type}): Promise<{name} | null> {{
+  return (await kv.get<{name}>(["{name}", {key}])).value;
+}}
+
+export async function save{name}({value}: {name}): Promise<void> {{
+  await kv.set(["{name}", {value}{access}], {value});
+}}
+
+export async function delete{name}({key}: {key_type}): Promise<void> {{
+  await kv.delete(["{name}", {key}]);
+}}
+"#,
+                plural = plural(name),
+                values = project.naming.name(Role::Field, &plural(name)),
+                value = project.naming.name(Role::Field, name),
+                key = project.naming.name(Role::Field, &key),
+                access = ts_access(&key),
+            ));
+        }
+    }
+    db
+}
+
+/// A schema field type in TypeScript: built-in types map to their TypeScript
+/// counterparts, model and enum names stay, and anything else is `unknown`
+fn ts_type(ty: &str, project: &Project) -> String {
+    if ty.contains('|') {
+        return ty.split('|').map(|part| ts_type(part.trim(), project)).collect::<Vec<_>>().join(" | ");
+    }
+    if let Some(element) = ty.strip_suffix("[]") {
+        return format!("{}[]", ts_type(element.trim(), project));
+    }
+    match base_type(ty) {
+        "string" | "text" | "uuid" | "email" | "url" | "cuid" => "string".to_string(),
+        "int" | "integer" | "number" | "float" | "double" | "decimal" | "bigint" | "serial" => "number".to_string(),
+        "bool" | "boolean" => "boolean".to_string(),
+        "date" | "datetime" | "timestamp" => "Date".to_string(),
+        "null" => "null".to_string(),
+        name if project.models.iter().any(|model| model.name == name) => name.to_string(),
+        _ => "unknown".to_string(),
+    }
+}
+
+const PLAIN_CONFIG: &str = r#"import { defineConfig } from "$fresh/server.ts";
+
+export default defineConfig({});
+"#;
+
+const TAILWIND_CONFIG: &str = r#"import { defineConfig } from "$fresh/server.ts";
+import tailwind from "$fresh/plugins/tailwind.ts";
+
+export default defineConfig({
+  plugins: [tailwind()],
+});
+"#;
+
+const TAILWIND_CONFIG_TS: &str = r#"import { type Config } from "tailwindcss";
+
+export default {
+  content: [
+    "{routes,islands,components}/**/*.{ts,tsx}",
+  ],
+} satisfies Config;
+"#;
+
+const TAILWIND_CSS: &str = r#"@tailwind base;
+@tailwind components;
+@tailwind utilities;
+"#;
+
+const PLAIN_CSS: &str = r#"*,
+*::before,
+*::after {
+  box-sizing: border-box;
+}
+
+body {
+  margin: 0;
+  font-family: system-ui, sans-serif;
+  line-height: 1.5;
+}
+
+main {
+  max-width: 48rem;
+  margin: 0 auto;
+  padding: 2rem 1rem;
+}
+"#;
@@ -1,14 +1,28 @@
+pub mod deno;
+pub(crate) mod output;
 pub mod nextjs;
 pub mod swiftui;
 pub mod rust;
 pub mod tauri;
+mod supabase;
+mod trpc;
 
-use z_ast::Element;
+use std::fs;
+use std::path::Path;
+
+use z_semantic::{Model, Namespace, Target};
+
+use crate::naming::Case;
+use crate::sink::FileSink;
+use crate::style::CodeStyle;
+use crate::target_options::CompilerOptions;
+use crate::templates::Templates;
+use crate::tr;
 
 /// Trait that all target compilers must implement
 pub trait TargetCompiler {
-    /// Generate code for the given AST element
-    fn compile(&self, ast: &Element) -> Result<String, String>;
+    /// Generate code for the given target, with the options it's compiled with
+    fn compile(&self, target: &Target, options: &CompilerOptions) -> Result<String, String>;
 
     /// Get the target name this compiler handles
     fn target_name(&self) -> &str;
@@ -16,20 +30,146 @@ pub trait TargetCompiler {
     /// Get the file extension for generated files
     fn file_extension(&self) -> &str;
 
-    /// Compile directly to a directory (for complex project structures)
-    /// Returns None if the compiler doesn't support directory compilation
-    fn compile_to_directory(&self, _ast: &Element, _output_dir: &std::path::Path) -> Option<Result<(), String>> {
+    /// Compile into a whole project (for complex project structures), writing its files
+    /// to `sink`. Returns None if the compiler doesn't support directory compilation
+    fn compile_to_directory(&self, _target: &Target, _options: &CompilerOptions, _sink: &dyn FileSink) -> Option<Result<(), String>> {
+        None
+    }
+
+    /// What of a target compiled with `options` this compiler generates code from, so
+    /// core can warn about the rest. None if it doesn't say
+    fn capabilities(&self, _options: &CompilerOptions) -> Option<Capabilities> {
         None
     }
+
+    /// What the project of a target compiled with `options` gives the targets that
+    /// [use](z_semantic::Target::uses) it
+    fn outputs(&self, _options: &CompilerOptions) -> Outputs {
+        Outputs::default()
+    }
+}
+
+/// What of a target a compiler generates code from
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Capabilities {
+    /// Namespaces of the sections it reads, e.g. `Routes`
+    pub sections: Vec<Namespace>,
+    /// Whether it reads the lines directly inside the target, outside any section
+    pub declarations: bool,
+}
+
+impl Capabilities {
+    /// Reads the sections `namespaces` and nothing else
+    pub fn sections(namespaces: impl IntoIterator<Item = Namespace>) -> Self {
+        Self { sections: namespaces.into_iter().collect(), declarations: false }
+    }
+}
+
+/// What a generated project serves, for the targets built from it
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Outputs {
+    /// Where its `build` script writes the static files, relative to the project, e.g. `out`
+    pub dist: Option<String>,
+    /// Where its `dev` script serves the app, e.g. `http://localhost:3000`
+    pub dev_url: Option<String>,
 }
 
 /// Factory function to get the appropriate compiler for a target
-pub fn get_compiler(target: &str) -> Option<Box<dyn TargetCompiler>> {
+pub fn get_compiler(target: &str, style: &CodeStyle, templates: &Templates) -> Option<Box<dyn TargetCompiler>> {
+    let style = style.clone();
+    let templates = templates.clone();
     match target {
-        "next" => Some(Box::new(nextjs::NextJSCompiler::new())),
-        "swift" => Some(Box::new(swiftui::SwiftUICompiler::new())),
-        "rust" => Some(Box::new(rust::RustCompiler::new())),
-        "tauri" => Some(Box::new(tauri::TauriCompiler::new())),
+        "next" => Some(Box::new(nextjs::NextJSCompiler::new(style, templates))),
+        "deno" => Some(Box::new(deno::DenoCompiler::new(style, templates))),
+        // Single-file output is written (and styled) by core
+        "swift" => Some(Box::new(swiftui::SwiftUICompiler::new(templates))),
+        "rust" => Some(Box::new(rust::RustCompiler::new(style, templates))),
+        "tauri" => Some(Box::new(tauri::TauriCompiler::new(style, templates))),
         _ => None,
     }
-}
\ No newline at end of file
+}
+
+/// The error for `tool` having exited unsuccessfully with `output`: what it printed to
+/// stderr, if anything
+pub(crate) fn tool_failed(tool: &str, output: &std::process::Output) -> String {
+    match String::from_utf8_lossy(&output.stderr).trim() {
+        "" => tr!("tool-failed", tool = tool),
+        stderr => tr!("tool-exec-failed", tool = tool, error = stderr),
+    }
+}
+
+/// Write a generated source file, restyled according to the configured code style
+pub(crate) fn write_source(path: &Path, contents: &str, style: &CodeStyle) -> std::io::Result<()> {
+    fs::write(path, style.apply(path, contents))
+}
+
+/// `name` as an npm or crate package name: `Notes App` and `notesApp` become `notes-app`
+pub(crate) fn package_name(name: &str) -> String {
+    Case::Kebab.apply(name)
+}
+
+/// `order_item` for `OrderItem` or `orderItem`
+pub(crate) fn snake(name: &str) -> String {
+    Case::Snake.apply(name)
+}
+
+/// `text` as a quoted JavaScript string
+pub(crate) fn js_string(text: &str) -> String {
+    serde_json::Value::String(text.to_string()).to_string()
+}
+
+/// `name` as a TypeScript property key; route segments may be hyphenated
+pub(crate) fn ts_property(name: &str) -> String {
+    if name.contains('-') { format!("\"{}\"", name) } else { name.to_string() }
+}
+
+/// `.name` or `["name"]`, to read the property `name`
+pub(crate) fn ts_access(name: &str) -> String {
+    if name.contains('-') { format!("[\"{}\"]", name) } else { format!(".{}", name) }
+}
+
+/// Models that are stored; enums and plain types are only types
+pub(crate) fn is_stored(model: &Model) -> bool {
+    !matches!(model.kind.as_deref(), Some("enum" | "type"))
+}
+
+/// The key field of a stored model, `@primary` or else `id`, and its TypeScript type
+pub(crate) fn key(model: &Model) -> (String, &'static str) {
+    let field = model.fields.iter()
+        .find(|field| field.annotations.iter().any(|annotation| annotation.name == "primary"))
+        .or_else(|| model.fields.iter().find(|field| field.name == "id"));
+    match field {
+        Some(field) => {
+            let numeric = matches!(base_type(field.ty.trim()), "int" | "integer" | "number" | "float" | "bigint" | "serial");
+            (field.name.clone(), if numeric { "number" } else { "string" })
+        }
+        None => ("id".to_string(), "string"),
+    }
+}
+
+/// `varchar` for `varchar(255)`
+pub(crate) fn base_type(ty: &str) -> &str {
+    ty.split('(').next().unwrap_or(ty).trim()
+}
+
+/// English plural, good enough for model names
+pub(crate) fn plural(name: &str) -> String {
+    let consonant_y = name.strip_suffix('y').filter(|stem| !stem.ends_with(['a', 'e', 'i', 'o', 'u']));
+    if let Some(stem) = consonant_y {
+        format!("{}ies", stem)
+    } else if name.ends_with(['s', 'x', 'z']) || name.ends_with("ch") || name.ends_with("sh") {
+        format!("{}es", name)
+    } else {
+        format!("{}s", name)
+    }
+}
+
+/// `orderItem` for `OrderItem` or `order_item`
+pub(crate) fn camel(name: &str) -> String {
+    Case::Camel.apply(name)
+}
+
+/// `Users` for `users`, `Id` for `[id]`, `OrderItems` for `order-items`
+pub(crate) fn pascal(segment: &str) -> String {
+    Case::Pascal.apply(segment)
+}
@@ -1,18 +1,94 @@
-use z_ast::{Element, Node};
-use super::TargetCompiler;
-use std::fs;
-use std::path::Path;
+use z_semantic::{Namespace, Profile, Route, Section, SectionBody, Target};
+use super::output::Output;
+use super::{js_string, supabase, trpc, ts_access, ts_property, Capabilities, Outputs, TargetCompiler};
+use crate::naming::{Naming, Role};
+use crate::runtime::Runtime;
+use crate::sink::FileSink;
+use crate::style::CodeStyle;
+use crate::target_options::CompilerOptions;
+use crate::templates::{context, Templates};
+use serde_json::json;
+
+pub struct NextJSCompiler {
+    style: CodeStyle,
+    templates: Templates,
+}
+
+/// Next.js generation to write the project for, from the `framework` option
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+enum Framework {
+    /// Next.js 14 on React 18
+    #[default]
+    Next14,
+    /// Next.js 15 on React 19: request APIs are async, fetches and client router
+    /// visits aren't cached by default, and `ref` is a regular prop
+    Next15,
+}
+
+impl std::str::FromStr for Framework {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "next14" => Ok(Framework::Next14),
+            "next15" => Ok(Framework::Next15),
+            _ => Err(format!("invalid framework '{}' (expected 'next14' or 'next15')", s)),
+        }
+    }
+}
 
-pub struct NextJSCompiler;
+/// How the `API` section is served, from the `api` option
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+enum Api {
+    /// Route handlers under `app/api`, left to be written by hand
+    #[default]
+    Rest,
+    /// A tRPC router with React Query hooks for the client (see the `trpc` module)
+    Trpc,
+}
+
+impl std::str::FromStr for Api {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "rest" => Ok(Api::Rest),
+            "trpc" => Ok(Api::Trpc),
+            _ => Err(format!("invalid api '{}' (expected 'rest' or 'trpc')", s)),
+        }
+    }
+}
+
+/// What serves the app's data, from the `backend` option
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+enum Backend {
+    /// The Next.js server, through the `API` section
+    #[default]
+    Server,
+    /// Supabase, called from the app directly (see the `supabase` module)
+    Supabase,
+}
+
+impl std::str::FromStr for Backend {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "server" => Ok(Backend::Server),
+            "supabase" => Ok(Backend::Supabase),
+            _ => Err(format!("invalid backend '{}' (expected 'server' or 'supabase')", s)),
+        }
+    }
+}
 
 impl NextJSCompiler {
-    pub fn new() -> Self {
-        Self
+    pub fn new(style: CodeStyle, templates: Templates) -> Self {
+        Self { style, templates }
     }
 }
 
 impl TargetCompiler for NextJSCompiler {
-    fn compile(&self, ast: &Element) -> Result<String, String> {
+    fn compile(&self, _target: &Target, _options: &CompilerOptions) -> Result<String, String> {
         // This method now just returns a summary, actual file creation happens in compile_to_directory
         Ok("Next.js project files generated successfully".to_string())
     }
@@ -25,109 +101,235 @@ impl TargetCompiler for NextJSCompiler {
         "tsx"
     }
 
-    fn compile_to_directory(&self, ast: &Element, output_dir: &std::path::Path) -> Option<Result<(), String>> {
-        Some(self.create_nextjs_project(ast, output_dir))
+    fn compile_to_directory(&self, target: &Target, options: &CompilerOptions, sink: &dyn FileSink) -> Option<Result<(), String>> {
+        Some(self.create_nextjs_project(target, options, sink))
+    }
+
+    fn capabilities(&self, options: &CompilerOptions) -> Option<Capabilities> {
+        let mut sections = vec![Namespace::Routes, Namespace::Api, Namespace::Components];
+        // Models only become code for tRPC's schemas or Supabase's tables. Invalid
+        // options fail the compile on their own
+        let trpc = options.parse::<Api>("api").map_or(true, |api| api == Api::Trpc);
+        let supabase = options.parse::<Backend>("backend").map_or(true, |backend| backend == Backend::Supabase);
+        if trpc || supabase {
+            sections.push(Namespace::Schema);
+        }
+        Some(Capabilities::sections(sections))
+    }
+
+    fn outputs(&self, options: &CompilerOptions) -> Outputs {
+        // Only exported when another target uses it (see `create_next_config`)
+        let exported = !options.used_by().is_empty();
+        Outputs { dist: exported.then(|| "out".to_string()), dev_url: Some("http://localhost:3000".to_string()) }
     }
 }
 
 impl NextJSCompiler {
-    pub fn create_nextjs_project(&self, ast: &Element, output_dir: &Path) -> Result<(), String> {
-        // Create the full Next.js project structure
-        self.create_project_structure(output_dir)?;
-        self.create_package_json(output_dir)?;
-        self.create_pnpm_workspace(output_dir)?;
-        self.create_next_config(output_dir)?;
-        self.create_tailwind_config(output_dir)?;
-        self.create_postcss_config(output_dir)?;
-        self.create_typescript_config(output_dir)?;
-        self.create_app_structure(output_dir, ast)?;
-        self.create_shadcn_config(output_dir)?;
-        self.create_globals_css(output_dir)?;
+    pub fn create_nextjs_project(&self, target: &Target, options: &CompilerOptions, sink: &dyn FileSink) -> Result<(), String> {
+        let mut out = Output::new(sink, &self.style);
+        self.create_files(target, options, &mut out)?;
+        out.write()
+    }
+
+    fn create_files(&self, target: &Target, options: &CompilerOptions, out: &mut Output) -> Result<(), String> {
+        // Create the Next.js project structure, with as much scaffolding as the profile asks for
+        let profile = target.profile;
+        let framework: Framework = options.parse("framework")?;
+        let runtime = Runtime::of(options)?;
+        let api: Api = options.parse("api")?;
+        let backend: Backend = options.parse("backend")?;
+        let naming = Naming::of(&target.kind, options)?;
+        if backend == Backend::Supabase && api == Api::Trpc {
+            return Err("backend: supabase has no server routes for api: trpc to serve".to_string());
+        }
+        self.create_project_structure(out, profile)?;
+        self.create_package_json(out, target, framework, runtime, api, backend)?;
+        match runtime {
+            Runtime::Node => self.create_pnpm_workspace(out)?,
+            Runtime::Bun => self.create_bunfig(out)?,
+        }
+        self.create_next_config(out, framework, !options.used_by().is_empty())?;
+        if profile >= Profile::Standard {
+            self.create_tailwind_config(out)?;
+            self.create_postcss_config(out)?;
+        }
+        self.create_typescript_config(target, out)?;
+        self.create_app_structure(out, target, api)?;
+        self.create_route_pages(out, target, framework, &naming)?;
+        if api == Api::Trpc {
+            trpc::create_trpc(target, out);
+        }
+        if backend == Backend::Supabase {
+            supabase::create_supabase(target, out)?;
+        }
+        if profile >= Profile::Standard {
+            self.create_shadcn_config(out, framework)?;
+        }
+        self.create_globals_css(out, profile)?;
+        if profile >= Profile::Full {
+            self.create_tooling_configs(out)?;
+            self.create_app_states(target, out)?;
+        }
 
         Ok(())
     }
 
-        fn create_project_structure(&self, output_dir: &Path) -> Result<(), String> {
-        let dirs = [
-            "app",
-            "app/api",
-            "app/globals",
-            "components",
-            "components/ui",
-            "lib",
-            "public",
-            "styles",
-        ];
+        fn create_project_structure(&self, out: &mut Output, profile: Profile) -> Result<(), String> {
+        let mut dirs = vec!["app", "app/api", "public"];
+        if profile >= Profile::Standard {
+            dirs.extend(["app/globals", "components", "components/ui", "lib", "styles"]);
+            dirs.sort();
+        }
 
         for dir in &dirs {
-            let dir_path = output_dir.join(dir);
-            fs::create_dir_all(&dir_path)
-                .map_err(|e| format!("Failed to create directory {}: {}", dir_path.display(), e))?;
+            out.dir(dir);
         }
 
         Ok(())
     }
 
-    fn create_package_json(&self, output_dir: &Path) -> Result<(), String> {
-        let package_json = r#"{
-  "name": "z-generated-nextjs",
-  "version": "0.1.0",
+    fn create_package_json(
+        &self,
+        out: &mut Output,
+        target: &Target,
+        framework: Framework,
+        runtime: Runtime,
+        api: Api,
+        backend: Backend,
+    ) -> Result<(), String> {
+        let profile = target.profile;
+        let next15 = framework == Framework::Next15;
+        // `--bun` runs Next.js itself on Bun; without it, `next`'s node shebang wins
+        let next = match runtime {
+            Runtime::Node => "next",
+            Runtime::Bun => "bun --bun next",
+        };
+        let dev = format!("{} dev{}", next, if next15 { " --turbopack" } else { "" });
+        let build = format!("{} build", next);
+        let start = format!("{} start", next);
+        let mut scripts = vec![
+            ("dev", dev.as_str()),
+            ("build", build.as_str()),
+            ("start", start.as_str()),
+            ("lint", "next lint"),
+            ("lint:fix", "next lint --fix"),
+            ("type-check", "tsc --noEmit"),
+        ];
+        let mut dependencies = if next15 {
+            vec![("next", "^15.0.0"), ("react", "^19.0.0"), ("react-dom", "^19.0.0")]
+        } else {
+            vec![("next", "^14.0.0"), ("react", "^18.2.0"), ("react-dom", "^18.2.0")]
+        };
+        let mut dev_dependencies = vec![
+            ("@types/node", "^20.9.0"),
+            ("eslint", "^8.53.0"),
+            ("typescript", "^5.2.2"),
+        ];
+        dev_dependencies.extend(if next15 {
+            [("@types/react", "^19.0.0"), ("@types/react-dom", "^19.0.0"), ("eslint-config-next", "15.0.0")]
+        } else {
+            [("@types/react", "^18.2.37"), ("@types/react-dom", "^18.2.15"), ("eslint-config-next", "14.0.0")]
+        });
+
+        if profile >= Profile::Standard {
+            // shadcn/ui on Tailwind CSS, with tailwindcss-animate for its transitions. The
+            // Radix and lucide releases before these don't list React 19 as a peer.
+            dependencies.extend([
+                ("@radix-ui/react-slot", if next15 { "^1.1.0" } else { "^1.0.2" }),
+                ("@radix-ui/react-icons", if next15 { "^1.3.2" } else { "^1.3.0" }),
+                ("class-variance-authority", "^0.7.0"),
+                ("clsx", "^2.0.0"),
+                ("lucide-react", if next15 { "^0.460.0" } else { "^0.294.0" }),
+                ("tailwind-merge", "^2.0.0"),
+                ("tailwindcss-animate", "^1.0.7"),
+            ]);
+            dev_dependencies.extend([
+                ("autoprefixer", "^10.4.16"),
+                ("postcss", "^8.4.31"),
+                ("tailwindcss", "^3.3.5"),
+            ]);
+        }
+        if api == Api::Trpc {
+            dependencies.extend(trpc::DEPENDENCIES);
+        }
+        if backend == Backend::Supabase {
+            scripts.extend(supabase::SCRIPTS);
+            dependencies.extend(supabase::DEPENDENCIES);
+            dev_dependencies.extend(supabase::DEV_DEPENDENCIES);
+        }
+        if profile >= Profile::Full {
+            scripts.push(("format", "prettier --write ."));
+            dev_dependencies.extend([
+                ("prettier", "^3.1.0"),
+                ("prettier-plugin-tailwindcss", "^0.5.7"),
+            ]);
+        }
+        dev_dependencies.sort();
+
+        // Bun isn't a corepack package manager, so it has no `packageManager` entry
+        let package_manager = match runtime {
+            Runtime::Node => ",\n  \"packageManager\": \"pnpm@8.10.0\"",
+            Runtime::Bun => "",
+        };
+        let meta = target.meta.as_ref();
+        let version = meta.and_then(|meta| meta.version.as_deref()).unwrap_or("0.1.0");
+        let description = meta.and_then(|meta| meta.description.as_deref())
+            .map_or_else(String::new, |description| format!("\n  \"description\": {},", js_string(description)));
+        let package_json = format!(
+            r#"{{
+  "name": "{}",
+  "version": "{}",{}
   "private": true,
-  "scripts": {
-    "dev": "next dev",
-    "build": "next build",
-    "start": "next start",
-    "lint": "next lint",
-    "lint:fix": "next lint --fix",
-    "type-check": "tsc --noEmit"
-  },
-  "dependencies": {
-    "next": "^14.0.0",
-    "react": "^18.2.0",
-    "react-dom": "^18.2.0",
-    "@radix-ui/react-slot": "^1.0.2",
-    "@radix-ui/react-icons": "^1.3.0",
-    "class-variance-authority": "^0.7.0",
-    "clsx": "^2.0.0",
-    "lucide-react": "^0.294.0",
-    "tailwind-merge": "^2.0.0",
-    "tailwindcss-animate": "^1.0.7"
-  },
-  "devDependencies": {
-    "@types/node": "^20.9.0",
-    "@types/react": "^18.2.37",
-    "@types/react-dom": "^18.2.15",
-    "autoprefixer": "^10.4.16",
-    "eslint": "^8.53.0",
-    "eslint-config-next": "14.0.0",
-    "postcss": "^8.4.31",
-    "tailwindcss": "^3.3.5",
-    "typescript": "^5.2.2"
-  },
-  "packageManager": "pnpm@8.10.0"
-}"#;
-
-        let file_path = output_dir.join("package.json");
-        fs::write(file_path, package_json)
-            .map_err(|e| format!("Failed to write package.json: {}", e))?;
+  "scripts": {{
+{}
+  }},
+  "dependencies": {{
+{}
+  }},
+  "devDependencies": {{
+{}
+  }}{}
+}}"#,
+            crate::identifiers::package(target),
+            version,
+            description,
+            json_entries(&scripts),
+            json_entries(&dependencies),
+            json_entries(&dev_dependencies),
+            package_manager,
+        );
+
+        out.file("package.json", package_json);
 
         Ok(())
     }
 
-    fn create_pnpm_workspace(&self, output_dir: &Path) -> Result<(), String> {
+    fn create_pnpm_workspace(&self, out: &mut Output) -> Result<(), String> {
         let pnpm_workspace = r#"packages:
   - "."
 "#;
 
-        let file_path = output_dir.join("pnpm-workspace.yaml");
-        fs::write(file_path, pnpm_workspace)
-            .map_err(|e| format!("Failed to write pnpm-workspace.yaml: {}", e))?;
+        out.file("pnpm-workspace.yaml", pnpm_workspace);
 
         Ok(())
     }
 
-    fn create_next_config(&self, output_dir: &Path) -> Result<(), String> {
-        let next_config = r#"/** @type {import('next').NextConfig} */
+    fn create_bunfig(&self, out: &mut Output) -> Result<(), String> {
+        let bunfig = r#"[install]
+# A text bun.lock, so dependency changes show up in review
+saveTextLockfile = true
+"#;
+
+        out.file("bunfig.toml", bunfig);
+
+        Ok(())
+    }
+
+    /// `next.config`, exporting the app as static files into `out` when `exported`, which
+    /// is what the targets using it load
+    fn create_next_config(&self, out: &mut Output, framework: Framework, exported: bool) -> Result<(), String> {
+        let (name, next_config) = match framework {
+            Framework::Next14 => ("next.config.js", r#"/** @type {import('next').NextConfig} */
 const nextConfig = {
   experimental: {
     appDir: true,
@@ -135,16 +337,33 @@ const nextConfig = {
 }
 
 module.exports = nextConfig
-"#;
+"#),
+            // Next.js 15 stopped reusing visited dynamic pages from the client router
+            // cache; keep them for 30 seconds as 14 did
+            Framework::Next15 => ("next.config.ts", r#"import type { NextConfig } from 'next'
 
-        let file_path = output_dir.join("next.config.js");
-        fs::write(file_path, next_config)
-            .map_err(|e| format!("Failed to write next.config.js: {}", e))?;
+const nextConfig: NextConfig = {
+  experimental: {
+    staleTimes: {
+      dynamic: 30,
+    },
+  },
+}
+
+export default nextConfig
+"#),
+        };
+
+        if exported {
+            out.file(name, next_config.replacen("= {\n", "= {\n  output: 'export',\n", 1));
+        } else {
+            out.file(name, next_config);
+        }
 
         Ok(())
     }
 
-    fn create_tailwind_config(&self, output_dir: &Path) -> Result<(), String> {
+    fn create_tailwind_config(&self, out: &mut Output) -> Result<(), String> {
         let tailwind_config = r#"/** @type {import('tailwindcss').Config} */
 module.exports = {
   darkMode: ["class"],
@@ -223,14 +442,12 @@ module.exports = {
 }
 "#;
 
-        let file_path = output_dir.join("tailwind.config.js");
-        fs::write(file_path, tailwind_config)
-            .map_err(|e| format!("Failed to write tailwind.config.js: {}", e))?;
+        out.file("tailwind.config.js", tailwind_config);
 
         Ok(())
     }
 
-    fn create_postcss_config(&self, output_dir: &Path) -> Result<(), String> {
+    fn create_postcss_config(&self, out: &mut Output) -> Result<(), String> {
         let postcss_config = r#"module.exports = {
   plugins: {
     tailwindcss: {},
@@ -239,123 +456,112 @@ module.exports = {
 }
 "#;
 
-        let file_path = output_dir.join("postcss.config.js");
-        fs::write(file_path, postcss_config)
-            .map_err(|e| format!("Failed to write postcss.config.js: {}", e))?;
+        out.file("postcss.config.js", postcss_config);
 
         Ok(())
     }
 
-    fn create_typescript_config(&self, output_dir: &Path) -> Result<(), String> {
-        let tsconfig = r#"{
-  "compilerOptions": {
-    "target": "es5",
-    "lib": ["dom", "dom.iterable", "es6"],
-    "allowJs": true,
-    "skipLibCheck": true,
-    "strict": true,
-    "noEmit": true,
-    "esModuleInterop": true,
-    "module": "esnext",
-    "moduleResolution": "bundler",
-    "resolveJsonModule": true,
-    "isolatedModules": true,
-    "jsx": "preserve",
-    "incremental": true,
-    "plugins": [
-      {
-        "name": "next"
-      }
-    ],
-    "baseUrl": ".",
-    "paths": {
-      "@/*": ["./*"]
-    }
-  },
-  "include": ["next-env.d.ts", "**/*.ts", "**/*.tsx", ".next/types/**/*.ts"],
-  "exclude": ["node_modules"]
-}
-"#;
+    fn create_typescript_config(&self, target: &Target, out: &mut Output) -> Result<(), String> {
+        let tsconfig = self.templates.render("next/tsconfig.json", &context(target))?;
 
-        let file_path = output_dir.join("tsconfig.json");
-        fs::write(file_path, tsconfig)
-            .map_err(|e| format!("Failed to write tsconfig.json: {}", e))?;
+        out.file("tsconfig.json", tsconfig);
 
         Ok(())
     }
 
-    fn create_app_structure(&self, output_dir: &Path, ast: &Element) -> Result<(), String> {
-        // Create layout.tsx
-        let layout_tsx = r#"import type { Metadata } from 'next'
-import { Inter } from 'next/font/google'
-import './globals.css'
-
-const inter = Inter({ subsets: ['latin'] })
-
-export const metadata: Metadata = {
-  title: 'Z Generated App',
-  description: 'Generated by Z compiler',
-}
-
-export default function RootLayout({
-  children,
-}: {
-  children: React.ReactNode
-}) {
-  return (
-    <html lang="en">
-      <body className={inter.className}>{children}</body>
-    </html>
-  )
-}
-"#;
+    fn create_app_structure(&self, out: &mut Output, target: &Target, api: Api) -> Result<(), String> {
+        // Create layout.tsx, titled after the app's `Meta` block. The tRPC client and
+        // its query cache are provided to the whole app
+        let mut context = context(target);
+        context["trpc"] = json!(api == Api::Trpc);
+        let layout_tsx = self.templates.render("next/layout.tsx", &context)?;
 
-        let layout_path = output_dir.join("app/layout.tsx");
-        fs::write(layout_path, layout_tsx)
-            .map_err(|e| format!("Failed to write app/layout.tsx: {}", e))?;
+        out.file("app/layout.tsx", layout_tsx);
 
         // Create main page.tsx
-        let page_tsx = self.generate_main_page(ast)?;
-        let page_path = output_dir.join("app/page.tsx");
-        fs::write(page_path, page_tsx)
-            .map_err(|e| format!("Failed to write app/page.tsx: {}", e))?;
+        let page_tsx = if target.profile >= Profile::Standard {
+            self.generate_main_page(target)?
+        } else {
+            self.generate_minimal_page(target)
+        };
+        out.file("app/page.tsx", page_tsx);
+
+        // Create utils (the `cn` helper shadcn/ui components use)
+        if target.profile >= Profile::Standard {
+            self.create_utils(out)?;
+        }
 
-        // Create utils
-        self.create_utils(output_dir)?;
+        Ok(())
+    }
 
+    /// A page under `app/` for every route in the target's `Routes` sections
+    fn create_route_pages(&self, out: &mut Output, target: &Target, framework: Framework, naming: &Naming) -> Result<(), String> {
+        for section in &target.sections {
+            if let SectionBody::Routes(routes) = &section.body {
+                self.create_routes(out, routes, "", &[], target.profile, framework, naming)?;
+            }
+        }
         Ok(())
     }
 
-    fn generate_main_page(&self, ast: &Element) -> Result<String, String> {
+    #[allow(clippy::too_many_arguments)]
+    fn create_routes(
+        &self,
+        out: &mut Output,
+        routes: &[Route],
+        parent: &str,
+        params: &[&str],
+        profile: Profile,
+        framework: Framework,
+        naming: &Naming,
+    ) -> Result<(), String> {
+        for route in routes {
+            let mut params = params.to_vec();
+            let directory = if route.dynamic {
+                params.push(&route.segment);
+                format!("{}/[{}]", parent, route.segment)
+            } else {
+                format!("{}/{}", parent, naming.name(Role::Route, &route.segment))
+            };
+
+            out.file(format!("app{}/page.tsx", directory), route_page(&directory, &params, profile, framework));
+
+            self.create_routes(out, &route.children, &directory, &params, profile, framework, naming)?;
+        }
+        Ok(())
+    }
+
+    /// Unstyled page listing the target's sections, for projects without Tailwind
+    fn generate_minimal_page(&self, target: &Target) -> String {
+        let mut page = String::new();
+        page.push_str("export default function Home() {\n");
+        page.push_str("  return (\n");
+        page.push_str("    <main>\n");
+        page.push_str(&format!("      <h1>Welcome to {}</h1>\n", target.display_name()));
+        for section in &target.sections {
+            if matches!(section.namespace, Namespace::Routes | Namespace::Api | Namespace::Components) {
+                page.push_str(&format!("      <section>\n        <h2>{}</h2>\n      </section>\n", section.namespace));
+            }
+        }
+        page.push_str("    </main>\n");
+        page.push_str("  )\n");
+        page.push_str("}\n");
+        page
+    }
+
+    fn generate_main_page(&self, target: &Target) -> Result<String, String> {
+        // One card per section of the target
         let mut imports = Vec::new();
         let mut components = Vec::new();
-
-        // Extract components from AST
-        for child in &ast.children {
-            if let Node::Element(element) = child {
-                match element.name.split(':').next().unwrap_or("") {
-                    "next" => {
-                        // This is our target, process its children
-                        for app_child in &element.children {
-                            if let Node::Element(section) = app_child {
-                                match section.name.as_str() {
-                                    "Routes" => {
-                                        imports.push("import { Button } from '@/components/ui/button'");
-                                        components.push(self.generate_routes_section(section));
-                                    },
-                                    "API" => {
-                                        components.push(self.generate_api_section(section));
-                                    },
-                                    "Components" => {
-                                        components.push(self.generate_components_section(section));
-                                    },
-                                    _ => {}
-                                }
-                            }
-                        }
-                    },
-                    _ => {}
+        for section in &target.sections {
+            match section.namespace {
+                Namespace::Routes => {
+                    imports.push("import { Button } from '@/components/ui/button'");
+                    components.push(self.generate_routes_section(section));
                 }
+                Namespace::Api => components.push(self.generate_api_section(section)),
+                Namespace::Components => components.push(self.generate_components_section(section)),
+                _ => {}
             }
         }
 
@@ -374,7 +580,7 @@ export default function RootLayout({
         page.push_str("      <div className=\"container mx-auto px-4 py-8\">\n");
         page.push_str("        <div className=\"text-center mb-12\">\n");
         page.push_str("          <h1 className=\"text-4xl font-bold text-slate-900 dark:text-slate-100 mb-4\">\n");
-        page.push_str("            Welcome to Your Z Generated App\n");
+        page.push_str(&format!("            Welcome to {}\n", target.display_name()));
         page.push_str("          </h1>\n");
         page.push_str("          <p className=\"text-xl text-slate-600 dark:text-slate-400\">\n");
         page.push_str("            Built with Next.js, Tailwind CSS, and shadcn/ui\n");
@@ -395,7 +601,7 @@ export default function RootLayout({
         Ok(page)
     }
 
-    fn generate_routes_section(&self, _element: &Element) -> String {
+    fn generate_routes_section(&self, _section: &Section) -> String {
         r#"<div className="bg-white dark:bg-slate-800 rounded-lg shadow-md p-6">
             <h2 className="text-2xl font-semibold text-slate-900 dark:text-slate-100 mb-4">🛣️ Routes</h2>
             <p className="text-slate-600 dark:text-slate-400 mb-4">Your application routes are ready to be implemented.</p>
@@ -403,7 +609,7 @@ export default function RootLayout({
           </div>"#.to_string()
     }
 
-    fn generate_api_section(&self, _element: &Element) -> String {
+    fn generate_api_section(&self, _section: &Section) -> String {
         r#"<div className="bg-white dark:bg-slate-800 rounded-lg shadow-md p-6">
             <h2 className="text-2xl font-semibold text-slate-900 dark:text-slate-100 mb-4">🔌 API</h2>
             <p className="text-slate-600 dark:text-slate-400 mb-4">API endpoints are configured and ready for development.</p>
@@ -413,7 +619,7 @@ export default function RootLayout({
           </div>"#.to_string()
     }
 
-    fn generate_components_section(&self, _element: &Element) -> String {
+    fn generate_components_section(&self, _section: &Section) -> String {
         r#"<div className="bg-white dark:bg-slate-800 rounded-lg shadow-md p-6">
             <h2 className="text-2xl font-semibold text-slate-900 dark:text-slate-100 mb-4">🧩 Components</h2>
             <p className="text-slate-600 dark:text-slate-400 mb-4">Reusable components with shadcn/ui integration.</p>
@@ -434,7 +640,7 @@ export default function RootLayout({
           </div>"#.to_string()
     }
 
-    fn create_utils(&self, output_dir: &Path) -> Result<(), String> {
+    fn create_utils(&self, out: &mut Output) -> Result<(), String> {
         let utils_ts = r#"import { type ClassValue, clsx } from "clsx"
 import { twMerge } from "tailwind-merge"
 
@@ -443,14 +649,12 @@ export function cn(...inputs: ClassValue[]) {
 }
 "#;
 
-        let file_path = output_dir.join("lib/utils.ts");
-        fs::write(file_path, utils_ts)
-            .map_err(|e| format!("Failed to write lib/utils.ts: {}", e))?;
+        out.file("lib/utils.ts", utils_ts);
 
         Ok(())
     }
 
-    fn create_shadcn_config(&self, output_dir: &Path) -> Result<(), String> {
+    fn create_shadcn_config(&self, out: &mut Output, framework: Framework) -> Result<(), String> {
         let components_json = r#"{
   "$schema": "https://ui.shadcn.com/schema.json",
   "style": "default",
@@ -469,84 +673,31 @@ export function cn(...inputs: ClassValue[]) {
 }
 "#;
 
-        let file_path = output_dir.join("components.json");
-        fs::write(file_path, components_json)
-            .map_err(|e| format!("Failed to write components.json: {}", e))?;
+        out.file("components.json", components_json);
 
         // Create a basic Button component
-        self.create_button_component(output_dir)?;
+        self.create_button_component(out, framework)?;
 
         Ok(())
     }
 
-    fn create_button_component(&self, output_dir: &Path) -> Result<(), String> {
-        let button_tsx = r#"import * as React from "react"
-import { Slot } from "@radix-ui/react-slot"
-import { cva, type VariantProps } from "class-variance-authority"
-
-import { cn } from "@/lib/utils"
+    fn create_button_component(&self, out: &mut Output, framework: Framework) -> Result<(), String> {
+        let button_tsx = format!("{}{}", BUTTON_VARIANTS, match framework {
+            Framework::Next14 => BUTTON_FORWARD_REF,
+            // React 19 passes `ref` as a regular prop, so there's no forwardRef to wrap in
+            Framework::Next15 => BUTTON_REF_PROP,
+        });
 
-const buttonVariants = cva(
-  "inline-flex items-center justify-center rounded-md text-sm font-medium ring-offset-background transition-colors focus-visible:outline-none focus-visible:ring-2 focus-visible:ring-ring focus-visible:ring-offset-2 disabled:pointer-events-none disabled:opacity-50",
-  {
-    variants: {
-      variant: {
-        default: "bg-primary text-primary-foreground hover:bg-primary/90",
-        destructive:
-          "bg-destructive text-destructive-foreground hover:bg-destructive/90",
-        outline:
-          "border border-input bg-background hover:bg-accent hover:text-accent-foreground",
-        secondary:
-          "bg-secondary text-secondary-foreground hover:bg-secondary/80",
-        ghost: "hover:bg-accent hover:text-accent-foreground",
-        link: "text-primary underline-offset-4 hover:underline",
-      },
-      size: {
-        default: "h-10 px-4 py-2",
-        sm: "h-9 rounded-md px-3",
-        lg: "h-11 rounded-md px-8",
-        icon: "h-10 w-10",
-      },
-    },
-    defaultVariants: {
-      variant: "default",
-      size: "default",
-    },
-  }
-)
-
-export interface ButtonProps
-  extends React.ButtonHTMLAttributes<HTMLButtonElement>,
-    VariantProps<typeof buttonVariants> {
-  asChild?: boolean
-}
-
-const Button = React.forwardRef<HTMLButtonElement, ButtonProps>(
-  ({ className, variant, size, asChild = false, ...props }, ref) => {
-    const Comp = asChild ? Slot : "button"
-    return (
-      <Comp
-        className={cn(buttonVariants({ variant, size, className }))}
-        ref={ref}
-        {...props}
-      />
-    )
-  }
-)
-Button.displayName = "Button"
-
-export { Button, buttonVariants }
-"#;
-
-        let file_path = output_dir.join("components/ui/button.tsx");
-        fs::write(file_path, button_tsx)
-            .map_err(|e| format!("Failed to write components/ui/button.tsx: {}", e))?;
+        out.file("components/ui/button.tsx", button_tsx);
 
         Ok(())
     }
 
-    fn create_globals_css(&self, output_dir: &Path) -> Result<(), String> {
-        let globals_css = r#"@tailwind base;
+    fn create_globals_css(&self, out: &mut Output, profile: Profile) -> Result<(), String> {
+        let globals_css = if profile < Profile::Standard {
+            MINIMAL_GLOBALS_CSS
+        } else {
+            r#"@tailwind base;
 @tailwind components;
 @tailwind utilities;
 
@@ -622,13 +773,186 @@ export { Button, buttonVariants }
     @apply bg-background text-foreground;
   }
 }
-"#;
+"#
+        };
+
+        out.file("app/globals.css", globals_css);
+
+        Ok(())
+    }
+
+    /// ESLint and Prettier configuration for the `full` profile
+    fn create_tooling_configs(&self, out: &mut Output) -> Result<(), String> {
+        let files = [
+            (".eslintrc.json", "{\n  \"extends\": \"next/core-web-vitals\"\n}\n"),
+            (".prettierrc", "{\n  \"plugins\": [\"prettier-plugin-tailwindcss\"]\n}\n"),
+        ];
+
+        for (name, contents) in files {
+            out.file(name, contents);
+        }
+
+        Ok(())
+    }
 
-        let file_path = output_dir.join("app/globals.css");
-        fs::write(file_path, globals_css)
-            .map_err(|e| format!("Failed to write app/globals.css: {}", e))?;
+    /// Loading, error and not-found pages for the `full` profile
+    fn create_app_states(&self, target: &Target, out: &mut Output) -> Result<(), String> {
+        let context = context(target);
+        for page in ["loading", "error", "not-found"] {
+            let contents = self.templates.render(&format!("next/{}.tsx", page), &context)?;
+            out.file(format!("app/{}.tsx", page), contents);
+        }
 
         Ok(())
     }
+}
+
+const MINIMAL_GLOBALS_CSS: &str = r#"*,
+*::before,
+*::after {
+  box-sizing: border-box;
+}
+
+body {
+  margin: 0;
+  font-family: system-ui, sans-serif;
+  line-height: 1.5;
+}
+
+main {
+  max-width: 64rem;
+  margin: 0 auto;
+  padding: 2rem 1rem;
+}
+"#;
+
+/// shadcn/ui's button up to its variants, shared by both React generations
+const BUTTON_VARIANTS: &str = r#"import * as React from "react"
+import { Slot } from "@radix-ui/react-slot"
+import { cva, type VariantProps } from "class-variance-authority"
 
-}
\ No newline at end of file
+import { cn } from "@/lib/utils"
+
+const buttonVariants = cva(
+  "inline-flex items-center justify-center rounded-md text-sm font-medium ring-offset-background transition-colors focus-visible:outline-none focus-visible:ring-2 focus-visible:ring-ring focus-visible:ring-offset-2 disabled:pointer-events-none disabled:opacity-50",
+  {
+    variants: {
+      variant: {
+        default: "bg-primary text-primary-foreground hover:bg-primary/90",
+        destructive:
+          "bg-destructive text-destructive-foreground hover:bg-destructive/90",
+        outline:
+          "border border-input bg-background hover:bg-accent hover:text-accent-foreground",
+        secondary:
+          "bg-secondary text-secondary-foreground hover:bg-secondary/80",
+        ghost: "hover:bg-accent hover:text-accent-foreground",
+        link: "text-primary underline-offset-4 hover:underline",
+      },
+      size: {
+        default: "h-10 px-4 py-2",
+        sm: "h-9 rounded-md px-3",
+        lg: "h-11 rounded-md px-8",
+        icon: "h-10 w-10",
+      },
+    },
+    defaultVariants: {
+      variant: "default",
+      size: "default",
+    },
+  }
+)
+
+"#;
+
+const BUTTON_FORWARD_REF: &str = r#"export interface ButtonProps
+  extends React.ButtonHTMLAttributes<HTMLButtonElement>,
+    VariantProps<typeof buttonVariants> {
+  asChild?: boolean
+}
+
+const Button = React.forwardRef<HTMLButtonElement, ButtonProps>(
+  ({ className, variant, size, asChild = false, ...props }, ref) => {
+    const Comp = asChild ? Slot : "button"
+    return (
+      <Comp
+        className={cn(buttonVariants({ variant, size, className }))}
+        ref={ref}
+        {...props}
+      />
+    )
+  }
+)
+Button.displayName = "Button"
+
+export { Button, buttonVariants }
+"#;
+
+const BUTTON_REF_PROP: &str = r#"export interface ButtonProps
+  extends React.ComponentProps<"button">,
+    VariantProps<typeof buttonVariants> {
+  asChild?: boolean
+}
+
+function Button({ className, variant, size, asChild = false, ...props }: ButtonProps) {
+  const Comp = asChild ? Slot : "button"
+  return (
+    <Comp
+      className={cn(buttonVariants({ variant, size, className }))}
+      {...props}
+    />
+  )
+}
+
+export { Button, buttonVariants }
+"#;
+
+/// The page of the route at `directory` (`/todos/[id]`), whose dynamic segments are
+/// `params`. Next.js 15 hands them over as a promise.
+fn route_page(directory: &str, params: &[&str], profile: Profile, framework: Framework) -> String {
+    let params_type = params.iter()
+        .map(|param| format!("{}: string", ts_property(param)))
+        .collect::<Vec<_>>()
+        .join("; ");
+    let mut page = String::new();
+    match (params.is_empty(), framework) {
+        (true, _) => page.push_str("export default function Page() {\n"),
+        (false, Framework::Next14) => {
+            page.push_str(&format!("export default function Page({{ params }}: {{ params: {{ {} }} }}) {{\n", params_type));
+        }
+        (false, Framework::Next15) => {
+            page.push_str(&format!("export default async function Page(props: {{ params: Promise<{{ {} }}> }}) {{\n", params_type));
+            page.push_str("  const params = await props.params\n");
+        }
+    }
+
+    // The path with each dynamic segment showing its value
+    let mut heading = String::new();
+    for segment in directory.split('/').skip(1) {
+        heading.push('/');
+        match segment.strip_prefix('[').and_then(|segment| segment.strip_suffix(']')) {
+            Some(param) => heading.push_str(&format!("{{params{}}}", ts_access(param))),
+            None => heading.push_str(segment),
+        }
+    }
+
+    let (main, h1) = if profile >= Profile::Standard {
+        (" className=\"container mx-auto px-4 py-8\"", " className=\"text-3xl font-bold\"")
+    } else {
+        ("", "")
+    };
+    page.push_str("  return (\n");
+    page.push_str(&format!("    <main{}>\n", main));
+    page.push_str(&format!("      <h1{}>{}</h1>\n", h1, heading));
+    page.push_str("    </main>\n");
+    page.push_str("  )\n");
+    page.push_str("}\n");
+    page
+}
+
+/// `"key": "value"` lines for the body of a JSON object
+fn json_entries(entries: &[(&str, &str)]) -> String {
+    entries.iter()
+        .map(|(key, value)| format!("    \"{}\": \"{}\"", key, value))
+        .collect::<Vec<_>>()
+        .join(",\n")
+}
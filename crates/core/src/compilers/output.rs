@@ -0,0 +1,88 @@
+//! The files a compiler generates, written once it's done generating them.
+//!
+//! Compilers add files to an [`Output`] instead of writing each to their [`FileSink`]
+//! as they go. Writing them all at the end lets the directories be created together
+//! first, each once, and the files be styled and written by a few threads at a time,
+//! so projects with hundreds of routes and components aren't held up waiting on a
+//! slow disk.
+
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+
+use crate::crash;
+use crate::sink::FileSink;
+use crate::style::CodeStyle;
+
+/// Most files written at the same time
+const MAX_WRITERS: usize = 8;
+
+/// Generated files waiting to be written to a project's sink
+pub(crate) struct Output<'a> {
+    sink: &'a dyn FileSink,
+    style: &'a CodeStyle,
+    /// Directories to create even if no file goes in them
+    dirs: BTreeSet<PathBuf>,
+    /// Paths in the project, with their contents, in the order they were added
+    files: Vec<(String, String)>,
+}
+
+impl<'a> Output<'a> {
+    pub fn new(sink: &'a dyn FileSink, style: &'a CodeStyle) -> Self {
+        Self { sink, style, dirs: BTreeSet::new(), files: Vec::new() }
+    }
+
+    /// Create the directory `name`, empty or not
+    pub fn dir(&mut self, name: &str) {
+        self.dirs.insert(PathBuf::from(name));
+    }
+
+    /// Write `contents` to the file `name`, restyled by the code style; a later file of
+    /// the same name replaces it
+    pub fn file(&mut self, name: impl Into<String>, contents: impl Into<String>) {
+        let name = name.into();
+        self.files.retain(|(queued, _)| *queued != name);
+        self.files.push((name, contents.into()));
+    }
+
+    /// Write every file, after creating the directories they go in. The error is the
+    /// first file's to fail, in the order the files were added.
+    pub fn write(mut self) -> Result<(), String> {
+        let parents: Vec<PathBuf> = self.files.iter()
+            .filter_map(|(name, _)| Path::new(name).parent().map(Path::to_path_buf))
+            .filter(|parent| !parent.as_os_str().is_empty())
+            .collect();
+        self.dirs.extend(parents);
+        // A directory is created along with the ones under it, which sort right after it
+        let dirs: Vec<&PathBuf> = self.dirs.iter().collect();
+        for (index, dir) in dirs.iter().enumerate() {
+            if !dirs.get(index + 1).is_some_and(|next| next.starts_with(dir)) {
+                self.sink.create_dir(dir)?;
+            }
+        }
+
+        let writers = thread::available_parallelism().map_or(1, usize::from).min(MAX_WRITERS).min(self.files.len());
+        let next = AtomicUsize::new(0);
+        let isolated = crash::is_isolated();
+        let failed = thread::scope(|scope| {
+            let writers: Vec<_> = (0..writers).map(|_| scope.spawn(|| {
+                // Panics here are caught wherever the compiler's are (see `crash::isolate`)
+                crash::set_isolated(isolated);
+                let mut failed = Vec::new();
+                while let Some((name, contents)) = self.files.get(next.fetch_add(1, Ordering::Relaxed)) {
+                    let path = Path::new(name);
+                    if let Err(e) = self.sink.write(path, self.style.apply(path, contents).as_bytes()) {
+                        failed.push((name.as_str(), e));
+                    }
+                }
+                failed
+            })).collect();
+            writers.into_iter()
+                .flat_map(|writer| writer.join().unwrap_or_else(|panic| std::panic::resume_unwind(panic)))
+                .collect::<Vec<_>>()
+        });
+        let first = failed.into_iter().min_by_key(|(name, _)| self.files.iter().position(|(queued, _)| queued == name));
+        first.map_or(Ok(()), |(_, error)| Err(error))
+    }
+}
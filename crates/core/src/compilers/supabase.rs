@@ -0,0 +1,529 @@
+//! Supabase as the backend of Next.js targets with `backend: supabase`.
+//!
+//! No server routes are generated: the app talks to Supabase directly. The models in
+//! `Schema` become tables in a migration under `supabase/migrations`, with row level
+//! security policies from their annotations, and `lib/database.types.ts` types them
+//! for the client. Every `@model` endpoint in `API` becomes a typed call in
+//! `lib/api.ts`; other endpoints invoke the Edge Function of the same name.
+//!
+//! Access to a table is decided by the model's annotations, its own or those of its
+//! `Schema` section:
+//!
+//! - without any, signed-in users can read and write every row
+//! - `@public` lets anyone read
+//! - `@owner(field)` ties each row to the user in `field`, who alone can read and write it
+//! - `@roles(admin, editor)` lets only users with one of those roles in their
+//!   `app_metadata` write (and read owned rows)
+
+use z_semantic::{Annotated, Endpoint, Field, Model, Target};
+
+use super::output::Output;
+use super::{base_type, camel, is_stored, js_string, key, pascal, plural, snake, ts_property};
+
+/// Packages the generated files import, with the versions they're written for
+pub(super) const DEPENDENCIES: [(&str, &str); 2] = [
+    ("@supabase/ssr", "^0.5.1"),
+    ("@supabase/supabase-js", "^2.45.0"),
+];
+
+/// The Supabase CLI, for the local stack and migrations
+pub(super) const DEV_DEPENDENCIES: [(&str, &str); 1] = [("supabase", "^1.200.0")];
+
+/// Scripts running the Supabase CLI
+pub(super) const SCRIPTS: [(&str, &str); 3] = [
+    ("db:start", "supabase start"),
+    ("db:reset", "supabase db reset"),
+    ("db:push", "supabase db push"),
+];
+
+/// Write the migration, the typed clients and the auth wiring for `target` into `out`
+pub(super) fn create_supabase(target: &Target, out: &mut Output) -> Result<(), String> {
+    let models: Vec<&Model> = target.models().collect();
+    let tables = models.iter()
+        .filter(|model| is_stored(model))
+        .map(|model| Table::new(model, &models))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    out.file("supabase/config.toml", format!("project_id = \"{}\"\n", crate::identifiers::package(target)));
+    out.file("supabase/migrations/00000000000000_schema.sql", migration_sql(&models, &tables));
+    out.file("lib/database.types.ts", database_types_ts(&models, &tables));
+    out.file("lib/supabase/client.ts", CLIENT_TS);
+    out.file("lib/supabase/server.ts", SERVER_TS);
+    out.file("lib/auth.ts", AUTH_TS);
+    out.file("lib/api.ts", api_ts(&target.endpoints().collect::<Vec<_>>(), &tables));
+    out.file("middleware.ts", MIDDLEWARE_TS);
+    out.file("app/auth/callback/route.ts", CALLBACK_TS);
+    out.file(".env.local.example", ENV_EXAMPLE);
+    Ok(())
+}
+
+/// A stored model as a table
+struct Table<'a> {
+    model: &'a Model,
+    /// `order_items` for `OrderItem`
+    name: String,
+    columns: Vec<Column>,
+    /// The key column and its TypeScript type
+    key: (String, &'static str),
+    public: bool,
+    /// The column holding the owning user, from `@owner(field)`
+    owner: Option<String>,
+    /// Roles allowed to write, from `@roles(...)`
+    roles: Vec<String>,
+}
+
+struct Column {
+    name: String,
+    /// The column's SQL type and constraints, e.g. `text not null unique`
+    sql: String,
+    /// The type of its values in TypeScript
+    ts: String,
+    /// Whether rows may leave it out, because it's nullable or has a default
+    optional: bool,
+    /// The table it points to and what deleting the row there does, for foreign keys
+    references: Option<(String, &'static str)>,
+}
+
+impl<'a> Table<'a> {
+    fn new(model: &'a Model, models: &[&Model]) -> Result<Self, String> {
+        let (key, key_type) = key(model);
+        let mut columns = Vec::new();
+        if !model.fields.iter().any(|field| field.name == key) {
+            columns.push(Column {
+                name: "id".to_string(),
+                sql: "uuid primary key default gen_random_uuid()".to_string(),
+                ts: "string".to_string(),
+                optional: true,
+                references: None,
+            });
+        }
+
+        let owner = model.annotation("owner").map(|annotation| annotation.args.as_deref().unwrap_or("").trim().to_string());
+        if let Some(owner) = &owner {
+            if !model.fields.iter().any(|field| &field.name == owner) {
+                return Err(format!("@owner field '{}' isn't a field of {}", owner, model.name));
+            }
+        }
+        for field in &model.fields {
+            if field.name == key {
+                columns.push(key_column(field));
+            } else if owner.as_deref() == Some(field.name.as_str()) {
+                columns.push(Column {
+                    name: snake(&field.name),
+                    sql: "uuid not null default auth.uid() references auth.users on delete cascade".to_string(),
+                    ts: "string".to_string(),
+                    optional: true,
+                    references: None,
+                });
+            } else {
+                columns.extend(column(field, models));
+            }
+        }
+
+        let roles = model.annotation("roles")
+            .and_then(|annotation| annotation.args.as_deref())
+            .map(|roles| roles.split(',').map(|role| role.trim().trim_matches('"').to_string()).filter(|role| !role.is_empty()).collect())
+            .unwrap_or_default();
+        Ok(Table {
+            model,
+            name: snake(&plural(&model.name)),
+            columns,
+            key: (snake(&key), key_type),
+            public: model.annotation("public").is_some(),
+            owner: owner.map(|owner| snake(&owner)),
+            roles,
+        })
+    }
+
+    /// Who may read a row, as a policy expression
+    fn read_check(&self) -> String {
+        match &self.owner {
+            Some(_) => self.write_check(),
+            None => "true".to_string(),
+        }
+    }
+
+    /// Who may write a row, as a policy expression
+    fn write_check(&self) -> String {
+        let owner = self.owner.as_ref().map(|owner| format!("auth.uid() = {}", owner));
+        let roles = (!self.roles.is_empty()).then(|| {
+            let roles: Vec<String> = self.roles.iter().map(|role| format!("'{}'", role.replace('\'', "''"))).collect();
+            format!("(auth.jwt() -> 'app_metadata' ->> 'role') in ({})", roles.join(", "))
+        });
+        match (owner, roles) {
+            (Some(owner), Some(roles)) => format!("{} or {}", owner, roles),
+            (Some(check), None) | (None, Some(check)) => check,
+            (None, None) => "true".to_string(),
+        }
+    }
+}
+
+/// The key column: uuids and strings default to a random uuid, numbers to an identity
+fn key_column(field: &Field) -> Column {
+    let ty = key_sql_type(field);
+    let default = match ty {
+        "uuid" => "default gen_random_uuid()",
+        "text" => "default gen_random_uuid()::text",
+        _ => "generated by default as identity",
+    };
+    let ts = if ty == "text" || ty == "uuid" { "string" } else { "number" };
+    Column { name: snake(&field.name), sql: format!("{} primary key {}", ty, default), ts: ts.to_string(), optional: true, references: None }
+}
+
+/// The Postgres type of the key field `field`, which foreign keys to it share
+fn key_sql_type(field: &Field) -> &'static str {
+    match base_type(field.ty.trim()) {
+        "uuid" => "uuid",
+        "int" | "integer" | "serial" => "integer",
+        "bigint" | "number" | "float" => "bigint",
+        _ => "text",
+    }
+}
+
+/// The column for `field`, if it has one: a field holding a stored model becomes a
+/// foreign key, `name_id`, and one holding a list of them has no column, since the
+/// other table points back
+fn column(field: &Field, models: &[&Model]) -> Option<Column> {
+    let (ty, nullable) = match field.ty.trim().strip_suffix('?') {
+        Some(ty) => (ty.trim(), true),
+        None => (field.ty.trim(), false),
+    };
+    let stored = |name: &str| models.iter().copied().find(|model| model.name == name && is_stored(model));
+    if ty.strip_suffix("[]").is_some_and(|element| stored(element.trim()).is_some()) {
+        return None;
+    }
+
+    let unique = field.annotations.iter().any(|annotation| annotation.name == "unique");
+    let constraints = format!("{}{}", if nullable { "" } else { " not null" }, if unique { " unique" } else { "" });
+    let mut references = None;
+    let (name, sql, ts) = match stored(ty) {
+        Some(model) => {
+            let (key, key_type) = key(model);
+            let key_sql = model.fields.iter().find(|field| field.name == key).map_or("uuid", key_sql_type);
+            references = Some((snake(&plural(&model.name)), if nullable { "set null" } else { "cascade" }));
+            (format!("{}_{}", snake(&field.name), snake(&key)), format!("{}{}", key_sql, constraints), key_type.to_string())
+        }
+        None => (snake(&field.name), format!("{}{}", sql_type(ty, models), constraints), ts_type(ty, models)),
+    };
+    let ts = if nullable { format!("{} | null", ts) } else { ts };
+    Some(Column { name, sql, ts, optional: nullable, references })
+}
+
+/// A schema type in Postgres; enums are their own types, and anything without a
+/// column type of its own is kept as `jsonb`
+fn sql_type(ty: &str, models: &[&Model]) -> String {
+    if let Some(element) = ty.strip_suffix("[]") {
+        return match sql_type(element.trim(), models).as_str() {
+            "jsonb" => "jsonb".to_string(),
+            element => format!("{}[]", element),
+        };
+    }
+    match base_type(ty) {
+        "string" | "text" | "email" | "url" | "cuid" => "text".to_string(),
+        "uuid" => "uuid".to_string(),
+        "int" | "integer" | "serial" => "integer".to_string(),
+        "bigint" => "bigint".to_string(),
+        "number" | "float" | "double" => "double precision".to_string(),
+        "decimal" => "numeric".to_string(),
+        "bool" | "boolean" => "boolean".to_string(),
+        "date" => "date".to_string(),
+        "datetime" | "timestamp" => "timestamptz".to_string(),
+        name if models.iter().any(|model| model.name == name && model.kind.as_deref() == Some("enum")) => {
+            format!("public.{}", snake(name))
+        }
+        _ => "jsonb".to_string(),
+    }
+}
+
+/// A schema type as the TypeScript type Supabase returns for it: dates arrive as
+/// ISO strings, and `jsonb` as `Json`
+fn ts_type(ty: &str, models: &[&Model]) -> String {
+    if let Some(element) = ty.strip_suffix("[]") {
+        return match sql_type(element.trim(), models).as_str() {
+            "jsonb" => "Json".to_string(),
+            _ => format!("{}[]", ts_type(element.trim(), models)),
+        };
+    }
+    match base_type(ty) {
+        "string" | "text" | "email" | "url" | "cuid" | "uuid" | "date" | "datetime" | "timestamp" => "string".to_string(),
+        "int" | "integer" | "serial" | "bigint" | "number" | "float" | "double" | "decimal" => "number".to_string(),
+        "bool" | "boolean" => "boolean".to_string(),
+        name => match models.iter().find(|model| model.name == name && model.kind.as_deref() == Some("enum")) {
+            Some(model) => enum_type(model),
+            None => "Json".to_string(),
+        },
+    }
+}
+
+fn enum_type(model: &Model) -> String {
+    if model.values.is_empty() {
+        return "never".to_string();
+    }
+    model.values.iter().map(|value| format!("\"{}\"", value)).collect::<Vec<_>>().join(" | ")
+}
+
+fn migration_sql(models: &[&Model], tables: &[Table]) -> String {
+    let mut sql = String::from("-- Generated from the Schema section; changes here are overwritten\n");
+    for model in models.iter().filter(|model| model.kind.as_deref() == Some("enum")) {
+        let values: Vec<String> = model.values.iter().map(|value| format!("'{}'", value.replace('\'', "''"))).collect();
+        sql.push_str(&format!("\ncreate type public.{} as enum ({});\n", snake(&model.name), values.join(", ")));
+    }
+
+    for table in tables {
+        let columns: Vec<String> = table.columns.iter().map(|column| format!("  {} {}", column.name, column.sql)).collect();
+        sql.push_str(&format!("\ncreate table public.{} (\n{}\n);\n", table.name, columns.join(",\n")));
+    }
+
+    // Added once every table exists, so models can refer to each other in any order
+    let foreign_keys: Vec<String> = tables.iter()
+        .flat_map(|table| table.columns.iter().map(move |column| (table, column)))
+        .filter_map(|(table, column)| {
+            let (references, on_delete) = column.references.as_ref()?;
+            Some(format!(
+                "alter table public.{} add foreign key ({}) references public.{} on delete {};\n",
+                table.name, column.name, references, on_delete,
+            ))
+        })
+        .collect();
+    if !foreign_keys.is_empty() {
+        sql.push('\n');
+        sql.push_str(&foreign_keys.concat());
+    }
+
+    for table in tables {
+        let name = &table.name;
+        let read = table.read_check();
+        let write = table.write_check();
+        let readers = if table.public { "anon, authenticated" } else { "authenticated" };
+        sql.push_str(&format!("\nalter table public.{} enable row level security;\n", name));
+        sql.push_str(&format!("create policy \"{0} are readable\" on public.{0} for select to {1} using ({2});\n", name, readers, read));
+        sql.push_str(&format!("create policy \"{0} are insertable\" on public.{0} for insert to authenticated with check ({1});\n", name, write));
+        sql.push_str(&format!(
+            "create policy \"{0} are updatable\" on public.{0} for update to authenticated using ({1}) with check ({1});\n",
+            name, write,
+        ));
+        sql.push_str(&format!("create policy \"{0} are deletable\" on public.{0} for delete to authenticated using ({1});\n", name, write));
+    }
+    sql
+}
+
+/// The `Database` type, in the shape `supabase gen types typescript` writes it, with a
+/// row type per model and a type per enum
+fn database_types_ts(models: &[&Model], tables: &[Table]) -> String {
+    let mut types = String::from(
+        "export type Json = string | number | boolean | null | { [key: string]: Json | undefined } | Json[];\n\n",
+    );
+    types.push_str("export type Database = {\n  public: {\n    Tables: {\n");
+    for table in tables {
+        let row: Vec<String> = table.columns.iter().map(|column| format!("          {}: {};", ts_property(&column.name), column.ts)).collect();
+        let insert: Vec<String> = table.columns.iter()
+            .map(|column| format!("          {}{}: {};", ts_property(&column.name), if column.optional { "?" } else { "" }, column.ts))
+            .collect();
+        let update: Vec<String> = table.columns.iter().map(|column| format!("          {}?: {};", ts_property(&column.name), column.ts)).collect();
+        types.push_str(&format!(
+            "      {}: {{\n        Row: {{\n{}\n        }};\n        Insert: {{\n{}\n        }};\n        Update: {{\n{}\n        }};\n        Relationships: [];\n      }};\n",
+            table.name, row.join("\n"), insert.join("\n"), update.join("\n"),
+        ));
+    }
+    types.push_str("    };\n    Views: { [_ in never]: never };\n    Functions: { [_ in never]: never };\n    Enums: {\n");
+    let enums: Vec<&&Model> = models.iter().filter(|model| model.kind.as_deref() == Some("enum")).collect();
+    for model in &enums {
+        types.push_str(&format!("      {}: {};\n", snake(&model.name), enum_type(model)));
+    }
+    types.push_str("    };\n    CompositeTypes: { [_ in never]: never };\n  };\n};\n");
+
+    if !tables.is_empty() || !enums.is_empty() {
+        types.push('\n');
+    }
+    for table in tables {
+        let name = &table.model.name;
+        types.push_str(&format!("export type {} = Database[\"public\"][\"Tables\"][\"{}\"][\"Row\"];\n", name, table.name));
+        types.push_str(&format!("export type New{} = Database[\"public\"][\"Tables\"][\"{}\"][\"Insert\"];\n", name, table.name));
+    }
+    for model in enums {
+        types.push_str(&format!("export type {} = Database[\"public\"][\"Enums\"][\"{}\"];\n", model.name, snake(&model.name)));
+    }
+    types
+}
+
+/// A typed call per endpoint: `@model` endpoints read and write the model's table,
+/// the others invoke the Edge Function named after them
+fn api_ts(endpoints: &[&Endpoint], tables: &[Table]) -> String {
+    let mut types = Vec::new();
+    let mut calls = Vec::new();
+    for endpoint in endpoints {
+        let method = endpoint.method.as_deref().unwrap_or("get").to_ascii_lowercase();
+        let table = endpoint.annotation("model")
+            .and_then(|annotation| annotation.args.as_deref())
+            .and_then(|name| tables.iter().find(|table| table.model.name == name.trim()));
+        let Some(table) = table else {
+            let name = camel(&endpoint.name.split('/').map(pascal).collect::<String>());
+            calls.push(format!(
+                "export async function {}(body?: Record<string, unknown>, supabase = createClient()) {{\n  const {{ data, error }} = await supabase.functions.invoke({}, {{ body }});\n  if (error) throw error;\n  return data;\n}}",
+                name, js_string(&endpoint.name),
+            ));
+            continue;
+        };
+
+        let model = &table.model.name;
+        let (key, key_type) = &table.key;
+        let from = format!("supabase.from(\"{}\")", table.name);
+        let call = match method.as_str() {
+            "get" => {
+                types.push(model.clone());
+                format!(
+                    "export async function list{}(supabase = createClient()): Promise<{}[]> {{\n  const {{ data, error }} = await {}.select();\n  if (error) throw error;\n  return data;\n}}",
+                    plural(model), model, from,
+                )
+            }
+            "post" => {
+                types.extend([model.clone(), format!("New{}", model)]);
+                format!(
+                    "export async function create{0}(values: New{0}, supabase = createClient()): Promise<{0}> {{\n  const {{ data, error }} = await {1}.insert(values).select().single();\n  if (error) throw error;\n  return data;\n}}",
+                    model, from,
+                )
+            }
+            "put" | "patch" => {
+                types.extend([model.clone(), format!("New{}", model)]);
+                format!(
+                    "export async function update{0}({1}: {2}, values: Partial<New{0}>, supabase = createClient()): Promise<{0}> {{\n  const {{ data, error }} = await {3}.update(values).eq(\"{4}\", {1}).select().single();\n  if (error) throw error;\n  return data;\n}}",
+                    model, camel(key), key_type, from, key,
+                )
+            }
+            _ => format!(
+                "export async function delete{0}({1}: {2}, supabase = createClient()): Promise<void> {{\n  const {{ error }} = await {3}.delete().eq(\"{4}\", {1});\n  if (error) throw error;\n}}",
+                model, camel(key), key_type, from, key,
+            ),
+        };
+        calls.push(call);
+    }
+    types.sort();
+    types.dedup();
+    calls.dedup();
+
+    let mut api = String::from("import { createClient } from \"./supabase/client\";\n");
+    if !types.is_empty() {
+        api.push_str(&format!("import type {{ {} }} from \"./database.types\";\n", types.join(", ")));
+    }
+    api.push_str("\n// Pass the client from `lib/supabase/server` to call these from server code\n");
+    for call in calls {
+        api.push('\n');
+        api.push_str(&call);
+        api.push('\n');
+    }
+    api
+}
+
+const CLIENT_TS: &str = r#"import { createBrowserClient } from "@supabase/ssr";
+import type { Database } from "../database.types";
+
+export function createClient() {
+  return createBrowserClient<Database>(
+    process.env.NEXT_PUBLIC_SUPABASE_URL!,
+    process.env.NEXT_PUBLIC_SUPABASE_ANON_KEY!,
+  );
+}
+"#;
+
+const SERVER_TS: &str = r#"import { createServerClient } from "@supabase/ssr";
+import { cookies } from "next/headers";
+import type { Database } from "../database.types";
+
+export async function createClient() {
+  const cookieStore = await cookies();
+
+  return createServerClient<Database>(
+    process.env.NEXT_PUBLIC_SUPABASE_URL!,
+    process.env.NEXT_PUBLIC_SUPABASE_ANON_KEY!,
+    {
+      cookies: {
+        getAll() {
+          return cookieStore.getAll();
+        },
+        setAll(cookiesToSet) {
+          try {
+            cookiesToSet.forEach(({ name, value, options }) => cookieStore.set(name, value, options));
+          } catch {
+            // Server components can't set cookies; the middleware refreshes the session
+          }
+        },
+      },
+    },
+  );
+}
+"#;
+
+const AUTH_TS: &str = r#"import { createClient } from "./supabase/client";
+
+// Email a sign-in link that comes back through /auth/callback
+export async function signIn(email: string) {
+  const { error } = await createClient().auth.signInWithOtp({
+    email,
+    options: { emailRedirectTo: `${window.location.origin}/auth/callback` },
+  });
+  if (error) throw error;
+}
+
+export async function signOut() {
+  const { error } = await createClient().auth.signOut();
+  if (error) throw error;
+}
+"#;
+
+const MIDDLEWARE_TS: &str = r#"import { createServerClient } from "@supabase/ssr";
+import { NextResponse, type NextRequest } from "next/server";
+
+// Refreshes the session cookie on every request, so server code sees the signed-in user
+export async function middleware(request: NextRequest) {
+  let response = NextResponse.next({ request });
+
+  const supabase = createServerClient(
+    process.env.NEXT_PUBLIC_SUPABASE_URL!,
+    process.env.NEXT_PUBLIC_SUPABASE_ANON_KEY!,
+    {
+      cookies: {
+        getAll() {
+          return request.cookies.getAll();
+        },
+        setAll(cookiesToSet) {
+          cookiesToSet.forEach(({ name, value }) => request.cookies.set(name, value));
+          response = NextResponse.next({ request });
+          cookiesToSet.forEach(({ name, value, options }) => response.cookies.set(name, value, options));
+        },
+      },
+    },
+  );
+  await supabase.auth.getUser();
+
+  return response;
+}
+
+export const config = {
+  matcher: ["/((?!_next/static|_next/image|favicon.ico|.*\\.(?:svg|png|jpg|jpeg|gif|webp)$).*)"],
+};
+"#;
+
+const CALLBACK_TS: &str = r#"import { NextResponse } from "next/server";
+import { createClient } from "@/lib/supabase/server";
+
+// Where sign-in links land: trades the code for a session, then goes on to `next`
+export async function GET(request: Request) {
+  const { searchParams, origin } = new URL(request.url);
+  const code = searchParams.get("code");
+  const next = searchParams.get("next") ?? "/";
+
+  if (code) {
+    const supabase = await createClient();
+    const { error } = await supabase.auth.exchangeCodeForSession(code);
+    if (!error) {
+      return NextResponse.redirect(`${origin}${next}`);
+    }
+  }
+  return NextResponse.redirect(`${origin}/?error=auth`);
+}
+"#;
+
+const ENV_EXAMPLE: &str = r#"# From `supabase status` for the local stack, or the project's API settings
+NEXT_PUBLIC_SUPABASE_URL=http://127.0.0.1:54321
+NEXT_PUBLIC_SUPABASE_ANON_KEY=
+"#;
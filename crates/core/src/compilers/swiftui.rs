@@ -1,28 +1,42 @@
-use z_ast::{Element, Node};
-use super::TargetCompiler;
-
-pub struct SwiftUICompiler;
+use serde_json::json;
+use z_semantic::{DeepLinks, Namespace, Profile, Section, Target};
+use super::{Capabilities, TargetCompiler};
+use crate::target_options::CompilerOptions;
+use crate::templates::{context, Templates};
+
+/// Oldest iOS and macOS versions an app runs on, without the `ios` and `macos` options
+const IOS: u32 = 15;
+const MACOS: u32 = 12;
+
+pub struct SwiftUICompiler {
+    templates: Templates,
+}
 
 impl SwiftUICompiler {
-    pub fn new() -> Self {
-        Self
+    pub fn new(templates: Templates) -> Self {
+        Self { templates }
     }
 }
 
 impl TargetCompiler for SwiftUICompiler {
-    fn compile(&self, ast: &Element) -> Result<String, String> {
+    fn compile(&self, target: &Target, options: &CompilerOptions) -> Result<String, String> {
         let mut output = String::new();
 
         // Generate main App structure
-        output.push_str(&self.generate_app_file(ast)?);
+        output.push_str(&self.generate_app_file(target)?);
         output.push_str("\n\n");
 
         // Generate ContentView
-        output.push_str(&self.generate_content_view(ast)?);
+        output.push_str(&self.generate_content_view(target)?);
         output.push_str("\n\n");
 
         // Generate Package.swift
-        output.push_str(&self.generate_package_swift());
+        let mut context = context(target);
+        context["platforms"] = json!({
+            "ios": platform_version(options, "ios", IOS)?,
+            "macos": platform_version(options, "macos", MACOS)?,
+        });
+        output.push_str(&self.templates.render("swift/Package.swift", &context)?);
 
         Ok(output)
     }
@@ -34,25 +48,91 @@ impl TargetCompiler for SwiftUICompiler {
     fn file_extension(&self) -> &str {
         "swift"
     }
+
+    fn capabilities(&self, _options: &CompilerOptions) -> Option<Capabilities> {
+        Some(Capabilities::sections([Namespace::App, Namespace::Components]))
+    }
 }
 
 impl SwiftUICompiler {
-    fn generate_app_file(&self, _ast: &Element) -> Result<String, String> {
-        let app_swift = r#"// ZGeneratedApp.swift
-import SwiftUI
-
-@main
-struct ZGeneratedApp: App {
-    var body: some Scene {
-        WindowGroup {
-            ContentView()
+    fn generate_app_file(&self, target: &Target) -> Result<String, String> {
+        // Shared state injected into every view
+        let app_state = target.profile >= Profile::Full;
+        let links = target.links.as_ref();
+
+        let mut app = String::from("// ZGeneratedApp.swift\nimport SwiftUI\n\n");
+        if app_state {
+            app.push_str(r#"final class AppState: ObservableObject {
+    @Published var isLoading = false
+    @Published var errorMessage: String?
+}
+
+"#);
+        }
+        if let Some(links) = links {
+            app.push_str(&self.generate_deep_link(links));
+        }
+
+        app.push_str("@main\nstruct ZGeneratedApp: App {\n");
+        if app_state {
+            app.push_str("    @StateObject private var appState = AppState()\n");
         }
+        if links.is_some() {
+            app.push_str("    @State private var deepLink: DeepLink?\n");
+        }
+        if app_state || links.is_some() {
+            app.push('\n');
+        }
+        app.push_str("    var body: some Scene {\n        WindowGroup {\n            ContentView()\n");
+        if app_state {
+            app.push_str("                .environmentObject(appState)\n");
+        }
+        if links.is_some() {
+            app.push_str("                .environment(\\.deepLink, deepLink)\n");
+            app.push_str("                .onOpenURL { url in deepLink = DeepLink(url: url) }\n");
+        }
+        app.push_str("        }\n    }\n}");
+        Ok(app)
     }
-}"#;
-        Ok(app_swift.to_string())
+
+    /// `DeepLink`, the path of the link the app was opened with, read by views from the environment
+    fn generate_deep_link(&self, links: &DeepLinks) -> String {
+        let domains: Vec<String> = links.domains.iter().map(|domain| format!("{:?}", domain)).collect();
+        let scheme = links.scheme.as_ref().map_or("nil".to_string(), |scheme| format!("{:?}", scheme));
+        format!(r#"/// A link the app was opened with, as the path shared with the web app (`/notes/42`)
+struct DeepLink: Equatable {{
+    static let scheme: String? = {}
+    static let domains: [String] = [{}]
+
+    let path: String
+
+    init?(url: URL) {{
+        if let scheme = DeepLink.scheme, url.scheme == scheme {{
+            // In `{}://notes/42` the first segment is the host
+            path = "/" + ((url.host ?? "") + url.path).trimmingCharacters(in: CharacterSet(charactersIn: "/"))
+        }} else if url.scheme == "https", let host = url.host, DeepLink.domains.contains(host) {{
+            path = url.path.isEmpty ? "/" : url.path
+        }} else {{
+            return nil
+        }}
+    }}
+}}
+
+private struct DeepLinkKey: EnvironmentKey {{
+    static let defaultValue: DeepLink? = nil
+}}
+
+extension EnvironmentValues {{
+    var deepLink: DeepLink? {{
+        get {{ self[DeepLinkKey.self] }}
+        set {{ self[DeepLinkKey.self] = newValue }}
+    }}
+}}
+
+"#, scheme, domains.join(", "), links.scheme.as_deref().unwrap_or("app"))
     }
 
-    fn generate_content_view(&self, ast: &Element) -> Result<String, String> {
+    fn generate_content_view(&self, target: &Target) -> Result<String, String> {
         let mut content_view = String::new();
         content_view.push_str("// ContentView.swift\n");
         content_view.push_str("import SwiftUI\n\n");
@@ -60,19 +140,20 @@ struct ZGeneratedApp: App {
         content_view.push_str("    var body: some View {\n");
         content_view.push_str("        NavigationView {\n");
         content_view.push_str("            VStack(spacing: 20) {\n");
-        content_view.push_str("                Text(\"Z Generated App\")\n");
+        content_view.push_str(&format!("                Text({:?})\n", target.display_name()));
         content_view.push_str("                    .font(.largeTitle)\n");
         content_view.push_str("                    .fontWeight(.bold)\n");
         content_view.push_str("                    .foregroundColor(.primary)\n\n");
 
-        // Extract and generate child components
-        for child in &ast.children {
-            if let Node::Element(element) = child {
-                match element.name.as_str() {
-                    "App" => content_view.push_str(&self.generate_app_component(element)),
-                    "Components" => content_view.push_str(&self.generate_components_component(element)),
-                    _ => content_view.push_str(&format!("                // Unknown component: {}\n", element.name)),
-                }
+        // Extract and generate child components (placeholder cards are left out of minimal scaffolds)
+        let sections = if target.profile >= Profile::Standard { target.sections.as_slice() } else { &[] };
+        for section in sections {
+            match section.namespace {
+                Namespace::App => content_view.push_str(&self.generate_app_component(section)),
+                Namespace::Components => content_view.push_str(&self.generate_components_component(section)),
+                // Release metadata, written next to the sources rather than into them
+                Namespace::Store => {}
+                _ => content_view.push_str(&format!("                // Unknown component: {}\n", section.namespace)),
             }
         }
 
@@ -90,7 +171,7 @@ struct ZGeneratedApp: App {
         Ok(content_view)
     }
 
-    fn generate_app_component(&self, _element: &Element) -> String {
+    fn generate_app_component(&self, _section: &Section) -> String {
         r#"                VStack {
                     Image(systemName: "app.badge")
                         .font(.system(size: 40))
@@ -105,7 +186,7 @@ struct ZGeneratedApp: App {
 "#.to_string()
     }
 
-    fn generate_components_component(&self, _element: &Element) -> String {
+    fn generate_components_component(&self, _section: &Section) -> String {
         r#"                VStack {
                     Image(systemName: "puzzlepiece.extension")
                         .font(.system(size: 40))
@@ -119,31 +200,14 @@ struct ZGeneratedApp: App {
 
 "#.to_string()
     }
-
-    fn generate_package_swift(&self) -> String {
-        r#"// Package.swift
-// swift-tools-version: 5.9
-import PackageDescription
-
-let package = Package(
-    name: "ZGeneratedApp",
-    platforms: [
-        .iOS(.v15),
-        .macOS(.v12)
-    ],
-    products: [
-        .executable(
-            name: "ZGeneratedApp",
-            targets: ["ZGeneratedApp"]
-        ),
-    ],
-    dependencies: [],
-    targets: [
-        .executableTarget(
-            name: "ZGeneratedApp",
-            dependencies: []
-        ),
-    ]
-)"#.to_string()
+}
+/// The major version in the option `key`, one Swift packages can name, or `default`
+fn platform_version(options: &CompilerOptions, key: &str, default: u32) -> Result<u32, String> {
+    let Some(value) = options.get(key) else {
+        return Ok(default);
+    };
+    match value.parse::<u32>() {
+        Ok(version) if version >= default => Ok(version),
+        _ => Err(format!("invalid {} version '{}' (expected a major version, {} or later)", key, value, default)),
     }
-}
\ No newline at end of file
+}
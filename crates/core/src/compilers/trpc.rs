@@ -0,0 +1,320 @@
+//! tRPC for Next.js targets with `api: trpc`.
+//!
+//! Instead of leaving `app/api` to hand-written route handlers, every endpoint in the
+//! `API` section becomes a procedure of one router served from `/api/trpc`. Types flow
+//! from the `Schema` section to the client: each model gets a zod schema in
+//! `server/schema.ts`, procedures validate their input with it, and `lib/hooks.ts` wraps
+//! every procedure in a React Query hook.
+//!
+//! - `get users` is the query `users`; `post`, `put`/`patch` and `delete` become the
+//!   mutations `createUsers`, `updateUsers` and `deleteUsers`
+//! - Parameters in the signature (`get user(id: string)`) are the procedure's input
+//! - With `@model(User)`, the procedure lists, saves or deletes `User` records in
+//!   `server/db.ts`, an in-memory store to be swapped for a database; without it, it
+//!   answers `NOT_IMPLEMENTED`
+
+use z_semantic::{Annotated, Endpoint, Model, Target};
+
+use super::output::Output;
+use super::{base_type, camel, is_stored, key, pascal, plural, ts_property};
+
+/// Packages the generated files import, with the versions they're written for
+pub(super) const DEPENDENCIES: [(&str, &str); 6] = [
+    ("@tanstack/react-query", "^5.59.0"),
+    ("@trpc/client", "^11.0.0"),
+    ("@trpc/react-query", "^11.0.0"),
+    ("@trpc/server", "^11.0.0"),
+    ("superjson", "^2.2.1"),
+    ("zod", "^3.23.8"),
+];
+
+/// Write the router, its route handler and the client for `target` into `out`
+pub(super) fn create_trpc(target: &Target, out: &mut Output) {
+    let endpoints: Vec<&Endpoint> = target.endpoints().collect();
+    let models: Vec<&Model> = target.models().collect();
+    let procedures: Vec<Procedure> = endpoints.iter().map(|endpoint| Procedure::new(endpoint, &models)).collect();
+
+    out.file("server/trpc.ts", TRPC_TS);
+    out.file("server/schema.ts", schema_ts(&models));
+    if procedures.iter().any(|procedure| procedure.model.is_some()) {
+        out.file("server/db.ts", db_ts(&models));
+    }
+    out.file("server/routers/_app.ts", router_ts(&procedures, &models));
+    out.file("app/api/trpc/[trpc]/route.ts", ROUTE_TS);
+    out.file("lib/trpc.ts", CLIENT_TS);
+    out.file("lib/hooks.ts", hooks_ts(&procedures));
+    out.file("app/providers.tsx", PROVIDERS_TSX);
+}
+
+/// An endpoint as a router procedure
+struct Procedure<'a> {
+    name: String,
+    method: String,
+    /// Parameters from the signature, as `(name, type)`
+    params: Vec<(String, String)>,
+    /// The stored model from `@model(Name)`
+    model: Option<&'a Model>,
+}
+
+impl<'a> Procedure<'a> {
+    fn new(endpoint: &Endpoint, models: &[&'a Model]) -> Self {
+        let method = endpoint.method.as_deref().unwrap_or("get").to_ascii_lowercase();
+        let path: String = endpoint.name.split('/').map(pascal).collect();
+        let name = match method.as_str() {
+            "get" => camel(&path),
+            "post" => format!("create{}", path),
+            "put" | "patch" => format!("update{}", path),
+            other => format!("{}{}", camel(other), path),
+        };
+        let model = endpoint.annotation("model")
+            .and_then(|annotation| annotation.args.as_deref())
+            .and_then(|name| models.iter().copied().find(|model| model.name == name.trim() && is_stored(model)));
+        Procedure { name, method, params: params(endpoint.signature.as_deref()), model }
+    }
+
+    fn is_query(&self) -> bool {
+        self.method == "get"
+    }
+}
+
+/// `(id: string, page?: number)` as `[("id", "string"), ("page?", "number")]`
+fn params(signature: Option<&str>) -> Vec<(String, String)> {
+    let Some(inner) = signature.map(|signature| signature.trim().trim_start_matches('(').trim_end_matches(')')) else {
+        return Vec::new();
+    };
+    inner.split(',')
+        .filter_map(|param| {
+            let (name, ty) = param.split_once(':').unwrap_or((param, "string"));
+            let name = name.trim();
+            (!name.is_empty()).then(|| (name.to_string(), ty.trim().to_string()))
+        })
+        .collect()
+}
+
+fn schema_ts(models: &[&Model]) -> String {
+    let mut schema = String::from("import { z } from \"zod\";\n");
+    for model in models {
+        schema.push('\n');
+        let name = &model.name;
+        if model.kind.as_deref() == Some("enum") {
+            let values: Vec<String> = model.values.iter().map(|value| format!("\"{}\"", value)).collect();
+            let zod = if values.is_empty() { "z.never()".to_string() } else { format!("z.enum([{}])", values.join(", ")) };
+            schema.push_str(&format!("export const {}Schema = {};\n", name, zod));
+        } else {
+            schema.push_str(&format!("export const {}Schema = z.object({{\n", name));
+            if is_stored(model) && !model.fields.iter().any(|field| field.name == key(model).0) {
+                schema.push_str("  id: z.string(),\n");
+            }
+            for field in &model.fields {
+                schema.push_str(&format!("  {}: {},\n", ts_property(&field.name), zod_field(&field.ty, models)));
+            }
+            schema.push_str("});\n");
+        }
+        schema.push_str(&format!("export type {0} = z.infer<typeof {0}Schema>;\n", name));
+    }
+    schema
+}
+
+/// A field type as a zod schema, `.optional()` when it ends in `?`
+fn zod_field(ty: &str, models: &[&Model]) -> String {
+    match ty.trim().strip_suffix('?') {
+        Some(ty) => format!("{}.optional()", zod_type(ty.trim(), models)),
+        None => zod_type(ty.trim(), models),
+    }
+}
+
+/// A schema type as a zod schema; models are referenced lazily, since they may be
+/// declared further down
+fn zod_type(ty: &str, models: &[&Model]) -> String {
+    if ty.contains('|') {
+        let members: Vec<String> = ty.split('|').map(|member| zod_type(member.trim(), models)).collect();
+        return format!("z.union([{}])", members.join(", "));
+    }
+    if let Some(element) = ty.strip_suffix("[]") {
+        return format!("z.array({})", zod_type(element.trim(), models));
+    }
+    match base_type(ty) {
+        "string" | "text" | "cuid" => "z.string()".to_string(),
+        "uuid" => "z.string().uuid()".to_string(),
+        "email" => "z.string().email()".to_string(),
+        "url" => "z.string().url()".to_string(),
+        "int" | "integer" | "bigint" | "serial" => "z.number().int()".to_string(),
+        "number" | "float" | "double" | "decimal" => "z.number()".to_string(),
+        "bool" | "boolean" => "z.boolean()".to_string(),
+        "date" | "datetime" | "timestamp" => "z.coerce.date()".to_string(),
+        "null" => "z.null()".to_string(),
+        name if models.iter().any(|model| model.name == name) => format!("z.lazy(() => {}Schema)", name),
+        _ => "z.unknown()".to_string(),
+    }
+}
+
+/// In-memory stores for the stored models
+fn db_ts(models: &[&Model]) -> String {
+    let stored: Vec<&&Model> = models.iter().filter(|model| is_stored(model)).collect();
+    let types: Vec<&str> = stored.iter().map(|model| model.name.as_str()).collect();
+    let mut db = format!("import type {{ {} }} from \"./schema\";\n\n", types.join(", "));
+    db.push_str("// Kept in memory for as long as the server runs; replace with a database\n");
+    for model in stored {
+        let (_, key_type) = key(model);
+        db.push_str(&format!("export const {} = new Map<{}, {}>();\n", camel(&plural(&model.name)), key_type, model.name));
+    }
+    db
+}
+
+fn router_ts(procedures: &[Procedure], models: &[&Model]) -> String {
+    let mut schema_imports: Vec<String> = Vec::new();
+    let mut stores: Vec<String> = Vec::new();
+    let mut uses_zod = false;
+    let mut not_implemented = false;
+
+    let mut entries = Vec::new();
+    for procedure in procedures {
+        let kind = if procedure.is_query() { "query" } else { "mutation" };
+        let mut input = if procedure.params.is_empty() {
+            None
+        } else {
+            uses_zod = true;
+            let fields: Vec<String> = procedure.params.iter()
+                .map(|(name, ty)| {
+                    let (name, optional) = name.strip_suffix('?').map_or((name.as_str(), ""), |name| (name, "?"));
+                    let referenced = models.iter().filter(|model| ty.split(['|', '[', ']', '?', ' ']).any(|part| part == model.name));
+                    schema_imports.extend(referenced.map(|model| format!("{}Schema", model.name)));
+                    format!("{}: {}", ts_property(name), zod_field(&format!("{}{}", ty, optional), models))
+                })
+                .collect();
+            Some(format!("z.object({{ {} }})", fields.join(", ")))
+        };
+
+        let body = match procedure.model {
+            Some(model) => {
+                let store = camel(&plural(&model.name));
+                stores.push(store.clone());
+                let (key, key_type) = key(model);
+                match procedure.method.as_str() {
+                    "get" => format!("() => [...{}.values()]", store),
+                    "delete" => {
+                        uses_zod = true;
+                        let zod = if key_type == "number" { "z.number()" } else { "z.string()" };
+                        input = Some(format!("z.object({{ {}: {} }})", ts_property(&key), zod));
+                        format!("({{ input }}) => {{\n      {}.delete(input.{});\n    }}", store, key)
+                    }
+                    method => {
+                        schema_imports.push(format!("{}Schema", model.name));
+                        if method == "post" && key_type == "string" {
+                            // New records get their key here rather than from the client
+                            input = Some(format!("{}Schema.omit({{ {}: true }})", model.name, ts_property(&key)));
+                            format!(
+                                "({{ input }}) => {{\n      const {0} = {{ ...input, {1}: crypto.randomUUID() }};\n      {2}.set({0}.{3}, {0});\n      return {0};\n    }}",
+                                camel(&model.name), ts_property(&key), store, key,
+                            )
+                        } else {
+                            input = Some(format!("{}Schema", model.name));
+                            format!("({{ input }}) => {{\n      {}.set(input.{}, input);\n      return input;\n    }}", store, key)
+                        }
+                    }
+                }
+            }
+            None => {
+                not_implemented = true;
+                "() => {\n      throw new TRPCError({ code: \"NOT_IMPLEMENTED\" });\n    }".to_string()
+            }
+        };
+        let input = input.map_or_else(String::new, |input| format!("\n    .input({})", input));
+        entries.push(format!("  {}: publicProcedure{}\n    .{}({}),", procedure.name, input, kind, body));
+    }
+    schema_imports.sort();
+    schema_imports.dedup();
+    stores.sort();
+    stores.dedup();
+
+    let mut router = String::new();
+    if not_implemented {
+        router.push_str("import { TRPCError } from \"@trpc/server\";\n");
+    }
+    if uses_zod {
+        router.push_str("import { z } from \"zod\";\n");
+    }
+    router.push_str("import { publicProcedure, router } from \"../trpc\";\n");
+    if !schema_imports.is_empty() {
+        router.push_str(&format!("import {{ {} }} from \"../schema\";\n", schema_imports.join(", ")));
+    }
+    if !stores.is_empty() {
+        router.push_str(&format!("import {{ {} }} from \"../db\";\n", stores.join(", ")));
+    }
+    router.push_str(&format!("\nexport const appRouter = router({{\n{}\n}});\n\n", entries.join("\n")));
+    router.push_str("export type AppRouter = typeof appRouter;\n");
+    router
+}
+
+/// A React Query hook per procedure: `useUsers()`, `useCreateUsers()`, ...
+fn hooks_ts(procedures: &[Procedure]) -> String {
+    let mut hooks = String::from("\"use client\";\n\nimport { trpc } from \"./trpc\";\n");
+    for procedure in procedures {
+        let hook = format!("use{}", pascal(&procedure.name));
+        if !procedure.is_query() {
+            hooks.push_str(&format!("\nexport const {} = () => trpc.{}.useMutation();\n", hook, procedure.name));
+        } else if procedure.params.is_empty() {
+            hooks.push_str(&format!("\nexport const {} = () => trpc.{}.useQuery();\n", hook, procedure.name));
+        } else {
+            hooks.push_str(&format!(
+                "\nexport const {0} = (input: Parameters<typeof trpc.{1}.useQuery>[0]) => trpc.{1}.useQuery(input);\n",
+                hook, procedure.name,
+            ));
+        }
+    }
+    hooks
+}
+
+const TRPC_TS: &str = r#"import { initTRPC } from "@trpc/server";
+import superjson from "superjson";
+
+// superjson keeps dates and maps intact between the server and the client
+const t = initTRPC.create({ transformer: superjson });
+
+export const router = t.router;
+export const publicProcedure = t.procedure;
+"#;
+
+const ROUTE_TS: &str = r#"import { fetchRequestHandler } from "@trpc/server/adapters/fetch";
+import { appRouter } from "@/server/routers/_app";
+
+const handler = (req: Request) =>
+  fetchRequestHandler({
+    endpoint: "/api/trpc",
+    req,
+    router: appRouter,
+    createContext: () => ({}),
+  });
+
+export { handler as GET, handler as POST };
+"#;
+
+const CLIENT_TS: &str = r#"import { createTRPCReact } from "@trpc/react-query";
+import type { AppRouter } from "@/server/routers/_app";
+
+export const trpc = createTRPCReact<AppRouter>();
+"#;
+
+const PROVIDERS_TSX: &str = r#""use client";
+
+import { QueryClient, QueryClientProvider } from "@tanstack/react-query";
+import { httpBatchLink } from "@trpc/client";
+import { useState } from "react";
+import superjson from "superjson";
+import { trpc } from "@/lib/trpc";
+
+export function Providers({ children }: { children: React.ReactNode }) {
+  const [queryClient] = useState(() => new QueryClient());
+  const [trpcClient] = useState(() =>
+    trpc.createClient({
+      links: [httpBatchLink({ url: "/api/trpc", transformer: superjson })],
+    }),
+  );
+
+  return (
+    <trpc.Provider client={trpcClient} queryClient={queryClient}>
+      <QueryClientProvider client={queryClient}>{children}</QueryClientProvider>
+    </trpc.Provider>
+  );
+}
+"#;
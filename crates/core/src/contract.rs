@@ -0,0 +1,390 @@
+//! Types and routes a workspace's frontends and backend agree on.
+//!
+//! When a workspace, or the top level of a program, has both a TypeScript frontend
+//! (`next` or `deno`) and a `rust` backend, the models of its shared `Schema` are
+//! generated on both sides from the same declarations: interfaces in
+//! `lib/shared/models.ts` and structs in `src/models.rs`. The backend's `API` routes
+//! become a trait to implement in `src/api.rs`, and every frontend gets a typed fetch
+//! client for them, so the two sides stay in sync by construction.
+
+use std::path::Path;
+
+use z_semantic::{Annotated, Endpoint, Model, Namespace, Profile, SectionBody, Target};
+
+use crate::compilers::{base_type, key, pascal, snake, ts_property};
+use crate::naming::{Naming, Role};
+use crate::sink::FileSink;
+use crate::style::CodeStyle;
+use crate::tr;
+
+/// Write the shared types of `target`, and the client or trait for the backend's
+/// routes, if `targets` has a peer on the other side of the wire
+pub(crate) fn write_shared_types(target: &Target, targets: &[Target], naming: &Naming, sink: &dyn FileSink, style: &CodeStyle) -> Result<(), String> {
+    let models = shared_models(target);
+    let mut peers = targets.iter().filter(|peer| peer.workspace == target.workspace && peer.qualified_name() != target.qualified_name());
+    if is_frontend(target) {
+        let backends: Vec<&Target> = peers.filter(|peer| is_backend(peer)).collect();
+        if backends.is_empty() || (models.is_empty() && backends.iter().all(|backend| backend.endpoints().next().is_none())) {
+            return Ok(());
+        }
+        // Deno resolves imports by their full name
+        let extension = if target.kind == "deno" { ".ts" } else { "" };
+        let dir = Path::new("lib/shared");
+        if !models.is_empty() {
+            write(sink, &dir.join("models.ts"), &models_ts(&models), style)?;
+        }
+        for backend in backends.into_iter().filter(|backend| backend.endpoints().next().is_some()) {
+            let client = client_ts(backend, &models, extension, naming);
+            write(sink, &dir.join(format!("{}.ts", naming.name(Role::Module, &backend.name))), &client, style)?;
+        }
+    } else if is_backend(target) && peers.any(is_frontend) {
+        let has_api = target.endpoints().next().is_some();
+        if models.is_empty() && !has_api {
+            return Ok(());
+        }
+        let src = Path::new("src");
+        let mut modules = Vec::new();
+        if !models.is_empty() {
+            write(sink, &src.join("models.rs"), &models_rs(&models, target.profile, naming), style)?;
+            modules.push("mod models;");
+        }
+        if has_api {
+            write(sink, &src.join("api.rs"), &api_rs(target, &models, naming), style)?;
+            modules.push("mod api;");
+        }
+        declare_modules(sink, &src.join("main.rs"), &modules)?;
+    }
+    Ok(())
+}
+
+/// The sections of `target` read here rather than by its compiler: a backend's `API`
+/// and `Schema` when there's a frontend to share them with
+pub(crate) fn sections(target: &Target, targets: &[Target]) -> &'static [Namespace] {
+    let mut peers = targets.iter().filter(|peer| peer.workspace == target.workspace);
+    if is_backend(target) && peers.any(is_frontend) {
+        &[Namespace::Api, Namespace::Schema]
+    } else {
+        &[]
+    }
+}
+
+/// What the shared types of `target` depend on beyond its own block and the shared
+/// ones: the peers it's generated for and their routes
+pub(crate) fn fingerprint(target: &Target, targets: &[Target]) -> String {
+    let peers = targets.iter().filter(|peer| {
+        peer.workspace == target.workspace
+            && peer.qualified_name() != target.qualified_name()
+            && ((is_frontend(target) && is_backend(peer)) || (is_backend(target) && is_frontend(peer)))
+    });
+    let mut fingerprint = String::new();
+    for peer in peers {
+        fingerprint.push_str(&format!("{}\n", peer.qualified_name()));
+        if is_backend(peer) {
+            for endpoint in peer.endpoints() {
+                fingerprint.push_str(&format!("  {:?} {} {:?}\n", endpoint.method, endpoint.name, model_name(endpoint)));
+            }
+        }
+    }
+    fingerprint
+}
+
+fn is_frontend(target: &Target) -> bool {
+    matches!(target.kind.as_str(), "next" | "deno")
+}
+
+fn is_backend(target: &Target) -> bool {
+    target.kind == "rust"
+}
+
+/// Models of the `Schema` sections the target shares with its workspace, which lie
+/// outside its own block
+fn shared_models(target: &Target) -> Vec<&Model> {
+    target.sections.iter()
+        .filter(|section| section.span.start < target.span.start || section.span.end > target.span.end)
+        .flat_map(|section| match &section.body {
+            SectionBody::Models(models) => models.as_slice(),
+            _ => &[],
+        })
+        .collect()
+}
+
+/// Write `contents` to `path` in the project, in the code style
+pub(crate) fn write(sink: &dyn FileSink, path: &Path, contents: &str, style: &CodeStyle) -> Result<(), String> {
+    sink.write_text(path, &style.apply(path, contents))
+}
+
+/// Add `modules` to the backend's `main.rs`, under its header comment
+pub(crate) fn declare_modules(sink: &dyn FileSink, main_rs: &Path, modules: &[&str]) -> Result<(), String> {
+    let main = sink.read_text(main_rs)?
+        .ok_or_else(|| tr!("read-failed", path = main_rs.display(), error = std::io::Error::from(std::io::ErrorKind::NotFound)))?;
+    let (header, rest) = match main.split_once('\n') {
+        Some((header, rest)) if header.starts_with("//") => (format!("{}\n", header), rest),
+        _ => (String::new(), main.as_str()),
+    };
+    let declared = format!("{}{}\n\n{}", header, modules.join("\n"), rest);
+    sink.write_text(main_rs, &declared)
+}
+
+/// The model named by an endpoint's `@model(Name)`
+fn model_name(endpoint: &Endpoint) -> Option<&str> {
+    endpoint.annotation("model").and_then(|annotation| annotation.args.as_deref()).map(str::trim)
+}
+
+/// One route of the backend, as both sides see it
+struct Call<'a> {
+    method: String,
+    /// `/api/products/{id}`, with the path parameters in braces
+    path: String,
+    params: Vec<&'a str>,
+    model: Option<&'a Model>,
+}
+
+impl<'a> Call<'a> {
+    fn of(endpoint: &'a Endpoint, models: &[&'a Model]) -> Self {
+        let method = endpoint.method.as_deref().unwrap_or("get").to_ascii_uppercase();
+        let mut path = String::from("/api");
+        let mut params = Vec::new();
+        for segment in endpoint.name.split('/').filter(|segment| !segment.is_empty()) {
+            match segment.strip_prefix('[').and_then(|segment| segment.strip_suffix(']')) {
+                Some(param) => {
+                    path.push_str(&format!("/{{{}}}", param));
+                    params.push(param);
+                }
+                None => path.push_str(&format!("/{}", segment)),
+            }
+        }
+        let model = model_name(endpoint).and_then(|name| models.iter().copied().find(|model| model.name == name));
+        Self { method, path, params, model }
+    }
+
+    /// `getProducts` for `get products`
+    fn name(&self, path: &str) -> String {
+        format!("{}{}", self.method.to_ascii_lowercase(), pascal(path))
+    }
+
+    /// The key field and type of the record a `DELETE` of a model removes
+    fn key(&self) -> Option<(String, String)> {
+        let model = self.model.filter(|_| self.method == "DELETE")?;
+        let (name, _) = key(model);
+        let ty = model.fields.iter().find(|field| field.name == name).map_or("string".to_string(), |field| field.ty.clone());
+        Some((name, ty))
+    }
+}
+
+/// The shared models as TypeScript, as they travel as JSON: dates are ISO strings
+fn models_ts(models: &[&Model]) -> String {
+    let mut ts = String::from("// Generated from the shared Schema; the backend's src/models.rs has the same types\n");
+    for model in models {
+        ts.push('\n');
+        if model.kind.as_deref() == Some("enum") {
+            let values: Vec<String> = model.values.iter().map(|value| format!("\"{}\"", value)).collect();
+            let values = if values.is_empty() { "never".to_string() } else { values.join(" | ") };
+            ts.push_str(&format!("export type {} = {};\n", model.name, values));
+            continue;
+        }
+        ts.push_str(&format!("export interface {} {{\n", model.name));
+        for field in &model.fields {
+            ts.push_str(&format!("  {}: {};\n", ts_property(&field.name), ts_type(&field.ty, models)));
+        }
+        ts.push_str("}\n");
+    }
+    ts
+}
+
+/// A schema field type in TypeScript; anything that isn't built in or a shared model
+/// is `unknown`
+fn ts_type(ty: &str, models: &[&Model]) -> String {
+    let ty = ty.trim();
+    if ty.contains('|') {
+        return ty.split('|').map(|part| ts_type(part, models)).collect::<Vec<_>>().join(" | ");
+    }
+    if let Some(element) = ty.strip_suffix("[]") {
+        return format!("{}[]", ts_type(element, models));
+    }
+    match base_type(ty) {
+        "string" | "text" | "uuid" | "email" | "url" | "cuid" | "date" | "datetime" | "timestamp" => "string".to_string(),
+        "int" | "integer" | "number" | "float" | "double" | "decimal" | "bigint" | "serial" => "number".to_string(),
+        "bool" | "boolean" => "boolean".to_string(),
+        "null" => "null".to_string(),
+        name if models.iter().any(|model| model.name == name) => name.to_string(),
+        _ => "unknown".to_string(),
+    }
+}
+
+/// A fetch client for the routes of `backend`, made with `create<Name>Client(baseUrl)`
+fn client_ts(backend: &Target, models: &[&Model], extension: &str, naming: &Naming) -> String {
+    let calls: Vec<(String, Call)> = backend.endpoints().map(|endpoint| (endpoint.name.clone(), Call::of(endpoint, models))).collect();
+    let mut used: Vec<&str> = calls.iter().filter_map(|(_, call)| call.model.map(|model| model.name.as_str())).collect();
+    used.sort();
+    used.dedup();
+
+    let mut ts = format!(
+        "// Generated client for the API of {}; its src/api.rs has the same routes\n",
+        backend.qualified_name(),
+    );
+    if !used.is_empty() {
+        ts.push_str(&format!("\nimport type {{ {} }} from \"./models{}\";\n", used.join(", "), extension));
+    }
+    ts.push_str(&format!("\nexport function create{}Client(baseUrl: string) {{\n", pascal(&backend.name)));
+    ts.push_str(CLIENT_REQUEST);
+    ts.push_str("\n  return {\n");
+    for (name, call) in &calls {
+        let mut args: Vec<String> = call.params.iter().map(|param| format!("{}: string", naming.name(Role::Field, param))).collect();
+        let mut path = call.path.clone();
+        for param in &call.params {
+            path = path.replace(&format!("{{{}}}", param), &format!("${{encodeURIComponent({})}}", naming.name(Role::Field, param)));
+        }
+        let mut body = String::new();
+        let response = match (call.method.as_str(), call.model) {
+            ("GET", Some(model)) => format!("{}[]", model.name),
+            ("POST" | "PUT" | "PATCH", Some(model)) => {
+                args.push(format!("body: {}", model.name));
+                body = ", body".to_string();
+                model.name.clone()
+            }
+            ("DELETE", Some(_)) => "void".to_string(),
+            _ => "unknown".to_string(),
+        };
+        if let Some((key, ty)) = call.key() {
+            args.push(format!("{}: {}", naming.name(Role::Field, &key), ts_type(&ty, models)));
+            path.push_str(&format!("?{}=${{encodeURIComponent({})}}", key, naming.name(Role::Field, &key)));
+        }
+        ts.push_str(&format!(
+            "    {}: ({}) => request<{}>(\"{}\", `{}`{}),\n",
+            call.name(name), args.join(", "), response, call.method, path, body,
+        ));
+    }
+    ts.push_str("  };\n}\n");
+    ts
+}
+
+const CLIENT_REQUEST: &str = r#"  async function request<T>(method: string, path: string, body?: unknown): Promise<T> {
+    const response = await fetch(new URL(path, baseUrl), {
+      method,
+      headers: body === undefined ? undefined : { "Content-Type": "application/json" },
+      body: body === undefined ? undefined : JSON.stringify(body),
+    });
+    if (!response.ok) {
+      throw new Error(`${method} ${path} failed with status ${response.status}`);
+    }
+    return (response.status === 204 ? undefined : await response.json()) as T;
+  }
+"#;
+
+/// The shared models as Rust, named as in the source on the wire. serde is only a
+/// dependency from the standard profile up.
+fn models_rs(models: &[&Model], profile: Profile, naming: &Naming) -> String {
+    let serde = profile >= Profile::Standard;
+    let mut rs = String::from("// Generated from the shared Schema; the frontends' lib/shared/models.ts has the same types\n");
+    if serde {
+        rs.push_str("\nuse serde::{Deserialize, Serialize};\n");
+    }
+    let serialize = if serde { ", Serialize, Deserialize" } else { "" };
+    for model in models {
+        rs.push('\n');
+        if model.kind.as_deref() == Some("enum") {
+            rs.push_str(&format!("#[derive(Debug, Clone, Copy, PartialEq, Eq{})]\npub enum {} {{\n", serialize, model.name));
+            for value in &model.values {
+                let variant = pascal(value);
+                if serde && variant != *value {
+                    rs.push_str(&format!("    #[serde(rename = \"{}\")]\n", value));
+                }
+                rs.push_str(&format!("    {},\n", variant));
+            }
+            rs.push_str("}\n");
+            continue;
+        }
+        rs.push_str(&format!("#[derive(Debug, Clone, PartialEq{})]\npub struct {} {{\n", serialize, model.name));
+        for field in &model.fields {
+            let name = rust_field(&field.name, naming);
+            if serde && name.trim_start_matches("r#") != field.name {
+                rs.push_str(&format!("    #[serde(rename = \"{}\")]\n", field.name));
+            }
+            rs.push_str(&format!("    pub {}: {},\n", name, rust_type(&field.ty, models, serde)));
+        }
+        rs.push_str("}\n");
+    }
+    rs
+}
+
+/// The name of the field `name` in Rust, `created_at` for `createdAt` unless the target
+/// names fields otherwise, and `r#type` for `type`
+fn rust_field(name: &str, naming: &Naming) -> String {
+    let name = naming.name(Role::Field, name);
+    match name.as_str() {
+        "as" | "box" | "const" | "crate" | "enum" | "fn" | "impl" | "in" | "loop" | "match" | "mod" | "move" | "mut" | "ref"
+        | "static" | "struct" | "trait" | "type" | "use" | "where" => format!("r#{}", name),
+        _ => name,
+    }
+}
+
+/// A schema field type in Rust: `T | null` is optional, and what isn't built in or a
+/// shared model is any JSON value (a string without serde)
+fn rust_type(ty: &str, models: &[&Model], serde: bool) -> String {
+    let ty = ty.trim();
+    let any = if serde { "serde_json::Value" } else { "String" };
+    if ty.contains('|') {
+        let parts: Vec<&str> = ty.split('|').map(str::trim).filter(|part| *part != "null").collect();
+        return match parts.as_slice() {
+            [part] if ty.split('|').count() == 2 => format!("Option<{}>", rust_type(part, models, serde)),
+            _ => any.to_string(),
+        };
+    }
+    if let Some(element) = ty.strip_suffix("[]") {
+        return format!("Vec<{}>", rust_type(element, models, serde));
+    }
+    match base_type(ty) {
+        "string" | "text" | "uuid" | "email" | "url" | "cuid" | "date" | "datetime" | "timestamp" => "String".to_string(),
+        "int" | "integer" | "bigint" | "serial" => "i64".to_string(),
+        "number" | "float" | "double" | "decimal" => "f64".to_string(),
+        "bool" | "boolean" => "bool".to_string(),
+        name if models.iter().any(|model| model.name == name) => name.to_string(),
+        _ => any.to_string(),
+    }
+}
+
+/// The backend's routes: a table to serve them from and a trait with a method for each
+fn api_rs(backend: &Target, models: &[&Model], naming: &Naming) -> String {
+    let serde = backend.profile >= Profile::Standard;
+    let any = if serde { "serde_json::Value" } else { "String" };
+    let calls: Vec<(String, Call)> = backend.endpoints().map(|endpoint| (endpoint.name.clone(), Call::of(endpoint, models))).collect();
+    let mut used: Vec<&str> = calls.iter().filter_map(|(_, call)| call.model.map(|model| model.name.as_str())).collect();
+    used.sort();
+    used.dedup();
+
+    let mut rs = String::from("// Generated from the API section; the frontends' clients call these routes\n");
+    match used.as_slice() {
+        [] => {}
+        [model] => rs.push_str(&format!("\nuse crate::models::{};\n", model)),
+        _ => rs.push_str(&format!("\nuse crate::models::{{{}}};\n", used.join(", "))),
+    }
+    rs.push_str("\n/// Method and path of every route, with path parameters in braces\npub const ROUTES: &[(&str, &str)] = &[\n");
+    for (_, call) in &calls {
+        rs.push_str(&format!("    (\"{}\", \"{}\"),\n", call.method, call.path));
+    }
+    rs.push_str("];\n\n/// What the backend does for each of [`ROUTES`]\npub trait Api {\n    type Error;\n");
+    for (name, call) in &calls {
+        let mut args: Vec<String> = call.params.iter().map(|param| format!("{}: String", rust_field(param, naming))).collect();
+        let response = match (call.method.as_str(), call.model) {
+            ("GET", Some(model)) => format!("Vec<{}>", model.name),
+            ("POST" | "PUT" | "PATCH", Some(model)) => {
+                args.push(format!("body: {}", model.name));
+                model.name.clone()
+            }
+            ("DELETE", Some(_)) => "()".to_string(),
+            _ => any.to_string(),
+        };
+        let mut route = call.path.clone();
+        if let Some((key, ty)) = call.key() {
+            args.push(format!("{}: {}", rust_field(&key, naming), rust_type(&ty, models, serde)));
+            route.push_str(&format!("?{}=", key));
+        }
+        let args: String = args.iter().map(|arg| format!(", {}", arg)).collect();
+        rs.push_str(&format!(
+            "\n    /// `{} {}`\n    fn {}(&self{}) -> Result<{}, Self::Error>;\n",
+            call.method, route, snake(&call.name(name)), args, response,
+        ));
+    }
+    rs.push_str("}\n");
+    rs
+}
@@ -0,0 +1,153 @@
+//! Bug report bundles for compiler crashes.
+//!
+//! When the compiler panics, the CLI can write a [`CrashReport`] to a zip file
+//! holding what's needed to reproduce it and nothing else:
+//!
+//! - `crash.txt`: compiler version, platform, command line, panic message and backtrace
+//! - `inputs/`: the `.z` sources and `z.toml` files the command read
+//!
+//! Inputs are [redacted](redact) before they're written: values of properties that
+//! look like credentials are replaced, and the home directory is shortened to `~`
+//! everywhere in the bundle.
+//!
+//! A panic in a target's compiler isn't a crash of the compiler: [`isolate`] turns it
+//! into the failure of that target, and the other targets are still compiled.
+
+use std::any::Any;
+use std::cell::Cell;
+use std::io::Write;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::{Path, PathBuf};
+
+use crate::archive::Zip;
+use crate::toml::CONFIG_FILE;
+use crate::tr;
+
+/// Property keys whose values are left out of bundles, matched ignoring case, `_` and `-`
+const SENSITIVE_KEYS: &[&str] = &["secret", "token", "password", "passwd", "apikey", "privatekey", "credential"];
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CrashReport {
+    pub message: String,
+    /// `file:line:column` of the panic in the compiler's source
+    pub location: Option<String>,
+    pub backtrace: String,
+    /// Compiler version, e.g. `0.1.0`
+    pub version: String,
+    /// Command line the compiler ran with
+    pub command: Vec<String>,
+    /// Source files the command read
+    pub inputs: Vec<PathBuf>,
+}
+
+impl CrashReport {
+    /// Contents of `crash.txt`
+    pub fn summary(&self) -> String {
+        let mut summary = format!(
+            "z {}\nplatform: {}-{}\ncommand: {}\n\npanic: {}\n",
+            self.version,
+            std::env::consts::OS,
+            std::env::consts::ARCH,
+            self.command.join(" "),
+            self.message,
+        );
+        if let Some(location) = &self.location {
+            summary.push_str(&format!("at: {}\n", location));
+        }
+        summary.push_str(&format!("\nbacktrace:\n{}\n", self.backtrace));
+        redact_home(&summary)
+    }
+
+    /// Write the bundle as a zip file at `path`
+    pub fn write_bundle(&self, path: &Path) -> Result<(), String> {
+        let mut zip = Zip::default();
+        zip.add("crash.txt", self.summary().as_bytes());
+
+        let mut added: Vec<String> = Vec::new();
+        let mut files = self.inputs.clone();
+        for config in self.inputs.iter().filter_map(|input| input.parent()).map(|dir| dir.join(CONFIG_FILE)) {
+            if config.is_file() && !files.contains(&config) {
+                files.push(config);
+            }
+        }
+        for input in files {
+            let Ok(contents) = std::fs::read_to_string(&input) else {
+                continue;
+            };
+            let name = input.file_name().map_or_else(|| "input".into(), |name| name.to_string_lossy());
+            // Files of the same name from different directories get a number
+            let namesakes = added.iter().filter(|added| **added == name).count();
+            let entry = match namesakes {
+                0 => format!("inputs/{}", name),
+                n => format!("inputs/{}-{}", n, name),
+            };
+            added.push(name.into_owned());
+            zip.add(&entry, redact(&contents).as_bytes());
+        }
+
+        std::fs::File::create(path)
+            .and_then(|mut file| file.write_all(&zip.finish()))
+            .map_err(|e| tr!("write-failed", path = path.display(), error = e))
+    }
+}
+
+thread_local! {
+    static ISOLATED: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Run `f`, turning a panic in it into an error with the panic's message. Panic hooks
+/// can check [`is_isolated`] to leave such panics to the caller.
+pub fn isolate<T>(f: impl FnOnce() -> T) -> Result<T, String> {
+    let outer = ISOLATED.with(|isolated| isolated.replace(true));
+    let result = panic::catch_unwind(AssertUnwindSafe(f));
+    ISOLATED.with(|isolated| isolated.set(outer));
+    result.map_err(|payload| panic_message(&*payload))
+}
+
+/// Whether a panic on this thread is caught by [`isolate`] rather than a crash
+pub fn is_isolated() -> bool {
+    ISOLATED.with(Cell::get)
+}
+
+/// Mark the threads a caller of [`isolate`] starts, whose panics it also catches
+pub(crate) fn set_isolated(isolated: bool) {
+    ISOLATED.with(|cell| cell.set(isolated));
+}
+
+/// The message a panic was raised with
+pub fn panic_message(payload: &(dyn Any + Send)) -> String {
+    payload.downcast_ref::<&str>().map(|message| message.to_string())
+        .or_else(|| payload.downcast_ref::<String>().cloned())
+        .unwrap_or_default()
+}
+
+/// `source` with the values of credential-like properties (`apiKey: ...`,
+/// `DB_PASSWORD = ...`) replaced by `<redacted>` and the home directory by `~`
+pub fn redact(source: &str) -> String {
+    let lines: Vec<String> = source.split('\n')
+        .map(|line| {
+            let Some(at) = line.find([':', '=']) else {
+                return line.to_string();
+            };
+            let key = line[..at].trim().trim_start_matches("const ");
+            let normalized = key.to_ascii_lowercase().replace(['_', '-'], "");
+            let sensitive = !key.is_empty()
+                && key.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.'))
+                && SENSITIVE_KEYS.iter().any(|sensitive| normalized.contains(sensitive));
+            let value = line[at + 1..].trim();
+            if sensitive && !value.is_empty() && value != "{" {
+                format!("{}{} <redacted>", &line[..at], &line[at..at + 1])
+            } else {
+                line.to_string()
+            }
+        })
+        .collect();
+    redact_home(&lines.join("\n"))
+}
+
+fn redact_home(text: &str) -> String {
+    match std::env::var("HOME") {
+        Ok(home) if home.len() > 1 => text.replace(&home, "~"),
+        _ => text.to_string(),
+    }
+}
@@ -0,0 +1,267 @@
+//! Project-chosen versions for the packages generated projects depend on.
+//!
+//! The emitters pin a version for every package they use. A project can pick
+//! another one per target type in `z.toml`:
+//!
+//! ```toml
+//! [dependencies.next]
+//! next = "15.0.0-canary.12"
+//! react = "^19.0.0"
+//! eslint = { version = "9.0.0", force = true }
+//! ```
+//!
+//! The registry lists, under `compatibleDependencies`, the range of each package
+//! the generated code is known to work with. A version outside it, or one that
+//! can't be checked like a dist-tag, is an error unless the entry sets
+//! `force = true`, which turns it into a warning.
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use serde_json::Value;
+
+use crate::sink::FileSink;
+use crate::tr;
+
+/// One package version from `z.toml`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DependencyOverride {
+    pub package: String,
+    /// As written, e.g. `^19.0.0` or `15.0.0-canary.12`
+    pub version: String,
+    /// Use the version even outside the known-compatible range
+    pub force: bool,
+}
+
+/// The `[dependencies.<target type>]` tables of `z.toml`
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DependencyOverrides {
+    by_kind: BTreeMap<String, Vec<DependencyOverride>>,
+}
+
+impl DependencyOverrides {
+    /// Read the `dependencies` table of a parsed `z.toml`
+    pub fn from_config(config: &Value) -> Result<Self, String> {
+        let mut overrides = Self::default();
+        let Some(kinds) = config.get("dependencies") else {
+            return Ok(overrides);
+        };
+        let kinds = kinds.as_object().ok_or("'dependencies' must be a table of target types")?;
+        for (kind, packages) in kinds {
+            let packages = packages.as_object()
+                .ok_or_else(|| format!("'dependencies.{}' must be a table of package versions", kind))?;
+            let mut entries = Vec::new();
+            for (package, entry) in packages {
+                let invalid = || format!(
+                    "'dependencies.{}.{}' must be a version or {{ version = \"...\", force = true }}",
+                    kind, package,
+                );
+                let (version, force) = match entry {
+                    Value::String(version) => (version.clone(), false),
+                    Value::Object(table) => {
+                        let version = table.get("version").and_then(Value::as_str).ok_or_else(invalid)?;
+                        let force = match table.get("force") {
+                            None => false,
+                            Some(force) => force.as_bool().ok_or_else(invalid)?,
+                        };
+                        if let Some(key) = table.keys().find(|key| !matches!(key.as_str(), "version" | "force")) {
+                            return Err(format!("unknown key '{}' in 'dependencies.{}.{}'", key, kind, package));
+                        }
+                        (version.to_string(), force)
+                    }
+                    _ => return Err(invalid()),
+                };
+                if version.trim().is_empty() {
+                    return Err(invalid());
+                }
+                entries.push(DependencyOverride { package: package.clone(), version: version.trim().to_string(), force });
+            }
+            overrides.by_kind.insert(kind.clone(), entries);
+        }
+        Ok(overrides)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.by_kind.values().all(Vec::is_empty)
+    }
+
+    /// Overrides for targets of type `kind`
+    pub fn for_kind(&self, kind: &str) -> &[DependencyOverride] {
+        self.by_kind.get(kind).map_or(&[], Vec::as_slice)
+    }
+
+    /// Check the overrides for `kind` against the ranges in its registry entry,
+    /// returning a warning for each forced version outside them
+    pub fn check(&self, kind: &str, target_info: &Value) -> Result<Vec<String>, String> {
+        let mut warnings = Vec::new();
+        for entry in self.for_kind(kind) {
+            let Some(range) = target_info["compatibleDependencies"][entry.package.as_str()].as_str() else {
+                continue;
+            };
+            let problem = match satisfies(&entry.version, range) {
+                Some(true) => continue,
+                Some(false) => tr!("dependency-incompatible", package = &entry.package, version = &entry.version, range = range),
+                None => tr!("dependency-unchecked", package = &entry.package, version = &entry.version, range = range),
+            };
+            if !entry.force {
+                return Err(tr!("dependency-needs-force", problem = problem, kind = kind, package = &entry.package));
+            }
+            warnings.push(problem);
+        }
+        Ok(warnings)
+    }
+}
+
+/// Whether the lowest version `requirement` allows is in `range`, a list of
+/// comparators that must all hold (`>=14.0.0 <16.0.0`). `None` when the
+/// requirement isn't a version, like `latest` or a git URL.
+fn satisfies(requirement: &str, range: &str) -> Option<bool> {
+    let version = parse_version(requirement.trim_start_matches(['^', '~', '=', 'v', ' ']).trim_start_matches(">="))?;
+    let satisfied = range.split_whitespace().all(|comparator| {
+        let (operator, bound) = match comparator.find(|c: char| c.is_ascii_digit()) {
+            Some(at) => comparator.split_at(at),
+            None => return false,
+        };
+        let Some(bound) = parse_version(bound) else {
+            return false;
+        };
+        match operator {
+            ">=" => version >= bound,
+            ">" => version > bound,
+            "<=" => version <= bound,
+            "<" => version < bound,
+            "" | "=" => version == bound,
+            _ => false,
+        }
+    });
+    Some(satisfied)
+}
+
+/// `major.minor.patch` of a version, missing parts as 0; pre-release and build
+/// suffixes are ignored
+fn parse_version(text: &str) -> Option<(u64, u64, u64)> {
+    let core = text.split(['-', '+']).next()?;
+    let mut parts = core.split('.').map(|part| part.parse::<u64>());
+    let major = parts.next()?.ok()?;
+    let minor = parts.next().transpose().ok()?.unwrap_or(0);
+    let patch = parts.next().transpose().ok()?.unwrap_or(0);
+    if parts.next().is_some() {
+        return None;
+    }
+    Some((major, minor, patch))
+}
+
+/// Write `overrides` into the manifests of the project, returning the files that
+/// changed. A package no manifest depends on is an error, since that's almost always
+/// a typo.
+pub(crate) fn apply_overrides(sink: &dyn FileSink, overrides: &[DependencyOverride]) -> Result<Vec<PathBuf>, String> {
+    if overrides.is_empty() {
+        return Ok(Vec::new());
+    }
+    let manifests = ["package.json", "Cargo.toml", "src-tauri/Cargo.toml"];
+    let mut found = vec![false; overrides.len()];
+    let mut updated = Vec::new();
+
+    for manifest in manifests {
+        let path = Path::new(manifest);
+        let Some(original) = sink.read_text(path)? else {
+            continue;
+        };
+        let mut contents = original.clone();
+        for (entry, found) in overrides.iter().zip(found.iter_mut()) {
+            let replaced = if manifest.ends_with(".json") {
+                set_json_dependency(&contents, &entry.package, &entry.version)
+            } else {
+                set_toml_dependency(&contents, &entry.package, &entry.version)
+            };
+            if let Some(replaced) = replaced {
+                contents = replaced;
+                *found = true;
+            }
+        }
+        if contents != original {
+            sink.write_text(path, &contents)?;
+            updated.push(path.to_path_buf());
+        }
+    }
+
+    if let Some((entry, _)) = overrides.iter().zip(&found).find(|(_, found)| !**found) {
+        return Err(tr!("dependency-not-used", package = &entry.package));
+    }
+    Ok(updated)
+}
+
+/// `json` with every `"package": "..."` entry set to `version`, keeping the layout
+fn set_json_dependency(json: &str, package: &str, version: &str) -> Option<String> {
+    let key = format!("\"{}\"", package);
+    let mut out = String::with_capacity(json.len());
+    let mut copied = 0;
+    let mut from = 0;
+    while let Some(found) = json[from..].find(&key) {
+        from += found + key.len();
+        // Only a key followed by a string value; the same text elsewhere is left alone
+        let Some(quoted) = json[from..].trim_start().strip_prefix(':').and_then(|value| value.trim_start().strip_prefix('"')) else {
+            continue;
+        };
+        let start = json.len() - quoted.len();
+        let end = start + quoted.find('"')?;
+        out.push_str(&json[copied..start]);
+        out.push_str(version);
+        copied = end;
+        from = end;
+    }
+    (copied > 0).then(|| out + &json[copied..])
+}
+
+/// `toml` with `package` in its dependency tables set to `version`, whether it's
+/// written as `package = "1.0"` or `package = { version = "1.0", ... }`
+fn set_toml_dependency(toml: &str, package: &str, version: &str) -> Option<String> {
+    let mut in_dependencies = false;
+    let mut replaced = false;
+    let lines: Vec<String> = toml.lines()
+        .map(|line| {
+            let trimmed = line.trim_start();
+            if trimmed.starts_with('[') {
+                let table = trimmed.trim_start_matches('[').split(']').next().unwrap_or_default().trim();
+                in_dependencies = table.ends_with("dependencies");
+                return line.to_string();
+            }
+            let Some((key, value)) = trimmed.split_once('=') else {
+                return line.to_string();
+            };
+            if !in_dependencies || key.trim().trim_matches('"') != package {
+                return line.to_string();
+            }
+            let indent = &line[..line.len() - trimmed.len()];
+            let value = value.trim();
+            let new_value = if value.starts_with('"') {
+                Some(format!("\"{}\"", version))
+            } else if value.starts_with('{') {
+                replace_inline_version(value, version)
+            } else {
+                None
+            };
+            match new_value {
+                Some(new_value) => {
+                    replaced = true;
+                    format!("{}{} = {}", indent, key.trim(), new_value)
+                }
+                None => line.to_string(),
+            }
+        })
+        .collect();
+    let trailing_newline = if toml.ends_with('\n') { "\n" } else { "" };
+    replaced.then(|| lines.join("\n") + trailing_newline)
+}
+
+/// `{ version = "1.0", features = [...] }` with the version replaced
+fn replace_inline_version(table: &str, version: &str) -> Option<String> {
+    let at = table.find("version")?;
+    let after = at + "version".len();
+    let start = after + table[after..].find('"')? + 1;
+    if !table[after..start - 1].trim().starts_with('=') {
+        return None;
+    }
+    let end = start + table[start..].find('"')?;
+    Some(format!("{}{}{}", &table[..start], version, &table[end..]))
+}
@@ -0,0 +1,223 @@
+//! Deployment of generated projects through the providers' own CLIs.
+//!
+//! A target's `Deploy` section names the provider and the provider's id for the app;
+//! [`deploy_steps`] turns it into the commands to run in the generated project:
+//!
+//! | Provider  | Targets | Deploys with                              | Promotes with                   |
+//! | --------- | ------- | ----------------------------------------- | ------------------------------- |
+//! | `vercel`  | `next`  | `vercel deploy`                           | `vercel promote`                |
+//! | `netlify` | `next`  | `netlify deploy --build`                  | `netlify api restoreSiteDeploy` |
+//! | `fly`     | `rust`  | `flyctl deploy`, one app per environment  | —                               |
+//! | `railway` | `rust`  | `railway up`                              | —                               |
+//! | `github`  | `tauri` | `pnpm tauri build`, then a GitHub release | `gh release edit`               |
+//!
+//! Deploys go to the `preview` environment unless another is asked for; promotion
+//! always goes to `production`. Credentials come from each CLI's usual login or
+//! token variables.
+
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use z_semantic::{Deployment, Provider, Target};
+
+use crate::store::read_json;
+
+pub const PRODUCTION: &str = "production";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DeployRequest {
+    /// Environment to deploy to, e.g. `preview` or `production`
+    pub environment: String,
+    /// Deployment (URL, id or release tag, depending on the provider) to promote to
+    /// production instead of deploying
+    pub promote: Option<String>,
+}
+
+impl Default for DeployRequest {
+    fn default() -> Self {
+        Self { environment: "preview".to_string(), promote: None }
+    }
+}
+
+/// One command of a deployment
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DeployStep {
+    pub program: String,
+    pub args: Vec<String>,
+    /// Extra environment variables
+    pub env: Vec<(String, String)>,
+    /// Directory to run in
+    pub dir: PathBuf,
+    /// Bundles in this directory when the step runs are appended to the arguments,
+    /// since they only exist once an earlier step has built them
+    pub attach_files_in: Option<PathBuf>,
+}
+
+impl DeployStep {
+    fn new(program: &str, args: &[&str], dir: &Path) -> Self {
+        Self {
+            program: program.to_string(),
+            args: args.iter().map(|arg| arg.to_string()).collect(),
+            env: Vec::new(),
+            dir: dir.to_path_buf(),
+            attach_files_in: None,
+        }
+    }
+
+    fn arg(mut self, arg: impl Into<String>) -> Self {
+        self.args.push(arg.into());
+        self
+    }
+
+    fn env(mut self, name: &str, value: &str) -> Self {
+        self.env.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    /// The command to run, with any attached files listed
+    pub fn command(&self) -> Command {
+        let mut command = Command::new(&self.program);
+        command.args(&self.args).envs(self.env.iter().map(|(name, value)| (name, value))).current_dir(&self.dir);
+        if let Some(dir) = &self.attach_files_in {
+            command.args(bundle_files(dir));
+        }
+        command
+    }
+}
+
+impl fmt::Display for DeployStep {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.program)?;
+        for arg in &self.args {
+            write!(f, " {}", arg)?;
+        }
+        if let Some(dir) = &self.attach_files_in {
+            write!(f, " {}/*/*", dir.display())?;
+        }
+        Ok(())
+    }
+}
+
+/// Commands deploying `target`, whose generated project is in `project_dir`
+pub fn deploy_steps(target: &Target, project_dir: &Path, request: &DeployRequest) -> Result<Vec<DeployStep>, String> {
+    let Some(deployment) = target.deployment() else {
+        return Err(format!("{} has no Deploy section", target.qualified_name()));
+    };
+
+    let supported = match deployment.provider {
+        Provider::Vercel | Provider::Netlify => "next",
+        Provider::Fly | Provider::Railway => "rust",
+        Provider::GitHub => "tauri",
+    };
+    if target.kind != supported {
+        return Err(format!(
+            "{} can't deploy {} targets (it deploys {} targets)",
+            deployment.provider.as_str(), target.kind, supported
+        ));
+    }
+
+    match &request.promote {
+        Some(deployment_id) => promote(deployment, project_dir, deployment_id),
+        None => deploy(deployment, project_dir, &request.environment),
+    }
+}
+
+fn deploy(deployment: &Deployment, dir: &Path, environment: &str) -> Result<Vec<DeployStep>, String> {
+    let production = environment == PRODUCTION;
+    let project = deployment.project.as_str();
+
+    let steps = match deployment.provider {
+        Provider::Vercel => {
+            let step = vercel(deployment, dir, &["deploy", "--yes"])?;
+            vec![match environment {
+                PRODUCTION => step.arg("--prod"),
+                "preview" => step,
+                custom => step.arg("--target").arg(custom),
+            }]
+        }
+        Provider::Netlify => {
+            let step = DeployStep::new("netlify", &["deploy", "--build", "--site", project], dir);
+            vec![if production { step.arg("--prod") } else { step.arg("--alias").arg(environment) }]
+        }
+        // Fly has no environments; the usual setup is one app per environment
+        Provider::Fly => {
+            let app = if production { project.to_string() } else { format!("{}-{}", project, environment) };
+            vec![DeployStep::new("flyctl", &["deploy", "--app", &app, "--remote-only"], dir)]
+        }
+        Provider::Railway => {
+            vec![DeployStep::new("railway", &["up", "--service", project, "--environment", environment, "--detach"], dir)]
+        }
+        // Bundles go to a release tagged with the app version; anything but production is a prerelease
+        Provider::GitHub => {
+            let tag = format!("v{}", tauri_version(dir)?);
+            let mut release = DeployStep::new(
+                "gh",
+                &["release", "create", &tag, "--repo", project, "--title", &tag, "--generate-notes"],
+                dir,
+            );
+            if !production {
+                release = release.arg("--prerelease");
+            }
+            release.attach_files_in = Some(dir.join("src-tauri/target/release/bundle"));
+            vec![DeployStep::new("pnpm", &["tauri", "build"], dir), release]
+        }
+    };
+    Ok(steps)
+}
+
+fn promote(deployment: &Deployment, dir: &Path, deployment_id: &str) -> Result<Vec<DeployStep>, String> {
+    let project = deployment.project.as_str();
+    let step = match deployment.provider {
+        Provider::Vercel => vercel(deployment, dir, &["promote", deployment_id, "--yes"])?,
+        Provider::Netlify => {
+            let data = serde_json::json!({ "site_id": project, "deploy_id": deployment_id }).to_string();
+            DeployStep::new("netlify", &["api", "restoreSiteDeploy", "--data", &data], dir)
+        }
+        Provider::GitHub => DeployStep::new(
+            "gh",
+            &["release", "edit", deployment_id, "--repo", project, "--prerelease=false", "--latest"],
+            dir,
+        ),
+        Provider::Fly | Provider::Railway => {
+            return Err(format!("{} has no promotion; deploy with --env {} instead", deployment.provider.as_str(), PRODUCTION));
+        }
+    };
+    Ok(vec![step])
+}
+
+/// A `vercel` command linked to the project through its environment variables
+fn vercel(deployment: &Deployment, dir: &Path, args: &[&str]) -> Result<DeployStep, String> {
+    let Some(team) = &deployment.team else {
+        return Err("vercel deployments need a 'team' in the Deploy section".to_string());
+    };
+    Ok(DeployStep::new("vercel", args, dir)
+        .env("VERCEL_ORG_ID", team)
+        .env("VERCEL_PROJECT_ID", &deployment.project))
+}
+
+/// App version from `src-tauri/tauri.conf.json` (Tauri 2 at the top level, Tauri 1 under `package`)
+fn tauri_version(dir: &Path) -> Result<String, String> {
+    let path = dir.join("src-tauri/tauri.conf.json");
+    let config = read_json(&path)?;
+    config["version"].as_str()
+        .or_else(|| config["package"]["version"].as_str())
+        .map(str::to_string)
+        .ok_or_else(|| format!("no version in {}", path.display()))
+}
+
+/// Files one level down, e.g. `dmg/App.dmg` and `msi/App.msi` in a Tauri bundle directory;
+/// directories such as `macos/App.app` are left out
+fn bundle_files(dir: &Path) -> Vec<PathBuf> {
+    let Ok(kinds) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut files: Vec<PathBuf> = kinds.flatten()
+        .filter_map(|kind| std::fs::read_dir(kind.path()).ok())
+        .flat_map(|entries| entries.flatten())
+        .filter(|entry| entry.file_type().is_ok_and(|kind| kind.is_file()))
+        .map(|entry| entry.path())
+        .collect();
+    files.sort();
+    files
+}
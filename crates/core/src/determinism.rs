@@ -0,0 +1,68 @@
+//! Checking that compiling a program twice generates the same files, byte for byte.
+//!
+//! Generated projects are committed and reviewed, so a rebuild that changes nothing in
+//! the program must change nothing in them: no timestamps, and no order that depends on
+//! a hash map or on the order a directory is read in. [`check`] compiles a program twice,
+//! each time into a scratch directory of its own, and lists the files that came out
+//! different. Both compiles regenerate every target, skip the remote and user caches
+//! and run no hooks, so what's compared is what the compilers generate.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::diff;
+use crate::report::{collect_outputs, FileEntry};
+use crate::{compile_project, CompileOptions, CompileResult};
+
+/// A file the two compiles of a program generated differently
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Difference {
+    /// Relative to the output directory, e.g. `web/app/page.tsx`
+    pub path: PathBuf,
+    /// From the first compile's file to the second's; None when only one of them
+    /// generated the file, or it isn't text
+    pub diff: Option<String>,
+}
+
+/// Compile the program in `entry` twice and compare what was generated: the result of
+/// the first compile, and the files that differ
+pub fn check(entry: &Path, options: &CompileOptions) -> (CompileResult, Vec<Difference>) {
+    let options = CompileOptions { force: true, dry_run: false, remote_cache: None, user_cache: None, run_hooks: false, progress: None, ..options.clone() };
+    let scratch = std::env::temp_dir().join(format!("z-determinism-{}", std::process::id()));
+    let (first, second) = (scratch.join("first"), scratch.join("second"));
+    // Targets that fail generate nothing either time, so the others are still compared
+    let result = compile_project(entry, &first, &options);
+    compile_project(entry, &second, &options);
+    let differences = differences(&first, &second);
+    let _ = fs::remove_dir_all(&scratch);
+    (result, differences)
+}
+
+/// The files under `first` and `second` that aren't the same, by path
+fn differences(first: &Path, second: &Path) -> Vec<Difference> {
+    let (first_files, second_files) = (outputs(first), outputs(second));
+    let mut paths: Vec<&PathBuf> = first_files.iter().chain(&second_files).map(|file| &file.path).collect();
+    paths.sort();
+    paths.dedup();
+
+    let find = |files: &[FileEntry], path: &Path| files.iter().find(|file| file.path == path).map(|file| file.sha256.clone());
+    paths.into_iter()
+        .filter(|path| find(&first_files, path) != find(&second_files, path))
+        .map(|path| {
+            let text = |base: &Path| fs::read(base.join(path)).ok().and_then(|contents| String::from_utf8(contents).ok());
+            let diff = match (text(first), text(second)) {
+                (Some(old), Some(new)) => Some(diff::unified(&old, &new, &path.display().to_string())),
+                _ => None,
+            };
+            Difference { path: path.clone(), diff }
+        })
+        .collect()
+}
+
+/// Every generated file under `dir`, leaving out the compiler's own bookkeeping
+fn outputs(dir: &Path) -> Vec<FileEntry> {
+    let mut files = Vec::new();
+    collect_outputs(dir, Path::new(""), &mut files);
+    files.retain(|file| !file.path.components().any(|part| part.as_os_str().to_string_lossy().starts_with(".z-")));
+    files
+}
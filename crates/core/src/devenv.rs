@@ -0,0 +1,83 @@
+//! Reproducible development environments for generated projects.
+//!
+//! The toolchain each target needs lives in the registry under `toolchain`:
+//! a list of nixpkgs attributes for `flake.nix`, and an image plus features for
+//! `.devcontainer/devcontainer.json`. Targets without one get no environment files.
+
+use std::path::Path;
+use std::str::FromStr;
+
+use serde_json::{json, Value};
+
+use crate::sink::FileSink;
+
+/// nixpkgs release the generated flakes follow; `flake.lock` pins the exact revision
+const NIXPKGS: &str = "github:NixOS/nixpkgs/nixos-24.11";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DevEnv {
+    /// `flake.nix` with a default dev shell, for `nix develop`
+    Nix,
+    /// `.devcontainer/devcontainer.json`, for VS Code and Codespaces
+    Devcontainer,
+}
+
+impl FromStr for DevEnv {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "nix" => Ok(DevEnv::Nix),
+            "devcontainer" => Ok(DevEnv::Devcontainer),
+            _ => Err(format!("invalid dev environment '{}' (expected 'nix' or 'devcontainer')", s)),
+        }
+    }
+}
+
+/// Write the requested environment files for one target into its project
+pub(crate) fn write_dev_environments(kinds: &[DevEnv], toolchain: &Value, app_name: &str, sink: &dyn FileSink) -> Result<(), String> {
+    for kind in kinds {
+        match kind {
+            DevEnv::Nix => {
+                let Some(packages) = toolchain["nix"].as_array() else { continue };
+                let packages: Vec<&str> = packages.iter().filter_map(Value::as_str).collect();
+                sink.write_text(Path::new("flake.nix"), &flake(app_name, &packages))?;
+            }
+            DevEnv::Devcontainer => {
+                let Some(container) = toolchain["devcontainer"].as_object() else { continue };
+                let mut config = json!({ "name": app_name });
+                config.as_object_mut().expect("object").extend(container.clone());
+                sink.write_json(Path::new(".devcontainer/devcontainer.json"), &config)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+fn flake(app_name: &str, packages: &[&str]) -> String {
+    format!(
+        r#"{{
+  description = "Development environment for {app_name} (generated by Z)";
+
+  inputs = {{
+    nixpkgs.url = "{NIXPKGS}";
+    flake-utils.url = "github:numtide/flake-utils";
+  }};
+
+  outputs = {{ nixpkgs, flake-utils, ... }}:
+    flake-utils.lib.eachDefaultSystem (system:
+      let
+        pkgs = nixpkgs.legacyPackages.${{system}};
+      in
+      {{
+        devShells.default = pkgs.mkShell {{
+          packages = with pkgs; [
+{packages}
+          ];
+        }};
+      }});
+}}
+"#,
+        packages = packages.iter().map(|package| format!("            {}", package)).collect::<Vec<_>>().join("\n"),
+    )
+}
@@ -0,0 +1,389 @@
+//! Problems found while compiling, returned to the caller rather than printed.
+//!
+//! Every diagnostic has a stable [`code`](Diagnostic::code) tools can match on,
+//! whatever the message's locale. Those about the source carry its span and line;
+//! those about one target carry the target's `kind:name`.
+
+use std::fmt;
+use std::path::PathBuf;
+
+use serde::Serialize;
+use z_ast::Span;
+
+/// The source couldn't be parsed, or a pipeline pass rejected it
+pub const PARSE: &str = "parse";
+/// The program is well-formed but invalid, e.g. a malformed route or block
+pub const INVALID: &str = "invalid";
+/// A name refers to nothing declared, e.g. a `@model` that isn't in `Schema`
+pub const UNRESOLVED: &str = "unresolved";
+/// A section or field isn't one the registry lists for the target
+pub const SCHEMA: &str = "schema";
+/// An imported file is missing, or an import is malformed
+pub const IMPORT: &str = "import";
+/// A source file couldn't be read, or isn't UTF-8 text
+pub const READ: &str = "read";
+/// A bundle id or package name is invalid, or two apps share it
+pub const IDENTIFIER: &str = "identifier";
+/// Targets use each other in a cycle, so none of them can be generated first
+pub const CYCLE: &str = "cycle";
+/// A secret environment variable is declared where a client would get it
+pub const SECRET: &str = "secret";
+/// The program declares no targets
+pub const NO_TARGETS: &str = "no-targets";
+/// A target type isn't in the registry
+pub const UNKNOWN_TARGET: &str = "unknown-target";
+/// A target type is written with an old name that will stop working
+pub const DEPRECATED_TARGET: &str = "deprecated-target";
+/// A target type is in the registry but has no compiler
+pub const NO_COMPILER: &str = "no-compiler";
+/// A target has nothing its compiler understands, so it gets a placeholder app
+pub const EMPTY_TARGET: &str = "empty-target";
+/// A section or line of a target its compiler generates nothing from
+pub const IGNORED: &str = "ignored";
+/// A file generated before but not anymore was kept because it was edited since
+pub const ORPHAN: &str = "orphan";
+/// A region kept from a file's previous version had no place in the new one
+pub const KEEP_UNPLACED: &str = "keep-unplaced";
+/// A generated file was edited by hand since, so it was kept instead of regenerated
+pub const EDITED: &str = "edited";
+/// Generating a target failed
+pub const TARGET_FAILED: &str = "target-failed";
+/// A command run once a target was generated failed
+pub const HOOK_FAILED: &str = "hook-failed";
+/// A target wasn't generated because a target it uses wasn't
+pub const BLOCKED: &str = "blocked";
+/// The compiler of a target panicked; the other targets were still compiled
+pub const PANIC: &str = "panic";
+/// A dependency override is outside the versions known to work
+pub const DEPENDENCY: &str = "dependency";
+/// The remote or user cache, or the record of unchanged targets, couldn't be used
+pub const CACHE: &str = "cache";
+/// A component of a target is declared but no route uses it
+pub const UNUSED_COMPONENT: &str = "unused-component";
+
+/// The codes reported as warnings, which `@allow(...)` can silence
+pub const WARNINGS: &[&str] = &[
+    NO_TARGETS, UNKNOWN_TARGET, DEPRECATED_TARGET, EMPTY_TARGET, IGNORED, ORPHAN, KEEP_UNPLACED, EDITED, DEPENDENCY, CACHE,
+    UNUSED_COMPONENT,
+];
+
+/// What `z explain` prints about a code
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Explanation {
+    pub code: &'static str,
+    /// One line, as the code's constant is documented
+    pub summary: &'static str,
+    /// What the diagnostic means, why it's reported and how to fix it
+    pub description: &'static str,
+    /// A program that gets the diagnostic, for codes a program alone can get
+    pub wrong: Option<&'static str>,
+    /// `wrong` fixed
+    pub fixed: Option<&'static str>,
+}
+
+/// Every code, in the order they're declared above
+pub const EXPLANATIONS: &[Explanation] = &[
+    Explanation {
+        code: PARSE,
+        summary: "The source couldn't be parsed, or a pipeline pass rejected it",
+        description: "The file isn't written the way Z programs are, so nothing after the problem could be read: \
+            a block isn't closed, a line has a character that can't start anything, or a pass such as template \
+            expansion rejected what it was given. The message names the line the parser stopped at; the mistake \
+            is often just above it.",
+        wrong: Some("next Web {\n  Routes {\n    home\n  }\n"),
+        fixed: Some("next Web {\n  Routes {\n    home\n  }\n}\n"),
+    },
+    Explanation {
+        code: INVALID,
+        summary: "The program is well-formed but invalid, e.g. a malformed route or block",
+        description: "The file parses, but a block or line doesn't make sense where it is: a target without a name, \
+            a malformed route, a property with a value it can't take. The message says what was expected.",
+        wrong: Some("next {\n  Routes {\n    home\n  }\n}\n"),
+        fixed: Some("next Web {\n  Routes {\n    home\n  }\n}\n"),
+    },
+    Explanation {
+        code: UNRESOLVED,
+        summary: "A name refers to nothing declared, e.g. a `@model` that isn't in `Schema`",
+        description: "A name refers to nothing declared in the target: a component named by `@component(...)`, a model \
+            named by `@model(...)`, a variable named by `@env(...)`, or a target named by `uses`. Names are looked up in \
+            the target itself, and in the `Components` and `Schema` sections of its file or workspace; a name only \
+            another target declares isn't visible.",
+        wrong: Some("next Web {\n  Routes {\n    home @component(Hero)\n  }\n\n  Components {\n    Header\n  }\n}\n"),
+        fixed: Some("next Web {\n  Routes {\n    home @component(Header)\n  }\n\n  Components {\n    Header\n  }\n}\n"),
+    },
+    Explanation {
+        code: SCHEMA,
+        summary: "A section or field isn't one the registry lists for the target",
+        description: "A section or property isn't one the registry lists for the target type, so its compiler wouldn't \
+            know what to do with it. Sections are specific to a platform: `Routes` belong to web targets, `App` to \
+            Swift ones. The message lists the sections the type takes, and which types take the one written.",
+        wrong: Some("swift Mobile {\n  Routes {\n    home\n  }\n}\n"),
+        fixed: Some("swift Mobile {\n  App {\n    HomeView\n  }\n}\n"),
+    },
+    Explanation {
+        code: IMPORT,
+        summary: "An imported file is missing, or an import is malformed",
+        description: "An `import` names a file that isn't there or can't be read, isn't written as `import \"./file.z\"` \
+            or `import name from \"./file.z\"`, or imports a file under a name when that file declares workspaces. \
+            Paths are relative to the file importing them, and imports are only followed when compiling a file, not \
+            source text handed to the compiler.",
+        wrong: Some("import \"./models.z\"\n\nnext Web {\n  Routes {\n    home\n  }\n}\n"),
+        fixed: None,
+    },
+    Explanation {
+        code: READ,
+        summary: "A source file couldn't be read, or isn't UTF-8 text",
+        description: "The file being compiled, or one it imports, exists but couldn't be read: it's a directory, it \
+            isn't readable by the user running the compiler, or its bytes aren't UTF-8. Sources are always UTF-8; \
+            save a file written in another encoding, like Latin-1 or UTF-16, as UTF-8 again.",
+        wrong: None,
+        fixed: None,
+    },
+    Explanation {
+        code: IDENTIFIER,
+        summary: "A bundle id or package name is invalid, or two apps share it",
+        description: "A bundle id or package name, written or derived from the app's name, isn't valid for the platform, \
+            or two apps would be published under the same one. Bundle ids are reverse-DNS names; package names are \
+            lowercase. Set `bundle_id` or `package` on the target to choose one.",
+        wrong: Some("swift Notes {\n  bundle_id: notes\n\n  App {\n    ContentView\n  }\n}\n"),
+        fixed: Some("swift Notes {\n  bundle_id: dev.acme.notes\n\n  App {\n    ContentView\n  }\n}\n"),
+    },
+    Explanation {
+        code: CYCLE,
+        summary: "Targets use each other in a cycle, so none of them can be generated first",
+        description: "Targets `uses` each other in a circle, so none of them can be generated first. A target that's \
+            built from another, like a desktop shell around a web app, uses it; the other way round isn't needed.",
+        wrong: Some("next Web {\n  uses: Desktop\n\n  Routes {\n    home\n  }\n}\n\ntauri Desktop {\n  uses: Web\n\n  Frontend {\n    Shell\n  }\n}\n"),
+        fixed: Some("next Web {\n  Routes {\n    home\n  }\n}\n\ntauri Desktop {\n  uses: Web\n\n  Frontend {\n    Shell\n  }\n}\n"),
+    },
+    Explanation {
+        code: SECRET,
+        summary: "A secret environment variable is declared where a client would get it",
+        description: "An environment variable declared `secret` would reach a client, where anyone can read it: a \
+            `NEXT_PUBLIC_` variable of a Next.js app goes to the browser, and everything a Swift app is configured \
+            with ships inside it. Keep secrets on a server and drop the prefix, or declare the variable as a string \
+            if it isn't secret.",
+        wrong: Some("next Web {\n  env {\n    NEXT_PUBLIC_API_KEY: secret\n  }\n\n  Routes {\n    home\n  }\n}\n"),
+        fixed: Some("next Web {\n  env {\n    API_KEY: secret\n  }\n\n  Routes {\n    home\n  }\n}\n"),
+    },
+    Explanation {
+        code: NO_TARGETS,
+        summary: "The program declares no targets",
+        description: "The program declares no target, so there's nothing to generate. A target is a block whose name \
+            starts with its type, e.g. `next Web { ... }`; `z add target` adds one.",
+        wrong: Some("config {\n  name: \"Acme\"\n}\n"),
+        fixed: Some("config {\n  name: \"Acme\"\n}\n\nnext Web {\n  Routes {\n    home\n  }\n}\n"),
+    },
+    Explanation {
+        code: UNKNOWN_TARGET,
+        summary: "A target type isn't in the registry",
+        description: "A block looks like a target, but its type isn't in the registry, so it's skipped; often it's a \
+            typo. With `--strict` it's an error. The message lists the types there are, and a project can add its \
+            own with a registry override or a plugin compiler.",
+        wrong: Some("nxet Web {\n  Routes {\n    home\n  }\n}\n"),
+        fixed: Some("next Web {\n  Routes {\n    home\n  }\n}\n"),
+    },
+    Explanation {
+        code: DEPRECATED_TARGET,
+        summary: "A target type is written with an old name that will stop working",
+        description: "The target type is written with an old name, which still works but will stop working. The \
+            message gives the name to use instead.",
+        wrong: Some("NextJS Web {\n  Routes {\n    home\n  }\n}\n"),
+        fixed: Some("next Web {\n  Routes {\n    home\n  }\n}\n"),
+    },
+    Explanation {
+        code: NO_COMPILER,
+        summary: "A target type is in the registry but has no compiler",
+        description: "The target type is in the registry, but no compiler generates it: the registry of the project \
+            names a compiler that's neither built in nor one of its plugins. Declare the plugin in z.toml, or fix the \
+            `compiler` of the type in the registry override.",
+        wrong: None,
+        fixed: None,
+    },
+    Explanation {
+        code: EMPTY_TARGET,
+        summary: "A target has nothing its compiler understands, so it gets a placeholder app",
+        description: "The target has nothing its compiler generates code from, so it gets a placeholder app. Add one \
+            of the sections the message lists; with `--strict` this is an error.",
+        wrong: Some("rust Api {\n}\n"),
+        fixed: Some("rust Api {\n  fun {\n    main()\n  }\n}\n"),
+    },
+    Explanation {
+        code: IGNORED,
+        summary: "A section or line of a target its compiler generates nothing from",
+        description: "The registry allows the section or line in the target, but its compiler generates nothing from \
+            it, so writing it has no effect on the output. Move it where it's used, or silence the warning with \
+            `@allow(ignored)` if it's there for people to read. With `--strict` it's an error.",
+        wrong: Some("next Web {\n  Routes {\n    home\n  }\n\n  Schema {\n    User\n  }\n}\n"),
+        fixed: Some("next Web {\n  Routes {\n    home\n  }\n\n  // Kept for the docs, not generated from\n  Schema @allow(ignored) {\n    User\n  }\n}\n"),
+    },
+    Explanation {
+        code: ORPHAN,
+        summary: "A file generated before but not anymore was kept because it was edited since",
+        description: "A file the target generated before isn't generated anymore, but it was edited since, so it was \
+            kept rather than deleted. Delete it if the edits aren't needed, or compile with `--overwrite`.",
+        wrong: None,
+        fixed: None,
+    },
+    Explanation {
+        code: KEEP_UNPLACED,
+        summary: "A region kept from a file's previous version had no place in the new one",
+        description: "A `z:begin-keep` region of a generated file had no place in the file's new version, because \
+            what it was next to isn't generated anymore, so it was moved to the end of the file. Move it where it \
+            belongs.",
+        wrong: None,
+        fixed: None,
+    },
+    Explanation {
+        code: EDITED,
+        summary: "A generated file was edited by hand since, so it was kept instead of regenerated",
+        description: "A generated file was edited by hand since it was generated, so the compile kept it instead of \
+            writing the new version. Move the edits into a kept region or into the program, or compile with \
+            `--overwrite` to replace it.",
+        wrong: None,
+        fixed: None,
+    },
+    Explanation {
+        code: TARGET_FAILED,
+        summary: "Generating a target failed",
+        description: "The compiler of the target couldn't generate it, e.g. because a tool it runs is missing or a file \
+            couldn't be written. The message says why; the other targets were still generated.",
+        wrong: None,
+        fixed: None,
+    },
+    Explanation {
+        code: HOOK_FAILED,
+        summary: "A command run once a target was generated failed",
+        description: "A command the target lists in `after_generate` exited with an error, or couldn't be started. The \
+            files were generated; run the command in the project to see what went wrong, or skip hooks with \
+            `--no-hooks`.",
+        wrong: None,
+        fixed: None,
+    },
+    Explanation {
+        code: BLOCKED,
+        summary: "A target wasn't generated because a target it uses wasn't",
+        description: "The target wasn't generated because a target it `uses` wasn't. Fix that target first; its own \
+            diagnostic says what went wrong.",
+        wrong: None,
+        fixed: None,
+    },
+    Explanation {
+        code: PANIC,
+        summary: "The compiler of a target panicked; the other targets were still compiled",
+        description: "The compiler of the target crashed. That's a bug in the compiler, not in the program; the other \
+            targets were still compiled. Please report it.",
+        wrong: None,
+        fixed: None,
+    },
+    Explanation {
+        code: DEPENDENCY,
+        summary: "A dependency override is outside the versions known to work",
+        description: "A version a `[dependencies.KIND]` table of z.toml picks for a package is outside the range the \
+            generated code is known to work with, or can't be checked against it, like a dist-tag. It's an error \
+            unless the entry sets `force = true`, which makes it this warning; the message gives the range.",
+        wrong: None,
+        fixed: None,
+    },
+    Explanation {
+        code: CACHE,
+        summary: "The remote or user cache, or the record of unchanged targets, couldn't be used",
+        description: "The remote or user cache couldn't be read or written, or the record of unchanged targets couldn't \
+            be used, so the target was generated from scratch. The output is the same; only the time saved is lost.",
+        wrong: None,
+        fixed: None,
+    },
+    Explanation {
+        code: UNUSED_COMPONENT,
+        summary: "A component of a target is declared but no route uses it",
+        description: "A component is declared, but in a target whose routes name the components that render them, no \
+            route names it, so nothing shows it. Render it from a route with `@component(...)`, remove it, or \
+            silence the warning with `@allow(unused_component)` if it's rendered by hand.",
+        wrong: Some("next Web {\n  Routes {\n    home @component(Hero)\n  }\n\n  Components {\n    Hero\n    Footer\n  }\n}\n"),
+        fixed: Some("next Web {\n  Routes {\n    home @component(Hero)\n    about @component(Footer)\n  }\n\n  Components {\n    Hero\n    Footer\n  }\n}\n"),
+    },
+];
+
+/// What [`EXPLANATIONS`] says about `code`
+pub fn explain(code: &str) -> Option<&'static Explanation> {
+    EXPLANATIONS.iter().find(|explanation| explanation.code == code)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Diagnostic {
+    /// One of the codes in this module, e.g. [`SCHEMA`]
+    pub code: &'static str,
+    pub severity: Severity,
+    pub message: String,
+    /// The imported file the problem is in; the entry file when there's none
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub file: Option<PathBuf>,
+    /// Where in the source the problem is
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub span: Option<Span>,
+    /// 1-based line of `span`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub line: Option<usize>,
+    /// How to fix the problem, when there's an obvious way
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub help: Option<String>,
+    /// `kind:name` of the target the diagnostic belongs to, if any
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub target: Option<String>,
+}
+
+impl Diagnostic {
+    pub fn error(code: &'static str, message: String) -> Self {
+        Self::new(code, Severity::Error, message)
+    }
+
+    pub fn warning(code: &'static str, message: String) -> Self {
+        Self::new(code, Severity::Warning, message)
+    }
+
+    fn new(code: &'static str, severity: Severity, message: String) -> Self {
+        Self { code, severity, message, file: None, span: None, line: None, help: None, target: None }
+    }
+
+    /// Point the diagnostic at `span` of `source`
+    pub fn with_span(mut self, span: Span, source: &str) -> Self {
+        self.span = Some(span);
+        self.line = Some(crate::line_number(source, span.start));
+        self
+    }
+
+    pub fn with_help(mut self, help: String) -> Self {
+        self.help = Some(help);
+        self
+    }
+
+    /// Attach the diagnostic to the target named `kind:name`
+    pub fn with_target(mut self, target: String) -> Self {
+        self.target = Some(target);
+        self
+    }
+
+    pub fn is_error(&self) -> bool {
+        self.severity == Severity::Error
+    }
+}
+
+/// `line N: message`, with the file for imported ones (`models.z, line N: message`),
+/// or just the message when it isn't about a line
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (&self.file, self.line) {
+            (Some(file), Some(line)) => write!(f, "{}, line {}: {}", file.display(), line, self.message),
+            (Some(file), None) => write!(f, "{}: {}", file.display(), self.message),
+            (None, Some(line)) => write!(f, "line {}: {}", line, self.message),
+            (None, None) => f.write_str(&self.message),
+        }
+    }
+}
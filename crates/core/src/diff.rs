@@ -0,0 +1,128 @@
+//! Unified diffs of generated files, for dry runs.
+
+/// Lines of unchanged context around each change
+const CONTEXT: usize = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Edit {
+    Equal,
+    Delete,
+    Insert,
+}
+
+/// `old` and `new` as a unified diff of `path`, as `diff -u` and `git diff` print it;
+/// empty if they're the same
+pub(crate) fn unified(old: &str, new: &str, path: &str) -> String {
+    let old: Vec<&str> = old.lines().collect();
+    let new: Vec<&str> = new.lines().collect();
+    let edits = edits(&old, &new);
+    let changes: Vec<usize> = edits.iter().enumerate().filter(|(_, edit)| **edit != Edit::Equal).map(|(index, _)| index).collect();
+    let Some(&first) = changes.first() else {
+        return String::new();
+    };
+
+    // Changes close enough to share their context go in the same hunk
+    let mut hunks = vec![(first, first)];
+    for &change in &changes[1..] {
+        let last = hunks.last_mut().expect("hunks start with one");
+        if change - last.1 <= 2 * CONTEXT {
+            last.1 = change;
+        } else {
+            hunks.push((change, change));
+        }
+    }
+
+    let mut text = format!("--- a/{}\n+++ b/{}\n", path, path);
+    // Lines of `old` and `new` before each edit
+    let mut positions = Vec::with_capacity(edits.len() + 1);
+    let (mut old_line, mut new_line) = (0, 0);
+    for edit in &edits {
+        positions.push((old_line, new_line));
+        match edit {
+            Edit::Equal => (old_line, new_line) = (old_line + 1, new_line + 1),
+            Edit::Delete => old_line += 1,
+            Edit::Insert => new_line += 1,
+        }
+    }
+    positions.push((old_line, new_line));
+
+    for (first, last) in hunks {
+        let start = first.saturating_sub(CONTEXT);
+        let end = (last + CONTEXT + 1).min(edits.len());
+        let (old_start, new_start) = positions[start];
+        let (old_end, new_end) = positions[end];
+        text.push_str(&format!(
+            "@@ -{} +{} @@\n",
+            range(old_start, old_end - old_start),
+            range(new_start, new_end - new_start),
+        ));
+        for (index, edit) in edits.iter().enumerate().take(end).skip(start) {
+            let (old_line, new_line) = positions[index];
+            match edit {
+                Edit::Equal => text.push_str(&format!(" {}\n", old[old_line])),
+                Edit::Delete => text.push_str(&format!("-{}\n", old[old_line])),
+                Edit::Insert => text.push_str(&format!("+{}\n", new[new_line])),
+            }
+        }
+    }
+    text
+}
+
+/// `start,length` of a hunk, 1-based unless it's empty
+fn range(start: usize, length: usize) -> String {
+    match length {
+        0 => format!("{},0", start),
+        1 => format!("{}", start + 1),
+        _ => format!("{},{}", start + 1, length),
+    }
+}
+
+/// The shortest edit script turning `old` into `new` (Myers' algorithm)
+fn edits(old: &[&str], new: &[&str]) -> Vec<Edit> {
+    let (n, m) = (old.len() as isize, new.len() as isize);
+    let offset = n + m + 1;
+    let mut furthest = vec![0isize; 2 * offset as usize + 1];
+    // `furthest` before each round, to walk back through
+    let mut trace = Vec::new();
+
+    'search: for d in 0..=(n + m) {
+        trace.push(furthest.clone());
+        for k in (-d..=d).step_by(2) {
+            let index = (k + offset) as usize;
+            let mut x = if k == -d || (k != d && furthest[index - 1] < furthest[index + 1]) {
+                furthest[index + 1]
+            } else {
+                furthest[index - 1] + 1
+            };
+            let mut y = x - k;
+            while x < n && y < m && old[x as usize] == new[y as usize] {
+                (x, y) = (x + 1, y + 1);
+            }
+            furthest[index] = x;
+            if x >= n && y >= m {
+                break 'search;
+            }
+        }
+    }
+
+    let mut edits = Vec::new();
+    let (mut x, mut y) = (n, m);
+    for (d, furthest) in trace.iter().enumerate().rev() {
+        let d = d as isize;
+        let k = x - y;
+        let index = (k + offset) as usize;
+        let previous_k = if k == -d || (k != d && furthest[index - 1] < furthest[index + 1]) { k + 1 } else { k - 1 };
+        let previous_x = furthest[(previous_k + offset) as usize];
+        let previous_y = previous_x - previous_k;
+        while x > previous_x && y > previous_y {
+            edits.push(Edit::Equal);
+            (x, y) = (x - 1, y - 1);
+        }
+        if d > 0 {
+            edits.push(if x == previous_x { Edit::Insert } else { Edit::Delete });
+        }
+        (x, y) = (previous_x, previous_y);
+    }
+    edits.reverse();
+    edits
+}
@@ -0,0 +1,303 @@
+//! Moving generated files into place without touching the ones that didn't change.
+//!
+//! Each target is generated from scratch into a staging directory under `.z-cache/`,
+//! or into memory when nothing is written (see [`FileSink`]), then synced into its
+//! output directory. A file whose contents are already there
+//! isn't written again, so its modification time stays put and `next dev`, `cargo`
+//! and other watchers only rebuild what changed. Files only the output directory
+//! has, such as `node_modules` or `target`, are left alone too.
+//!
+//! Generating off to the side also means a target that fails to generate leaves its
+//! previous output as it was, rather than half overwritten. It also lets a dry run
+//! report what syncing would change (see [`plan`] and [`changes`]) without writing.
+//!
+//! Every generated file is listed, with its hash, in the output directory's
+//! [`MANIFEST_FILE`]. Files the previous compile generated and this one doesn't, such
+//! as the page of a removed route, are deleted, unless they were edited since; those
+//! are kept and reported.
+//!
+//! Regions marked to be kept in a file that's about to be replaced are carried over
+//! into its new version (see [`keep`](crate::keep)). A file edited outside them since
+//! it was generated, as its [header](crate::header) tells, is kept as it is and
+//! reported, unless the sync overwrites edited files.
+//!
+//! A directory that already has files but no manifest wasn't generated into, such as an
+//! existing project a target is [placed](crate::layout) over, so it's only synced into
+//! when edited files are overwritten too.
+//!
+//! Files a [hook](crate::hooks) rewrites after the sync, as a formatter does, are
+//! [adopted](adopt) as they are then.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::diff;
+use crate::hash::sha256_hex;
+use crate::header::{self, Provenance};
+use crate::incremental::CACHE_DIR;
+use crate::keep;
+use crate::report::{is_skipped, Change, FileEntry, PlannedChange};
+use crate::sink::FileSink;
+use crate::store::{create_dir, write_file};
+use crate::tr;
+
+/// Lists the files generated into an output directory, relative to it
+pub const MANIFEST_FILE: &str = ".z-manifest.json";
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Manifest {
+    files: Vec<FileEntry>,
+}
+
+/// What syncing a target will do, worked out before anything is written
+pub(crate) struct Plan {
+    /// Paths under the output directory, with what to do to them
+    operations: Vec<(PathBuf, Operation)>,
+    manifest: Manifest,
+    pub synced: Synced,
+}
+
+enum Operation {
+    /// Copy the generated file over, from its path in the staged target
+    Copy(PathBuf),
+    /// Write the generated file with the kept regions of the one it replaces
+    Write(String),
+    /// Delete a file that's no longer generated
+    Delete,
+}
+
+/// Files that syncing a target left for the user to look at
+#[derive(Debug, Default)]
+pub(crate) struct Synced {
+    /// No longer generated, but kept because they were edited since
+    pub kept: Vec<PathBuf>,
+    /// With kept regions that had nowhere to go in the new version, so were put at the end
+    pub unplaced: Vec<PathBuf>,
+    /// Edited since they were generated, so kept instead of their new version
+    pub edited: Vec<PathBuf>,
+}
+
+/// Where to generate `target` (as `kind:name`): named after the target like its output
+/// directory is, since tools such as `cargo init` name projects after their directory
+pub(crate) fn staging_dir(output_base_dir: &Path, target: &str) -> PathBuf {
+    let (kind, name) = target.split_once(':').unwrap_or(("", target));
+    output_base_dir.join(CACHE_DIR).join("staging").join(kind).join(name)
+}
+
+/// Empty `staging` of anything a previous compile left behind
+pub(crate) fn prepare(staging: &Path) -> Result<(), String> {
+    discard(staging);
+    create_dir(staging)
+}
+
+/// Remove a staging directory once it's been synced, or its target has failed
+pub(crate) fn discard(staging: &Path) {
+    let _ = fs::remove_dir_all(staging);
+    // `staging/<kind>` and `staging` go too once they're empty
+    for dir in staging.ancestors().skip(1).take(2) {
+        if fs::remove_dir(dir).is_err() {
+            break;
+        }
+    }
+}
+
+/// Sync the target generated in `staged` into `output_dir`, and replace its manifest.
+/// Files edited since they were generated are only replaced if `overwrite`.
+pub(crate) fn sync(staged: &dyn FileSink, output_dir: &Path, overwrite: bool) -> Result<Synced, String> {
+    apply(plan(staged, output_dir, overwrite)?, staged, output_dir)
+}
+
+/// What syncing `staged` into `output_dir` would do, without doing it. A directory
+/// with files in it but no manifest is someone else's, so it's only synced into if `overwrite`.
+pub(crate) fn plan(staged: &dyn FileSink, output_dir: &Path, overwrite: bool) -> Result<Plan, String> {
+    let occupied = fs::read_dir(output_dir).is_ok_and(|mut entries| entries.next().is_some());
+    if occupied && !overwrite && !output_dir.join(MANIFEST_FILE).exists() {
+        return Err(tr!("output-dir-occupied", path = output_dir.display()));
+    }
+    let mut plan = Plan { operations: Vec::new(), manifest: Manifest::default(), synced: Synced::default() };
+    // In path order, so a dry run lists the changes the same way every time
+    for path in staged.files() {
+        let contents = staged.read(&path)?.unwrap_or_default();
+        // A tool's repository, like the one `cargo init` makes, is synced but not generated
+        if !is_skipped(&path) {
+            plan.manifest.files.push(FileEntry::new(path.clone(), &contents));
+        }
+        plan_file(path, &contents, output_dir, overwrite, &mut plan);
+    }
+    let previous = manifest(output_dir);
+
+    for entry in previous.files.iter().filter(|entry| !plan.manifest.files.iter().any(|file| file.path == entry.path)) {
+        let path = output_dir.join(&entry.path);
+        match fs::read(&path) {
+            Ok(contents) if sha256_hex(&contents) == entry.sha256 => plan.operations.push((path, Operation::Delete)),
+            Ok(_) => plan.synced.kept.push(path),
+            Err(_) => {}
+        }
+    }
+    Ok(plan)
+}
+
+/// The files `plan` would create, update or delete, with their diffs, which name files
+/// as `web/app/page.tsx`, from `base`, the directory holding the targets
+pub(crate) fn changes(plan: &Plan, staged: &dyn FileSink, base: &Path) -> Vec<PlannedChange> {
+    plan.operations.iter()
+        .map(|(path, operation)| {
+            let old = fs::read(path).ok();
+            let new = match operation {
+                Operation::Copy(source) => staged.read(source).ok().flatten().unwrap_or_default(),
+                Operation::Write(contents) => contents.clone().into_bytes(),
+                Operation::Delete => Vec::new(),
+            };
+            let change = match (operation, &old) {
+                (Operation::Delete, _) => Change::Delete,
+                (_, None) => Change::Create,
+                (_, Some(_)) => Change::Update,
+            };
+            // Binary files are only listed
+            let diff = match (std::str::from_utf8(old.as_deref().unwrap_or_default()), std::str::from_utf8(&new)) {
+                (Ok(old), Ok(new)) => Some(diff::unified(old, new, &path.strip_prefix(base).unwrap_or(path).display().to_string())),
+                _ => None,
+            };
+            PlannedChange { path: path.clone(), change, diff }
+        })
+        .collect()
+}
+
+fn apply(plan: Plan, staged: &dyn FileSink, output_dir: &Path) -> Result<Synced, String> {
+    create_dir(output_dir)?;
+    for (path, operation) in plan.operations {
+        if let Some(parent) = path.parent().filter(|_| !matches!(operation, Operation::Delete)) {
+            create_dir(parent)?;
+        }
+        match operation {
+            // `copy` keeps the permissions, e.g. of executable scripts
+            Operation::Copy(source) => match staged.directory() {
+                Some(directory) => {
+                    let source = directory.join(source);
+                    fs::copy(&source, &path).map_err(|e| tr!("copy-failed", path = source.display(), error = e))?;
+                }
+                None => {
+                    let contents = staged.read(&source)?.unwrap_or_default();
+                    fs::write(&path, contents).map_err(|e| tr!("write-failed", path = path.display(), error = e))?;
+                }
+            },
+            Operation::Write(contents) => write_file(&path, &contents)?,
+            Operation::Delete => {
+                fs::remove_file(&path).map_err(|e| tr!("remove-failed", path = path.display(), error = e))?;
+                // Directories the file leaves empty, such as a removed route's, go too
+                for dir in path.ancestors().skip(1).take_while(|dir| *dir != output_dir) {
+                    if fs::remove_dir(dir).is_err() {
+                        break;
+                    }
+                }
+            }
+        }
+    }
+
+    let manifest = serde_json::to_string_pretty(&plan.manifest).expect("manifest is serializable");
+    write_file(&output_dir.join(MANIFEST_FILE), &(manifest + "\n"))?;
+    Ok(plan.synced)
+}
+
+/// Delete the files the last sync generated into `output_dir`, with the directories they
+/// leave empty. Returns the files deleted and those kept because they were edited since,
+/// which are deleted as well if `overwrite`. The manifest is left listing the kept ones,
+/// so that they can be deleted later, or deleted if there are none.
+pub(crate) fn remove(output_dir: &Path, overwrite: bool) -> Result<(Vec<PathBuf>, Vec<PathBuf>), String> {
+    let (mut removed, mut kept) = (Vec::new(), Manifest::default());
+    for entry in manifest(output_dir).files {
+        let path = output_dir.join(&entry.path);
+        match fs::read(&path) {
+            Ok(contents) if overwrite || sha256_hex(&contents) == entry.sha256 => {
+                fs::remove_file(&path).map_err(|e| tr!("remove-failed", path = path.display(), error = e))?;
+                for dir in path.ancestors().skip(1).take_while(|dir| *dir != output_dir) {
+                    if fs::remove_dir(dir).is_err() {
+                        break;
+                    }
+                }
+                removed.push(path);
+            }
+            Ok(_) => kept.files.push(entry),
+            Err(_) => {}
+        }
+    }
+    let path = output_dir.join(MANIFEST_FILE);
+    if !kept.files.is_empty() {
+        let manifest = serde_json::to_string_pretty(&kept).expect("manifest is serializable");
+        write_file(&path, &(manifest + "\n"))?;
+    } else if path.exists() {
+        fs::remove_file(&path).map_err(|e| tr!("remove-failed", path = path.display(), error = e))?;
+    }
+    Ok((removed, kept.files.into_iter().map(|entry| output_dir.join(entry.path)).collect()))
+}
+
+/// The files the last sync generated into `output_dir`, hashed as they are now
+pub(crate) fn generated(output_dir: &Path) -> Vec<FileEntry> {
+    manifest(output_dir).files.into_iter()
+        .filter_map(|file| fs::read(output_dir.join(&file.path)).ok().map(|contents| FileEntry::new(file.path, &contents)))
+        .collect()
+}
+
+/// Take the generated files that changed since `before`, as a formatter run on them
+/// changes them, as generated that way: give them headers that match and record their
+/// hashes in the manifest, so they don't count as edited by hand
+pub(crate) fn adopt(output_dir: &Path, before: &[FileEntry], provenance: &Provenance) -> Result<(), String> {
+    let mut manifest = manifest(output_dir);
+    let mut adopted = false;
+    for file in &mut manifest.files {
+        let path = output_dir.join(&file.path);
+        let Ok(contents) = fs::read(&path) else {
+            continue;
+        };
+        let unchanged = before.iter().any(|previous| previous.path == file.path && previous.sha256 == sha256_hex(&contents));
+        if unchanged {
+            continue;
+        }
+        header::stamp_file(&path, provenance)?;
+        let contents = fs::read(&path).map_err(|e| tr!("read-failed", path = path.display(), error = e))?;
+        *file = FileEntry::new(file.path.clone(), &contents);
+        adopted = true;
+    }
+    if !adopted {
+        return Ok(());
+    }
+    let manifest = serde_json::to_string_pretty(&manifest).expect("manifest is serializable");
+    write_file(&output_dir.join(MANIFEST_FILE), &(manifest + "\n"))
+}
+
+/// The manifest in `output_dir`; without one, or with one that can't be read, nothing
+/// counts as generated
+fn manifest(output_dir: &Path) -> Manifest {
+    fs::read_to_string(output_dir.join(MANIFEST_FILE)).ok()
+        .and_then(|contents| serde_json::from_str(&contents).ok())
+        .unwrap_or_default()
+}
+
+/// Plan to write the generated file at `path` if it differs from the one in `output_dir`,
+/// unless that one was edited by hand and not `overwrite`
+fn plan_file(path: PathBuf, contents: &[u8], output_dir: &Path, overwrite: bool, plan: &mut Plan) {
+    let destination = output_dir.join(&path);
+    let existing = fs::read(&destination).ok();
+    let existing_text = existing.as_deref().and_then(|existing| std::str::from_utf8(existing).ok());
+    if !overwrite && existing_text.is_some_and(header::edited) {
+        plan.synced.edited.push(destination);
+    } else if let Some(spliced) = with_kept_regions(contents, existing_text) {
+        if spliced.unplaced > 0 {
+            plan.synced.unplaced.push(destination.clone());
+        }
+        if existing_text != Some(spliced.content.as_str()) {
+            plan.operations.push((destination, Operation::Write(spliced.content)));
+        }
+    } else if existing.as_deref() != Some(contents) {
+        plan.operations.push((destination, Operation::Copy(path)));
+    }
+}
+
+/// The generated `contents` with the kept regions of the `existing` file, if it has any
+fn with_kept_regions(contents: &[u8], existing: Option<&str>) -> Option<keep::Spliced> {
+    let existing = existing.filter(|existing| keep::has_regions(existing))?;
+    let generated = std::str::from_utf8(contents).ok()?;
+    Some(keep::splice(generated, existing))
+}
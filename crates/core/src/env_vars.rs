@@ -0,0 +1,283 @@
+//! Plumbing for the environment variables a target declares in `env` blocks.
+//!
+//! Every target with variables gets a `.env.example` listing them, except `swift`
+//! targets, which are configured at build time instead:
+//!
+//! - `next` targets read them through `lib/env.ts`, which validates them with zod when
+//!   the module is loaded; `NEXT_PUBLIC_` variables are the only ones in the browser
+//! - `rust` targets read them into the `Config` struct of `src/config.rs`
+//! - `swift` targets get them from `Config.xcconfig` into `Info.plist`, and read them
+//!   through `AppConfig.swift`
+//!
+//! A secret must never reach a client: a `swift` app can't have one, and a `next`
+//! target's can't be a `NEXT_PUBLIC_` variable.
+
+use std::path::Path;
+
+use serde_json::json;
+use z_semantic::{EnvKind, EnvVar, Program, SemanticError, Target};
+
+use crate::compilers::{camel, pascal};
+use crate::contract::{declare_modules, write};
+use crate::sink::FileSink;
+use crate::style::CodeStyle;
+use crate::version::set_plist_string;
+
+/// Version of zod `lib/env.ts` is written for, when the project doesn't have it already
+const ZOD: &str = "^3.23.8";
+
+/// Secrets declared where a client would get them
+pub(crate) fn check(program: &Program) -> Vec<SemanticError> {
+    let mut errors = Vec::new();
+    for target in &program.targets {
+        for variable in target.env.iter().filter(|variable| variable.kind == EnvKind::Secret) {
+            let message = match target.kind.as_str() {
+                "swift" => format!(
+                    "secret '{}' can't be part of {}, where anyone with the app can read it; keep it on a server",
+                    variable.name, target.qualified_name(),
+                ),
+                "next" if variable.name.starts_with("NEXT_PUBLIC_") => format!(
+                    "secret '{}' would be sent to the browser by Next.js; drop the NEXT_PUBLIC_ prefix",
+                    variable.name,
+                ),
+                _ => continue,
+            };
+            errors.push(SemanticError { message, span: variable.span });
+        }
+    }
+    // Variables of a file or workspace are checked once per target that has them
+    errors.sort_by_key(|error| error.span.start);
+    errors.dedup();
+    errors
+}
+
+/// Write what `target` needs to read its environment variables, if it has any
+pub(crate) fn write_env_plumbing(target: &Target, sink: &dyn FileSink, style: &CodeStyle) -> Result<(), String> {
+    if target.env.is_empty() {
+        return Ok(());
+    }
+    match target.kind.as_str() {
+        "swift" => return write_swift_config(target, sink, style),
+        "next" => write_next_env(target, sink, style)?,
+        "rust" => {
+            write(sink, Path::new("src/config.rs"), &config_rs(target), style)?;
+            declare_modules(sink, Path::new("src/main.rs"), &["mod config;"])?;
+        }
+        _ => {}
+    }
+    sink.write_text(Path::new(".env.example"), &env_example(target))
+}
+
+/// Every variable with an empty value, to copy to `.env` and fill in
+fn env_example(target: &Target) -> String {
+    let mut example = format!("# Environment variables of {}; copy to .env and fill in\n", target.qualified_name());
+    for variable in &target.env {
+        let mut notes = Vec::new();
+        if variable.kind == EnvKind::Secret {
+            notes.push("secret, never commit its value");
+        }
+        if variable.optional {
+            notes.push("optional");
+        }
+        if !notes.is_empty() {
+            example.push_str(&format!("# {}\n", notes.join(", ")));
+        }
+        example.push_str(&format!("{}=\n", variable.name));
+    }
+    example
+}
+
+fn write_next_env(target: &Target, sink: &dyn FileSink, style: &CodeStyle) -> Result<(), String> {
+    write(sink, Path::new("lib/env.ts"), &env_ts(target), style)?;
+    let package_json = Path::new("package.json");
+    let mut package = sink.read_json(package_json)?;
+    if package["dependencies"]["zod"].is_null() {
+        package["dependencies"]["zod"] = json!(ZOD);
+        sink.write_json(package_json, &package)?;
+    }
+    Ok(())
+}
+
+/// The variables validated with zod: all of them on the server, only the public ones in
+/// the browser, where Next.js inlines nothing else
+fn env_ts(target: &Target) -> String {
+    let (public, server): (Vec<&EnvVar>, Vec<&EnvVar>) = target.env.iter().partition(|variable| variable.name.starts_with("NEXT_PUBLIC_"));
+    let schema = |variables: &[&EnvVar]| -> String {
+        variables.iter().map(|variable| format!("  {}: {},\n", variable.name, zod_type(variable))).collect()
+    };
+    let mut ts = String::from("// Generated from the env blocks of the program; .env.example lists every variable\n\n");
+    ts.push_str("import { z } from \"zod\";\n\n");
+    ts.push_str(&format!("const server = z.object({{\n{}}});\n\n", schema(&server)));
+    ts.push_str(&format!("const client = z.object({{\n{}}});\n\n", schema(&public)));
+    ts.push_str("// Each variable is read by name, so Next.js can inline the public ones in the browser bundle\n");
+    ts.push_str("const values = {\n");
+    for variable in &target.env {
+        ts.push_str(&format!("  {}: process.env.{},\n", variable.name, variable.name));
+    }
+    ts.push_str("};\n\n");
+    ts.push_str("export const env = (typeof window === \"undefined\" ? server.merge(client).parse(values) : client.parse(values)) as z.infer<typeof server> & z.infer<typeof client>;\n");
+    ts
+}
+
+fn zod_type(variable: &EnvVar) -> String {
+    let zod = match variable.kind {
+        EnvKind::String => "z.string()",
+        EnvKind::Secret => "z.string().min(1)",
+        EnvKind::Url => "z.string().url()",
+        EnvKind::Number => "z.coerce.number()",
+        EnvKind::Boolean => "z.enum([\"true\", \"false\"]).transform((value) => value === \"true\")",
+    };
+    if variable.optional {
+        format!("{}.optional()", zod)
+    } else {
+        zod.to_string()
+    }
+}
+
+/// `Config::from_env()`, reading every variable with `std::env`; secrets are left out
+/// of its `Debug` output
+fn config_rs(target: &Target) -> String {
+    let mut rs = String::from("// Generated from the env blocks of the program; .env.example lists every variable\n\n");
+    rs.push_str("use std::env;\nuse std::fmt;\n\n");
+    rs.push_str("/// Settings read from the environment\n#[derive(Clone)]\npub struct Config {\n");
+    for variable in &target.env {
+        rs.push_str(&format!("    pub {}: {},\n", field(variable), rust_type(variable)));
+    }
+    rs.push_str("}\n\nimpl Config {\n");
+    rs.push_str("    /// Read every variable, failing on the first one that's missing or malformed\n");
+    rs.push_str("    pub fn from_env() -> Result<Self, ConfigError> {\n        Ok(Self {\n");
+    for variable in &target.env {
+        let read = match (variable.kind, variable.optional) {
+            (EnvKind::Number | EnvKind::Boolean, false) => format!("parse(\"{}\", required(\"{}\")?)?", variable.name, variable.name),
+            (EnvKind::Number | EnvKind::Boolean, true) => {
+                format!("var(\"{}\").map(|value| parse(\"{}\", value)).transpose()?", variable.name, variable.name)
+            }
+            (_, false) => format!("required(\"{}\")?", variable.name),
+            (_, true) => format!("var(\"{}\")", variable.name),
+        };
+        rs.push_str(&format!("            {}: {},\n", field(variable), read));
+    }
+    rs.push_str("        })\n    }\n}\n\n");
+    rs.push_str("impl fmt::Debug for Config {\n    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {\n");
+    rs.push_str("        f.debug_struct(\"Config\")\n");
+    for variable in &target.env {
+        let value = if variable.kind == EnvKind::Secret { "&\"<secret>\"".to_string() } else { format!("&self.{}", field(variable)) };
+        rs.push_str(&format!("            .field(\"{}\", {})\n", field(variable), value));
+    }
+    rs.push_str("            .finish()\n    }\n}\n");
+    rs.push_str(CONFIG_HELPERS);
+    rs
+}
+
+const CONFIG_HELPERS: &str = r#"
+/// A variable that's missing, or can't be read as its type
+#[derive(Debug)]
+pub enum ConfigError {
+    Missing(&'static str),
+    Invalid { name: &'static str, value: String },
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::Missing(name) => write!(f, "environment variable {} is not set", name),
+            ConfigError::Invalid { name, value } => write!(f, "environment variable {} has an invalid value '{}'", name, value),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+fn var(name: &str) -> Option<String> {
+    env::var(name).ok().filter(|value| !value.is_empty())
+}
+
+fn required(name: &'static str) -> Result<String, ConfigError> {
+    var(name).ok_or(ConfigError::Missing(name))
+}
+
+fn parse<T: std::str::FromStr>(name: &'static str, value: String) -> Result<T, ConfigError> {
+    value.parse().map_err(|_| ConfigError::Invalid { name, value })
+}
+"#;
+
+/// `database_url` for `DATABASE_URL`
+fn field(variable: &EnvVar) -> String {
+    variable.name.to_lowercase()
+}
+
+fn rust_type(variable: &EnvVar) -> String {
+    let ty = match variable.kind {
+        EnvKind::Number => "f64",
+        EnvKind::Boolean => "bool",
+        EnvKind::String | EnvKind::Url | EnvKind::Secret => "String",
+    };
+    if variable.optional {
+        format!("Option<{}>", ty)
+    } else {
+        ty.to_string()
+    }
+}
+
+/// Each variable as a build setting of `Config.xcconfig`, passed to the app through
+/// `Info.plist`, and read by `AppConfig.swift`
+fn write_swift_config(target: &Target, sink: &dyn FileSink, style: &CodeStyle) -> Result<(), String> {
+    let mut xcconfig = format!(
+        "// Environment variables of {}; fill in the values before building.\n\
+         // `//` starts a comment here, so write URLs as https:/$()/example.com\n",
+        target.qualified_name(),
+    );
+    for variable in &target.env {
+        xcconfig.push_str(&format!("{} =\n", variable.name));
+    }
+    sink.write_text(Path::new("Config.xcconfig"), &xcconfig)?;
+
+    let info = Path::new("Info.plist");
+    // The deep link, metadata and identifier settings may have written it already
+    let mut contents = sink.read_text(info)?.unwrap_or_else(|| crate::links::plist(""));
+    for variable in &target.env {
+        contents = set_plist_string(&contents, &variable.name, &format!("$({})", variable.name));
+    }
+    sink.write_text(info, &contents)?;
+
+    write(sink, Path::new("AppConfig.swift"), &app_config_swift(target), style)
+}
+
+fn app_config_swift(target: &Target) -> String {
+    let mut swift = String::from("// Generated from the env blocks of the program; Config.xcconfig sets the values\n\n");
+    swift.push_str("import Foundation\n\n/// Settings the app was built with\nenum AppConfig {\n");
+    for variable in &target.env {
+        // `databaseUrl` for `DATABASE_URL`
+        let name = camel(&pascal(&variable.name.to_lowercase()));
+        let (ty, read) = match (variable.kind, variable.optional) {
+            (EnvKind::Number, false) => ("Double", format!("Double(required(\"{}\"))!", variable.name)),
+            (EnvKind::Number, true) => ("Double?", format!("value(\"{}\").flatMap(Double.init)", variable.name)),
+            (EnvKind::Boolean, false) => ("Bool", format!("required(\"{}\") == \"true\"", variable.name)),
+            (EnvKind::Boolean, true) => ("Bool?", format!("value(\"{}\").map {{ $0 == \"true\" }}", variable.name)),
+            (EnvKind::Url, false) => ("URL", format!("URL(string: required(\"{}\"))!", variable.name)),
+            (EnvKind::Url, true) => ("URL?", format!("value(\"{}\").flatMap(URL.init(string:))", variable.name)),
+            (_, false) => ("String", format!("required(\"{}\")", variable.name)),
+            (_, true) => ("String?", format!("value(\"{}\")", variable.name)),
+        };
+        swift.push_str(&format!("    static var {}: {} {{ {} }}\n", name, ty, read));
+    }
+    swift.push_str(SWIFT_HELPERS);
+    swift
+}
+
+const SWIFT_HELPERS: &str = r#"
+    private static func value(_ key: String) -> String? {
+        guard let value = Bundle.main.object(forInfoDictionaryKey: key) as? String, !value.isEmpty else {
+            return nil
+        }
+        return value
+    }
+
+    private static func required(_ key: String) -> String {
+        guard let value = value(key) else {
+            fatalError("\(key) isn't set; give it a value in Config.xcconfig")
+        }
+        return value
+    }
+}
+"#;
@@ -0,0 +1,149 @@
+//! Environments a program is compiled for, such as `dev`, `staging` and `prod`.
+//!
+//! An environment overrides top-level constants (see [`resolve`](crate::resolve)), so
+//! API base URLs, feature toggles and debug flags can differ between builds of the same
+//! program. Its values come from an `env` block of the program:
+//!
+//! ```z
+//! const apiUrl = "http://localhost:3000"
+//!
+//! env prod {
+//!   const apiUrl = "https://api.example.com"
+//! }
+//! ```
+//!
+//! and from the `[env.<name>]` tables of `z.toml`, which win over the block:
+//!
+//! ```toml
+//! [env.prod]
+//! apiUrl = "https://api.example.com"
+//! ```
+//!
+//! `env` blocks are taken out of the program whichever environment is active, so
+//! without one the constants keep the values written at the top level. Compilers see
+//! the name of the active environment in [`CompilerOptions::environment`](crate::CompilerOptions::environment).
+
+use std::collections::BTreeMap;
+
+use serde_json::Value;
+use z_ast::{Element, Node, Span};
+
+use crate::line_number;
+
+/// The `[env.<name>]` tables of `z.toml`
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Environments {
+    by_name: BTreeMap<String, BTreeMap<String, String>>,
+}
+
+impl Environments {
+    /// Read the `env` table of a parsed `z.toml`
+    pub fn from_config(config: &Value) -> Result<Self, String> {
+        let mut environments = Self::default();
+        let Some(names) = config.get("env") else {
+            return Ok(environments);
+        };
+        let names = names.as_object().ok_or("'env' must be a table of environments")?;
+        for (name, table) in names {
+            let table = table.as_object()
+                .ok_or_else(|| format!("'env.{}' must be a table of constants", name))?;
+            let mut values = BTreeMap::new();
+            for (key, value) in table {
+                // Constants hold raw value text, so strings keep their quotes
+                let value = match value {
+                    Value::String(_) | Value::Number(_) | Value::Bool(_) => value.to_string(),
+                    _ => return Err(format!("'env.{}.{}' must be a string, a number or a boolean", name, key)),
+                };
+                values.insert(key.clone(), value);
+            }
+            environments.by_name.insert(name.clone(), values);
+        }
+        Ok(environments)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.by_name.is_empty()
+    }
+
+    /// The constants `z.toml` sets for the environment `name`
+    pub fn get(&self, name: &str) -> Option<&BTreeMap<String, String>> {
+        self.by_name.get(name)
+    }
+}
+
+/// Take the `env` blocks out of `program`, parsed from `source`, and give the top-level
+/// constants the values of `active`, if any. Fails when neither the program nor
+/// `z.toml` declares `active`.
+pub(crate) fn apply(program: &mut Element, active: Option<&str>, configured: &Environments, source: &str) -> Result<(), String> {
+    let mut declared: Vec<String> = Vec::new();
+    // The `const` lines of the active environment's block, and where the block was
+    let mut block = None;
+    let mut index = 0;
+    while index < program.children.len() {
+        let name = match &program.children[index] {
+            Node::Element(element) => element.name.strip_prefix("env:").map(str::to_string),
+            _ => None,
+        };
+        let Some(name) = name else {
+            index += 1;
+            continue;
+        };
+        let Node::Element(element) = program.children.remove(index) else {
+            unreachable!("env blocks are elements");
+        };
+        let at = |span: Span| line_number(source, span.start);
+        if declared.contains(&name) {
+            return Err(format!("line {}: environment '{}' is declared twice", at(element.span), name));
+        }
+        if let Some(other) = element.children.iter().find(|child| !matches!(child, Node::Const { .. })) {
+            return Err(format!("line {}: an env block only holds `const` lines", at(other.span())));
+        }
+        if active == Some(name.as_str()) {
+            block = Some((element.children, index));
+        }
+        declared.push(name);
+    }
+
+    let Some(active) = active else {
+        return Ok(());
+    };
+    let values = configured.get(active);
+    if block.is_none() && values.is_none() {
+        let mut known: Vec<&str> = declared.iter().map(String::as_str).chain(configured.by_name.keys().map(String::as_str)).collect();
+        known.sort_unstable();
+        known.dedup();
+        return Err(if known.is_empty() {
+            format!("unknown environment '{}': neither the program nor z.toml declares any", active)
+        } else {
+            format!("unknown environment '{}' (declared: {})", active, known.join(", "))
+        });
+    }
+    let (mut overrides, position) = block.unwrap_or((Vec::new(), 0));
+    // Values from z.toml take the place of those in the block
+    for (name, value) in values.into_iter().flatten() {
+        let constant = Node::Const { name: name.clone(), value: value.clone(), span: Span::default(), trivia: Default::default() };
+        match overrides.iter_mut().find(|node| matches!(node, Node::Const { name: existing, .. } if existing == name)) {
+            Some(node) => *node = constant,
+            None => overrides.push(constant),
+        }
+    }
+
+    // A constant the program already has gets the new value where it's declared; the
+    // others are declared where the block was
+    let mut added = Vec::new();
+    for node in overrides {
+        let Node::Const { name, value, .. } = &node else {
+            continue;
+        };
+        let existing = program.children.iter_mut().find_map(|child| match child {
+            Node::Const { name: existing, value, .. } if existing == name => Some(value),
+            _ => None,
+        });
+        match existing {
+            Some(existing) => existing.clone_from(value),
+            None => added.push(node),
+        }
+    }
+    program.children.splice(position..position, added);
+    Ok(())
+}
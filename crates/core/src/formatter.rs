@@ -0,0 +1,28 @@
+//! Formatting `.z` files, for `z fmt`.
+//!
+//! A file is parsed as written, with its comments and blank lines, and printed back by
+//! [`z_ast::to_source`]: two-space indentation, one item per line, `{` on the line of the
+//! block it opens and `}` on a line of its own, `key: value` with one space after the
+//! colon, and a blank line between top-level blocks. Imports and template invocations
+//! stay as they are. Formatting a formatted file changes nothing.
+
+use z_parser::Limits;
+
+/// `source` formatted. Fails when it doesn't parse, with the parser's error, or when
+/// the formatted text wouldn't parse back into the same program, which would be a bug in
+/// the printer and is never worth changing a file over.
+pub fn format(source: &str, limits: &Limits) -> Result<String, String> {
+    let program = z_parser::parse_source_with(source, limits)?;
+    let formatted = z_ast::to_source(&program);
+    let reparsed = z_parser::parse_source_with(&formatted, limits)
+        .map_err(|e| format!("formatting would make the file unparsable ({}); it was left as it is", e))?;
+    if !z_ast::diff(&program, &reparsed).changes.is_empty() {
+        return Err("formatting would change what the file declares; it was left as it is".to_string());
+    }
+    Ok(formatted)
+}
+
+/// What formatting changes in `source`, as a unified diff of `path`; empty if nothing
+pub fn changes(source: &str, formatted: &str, path: &str) -> String {
+    crate::diff::unified(source, formatted, path)
+}
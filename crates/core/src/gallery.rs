@@ -0,0 +1,43 @@
+//! Example programs shipped with the compiler, for `z examples`.
+//!
+//! Each example is a `.z` file under `shared/examples`, embedded in the binary like
+//! the other [assets](crate::assets) so it can be listed, copied into a new project or compiled without a checkout of
+//! the repository. Between them they cover every kind of section and most target
+//! types, so compiling them all (`z examples check`, and `tests/examples.rs` under
+//! `cargo test`) is a quick end-to-end check of the compiler. The first line of an example is a comment saying what it shows.
+
+/// An example program
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Example {
+    pub name: &'static str,
+    /// The source built into the compiler
+    pub(crate) embedded: &'static str,
+}
+
+impl Example {
+    pub fn source(&self) -> &'static str {
+        crate::assets::get(&format!("examples/{}.z", self.name)).unwrap_or(self.embedded)
+    }
+
+    /// What the example shows, from its leading comment
+    pub fn description(&self) -> &'static str {
+        self.source().lines().next()
+            .and_then(|line| line.strip_prefix("//"))
+            .map_or("", str::trim)
+    }
+}
+
+/// Every example, by name
+pub const EXAMPLES: &[Example] = &[
+    Example { name: "blog", embedded: include_str!("../../../shared/examples/blog.z") },
+    Example { name: "todo", embedded: include_str!("../../../shared/examples/todo.z") },
+    Example { name: "api", embedded: include_str!("../../../shared/examples/api.z") },
+    Example { name: "cli", embedded: include_str!("../../../shared/examples/cli.z") },
+    Example { name: "desktop", embedded: include_str!("../../../shared/examples/desktop.z") },
+    Example { name: "workspace", embedded: include_str!("../../../shared/examples/workspace.z") },
+];
+
+/// The example called `name`
+pub fn find(name: &str) -> Option<&'static Example> {
+    EXAMPLES.iter().find(|example| example.name == name)
+}
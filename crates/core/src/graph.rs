@@ -0,0 +1,119 @@
+//! The order targets are generated in.
+//!
+//! A target that [uses](Target::uses) others is generated after them, so its compiler
+//! can build on what they generated, such as a desktop shell loading the pages of the
+//! web frontend it wraps:
+//!
+//! ```z
+//! next Web { ... }
+//!
+//! tauri Desktop {
+//!   uses: Web
+//! }
+//! ```
+//!
+//! The compiler gets the targets it uses in
+//! [`CompilerOptions::uses`](crate::CompilerOptions::uses). Targets that use each other
+//! in a cycle are reported and none of them is generated.
+
+use std::collections::BTreeSet;
+
+use z_semantic::{SemanticError, Target};
+
+use crate::schema::suggestion;
+
+/// Which targets of a program each one uses
+pub(crate) struct Graph {
+    /// Indexes of the targets each target uses, in the order it lists them
+    uses: Vec<Vec<usize>>,
+}
+
+impl Graph {
+    /// The graph of `targets`, and the `uses` that name no other target or more than one
+    pub(crate) fn build(targets: &[Target]) -> (Self, Vec<SemanticError>) {
+        let mut errors = Vec::new();
+        let mut uses = Vec::new();
+        for (index, target) in targets.iter().enumerate() {
+            let mut used = Vec::new();
+            for dependency in &target.uses {
+                let named: Vec<usize> = (0..targets.len())
+                    .filter(|&other| targets[other].name == dependency.target || targets[other].qualified_name() == dependency.target)
+                    .collect();
+                let message = match named.as_slice() {
+                    [other] if *other == index => format!("'{}' uses itself", target.qualified_name()),
+                    [other] => {
+                        if !used.contains(other) {
+                            used.push(*other);
+                        }
+                        continue;
+                    }
+                    [] => {
+                        let names: Vec<&str> = targets.iter().map(|target| target.name.as_str()).collect();
+                        format!("'uses': no target named '{}'{}", dependency.target, suggestion(&dependency.target, &names))
+                    }
+                    _ => {
+                        let names: Vec<String> = named.iter().map(|&other| targets[other].qualified_name()).collect();
+                        format!("'uses': more than one target is named '{}' ({}); write which, e.g. `uses: {}`", dependency.target, names.join(", "), names[0])
+                    }
+                };
+                errors.push(SemanticError { message, span: dependency.span });
+            }
+            uses.push(used);
+        }
+        (Self { uses }, errors)
+    }
+
+    /// Indexes of the targets the target at `index` uses
+    pub(crate) fn uses(&self, index: usize) -> &[usize] {
+        &self.uses[index]
+    }
+
+    /// Indexes of the targets that use the target at `index`
+    pub(crate) fn used_by(&self, index: usize) -> impl Iterator<Item = usize> + '_ {
+        (0..self.uses.len()).filter(move |&other| self.uses[other].contains(&index))
+    }
+
+    /// Indexes of `targets`, each after the targets it uses and otherwise in the order
+    /// they're written, and the cycles that kept some from being ordered. Those targets,
+    /// and the ones that use them, come last.
+    pub(crate) fn order(&self, targets: &[Target]) -> (Vec<usize>, Vec<SemanticError>) {
+        let mut order = Vec::new();
+        let mut ordered = vec![false; targets.len()];
+        let mut ready: BTreeSet<usize> = (0..targets.len()).filter(|&index| self.uses[index].is_empty()).collect();
+        while let Some(index) = ready.pop_first() {
+            order.push(index);
+            ordered[index] = true;
+            ready.extend(self.used_by(index).filter(|&other| !ordered[other] && self.uses[other].iter().all(|&used| ordered[used])));
+        }
+
+        // Every target left uses another one left, so following those leads to a cycle
+        let mut errors = Vec::new();
+        let mut reported = vec![false; targets.len()];
+        for start in (0..targets.len()).filter(|&index| !ordered[index]) {
+            let mut path = vec![start];
+            let cycle = loop {
+                let last = path[path.len() - 1];
+                let next = self.uses[last].iter().copied().find(|&used| !ordered[used]).expect("a target left uses another one left");
+                if let Some(at) = path.iter().position(|&index| index == next) {
+                    break path.split_off(at);
+                }
+                path.push(next);
+            };
+            if cycle.iter().any(|&index| reported[index]) {
+                continue;
+            }
+            let names: Vec<String> = cycle.iter().chain(&cycle[..1]).map(|&index| targets[index].qualified_name()).collect();
+            // Pointing at where the first target of the cycle uses the second
+            let (first, second) = (&targets[cycle[0]], &targets[cycle[1]]);
+            let span = first.uses.iter()
+                .find(|dependency| dependency.target == second.name || dependency.target == second.qualified_name())
+                .map_or(first.span, |dependency| dependency.span);
+            errors.push(SemanticError { message: format!("targets use each other in a cycle: {}", names.join(" → ")), span });
+            for index in cycle {
+                reported[index] = true;
+            }
+        }
+        order.extend((0..targets.len()).filter(|&index| !ordered[index]));
+        (order, errors)
+    }
+}
@@ -0,0 +1,155 @@
+//! SHA-256 content hashes for reports and caches.
+
+use z_ast::visit::{walk_child_line, ChildLine};
+use z_ast::{walk_element, Annotation, Element, Node, Span, Visitor};
+
+const K: [u32; 64] = [
+    0x428a2f98, 0x71374491, 0xb5c0fbcf, 0xe9b5dba5, 0x3956c25b, 0x59f111f1, 0x923f82a4, 0xab1c5ed5,
+    0xd807aa98, 0x12835b01, 0x243185be, 0x550c7dc3, 0x72be5d74, 0x80deb1fe, 0x9bdc06a7, 0xc19bf174,
+    0xe49b69c1, 0xefbe4786, 0x0fc19dc6, 0x240ca1cc, 0x2de92c6f, 0x4a7484aa, 0x5cb0a9dc, 0x76f988da,
+    0x983e5152, 0xa831c66d, 0xb00327c8, 0xbf597fc7, 0xc6e00bf3, 0xd5a79147, 0x06ca6351, 0x14292967,
+    0x27b70a85, 0x2e1b2138, 0x4d2c6dfc, 0x53380d13, 0x650a7354, 0x766a0abb, 0x81c2c92e, 0x92722c85,
+    0xa2bfe8a1, 0xa81a664b, 0xc24b8b70, 0xc76c51a3, 0xd192e819, 0xd6990624, 0xf40e3585, 0x106aa070,
+    0x19a4c116, 0x1e376c08, 0x2748774c, 0x34b0bcb5, 0x391c0cb3, 0x4ed8aa4a, 0x5b9cca4f, 0x682e6ff3,
+    0x748f82ee, 0x78a5636f, 0x84c87814, 0x8cc70208, 0x90befffa, 0xa4506ceb, 0xbef9a3f7, 0xc67178f2,
+];
+
+/// Hex-encoded SHA-256 digest of `data`
+pub fn sha256_hex(data: &[u8]) -> String {
+    sha256(data).iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+/// Hash of what a program means rather than how it's written: spans, comments and
+/// blank lines don't contribute, so reformatting a file keeps its fingerprint.
+pub fn ast_fingerprint(program: &Element) -> String {
+    let mut fingerprint = Fingerprint(String::new());
+    fingerprint.visit_element(program);
+    sha256_hex(fingerprint.0.as_bytes())
+}
+
+/// Fingerprint of what one target's output depends on: its own block (`kind:name`) and
+/// everything around it that isn't another target, such as shared `Meta` or `Schema`
+/// blocks. Editing one target leaves the fingerprints of the others as they were.
+pub fn target_fingerprint(program: &Element, target: &str) -> String {
+    let mut fingerprint = Fingerprint(String::new());
+    fingerprint.scope(program, target);
+    sha256_hex(fingerprint.0.as_bytes())
+}
+
+/// Length-prefixed dump of every meaningful field, so distinct trees can't collide
+struct Fingerprint(String);
+
+impl Fingerprint {
+    /// Visit the top level or a workspace, leaving out the targets other than `target`
+    fn scope(&mut self, parent: &Element, target: &str) {
+        self.field('E', Some(&parent.name));
+        self.field('s', parent.signature.as_deref());
+        for annotation in &parent.annotations {
+            self.visit_annotation(annotation);
+        }
+        for child in &parent.children {
+            match child {
+                Node::Element(element) if element.name.starts_with("workspace:") => self.scope(element, target),
+                Node::Element(element) if is_target(&element.name) && element.name != target => {}
+                _ => self.visit_node(child),
+            }
+        }
+        self.0.push('}');
+    }
+
+    fn field(&mut self, tag: char, value: Option<&str>) {
+        let value = value.unwrap_or_default();
+        self.0.push_str(&format!("{}{}:{}", tag, value.len(), value));
+    }
+}
+
+impl Visitor for Fingerprint {
+    fn visit_element(&mut self, element: &Element) {
+        self.field('E', Some(&element.name));
+        self.field('s', element.signature.as_deref());
+        walk_element(self, element);
+        self.0.push('}');
+    }
+
+    fn visit_child_line(&mut self, line: ChildLine<'_>) {
+        self.field('L', Some(line.id));
+        self.field('m', line.modifier);
+        self.field('s', line.signature);
+        walk_child_line(self, line);
+    }
+
+    fn visit_key_value(&mut self, key: &str, value: &str, _span: Span) {
+        self.field('K', Some(key));
+        self.field('v', Some(value));
+    }
+
+    fn visit_const(&mut self, name: &str, value: &str, _span: Span) {
+        self.field('C', Some(name));
+        self.field('v', Some(value));
+    }
+
+    fn visit_annotation(&mut self, annotation: &Annotation) {
+        self.field('@', Some(&annotation.name));
+        self.field('a', annotation.args.as_deref());
+    }
+}
+
+/// Whether an element named `name` is a target block such as `next:web`
+fn is_target(name: &str) -> bool {
+    name.split_once(':').is_some_and(|(kind, _)| kind != "template")
+}
+
+pub fn sha256(data: &[u8]) -> [u8; 32] {
+    let mut state: [u32; 8] = [
+        0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a, 0x510e527f, 0x9b05688c, 0x1f83d9ab, 0x5be0cd19,
+    ];
+
+    // Pad with a 1 bit, zeros, and the message length in bits to a multiple of 64 bytes
+    let mut message = data.to_vec();
+    message.push(0x80);
+    while message.len() % 64 != 56 {
+        message.push(0);
+    }
+    message.extend_from_slice(&((data.len() as u64) * 8).to_be_bytes());
+
+    for chunk in message.chunks_exact(64) {
+        let mut w = [0u32; 64];
+        for (i, word) in chunk.chunks_exact(4).enumerate() {
+            w[i] = u32::from_be_bytes([word[0], word[1], word[2], word[3]]);
+        }
+        for i in 16..64 {
+            let s0 = w[i - 15].rotate_right(7) ^ w[i - 15].rotate_right(18) ^ (w[i - 15] >> 3);
+            let s1 = w[i - 2].rotate_right(17) ^ w[i - 2].rotate_right(19) ^ (w[i - 2] >> 10);
+            w[i] = w[i - 16].wrapping_add(s0).wrapping_add(w[i - 7]).wrapping_add(s1);
+        }
+
+        let [mut a, mut b, mut c, mut d, mut e, mut f, mut g, mut h] = state;
+        for i in 0..64 {
+            let s1 = e.rotate_right(6) ^ e.rotate_right(11) ^ e.rotate_right(25);
+            let ch = (e & f) ^ (!e & g);
+            let t1 = h.wrapping_add(s1).wrapping_add(ch).wrapping_add(K[i]).wrapping_add(w[i]);
+            let s0 = a.rotate_right(2) ^ a.rotate_right(13) ^ a.rotate_right(22);
+            let maj = (a & b) ^ (a & c) ^ (b & c);
+            let t2 = s0.wrapping_add(maj);
+
+            h = g;
+            g = f;
+            f = e;
+            e = d.wrapping_add(t1);
+            d = c;
+            c = b;
+            b = a;
+            a = t1.wrapping_add(t2);
+        }
+
+        for (value, add) in state.iter_mut().zip([a, b, c, d, e, f, g, h]) {
+            *value = value.wrapping_add(add);
+        }
+    }
+
+    let mut digest = [0u8; 32];
+    for (bytes, value) in digest.chunks_exact_mut(4).zip(state) {
+        bytes.copy_from_slice(&value.to_be_bytes());
+    }
+    digest
+}
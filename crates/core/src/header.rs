@@ -0,0 +1,108 @@
+//! The comment generated files start with.
+//!
+//! Every generated file that can hold a comment starts with one saying what generated
+//! it, from which file and target, and a hash of what was generated:
+//!
+//! ```ts
+//! // @generated by z 0.1.0 from main.z (next:Web) z-hash:3f9a0c1d2b4e5f60
+//! ```
+//!
+//! The hash leaves out the header itself and the [kept regions](crate::keep), so when a
+//! file is about to be regenerated, a hash that no longer matches means it was edited
+//! by hand. Such a file is kept rather than overwritten (see [`emit`](crate::emit)).
+//!
+//! Files that can't hold a comment, such as JSON, or that must start with something
+//! else, such as HTML and XML, get no header, and neither do binary files.
+
+use std::fs;
+use std::path::Path;
+
+use crate::hash::sha256_hex;
+use crate::keep;
+use crate::report::is_skipped;
+use crate::sink::FileSink;
+use crate::store::write_file;
+
+const MARKER: &str = "@generated by z";
+const HASH: &str = "z-hash:";
+
+/// Where the files of a target came from
+pub(crate) struct Provenance<'a> {
+    /// The `.z` file declaring the target, as shown in messages, if it was read from one
+    pub source: Option<&'a Path>,
+    /// The target's `kind:name`
+    pub target: &'a str,
+}
+
+/// Start every file of the project in `sink` that can hold a comment with the header
+/// for `provenance`
+pub(crate) fn stamp(sink: &dyn FileSink, provenance: &Provenance) -> Result<(), String> {
+    for path in sink.files().into_iter().filter(|path| !is_skipped(path)) {
+        let Some(comment) = comment(&path) else {
+            continue;
+        };
+        // Binary files are left as they are
+        if let Some(contents) = sink.read(&path)?.and_then(|contents| String::from_utf8(contents).ok()) {
+            sink.write(&path, stamped(&contents, comment, provenance).as_bytes())?;
+        }
+    }
+    Ok(())
+}
+
+/// Start the file at `path` with the header for `provenance`, if it can hold a comment
+pub(crate) fn stamp_file(path: &Path, provenance: &Provenance) -> Result<(), String> {
+    let Some(comment) = comment(path) else {
+        return Ok(());
+    };
+    match fs::read_to_string(path) {
+        Ok(contents) => write_file(path, &stamped(&contents, comment, provenance)),
+        Err(_) => Ok(()),
+    }
+}
+
+/// Whether `contents` has a header whose hash it no longer matches
+pub(crate) fn edited(contents: &str) -> bool {
+    let hash = contents.lines().take(2)
+        .find(|line| line.contains(MARKER))
+        .and_then(|line| line.split(HASH).nth(1))
+        .map(|rest| rest.trim_start().chars().take_while(char::is_ascii_hexdigit).collect::<String>());
+    hash.is_some_and(|hash| hash != digest(contents))
+}
+
+/// `contents` with the header, in place of the one it has if any
+fn stamped(contents: &str, (open, close): (&str, &str), provenance: &Provenance) -> String {
+    let mut lines: Vec<&str> = contents.lines().filter(|line| !line.contains(MARKER)).collect();
+    let from = provenance.source.map(|source| format!(" from {}", source.display())).unwrap_or_default();
+    let header = format!(
+        "{} {} {}{} ({}) {}{}{}",
+        open, MARKER, env!("CARGO_PKG_VERSION"), from, provenance.target, HASH, digest(contents), close,
+    );
+    // A shebang, or the tools version of `Package.swift`, has to stay first
+    let first = lines.first().is_some_and(|line| line.starts_with("#!/") || line.starts_with("// swift-tools-version"));
+    lines.insert(usize::from(first), &header);
+    let mut stamped = lines.join("\n");
+    if contents.ends_with('\n') || contents.is_empty() {
+        stamped.push('\n');
+    }
+    stamped
+}
+
+/// Hash of the lines of `contents` that regenerating decides: those outside its kept
+/// regions, except the header
+fn digest(contents: &str) -> String {
+    let lines: Vec<&str> = keep::outside_regions(contents).filter(|line| !line.contains(MARKER)).collect();
+    sha256_hex(lines.join("\n").as_bytes())[..16].to_string()
+}
+
+/// How a line comment opens and closes in the file at `path`, if it can hold one
+fn comment(path: &Path) -> Option<(&'static str, &'static str)> {
+    if path.file_name().is_some_and(|name| name == ".env.example") {
+        return Some(("#", ""));
+    }
+    match path.extension()?.to_str()? {
+        "ts" | "tsx" | "js" | "jsx" | "mjs" | "cjs" | "rs" | "swift" | "xcconfig" => Some(("//", "")),
+        "css" | "scss" => Some(("/*", " */")),
+        "toml" | "yaml" | "yml" | "sh" => Some(("#", "")),
+        _ => None,
+    }
+}
@@ -0,0 +1,157 @@
+//! Commands run in a target's output directory once it's generated, such as installing
+//! its packages or formatting it.
+//!
+//! A target lists them in its block, or every target of a type in `z.toml`:
+//!
+//! ```z
+//! next Web {
+//!   after_generate: ["pnpm install", "pnpm format"]
+//! }
+//! ```
+//!
+//! ```toml
+//! [targets.next]
+//! after_generate = ["pnpm install", "pnpm format"]
+//! ```
+//!
+//! A list in the target block replaces the one in `z.toml`. The commands run one after
+//! the other in the project's [sandbox](crate::sandbox), which declaring them lets run
+//! their programs. They're split into words the way a shell would, but aren't run by
+//! one, so there are no pipes, redirects or variables. The first that fails fails the
+//! target, and what each printed is kept in the target's report as a [`HookRun`].
+//!
+//! The sandbox keeps hooks off the network unless its `[sandbox]` table says otherwise,
+//! so one installing packages needs `network = true`, approved with `z trust`. A hook
+//! that fails without it is reported with that as the fix.
+//!
+//! Hooks run whenever the target is generated or restored from a cache, but not when
+//! it's unchanged or in a dry run. Generated files they rewrite, as a formatter does,
+//! are [adopted](crate::emit::adopt) as they are then, so the next compile doesn't take
+//! them for files edited by hand.
+
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::time::Instant;
+
+use serde_json::Value;
+use z_semantic::Target;
+
+use crate::report::HookRun;
+use crate::sandbox::{Isolation, Sandbox};
+use crate::{tr, CompileOptions};
+
+/// Key of the commands in a target block and in the `[targets.<target type>]` tables of `z.toml`
+pub const AFTER_GENERATE: &str = "after_generate";
+
+/// The `after_generate` commands of the `[targets.<target type>]` tables of `z.toml`
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Hooks {
+    by_kind: BTreeMap<String, Vec<String>>,
+}
+
+impl Hooks {
+    /// Read the `targets` table of a parsed `z.toml`
+    pub fn from_config(config: &Value) -> Result<Self, String> {
+        let mut hooks = Self::default();
+        let Some(kinds) = config.get("targets").and_then(Value::as_object) else {
+            return Ok(hooks);
+        };
+        for (kind, table) in kinds {
+            let commands = match &table[AFTER_GENERATE] {
+                Value::Null => continue,
+                Value::String(command) => vec![command.clone()],
+                Value::Array(commands) => commands.iter()
+                    .map(|command| command.as_str().map(str::to_string))
+                    .collect::<Option<Vec<_>>>()
+                    .ok_or_else(|| format!("'targets.{}.{}' must be a list of commands", kind, AFTER_GENERATE))?,
+                _ => return Err(format!("'targets.{}.{}' must be a list of commands", kind, AFTER_GENERATE)),
+            };
+            hooks.by_kind.insert(kind.clone(), commands);
+        }
+        Ok(hooks)
+    }
+
+    /// Commands for every target of type `kind`
+    pub fn for_kind(&self, kind: &str) -> &[String] {
+        self.by_kind.get(kind).map(Vec::as_slice).unwrap_or_default()
+    }
+}
+
+/// The commands to run once `target` is generated
+pub(crate) fn commands<'a>(target: &'a Target, hooks: &'a Hooks) -> Vec<&'a str> {
+    if target.after_generate.is_empty() {
+        hooks.for_kind(&target.kind).iter().map(String::as_str).collect()
+    } else {
+        target.after_generate.iter().map(|hook| hook.command.as_str()).collect()
+    }
+}
+
+/// Run `commands` in `output_dir` until one fails, adding each one run to `runs`
+pub(crate) fn run(commands: &[&str], output_dir: &Path, options: &CompileOptions, runs: &mut Vec<HookRun>) -> Result<(), String> {
+    let programs = commands.iter().map(|command| words(command)).collect::<Vec<_>>();
+    let mut policy = options.plugins.policy.clone();
+    policy.commands.extend(programs.iter().filter_map(|words| words.first().cloned()));
+    let project_dir = Some(options.plugins.project_dir.as_path())
+        .filter(|dir| !dir.as_os_str().is_empty())
+        .or(options.source_dir.as_deref())
+        .filter(|dir| !dir.as_os_str().is_empty())
+        .unwrap_or(Path::new("."));
+    let sandbox = Sandbox::new(policy, project_dir, output_dir)
+        .map_err(|e| tr!("hook-failed", command = commands.first().copied().unwrap_or_default(), error = e))?;
+
+    for (command, words) in commands.iter().zip(programs) {
+        let failed = |error: String| tr!("hook-failed", command = command, error = error);
+        let Some((program, args)) = words.split_first() else {
+            continue;
+        };
+        let args: Vec<&str> = args.iter().map(String::as_str).collect();
+        let started = Instant::now();
+        let output = sandbox.command(program, &args, output_dir)
+            .and_then(|mut command| command.output().map_err(|e| tr!("tool-exec-failed", tool = program, error = e)))
+            .map_err(failed)?;
+        let run = HookRun {
+            command: command.to_string(),
+            exit_code: output.status.code(),
+            duration_ms: started.elapsed().as_millis() as u64,
+            stdout: String::from_utf8_lossy(&output.stdout).into_owned(),
+            stderr: String::from_utf8_lossy(&output.stderr).into_owned(),
+        };
+        let error = (!output.status.success()).then(|| match (run.stderr.trim(), output.status.code()) {
+            ("", Some(code)) => tr!("hook-exit-code", code = code),
+            ("", None) => tr!("tool-failed", tool = program),
+            (stderr, _) => stderr.to_string(),
+        });
+        runs.push(run);
+        if let Some(error) = error {
+            return Err(failed(error));
+        }
+    }
+    Ok(())
+}
+
+/// Whether the last of `runs` failed while the sandbox kept it off the network, which
+/// may be why
+pub(crate) fn offline(runs: &[HookRun], options: &CompileOptions) -> bool {
+    let failed = runs.last().is_some_and(|run| run.exit_code != Some(0));
+    failed && !options.plugins.policy.network && !matches!(Isolation::detect(), Ok(Isolation::Unconfined))
+}
+
+/// `command` split into words at spaces outside quotes, the quotes taken out
+fn words(command: &str) -> Vec<String> {
+    let mut words = Vec::new();
+    let mut word: Option<String> = None;
+    let mut quote = None;
+    for c in command.chars() {
+        match (c, quote) {
+            ('"' | '\'', None) => {
+                quote = Some(c);
+                word.get_or_insert_with(String::new);
+            }
+            (c, Some(open)) if c == open => quote = None,
+            (c, None) if c.is_whitespace() => words.extend(word.take()),
+            (c, _) => word.get_or_insert_with(String::new).push(c),
+        }
+    }
+    words.extend(word);
+    words
+}
@@ -0,0 +1,213 @@
+//! Localized compiler and CLI messages.
+//!
+//! Catalogs are Fluent (`.ftl`) files under `shared/locales/`, embedded at build
+//! time like the other [assets](crate::assets). Only the subset of Fluent we need is understood: `id = text` messages,
+//! indented continuation lines, `#` comments, `{ $variable }` placeables, and selects
+//! on a number, one variant per line, by its value or its plural category (`one` for 1,
+//! `other` for the rest, as in English and Spanish):
+//!
+//! ```ftl
+//! files-written = { $count ->
+//!         [one] One file
+//!        *[other] { $count } files
+//!     } written
+//! ```
+//!
+//! A message missing from the active catalog falls back to English, and an id
+//! missing from English too is shown as-is so a typo never hides output.
+
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::str::FromStr;
+use std::sync::{OnceLock, RwLock};
+
+/// Languages with a message catalog
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Locale {
+    #[default]
+    En,
+    Es,
+}
+
+impl Locale {
+    pub const ALL: [Locale; 2] = [Locale::En, Locale::Es];
+
+    pub fn code(self) -> &'static str {
+        match self {
+            Locale::En => "en",
+            Locale::Es => "es",
+        }
+    }
+
+    /// Locale requested by the environment: `Z_LOCALE` first, then the usual
+    /// POSIX variables (`LC_ALL`, `LC_MESSAGES`, `LANG`). Unsupported values are skipped.
+    pub fn from_env() -> Option<Locale> {
+        ["Z_LOCALE", "LC_ALL", "LC_MESSAGES", "LANG"]
+            .iter()
+            .filter_map(|var| std::env::var(var).ok())
+            .find_map(|value| value.parse().ok())
+    }
+
+    fn source(self) -> &'static str {
+        crate::assets::get(&format!("locales/{}.ftl", self.code())).unwrap_or(self.embedded())
+    }
+
+    /// The catalog built into the compiler
+    pub(crate) fn embedded(self) -> &'static str {
+        match self {
+            Locale::En => include_str!("../../../shared/locales/en.ftl"),
+            Locale::Es => include_str!("../../../shared/locales/es.ftl"),
+        }
+    }
+
+    fn catalog(self) -> &'static HashMap<String, String> {
+        static CATALOGS: [OnceLock<HashMap<String, String>>; 2] = [OnceLock::new(), OnceLock::new()];
+        CATALOGS[self as usize].get_or_init(|| parse_ftl(self.source()))
+    }
+}
+
+impl FromStr for Locale {
+    type Err = String;
+
+    /// Accepts bare language codes as well as POSIX/BCP 47 tags like `es_AR.UTF-8` or `en-GB`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let language = s.split(['-', '_', '.']).next().unwrap_or_default().to_ascii_lowercase();
+        Locale::ALL
+            .into_iter()
+            .find(|locale| locale.code() == language)
+            .ok_or_else(|| format!("unsupported locale '{}' (expected one of: en, es)", s))
+    }
+}
+
+static CURRENT: RwLock<Locale> = RwLock::new(Locale::En);
+
+/// Select the language used by every subsequent message
+pub fn set_locale(locale: Locale) {
+    *CURRENT.write().unwrap_or_else(|e| e.into_inner()) = locale;
+}
+
+pub fn locale() -> Locale {
+    *CURRENT.read().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Look up message `id` in the active locale and fill in its placeables.
+/// Prefer the [`tr!`](crate::tr) macro, which builds `args` from `name = value` pairs.
+pub fn message(id: &str, args: &[(&str, &dyn Display)]) -> String {
+    let pattern = locale().catalog().get(id)
+        .or_else(|| Locale::En.catalog().get(id))
+        .map_or(id, String::as_str);
+    format_pattern(pattern, args)
+}
+
+/// Translate a message: `tr!("compile-failed", target = kind, error = e)`
+#[macro_export]
+macro_rules! tr {
+    ($id:expr) => {
+        $crate::i18n::message($id, &[])
+    };
+    ($id:expr, $($name:ident = $value:expr),+ $(,)?) => {
+        $crate::i18n::message($id, &[$((stringify!($name), &$value as &dyn ::std::fmt::Display)),+])
+    };
+}
+
+fn format_pattern(pattern: &str, args: &[(&str, &dyn Display)]) -> String {
+    let mut out = String::with_capacity(pattern.len());
+    let mut rest = pattern;
+
+    while let Some(open) = rest.find('{') {
+        out.push_str(&rest[..open]);
+        let Some(close) = closing_brace(rest, open) else {
+            break;
+        };
+        let placeable = rest[open + 1..close].trim();
+        let argument = |name: &str| name.trim().strip_prefix('$')
+            .and_then(|name| args.iter().find(|(arg, _)| *arg == name));
+        if let Some((selector, variants)) = placeable.split_once("->") {
+            let value = argument(selector).map(|(_, value)| value.to_string()).unwrap_or_default();
+            out.push_str(&format_pattern(select(&value, variants), args));
+            rest = &rest[close + 1..];
+            continue;
+        }
+        match argument(placeable) {
+            Some((_, value)) => out.push_str(&value.to_string()),
+            // String literal placeables are how Fluent escapes braces: { "{" }
+            None if placeable.len() >= 2 && placeable.starts_with('"') && placeable.ends_with('"') => {
+                out.push_str(&placeable[1..placeable.len() - 1])
+            }
+            None => out.push_str(&rest[open..=close]),
+        }
+        rest = &rest[close + 1..];
+    }
+
+    out.push_str(rest);
+    out
+}
+
+/// Offset of the `}` closing the placeable opened at `open`, past those nested in it
+fn closing_brace(pattern: &str, open: usize) -> Option<usize> {
+    let mut depth = 0;
+    for (offset, c) in pattern[open..].char_indices() {
+        match c {
+            '{' => depth += 1,
+            '}' if depth == 1 => return Some(open + offset),
+            '}' => depth -= 1,
+            _ => {}
+        }
+    }
+    None
+}
+
+/// The variant of a select for `value`: the one keyed with `value` itself, else with
+/// its plural category, else the default one, marked with `*`
+fn select<'a>(value: &str, variants: &'a str) -> &'a str {
+    let variants: Vec<(bool, &str, &str)> = variants.lines()
+        .filter_map(|line| {
+            let line = line.trim();
+            let (default, line) = line.strip_prefix('*').map_or((false, line), |line| (true, line));
+            let (key, text) = line.strip_prefix('[')?.split_once(']')?;
+            Some((default, key.trim(), text.trim()))
+        })
+        .collect();
+    let category = match value.parse::<f64>() {
+        Ok(1.0) => "one",
+        Ok(_) => "other",
+        Err(_) => "",
+    };
+    variants.iter().find(|(_, key, _)| *key == value)
+        .or_else(|| variants.iter().find(|(_, key, _)| *key == category))
+        .or_else(|| variants.iter().find(|(default, _, _)| *default))
+        .map_or("", |(_, _, text)| text)
+}
+
+fn parse_ftl(source: &str) -> HashMap<String, String> {
+    let mut messages = HashMap::new();
+    let mut current: Option<(String, String)> = None;
+
+    for line in source.lines() {
+        if line.starts_with(' ') && !line.trim().is_empty() {
+            // Continuation of a multi-line message
+            if let Some((_, value)) = current.as_mut() {
+                if !value.is_empty() {
+                    value.push('\n');
+                }
+                value.push_str(line.trim());
+            }
+            continue;
+        }
+
+        if let Some((id, value)) = current.take() {
+            messages.insert(id, value);
+        }
+        if line.starts_with('#') {
+            continue;
+        }
+        if let Some((id, value)) = line.split_once('=') {
+            current = Some((id.trim().to_string(), value.trim().to_string()));
+        }
+    }
+
+    if let Some((id, value)) = current {
+        messages.insert(id, value);
+    }
+    messages
+}
@@ -0,0 +1,297 @@
+//! Identifiers platforms know an app by: the reverse-DNS bundle id of `swift` and
+//! `tauri` apps, and the package name of the npm and Cargo projects generated.
+//!
+//! They're derived unless a target sets them with `bundle_id: ...` or `package: ...`:
+//!
+//! - the bundle id from the `config` domain and the target's name: `acme.dev` and
+//!   `mobile` make `dev.acme.mobile`; without a domain, apps keep their tools' default;
+//! - the package name from the app's name, `Acme Notes` making `acme-notes`, with the
+//!   target's name added when apps of one ecosystem would share it (`acme-notes-admin`).
+//!   The crate name is the package name where Cargo takes it as it is.
+//!
+//! [`assign`] checks them all before anything is generated, as a platform would reject
+//! an invalid one and two apps can't share one, then records the derived ones as the
+//! target's settings, so compilers read them like ones written in the target block.
+
+use std::path::Path;
+
+use z_ast::Span;
+use z_semantic::{Program, SemanticError, Setting, Target};
+
+use crate::compilers::package_name;
+use crate::links::plist;
+use crate::sink::FileSink;
+use crate::version::{set_package_field, set_plist_string};
+
+/// Target types whose projects have a `package.json`
+const NPM: [&str; 2] = ["next", "tauri"];
+/// Target types whose projects have a `Cargo.toml`
+const CARGO: [&str; 2] = ["rust", "tauri"];
+/// Target types that are bundled as apps
+const BUNDLED: [&str; 2] = ["swift", "tauri"];
+
+/// Words Cargo doesn't take as package names
+const RESERVED_CRATES: [&str; 43] = [
+    "abstract", "as", "async", "await", "become", "box", "break", "const", "continue", "crate", "do", "dyn",
+    "else", "enum", "extern", "false", "final", "fn", "for", "if", "impl", "in", "let", "loop", "macro",
+    "match", "mod", "move", "mut", "override", "priv", "pub", "ref", "return", "self", "static", "struct",
+    "super", "trait", "true", "type", "test", "std",
+];
+
+/// Package name of `target`'s npm or Cargo project
+pub(crate) fn package(target: &Target) -> String {
+    setting(target, "package").map(str::to_string).unwrap_or_else(|| package_name(target.display_name()))
+}
+
+/// Name of `target`'s Cargo package: its package name without an npm scope
+pub(crate) fn crate_name(target: &Target) -> String {
+    let package = package(target);
+    let unscoped = package.rsplit('/').next().unwrap_or_default();
+    unscoped.replace(['.', '~'], "-")
+}
+
+/// Bundle id of `target`, if it has one
+pub(crate) fn bundle_id(target: &Target) -> Option<&str> {
+    setting(target, "bundle_id")
+}
+
+fn setting<'a>(target: &'a Target, key: &str) -> Option<&'a str> {
+    target.settings.iter().rev().find(|setting| setting.key == key).map(|setting| setting.value.trim().trim_matches('"'))
+}
+
+/// Check the identifiers of every target of `program`, and record the derived ones
+pub(crate) fn assign(program: &mut Program) -> Vec<SemanticError> {
+    let mut errors = Vec::new();
+    let mut assigned: Vec<Assigned> = Vec::new();
+
+    // Bases of derived package names, to tell which ones apps would share
+    let bases: Vec<String> = program.targets.iter()
+        .map(|target| Some(package_name(target.display_name())).filter(|base| !base.is_empty()).unwrap_or_else(|| package_name(&target.name)))
+        .collect();
+    for (index, target) in program.targets.iter().enumerate() {
+        let explicit_span = |key: &str| target.settings.iter().rev().find(|setting| setting.key == key).map(|setting| setting.span);
+
+        let package = match setting(target, "package") {
+            Some(package) => Identifier { value: package.to_string(), span: explicit_span("package").unwrap_or(target.span), explicit: true },
+            None => {
+                let base = &bases[index];
+                let shared = program.targets.iter().enumerate().any(|(other, other_target)| {
+                    other != index && bases[other] == *base && ecosystems_meet(&target.kind, &other_target.kind)
+                });
+                let name = package_name(&target.name);
+                let value = if shared && name != *base { format!("{}-{}", base, name) } else { base.clone() };
+                Identifier { value, span: target.span, explicit: false }
+            }
+        };
+        if NPM.contains(&target.kind.as_str()) {
+            if let Err(problem) = check_npm_name(&package.value) {
+                errors.push(invalid(&package, "package name", &problem));
+            }
+        }
+        if CARGO.contains(&target.kind.as_str()) {
+            let crate_name = package.value.rsplit('/').next().unwrap_or_default().replace(['.', '~'], "-");
+            if let Err(problem) = check_crate_name(&crate_name) {
+                errors.push(invalid(&Identifier { value: crate_name, ..package.clone() }, "crate name", &problem));
+            }
+        }
+
+        let bundle_id = match setting(target, "bundle_id") {
+            Some(bundle_id) => Some(Identifier { value: bundle_id.to_string(), span: explicit_span("bundle_id").unwrap_or(target.span), explicit: true }),
+            None => target.config.as_ref().and_then(|config| config.domain.as_deref()).map(|domain| Identifier {
+                value: format!("{}.{}", domain.rsplit('.').collect::<Vec<_>>().join("."), package_name(&target.name)),
+                span: target.span,
+                explicit: false,
+            }),
+        };
+        if let Some(bundle_id) = bundle_id.as_ref().filter(|_| BUNDLED.contains(&target.kind.as_str())) {
+            if let Err(problem) = check_bundle_id(&bundle_id.value) {
+                errors.push(invalid(bundle_id, "bundle id", &problem));
+            }
+        }
+        assigned.push(Assigned { package, bundle_id });
+    }
+
+    // Two apps of one ecosystem can't share a name
+    for (index, target) in program.targets.iter().enumerate() {
+        let earlier = program.targets[..index].iter().zip(&assigned);
+        for (other, other_assigned) in earlier {
+            let this = &assigned[index];
+            if this.package.value == other_assigned.package.value && ecosystems_meet(&target.kind, &other.kind) {
+                errors.push(taken(&this.package, "package name", other));
+            }
+            let both_bundled = BUNDLED.contains(&target.kind.as_str()) && BUNDLED.contains(&other.kind.as_str());
+            if let (Some(bundle_id), Some(other_bundle_id), true) = (&this.bundle_id, &other_assigned.bundle_id, both_bundled) {
+                if bundle_id.value == other_bundle_id.value {
+                    errors.push(taken(bundle_id, "bundle id", other));
+                }
+            }
+        }
+    }
+
+    for (target, assigned) in program.targets.iter_mut().zip(assigned) {
+        for (key, identifier) in [("package", Some(assigned.package)), ("bundle_id", assigned.bundle_id)] {
+            if let Some(identifier) = identifier.filter(|identifier| !identifier.explicit) {
+                target.settings.push(Setting { key: key.to_string(), value: identifier.value, span: identifier.span });
+            }
+        }
+    }
+    errors
+}
+
+#[derive(Clone)]
+struct Identifier {
+    value: String,
+    /// The setting, or the target block for derived ones
+    span: Span,
+    explicit: bool,
+}
+
+struct Assigned {
+    package: Identifier,
+    bundle_id: Option<Identifier>,
+}
+
+/// Whether apps of these two target types could be published under the same name
+fn ecosystems_meet(kind: &str, other: &str) -> bool {
+    [&NPM[..], &CARGO[..]].iter().any(|ecosystem| ecosystem.contains(&kind) && ecosystem.contains(&other))
+}
+
+/// What an identifier is, and the setting that sets it
+fn setting_of(what: &str) -> &'static str {
+    if what == "bundle id" { "bundle_id" } else { "package" }
+}
+
+fn invalid(identifier: &Identifier, what: &str, problem: &str) -> SemanticError {
+    let message = if identifier.explicit {
+        format!("{} '{}' {}", what, identifier.value, problem)
+    } else {
+        format!("the derived {} '{}' {}; set one with `{}: ...`", what, identifier.value, problem, setting_of(what))
+    };
+    SemanticError { message, span: identifier.span }
+}
+
+fn taken(identifier: &Identifier, what: &str, other: &Target) -> SemanticError {
+    let setting = setting_of(what);
+    SemanticError {
+        message: format!(
+            "{} '{}' is already {}'s; set another with `{}: ...`",
+            what, identifier.value, other.qualified_name(), setting,
+        ),
+        span: identifier.span,
+    }
+}
+
+/// npm's rules for package names, scoped (`@acme/notes`) or not
+fn check_npm_name(name: &str) -> Result<(), String> {
+    let unscoped = match name.strip_prefix('@') {
+        Some(scoped) => match scoped.split_once('/') {
+            Some((scope, rest)) if !scope.is_empty() => {
+                check_npm_part(scope)?;
+                rest
+            }
+            _ => return Err("isn't a scoped name like @acme/notes".to_string()),
+        },
+        None => name,
+    };
+    if name.len() > 214 {
+        return Err("is longer than the 214 characters npm allows".to_string());
+    }
+    if unscoped.starts_with(['.', '_']) {
+        return Err("can't start with '.' or '_'".to_string());
+    }
+    check_npm_part(unscoped)
+}
+
+fn check_npm_part(part: &str) -> Result<(), String> {
+    if part.is_empty() {
+        return Err("is empty".to_string());
+    }
+    match part.chars().find(|c| !(c.is_ascii_lowercase() || c.is_ascii_digit() || "-._~".contains(*c))) {
+        Some(c) if c.is_ascii_uppercase() => Err("has uppercase letters, which npm doesn't allow".to_string()),
+        Some(c) => Err(format!("has '{}'; npm names are lowercase letters, digits, '-', '.', '_' and '~'", c)),
+        None => Ok(()),
+    }
+}
+
+/// Cargo's rules for package names
+fn check_crate_name(name: &str) -> Result<(), String> {
+    let Some(first) = name.chars().next() else {
+        return Err("is empty".to_string());
+    };
+    if !first.is_ascii_alphabetic() {
+        return Err("must start with a letter".to_string());
+    }
+    if let Some(c) = name.chars().find(|c| !(c.is_ascii_alphanumeric() || *c == '-' || *c == '_')) {
+        return Err(format!("has '{}'; crate names are letters, digits, '-' and '_'", c));
+    }
+    if RESERVED_CRATES.contains(&name) {
+        return Err("is reserved by Rust".to_string());
+    }
+    Ok(())
+}
+
+/// Apple's and Tauri's rules for bundle ids
+fn check_bundle_id(id: &str) -> Result<(), String> {
+    let segments: Vec<&str> = id.split('.').collect();
+    if segments.len() < 2 {
+        return Err("isn't a reverse-DNS name like dev.acme.notes".to_string());
+    }
+    for segment in &segments {
+        if segment.is_empty() {
+            return Err("has an empty part".to_string());
+        }
+        if let Some(c) = segment.chars().find(|c| !(c.is_ascii_alphanumeric() || *c == '-')) {
+            return Err(format!("has '{}'; bundle ids are letters, digits, '-' and '.'", c));
+        }
+        if segment.starts_with('-') {
+            return Err(format!("has a part starting with '-', '{}'", segment));
+        }
+    }
+    if id.ends_with(".app") {
+        return Err("ends in '.app', which macOS takes for an application bundle".to_string());
+    }
+    Ok(())
+}
+
+/// Write `target`'s identifiers into the manifests generated by other tools, which
+/// name the project after its directory: `Cargo.toml` and `package.json` of `tauri`
+/// apps, and the bundle id wherever its platform reads it from
+pub(crate) fn write_identifiers(target: &Target, sink: &dyn FileSink) -> Result<(), String> {
+    match target.kind.as_str() {
+        "tauri" => {
+            let manifest = Path::new("src-tauri/Cargo.toml");
+            if let Some(contents) = sink.read_text(manifest)? {
+                if let Some(updated) = set_package_field(&contents, "name", &crate_name(target)) {
+                    sink.write_text(manifest, &updated)?;
+                }
+            }
+            let package_json = Path::new("package.json");
+            if sink.exists(package_json) {
+                let mut manifest = sink.read_json(package_json)?;
+                manifest["name"] = package(target).into();
+                sink.write_json(package_json, &manifest)?;
+            }
+            let path = Path::new("src-tauri/tauri.conf.json");
+            let Some(bundle_id) = bundle_id(target).filter(|_| sink.exists(path)) else {
+                return Ok(());
+            };
+            let mut config = sink.read_json(path)?;
+            // Tauri 1 keeps it under `tauri.bundle`, Tauri 2 at the top level
+            if config["tauri"]["bundle"].is_object() {
+                config["tauri"]["bundle"]["identifier"] = bundle_id.into();
+            } else {
+                config["identifier"] = bundle_id.into();
+            }
+            sink.write_json(path, &config)
+        }
+        "swift" => {
+            let Some(bundle_id) = bundle_id(target) else {
+                return Ok(());
+            };
+            let info = Path::new("Info.plist");
+            let contents = sink.read_text(info)?.unwrap_or_else(|| plist(""));
+            sink.write_text(info, &set_plist_string(&contents, "CFBundleIdentifier", bundle_id))
+        }
+        _ => Ok(()),
+    }
+}
@@ -0,0 +1,44 @@
+//! Skipping targets whose inputs haven't changed since the last compile.
+//!
+//! Once a target is generated, its cache key is recorded under `.z-cache/` in the
+//! output directory. The key covers the target's own block and what it shares with
+//! the rest of the program (see [`target_fingerprint`](crate::hash::target_fingerprint)),
+//! along with the registry, the compile options and the compiler version, so editing
+//! one target leaves the others' keys as they were. A target whose key matches the
+//! recorded one, and whose directory is still there, isn't generated again.
+//!
+//! Files the program only points to, such as an icon, aren't part of the key;
+//! `force` regenerates every target regardless.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::store::{create_dir, write_file};
+
+/// Directory under the output directory holding the recorded keys
+pub const CACHE_DIR: &str = ".z-cache";
+
+/// Whether `target` (as `kind:name`) was last generated into `output_dir` with `key`
+pub(crate) fn is_unchanged(output_base_dir: &Path, target: &str, key: &str, output_dir: &Path) -> bool {
+    output_dir.is_dir() && fs::read_to_string(record_path(output_base_dir, target)).is_ok_and(|recorded| recorded.trim() == key)
+}
+
+/// Remember that `target` was generated with `key`
+pub(crate) fn record(output_base_dir: &Path, target: &str, key: &str) -> Result<(), String> {
+    let path = record_path(output_base_dir, target);
+    if let Some(parent) = path.parent() {
+        create_dir(parent)?;
+    }
+    write_file(&path, key)
+}
+
+/// Forget the key of `target`, whose output may now be incomplete
+pub(crate) fn forget(output_base_dir: &Path, target: &str) {
+    let _ = fs::remove_file(record_path(output_base_dir, target));
+}
+
+/// `.z-cache/next/web` for `next:web`
+fn record_path(output_base_dir: &Path, target: &str) -> PathBuf {
+    let (kind, name) = target.split_once(':').unwrap_or(("", target));
+    output_base_dir.join(CACHE_DIR).join(kind).join(name)
+}
@@ -0,0 +1,290 @@
+//! Dumping the trees of a program, for `z ast`: the AST as the parser reads the file, or
+//! the typed model the compilers get, to find out why a block isn't picked up.
+//!
+//! Either comes as JSON, YAML or an outline with a line per node and where it is in the
+//! source. A [selector](z_ast::Selector), e.g. `next:MySite > Routes`, narrows it down to
+//! the nodes it matches, and in the model to what was made from them.
+
+use std::fmt;
+use std::str::FromStr;
+
+use serde_json::Value;
+use z_ast::{Annotation, Element, Node, Selector, Span};
+use z_semantic::{Program, Route, SectionBody, Target};
+
+use crate::{load_registry, parse, CompileOptions};
+
+/// Which tree of the program to dump
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Tree {
+    /// The blocks, lines and properties of the file, before any pass
+    #[default]
+    Ast,
+    /// Targets and their sections, as lowered from the AST the passes left
+    Model,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DumpFormat {
+    Json,
+    Yaml,
+    /// A line per node, indented under its parent
+    #[default]
+    Tree,
+}
+
+impl FromStr for DumpFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "json" => Ok(DumpFormat::Json),
+            "yaml" | "yml" => Ok(DumpFormat::Yaml),
+            "tree" => Ok(DumpFormat::Tree),
+            _ => Err(format!("invalid format '{}' (expected 'json', 'yaml' or 'tree')", s)),
+        }
+    }
+}
+
+impl fmt::Display for DumpFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            DumpFormat::Json => "json",
+            DumpFormat::Yaml => "yaml",
+            DumpFormat::Tree => "tree",
+        })
+    }
+}
+
+/// `tree` of `source` in `format`, only the parts `query` selects if given. The model is
+/// dumped even when the program has errors; what couldn't be understood is left out of it.
+pub fn dump(source: &str, tree: Tree, format: DumpFormat, query: Option<&str>, options: &CompileOptions) -> Result<String, String> {
+    let selector = query.map(Selector::parse).transpose()?;
+    let (ast, program) = match tree {
+        Tree::Ast => (z_parser::parse_source_with(source, &options.limits)?, None),
+        Tree::Model => {
+            let (ast, _) = parse(source, options, &load_registry(options))?;
+            let (program, _) = z_semantic::build(&ast);
+            (ast, Some(program))
+        }
+    };
+    let selected: Option<Vec<Span>> = selector.map(|selector| selector.select(&ast).iter().map(|node| node.span()).collect());
+
+    if format == DumpFormat::Tree {
+        let mut lines = Vec::new();
+        match &program {
+            Some(program) => model_outline(program, &mut lines),
+            None => ast.children.iter().for_each(|node| ast_outline(node, 0, &mut lines)),
+        }
+        return Ok(render(&lines, selected.as_deref(), source));
+    }
+
+    match (&program, &selected) {
+        (None, None) => Ok(match format {
+            DumpFormat::Yaml => z_ast::format::to_yaml(&ast),
+            _ => z_ast::format::to_json(&ast),
+        }),
+        (None, Some(spans)) => {
+            let nodes: Vec<&Node> = ast.children.iter().flat_map(|node| within(node, spans)).collect();
+            Ok(write(&nodes, format))
+        }
+        (Some(program), None) => Ok(write(program, format)),
+        (Some(program), Some(spans)) => {
+            // Found in the serialized model, where fields are in name order
+            let mut found = Vec::new();
+            spanned(&serde_json::to_value(program).expect("model serializes"), spans, &mut found);
+            Ok(write(&found, format))
+        }
+    }
+}
+
+/// `value` as JSON or YAML; JSON is written straight from the structs, so that fields
+/// keep their declaration order
+fn write(value: &impl serde::Serialize, format: DumpFormat) -> String {
+    match format {
+        DumpFormat::Yaml => z_ast::format::value_to_yaml(&serde_json::to_value(value).expect("tree serializes")),
+        _ => serde_json::to_string_pretty(value).expect("tree serializes") + "\n",
+    }
+}
+
+/// A node of an outline
+struct Line {
+    depth: usize,
+    label: String,
+    span: Span,
+}
+
+fn line(lines: &mut Vec<Line>, depth: usize, label: String, span: Span) {
+    lines.push(Line { depth, label, span });
+}
+
+/// `name @a @b(x)`
+fn annotated(label: String, annotations: &[Annotation]) -> String {
+    annotations.iter().fold(label, |label, annotation| match &annotation.args {
+        Some(args) => format!("{} @{}({})", label, annotation.name, args),
+        None => format!("{} @{}", label, annotation.name),
+    })
+}
+
+fn ast_outline(node: &Node, depth: usize, lines: &mut Vec<Line>) {
+    match node {
+        Node::Element(element) => {
+            let label = format!("{}{}", element.name, signature(element.signature.as_deref()));
+            line(lines, depth, annotated(label, &element.annotations), element.span);
+            element.children.iter().for_each(|child| ast_outline(child, depth + 1, lines));
+        }
+        Node::ChildLine { modifier, id, signature, annotations, span, .. } => {
+            let label = format!("{}{}{}", modifier.as_ref().map(|modifier| format!("{} ", modifier)).unwrap_or_default(), id, self::signature(signature.as_deref()));
+            line(lines, depth, annotated(label, annotations), *span);
+        }
+        Node::KeyValue { key, value, span, .. } => line(lines, depth, format!("{}: {}", key, value), *span),
+        Node::Const { name, value, span, .. } => line(lines, depth, format!("const {} = {}", name, value), *span),
+        Node::Expansion { template, args, children, span, .. } => {
+            line(lines, depth, format!("use {}({})", template, args.as_deref().unwrap_or_default()), *span);
+            children.iter().for_each(|child| ast_outline(child, depth + 1, lines));
+        }
+    }
+}
+
+fn model_outline(program: &Program, lines: &mut Vec<Line>) {
+    if let Some(config) = &program.config {
+        line(lines, 0, "config".to_string(), config.span);
+        config.values.iter().for_each(|setting| line(lines, 1, format!("{}: {}", setting.key, setting.value), setting.span));
+    }
+    for target in &program.targets {
+        target_outline(target, lines);
+    }
+}
+
+fn target_outline(target: &Target, lines: &mut Vec<Line>) {
+    let label = match &target.workspace {
+        Some(workspace) => format!("{} (workspace {})", target.qualified_name(), workspace),
+        None => target.qualified_name(),
+    };
+    line(lines, 0, annotated(label, &target.annotations), target.span);
+    for setting in &target.settings {
+        line(lines, 1, format!("{}: {}", setting.key, setting.value), setting.span);
+    }
+    for dependency in &target.uses {
+        line(lines, 1, format!("uses {}", dependency.target), dependency.span);
+    }
+    for hook in &target.after_generate {
+        line(lines, 1, format!("after_generate {}", hook.command), hook.span);
+    }
+    for variable in &target.env {
+        line(lines, 1, format!("env {}: {}{}", variable.name, format!("{:?}", variable.kind).to_lowercase(), if variable.optional { "?" } else { "" }), variable.span);
+    }
+    for item in &target.declarations {
+        line(lines, 1, annotated(item_label(item.kind.as_deref(), &item.name, item.signature.as_deref()), &item.annotations), item.span);
+    }
+    for section in &target.sections {
+        line(lines, 1, annotated(section.namespace.to_string(), &section.annotations), section.span);
+        match &section.body {
+            SectionBody::Routes(routes) => routes.iter().for_each(|route| route_outline(route, 2, lines)),
+            SectionBody::Endpoints(endpoints) => endpoints.iter().for_each(|endpoint| {
+                line(lines, 2, annotated(item_label(endpoint.method.as_deref(), &endpoint.name, endpoint.signature.as_deref()), &endpoint.annotations), endpoint.span);
+            }),
+            SectionBody::Components(components) => components.iter().for_each(|component| {
+                line(lines, 2, annotated(item_label(None, &component.name, component.props.as_deref()), &component.annotations), component.span);
+            }),
+            SectionBody::Models(models) => models.iter().for_each(|model| {
+                line(lines, 2, annotated(item_label(model.kind.as_deref(), &model.name, None), &model.annotations), model.span);
+                for field in &model.fields {
+                    line(lines, 3, annotated(format!("{}: {}", field.name, field.ty), &field.annotations), field.span);
+                }
+                for value in &model.values {
+                    line(lines, 3, value.clone(), model.span);
+                }
+            }),
+            SectionBody::Settings(settings) => settings.iter().for_each(|setting| line(lines, 2, format!("{}: {}", setting.key, setting.value), setting.span)),
+            SectionBody::Store(listing) => line(lines, 2, format!("{}: {}", listing.name, listing.description), listing.span),
+            SectionBody::Deploy(deployment) => line(lines, 2, format!("{} {}", format!("{:?}", deployment.provider).to_lowercase(), deployment.project), deployment.span),
+            SectionBody::Items(items) => items.iter().for_each(|item| {
+                line(lines, 2, annotated(item_label(item.kind.as_deref(), &item.name, item.signature.as_deref()), &item.annotations), item.span);
+            }),
+        }
+    }
+}
+
+fn route_outline(route: &Route, depth: usize, lines: &mut Vec<Line>) {
+    let segment = if route.dynamic { format!("/[{}]", route.segment) } else { format!("/{}", route.segment) };
+    line(lines, depth, annotated(segment, &route.annotations), route.span);
+    route.children.iter().for_each(|child| route_outline(child, depth + 1, lines));
+}
+
+/// `kind name signature`, leaving out what isn't there
+fn item_label(kind: Option<&str>, name: &str, signature: Option<&str>) -> String {
+    let label = match kind {
+        Some(kind) => format!("{} {}", kind, name),
+        None => name.to_string(),
+    };
+    format!("{}{}", label, self::signature(signature))
+}
+
+/// A signature as written after a name: parameters right after it, anything else after a space
+fn signature(signature: Option<&str>) -> String {
+    match signature {
+        Some(signature) if signature.starts_with(['(', '[', '<']) => signature.to_string(),
+        Some(signature) => format!(" {}", signature),
+        None => String::new(),
+    }
+}
+
+/// The outline, each line with where it is in `source`; with `selected`, only the nodes
+/// at those spans, each with what's under it
+fn render(lines: &[Line], selected: Option<&[Span]>, source: &str) -> String {
+    let mut out = String::new();
+    // Depth of the selected node being printed, if inside one
+    let mut root: Option<usize> = None;
+    for line in lines {
+        if root.is_some_and(|depth| line.depth <= depth) {
+            root = None;
+        }
+        let depth = match (selected, root) {
+            (None, _) => line.depth,
+            (Some(_), Some(depth)) => line.depth - depth,
+            (Some(spans), None) if spans.contains(&line.span) => {
+                root = Some(line.depth);
+                0
+            }
+            (Some(_), None) => continue,
+        };
+        let (number, column) = position(source, line.span.start);
+        out.push_str(&format!("{}{}  {}:{}\n", "  ".repeat(depth), line.label, number, column));
+    }
+    out
+}
+
+/// 1-based line and column of the byte `offset` of `source`
+fn position(source: &str, offset: usize) -> (usize, usize) {
+    let before = source.get(..offset).unwrap_or(source);
+    let line_start = before.rfind('\n').map_or(0, |newline| newline + 1);
+    (before.matches('\n').count() + 1, before[line_start..].chars().count() + 1)
+}
+
+/// The outermost nodes at or under `node` whose span is one of `spans`
+fn within<'a>(node: &'a Node, spans: &[Span]) -> Vec<&'a Node> {
+    if spans.contains(&node.span()) {
+        return vec![node];
+    }
+    match node {
+        Node::Element(Element { children, .. }) | Node::Expansion { children, .. } => children.iter().flat_map(|child| within(child, spans)).collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// The outermost objects of the serialized model whose span is one of `spans`
+fn spanned(value: &Value, spans: &[Span], found: &mut Vec<Value>) {
+    match value {
+        Value::Object(fields) => {
+            let span = fields.get("span").and_then(|span| serde_json::from_value::<Span>(span.clone()).ok());
+            if span.is_some_and(|span| spans.contains(&span)) {
+                found.push(value.clone());
+                return;
+            }
+            fields.values().for_each(|field| spanned(field, spans, found));
+        }
+        Value::Array(items) => items.iter().for_each(|item| spanned(item, spans, found)),
+        _ => {}
+    }
+}
@@ -0,0 +1,142 @@
+//! Hand edits that survive regeneration.
+//!
+//! Lines between a `z:begin-keep` and a `z:end-keep` marker in a generated file are
+//! carried over into the file generated next. The markers go in whatever comment the
+//! language has (`// z:begin-keep`, `{/* z:begin-keep */}` in JSX, `# z:begin-keep`),
+//! and a begin marker may name its region: `// z:begin-keep imports`.
+//!
+//! A region is put back, in order of preference:
+//! - in place of the region with the same name, if the new file has one;
+//! - right after the line that came before it, if the new file still has that line;
+//! - at the end of the file, which is reported since it's rarely where it belongs.
+//!
+//! A region without an end marker runs to the end of the file.
+
+const BEGIN: &str = "z:begin-keep";
+const END: &str = "z:end-keep";
+
+/// Where a region was in the file it's taken from
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Anchor<'a> {
+    /// Before any other line
+    Start,
+    /// Right after this line, trimmed
+    After(&'a str),
+    /// Right after the previous region
+    AfterRegion,
+}
+
+struct Region<'a> {
+    name: &'a str,
+    anchor: Anchor<'a>,
+    /// The region with its markers
+    lines: Vec<&'a str>,
+}
+
+/// A freshly generated file with the regions of the file it replaces put back
+pub(crate) struct Spliced {
+    pub content: String,
+    /// How many regions had nowhere to go and were put at the end
+    pub unplaced: usize,
+}
+
+/// Whether `text` has anything to keep
+pub(crate) fn has_regions(text: &str) -> bool {
+    text.contains(BEGIN)
+}
+
+/// The lines of `text` outside its regions, markers included, which are what
+/// regenerating decides
+pub(crate) fn outside_regions(text: &str) -> impl Iterator<Item = &str> {
+    let mut inside = false;
+    text.lines().filter(move |line| {
+        if begin_marker(line).is_some() {
+            inside = true;
+        }
+        let outside = !inside;
+        if line.contains(END) {
+            inside = false;
+        }
+        outside
+    })
+}
+
+/// Put the regions of `existing` into `generated`
+pub(crate) fn splice(generated: &str, existing: &str) -> Spliced {
+    let mut lines: Vec<&str> = generated.lines().collect();
+    // Regions go in the order they were in, so each anchor is looked for after the last one
+    let mut cursor = 0;
+    // Regions with nowhere to go, and the ones that came right after them
+    let mut trailing: Vec<&str> = Vec::new();
+    let mut unplaced = 0;
+    let mut previous_placed = true;
+
+    for region in regions(existing) {
+        let named = Some(region.name).filter(|name| !name.is_empty()).and_then(|name| find_region(&lines, name));
+        let place = match (named, region.anchor) {
+            (Some((start, end)), _) => Some((start, end + 1)),
+            (None, Anchor::Start) => Some((0, 0)),
+            (None, Anchor::AfterRegion) => previous_placed.then_some((cursor, cursor)),
+            (None, Anchor::After(anchor)) => lines.iter().skip(cursor).position(|line| line.trim() == anchor)
+                .map(|index| (cursor + index + 1, cursor + index + 1)),
+        };
+        previous_placed = place.is_some();
+        match place {
+            Some((start, end)) => {
+                cursor = start + region.lines.len();
+                lines.splice(start..end, region.lines);
+            }
+            None => {
+                if region.anchor != Anchor::AfterRegion {
+                    unplaced += 1;
+                }
+                trailing.extend(region.lines);
+            }
+        }
+    }
+    lines.extend(trailing);
+
+    let mut content = lines.join("\n");
+    if generated.ends_with('\n') {
+        content.push('\n');
+    }
+    Spliced { content, unplaced }
+}
+
+/// The regions of `text`, in order
+fn regions(text: &str) -> Vec<Region<'_>> {
+    let mut regions = Vec::new();
+    let mut anchor = Anchor::Start;
+    let mut current: Option<Region> = None;
+
+    for line in text.lines() {
+        if let Some(region) = current.as_mut() {
+            region.lines.push(line);
+            if line.contains(END) {
+                regions.extend(current.take());
+                anchor = Anchor::AfterRegion;
+            }
+        } else if let Some(name) = begin_marker(line) {
+            current = Some(Region { name, anchor, lines: vec![line] });
+        } else if !line.trim().is_empty() {
+            anchor = Anchor::After(line.trim());
+        }
+    }
+    regions.extend(current);
+    regions
+}
+
+/// First and last line of the region called `name` in `lines`
+fn find_region(lines: &[&str], name: &str) -> Option<(usize, usize)> {
+    let start = lines.iter().position(|line| begin_marker(line) == Some(name))?;
+    let end = lines[start..].iter().position(|line| line.contains(END)).map_or(lines.len() - 1, |offset| start + offset);
+    Some((start, end))
+}
+
+/// The name of the region `line` begins, which is empty for an unnamed one
+fn begin_marker(line: &str) -> Option<&str> {
+    let after = &line[line.find(BEGIN)? + BEGIN.len()..];
+    let after = after.trim_start();
+    let length = after.find(|c: char| !c.is_alphanumeric() && c != '_').unwrap_or(after.len());
+    Some(&after[..length])
+}
@@ -0,0 +1,130 @@
+//! Where in the output directory each target is generated.
+//!
+//! A target goes in a directory named after it, `out/<name>`, unless its block says
+//! otherwise with `@output(...)` or `z.toml` does, which lets the targets land in the
+//! places an existing monorepo keeps its projects:
+//!
+//! ```z
+//! rust Api @output(services/api) { ... }
+//! ```
+//!
+//! ```toml
+//! [output]
+//! # Where targets go unless listed below; {kind} and {name} are the target's
+//! pattern = "apps/{name}"
+//!
+//! [output.targets]
+//! Api = "services/api"
+//! ```
+//!
+//! The annotation comes first, then `output.targets`, by name or `kind:name`, then the
+//! pattern. Paths are relative to the output directory, so compiling with `-o .` from
+//! the monorepo's root puts the targets in place. A path leaving the output directory,
+//! or a target's directory inside another's, fails that target. So does syncing into a
+//! directory that isn't empty and wasn't generated into before, unless the sync
+//! overwrites (see [`emit`](crate::emit)), so a typo doesn't write over a project.
+
+use std::collections::BTreeMap;
+use std::path::{Component, Path, PathBuf};
+
+use serde_json::Value;
+use z_semantic::Target;
+
+/// Name of the annotation and of the `z.toml` table placing targets
+pub const OUTPUT: &str = "output";
+
+/// How targets are placed in the output directory, from the `[output]` table of `z.toml`
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Layout {
+    /// Directory of the targets not listed in `by_target`, with `{kind}` and `{name}`
+    pattern: Option<String>,
+    /// Directory of each target listed, by name or `kind:name`
+    by_target: BTreeMap<String, String>,
+}
+
+impl Layout {
+    /// Read the `output` table of a parsed `z.toml`
+    pub fn from_config(config: &Value) -> Result<Self, String> {
+        let mut layout = Self::default();
+        let Some(table) = config.get(OUTPUT) else {
+            return Ok(layout);
+        };
+        let table = table.as_object().ok_or_else(|| format!("'{}' must be a table", OUTPUT))?;
+        for (key, value) in table {
+            match (key.as_str(), value) {
+                ("pattern", Value::String(pattern)) => layout.pattern = Some(pattern.clone()),
+                ("pattern", _) => return Err(format!("'{}.pattern' must be a path, e.g. \"apps/{{name}}\"", OUTPUT)),
+                ("targets", Value::Object(targets)) => {
+                    for (target, dir) in targets {
+                        let dir = dir.as_str()
+                            .ok_or_else(|| format!("'{}.targets.{}' must be a path, e.g. \"services/api\"", OUTPUT, target))?;
+                        layout.by_target.insert(target.clone(), dir.to_string());
+                    }
+                }
+                ("targets", _) => return Err(format!("'{}.targets' must be a table of paths by target name", OUTPUT)),
+                _ => return Err(format!("unknown key '{}.{}' (expected pattern or targets)", OUTPUT, key)),
+            }
+        }
+        Ok(layout)
+    }
+
+    /// The directory of `target`, relative to the output directory
+    pub fn dir(&self, target: &Target) -> Result<PathBuf, String> {
+        let annotation = target.annotations.iter()
+            .find(|annotation| annotation.name == OUTPUT)
+            .map(|annotation| annotation.args.as_deref().unwrap_or_default().trim().trim_matches('"'));
+        let path = annotation
+            .or_else(|| self.by_target.get(&target.qualified_name()).map(String::as_str))
+            .or_else(|| self.by_target.get(&target.name).map(String::as_str))
+            .or(self.pattern.as_deref())
+            .unwrap_or("{name}")
+            .replace("{kind}", &target.kind)
+            .replace("{name}", &target.name);
+        relative(&path).ok_or_else(|| format!(
+            "output directory '{}' of {} must be a path inside the output directory, e.g. services/api",
+            path, target.qualified_name(),
+        ))
+    }
+}
+
+/// The directory of each of `targets`, relative to the output directory, or why it
+/// can't be used: a directory can't be or hold the directory of a target before it
+pub(crate) fn resolve(targets: &[Target], layout: &Layout) -> Vec<Result<PathBuf, String>> {
+    let mut dirs: Vec<Result<PathBuf, String>> = Vec::new();
+    for target in targets {
+        let dir = layout.dir(target).and_then(|dir| {
+            let taken = targets.iter().zip(&dirs)
+                .find(|(_, other)| other.as_ref().is_ok_and(|other| other.starts_with(&dir) || dir.starts_with(other)));
+            match taken {
+                Some((other, _)) => Err(format!(
+                    "output directory '{}' of {} overlaps that of {}; give them separate directories",
+                    dir.display(), target.qualified_name(), other.qualified_name(),
+                )),
+                None => Ok(dir),
+            }
+        });
+        dirs.push(dir);
+    }
+    dirs
+}
+
+/// `to` as seen from `from`, both relative to the same directory
+pub(crate) fn path_between(from: &Path, to: &Path) -> PathBuf {
+    let common = from.components().zip(to.components()).take_while(|(a, b)| a == b).count();
+    let up = from.components().skip(common).map(|_| Component::ParentDir);
+    up.chain(to.components().skip(common)).collect()
+}
+
+/// `path` without `.` parts, if it's relative, stays inside the directory it's relative
+/// to and isn't that directory itself
+fn relative(path: &str) -> Option<PathBuf> {
+    let mut dir = PathBuf::new();
+    for component in Path::new(path.trim()).components() {
+        match component {
+            Component::Normal(part) => dir.push(part),
+            Component::CurDir => {}
+            Component::ParentDir | Component::RootDir | Component::Prefix(_) => return None,
+        }
+    }
+    (!dir.as_os_str().is_empty()).then_some(dir)
+}
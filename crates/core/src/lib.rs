@@ -1,98 +1,996 @@
-use z_parser::parse_source;
-use z_ast::{Element, Node};
-use std::collections::HashMap;
-use std::fs;
-use std::path::Path;
+//! The Z compiler: parses a program, checks it, and generates a project for each of
+//! its targets.
+//!
+//! Programs other than `z-cli` embed it through [`Compiler`]:
+//! `Compiler::new(options).compile_file(path)` compiles a program into its output
+//! directory and returns a [`CompileResult`], reporting [`Progress`] on the way. Nothing
+//! in this crate prints; problems are [`Diagnostic`]s for the caller to show.
+//!
+//! # Stability
+//!
+//! These items are the public API, changed only in a new major version:
+//!
+//! - [`Compiler`], [`CompileOptions`], [`CompileResult`], [`Progress`] and [`ProgressListener`];
+//! - [`Diagnostic`], [`Severity`] and the codes in [`diagnostic`];
+//! - [`CompileReport`] and [`TargetReport`], whose JSON form is `doc/compile-report.md`;
+//! - [`compile`], [`compile_project`], [`lower`] and [`load_program`];
+//! - the extension points: [`TargetCompiler`], [`Pass`], [`CompileObserver`] and [`FileSink`].
+//!
+//! The other public modules exist for `z-cli` and the language server, and may change
+//! in any release.
 
+use z_parser::parse_source_with;
+use z_semantic::{Namespace, Section, Target};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::Sender;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+mod aliases;
+mod archive;
+pub mod assets;
+pub mod clean;
+pub mod codemod;
+mod compiler;
 mod compilers;
-use compilers::{get_compiler, TargetCompiler};
+mod contract;
+pub mod crash;
+pub mod deploy;
+pub mod dependencies;
+pub mod determinism;
+pub mod devenv;
+pub mod diagnostic;
+mod env_vars;
+mod environment;
+mod diff;
+mod emit;
+pub mod formatter;
+pub mod gallery;
+mod graph;
+mod header;
+pub mod hooks;
+pub mod hash;
+pub mod i18n;
+mod identifiers;
+pub mod incremental;
+pub mod inspect;
+mod keep;
+pub mod layout;
+pub mod members;
+mod links;
+mod meta;
+pub mod modules;
+pub mod naming;
+pub mod observer;
+pub mod pipeline;
+pub mod plugin;
+pub mod preview;
+pub mod publish;
+pub mod registry;
+pub mod remote_cache;
+pub mod report;
+mod resolve;
+pub mod runtime;
+pub mod sandbox;
+pub mod settings;
+pub mod schema;
+pub mod sink;
+mod store;
+pub mod template;
+pub mod style;
+pub mod structure;
+pub mod symbols;
+pub mod target_options;
+pub mod telemetry;
+pub mod templates;
+pub mod testing;
+pub mod toml;
+pub mod user_cache;
+pub mod version;
+pub mod watch;
+mod warnings;
+
+use compilers::get_compiler;
+pub use compiler::Compiler;
+pub use compilers::{Capabilities, Outputs, TargetCompiler};
+pub use deploy::{deploy_steps, DeployRequest, DeployStep};
+pub use dependencies::DependencyOverrides;
+pub use devenv::DevEnv;
+pub use diagnostic::{Diagnostic, Severity};
+pub use environment::Environments;
+pub use hooks::Hooks;
+pub use i18n::Locale;
+pub use layout::Layout;
+pub use modules::Project;
+pub use naming::Naming;
+pub use observer::{CompileObserver, Observers};
+pub use pipeline::{Pass, PassContext, Pipeline};
+pub use plugin::Plugins;
+pub use publish::{publish_output, PublishOptions, Published};
+pub use registry::RegistryLayers;
+pub use templates::Templates;
+pub use remote_cache::{CacheMode, RemoteCache};
+pub use report::{CompileReport, TargetReport};
+pub use runtime::Runtime;
+pub use user_cache::UserCache;
+pub use sink::{ArchiveFormat, ArchiveSink, FileSink, FsSink, MemorySink};
+use report::{FileEntry, Status};
+pub use style::CodeStyle;
+pub use target_options::{CompilerOptions, Prerequisite, TargetOptions};
+pub use z_parser::Limits;
+pub use version::{Bump, Version};
+
+/// Settings that shape a compilation without being part of the source program
+#[derive(Debug, Clone)]
+pub struct CompileOptions {
+    /// Formatting applied to every generated source file
+    pub style: CodeStyle,
+    /// Development environment files to emit with each generated project
+    pub dev_env: Vec<DevEnv>,
+    /// Shared cache of generated targets to restore from (and possibly upload to)
+    pub remote_cache: Option<RemoteCache>,
+    /// Cache of generated targets shared by the user's projects, usually in `~/.cache/z`
+    pub user_cache: Option<UserCache>,
+    /// Directory of the source file, for paths in the program such as screenshots
+    pub source_dir: Option<PathBuf>,
+    /// Rewrites applied to the parsed program before it's lowered
+    pub pipeline: Pipeline,
+    /// Hooks called before parsing, after checking, and around generating each target
+    pub observers: Observers,
+    /// App version written into every generated manifest, usually from the `VERSION` file
+    pub version: Option<Version>,
+    /// Bounds on the size and shape of the source
+    pub limits: Limits,
+    /// Package versions chosen in `z.toml`, replacing the ones the emitters pin
+    pub dependencies: DependencyOverrides,
+    /// Regenerate every target, even those whose inputs haven't changed since the last compile
+    pub force: bool,
+    /// Replace generated files that were edited since, rather than keep them
+    pub overwrite: bool,
+    /// Report blocks nothing generates from (unknown target types, and targets or sections
+    /// their compiler ignores) as errors instead of warnings, so the build fails on them
+    pub strict: bool,
+    /// Report every warning not silenced by `@allow(...)` as an error, so the build fails
+    /// on it (see [`warnings`])
+    pub deny_warnings: bool,
+    /// Who to tell about [`Progress`] as the compile goes, e.g. for a live view of the build
+    pub progress: Option<ProgressListener>,
+    /// Work out what each target would change in its output directory, and report it in
+    /// [`TargetReport::planned`], without writing anything there
+    pub dry_run: bool,
+    /// Stop once the program and each target were checked against the registry, generating
+    /// nothing, so only the diagnostics of the result mean anything (see [`check_project`])
+    pub check_only: bool,
+    /// The targets to generate, by name or `kind:name`; every one when empty. The others
+    /// are still checked, and give the targets using them what they would generate.
+    pub targets: Vec<String>,
+    /// Targets not to generate, by name or `kind:name`, even when [`targets`](Self::targets)
+    /// names them; they're checked like the targets left out of it
+    pub excluded: Vec<String>,
+    /// The [environment](Environments) to compile for, e.g. `prod`, whose values replace
+    /// the program's top-level constants
+    pub environment: Option<String>,
+    /// Values of each environment from `z.toml`
+    pub environments: Environments,
+    /// Target types compiled by external programs, from `z.toml`
+    pub plugins: Plugins,
+    /// Registry files changing the built-in registry
+    pub registry: RegistryLayers,
+    /// Templates of generated files, with the project's and user's replacements
+    pub templates: Templates,
+    /// Compiler options for every target of a type, from `z.toml`
+    pub target_options: TargetOptions,
+    /// Commands to run once every target of a type is generated, from `z.toml`
+    pub hooks: Hooks,
+    /// Run the [hooks](hooks) of the targets generated
+    pub run_hooks: bool,
+    /// Where targets go in the output directory, from `z.toml`
+    pub layout: Layout,
+    /// Pack the generated projects into one file of this format, returned in
+    /// [`CompileResult::archive`], instead of writing them into the output directory
+    pub archive: Option<ArchiveFormat>,
+    /// Keep the generated projects in [`CompileResult::files`] instead of writing them into
+    /// the output directory, e.g. to print them
+    pub in_memory: bool,
+}
 
-// Load the standard library registry from shared location
-fn load_registry() -> serde_json::Value {
-    let registry_json = include_str!("../../../shared/registry.json");
-    serde_json::from_str(registry_json).expect("Invalid registry.json")
+impl Default for CompileOptions {
+    fn default() -> Self {
+        Self {
+            style: CodeStyle::default(),
+            dev_env: Vec::new(),
+            remote_cache: None,
+            user_cache: None,
+            source_dir: None,
+            pipeline: Pipeline::standard(),
+            observers: Observers::default(),
+            version: None,
+            limits: Limits::default(),
+            dependencies: DependencyOverrides::default(),
+            force: false,
+            overwrite: false,
+            strict: false,
+            deny_warnings: false,
+            progress: None,
+            dry_run: false,
+            check_only: false,
+            targets: Vec::new(),
+            excluded: Vec::new(),
+            environment: None,
+            environments: Environments::default(),
+            plugins: Plugins::default(),
+            registry: RegistryLayers::default(),
+            templates: Templates::default(),
+            target_options: TargetOptions::default(),
+            hooks: Hooks::default(),
+            run_hooks: true,
+            layout: Layout::default(),
+            archive: None,
+            in_memory: false,
+        }
+    }
 }
 
-pub fn compile(source: &str, output_base_dir: &std::path::Path) {
-    let registry = load_registry();
+/// A step of [`compile`], sent to [`CompileOptions::progress`] as it happens
+#[derive(Debug, Clone)]
+pub enum Progress {
+    /// The source was checked: the targets it declares, in source order, and the
+    /// problems found in it
+    Checked { targets: Vec<String>, diagnostics: Vec<Diagnostic> },
+    /// Generating the target `kind:name` started
+    Started(String),
+    /// The target `kind:name` is done: how it went, unless it couldn't be compiled at
+    /// all, and its diagnostics
+    Finished { target: String, report: Option<Box<TargetReport>>, diagnostics: Vec<Diagnostic> },
+}
 
-    // Parse source to get top-level elements
-    match parse_source(source) {
-        Ok(ast) => {
-            let targets = detect_targets(&ast);
+/// Something told each [`Progress`] of a compile, on the thread compiling
+#[derive(Clone)]
+pub struct ProgressListener(Arc<dyn Fn(Progress) + Send + Sync>);
 
-            if targets.is_empty() {
-                eprintln!("No target blocks found in entry file.");
-                return;
-            }
+impl ProgressListener {
+    pub fn new(listener: impl Fn(Progress) + Send + Sync + 'static) -> Self {
+        Self(Arc::new(listener))
+    }
+}
+
+/// Sends each step down the channel, e.g. to a thread drawing the build
+impl From<Sender<Progress>> for ProgressListener {
+    fn from(sender: Sender<Progress>) -> Self {
+        // Nobody listening anymore isn't a reason to stop compiling
+        Self::new(move |progress| {
+            let _ = sender.send(progress);
+        })
+    }
+}
+
+impl std::fmt::Debug for ProgressListener {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("ProgressListener")
+    }
+}
+
+impl CompileOptions {
+    fn notify(&self, progress: Progress) {
+        if let Some(listener) = &self.progress {
+            (listener.0)(progress);
+        }
+    }
+}
+
+/// The built-in registry with the registry files and plugins of the options layered over it
+pub fn load_registry(options: &CompileOptions) -> serde_json::Value {
+    let mut registry = registry::built_in();
+    options.registry.apply(&mut registry);
+    options.plugins.extend(&mut registry);
+    registry
+}
+
+/// Whether a compiler built into z generates targets of type `kind`
+pub fn is_built_in(kind: &str) -> bool {
+    get_compiler(kind, &CodeStyle::default(), &Templates::default()).is_some()
+}
+
+/// The compiler for targets of type `kind`: a built-in one, or else the plugin's
+pub fn compiler_for(kind: &str, program: &z_ast::Element, options: &CompileOptions) -> Option<Box<dyn TargetCompiler>> {
+    get_compiler(kind, &options.style, &options.templates).or_else(|| {
+        let plugin = options.plugins.get(kind)?;
+        Some(Box::new(plugin::PluginCompiler::new(plugin, &options.plugins, program, &options.style)))
+    })
+}
+
+/// The target's own sections its compiler generates nothing from, of those `allowed`
+/// in it, and whether it ignores the lines directly inside the target. Sections shared
+/// by a workspace, and those core reads itself, aren't ignored.
+fn ignored<'a>(
+    target: &'a Target,
+    targets: &[Target],
+    allowed: &[&str],
+    compiler: &dyn TargetCompiler,
+    target_options: &CompilerOptions,
+) -> (Vec<&'a Section>, bool) {
+    let Some(capabilities) = compiler.capabilities(target_options) else {
+        return (Vec::new(), false);
+    };
+    let read_by_core = contract::sections(target, targets);
+    let sections = target.sections.iter()
+        .filter(|section| target.span.start <= section.span.start && section.span.end <= target.span.end)
+        .filter(|section| allowed.contains(&section.namespace.as_str()))
+        .filter(|section| !matches!(section.namespace, Namespace::Store | Namespace::Deploy))
+        .filter(|section| !read_by_core.contains(&section.namespace) && !capabilities.sections.contains(&section.namespace))
+        .collect();
+    (sections, !capabilities.declarations && !target.declarations.is_empty())
+}
+
+/// Cache key for one target: everything its generated files depend on
+fn cache_key(target_fingerprint: &str, target_type: &str, app_name: &str, options: &CompileOptions) -> String {
+    let inputs = format!(
+        "{}\n{}\n{}\n{}\n{}\n{:?}\n{:?}\n{:?}\n{:?}\n{:?}\n{:?}\n{:?}\n{:?}\n{:?}\n{:?}\n{:?}\n{:?}",
+        env!("CARGO_PKG_VERSION"),
+        target_type,
+        app_name,
+        target_fingerprint,
+        assets::fingerprint(),
+        options.style,
+        options.dev_env,
+        options.pipeline,
+        options.observers,
+        options.version,
+        options.environment,
+        options.dependencies.for_kind(target_type),
+        options.plugins.get(target_type),
+        options.registry,
+        options.templates,
+        options.target_options.for_kind(target_type),
+        options.hooks.for_kind(target_type),
+    );
+    hash::sha256_hex(inputs.as_bytes())
+}
+
+/// Outcome of [`compile`]
+#[derive(Debug, Clone, Default)]
+pub struct CompileResult {
+    /// `kind:name` of every target in the program, in source order
+    pub targets: Vec<String>,
+    /// Errors and warnings, in the order they were found
+    pub diagnostics: Vec<Diagnostic>,
+    /// One entry per target that was generated or restored, whether or not it succeeded
+    pub artifacts: Vec<TargetReport>,
+    /// The source files read, from [`compile_project`]; empty for [`compile`]
+    pub inputs: Vec<FileEntry>,
+    /// The generated projects packed into one file, when [`CompileOptions::archive`] asks for it
+    pub archive: Option<Vec<u8>>,
+    /// The generated files by path under the output directory, when
+    /// [`CompileOptions::in_memory`] asks for them
+    pub files: std::collections::BTreeMap<PathBuf, Vec<u8>>,
+    pub duration: Duration,
+}
+
+impl CompileResult {
+    pub fn has_errors(&self) -> bool {
+        self.diagnostics.iter().any(Diagnostic::is_error)
+    }
+
+    /// The build report for this result, with the files read as its inputs
+    pub fn report(&self) -> CompileReport {
+        CompileReport {
+            status: if self.has_errors() { Status::Failed } else { Status::Success },
+            duration_ms: self.duration.as_millis() as u64,
+            inputs: self.inputs.clone(),
+            targets: self.artifacts.clone(),
+            diagnostics: self.diagnostics.clone(),
+            ..CompileReport::default()
+        }
+    }
+}
+
+/// Compile every target in `source` into its own directory under `output_base_dir`.
+///
+/// Nothing is printed: problems come back as diagnostics, for the caller to show.
+/// `source` can't import other files; see [`compile_project`] for that.
+pub fn compile(source: &str, output_base_dir: &std::path::Path, options: &CompileOptions) -> CompileResult {
+    compile_modules(source, output_base_dir, options, &Project::default(), &load_registry(options))
+}
+
+/// Compile the program in `entry` and the files it imports (see [`modules`]), like
+/// [`compile`] does a single source. Diagnostics in imported files name the file.
+pub fn compile_project(entry: &std::path::Path, output_base_dir: &std::path::Path, options: &CompileOptions) -> CompileResult {
+    compile_entry(entry, output_base_dir, options, &mut modules::Sources::new(), &load_registry(options))
+}
+
+/// [`compile_project`] taking the files kept in `sources` from there, and keeping those it
+/// reads there, for compiling the same program again: watch mode refreshes them with
+/// [`Sources::refresh`](modules::Sources::refresh) so only what changed is parsed again
+pub fn compile_project_with(
+    entry: &std::path::Path,
+    output_base_dir: &std::path::Path,
+    options: &CompileOptions,
+    sources: &mut modules::Sources,
+) -> CompileResult {
+    compile_entry(entry, output_base_dir, options, sources, &load_registry(options))
+}
+
+/// Parse and check the program in `entry` and its targets, as [`compile_project`] does
+/// before generating them, without generating anything or touching an output directory.
+/// Targets have no artifacts; the diagnostics are those a compile would start with.
+pub fn check_project(entry: &std::path::Path, options: &CompileOptions) -> CompileResult {
+    let options = CompileOptions { check_only: true, dry_run: false, archive: None, in_memory: false, run_hooks: false, ..options.clone() };
+    compile_project(entry, std::path::Path::new(""), &options)
+}
+
+/// [`compile_project`] with the files in `sources` read already, and `registry` loaded
+/// for `options`
+pub(crate) fn compile_entry(
+    entry: &std::path::Path,
+    output_base_dir: &std::path::Path,
+    options: &CompileOptions,
+    sources: &mut modules::Sources,
+    registry: &serde_json::Value,
+) -> CompileResult {
+    let started = Instant::now();
+    let project = match Project::load_with(entry, &options.limits, sources) {
+        Ok(project) => project,
+        Err(diagnostics) => {
+            options.notify(Progress::Checked { targets: Vec::new(), diagnostics: diagnostics.clone() });
+            return CompileResult { diagnostics, duration: started.elapsed(), ..CompileResult::default() };
+        }
+    };
+    // Each file was held to the size limit as it was read; together they may be over it
+    let limits = Limits { max_bytes: options.limits.max_bytes.max(project.source.len()), ..options.limits };
+    let options = CompileOptions { limits, ..options.clone() };
+    let mut result = compile_modules(&project.source, output_base_dir, &options, &project, registry);
+    result.inputs = project.modules.into_iter().map(|module| module.input).collect();
+    result.duration = started.elapsed();
+    result
+}
+
+/// [`compile`] for `source`, which is `project`'s files laid out together
+fn compile_modules(
+    source: &str,
+    output_base_dir: &std::path::Path,
+    options: &CompileOptions,
+    project: &Project,
+    registry: &serde_json::Value,
+) -> CompileResult {
+    let started = Instant::now();
+    let mut result = CompileResult { diagnostics: options.observers.pre_parse(source), ..CompileResult::default() };
+
+    if result.has_errors() {
+        project.locate(&mut result.diagnostics);
+        options.notify(Progress::Checked { targets: Vec::new(), diagnostics: result.diagnostics.clone() });
+        result.duration = started.elapsed();
+        return result;
+    }
+
+    let (ast, renamed) = match parse(source, options, registry) {
+        Ok(parsed) => parsed,
+        Err(e) => {
+            result.diagnostics.push(Diagnostic::error(diagnostic::PARSE, tr!("parse-error", error = e)));
+            options.notify(Progress::Checked { targets: Vec::new(), diagnostics: result.diagnostics.clone() });
+            result.duration = started.elapsed();
+            return result;
+        }
+    };
+
+    let allowed = warnings::Allowed::collect(&ast, source);
+    let (program, compiler_options, mut diagnostics) = check(source, &ast, &renamed, options, registry);
+    diagnostics.extend(allowed.unknown());
+    diagnostics.extend(options.observers.post_validate(&program, source));
+    diagnostics.sort_by_key(|diagnostic| diagnostic.span.map(|span| span.start));
+    result.diagnostics.extend(diagnostics);
+    if program.targets.is_empty() {
+        result.diagnostics.push(Diagnostic::warning(diagnostic::NO_TARGETS, tr!("no-targets")).with_help(tr!("no-targets-help")));
+    }
+    // Spans are into `source` until located in the project's files
+    allowed.settle(&mut result.diagnostics, 0, None, options);
+    project.locate(&mut result.diagnostics);
+    result.targets = program.targets.iter().map(Target::qualified_name).collect();
+    for name in options.targets.iter().filter(|name| !program.targets.iter().any(|target| selects(name, target))) {
+        result.diagnostics.push(Diagnostic::error(diagnostic::INVALID, tr!("selected-target-unknown", name = name, targets = result.targets.join(", "))));
+    }
+    for name in options.excluded.iter().filter(|name| !program.targets.iter().any(|target| selects(name, target))) {
+        result.diagnostics.push(Diagnostic::error(diagnostic::INVALID, tr!("excluded-target-unknown", name = name, targets = result.targets.join(", "))));
+    }
+    options.notify(Progress::Checked { targets: result.targets.clone(), diagnostics: result.diagnostics.clone() });
 
-            println!("Detected targets: {}", targets.join(", "));
+    // A dry run, or one packing the targets into an archive or keeping them in memory,
+    // generates in memory, so nothing is written to disk
+    let archive = options.archive.map(|format| ArchiveSink::new(format, ""));
+    let memory = options.in_memory.then(MemorySink::new);
+    let packed: Option<&dyn FileSink> = match (&archive, &memory) {
+        (Some(archive), _) => Some(archive),
+        (None, memory) => memory.as_ref().map(|memory| memory as &dyn FileSink),
+    };
+    let writes_output = !options.dry_run && !options.check_only && packed.is_none();
 
-                        // Validate and compile each target
-            for target_with_name in &targets {
-                // Parse target:name format
-                let parts: Vec<&str> = target_with_name.split(':').collect();
-                if parts.len() != 2 {
-                    eprintln!("  ❌ Invalid target format: {} (expected target:name)", target_with_name);
-                    continue;
+    // Validate and compile each target, after the targets it uses
+    let graph = graph::Graph::build(&program.targets).0;
+    let output_dirs = layout::resolve(&program.targets, &options.layout);
+    let dir_of = |index: usize| output_dirs[index].clone().unwrap_or_else(|_| PathBuf::from(&program.targets[index].name));
+    // What the targets generated so far give the targets that use them
+    let mut built: Vec<Option<Outputs>> = vec![None; program.targets.len()];
+    for index in graph.order(&program.targets).0 {
+        let target = &program.targets[index];
+        let target_with_name = target.qualified_name();
+        let target_type = target.kind.as_str();
+        let app_name = target.name.as_str();
+        let first_diagnostic = result.diagnostics.len();
+        options.notify(Progress::Started(target_with_name.clone()));
+
+        // The targets it uses, and the first of them that wasn't generated, if any
+        let mut uses = Vec::new();
+        let mut blocked = None;
+        for &used in graph.uses(index) {
+            let prerequisite = &program.targets[used];
+            match &built[used] {
+                Some(outputs) => uses.push(Prerequisite {
+                    target: prerequisite.qualified_name(),
+                    dir: layout::path_between(&dir_of(index), &dir_of(used)),
+                    outputs: outputs.clone(),
+                }),
+                None => {
+                    blocked.get_or_insert_with(|| prerequisite.qualified_name());
                 }
+            }
+        }
+        let target_options = &compiler_options[index].clone().after(uses);
 
-                let target_type = parts[0];
-                let app_name = parts[1];
+        let Some(target_info) = registry["targets"][target_type].as_object() else {
+            let known: Vec<&str> = registry["targets"].as_object()
+                .map(|targets| targets.keys().map(String::as_str).filter(|kind| *kind != "workspace").collect())
+                .unwrap_or_default();
+            result.diagnostics.push(
+                Diagnostic::warning(diagnostic::UNKNOWN_TARGET, tr!("unknown-target", target = target_type))
+                    .with_span(target.span, source)
+                    .with_help(tr!("unknown-target-help", targets = known.join(", ")))
+                    .with_target(target_with_name.clone()),
+            );
+            allowed.settle(&mut result.diagnostics, first_diagnostic, Some(target.span), options);
+            project.locate(&mut result.diagnostics[first_diagnostic..]);
+            let diagnostics = result.diagnostics[first_diagnostic..].to_vec();
+            options.notify(Progress::Finished { target: target_with_name, report: None, diagnostics });
+            continue;
+        };
+        // Get the appropriate compiler for this target type
+        let Some(compiler) = compiler_for(target_type, &ast, options) else {
+            result.diagnostics.push(
+                Diagnostic::error(diagnostic::NO_COMPILER, tr!("no-compiler", target = target_type))
+                    .with_span(target.span, source)
+                    .with_target(target_with_name.clone()),
+            );
+            allowed.settle(&mut result.diagnostics, first_diagnostic, Some(target.span), options);
+            project.locate(&mut result.diagnostics[first_diagnostic..]);
+            let diagnostics = result.diagnostics[first_diagnostic..].to_vec();
+            options.notify(Progress::Finished { target: target_with_name, report: None, diagnostics });
+            continue;
+        };
+        let output_dir = match &output_dirs[index] {
+            Ok(dir) => output_base_dir.join(dir),
+            Err(e) => {
+                result.diagnostics.push(
+                    Diagnostic::error(diagnostic::INVALID, e.clone())
+                        .with_span(target.span, source)
+                        .with_target(target_with_name.clone()),
+                );
+                allowed.settle(&mut result.diagnostics, first_diagnostic, Some(target.span), options);
+                project.locate(&mut result.diagnostics[first_diagnostic..]);
+                let diagnostics = result.diagnostics[first_diagnostic..].to_vec();
+                options.notify(Progress::Finished { target: target_with_name, report: None, diagnostics });
+                continue;
+            }
+        };
+        // Sections the target's compiler doesn't know were reported by the schema check
+        let sections: Vec<&str> = target_info.get("allowedChildren")
+            .and_then(serde_json::Value::as_array)
+            .map(|children| children.iter().filter_map(serde_json::Value::as_str).collect())
+            .unwrap_or_default();
+        let recognized = target.sections.iter().any(|section| sections.contains(&section.namespace.as_str()));
+        if !recognized && target.declarations.is_empty() {
+            result.diagnostics.push(
+                Diagnostic::warning(diagnostic::EMPTY_TARGET, tr!("empty-target", target = target_with_name))
+                    .with_span(target.span, source)
+                    .with_help(tr!("empty-target-help", sections = sections.join(", ")))
+                    .with_target(target_with_name.clone()),
+            );
+        }
 
-                if let Some(target_info) = registry["targets"][target_type].as_object() {
-                    println!("  {} {} - {}", target_type, app_name, target_info["description"].as_str().unwrap_or(""));
+        // What the compiler would silently drop
+        let (ignored_sections, ignored_declarations) = ignored(target, &program.targets, &sections, &*compiler, target_options);
+        let supported = || compiler.capabilities(target_options)
+            .map(|capabilities| capabilities.sections.iter().map(Namespace::to_string).collect::<Vec<_>>().join(", "))
+            .unwrap_or_default();
+        for section in ignored_sections {
+            result.diagnostics.push(
+                Diagnostic::warning(diagnostic::IGNORED, tr!("section-ignored", section = section.namespace, target = target_with_name))
+                    .with_span(section.span, source)
+                    .with_help(tr!("section-ignored-help", kind = target_type, sections = supported()))
+                    .with_target(target_with_name.clone()),
+            );
+        }
+        if ignored_declarations {
+            result.diagnostics.push(
+                Diagnostic::warning(diagnostic::IGNORED, tr!("declarations-ignored", target = target_with_name))
+                    .with_span(target.declarations[0].span, source)
+                    .with_help(tr!("declarations-ignored-help", sections = supported()))
+                    .with_target(target_with_name.clone()),
+            );
+        }
 
-                    // Get the appropriate compiler for this target type
-                    if let Some(compiler) = get_compiler(target_type) {
-                        match compile_target(&ast, &*compiler, target_type, app_name, output_base_dir) {
-                            Ok(_) => println!("  ✅ {} {} compilation successful", target_type, app_name),
-                            Err(e) => eprintln!("  ❌ {} {} compilation failed: {}", target_type, app_name, e),
-                        }
-                    } else {
-                        eprintln!("  ❌ No compiler available for target: {}", target_type);
-                    }
+        let selected = (options.targets.is_empty() || options.targets.iter().any(|name| selects(name, target)))
+            && !options.excluded.iter().any(|name| selects(name, target));
+        if options.check_only || !selected {
+            built[index] = Some(compiler.outputs(target_options));
+            allowed.settle(&mut result.diagnostics, first_diagnostic, Some(target.span), options);
+            project.locate(&mut result.diagnostics[first_diagnostic..]);
+            let diagnostics = result.diagnostics[first_diagnostic..].to_vec();
+            options.notify(Progress::Finished { target: target_with_name, report: None, diagnostics });
+            continue;
+        }
+
+        let target_started = Instant::now();
+        let mut diagnostics = Vec::new();
+        // The types shared with the other side of the wire depend on the targets there too
+        let mut fingerprint = format!("{}\n{}", hash::target_fingerprint(&ast, &target_with_name), contract::fingerprint(target, &program.targets));
+        // So do the projects of the targets it uses, and of those using it
+        if !target_options.uses().is_empty() || !target_options.used_by().is_empty() {
+            fingerprint.push_str(&format!("\n{:?} {:?}", target_options.uses(), target_options.used_by()));
+        }
+        // And where it goes, when that's not the usual place
+        if dir_of(index) != Path::new(app_name) {
+            fingerprint.push_str(&format!("\n{}", dir_of(index).display()));
+        }
+        let key = cache_key(&fingerprint, target_type, app_name, options);
+        let unchanged = blocked.is_none() && writes_output && !options.force && incremental::is_unchanged(output_base_dir, &target_with_name, &key, &output_dir);
+        let cache = options.remote_cache.as_ref().filter(|_| !unchanged && blocked.is_none());
+        // Generated off to the side, then synced into `output_dir` (see `emit`): in a
+        // staging directory when it's written there, in memory otherwise
+        let staging = writes_output.then(|| emit::staging_dir(output_base_dir, &target_with_name));
+        let (on_disk, in_memory) = (staging.as_ref().map(FsSink::new), MemorySink::new());
+        let staged: &dyn FileSink = match &on_disk {
+            Some(on_disk) => on_disk,
+            None => &in_memory,
+        };
+        let prepared = match &staging {
+            Some(staging) if !unchanged => emit::prepare(staging),
+            _ => Ok(()),
+        };
+
+        let user_cache = options.user_cache.as_ref().filter(|_| !unchanged && blocked.is_none());
+        let user_key = user_cache.map(|_| user_cache::key(&key, target, options.source_dir.as_deref())).unwrap_or_default();
+
+        // A cache failure never fails the build; it just means generating locally
+        let restored_locally = user_cache.filter(|_| prepared.is_ok()).is_some_and(|cache| match cache.restore(&user_key, staged) {
+            Ok(hit) => hit,
+            Err(e) => {
+                diagnostics.push(Diagnostic::warning(diagnostic::CACHE, tr!("user-cache-unavailable", path = cache.dir().display(), error = e)));
+                false
+            }
+        });
+        let restored = restored_locally || cache.filter(|_| prepared.is_ok()).is_some_and(|cache| match cache.restore(&key, staged) {
+            Ok(hit) => hit,
+            Err(e) => {
+                diagnostics.push(Diagnostic::warning(diagnostic::CACHE, tr!("cache-unavailable", error = e)));
+                false
+            }
+        });
+
+        // Versions outside the known-compatible ranges fail the target unless forced
+        let dependencies = options.dependencies.check(target_type, &registry["targets"][target_type]);
+        for warning in dependencies.iter().flatten() {
+            diagnostics.push(Diagnostic::warning(diagnostic::DEPENDENCY, warning.clone()));
+        }
+
+        let mut planned = Vec::new();
+        let mut panicked = false;
+        let mut hook_runs = Vec::new();
+        let mut hook_failed = false;
+        let provenance = header::Provenance { source: project.file_at(target.span.start), target: &target_with_name };
+        let generated = if let Some(prerequisite) = &blocked {
+            Err(tr!("target-blocked", target = target_with_name, prerequisite = prerequisite))
+        } else if let Err(e) = dependencies {
+            Err(e)
+        } else if unchanged {
+            Ok(emit::Synced::default())
+        } else {
+            prepared
+                .and_then(|_| options.observers.pre_emit(target, &output_dir))
+                .and_then(|_| if restored {
+                    Ok(())
                 } else {
-                    eprintln!("  {} - Unknown target type (not in registry)", target_type);
+                    // A compiler that panics fails its target, not the build
+                    crash::isolate(|| generate(target, &program.targets, &*compiler, target_options, staged, options, registry))
+                        .unwrap_or_else(|panic| {
+                            panicked = true;
+                            Err(panic)
+                        })
+                })
+                .and_then(|_| options.observers.post_emit(target, staged))
+                .and_then(|_| header::stamp(staged, &provenance))
+                .and_then(|_| if options.dry_run {
+                    emit::plan(staged, &output_dir, options.overwrite).map(|plan| {
+                        planned = emit::changes(&plan, staged, output_base_dir);
+                        plan.synced
+                    })
+                } else if let Some(packed) = packed {
+                    staged.copy_to(&sink::Within { dir: &dir_of(index), sink: packed }).map(|_| emit::Synced::default())
+                } else {
+                    emit::sync(staged, &output_dir, options.overwrite)
+                })
+                .and_then(|synced| {
+                    let commands = hooks::commands(target, &options.hooks);
+                    if !writes_output || !options.run_hooks || commands.is_empty() {
+                        return Ok(synced);
+                    }
+                    let before = emit::generated(&output_dir);
+                    let ran = hooks::run(&commands, &output_dir, options, &mut hook_runs);
+                    hook_failed = ran.is_err();
+                    // Even after a hook fails, what the ones before it formatted is kept
+                    emit::adopt(&output_dir, &before, &provenance).and(ran).map(|_| synced)
+                })
+        };
+        let status = match generated {
+            Ok(synced) => {
+                for path in synced.kept {
+                    diagnostics.push(Diagnostic::warning(diagnostic::ORPHAN, tr!("orphan-kept", path = path.display())));
+                }
+                for path in synced.unplaced {
+                    diagnostics.push(Diagnostic::warning(diagnostic::KEEP_UNPLACED, tr!("keep-unplaced", path = path.display())));
+                }
+                for path in synced.edited {
+                    diagnostics.push(Diagnostic::warning(diagnostic::EDITED, tr!("file-edited", path = path.display())).with_help(tr!("file-edited-help")));
                 }
+                if let Some(cache) = cache.filter(|cache| !restored && !options.dry_run && cache.mode == CacheMode::ReadWrite) {
+                    if let Err(e) = cache.store(&key, staged) {
+                        diagnostics.push(Diagnostic::warning(diagnostic::CACHE, tr!("cache-unavailable", error = e)));
+                    }
+                }
+                if let Some(cache) = user_cache.filter(|_| !restored_locally && !options.dry_run) {
+                    if let Err(e) = cache.store(&user_key, staged) {
+                        diagnostics.push(Diagnostic::warning(diagnostic::CACHE, tr!("user-cache-unavailable", path = cache.dir().display(), error = e)));
+                    }
+                }
+                // After a dry run, the output is still as it was
+                if writes_output {
+                    if let Err(e) = incremental::record(output_base_dir, &target_with_name, &key) {
+                        diagnostics.push(Diagnostic::warning(diagnostic::CACHE, e));
+                    }
+                }
+                built[index] = Some(compiler.outputs(target_options));
+                Status::Success
             }
+            Err(e) => {
+                if writes_output {
+                    incremental::forget(output_base_dir, &target_with_name);
+                }
+                diagnostics.push(if panicked {
+                    Diagnostic::error(diagnostic::PANIC, tr!("compiler-panicked", target = target_type, name = app_name, error = e))
+                        .with_help(tr!("compiler-panicked-help", compiler = compiler.target_name()))
+                } else if blocked.is_some() {
+                    Diagnostic::error(diagnostic::BLOCKED, e)
+                } else if hook_failed && hooks::offline(&hook_runs, options) {
+                    Diagnostic::error(diagnostic::HOOK_FAILED, e).with_help(tr!("hook-offline-help"))
+                } else if hook_failed {
+                    Diagnostic::error(diagnostic::HOOK_FAILED, e)
+                } else {
+                    Diagnostic::error(diagnostic::TARGET_FAILED, tr!("compile-failed", target = target_type, name = app_name, error = e))
+                });
+                Status::Failed
+            }
+        };
+        result.diagnostics.extend(diagnostics.into_iter().map(|diagnostic| diagnostic.with_target(target_with_name.clone())));
+        // A target packed into an archive is where it is in the archive, with the files packed
+        let mut artifact = match packed {
+            Some(_) => TargetReport::of_files(target_type, app_name, dir_of(index), staged, status, target_started.elapsed()),
+            None => TargetReport::new(target_type, app_name, output_dir, status, target_started.elapsed()),
+        };
+        if let Some(staging) = &staging {
+            emit::discard(staging);
         }
-        Err(e) => {
-            eprintln!("Parse error: {}", e);
-        }
+        artifact.cached = restored;
+        artifact.unchanged = unchanged;
+        artifact.planned = planned;
+        artifact.hooks = hook_runs;
+        artifact.description = target_info["description"].as_str().unwrap_or_default().to_string();
+        artifact.compiler = compiler.target_name().to_string();
+        allowed.settle(&mut result.diagnostics, first_diagnostic, Some(target.span), options);
+        project.locate(&mut result.diagnostics[first_diagnostic..]);
+        let diagnostics = result.diagnostics[first_diagnostic..].to_vec();
+        options.notify(Progress::Finished { target: target_with_name, report: Some(Box::new(artifact.clone())), diagnostics });
+        result.artifacts.push(artifact);
     }
+    result.archive = archive.map(ArchiveSink::finish);
+    result.files = memory.map(MemorySink::into_files).unwrap_or_default();
+
+    result.duration = started.elapsed();
+    result
+}
+
+/// Whether `name`, as given to [`CompileOptions::targets`], is `target`
+pub(crate) fn selects(name: &str, target: &Target) -> bool {
+    target.name == name || target.qualified_name() == name
 }
 
-fn compile_target(ast: &Element, compiler: &dyn TargetCompiler, _target_type: &str, app_name: &str, output_base_dir: &std::path::Path) -> Result<(), String> {
-    // Create app-specific output directory
-    let output_dir = output_base_dir.join(app_name);
-    fs::create_dir_all(&output_dir)
-        .map_err(|e| format!("Failed to create output directory {}: {}", output_dir.display(), e))?;
+/// The typed model of `source`, for commands that act on targets without compiling them.
+/// Fails if the program has any error, with one `line N: message` per line.
+pub fn load_program(source: &str, options: &CompileOptions) -> Result<z_semantic::Program, String> {
+    let registry = load_registry(options);
+    let (ast, _) = parse(source, options, &registry)?;
+    let (mut program, mut errors) = z_semantic::build(&ast);
+    errors.extend(identifiers::assign(&mut program));
+    errors.extend(symbols::resolve(&program).1);
+    let (graph, graph_errors) = graph::Graph::build(&program.targets);
+    errors.extend(graph_errors);
+    errors.extend(graph.order(&program.targets).1);
+    errors.extend(schema::validate(&ast, &registry));
+    errors.sort_by_key(|error| error.span.start);
+    if errors.is_empty() {
+        return Ok(program);
+    }
+    let messages: Vec<String> = errors.iter()
+        .map(|error| source_diagnostic(diagnostic::INVALID, error, source, &ast).to_string())
+        .collect();
+    Err(messages.join("\n"))
+}
+
+/// A diagnostic for a problem in the source, naming the template when the problem
+/// is in what one expanded to
+fn source_diagnostic(code: &'static str, error: &z_semantic::SemanticError, source: &str, ast: &z_ast::Element) -> Diagnostic {
+    let message = match template::expanded_from(ast, error.span) {
+        Some(name) => format!("{} (in template '{}')", error.message, name),
+        None => error.message.clone(),
+    };
+    Diagnostic::error(code, message).with_span(error.span, source)
+}
+
+/// Parse `source` into top-level elements, with target types written as aliases
+/// replaced, the active environment's constants in place of the `env` blocks, and
+/// normalized by the pipeline's passes; also returns the aliases replaced
+fn parse(source: &str, options: &CompileOptions, registry: &serde_json::Value) -> Result<(z_ast::Element, Vec<aliases::Renamed>), String> {
+    let context = PassContext { source, registry };
+    let mut ast = parse_source_with(source, &options.limits)?;
+    let renamed = aliases::resolve_aliases(&mut ast, registry);
+    environment::apply(&mut ast, options.environment.as_deref(), &options.environments, source)?;
+    options.pipeline.run(&mut ast, &context)?;
+    Ok((ast, renamed))
+}
+
+/// The targets of a program as [`compile`] hands them to their compilers: each with the
+/// options it's compiled with, along with the parsed program
+#[derive(Debug, Clone)]
+pub struct Lowered {
+    pub ast: z_ast::Element,
+    pub targets: Vec<(Target, CompilerOptions)>,
+}
+
+/// Parse and check `source` as [`compile`] does, without generating anything; the
+/// errors are those that would be reported
+pub fn lower(source: &str, options: &CompileOptions) -> Result<Lowered, Vec<Diagnostic>> {
+    let registry = load_registry(options);
+    let errors: Vec<Diagnostic> = options.observers.pre_parse(source).into_iter().filter(Diagnostic::is_error).collect();
+    if !errors.is_empty() {
+        return Err(errors);
+    }
+    let (ast, renamed) = parse(source, options, &registry)
+        .map_err(|e| vec![Diagnostic::error(diagnostic::PARSE, tr!("parse-error", error = e))])?;
+    let (program, compiler_options, mut diagnostics) = check(source, &ast, &renamed, options, &registry);
+    diagnostics.extend(options.observers.post_validate(&program, source));
+    let errors: Vec<Diagnostic> = diagnostics.into_iter().filter(Diagnostic::is_error).collect();
+    if !errors.is_empty() {
+        return Err(errors);
+    }
+    Ok(Lowered { ast, targets: program.targets.into_iter().zip(compiler_options).collect() })
+}
+
+/// The program of `ast`, with the options each of its targets is compiled with, and
+/// every problem found in it before any target is generated
+fn check(
+    source: &str,
+    ast: &z_ast::Element,
+    renamed: &[aliases::Renamed],
+    options: &CompileOptions,
+    registry: &serde_json::Value,
+) -> (z_semantic::Program, Vec<CompilerOptions>, Vec<Diagnostic>) {
+    let (mut program, errors) = z_semantic::build(ast);
+    let mut diagnostics: Vec<Diagnostic> = errors.iter()
+        .map(|error| source_diagnostic(diagnostic::INVALID, error, source, ast))
+        .collect();
+    // Bundle ids and package names platforms would reject, or that two apps share
+    diagnostics.extend(identifiers::assign(&mut program).iter().map(|error| source_diagnostic(diagnostic::IDENTIFIER, error, source, ast)));
+    // Old names of target types still work, with a nudge towards the new one
+    diagnostics.extend(renamed.iter().filter(|renamed| renamed.deprecated).map(|renamed| {
+        Diagnostic::warning(diagnostic::DEPRECATED_TARGET, tr!("deprecated-target", alias = renamed.alias, target = renamed.target))
+            .with_span(renamed.span, source)
+            .with_help(tr!("deprecated-target-help", alias = renamed.alias, target = renamed.target))
+    }));
+    // Resolve names across the model so broken references surface before any codegen
+    let (symbols, resolve_errors) = symbols::resolve(&program);
+    diagnostics.extend(resolve_errors.iter().map(|error| source_diagnostic(diagnostic::UNRESOLVED, error, source, ast)));
+    diagnostics.extend(symbols.unused_components().iter().map(|component| {
+        Diagnostic::warning(diagnostic::UNUSED_COMPONENT, tr!("unused-component", component = component.name))
+            .with_span(component.span, source)
+            .with_help(tr!("unused-component-help"))
+    }));
+    // Targets are generated after those they use, which takes using each other in no cycle
+    let (graph, graph_errors) = graph::Graph::build(&program.targets);
+    diagnostics.extend(graph_errors.iter().map(|error| source_diagnostic(diagnostic::UNRESOLVED, error, source, ast)));
+    diagnostics.extend(graph.order(&program.targets).1.iter().map(|error| source_diagnostic(diagnostic::CYCLE, error, source, ast)));
+    // Secrets declared where a client would get them
+    diagnostics.extend(env_vars::check(&program).iter().map(|error| source_diagnostic(diagnostic::SECRET, error, source, ast)));
+    // Sections and fields the target compilers wouldn't understand
+    diagnostics.extend(schema::validate(ast, registry).iter().map(|error| source_diagnostic(diagnostic::SCHEMA, error, source, ast)));
+    // Options the target compilers don't take
+    diagnostics.extend(options.target_options.check(registry).into_iter().map(|error| Diagnostic::error(diagnostic::SCHEMA, error)));
+    let mut compiler_options = Vec::new();
+    for (index, target) in program.targets.iter().enumerate() {
+        let (target_options, errors) = target_options::resolve(target, &options.target_options, registry);
+        diagnostics.extend(errors.iter().map(|error| source_diagnostic(diagnostic::SCHEMA, error, source, ast)));
+        let used_by = graph.used_by(index).map(|other| program.targets[other].kind.clone()).collect();
+        compiler_options.push(target_options.in_environment(options.environment.as_deref()).used_by_kinds(used_by));
+    }
+    // Imports only mean something to `compile_project`, which takes them out of the source
+    diagnostics.extend(modules::import_lines(ast).iter().map(|(_, span)| {
+        Diagnostic::error(diagnostic::IMPORT, tr!("imports-unresolved")).with_span(*span, source)
+    }));
+    (program, compiler_options, diagnostics)
+}
+
+/// Generate `target`, one of `targets`, into `sink`: the compiler's project, then the
+/// files every target gets from its settings and shared blocks
+fn generate(
+    target: &Target,
+    targets: &[Target],
+    compiler: &dyn TargetCompiler,
+    target_options: &CompilerOptions,
+    sink: &dyn FileSink,
+    options: &CompileOptions,
+    registry: &serde_json::Value,
+) -> Result<(), String> {
+    let kind = target.kind.as_str();
+    compile_target(target, compiler, target_options, sink, options)?;
+    devenv::write_dev_environments(
+        &options.dev_env,
+        // An invalid runtime has already failed the compile
+        &Runtime::of(target_options).unwrap_or_default().toolchain(&registry["targets"][kind]),
+        &target.name,
+        sink,
+    )?;
+    store::write_store_metadata(target, sink, options.source_dir.as_deref())?;
+    links::write_deep_links(target, sink)?;
+    meta::write_app_metadata(target, sink, options.source_dir.as_deref())?;
+    identifiers::write_identifiers(target, sink)?;
+    contract::write_shared_types(target, targets, &Naming::of(kind, target_options)?, sink, &options.style)?;
+    env_vars::write_env_plumbing(target, sink, &options.style)?;
+    if let Some(version) = &options.version {
+        version::stamp_version(target, sink, version)?;
+    }
+    dependencies::apply_overrides(sink, options.dependencies.for_kind(kind)).map(drop)
+}
+
+/// Generate what `compiler` makes of `target` into `sink`: its project, or else its one
+/// file, `generated.<extension>`
+pub fn compile_target(
+    target: &Target,
+    compiler: &dyn TargetCompiler,
+    target_options: &CompilerOptions,
+    sink: &dyn FileSink,
+    options: &CompileOptions,
+) -> Result<(), String> {
+    sink.create_dir(std::path::Path::new(""))?;
 
     // Try directory-based compilation first (for complex project structures like Next.js)
-    if let Some(result) = compiler.compile_to_directory(ast, &output_dir) {
-        result?;
-        println!("  📁 Project created in: {}", output_dir.display());
-        return Ok(());
+    if let Some(result) = compiler.compile_to_directory(target, target_options, sink) {
+        return result;
     }
 
     // Fallback to standard single-file compilation
-    let generated_code = compiler.compile(ast)?;
+    let generated_code = compiler.compile(target, target_options)?;
 
     // Write the generated code to appropriate files
-    let output_file = output_dir.join(format!("generated.{}", compiler.file_extension()));
-    fs::write(&output_file, generated_code)
-        .map_err(|e| format!("Failed to write {}: {}", output_file.display(), e))?;
-
-    println!("  📁 Output written to: {}", output_file.display());
-    Ok(())
+    let output_file = PathBuf::from(format!("generated.{}", compiler.file_extension()));
+    sink.write(&output_file, options.style.apply(&output_file, &generated_code).as_bytes())
 }
 
-fn detect_targets(ast: &Element) -> Vec<String> {
-    ast.children.iter()
-        .filter_map(|node| match node {
-            Node::Element(element) => Some(element.name.clone()), // This is already in "target:name" format
-            _ => None,
-        })
-        .collect()
-}
\ No newline at end of file
+/// 1-based line of a byte offset, for diagnostics
+pub(crate) fn line_number(source: &str, offset: usize) -> usize {
+    source.get(..offset).map_or(0, |before| before.matches('\n').count()) + 1
+}
@@ -0,0 +1,124 @@
+//! Deep link configuration generated from the `Links` block a target shares with
+//! the rest of its workspace.
+//!
+//! - `next` targets host the verification files for the associated domains:
+//!   `apple-app-site-association` and `assetlinks.json` under `public/.well-known`
+//! - `swift` targets get the `applinks:` entitlements and the URL scheme in `Info.plist`;
+//!   the generated app hands opened links to its views (see the SwiftUI compiler)
+//! - `tauri` targets get the `deep-link` plugin settings in `src-tauri/tauri.conf.json`
+
+use std::path::Path;
+
+use serde_json::{json, Value};
+use z_semantic::{DeepLinks, Target};
+
+use crate::sink::FileSink;
+
+/// Write the deep link configuration for `target` into its project, if it has links
+pub(crate) fn write_deep_links(target: &Target, sink: &dyn FileSink) -> Result<(), String> {
+    let Some(links) = &target.links else {
+        return Ok(());
+    };
+
+    match target.kind.as_str() {
+        "next" => write_well_known(links, sink),
+        "swift" => write_apple_config(links, &target.name, sink),
+        "tauri" => write_tauri_plugin(links, sink),
+        _ => Ok(()),
+    }
+}
+
+fn write_well_known(links: &DeepLinks, sink: &dyn FileSink) -> Result<(), String> {
+    // Only `https` links are verified against the site
+    if links.domains.is_empty() {
+        return Ok(());
+    }
+    let well_known = Path::new("public/.well-known");
+
+    if let Some(app_id) = &links.apple_app_id {
+        let components: Vec<Value> = if links.paths.is_empty() {
+            vec![json!({ "/": "*" })]
+        } else {
+            links.paths.iter().map(|path| json!({ "/": path })).collect()
+        };
+        let association = json!({
+            "applinks": {
+                "details": [{ "appIDs": [app_id], "components": components }],
+            },
+        });
+        sink.write_json(&well_known.join("apple-app-site-association"), &association)?;
+    }
+
+    if let Some(package) = &links.android_package {
+        let statements = json!([{
+            "relation": ["delegate_permission/common.handle_all_urls"],
+            "target": {
+                "namespace": "android_app",
+                "package_name": package,
+                "sha256_cert_fingerprints": links.android_fingerprints,
+            },
+        }]);
+        sink.write_json(&well_known.join("assetlinks.json"), &statements)?;
+    }
+    Ok(())
+}
+
+fn write_apple_config(links: &DeepLinks, app_name: &str, sink: &dyn FileSink) -> Result<(), String> {
+    if !links.domains.is_empty() {
+        let domains: String = links.domains.iter()
+            .map(|domain| format!("\t\t<string>applinks:{}</string>\n", domain))
+            .collect();
+        let entitlements = plist(&format!(
+            "\t<key>com.apple.developer.associated-domains</key>\n\t<array>\n{}\t</array>\n",
+            domains,
+        ));
+        sink.write_text(Path::new(&format!("{}.entitlements", app_name)), &entitlements)?;
+    }
+
+    if let Some(scheme) = &links.scheme {
+        let url_name = links.apple_app_id.as_deref()
+            .and_then(|app_id| app_id.split_once('.'))
+            .map_or(app_name, |(_, bundle_id)| bundle_id);
+        let info = plist(&format!(
+            "\t<key>CFBundleURLTypes</key>\n\t<array>\n\t\t<dict>\n\
+             \t\t\t<key>CFBundleURLName</key>\n\t\t\t<string>{}</string>\n\
+             \t\t\t<key>CFBundleURLSchemes</key>\n\t\t\t<array>\n\t\t\t\t<string>{}</string>\n\t\t\t</array>\n\
+             \t\t</dict>\n\t</array>\n",
+            url_name, scheme,
+        ));
+        sink.write_text(Path::new("Info.plist"), &info)?;
+    }
+    Ok(())
+}
+
+fn write_tauri_plugin(links: &DeepLinks, sink: &dyn FileSink) -> Result<(), String> {
+    let path = Path::new("src-tauri/tauri.conf.json");
+    let mut config = sink.read_json(path)?;
+
+    let mut deep_link = json!({});
+    if !links.domains.is_empty() {
+        let prefixes: Vec<&str> = links.paths.iter().map(|path| path.trim_end_matches('*')).collect();
+        deep_link["mobile"] = links.domains.iter()
+            .map(|domain| match prefixes.as_slice() {
+                [] => json!({ "host": domain }),
+                prefixes => json!({ "host": domain, "pathPrefix": prefixes }),
+            })
+            .collect();
+    }
+    if let Some(scheme) = &links.scheme {
+        deep_link["desktop"] = json!({ "schemes": [scheme] });
+    }
+    config["plugins"]["deep-link"] = deep_link;
+
+    sink.write_json(path, &config)
+}
+
+/// A property list document with the given entries in its top-level dictionary
+pub(crate) fn plist(entries: &str) -> String {
+    format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+         <!DOCTYPE plist PUBLIC \"-//Apple//DTD PLIST 1.0//EN\" \"http://www.apple.com/DTDs/PropertyList-1.0.dtd\">\n\
+         <plist version=\"1.0\">\n<dict>\n{}</dict>\n</plist>\n",
+        entries,
+    )
+}
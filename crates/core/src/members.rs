@@ -0,0 +1,211 @@
+//! Compiling several programs in one go, such as the apps of a monorepo that each have
+//! their own entry file.
+//!
+//! The root `z.toml` lists the entry files, as paths or globs relative to it:
+//!
+//! ```toml
+//! members = ["apps/*/main.z", "tools/cli.z"]
+//! ```
+//!
+//! Each program compiles as it would on its own, into its [`Member::output_dir`], but
+//! with the settings the root's `z.toml` gives, so the registry is loaded once. Files
+//! several programs import, such as shared models, are read and parsed once.
+//! [`CompiledMembers::report`] puts the build reports of them all together.
+
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use serde_json::Value;
+
+use crate::modules::Sources;
+use crate::report::{CompileReport, FileEntry, Status};
+use crate::{compile_entry, load_registry, CompileOptions, CompileResult};
+
+/// Key of the entry files in `z.toml`
+pub const MEMBERS: &str = "members";
+
+/// Directories a glob doesn't look in: generated projects and tools' state
+const SKIPPED_DIRS: &[&str] = &["out", "node_modules", "target"];
+
+/// The entry files `z.toml` lists in `members`, as written
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Members {
+    patterns: Vec<String>,
+}
+
+/// A program to compile, and where to
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Member {
+    pub entry: PathBuf,
+    pub output_dir: PathBuf,
+}
+
+/// How compiling each member went, in the order they were given
+#[derive(Debug, Clone, Default)]
+pub struct CompiledMembers {
+    pub members: Vec<(Member, CompileResult)>,
+    pub duration: Duration,
+}
+
+impl Members {
+    /// Read `members` from a parsed `z.toml`
+    pub fn from_config(config: &Value) -> Result<Self, String> {
+        let patterns = match &config[MEMBERS] {
+            Value::Null => Vec::new(),
+            Value::Array(patterns) => patterns.iter()
+                .map(|pattern| pattern.as_str().map(str::to_string))
+                .collect::<Option<Vec<_>>>()
+                .ok_or_else(|| format!("'{}' must be a list of entry files, e.g. [\"apps/*/main.z\"]", MEMBERS))?,
+            _ => return Err(format!("'{}' must be a list of entry files, e.g. [\"apps/*/main.z\"]", MEMBERS)),
+        };
+        Ok(Self { patterns })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.patterns.is_empty()
+    }
+
+    /// The entry files under `root` the members name, sorted, each once. A pattern that
+    /// matches nothing is an error, since it's most likely a typo.
+    pub fn entries(&self, root: &Path) -> Result<Vec<PathBuf>, String> {
+        let mut entries = BTreeSet::new();
+        for pattern in &self.patterns {
+            let matched = glob(root, pattern);
+            if matched.is_empty() {
+                return Err(format!("'{}' in '{}' matches no file in {}", pattern, MEMBERS, root.display()));
+            }
+            entries.extend(matched);
+        }
+        Ok(entries.into_iter().collect())
+    }
+}
+
+impl CompiledMembers {
+    pub fn has_errors(&self) -> bool {
+        self.members.iter().any(|(_, result)| result.has_errors())
+    }
+
+    /// One build report for every member: their inputs, targets and diagnostics, with
+    /// the files named from where the members' entries are
+    pub fn report(&self) -> CompileReport {
+        let mut report = CompileReport {
+            status: if self.has_errors() { Status::Failed } else { Status::Success },
+            duration_ms: self.duration.as_millis() as u64,
+            ..CompileReport::default()
+        };
+        for (member, result) in &self.members {
+            let dir = member.entry.parent().unwrap_or(Path::new(""));
+            let member_report = result.report();
+            report.inputs.extend(member_report.inputs.into_iter().map(|input| FileEntry { path: dir.join(&input.path), ..input }));
+            report.targets.extend(member_report.targets);
+            report.diagnostics.extend(member_report.diagnostics.into_iter().map(|mut diagnostic| {
+                diagnostic.file = Some(diagnostic.file.map_or_else(|| member.entry.clone(), |file| dir.join(file)));
+                diagnostic
+            }));
+        }
+        report
+    }
+}
+
+/// Compile each of `members` with `options`, its files read from where the entry is
+pub fn compile_members(members: &[Member], options: &CompileOptions) -> CompiledMembers {
+    let started = Instant::now();
+    let registry = load_registry(options);
+    let mut sources = Sources::new();
+    let members = members.iter()
+        .map(|member| {
+            let dir = member.entry.parent().filter(|dir| !dir.as_os_str().is_empty()).unwrap_or(Path::new("."));
+            let options = CompileOptions { source_dir: Some(dir.to_path_buf()), ..options.clone() };
+            let result = compile_entry(&member.entry, &member.output_dir, &options, &mut sources, &registry);
+            (member.clone(), result)
+        })
+        .collect();
+    CompiledMembers { members, duration: started.elapsed() }
+}
+
+/// The files under `root` whose path from it matches `pattern`, sorted: `*` stands for any
+/// part of a name, `?` for one character, and `**` for any number of directories
+pub fn glob(root: &Path, pattern: &str) -> Vec<PathBuf> {
+    let parts: Vec<&str> = Path::new(pattern).components()
+        .filter_map(|component| match component {
+            Component::Normal(part) => part.to_str(),
+            _ => None,
+        })
+        .collect();
+    let mut matches = Vec::new();
+    walk(root, &parts, &mut matches);
+    matches.sort();
+    matches.dedup();
+    matches
+}
+
+fn walk(dir: &Path, parts: &[&str], matches: &mut Vec<PathBuf>) {
+    let Some((part, rest)) = parts.split_first() else {
+        return;
+    };
+    if *part == "**" {
+        // None of the directories, then one more
+        walk(dir, rest, matches);
+        for child in subdirectories(dir) {
+            walk(&child, parts, matches);
+        }
+        return;
+    }
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let name = entry.file_name().to_string_lossy().into_owned();
+        if !matches_name(part, &name) || (name.starts_with('.') && !part.starts_with('.')) {
+            continue;
+        }
+        let path = entry.path();
+        match (rest.is_empty(), path.is_dir()) {
+            (true, false) => matches.push(path),
+            (false, true) => walk(&path, rest, matches),
+            _ => {}
+        }
+    }
+}
+
+fn subdirectories(dir: &Path) -> Vec<PathBuf> {
+    fs::read_dir(dir).into_iter().flatten().flatten()
+        .filter(|entry| entry.file_type().is_ok_and(|kind| kind.is_dir()))
+        .filter(|entry| {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            !name.starts_with('.') && !SKIPPED_DIRS.contains(&name.as_str())
+        })
+        .map(|entry| entry.path())
+        .collect()
+}
+
+/// Whether `name` matches `pattern`, with `*` and `?`
+fn matches_name(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+    // Where the last `*` was, and how much of the name it has taken so far
+    let (mut p, mut n, mut star) = (0, 0, None);
+    while n < name.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                star = Some((p, n));
+                p += 1;
+            }
+            Some(c) if *c == '?' || *c == name[n] => {
+                p += 1;
+                n += 1;
+            }
+            _ => match star {
+                Some((star_p, star_n)) => {
+                    p = star_p + 1;
+                    n = star_n + 1;
+                    star = Some((star_p, star_n + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|c| *c == '*')
+}
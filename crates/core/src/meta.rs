@@ -0,0 +1,134 @@
+//! App metadata from the `Meta` block a target shares with the rest of its workspace.
+//! Without one, the name comes from the program's `config` block.
+//!
+//! Compilers put the name and description in the code they generate (page titles,
+//! `package.json` of the projects they write). This fills in the files generated by
+//! other tools, and copies the icon:
+//!
+//! - `rust` and `tauri` targets: `version` and `description` in `[package]` of `Cargo.toml`
+//! - `tauri` targets: `productName` and `version` in `src-tauri/tauri.conf.json`, and the
+//!   frontend's `package.json`; the icon goes to `app-icon.<ext>`, for `tauri icon`
+//! - `swift` targets: the bundle name, version and icon file in `Info.plist`
+//! - `next` targets: the icon as `app/icon.<ext>`, which Next.js serves as the favicon
+//! - `deno` targets: the icon as `static/favicon.<ext>`, which the layout links
+//!
+//! The icon path is relative to the directory of the source file. A `VERSION` file
+//! wins over `version`, since it's stamped after this runs.
+
+use std::fs;
+use std::path::Path;
+
+use serde_json::json;
+use z_semantic::{Meta, Target};
+
+use crate::links::plist;
+use crate::sink::FileSink;
+use crate::tr;
+use crate::version::{set_package_field, set_plist_string};
+
+/// Write the metadata for `target` into its project, if it has a `Meta` block or the
+/// program a `config` block naming the product
+pub(crate) fn write_app_metadata(target: &Target, sink: &dyn FileSink, source_dir: Option<&Path>) -> Result<(), String> {
+    let named = target.config.as_ref().filter(|config| config.name.is_some());
+    let Some(meta) = &target.meta.clone().or_else(|| named.map(|config| Meta {
+        name: None,
+        version: None,
+        description: None,
+        icon: None,
+        span: config.span,
+    })) else {
+        return Ok(());
+    };
+    let source_dir = source_dir.unwrap_or(Path::new("."));
+
+    match target.kind.as_str() {
+        "rust" => write_cargo_package(meta, sink, Path::new("Cargo.toml")),
+        "tauri" => {
+            write_cargo_package(meta, sink, Path::new("src-tauri/Cargo.toml"))?;
+            write_tauri_config(target, meta, sink)?;
+            copy_icon(meta, source_dir, sink, Path::new("app-icon"))
+        }
+        "swift" => write_info_plist(target, meta, sink, source_dir),
+        "next" => copy_icon(meta, source_dir, sink, Path::new("app/icon")),
+        "deno" => copy_icon(meta, source_dir, sink, Path::new("static/favicon")),
+        _ => Ok(()),
+    }
+}
+
+fn write_cargo_package(meta: &Meta, sink: &dyn FileSink, manifest: &Path) -> Result<(), String> {
+    let Some(mut contents) = sink.read_text(manifest)? else {
+        return Ok(());
+    };
+    for (key, value) in [("version", &meta.version), ("description", &meta.description)] {
+        if let Some(updated) = value.as_deref().and_then(|value| set_package_field(&contents, key, value)) {
+            contents = updated;
+        }
+    }
+    sink.write_text(manifest, &contents)
+}
+
+fn write_tauri_config(target: &Target, meta: &Meta, sink: &dyn FileSink) -> Result<(), String> {
+    let path = Path::new("src-tauri/tauri.conf.json");
+    let mut config = sink.read_json(path)?;
+    // Tauri 1 keeps these under `package`, Tauri 2 at the top level
+    let package = if config["package"].is_object() { &mut config["package"] } else { &mut config };
+    // A store listing names the app in the bundle already
+    if target.store().is_none() {
+        package["productName"] = json!(target.display_name());
+    }
+    if let Some(version) = &meta.version {
+        package["version"] = json!(version);
+    }
+    sink.write_json(path, &config)?;
+
+    let package_json = Path::new("package.json");
+    if sink.exists(package_json) {
+        let mut package = sink.read_json(package_json)?;
+        if let Some(version) = &meta.version {
+            package["version"] = json!(version);
+        }
+        if let Some(description) = &meta.description {
+            package["description"] = json!(description);
+        }
+        sink.write_json(package_json, &package)?;
+    }
+    Ok(())
+}
+
+fn write_info_plist(target: &Target, meta: &Meta, sink: &dyn FileSink, source_dir: &Path) -> Result<(), String> {
+    let info = Path::new("Info.plist");
+    // The deep link settings may have written it already
+    let mut contents = sink.read_text(info)?.unwrap_or_else(|| plist(""));
+
+    let mut entries = vec![("CFBundleName", target.display_name()), ("CFBundleDisplayName", target.display_name())];
+    if let Some(version) = &meta.version {
+        entries.extend([("CFBundleShortVersionString", version.as_str()), ("CFBundleVersion", version.as_str())]);
+    }
+    let icon_file = meta.icon.as_deref().and_then(icon_extension).map(|extension| format!("AppIcon.{}", extension));
+    if let Some(icon_file) = &icon_file {
+        entries.push(("CFBundleIconFile", icon_file));
+    }
+    for (key, value) in entries {
+        let value = value.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;");
+        contents = set_plist_string(&contents, key, &value);
+    }
+    sink.write_text(info, &contents)?;
+
+    copy_icon(meta, source_dir, sink, Path::new("AppIcon"))
+}
+
+/// Copy the icon to `to` in the project, given the icon's extension
+fn copy_icon(meta: &Meta, source_dir: &Path, sink: &dyn FileSink, to: &Path) -> Result<(), String> {
+    let Some(icon) = &meta.icon else {
+        return Ok(());
+    };
+    let from = source_dir.join(icon);
+    let to = to.with_extension(icon_extension(icon).unwrap_or_default());
+    let contents = fs::read(&from).map_err(|e| tr!("copy-failed", path = from.display(), error = e))?;
+    sink.create_dir(to.parent().unwrap_or(Path::new("")))?;
+    sink.write(&to, &contents)
+}
+
+fn icon_extension(icon: &str) -> Option<String> {
+    Path::new(icon).extension().map(|extension| extension.to_string_lossy().to_lowercase())
+}
@@ -0,0 +1,275 @@
+//! Programs split across files, joined by imports at the top level of a file:
+//!
+//! ```z
+//! import "./models.z"              // its targets and shared blocks join this file's
+//! import admin from "./admin.z"    // its targets go into a workspace called admin
+//! ```
+//!
+//! Paths are relative to the importing file. [`Project::load`] reads and parses every
+//! file once, however many files import it, or however many programs do when they're
+//! loaded with the same [`Sources`], and lays them out one after the other as a
+//! single source, with the import lines blanked out and namespaced files wrapped in
+//! their workspace. That source compiles like any other; [`Project::locate`] then
+//! points the diagnostics in imported files back at their file and line.
+//!
+//! Imports in a file imported into a namespace go into the same namespace, unless they
+//! name their own. A file imported into a namespace can't declare workspaces itself.
+
+use std::collections::HashMap;
+use std::fs;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+use z_ast::{Element, Node, Span};
+
+use crate::diagnostic::{self, Diagnostic};
+use crate::report::FileEntry;
+use crate::{line_number, tr, Limits};
+
+/// A program read from its entry file and the files it imports
+#[derive(Debug, Clone, Default)]
+pub struct Project {
+    /// Every file, the entry first, as one source
+    pub source: String,
+    pub modules: Vec<Module>,
+}
+
+/// One file of a [`Project`]
+#[derive(Debug, Clone)]
+pub struct Module {
+    /// Canonical path of the file
+    pub path: PathBuf,
+    /// The path shown in messages, relative to the entry's directory when it can be
+    pub display: PathBuf,
+    /// Workspace the file's targets go into, for `import name from ...`
+    pub namespace: Option<String>,
+    /// Where the file's contents are in [`Project::source`]
+    pub range: Range<usize>,
+    /// The file as read, for the build report
+    pub input: FileEntry,
+}
+
+/// Files read and parsed while loading projects, kept for the projects loaded after, so
+/// files several programs import are read and parsed once
+#[derive(Debug, Default)]
+pub struct Sources {
+    /// Text and parsed program of each file, by canonical path
+    files: HashMap<PathBuf, (String, Result<Element, String>)>,
+}
+
+impl Sources {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The canonical path of `path`, with the file's text and the program parsed from it
+    fn read(&mut self, path: &Path, limits: &Limits) -> std::io::Result<(PathBuf, String, Result<Element, String>)> {
+        let path = path.canonicalize()?;
+        if let Some((text, ast)) = self.files.get(&path) {
+            return Ok((path, text.clone(), ast.clone()));
+        }
+        let text = fs::read_to_string(&path)?;
+        let ast = z_parser::parse_source_with(&text, limits);
+        self.files.insert(path.clone(), (text.clone(), ast.clone()));
+        Ok((path, text, ast))
+    }
+
+    /// Read the files kept here again, as watch mode does before each compile. A file
+    /// that changed is parsed again only around the edit, reusing the rest of its previous
+    /// tree (see [`z_parser::reparse`]); one that can't be read anymore is dropped.
+    pub fn refresh(&mut self, limits: &Limits) {
+        self.files.retain(|path, (text, ast)| {
+            let Ok(new_text) = fs::read_to_string(path) else {
+                return false;
+            };
+            if let Some(edit) = z_parser::TextEdit::between(text, &new_text) {
+                *ast = match ast {
+                    Ok(old) => z_parser::reparse(old.clone(), &new_text, &[edit], limits),
+                    Err(_) => z_parser::parse_source_with(&new_text, limits),
+                };
+                *text = new_text;
+            }
+            true
+        });
+    }
+}
+
+/// An import waiting to be read
+struct Pending {
+    path: PathBuf,
+    /// The path as the import wrote it
+    written: String,
+    namespace: Option<String>,
+    /// The importing module and the import line in it
+    from: Option<(usize, Span)>,
+}
+
+impl Project {
+    /// Read `entry` and every file it imports, directly or not
+    pub fn load(entry: &Path, limits: &Limits) -> Result<Self, Vec<Diagnostic>> {
+        Self::load_with(entry, limits, &mut Sources::new())
+    }
+
+    /// [`load`](Self::load), taking the files already in `sources` from there and keeping
+    /// the others read there
+    pub fn load_with(entry: &Path, limits: &Limits, sources: &mut Sources) -> Result<Self, Vec<Diagnostic>> {
+        let base = entry.canonicalize().ok().and_then(|entry| entry.parent().map(Path::to_path_buf)).unwrap_or_default();
+        let mut project = Self::default();
+        let mut texts: Vec<String> = Vec::new();
+        let mut loaded: HashMap<PathBuf, usize> = HashMap::new();
+        let mut errors = Vec::new();
+        let mut pending = vec![Pending { path: entry.to_path_buf(), written: entry.display().to_string(), namespace: None, from: None }];
+
+        // Depth first, so each file's imports follow it
+        while let Some(import) = pending.pop() {
+            // Problems with the import are the importing file's
+            let located = |diagnostic: Diagnostic| match import.from {
+                Some((importer, span)) => project.in_file(importer, diagnostic.with_span(span, &texts[importer])),
+                None => diagnostic,
+            };
+            let (path, text, parsed) = match sources.read(&import.path, limits) {
+                Ok(read) => read,
+                Err(e) => {
+                    let diagnostic = match import.from {
+                        _ if e.kind() == std::io::ErrorKind::InvalidData => {
+                            Diagnostic::error(diagnostic::READ, tr!("source-not-utf8", path = import.written))
+                        }
+                        Some(_) => Diagnostic::error(diagnostic::IMPORT, tr!("import-missing", path = import.written, error = e)),
+                        None => Diagnostic::error(diagnostic::READ, tr!("read-source-failed", path = import.path.display(), error = e)),
+                    };
+                    errors.push(located(diagnostic));
+                    continue;
+                }
+            };
+            if let Some(&index) = loaded.get(&path) {
+                let first = &project.modules[index];
+                if first.namespace != import.namespace {
+                    let namespace = |namespace: &Option<String>| match namespace {
+                        Some(namespace) => format!("workspace {}", namespace),
+                        None => tr!("import-top-level"),
+                    };
+                    errors.push(located(Diagnostic::error(diagnostic::IMPORT, tr!(
+                        "import-namespace-conflict",
+                        path = first.display.display(),
+                        first = namespace(&first.namespace),
+                        second = namespace(&import.namespace),
+                    ))));
+                }
+                continue;
+            }
+
+            let index = project.modules.len();
+            let display = path.strip_prefix(&base).map_or(path.clone(), Path::to_path_buf);
+            let in_module = |diagnostic: Diagnostic| if index == 0 { diagnostic } else { Diagnostic { file: Some(display.clone()), ..diagnostic } };
+            let ast = match parsed {
+                Ok(ast) => ast,
+                Err(e) => {
+                    errors.push(in_module(Diagnostic::error(diagnostic::PARSE, tr!("parse-error", error = e))));
+                    continue;
+                }
+            };
+            let dir = path.parent().unwrap_or(Path::new("")).to_path_buf();
+            let mut blanked = text.clone();
+            let mut imports = Vec::new();
+            for (line, span) in import_lines(&ast) {
+                blanked.replace_range(span.start..span.end, &" ".repeat(span.end - span.start));
+                match line {
+                    Ok((file, namespace)) => imports.push(Pending {
+                        path: dir.join(&file),
+                        written: file,
+                        namespace: namespace.or_else(|| import.namespace.clone()),
+                        from: Some((index, span)),
+                    }),
+                    Err(written) => errors.push(in_module(
+                        Diagnostic::error(diagnostic::IMPORT, tr!("import-invalid", import = written.trim())).with_span(span, &text),
+                    )),
+                }
+            }
+            if import.namespace.is_some() && index > 0 {
+                let workspace = ast.items().find_map(|node| match node {
+                    Node::Element(element) if element.name.starts_with("workspace:") => Some(element.span),
+                    _ => None,
+                });
+                if let Some(span) = workspace {
+                    errors.push(in_module(Diagnostic::error(diagnostic::IMPORT, tr!("import-namespace-workspace")).with_span(span, &text)));
+                }
+            }
+
+            let (prefix, suffix) = match &import.namespace {
+                Some(namespace) if index > 0 => (format!("workspace {} {{\n", namespace), "\n}"),
+                _ => (String::new(), ""),
+            };
+            if index > 0 {
+                project.source.push('\n');
+            }
+            project.source.push_str(&prefix);
+            let start = project.source.len();
+            project.source.push_str(&blanked);
+            let range = start..project.source.len();
+            project.source.push_str(suffix);
+
+            loaded.insert(path.clone(), index);
+            let input = FileEntry::new(display.clone(), text.as_bytes());
+            project.modules.push(Module { path, display, namespace: import.namespace, range, input });
+            texts.push(text);
+            pending.extend(imports.into_iter().rev());
+        }
+        if errors.is_empty() { Ok(project) } else { Err(errors) }
+    }
+
+    /// The file byte `offset` of [`Project::source`] is in, as shown in messages
+    pub fn file_at(&self, offset: usize) -> Option<&Path> {
+        self.modules.iter().find(|module| module.range.contains(&offset)).map(|module| module.display.as_path())
+    }
+
+    /// Point the diagnostics about imported files at the file and its line
+    pub fn locate(&self, diagnostics: &mut [Diagnostic]) {
+        for diagnostic in diagnostics.iter_mut() {
+            let Some(span) = diagnostic.span.filter(|_| diagnostic.file.is_none()) else {
+                continue;
+            };
+            // The entry starts the source, so its spans and lines are already right
+            let Some(module) = self.modules.iter().skip(1).find(|module| module.range.contains(&span.start)) else {
+                continue;
+            };
+            let start = module.range.start;
+            diagnostic.file = Some(module.display.clone());
+            diagnostic.span = Some(Span::new(span.start - start, span.end.saturating_sub(start)));
+            diagnostic.line = Some(line_number(&self.source[module.range.clone()], span.start - start));
+        }
+    }
+
+    /// `diagnostic`, about module `index`, naming its file unless it's the entry
+    fn in_file(&self, index: usize, diagnostic: Diagnostic) -> Diagnostic {
+        match self.modules.get(index).filter(|_| index > 0) {
+            Some(module) => Diagnostic { file: Some(module.display.clone()), ..diagnostic },
+            None => diagnostic,
+        }
+    }
+}
+
+/// The file an import names, and the namespace, for `import name from ...`
+type Import = (String, Option<String>);
+
+/// The import lines at the top level of `program`: what each imports, or what was
+/// written when it isn't an import of either form
+pub(crate) fn import_lines(program: &Element) -> Vec<(Result<Import, String>, Span)> {
+    let unquote = |path: &str| path.trim().trim_matches('"').to_string();
+    program.items()
+        .filter_map(|node| match node {
+            // import "./models.z"
+            Node::ChildLine { modifier: None, id, signature, span, .. } if id == "import" => {
+                let path = signature.as_deref().map(unquote).filter(|path| !path.is_empty());
+                Some((path.map(|path| (path, None)).ok_or_else(|| "import".to_string()), *span))
+            }
+            // import admin from "./admin.z"
+            Node::ChildLine { modifier: Some(modifier), id, signature, span, .. } if modifier == "import" => {
+                let path = signature.as_deref().and_then(|signature| signature.trim().strip_prefix("from ")).map(unquote);
+                let named = path.filter(|path| !path.is_empty() && id.chars().all(|c| c.is_alphanumeric() || c == '_' || c == '-'));
+                let written = format!("import {} {}", id, signature.as_deref().unwrap_or_default());
+                Some((named.map(|path| (path, Some(id.clone()))).ok_or(written), *span))
+            }
+            _ => None,
+        })
+        .collect()
+}
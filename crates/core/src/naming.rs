@@ -0,0 +1,205 @@
+//! How generated code names what a program declares, in the conventions of each
+//! target's language.
+//!
+//! An identifier is taken as words, split at anything that isn't a letter or digit and
+//! where a lowercase letter or digit meets an uppercase one, so `orderItem`,
+//! `order_item` and `Order Item` are all `order item`. The words are then joined in the
+//! [`Case`] the target uses for what's being named, its [`Role`]:
+//!
+//! | Role                        | `next`, `deno` | `rust` | Option           |
+//! | --------------------------- | -------------- | ------ | ---------------- |
+//! | route segment of a page     | kebab          | kebab  | `route_case`     |
+//! | file of a component         | pascal         | pascal | `component_case` |
+//! | file named after something  | kebab          | snake  | `module_case`    |
+//! | variable or parameter       | camel          | snake  | `field_case`     |
+//!
+//! A target changes them with the options its target type lists, `@options(field_case: snake)`
+//! or in the `[targets.<target type>]` table of `z.toml` (see [`target_options`](crate::target_options)),
+//! taking `kebab`, `snake`, `camel`, `pascal` or `constant`. Names that travel between
+//! targets, like the properties of a shared model, stay as written on the wire: a
+//! Rust field named differently is renamed back by serde.
+
+use std::fmt;
+use std::str::FromStr;
+
+use crate::target_options::CompilerOptions;
+
+/// How the words of a name are joined
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Case {
+    /// `order-item`
+    Kebab,
+    /// `order_item`
+    Snake,
+    /// `orderItem`
+    Camel,
+    /// `OrderItem`
+    Pascal,
+    /// `ORDER_ITEM`
+    Constant,
+}
+
+/// What a generated name is for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Role {
+    /// A path segment of a page
+    Route,
+    /// The file of a UI component; the component itself is PascalCase, as JSX takes it
+    Component,
+    /// A source file or module generated for something declared, like an API client
+    Module,
+    /// A property, variable or parameter
+    Field,
+}
+
+/// The case a target uses for each [`Role`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Naming {
+    route: Case,
+    component: Case,
+    module: Case,
+    field: Case,
+}
+
+impl FromStr for Case {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "kebab" => Ok(Case::Kebab),
+            "snake" => Ok(Case::Snake),
+            "camel" => Ok(Case::Camel),
+            "pascal" => Ok(Case::Pascal),
+            "constant" => Ok(Case::Constant),
+            _ => Err(format!("invalid case '{}' (expected kebab, snake, camel, pascal or constant)", s)),
+        }
+    }
+}
+
+impl fmt::Display for Case {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Case::Kebab => "kebab",
+            Case::Snake => "snake",
+            Case::Camel => "camel",
+            Case::Pascal => "pascal",
+            Case::Constant => "constant",
+        })
+    }
+}
+
+impl Case {
+    /// `name` in this case. Pascal and camel case keep the letters after the first of a
+    /// word as written, so acronyms like `HTTPServer` survive.
+    pub fn apply(self, name: &str) -> String {
+        let words = words(name);
+        match self {
+            Case::Kebab => words.iter().map(|word| word.to_ascii_lowercase()).collect::<Vec<_>>().join("-"),
+            Case::Snake => words.iter().map(|word| word.to_ascii_lowercase()).collect::<Vec<_>>().join("_"),
+            Case::Constant => words.iter().map(|word| word.to_ascii_uppercase()).collect::<Vec<_>>().join("_"),
+            Case::Pascal => words.iter().map(|word| capitalized(word)).collect(),
+            Case::Camel => match words.split_first() {
+                Some((first, rest)) => first.to_ascii_lowercase() + &rest.iter().map(|word| capitalized(word)).collect::<String>(),
+                None => String::new(),
+            },
+        }
+    }
+}
+
+impl Role {
+    /// The option setting the case of this role
+    pub fn option(self) -> &'static str {
+        match self {
+            Role::Route => "route_case",
+            Role::Component => "component_case",
+            Role::Module => "module_case",
+            Role::Field => "field_case",
+        }
+    }
+}
+
+impl Naming {
+    /// The conventions of targets of type `kind` when no option changes them
+    pub fn defaults(kind: &str) -> Self {
+        let native = matches!(kind, "rust" | "tauri");
+        Self {
+            route: Case::Kebab,
+            component: Case::Pascal,
+            module: if native { Case::Snake } else { Case::Kebab },
+            field: if native { Case::Snake } else { Case::Camel },
+        }
+    }
+
+    /// The conventions of a target of type `kind` with `options`
+    pub fn of(kind: &str, options: &CompilerOptions) -> Result<Self, String> {
+        let mut naming = Self::defaults(kind);
+        for role in [Role::Route, Role::Component, Role::Module, Role::Field] {
+            if let Some(value) = options.get(role.option()) {
+                let case = value.parse().map_err(|e| format!("{}: {}", role.option(), e))?;
+                if role == Role::Field && case == Case::Kebab {
+                    return Err(format!("{}: kebab case doesn't make identifiers; use snake, camel, pascal or constant", role.option()));
+                }
+                *naming.case_mut(role) = case;
+            }
+        }
+        Ok(naming)
+    }
+
+    pub fn case(&self, role: Role) -> Case {
+        match role {
+            Role::Route => self.route,
+            Role::Component => self.component,
+            Role::Module => self.module,
+            Role::Field => self.field,
+        }
+    }
+
+    /// `name` as this target names a `role`
+    pub fn name(&self, role: Role, name: &str) -> String {
+        self.case(role).apply(name)
+    }
+
+    fn case_mut(&mut self, role: Role) -> &mut Case {
+        match role {
+            Role::Route => &mut self.route,
+            Role::Component => &mut self.component,
+            Role::Module => &mut self.module,
+            Role::Field => &mut self.field,
+        }
+    }
+}
+
+/// The words of `name`, as written
+pub fn words(name: &str) -> Vec<&str> {
+    let mut words = Vec::new();
+    let mut start = None;
+    let mut previous: Option<char> = None;
+    for (index, c) in name.char_indices() {
+        if !c.is_ascii_alphanumeric() {
+            if let Some(start) = start.take() {
+                words.push(&name[start..index]);
+            }
+        } else {
+            let word_start = c.is_ascii_uppercase() && previous.is_some_and(|p| p.is_ascii_lowercase() || p.is_ascii_digit());
+            match start {
+                Some(from) if word_start => {
+                    words.push(&name[from..index]);
+                    start = Some(index);
+                }
+                None => start = Some(index),
+                Some(_) => {}
+            }
+        }
+        previous = Some(c);
+    }
+    if let Some(start) = start {
+        words.push(&name[start..]);
+    }
+    words
+}
+
+/// `word` with its first letter uppercase
+fn capitalized(word: &str) -> String {
+    let mut chars = word.chars();
+    chars.next().map_or_else(String::new, |first| first.to_ascii_uppercase().to_string() + chars.as_str())
+}
@@ -0,0 +1,94 @@
+//! Hooks into the steps of a compilation.
+//!
+//! A [`CompileObserver`] is called as [`compile`](crate::compile) goes: before the source
+//! is parsed, once the program has been checked, and before and after each target is
+//! generated. Embedders use them for checks of their own, telemetry, or formatting the
+//! generated files with `prettier` or `rustfmt`. Every hook does nothing unless the
+//! observer overrides it; [`Observers`] calls them in the order they were added.
+
+use std::fmt;
+use std::path::Path;
+use std::sync::Arc;
+
+use z_semantic::{Program, Target};
+
+use crate::sink::FileSink;
+use crate::Diagnostic;
+
+/// Something told about each step of a compilation, and able to fail it
+pub trait CompileObserver: Send + Sync {
+    /// Short identifier, e.g. `rustfmt`; part of the remote cache key
+    fn name(&self) -> &str;
+
+    /// Before `source` is parsed: problems to report, where an error stops the
+    /// compilation as a parse error does
+    fn pre_parse(&self, _source: &str) -> Vec<Diagnostic> {
+        Vec::new()
+    }
+
+    /// Once `program`, parsed from `source`, has been checked: problems to report with
+    /// those the checks found
+    fn post_validate(&self, _program: &Program, _source: &str) -> Vec<Diagnostic> {
+        Vec::new()
+    }
+
+    /// Before `target` is generated, or restored from the remote cache, for `output_dir`;
+    /// an error fails the target
+    fn pre_emit(&self, _target: &Target, _output_dir: &Path) -> Result<(), String> {
+        Ok(())
+    }
+
+    /// After `target` was generated into `files`, before they're synced into the output
+    /// directory; what the observer changes in `files` is what gets written there. An
+    /// error fails the target.
+    fn post_emit(&self, _target: &Target, _files: &dyn FileSink) -> Result<(), String> {
+        Ok(())
+    }
+}
+
+/// The observers of a compilation, called in order
+#[derive(Clone, Default)]
+pub struct Observers {
+    observers: Vec<Arc<dyn CompileObserver>>,
+}
+
+impl Observers {
+    /// No observers
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add `observer` after the ones already there
+    pub fn with(mut self, observer: impl CompileObserver + 'static) -> Self {
+        self.observers.push(Arc::new(observer));
+        self
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.observers.iter().map(|observer| observer.name())
+    }
+
+    pub(crate) fn pre_parse(&self, source: &str) -> Vec<Diagnostic> {
+        self.observers.iter().flat_map(|observer| observer.pre_parse(source)).collect()
+    }
+
+    pub(crate) fn post_validate(&self, program: &Program, source: &str) -> Vec<Diagnostic> {
+        self.observers.iter().flat_map(|observer| observer.post_validate(program, source)).collect()
+    }
+
+    /// Every observer's `pre_emit`, stopping at the first error
+    pub(crate) fn pre_emit(&self, target: &Target, output_dir: &Path) -> Result<(), String> {
+        self.observers.iter().try_for_each(|observer| observer.pre_emit(target, output_dir))
+    }
+
+    /// Every observer's `post_emit`, stopping at the first error
+    pub(crate) fn post_emit(&self, target: &Target, files: &dyn FileSink) -> Result<(), String> {
+        self.observers.iter().try_for_each(|observer| observer.post_emit(target, files))
+    }
+}
+
+impl fmt::Debug for Observers {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.names()).finish()
+    }
+}
@@ -0,0 +1,146 @@
+//! Transformations of the AST between parsing and lowering.
+//!
+//! A [`Pipeline`] runs [`Pass`]es over the parsed program in order, so the semantic
+//! model and the target compilers see one normalized form whatever shorthand the
+//! source used. [`Pipeline::standard`] holds the passes every compilation needs;
+//! tools can add their own with [`Pipeline::with`].
+
+use std::fmt;
+use std::sync::Arc;
+
+use serde_json::Value;
+use z_ast::{Element, Node};
+
+use crate::resolve::resolve_constants;
+use crate::template::expand_templates;
+
+/// What a pass can look at besides the tree
+pub struct PassContext<'a> {
+    /// The source the program was parsed from, for line numbers in errors
+    pub source: &'a str,
+    pub registry: &'a Value,
+}
+
+/// One rewrite of the program
+pub trait Pass: Send + Sync {
+    /// Short identifier, e.g. `constants`; part of the remote cache key
+    fn name(&self) -> &str;
+
+    /// Rewrite `program` in place; an error stops the pipeline
+    fn run(&self, program: &mut Element, context: &PassContext<'_>) -> Result<(), String>;
+}
+
+/// Passes run in order over a parsed program
+#[derive(Clone, Default)]
+pub struct Pipeline {
+    passes: Vec<Arc<dyn Pass>>,
+}
+
+impl Pipeline {
+    /// A pipeline with no passes
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Template expansion, constant substitution, then desugaring
+    pub fn standard() -> Self {
+        Self::new().with(ExpandTemplates).with(ResolveConstants).with(Desugar)
+    }
+
+    /// Add `pass` after the ones already in the pipeline
+    pub fn with(mut self, pass: impl Pass + 'static) -> Self {
+        self.passes.push(Arc::new(pass));
+        self
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.passes.iter().map(|pass| pass.name())
+    }
+
+    /// Run every pass, stopping at the first error
+    pub fn run(&self, program: &mut Element, context: &PassContext<'_>) -> Result<(), String> {
+        self.passes.iter().try_for_each(|pass| pass.run(program, context))
+    }
+}
+
+impl fmt::Debug for Pipeline {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.names()).finish()
+    }
+}
+
+/// Replaces each `use` of a template with its body; see [`expand_templates`]
+pub struct ExpandTemplates;
+
+impl Pass for ExpandTemplates {
+    fn name(&self) -> &str {
+        "templates"
+    }
+
+    fn run(&self, program: &mut Element, context: &PassContext<'_>) -> Result<(), String> {
+        expand_templates(program, context.source)
+    }
+}
+
+/// Substitutes `const` values for `$name` references; see [`resolve_constants`]
+pub struct ResolveConstants;
+
+impl Pass for ResolveConstants {
+    fn name(&self) -> &str {
+        "constants"
+    }
+
+    fn run(&self, program: &mut Element, context: &PassContext<'_>) -> Result<(), String> {
+        resolve_constants(program, context.source)
+    }
+}
+
+/// Drops keywords that only repeat what a namespace already implies, so
+/// `Routes { route home }` reads as `Routes { home }`. Applies to namespaces
+/// whose registry entry allows a single child type.
+pub struct Desugar;
+
+impl Pass for Desugar {
+    fn name(&self) -> &str {
+        "desugar"
+    }
+
+    fn run(&self, program: &mut Element, context: &PassContext<'_>) -> Result<(), String> {
+        desugar(program, None, context.registry);
+        Ok(())
+    }
+}
+
+/// `inherited` is the keyword implied for the children of a nested block, e.g. a route's subroutes
+fn desugar(element: &mut Element, inherited: Option<&str>, registry: &Value) {
+    let namespace = &registry["namespaces"][element.name.as_str()];
+    let implied = match namespace["childMode"].as_str() {
+        Some("single") => namespace["childType"].as_str(),
+        _ => inherited,
+    };
+    let nested = implied.filter(|implied| registry["childTypes"][*implied]["allowsNesting"] == true);
+    desugar_nodes(&mut element.children, implied, nested, registry);
+}
+
+fn desugar_nodes(nodes: &mut [Node], implied: Option<&str>, nested: Option<&str>, registry: &Value) {
+    for node in nodes {
+        match node {
+            Node::ChildLine { modifier, .. } => {
+                if implied.is_some() && modifier.as_deref() == implied {
+                    *modifier = None;
+                }
+            }
+            Node::Element(child) => {
+                let keyword = implied.and_then(|implied| {
+                    child.name.strip_prefix(implied).and_then(|rest| rest.strip_prefix(':'))
+                });
+                if let Some(name) = keyword {
+                    child.name = name.to_string();
+                }
+                desugar(child, nested, registry);
+            }
+            Node::Expansion { children, .. } => desugar_nodes(children, implied, nested, registry),
+            Node::KeyValue { .. } | Node::Const { .. } => {}
+        }
+    }
+}
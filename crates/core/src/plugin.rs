@@ -0,0 +1,251 @@
+//! Target types added by external compilers, without changing the compiler itself.
+//!
+//! A plugin is an executable declared in the `[plugins]` table of `z.toml`, naming the
+//! target type it compiles:
+//!
+//! ```toml
+//! [plugins]
+//! flutter = "z-flutter"              # on PATH
+//! django = "./tools/z-django"        # relative to the project
+//! ```
+//!
+//! It's run in the project's [sandbox](crate::sandbox) with one argument, the request,
+//! and speaks JSON: one document on stdin, one answer on stdout. Anything it prints on
+//! stderr is shown when it fails, which it says with a non-zero exit status.
+//!
+//! - `describe` gets `{"protocol": 1}` and answers with the target's registry entry and
+//!   any sections it adds: `{"target": {"description": ..., "allowedChildren": [...]},
+//!   "namespaces": {"Screens": {...}}}`. Both use the layout of `shared/registry.json`.
+//! - `generate` gets `{"protocol": 1, "target": "flutter:App", "options": {...}, "environment": "prod", "program": ...}`,
+//!   where `options` are the target's [options](crate::target_options) as strings,
+//!   `environment` is the [environment](crate::Environments) compiled for, or null, and
+//!   `program` is the whole program as a [`z_ast::format`] document, and answers with
+//!   the files of the project: `{"files": [{"path": "lib/main.dart", "contents": ...}]}`.
+//!
+//! Plugins are described once when they're loaded, so a broken plugin fails the build
+//! before anything is generated.
+
+use std::io::Write;
+use std::path::{Component, Path, PathBuf};
+use std::process::Stdio;
+
+use serde_json::{json, Map, Value};
+use z_ast::Element;
+use z_semantic::{Namespace, Target};
+
+use crate::compilers::output::Output;
+use crate::compilers::{Capabilities, TargetCompiler};
+use crate::sandbox::{Sandbox, SandboxPolicy};
+use crate::sink::{self, FileSink};
+use crate::style::CodeStyle;
+use crate::target_options::CompilerOptions;
+use crate::tr;
+
+/// Version of the requests and answers
+pub const PROTOCOL_VERSION: u64 = 1;
+
+/// A target type compiled by an external program
+#[derive(Debug, Clone, PartialEq)]
+pub struct Plugin {
+    /// Target type it compiles, e.g. `flutter`
+    pub kind: String,
+    /// Program to run: a name looked up on `PATH`, or a path
+    pub command: String,
+    /// The target's registry entry, as `describe` answered
+    pub target: Value,
+    /// Sections the plugin adds to the registry
+    pub namespaces: Map<String, Value>,
+}
+
+/// The plugins of a project, with the sandbox they run in
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Plugins {
+    pub plugins: Vec<Plugin>,
+    /// The project's sandbox settings, allowing every plugin's command
+    pub policy: SandboxPolicy,
+    /// Directory of `z.toml`, which plugins run in
+    pub project_dir: PathBuf,
+}
+
+impl Plugins {
+    /// The plugins in the `[plugins]` table of `config`, described. Plugins can't
+    /// replace the built-in target types.
+    pub fn from_config(config: &Value, project_dir: &Path) -> Result<Self, String> {
+        // `main.z` has an empty parent, which isn't a directory commands can run in
+        let project_dir = &Path::new(".").join(project_dir).canonicalize()
+            .map_err(|e| tr!("read-dir-failed", path = project_dir.display(), error = e))?;
+        let mut plugins = Self {
+            plugins: Vec::new(),
+            policy: SandboxPolicy::from_config(config, project_dir)?,
+            project_dir: project_dir.to_path_buf(),
+        };
+        let table = &config["plugins"];
+        if table.is_null() {
+            return Ok(plugins);
+        }
+        let entries = table.as_object().ok_or("[plugins] in z.toml must be a table")?;
+        let registry = crate::registry::built_in();
+
+        for (kind, command) in entries {
+            let command = command.as_str()
+                .ok_or_else(|| format!("plugins.{} in z.toml must be the command to run", kind))?;
+            if registry["targets"][kind.as_str()].is_object() || registry["targetAliases"][kind.as_str()].is_object() {
+                return Err(format!("plugins.{} in z.toml: '{}' is a built-in target type", kind, kind));
+            }
+            // Paths are the project's, wherever the compiler runs from
+            let command = if command.contains('/') || command.contains(std::path::MAIN_SEPARATOR) {
+                let path = project_dir.join(command);
+                path.canonicalize().unwrap_or(path).display().to_string()
+            } else {
+                command.to_string()
+            };
+            // Declaring a plugin is what lets the project run it
+            plugins.policy.commands.push(command.clone());
+            plugins.plugins.push(Plugin { kind: kind.clone(), command, target: Value::Null, namespaces: Map::new() });
+        }
+
+        let scratch = std::env::temp_dir().join(format!("z-plugin-{}", std::process::id()));
+        let described: Result<Vec<Plugin>, String> = plugins.plugins.iter()
+            .map(|plugin| plugins.describe(plugin, &scratch))
+            .collect();
+        let _ = std::fs::remove_dir_all(&scratch);
+        plugins.plugins = described?;
+        Ok(plugins)
+    }
+
+    /// The plugin compiling `kind`
+    pub fn get(&self, kind: &str) -> Option<&Plugin> {
+        self.plugins.iter().find(|plugin| plugin.kind == kind)
+    }
+
+    /// Add the plugins' targets and sections to `registry`; built-in sections win
+    pub(crate) fn extend(&self, registry: &mut Value) {
+        for plugin in &self.plugins {
+            registry["targets"][plugin.kind.as_str()] = plugin.target.clone();
+            if let Some(children) = registry["targets"]["workspace"]["allowedChildren"].as_array_mut() {
+                children.push(Value::String(plugin.kind.clone()));
+            }
+            for (name, namespace) in &plugin.namespaces {
+                if registry["namespaces"][name.as_str()].is_null() {
+                    registry["namespaces"][name.as_str()] = namespace.clone();
+                }
+            }
+        }
+    }
+
+    /// `plugin` with its answer to `describe`
+    fn describe(&self, plugin: &Plugin, scratch: &Path) -> Result<Plugin, String> {
+        let answer = self.run(plugin, "describe", &json!({ "protocol": PROTOCOL_VERSION }), scratch)?;
+        let target = &answer["target"];
+        if !target["description"].is_string() || !target["allowedChildren"].is_array() {
+            return Err(format!(
+                "plugin {} ({}): `describe` must answer with a target that has a description and allowedChildren",
+                plugin.kind, plugin.command,
+            ));
+        }
+        let mut target = target.clone();
+        if target["compiler"].is_null() {
+            target["compiler"] = Value::String(plugin.command.clone());
+        }
+        let namespaces = answer["namespaces"].as_object().cloned().unwrap_or_default();
+        Ok(Plugin { target, namespaces, ..plugin.clone() })
+    }
+
+    /// Send `request` to `plugin` and parse its answer; `output_dir` is the only place
+    /// it may write
+    fn run(&self, plugin: &Plugin, subcommand: &str, request: &Value, output_dir: &Path) -> Result<Value, String> {
+        let failed = |message: String| format!("plugin {} ({}): {}", plugin.kind, plugin.command, message);
+        let sandbox = Sandbox::new(self.policy.clone(), &self.project_dir, output_dir)?;
+        let mut child = sandbox.command(&plugin.command, &[subcommand], &self.project_dir)?
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|e| tr!("tool-exec-failed", tool = plugin.command, error = e))?;
+        if let Some(mut stdin) = child.stdin.take() {
+            // A plugin that doesn't read its request fails or answers anyway
+            let _ = stdin.write_all(request.to_string().as_bytes());
+        }
+        let output = child.wait_with_output().map_err(|e| failed(e.to_string()))?;
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(failed(match stderr.trim() {
+                "" => tr!("tool-failed", tool = subcommand),
+                message => message.to_string(),
+            }));
+        }
+        serde_json::from_slice(&output.stdout).map_err(|e| failed(format!("`{}` answered with invalid JSON: {}", subcommand, e)))
+    }
+}
+
+/// Compiles targets of a plugin's type by asking the plugin to `generate` them
+pub(crate) struct PluginCompiler {
+    plugin: Plugin,
+    plugins: Plugins,
+    /// The program as a `z-ast` JSON document
+    program: Value,
+    style: CodeStyle,
+}
+
+impl PluginCompiler {
+    pub(crate) fn new(plugin: &Plugin, plugins: &Plugins, program: &Element, style: &CodeStyle) -> Self {
+        let document = z_ast::format::to_json(program);
+        Self {
+            plugin: plugin.clone(),
+            plugins: plugins.clone(),
+            program: serde_json::from_str(&document).expect("z-ast documents are JSON"),
+            style: style.clone(),
+        }
+    }
+}
+
+impl TargetCompiler for PluginCompiler {
+    fn compile(&self, _target: &Target, _options: &CompilerOptions) -> Result<String, String> {
+        Err(format!("plugin {} only generates whole projects", self.plugin.kind))
+    }
+
+    fn target_name(&self) -> &str {
+        Path::new(&self.plugin.command).file_name().and_then(|name| name.to_str()).unwrap_or(&self.plugin.command)
+    }
+
+    fn file_extension(&self) -> &str {
+        ""
+    }
+
+    fn compile_to_directory(&self, target: &Target, options: &CompilerOptions, sink: &dyn FileSink) -> Option<Result<(), String>> {
+        let request = json!({
+            "protocol": PROTOCOL_VERSION,
+            "target": target.qualified_name(),
+            "options": options.values(),
+            "environment": options.environment(),
+            "program": self.program,
+        });
+        // The plugin may only write in the project, on disk or not
+        let answer = sink::on_disk(sink, |output_dir| self.plugins.run(&self.plugin, "generate", &request, output_dir));
+        Some(answer.and_then(|answer| {
+            let files = answer["files"].as_array()
+                .ok_or_else(|| format!("plugin {}: `generate` must answer with a list of files", self.plugin.kind))?;
+            let mut out = Output::new(sink, &self.style);
+            for file in files {
+                let (Some(path), Some(contents)) = (file["path"].as_str(), file["contents"].as_str()) else {
+                    return Err(format!("plugin {}: every generated file needs a path and contents", self.plugin.kind));
+                };
+                // Files stay inside the project
+                let relative = Path::new(path);
+                if path.is_empty() || !relative.components().all(|component| matches!(component, Component::Normal(_))) {
+                    return Err(format!("plugin {}: '{}' isn't a path inside the project", self.plugin.kind, path));
+                }
+                out.file(path, contents);
+            }
+            out.write()
+        }))
+    }
+
+    /// Plugins get the whole program, and generate from the sections they described
+    fn capabilities(&self, _options: &CompilerOptions) -> Option<Capabilities> {
+        let sections = self.plugin.target["allowedChildren"].as_array()
+            .map(|children| children.iter().filter_map(Value::as_str).map(Namespace::from_name).collect())
+            .unwrap_or_default();
+        Some(Capabilities { sections, declarations: true })
+    }
+}
@@ -0,0 +1,155 @@
+//! What a program generates, for editors to show next to the source.
+//!
+//! [`list_targets`] and [`generate_preview`] back the language server's
+//! `z/listTargets` and `z/generatePreview` requests. They work on programs with
+//! errors, as a program being edited usually has some; only a source that doesn't
+//! parse fails.
+
+use std::path::PathBuf;
+
+use serde::Serialize;
+use z_ast::{Element, Node};
+use z_semantic::Target;
+
+use crate::report::is_skipped;
+use crate::sink::MemorySink;
+use crate::{compiler_for, generate, line_number, load_registry, parse, CompileOptions};
+
+/// A target of the program, with where it's declared
+#[derive(Debug, Clone, Serialize)]
+pub struct TargetSummary {
+    pub kind: String,
+    pub name: String,
+    /// 1-based lines of the target's block
+    pub line: usize,
+    pub end_line: usize,
+    /// Namespaces of the target's sections its compiler generates nothing from
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub ignored: Vec<String>,
+}
+
+/// The files generated for one element of a program
+#[derive(Debug, Clone, Serialize)]
+pub struct Preview {
+    /// `kind:name` of the target the files belong to
+    pub target: String,
+    /// Name of the route, component, model or other declaration the files were picked
+    /// for; without one, every file of the target is included
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub element: Option<String>,
+    pub files: Vec<PreviewFile>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PreviewFile {
+    /// Relative to the target's output directory
+    pub path: PathBuf,
+    pub contents: String,
+}
+
+/// The targets declared in `source`, in source order
+pub fn list_targets(source: &str, options: &CompileOptions) -> Result<Vec<TargetSummary>, String> {
+    let registry = load_registry(options);
+    let (ast, _) = parse(source, options, &registry)?;
+    let (program, _) = z_semantic::build(&ast);
+    let ignored = |target: &Target| -> Vec<String> {
+        let Some(compiler) = compiler_for(&target.kind, &ast, options) else {
+            return Vec::new();
+        };
+        let allowed: Vec<&str> = registry["targets"][target.kind.as_str()]["allowedChildren"].as_array()
+            .map(|children| children.iter().filter_map(serde_json::Value::as_str).collect())
+            .unwrap_or_default();
+        let (target_options, _) = crate::target_options::resolve(target, &options.target_options, &registry);
+        let (sections, _) = crate::ignored(target, &program.targets, &allowed, &*compiler, &target_options);
+        sections.iter().map(|section| section.namespace.to_string()).collect()
+    };
+    Ok(program.targets.iter()
+        .map(|target| TargetSummary {
+            kind: target.kind.clone(),
+            name: target.name.clone(),
+            line: line_number(source, target.span.start),
+            end_line: line_number(source, target.span.end),
+            ignored: ignored(target),
+        })
+        .collect())
+}
+
+/// Generate the target around byte `offset` of `source`, or the one named `target`
+/// (as `name` or `kind:name`), and return the files for the declaration at `offset`
+pub fn generate_preview(source: &str, offset: usize, target: Option<&str>, options: &CompileOptions) -> Result<Preview, String> {
+    let registry = load_registry(options);
+    let (ast, _) = parse(source, options, &registry)?;
+    let (mut program, _) = z_semantic::build(&ast);
+    // Problems are the compile's to report; the preview only needs the identifiers
+    crate::identifiers::assign(&mut program);
+    let chosen = match target {
+        Some(name) => program.targets.iter().find(|target| target.name == name || target.qualified_name() == name)
+            .ok_or_else(|| format!("no target named '{}'", name))?,
+        // Outside every target, e.g. in a shared Schema, the first target stands in
+        None => program.targets.iter().find(|target| target.span.start <= offset && offset <= target.span.end)
+            .or(program.targets.first())
+            .ok_or_else(|| "the program has no targets".to_string())?,
+    };
+    let compiler = compiler_for(&chosen.kind, &ast, options)
+        .ok_or_else(|| format!("no compiler for target type '{}'", chosen.kind))?;
+
+    // Options it doesn't take are the compile's to report, like the identifiers
+    let (target_options, _) = crate::target_options::resolve(chosen, &options.target_options, &registry);
+    let generated = MemorySink::new();
+    generate(chosen, &program.targets, &*compiler, &target_options, &generated, options, &registry)?;
+    let files = files(generated);
+
+    let element = declaration_at(&ast, offset, chosen);
+    let files = match element.as_deref().map(|name| matching(&files, name)) {
+        Some(matching) if !matching.is_empty() => matching,
+        _ => files,
+    };
+    Ok(Preview { target: chosen.qualified_name(), element, files })
+}
+
+/// Every text file in `generated`
+fn files(generated: MemorySink) -> Vec<PreviewFile> {
+    generated.into_files().into_iter()
+        .filter(|(path, _)| !is_skipped(path))
+        .filter_map(|(path, contents)| Some(PreviewFile { path, contents: String::from_utf8(contents).ok()? }))
+        .collect()
+}
+
+/// Name of the innermost declaration around `offset`, such as a route, a component
+/// or a model; sections and the target itself don't count
+fn declaration_at(ast: &Element, offset: usize, target: &Target) -> Option<String> {
+    let mut found = None;
+    let mut nodes: Vec<&Node> = ast.items().collect();
+    while let Some(node) = nodes.iter().copied().find(|node| node.span().start <= offset && offset <= node.span().end) {
+        match node {
+            Node::Element(element) => {
+                // Not the target, nor the workspace around it
+                let encloses_target = element.span.start <= target.span.start && target.span.end <= element.span.end;
+                if let Some((_, name)) = element.name.split_once(':').filter(|_| !encloses_target) {
+                    found = Some(name.to_string());
+                }
+                nodes = element.items().collect();
+            }
+            Node::ChildLine { id, .. } => return Some(id.clone()),
+            _ => break,
+        }
+    }
+    found
+}
+
+/// Files named after `name`, or else mentioning it
+fn matching(files: &[PreviewFile], name: &str) -> Vec<PreviewFile> {
+    let normalize = |text: &str| text.chars().filter(|c| *c != '-' && *c != '_').collect::<String>().to_lowercase();
+    let wanted = normalize(name);
+    let named: Vec<PreviewFile> = files.iter()
+        .filter(|file| file.path.iter().any(|part| {
+            let part = part.to_string_lossy();
+            normalize(part.split('.').next().unwrap_or_default()) == wanted
+        }))
+        .cloned()
+        .collect();
+    if !named.is_empty() {
+        return named;
+    }
+    files.iter().filter(|file| file.contents.contains(name)).cloned().collect()
+}
@@ -0,0 +1,214 @@
+//! Publishing generated projects to a git branch, so downstream consumers can
+//! depend on the generated code without running the compiler.
+//!
+//! The branch holds one directory per target, as laid out in the output directory;
+//! with [`PublishOptions::split`] every target gets a branch of its own
+//! (`<branch>-<name>`) with the project at its root instead. Commits are built
+//! with git plumbing from the files on disk, so neither the working tree nor the
+//! index of the current repository is touched. A branch whose content hasn't
+//! changed gets no new commit.
+//!
+//! The same files as for the [remote cache](crate::remote_cache) are published:
+//! dependencies and build products (`node_modules`, `target`, ...) and the build
+//! report are left out.
+
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+use crate::emit::MANIFEST_FILE;
+use crate::report::{collect_outputs, FileEntry, REPORT_FILE};
+use crate::tr;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PublishOptions {
+    /// Branch to commit to, created if it doesn't exist yet
+    pub branch: String,
+    /// Repository to push the branch to; `None` commits to the current repository
+    pub repository: Option<String>,
+    /// Push the branch to `origin` after committing to the current repository
+    pub push: bool,
+    /// Names of the targets to publish; every generated project when empty
+    pub targets: Vec<String>,
+    /// One branch per target, `<branch>-<name>`, with the project at its root
+    pub split: bool,
+    /// Commit message; defaults to one naming the source commit
+    pub message: Option<String>,
+}
+
+/// Outcome for one published branch
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Published {
+    pub branch: String,
+    /// The new commit, or `None` if the branch already had this content
+    pub commit: Option<String>,
+    pub files: usize,
+    /// Where the branch was pushed, if anywhere
+    pub remote: Option<String>,
+}
+
+/// Commit the generated projects in `output_dir` according to `options`
+pub fn publish_output(output_dir: &Path, options: &PublishOptions) -> Result<Vec<Published>, String> {
+    let projects = projects(output_dir, &options.targets)?;
+    let message = options.message.clone().unwrap_or_else(default_message);
+
+    let (git, remote, scratch) = match &options.repository {
+        // Objects are built in a scratch repository and only the branch is pushed
+        Some(url) => {
+            let scratch = std::env::temp_dir().join(format!("z-publish-{}", std::process::id()));
+            git(&["init", "-q", "--bare", &scratch.to_string_lossy()], &[], None)?;
+            (Git { dir: scratch.clone(), scratch: true }, Some(url.clone()), Some(scratch))
+        }
+        None => {
+            let dir = git(&["rev-parse", "--absolute-git-dir"], &[], None)?;
+            (Git { dir: PathBuf::from(dir), scratch: false }, options.push.then(|| "origin".to_string()), None)
+        }
+    };
+
+    // Publish each unit in turn; the scratch repository goes away either way
+    let units: Vec<(String, PathBuf, Vec<FileEntry>)> = if options.split {
+        projects.into_iter()
+            .map(|name| {
+                let dir = output_dir.join(&name);
+                let files = files(&dir, Path::new(""));
+                (format!("{}-{}", options.branch, name), dir, files)
+            })
+            .collect()
+    } else {
+        let files = projects.iter().flat_map(|name| files(&output_dir.join(name), Path::new(name))).collect();
+        vec![(options.branch.clone(), output_dir.to_path_buf(), files)]
+    };
+    let result = units.into_iter()
+        .map(|(branch, dir, files)| git.publish(&branch, &dir, &files, &message, remote.as_deref()))
+        .collect();
+
+    if let Some(scratch) = scratch {
+        let _ = std::fs::remove_dir_all(scratch);
+    }
+    result
+}
+
+/// Names of the generated projects to publish: the target directories in `output_dir`
+fn projects(output_dir: &Path, targets: &[String]) -> Result<Vec<String>, String> {
+    let entries = std::fs::read_dir(output_dir)
+        .map_err(|e| tr!("read-dir-failed", path = output_dir.display(), error = e))?;
+    let mut projects: Vec<String> = entries.flatten()
+        .filter(|entry| entry.file_type().is_ok_and(|kind| kind.is_dir()))
+        .map(|entry| entry.file_name().to_string_lossy().into_owned())
+        .filter(|name| !name.starts_with('.'))
+        .collect();
+    projects.sort();
+
+    if let Some(missing) = targets.iter().find(|target| !projects.contains(target)) {
+        return Err(tr!("publish-unknown-target", name = missing, path = output_dir.display()));
+    }
+    if !targets.is_empty() {
+        projects.retain(|name| targets.contains(name));
+    }
+    if projects.is_empty() {
+        return Err(tr!("publish-no-output", path = output_dir.display()));
+    }
+    Ok(projects)
+}
+
+fn files(dir: &Path, relative: &Path) -> Vec<FileEntry> {
+    let mut files = Vec::new();
+    collect_outputs(dir, relative, &mut files);
+    files.retain(|file| file.path != Path::new(REPORT_FILE) && file.path.file_name() != Some(MANIFEST_FILE.as_ref()));
+    files
+}
+
+/// "Update generated output", naming the commit of the current repository if there is one
+fn default_message() -> String {
+    match git(&["rev-parse", "--short", "HEAD"], &[], None) {
+        Ok(commit) => format!("Update generated output\n\nGenerated from {}", commit),
+        Err(_) => "Update generated output".to_string(),
+    }
+}
+
+struct Git {
+    dir: PathBuf,
+    /// A throwaway repository that starts out without the branches it pushes
+    scratch: bool,
+}
+
+impl Git {
+    fn run(&self, args: &[&str], env: &[(&str, &Path)], stdin: Option<&[u8]>) -> Result<String, String> {
+        let git_dir = format!("--git-dir={}", self.dir.display());
+        let args: Vec<&str> = std::iter::once(git_dir.as_str()).chain(args.iter().copied()).collect();
+        git(&args, env, stdin)
+    }
+
+    fn publish(&self, branch: &str, dir: &Path, files: &[FileEntry], message: &str, remote: Option<&str>) -> Result<Published, String> {
+        let reference = format!("refs/heads/{}", branch);
+        if let Some(remote) = remote.filter(|_| self.scratch) {
+            // Start from what the remote has, if it has the branch yet
+            if !self.run(&["ls-remote", "--heads", remote, &reference], &[], None)?.is_empty() {
+                self.run(&["fetch", "-q", remote, &format!("+{}:{}", reference, reference)], &[], None)?;
+            }
+        }
+        let parent = self.run(&["rev-parse", "--verify", "-q", &format!("{}^{{commit}}", reference)], &[], None).ok();
+
+        let tree = self.write_tree(dir, files)?;
+        let unchanged = match &parent {
+            Some(parent) => self.run(&["rev-parse", &format!("{}^{{tree}}", parent)], &[], None)? == tree,
+            None => false,
+        };
+
+        let commit = if unchanged {
+            None
+        } else {
+            let mut args = vec!["commit-tree", tree.as_str(), "-m", message];
+            if let Some(parent) = &parent {
+                args.extend(["-p", parent.as_str()]);
+            }
+            let commit = self.run(&args, &[], None)?;
+            self.run(&["update-ref", &reference, &commit], &[], None)?;
+            Some(commit)
+        };
+
+        if let Some(remote) = remote {
+            self.run(&["push", "-q", remote, &format!("{}:{}", reference, reference)], &[], None)?;
+        }
+        Ok(Published { branch: branch.to_string(), commit, files: files.len(), remote: remote.map(str::to_string) })
+    }
+
+    /// Tree object holding `files` (relative to `dir`), built in a private index
+    fn write_tree(&self, dir: &Path, files: &[FileEntry]) -> Result<String, String> {
+        let index = self.dir.join(format!("z-publish-index-{}", std::process::id()));
+        let _ = std::fs::remove_file(&index);
+
+        let mut paths = Vec::new();
+        for file in files {
+            paths.extend(file.path.to_string_lossy().as_bytes());
+            paths.push(0);
+        }
+        let work_tree = format!("--work-tree={}", dir.display());
+        let env = [("GIT_INDEX_FILE", index.as_path())];
+        let tree = self.run(&[&work_tree, "add", "--force", "--pathspec-from-file=-", "--pathspec-file-nul"], &env, Some(&paths))
+            .and_then(|_| self.run(&["write-tree"], &env, None));
+        let _ = std::fs::remove_file(&index);
+        tree
+    }
+}
+
+/// Run git, returning its trimmed standard output
+pub(crate) fn git(args: &[&str], env: &[(&str, &Path)], stdin: Option<&[u8]>) -> Result<String, String> {
+    let mut command = Command::new("git");
+    command.args(args)
+        .envs(env.iter().copied())
+        .stdin(if stdin.is_some() { Stdio::piped() } else { Stdio::null() })
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+
+    let mut child = command.spawn().map_err(|e| tr!("tool-exec-failed", tool = "git", error = e))?;
+    if let Some(input) = stdin {
+        child.stdin.take().expect("piped stdin").write_all(input)
+            .map_err(|e| tr!("tool-exec-failed", tool = "git", error = e))?;
+    }
+    let output = child.wait_with_output().map_err(|e| tr!("tool-exec-failed", tool = "git", error = e))?;
+    if !output.status.success() {
+        return Err(tr!("tool-exec-failed", tool = "git", error = String::from_utf8_lossy(&output.stderr).trim()));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
@@ -0,0 +1,253 @@
+//! The registry of target types, sections and child types, and the registry files
+//! that change it.
+//!
+//! The built-in registry, `shared/registry.json`, is compiled in. Registry files
+//! layered over it can add target types and sections, describe them differently or
+//! add aliases, without rebuilding the compiler:
+//!
+//! - `registry.json` in the user's config directory (`~/.config/z`), for every project;
+//! - [`PROJECT_FILE`] next to the source, for one project.
+//!
+//! Later layers win. Objects are merged key by key, any other value (lists included)
+//! replaces the one before, and `null` removes a key. After each layer the merged
+//! registry is checked against [`SCHEMA`], plus the references between its parts, so
+//! a mistake is reported with the file that made it. A target type added this way
+//! still needs a compiler, built in or from a [plugin](crate::plugin).
+
+use std::path::{Path, PathBuf};
+
+use serde_json::{Map, Value};
+
+use crate::schema::{join_or, suggestion};
+use crate::tr;
+
+/// The registry compiled into the compiler
+pub const BUILT_IN: &str = include_str!("../../../shared/registry.json");
+
+/// JSON Schema every registry, merged, must satisfy
+pub const SCHEMA: &str = include_str!("../../../shared/registry.schema.json");
+
+/// Registry file of a project, next to its source
+pub const PROJECT_FILE: &str = "z-registry.json";
+
+/// Registry files read over the built-in registry, in the order they apply
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RegistryLayers {
+    pub layers: Vec<Layer>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Layer {
+    pub path: PathBuf,
+    pub contents: Value,
+}
+
+impl RegistryLayers {
+    /// The user's registry file and the one in `project_dir`, those that exist, checked
+    pub fn load(project_dir: &Path) -> Result<Self, String> {
+        let user = crate::sandbox::config_dir().map(|dir| dir.join("registry.json"));
+        let project = Path::new(".").join(project_dir).join(PROJECT_FILE);
+        let mut layers = Self::default();
+        let mut registry = built_in();
+        for path in user.into_iter().chain([project]) {
+            let text = match std::fs::read_to_string(&path) {
+                Ok(text) => text,
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+                Err(e) => return Err(tr!("read-failed", path = path.display(), error = e)),
+            };
+            let contents: Value = serde_json::from_str(&text)
+                .map_err(|e| format!("{}:{}:{}: {}", path.display(), e.line(), e.column(), e))?;
+            if !contents.is_object() {
+                return Err(format!("{}: a registry file holds a JSON object", path.display()));
+            }
+            merge(&mut registry, &contents);
+            let errors = validate(&registry);
+            if !errors.is_empty() {
+                return Err(format!("{} makes the registry invalid:\n  {}", path.display(), errors.join("\n  ")));
+            }
+            layers.layers.push(Layer { path, contents });
+        }
+        Ok(layers)
+    }
+
+    /// `registry` with every layer merged in
+    pub(crate) fn apply(&self, registry: &mut Value) {
+        for layer in &self.layers {
+            merge(registry, &layer.contents);
+        }
+    }
+}
+
+/// The built-in registry
+pub fn built_in() -> Value {
+    serde_json::from_str(crate::assets::get("registry.json").unwrap_or(BUILT_IN)).expect("Invalid registry.json")
+}
+
+/// Problems with `registry`, one line each naming where it is: against the schema
+/// first, then names that refer to nothing
+pub fn validate(registry: &Value) -> Vec<String> {
+    let schema: Value = serde_json::from_str(crate::assets::get("registry.schema.json").unwrap_or(SCHEMA))
+        .expect("Invalid registry.schema.json");
+    let mut errors = Vec::new();
+    check(registry, &schema, &schema, "", &mut errors);
+    if !errors.is_empty() {
+        return errors;
+    }
+
+    let targets = keys(&registry["targets"]);
+    let namespaces = keys(&registry["namespaces"]);
+    for (kind, target) in entries(&registry["targets"]) {
+        for child in target["allowedChildren"].as_array().into_iter().flatten().filter_map(Value::as_str) {
+            if !namespaces.contains(&child) && !targets.contains(&child) {
+                errors.push(format!(
+                    "targets.{}.allowedChildren: '{}' isn't a section in namespaces{}",
+                    kind, child, suggestion(child, &namespaces),
+                ));
+            }
+        }
+    }
+    for (alias, entry) in entries(&registry["targetAliases"]) {
+        let target = entry["target"].as_str().unwrap_or_default();
+        if targets.contains(&alias.as_str()) {
+            errors.push(format!("targetAliases.{}: '{}' is already a target type", alias, alias));
+        } else if !targets.contains(&target) {
+            errors.push(format!(
+                "targetAliases.{}.target: '{}' isn't a target type{}",
+                alias, target, suggestion(target, &targets),
+            ));
+        }
+    }
+    errors
+}
+
+/// A target type or section of a registry, as `z registry show` looks it up
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Entry<'a> {
+    /// A target type, by its own name even when looked up by an alias
+    Target(&'a str, &'a Value),
+    Section(&'a str, &'a Value),
+}
+
+/// What `name` is in `registry`: a target type, an alias of one, or a section
+pub fn lookup<'a>(registry: &'a Value, name: &str) -> Result<Entry<'a>, String> {
+    let kind = registry["targetAliases"][name]["target"].as_str().unwrap_or(name);
+    if let Some((kind, target)) = registry["targets"].as_object().and_then(|targets| targets.get_key_value(kind)) {
+        return Ok(Entry::Target(kind, target));
+    }
+    if let Some((namespace, section)) = registry["namespaces"].as_object().and_then(|namespaces| namespaces.get_key_value(name)) {
+        return Ok(Entry::Section(namespace, section));
+    }
+    let mut names = keys(&registry["targets"]);
+    names.extend(keys(&registry["targetAliases"]));
+    names.extend(keys(&registry["namespaces"]));
+    Err(tr!("registry-unknown", name = name, suggestion = suggestion(name, &names)))
+}
+
+/// Merge `layer` into `base`: objects key by key, `null` removing a key, anything
+/// else replacing what was there
+fn merge(base: &mut Value, layer: &Value) {
+    match (base, layer) {
+        (Value::Object(base), Value::Object(layer)) => {
+            for (key, value) in layer {
+                if value.is_null() {
+                    base.remove(key);
+                } else {
+                    merge(base.entry(key.clone()).or_insert(Value::Null), value);
+                }
+            }
+        }
+        (base, layer) => *base = layer.clone(),
+    }
+}
+
+/// Check `value` at `path` against `schema`, the subset of JSON Schema the registry
+/// schema uses: `$ref` into `$defs`, `type`, `enum`, `required`, `properties`,
+/// `additionalProperties` and `items`
+fn check(value: &Value, schema: &Value, root: &Value, path: &str, errors: &mut Vec<String>) {
+    if let Some(reference) = schema["$ref"].as_str() {
+        let name = reference.trim_start_matches("#/$defs/");
+        return check(value, &root["$defs"][name], root, path, errors);
+    }
+    let at = if path.is_empty() { "the registry".to_string() } else { path.to_string() };
+
+    let types: Vec<&str> = match &schema["type"] {
+        Value::String(name) => vec![name.as_str()],
+        Value::Array(names) => names.iter().filter_map(Value::as_str).collect(),
+        _ => Vec::new(),
+    };
+    let actual = type_name(value);
+    // Integers are numbers too
+    let matches = types.contains(&actual) || (actual == "integer" && types.contains(&"number"));
+    if !types.is_empty() && !matches {
+        let expected: Vec<String> = types.iter().map(|name| describe_type(name)).collect();
+        let expected: Vec<&str> = expected.iter().map(String::as_str).collect();
+        errors.push(format!("{}: expected {}, found {}", at, join_or(&expected), describe_type(actual)));
+        return;
+    }
+    if let Some(allowed) = schema["enum"].as_array() {
+        if !allowed.contains(value) {
+            let allowed: Vec<String> = allowed.iter().map(Value::to_string).collect();
+            let allowed: Vec<&str> = allowed.iter().map(String::as_str).collect();
+            errors.push(format!("{}: expected {}, found {}", at, join_or(&allowed), value));
+        }
+        return;
+    }
+
+    if let Value::Object(fields) = value {
+        for required in schema["required"].as_array().into_iter().flatten().filter_map(Value::as_str) {
+            if !fields.contains_key(required) {
+                errors.push(format!("{}: missing '{}'", at, required));
+            }
+        }
+        let properties = schema["properties"].as_object();
+        for (key, field) in fields {
+            let field_path = if path.is_empty() { key.clone() } else { format!("{}.{}", path, key) };
+            match (properties.and_then(|properties| properties.get(key)), &schema["additionalProperties"]) {
+                (Some(property), _) => check(field, property, root, &field_path, errors),
+                (None, Value::Bool(false)) => {
+                    let known: Vec<&str> = properties.map(|properties| properties.keys().map(String::as_str).collect()).unwrap_or_default();
+                    errors.push(format!("{}: unknown key '{}'{}", at, key, suggestion(key, &known)));
+                }
+                (None, additional) if additional.is_object() => check(field, additional, root, &field_path, errors),
+                (None, _) => {}
+            }
+        }
+    }
+    if let (Value::Array(items), item_schema) = (value, &schema["items"]) {
+        if item_schema.is_object() {
+            for (index, item) in items.iter().enumerate() {
+                check(item, item_schema, root, &format!("{}[{}]", at, index), errors);
+            }
+        }
+    }
+}
+
+fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(number) if number.is_i64() || number.is_u64() => "integer",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+fn describe_type(name: &str) -> String {
+    match name {
+        "array" => "a list".to_string(),
+        "object" => "an object".to_string(),
+        "null" => "null".to_string(),
+        "integer" => "an integer".to_string(),
+        other => format!("a {}", other),
+    }
+}
+
+fn keys(map: &Value) -> Vec<&str> {
+    map.as_object().map(|fields| fields.keys().map(String::as_str).collect()).unwrap_or_default()
+}
+
+fn entries(map: &Value) -> impl Iterator<Item = (&String, &Value)> {
+    map.as_object().into_iter().flat_map(Map::iter)
+}
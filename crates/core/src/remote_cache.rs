@@ -0,0 +1,255 @@
+//! Remote cache of generated targets, shared by CI and teammates.
+//!
+//! Each entry holds every file of one generated target and is stored under a key
+//! derived from everything that determines that output: the compiler version, the
+//! target, its [fingerprint](crate::hash::target_fingerprint), the registry
+//! and the compile options. Entries carry a manifest with the SHA-256 of each file,
+//! checked before anything is written; a corrupt entry is treated as a miss.
+//!
+//! Backends:
+//! - `http://` / `https://` base URLs, read with `GET` and written with `PUT`
+//!   (a bearer token can be supplied through `Z_REMOTE_CACHE_TOKEN`)
+//! - `s3://bucket/prefix`, signed with the usual `AWS_*` environment variables
+//! - `file://` URLs or plain paths, e.g. a shared network drive
+//!
+//! HTTP transfers go through `curl`, the same way project setup shells out to
+//! `cargo` and `pnpm`.
+
+use std::fs;
+use std::io::Write;
+use std::path::{Component, Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+use crate::hash::sha256_hex;
+use crate::report::{is_skipped, FileEntry};
+use crate::sink::FileSink;
+use crate::tr;
+
+const MAGIC: &[u8] = b"ZCACHE1\n";
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum CacheMode {
+    /// Restore hits but never upload; the safe default for developer machines
+    #[default]
+    Read,
+    /// Restore hits and upload freshly generated targets, typically from CI
+    ReadWrite,
+}
+
+impl FromStr for CacheMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "read" | "read-only" => Ok(CacheMode::Read),
+            "read-write" => Ok(CacheMode::ReadWrite),
+            _ => Err(format!("invalid cache mode '{}' (expected 'read' or 'read-write')", s)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Backend {
+    Http(String),
+    S3 { bucket: String, prefix: String },
+    Directory(PathBuf),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RemoteCache {
+    backend: Backend,
+    pub mode: CacheMode,
+}
+
+impl RemoteCache {
+    /// Parse a cache location: an `http(s)://` base URL, `s3://bucket/prefix`, or a directory
+    pub fn new(location: &str, mode: CacheMode) -> Result<Self, String> {
+        let backend = if location.starts_with("http://") || location.starts_with("https://") {
+            Backend::Http(location.trim_end_matches('/').to_string())
+        } else if let Some(rest) = location.strip_prefix("s3://") {
+            let (bucket, prefix) = rest.split_once('/').unwrap_or((rest, ""));
+            if bucket.is_empty() {
+                return Err(format!("invalid S3 cache location '{}' (expected s3://bucket/prefix)", location));
+            }
+            Backend::S3 { bucket: bucket.to_string(), prefix: prefix.trim_matches('/').to_string() }
+        } else {
+            Backend::Directory(PathBuf::from(location.strip_prefix("file://").unwrap_or(location)))
+        };
+        Ok(Self { backend, mode })
+    }
+
+    /// A cache kept in `dir`
+    pub(crate) fn directory(dir: PathBuf, mode: CacheMode) -> Self {
+        Self { backend: Backend::Directory(dir), mode }
+    }
+
+    /// Restore the entry for `key` into `sink`. `Ok(false)` is a miss.
+    pub(crate) fn restore(&self, key: &str, sink: &dyn FileSink) -> Result<bool, String> {
+        let Some(entry) = self.fetch(key)? else {
+            return Ok(false);
+        };
+        let files = unpack(&entry, key)?;
+
+        for (path, contents) in files {
+            sink.create_dir(path.parent().unwrap_or(Path::new("")))?;
+            sink.write(&path, contents)?;
+        }
+        Ok(true)
+    }
+
+    /// Upload everything generated in `sink` under `key`
+    pub(crate) fn store(&self, key: &str, sink: &dyn FileSink) -> Result<(), String> {
+        self.put(key, &pack(key, sink)?)
+    }
+
+    fn fetch(&self, key: &str) -> Result<Option<Vec<u8>>, String> {
+        match &self.backend {
+            Backend::Directory(dir) => match fs::read(dir.join(key)) {
+                Ok(entry) => Ok(Some(entry)),
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+                Err(e) => Err(tr!("read-failed", path = dir.join(key).display(), error = e)),
+            },
+            Backend::Http(_) | Backend::S3 { .. } => {
+                let download = std::env::temp_dir().join(format!("z-cache-{}-{}", std::process::id(), key));
+                let status = self.curl(key, &["-o".as_ref(), download.as_os_str(), "-w".as_ref(), "%{http_code}".as_ref()])?;
+                let entry = fs::read(&download).ok();
+                let _ = fs::remove_file(&download);
+                match status.as_str() {
+                    "200" => Ok(entry),
+                    // S3 answers 403 rather than 404 for missing keys without list permission
+                    "404" | "403" => Ok(None),
+                    code => Err(tr!("cache-http-failed", code = code)),
+                }
+            }
+        }
+    }
+
+    fn put(&self, key: &str, entry: &[u8]) -> Result<(), String> {
+        match &self.backend {
+            Backend::Directory(dir) => {
+                fs::create_dir_all(dir).map_err(|e| tr!("create-dir-failed", path = dir.display(), error = e))?;
+                // Write then rename so readers never see half an entry
+                let partial = dir.join(format!(".{}.{}", key, std::process::id()));
+                fs::write(&partial, entry).map_err(|e| tr!("write-failed", path = partial.display(), error = e))?;
+                fs::rename(&partial, dir.join(key)).map_err(|e| tr!("write-failed", path = dir.join(key).display(), error = e))
+            }
+            Backend::Http(_) | Backend::S3 { .. } => {
+                let upload = std::env::temp_dir().join(format!("z-cache-{}-{}.upload", std::process::id(), key));
+                fs::write(&upload, entry).map_err(|e| tr!("write-failed", path = upload.display(), error = e))?;
+                let status = self.curl(key, &["-T".as_ref(), upload.as_os_str(), "-o".as_ref(), "/dev/null".as_ref(), "-w".as_ref(), "%{http_code}".as_ref()]);
+                let _ = fs::remove_file(&upload);
+                match status?.as_str() {
+                    "200" | "201" | "204" => Ok(()),
+                    code => Err(tr!("cache-http-failed", code = code)),
+                }
+            }
+        }
+    }
+
+    /// Run curl against the URL for `key`, returning what it printed (the `-w` format).
+    /// Credentials go through a config on stdin so they never show up in `ps`.
+    fn curl(&self, key: &str, args: &[&std::ffi::OsStr]) -> Result<String, String> {
+        let mut config = String::new();
+        let url = match &self.backend {
+            Backend::Http(base) => {
+                if let Ok(token) = std::env::var("Z_REMOTE_CACHE_TOKEN") {
+                    config.push_str(&format!("header = \"Authorization: Bearer {}\"\n", token));
+                }
+                format!("{}/{}", base, key)
+            }
+            Backend::S3 { bucket, prefix } => {
+                let region = std::env::var("AWS_REGION")
+                    .or_else(|_| std::env::var("AWS_DEFAULT_REGION"))
+                    .unwrap_or_else(|_| "us-east-1".to_string());
+                let id = std::env::var("AWS_ACCESS_KEY_ID").unwrap_or_default();
+                let secret = std::env::var("AWS_SECRET_ACCESS_KEY").unwrap_or_default();
+                config.push_str(&format!("aws-sigv4 = \"aws:amz:{}:s3\"\nuser = \"{}:{}\"\n", region, id, secret));
+                if let Ok(token) = std::env::var("AWS_SESSION_TOKEN") {
+                    config.push_str(&format!("header = \"x-amz-security-token: {}\"\n", token));
+                }
+                let key = if prefix.is_empty() { key.to_string() } else { format!("{}/{}", prefix, key) };
+                format!("https://{}.s3.{}.amazonaws.com/{}", bucket, region, key)
+            }
+            Backend::Directory(_) => unreachable!("directory caches don't use curl"),
+        };
+
+        let mut child = Command::new("curl")
+            .args(["-sS", "-K", "-"])
+            .args(args)
+            .arg(url)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|e| tr!("tool-exec-failed", tool = "curl", error = e))?;
+        child.stdin.take().expect("piped stdin").write_all(config.as_bytes())
+            .map_err(|e| tr!("tool-exec-failed", tool = "curl", error = e))?;
+        let output = child.wait_with_output().map_err(|e| tr!("tool-exec-failed", tool = "curl", error = e))?;
+        if !output.status.success() {
+            return Err(tr!("tool-exec-failed", tool = "curl", error = String::from_utf8_lossy(&output.stderr).trim()));
+        }
+        Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct Manifest {
+    key: String,
+    files: Vec<FileEntry>,
+}
+
+/// Entry layout: magic line, one line of JSON manifest, then the file contents back to back
+fn pack(key: &str, sink: &dyn FileSink) -> Result<Vec<u8>, String> {
+    let mut files = Vec::new();
+    let mut contents = Vec::new();
+    for path in sink.files().into_iter().filter(|path| !is_skipped(path)) {
+        let file = sink.read(&path)?.unwrap_or_default();
+        files.push(FileEntry::new(path, &file));
+        contents.extend(file);
+    }
+
+    let manifest = Manifest { key: key.to_string(), files };
+    let mut entry = MAGIC.to_vec();
+    entry.extend(serde_json::to_vec(&manifest).expect("manifest is serializable"));
+    entry.push(b'\n');
+    entry.extend(contents);
+    Ok(entry)
+}
+
+/// Split an entry back into files, verifying the key, every path and every hash
+fn unpack<'a>(entry: &'a [u8], key: &str) -> Result<Vec<(PathBuf, &'a [u8])>, String> {
+    let corrupt = |reason: &str| tr!("cache-corrupt", reason = reason);
+
+    let body = entry.strip_prefix(MAGIC).ok_or_else(|| corrupt("unknown format"))?;
+    let newline = body.iter().position(|&b| b == b'\n').ok_or_else(|| corrupt("missing manifest"))?;
+    let manifest: Manifest = serde_json::from_slice(&body[..newline]).map_err(|e| corrupt(&e.to_string()))?;
+    if manifest.key != key {
+        return Err(corrupt("key mismatch"));
+    }
+
+    let mut contents = &body[newline + 1..];
+    let mut files = Vec::with_capacity(manifest.files.len());
+    for file in manifest.files {
+        let safe = file.path.components().all(|component| matches!(component, Component::Normal(_)));
+        if !safe {
+            return Err(corrupt(&format!("unsafe path {}", file.path.display())));
+        }
+        if (contents.len() as u64) < file.bytes {
+            return Err(corrupt("truncated"));
+        }
+        let (data, rest) = contents.split_at(file.bytes as usize);
+        if sha256_hex(data) != file.sha256 {
+            return Err(corrupt(&format!("hash mismatch for {}", file.path.display())));
+        }
+        files.push((file.path, data));
+        contents = rest;
+    }
+
+    if !contents.is_empty() {
+        return Err(corrupt("trailing data"));
+    }
+    Ok(files)
+}
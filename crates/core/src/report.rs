@@ -0,0 +1,206 @@
+//! Machine-readable summary of a build, written as `z-report.json`.
+//!
+//! The schema is documented in `doc/compile-report.md`. Bump [`SCHEMA_VERSION`]
+//! whenever a field is removed or changes meaning; adding fields is compatible.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::diagnostic::Diagnostic;
+use crate::emit::MANIFEST_FILE;
+use crate::hash::sha256_hex;
+use crate::sink::FileSink;
+
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// Default file name, written next to the generated targets
+pub const REPORT_FILE: &str = "z-report.json";
+
+/// Directories inside generated projects that hold tool state rather than compiler output
+const SKIPPED_DIRS: [&str; 4] = [".git", "node_modules", "target", ".next"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Status {
+    Success,
+    Failed,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CompileReport {
+    pub schema_version: u32,
+    pub compiler_version: &'static str,
+    pub status: Status,
+    pub duration_ms: u64,
+    pub inputs: Vec<FileEntry>,
+    pub targets: Vec<TargetReport>,
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+/// A file read or written by the build, with its content hash
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FileEntry {
+    pub path: PathBuf,
+    pub sha256: String,
+    pub bytes: u64,
+}
+
+/// A file a dry run would have written or deleted
+#[derive(Debug, Clone, Serialize)]
+pub struct PlannedChange {
+    pub path: PathBuf,
+    pub change: Change,
+    /// Unified diff from the current contents to the new ones; `None` for binary files
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub diff: Option<String>,
+}
+
+/// A [hook](crate::hooks) run once its target was generated, with what it printed
+#[derive(Debug, Clone, Serialize)]
+pub struct HookRun {
+    pub command: String,
+    /// None when it was killed by a signal
+    pub exit_code: Option<i32>,
+    pub duration_ms: u64,
+    pub stdout: String,
+    pub stderr: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Change {
+    Create,
+    Update,
+    Delete,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TargetReport {
+    /// Target type from the registry, e.g. `next`
+    pub kind: String,
+    pub name: String,
+    pub status: Status,
+    pub duration_ms: u64,
+    /// Where the target was generated, or its directory in the archive the targets were packed into
+    pub output_dir: PathBuf,
+    /// Whether the files were restored from the remote or user cache instead of generated
+    pub cached: bool,
+    /// Whether generation was skipped because nothing the target depends on changed
+    /// since the last compile
+    pub unchanged: bool,
+    /// Files under `output_dir`, relative to it, sorted by path
+    pub outputs: Vec<FileEntry>,
+    /// In a dry run, the files generating the target would create, update or delete
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub planned: Vec<PlannedChange>,
+    /// The commands run in `output_dir` once the target was generated, in order
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub hooks: Vec<HookRun>,
+    /// What the target type is for, from the registry
+    #[serde(skip)]
+    pub description: String,
+    /// Name of the compiler that generated the target, e.g. `NextJS`
+    #[serde(skip)]
+    pub compiler: String,
+}
+
+impl Default for CompileReport {
+    fn default() -> Self {
+        Self {
+            schema_version: SCHEMA_VERSION,
+            compiler_version: env!("CARGO_PKG_VERSION"),
+            status: Status::Success,
+            duration_ms: 0,
+            inputs: Vec::new(),
+            targets: Vec::new(),
+            diagnostics: Vec::new(),
+        }
+    }
+}
+
+impl CompileReport {
+    /// Record a source file the build read
+    pub fn add_input(&mut self, path: &Path, contents: &[u8]) {
+        self.inputs.push(FileEntry::new(path.to_path_buf(), contents));
+    }
+
+    pub fn write(&self, path: &Path) -> std::io::Result<()> {
+        let json = serde_json::to_string_pretty(self).map_err(std::io::Error::other)?;
+        fs::write(path, json + "\n")
+    }
+}
+
+impl FileEntry {
+    pub fn new(path: PathBuf, contents: &[u8]) -> Self {
+        Self { path, sha256: sha256_hex(contents), bytes: contents.len() as u64 }
+    }
+}
+
+impl TargetReport {
+    pub(crate) fn new(kind: &str, name: &str, output_dir: PathBuf, status: Status, duration: Duration) -> Self {
+        let mut outputs = Vec::new();
+        collect_outputs(&output_dir, Path::new(""), &mut outputs);
+        outputs.retain(|file| file.path != Path::new(MANIFEST_FILE));
+        Self::with_outputs(kind, name, output_dir, outputs, status, duration)
+    }
+
+    /// The report of a target whose files are in `files` rather than in `output_dir`,
+    /// e.g. one packed into an archive
+    pub(crate) fn of_files(kind: &str, name: &str, output_dir: PathBuf, files: &dyn FileSink, status: Status, duration: Duration) -> Self {
+        let outputs = files.files().into_iter()
+            .filter(|path| !is_skipped(path))
+            .filter_map(|path| files.read(&path).ok().flatten().map(|contents| FileEntry::new(path, &contents)))
+            .collect();
+        Self::with_outputs(kind, name, output_dir, outputs, status, duration)
+    }
+
+    fn with_outputs(kind: &str, name: &str, output_dir: PathBuf, mut outputs: Vec<FileEntry>, status: Status, duration: Duration) -> Self {
+        outputs.sort_by(|a, b| a.path.cmp(&b.path));
+        Self {
+            kind: kind.to_string(),
+            name: name.to_string(),
+            status,
+            duration_ms: duration.as_millis() as u64,
+            output_dir,
+            cached: false,
+            unchanged: false,
+            outputs,
+            planned: Vec::new(),
+            hooks: Vec::new(),
+            description: String::new(),
+            compiler: String::new(),
+        }
+    }
+}
+
+/// Whether `path`, relative to a project, is in a directory of version control,
+/// dependencies or build output, which aren't part of what's generated
+pub(crate) fn is_skipped(path: &Path) -> bool {
+    path.components().any(|component| SKIPPED_DIRS.iter().any(|skipped| component.as_os_str() == *skipped))
+}
+
+pub(crate) fn collect_outputs(dir: &Path, relative: &Path, outputs: &mut Vec<FileEntry>) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+
+    for entry in entries.flatten() {
+        let name = entry.file_name();
+        let path = entry.path();
+        let relative = relative.join(&name);
+        match entry.file_type() {
+            Ok(kind) if kind.is_dir() && !SKIPPED_DIRS.iter().any(|skipped| name == *skipped) => {
+                collect_outputs(&path, &relative, outputs);
+            }
+            Ok(kind) if kind.is_file() => {
+                if let Ok(contents) = fs::read(&path) {
+                    outputs.push(FileEntry::new(relative, &contents));
+                }
+            }
+            _ => {}
+        }
+    }
+}
@@ -0,0 +1,122 @@
+//! Substitution of `const` bindings into the rest of the program.
+//!
+//! A `const name = value` is visible to everything after it in the same block,
+//! nested blocks included, and may be shadowed by a later or inner `const`.
+//! Every `$name` outside a string literal is replaced by the constant's raw value
+//! text. The `Const` nodes stay in the tree, so compilers that want to emit
+//! named constants (e.g. a shared theme module) can still find them.
+//!
+//! Template definitions are skipped: their bodies are resolved where they're
+//! expanded, in the scope of the call site.
+
+use std::collections::HashMap;
+
+use z_ast::{Element, Node, Span};
+
+use crate::line_number;
+
+/// Resolve `$name` references throughout `program`, parsed from `source`
+pub fn resolve_constants(program: &mut Element, source: &str) -> Result<(), String> {
+    let mut scopes = vec![HashMap::new()];
+    for child in &mut program.children {
+        if matches!(child, Node::Element(element) if element.name.starts_with("template:")) {
+            continue;
+        }
+        resolve_node(child, &mut scopes, source)?;
+    }
+    Ok(())
+}
+
+fn resolve_block(element: &mut Element, scopes: &mut Vec<HashMap<String, String>>, source: &str) -> Result<(), String> {
+    scopes.push(HashMap::new());
+    let result = element.children.iter_mut().try_for_each(|child| resolve_node(child, scopes, source));
+    scopes.pop();
+    result
+}
+
+fn resolve_node(node: &mut Node, scopes: &mut Vec<HashMap<String, String>>, source: &str) -> Result<(), String> {
+    let span = node.span();
+    let resolve = |text: &mut String, scopes: &[HashMap<String, String>]| -> Result<(), String> {
+        *text = substitute(text, scopes).map_err(|name| undefined(&name, span, source))?;
+        Ok(())
+    };
+
+    match node {
+        Node::Const { name, value, .. } => {
+            resolve(value, scopes)?;
+            let scope = scopes.last_mut().expect("block scope");
+            scope.insert(name.clone(), value.clone());
+        }
+        Node::KeyValue { value, .. } => resolve(value, scopes)?,
+        Node::ChildLine { signature, annotations, .. } => {
+            if let Some(signature) = signature {
+                resolve(signature, scopes)?;
+            }
+            for args in annotations.iter_mut().filter_map(|annotation| annotation.args.as_mut()) {
+                resolve(args, scopes)?;
+            }
+        }
+        Node::Element(element) => {
+            if let Some(signature) = &mut element.signature {
+                resolve(signature, scopes)?;
+            }
+            for args in element.annotations.iter_mut().filter_map(|annotation| annotation.args.as_mut()) {
+                resolve(args, scopes)?;
+            }
+            resolve_block(element, scopes, source)?;
+        }
+        // Expanded nodes belong to the block the template was used in
+        Node::Expansion { children, .. } => {
+            children.iter_mut().try_for_each(|child| resolve_node(child, scopes, source))?;
+        }
+    }
+    Ok(())
+}
+
+/// Replace `$name` references outside string literals; unknown names are returned as the error
+fn substitute(text: &str, scopes: &[HashMap<String, String>]) -> Result<String, String> {
+    if !text.contains('$') {
+        return Ok(text.to_string());
+    }
+
+    let bytes = text.as_bytes();
+    let mut out = String::with_capacity(text.len());
+    let mut quote = None;
+    let mut copied = 0;
+    let mut i = 0;
+
+    while i < bytes.len() {
+        let b = bytes[i];
+        match quote {
+            Some(q) if b == q => quote = None,
+            Some(_) if b == b'\\' => i += 1,
+            Some(_) => {}
+            None if matches!(b, b'"' | b'\'' | b'`') => quote = Some(b),
+            None if b == b'$' => {
+                let name_len = bytes[i + 1..].iter()
+                    .take_while(|c| c.is_ascii_alphanumeric() || **c == b'_')
+                    .count();
+                if name_len > 0 && !bytes[i + 1].is_ascii_digit() {
+                    let name = &text[i + 1..i + 1 + name_len];
+                    let value = scopes.iter().rev()
+                        .find_map(|scope| scope.get(name))
+                        .ok_or_else(|| name.to_string())?;
+                    out.push_str(&text[copied..i]);
+                    out.push_str(value);
+                    i += 1 + name_len;
+                    copied = i;
+                    continue;
+                }
+            }
+            None => {}
+        }
+        i += 1;
+    }
+
+    out.push_str(&text[copied.min(text.len())..]);
+    Ok(out)
+}
+
+fn undefined(name: &str, span: Span, source: &str) -> String {
+    format!("line {}: undefined constant '${}'", line_number(source, span.start), name)
+}
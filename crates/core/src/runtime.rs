@@ -0,0 +1,102 @@
+//! The JavaScript runtime a web target's project runs on.
+//!
+//! Projects default to Node.js with pnpm. The `runtime: bun` option generates a project for Bun instead: a `bunfig.toml`, scripts that start
+//! Next.js under Bun, and no pnpm files. The registry entry of the target can
+//! give each runtime its own `toolchain` under `runtimes`, which the dev
+//! environments use in place of the default one.
+//!
+//! Generated Bun projects are recognized by their `bunfig.toml`, so commands run
+//! in them later go through `bun` and `bunx` rather than `pnpm`.
+
+use std::fmt;
+use std::path::Path;
+use std::str::FromStr;
+
+use serde_json::Value;
+use crate::store::read_json;
+use crate::target_options::CompilerOptions;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Runtime {
+    /// Node.js, with pnpm managing packages
+    #[default]
+    Node,
+    /// Bun as runtime, package manager and script runner
+    Bun,
+}
+
+impl FromStr for Runtime {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "node" => Ok(Runtime::Node),
+            "bun" => Ok(Runtime::Bun),
+            _ => Err(format!("invalid runtime '{}' (expected 'node' or 'bun')", s)),
+        }
+    }
+}
+
+impl fmt::Display for Runtime {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Runtime::Node => "node",
+            Runtime::Bun => "bun",
+        })
+    }
+}
+
+impl Runtime {
+    /// The runtime the `runtime` option picks
+    pub fn of(options: &CompilerOptions) -> Result<Self, String> {
+        options.parse("runtime")
+    }
+
+    /// The runtime a generated project in `project_dir` was written for
+    pub fn detect(project_dir: &Path) -> Self {
+        if project_dir.join("bunfig.toml").exists() {
+            Runtime::Bun
+        } else {
+            Runtime::Node
+        }
+    }
+
+    /// Program that installs packages and runs scripts
+    pub fn package_manager(self) -> &'static str {
+        match self {
+            Runtime::Node => "pnpm",
+            Runtime::Bun => "bun",
+        }
+    }
+
+    /// The toolchain for this runtime in a registry target entry, falling back to
+    /// the target's default one
+    pub fn toolchain(self, target_info: &Value) -> Value {
+        let specific = &target_info["runtimes"][self.to_string()]["toolchain"];
+        if specific.is_object() {
+            specific.clone()
+        } else {
+            target_info["toolchain"].clone()
+        }
+    }
+
+    /// Program and arguments that run `args` as a project command, like `dev` or
+    /// `add zod`, in the project in `project_dir`. pnpm takes them as they are.
+    /// With Bun, the project's scripts go through `bun run`, since Bun's own `build`
+    /// and `test` would shadow them, and anything that's neither a script nor a
+    /// package command runs as a package binary with `bunx`.
+    pub fn command(self, project_dir: &Path, args: &[String]) -> (&'static str, Vec<String>) {
+        if self == Runtime::Node {
+            return ("pnpm", args.to_vec());
+        }
+        let package = read_json(&project_dir.join("package.json")).unwrap_or_default();
+        match args.first().map(String::as_str) {
+            None => ("bun", vec!["install".to_string()]),
+            Some("install" | "add" | "remove" | "update" | "outdated" | "run" | "x") => ("bun", args.to_vec()),
+            Some(script) if package["scripts"].get(script).is_some() => {
+                ("bun", std::iter::once("run".to_string()).chain(args.iter().cloned()).collect())
+            }
+            Some(_) => ("bunx", args.to_vec()),
+        }
+    }
+}
@@ -0,0 +1,370 @@
+//! Running project-supplied commands, such as hooks and plugin compilers, with
+//! constrained capabilities.
+//!
+//! Building a project shouldn't mean trusting it. Commands it brings run in a
+//! [`Sandbox`] that can read the project and the system's toolchains, write only the
+//! output directory and a private temporary directory, see only a few environment
+//! variables, and not reach the network. The `[sandbox]` table of `z.toml` widens that:
+//!
+//! ```toml
+//! [sandbox]
+//! commands = ["pnpm", "prettier"]  # programs the project may run
+//! read = ["../shared", "~/.rustup"] # more readable paths, relative to the project
+//! write = ["../.cache"]             # more writable paths
+//! env = ["NPM_TOKEN"]               # environment variables passed through
+//! network = true                    # reach the network
+//! ```
+//!
+//! The project wrote that table itself, so anything past `commands` only takes effect
+//! once the user approves it with `z trust`. Approval is recorded per project for
+//! exactly the permissions shown; changing them needs approving again.
+//!
+//! Isolation uses bubblewrap (`bwrap`) on Linux and `sandbox-exec` on macOS. Where
+//! neither is available nothing runs, unless `Z_SANDBOX=off` says to run commands
+//! unconfined, with only the environment filtered.
+
+use std::path::{Component, Path, PathBuf};
+use std::process::Command;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use serde_json::Value;
+
+use crate::hash::sha256_hex;
+use crate::store::{read_json, write_json};
+
+/// Environment variable that turns isolation off when set to `off`
+pub const SANDBOX_ENV: &str = "Z_SANDBOX";
+
+/// Variables every sandboxed command sees, when they're set
+const DEFAULT_ENV: &[&str] = &["PATH", "LANG", "LC_ALL", "TERM", "TZ", "CI"];
+
+/// Read-only system directories: binaries, libraries, certificates and toolchains
+const SYSTEM_PATHS: &[&str] = &[
+    "/usr", "/bin", "/sbin", "/lib", "/lib32", "/lib64", "/etc", "/opt", "/nix", "/System", "/Library", "/private/etc",
+];
+
+/// What commands in the sandbox may do beyond the defaults
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SandboxPolicy {
+    /// Programs that may be run, by name
+    pub commands: Vec<String>,
+    /// Readable paths besides the project, the output directory and system directories
+    pub read: Vec<PathBuf>,
+    /// Writable paths besides the output directory
+    pub write: Vec<PathBuf>,
+    /// Environment variables passed through besides `PATH`, `LANG` and the like
+    pub env: Vec<String>,
+    pub network: bool,
+}
+
+impl SandboxPolicy {
+    /// The policy of the `[sandbox]` table in `config`, with paths resolved against
+    /// `project_dir`
+    pub fn from_config(config: &Value, project_dir: &Path) -> Result<Self, String> {
+        let table = &config["sandbox"];
+        if table.is_null() {
+            return Ok(Self::default());
+        }
+        let Some(fields) = table.as_object() else {
+            return Err("[sandbox] in z.toml must be a table".to_string());
+        };
+        let strings = |key: &str| -> Result<Vec<String>, String> {
+            match &table[key] {
+                Value::Null => Ok(Vec::new()),
+                Value::Array(items) => items.iter()
+                    .map(|item| item.as_str().map(str::to_string))
+                    .collect::<Option<_>>()
+                    .ok_or_else(|| format!("sandbox.{} in z.toml must be a list of strings", key)),
+                _ => Err(format!("sandbox.{} in z.toml must be a list of strings", key)),
+            }
+        };
+
+        if let Some(unknown) = fields.keys().find(|key| !["commands", "read", "write", "env", "network"].contains(&key.as_str())) {
+            return Err(format!("unknown sandbox setting '{}' in z.toml", unknown));
+        }
+        let project_dir = canonical(project_dir);
+        let resolve = |path: String| match path.strip_prefix("~/") {
+            Some(rest) => home_dir().map_or_else(|| PathBuf::from(&path), |home| canonical(&home.join(rest))),
+            None => canonical(&project_dir.join(path)),
+        };
+        Ok(Self {
+            commands: strings("commands")?,
+            read: strings("read")?.into_iter().map(resolve).collect(),
+            write: strings("write")?.into_iter().map(resolve).collect(),
+            env: strings("env")?,
+            network: match &table["network"] {
+                Value::Null => false,
+                Value::Bool(network) => *network,
+                _ => return Err("sandbox.network in z.toml must be true or false".to_string()),
+            },
+        })
+    }
+
+    /// Permissions past the defaults that the user has to approve, one line each
+    pub fn grants(&self) -> Vec<String> {
+        let mut grants = Vec::new();
+        grants.extend(self.read.iter().map(|path| format!("read {}", path.display())));
+        grants.extend(self.write.iter().map(|path| format!("write {}", path.display())));
+        grants.extend(self.env.iter().map(|name| format!("see ${}", name)));
+        if self.network {
+            grants.push("use the network".to_string());
+        }
+        grants
+    }
+}
+
+/// Whether the user approved `policy`'s [grants](SandboxPolicy::grants) for the project
+/// in `project_dir`; always true when there are none
+pub fn is_trusted(project_dir: &Path, policy: &SandboxPolicy) -> bool {
+    let grants = policy.grants();
+    if grants.is_empty() {
+        return true;
+    }
+    let Some(store) = trust_store() else {
+        return false;
+    };
+    let key = project_key(project_dir);
+    read_json(&store).is_ok_and(|trusted| trusted[key.as_str()] == fingerprint(&key, &grants))
+}
+
+/// Record the user's approval of `policy` for the project in `project_dir`
+pub fn trust(project_dir: &Path, policy: &SandboxPolicy) -> Result<(), String> {
+    let store = trust_store().ok_or("no home directory to record trusted projects in")?;
+    let mut trusted = read_json(&store)?;
+    let key = project_key(project_dir);
+    trusted[key.as_str()] = Value::String(fingerprint(&key, &policy.grants()));
+    write_json(&store, &trusted)
+}
+
+/// `~/.config/z/trusted.json`, or its equivalent on the platform
+fn trust_store() -> Option<PathBuf> {
+    Some(config_dir()?.join("trusted.json"))
+}
+
+/// `~/.config/z`, or its equivalent on the platform, for the user's own settings
+pub(crate) fn config_dir() -> Option<PathBuf> {
+    let config = std::env::var_os("XDG_CONFIG_HOME").map(PathBuf::from)
+        .or_else(|| std::env::var_os("APPDATA").map(PathBuf::from))
+        .or_else(|| home_dir().map(|home| home.join(".config")))?;
+    Some(config.join("z"))
+}
+
+pub(crate) fn home_dir() -> Option<PathBuf> {
+    std::env::var_os("HOME").or_else(|| std::env::var_os("USERPROFILE")).map(PathBuf::from)
+}
+
+fn project_key(project_dir: &Path) -> String {
+    canonical(project_dir).display().to_string()
+}
+
+fn fingerprint(project: &str, grants: &[String]) -> String {
+    sha256_hex(format!("{}\n{}", project, grants.join("\n")).as_bytes())
+}
+
+/// `path` with symlinks resolved when it exists, as sandboxes match real paths, and
+/// otherwise with `..` and `.` taken out
+fn canonical(path: &Path) -> PathBuf {
+    path.canonicalize().unwrap_or_else(|_| {
+        let mut normalized = PathBuf::new();
+        for component in path.components() {
+            match component {
+                Component::ParentDir => {
+                    normalized.pop();
+                }
+                Component::CurDir => {}
+                other => normalized.push(other),
+            }
+        }
+        normalized
+    })
+}
+
+/// How commands are confined
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Isolation {
+    /// Linux namespaces through `bwrap`
+    Bubblewrap(PathBuf),
+    /// The macOS sandbox through `sandbox-exec`
+    SandboxExec(PathBuf),
+    /// No confinement beyond a filtered environment; only with `Z_SANDBOX=off`
+    Unconfined,
+}
+
+impl Isolation {
+    /// The best isolation available on this machine
+    pub fn detect() -> Result<Self, String> {
+        if std::env::var(SANDBOX_ENV).is_ok_and(|value| value == "off") {
+            return Ok(Isolation::Unconfined);
+        }
+        if cfg!(target_os = "linux") {
+            if let Some(bwrap) = find_program("bwrap") {
+                return Ok(Isolation::Bubblewrap(bwrap));
+            }
+            return Err(format!(
+                "project commands run in a sandbox, which needs bubblewrap (`bwrap`); install it, or set {}=off to run them unconfined",
+                SANDBOX_ENV,
+            ));
+        }
+        if cfg!(target_os = "macos") {
+            return Ok(Isolation::SandboxExec(PathBuf::from("/usr/bin/sandbox-exec")));
+        }
+        Err(format!("project commands can't be sandboxed on this platform; set {}=off to run them unconfined", SANDBOX_ENV))
+    }
+}
+
+/// Confinement for the commands of one project; see the [module docs](self)
+#[derive(Debug)]
+pub struct Sandbox {
+    policy: SandboxPolicy,
+    project_dir: PathBuf,
+    output_dir: PathBuf,
+    /// Private home and temporary directory
+    scratch: PathBuf,
+    isolation: Isolation,
+}
+
+impl Sandbox {
+    /// A sandbox for the project in `project_dir` writing to `output_dir`. Fails when
+    /// the policy needs approval that wasn't given, or nothing can isolate commands.
+    pub fn new(policy: SandboxPolicy, project_dir: &Path, output_dir: &Path) -> Result<Self, String> {
+        if !is_trusted(project_dir, &policy) {
+            return Err(format!(
+                "z.toml asks to let project commands {}; run `z trust` to allow it",
+                policy.grants().join(", "),
+            ));
+        }
+        Self::with_isolation(policy, project_dir, output_dir, Isolation::detect()?)
+    }
+
+    /// [`new`](Self::new) with a given isolation and no approval check, for callers
+    /// that vetted the policy themselves
+    pub fn with_isolation(policy: SandboxPolicy, project_dir: &Path, output_dir: &Path, isolation: Isolation) -> Result<Self, String> {
+        crate::store::create_dir(output_dir)?;
+        static SANDBOXES: AtomicUsize = AtomicUsize::new(0);
+        let number = SANDBOXES.fetch_add(1, Ordering::Relaxed);
+        let scratch = std::env::temp_dir().join(format!("z-sandbox-{}-{}", std::process::id(), number));
+        crate::store::create_dir(&scratch)?;
+        Ok(Self {
+            policy,
+            project_dir: canonical(project_dir),
+            output_dir: canonical(output_dir),
+            scratch: canonical(&scratch),
+            isolation,
+        })
+    }
+
+    pub fn policy(&self) -> &SandboxPolicy {
+        &self.policy
+    }
+
+    /// A command running `program` with `args` in `dir`, confined to the sandbox.
+    /// Fails when the policy doesn't allow the program or it isn't installed.
+    pub fn command(&self, program: &str, args: &[&str], dir: &Path) -> Result<Command, String> {
+        if !self.policy.commands.iter().any(|allowed| allowed == program) {
+            return Err(format!("'{}' isn't in the commands z.toml lets the project run ([sandbox] commands)", program));
+        }
+        let resolved = find_program(program).ok_or_else(|| format!("'{}' isn't installed", program))?;
+        let dir = canonical(dir);
+
+        let mut command = match &self.isolation {
+            Isolation::Bubblewrap(bwrap) => {
+                let mut command = Command::new(bwrap);
+                command.args(self.bubblewrap_args(&dir)).arg("--").arg(&resolved);
+                command
+            }
+            Isolation::SandboxExec(sandbox_exec) => {
+                let mut command = Command::new(sandbox_exec);
+                command.arg("-p").arg(self.seatbelt_profile()).arg(&resolved);
+                command
+            }
+            Isolation::Unconfined => Command::new(&resolved),
+        };
+        command.args(args).current_dir(&dir).env_clear();
+        for name in DEFAULT_ENV.iter().copied().chain(self.policy.env.iter().map(String::as_str)) {
+            if let Some(value) = std::env::var_os(name) {
+                command.env(name, value);
+            }
+        }
+        command.env("HOME", &self.scratch).env("TMPDIR", &self.scratch);
+        Ok(command)
+    }
+
+    /// Readable directories: the system's, those on `PATH` (toolchains installed per
+    /// user) and the project's
+    fn readable(&self) -> Vec<PathBuf> {
+        let home = home_dir().map(|home| canonical(&home));
+        let on_path = std::env::var_os("PATH")
+            .map(|path| std::env::split_paths(&path).collect::<Vec<_>>())
+            .unwrap_or_default()
+            .into_iter()
+            // A home directory on PATH would expose everything in it
+            .filter(|dir| dir.is_absolute() && Some(canonical(dir)) != home);
+        let mut readable: Vec<PathBuf> = SYSTEM_PATHS.iter().map(PathBuf::from).chain(on_path).collect();
+        readable.push(self.project_dir.clone());
+        readable.extend(self.policy.read.iter().map(|path| canonical(path)));
+        readable.retain(|path| path.exists());
+        readable
+    }
+
+    fn writable(&self) -> Vec<PathBuf> {
+        let mut writable = vec![self.output_dir.clone(), self.scratch.clone()];
+        writable.extend(self.policy.write.iter().map(|path| canonical(path)));
+        writable.retain(|path| path.exists());
+        writable
+    }
+
+    fn bubblewrap_args(&self, dir: &Path) -> Vec<String> {
+        let mut args: Vec<String> = ["--die-with-parent", "--new-session", "--unshare-all"].map(str::to_string).to_vec();
+        if self.policy.network {
+            args.push("--share-net".to_string());
+        }
+        args.extend(["--proc", "/proc", "--dev", "/dev", "--tmpfs", "/tmp"].map(str::to_string));
+        for path in self.readable() {
+            let path = path.display().to_string();
+            args.extend(["--ro-bind".to_string(), path.clone(), path]);
+        }
+        for path in self.writable() {
+            let path = path.display().to_string();
+            args.extend(["--bind".to_string(), path.clone(), path]);
+        }
+        args.extend(["--chdir".to_string(), dir.display().to_string()]);
+        args
+    }
+
+    /// Profile for `sandbox-exec`: deny everything but the paths and network allowed
+    fn seatbelt_profile(&self) -> String {
+        let subpaths = |paths: Vec<PathBuf>| -> String {
+            paths.iter().map(|path| format!(" (subpath {:?})", path.display().to_string())).collect()
+        };
+        let mut profile = String::from("(version 1)\n(deny default)\n");
+        profile.push_str("(allow process-exec process-fork signal sysctl-read mach-lookup ipc-posix-shm file-read-metadata)\n");
+        profile.push_str(&format!("(allow file-read* (subpath \"/dev\"){})\n", subpaths(self.readable())));
+        profile.push_str(&format!("(allow file-write* (literal \"/dev/null\"){})\n", subpaths(self.writable())));
+        if self.policy.network {
+            profile.push_str("(allow network*)\n");
+        }
+        profile
+    }
+}
+
+impl Drop for Sandbox {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.scratch);
+    }
+}
+
+/// Full path of `program` on `PATH`
+fn find_program(program: &str) -> Option<PathBuf> {
+    if program.contains(std::path::MAIN_SEPARATOR) {
+        return Some(PathBuf::from(program)).filter(|path| path.is_file());
+    }
+    let path = std::env::var_os("PATH")?;
+    std::env::split_paths(&path).find_map(|dir| {
+        let candidate = dir.join(program);
+        if candidate.is_file() {
+            return Some(candidate);
+        }
+        let exe = candidate.with_extension("exe");
+        exe.is_file().then_some(exe)
+    })
+}
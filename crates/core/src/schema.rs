@@ -0,0 +1,170 @@
+//! Checks of the program's structure against the registry.
+//!
+//! Every target lists the sections (and, for code targets, the declaration kinds) its
+//! compiler understands in `allowedChildren`; properties are checked against the
+//! `fields` of the enclosing target or section, with `targetFields` allowed in every
+//! target. A name that isn't allowed is reported as misplaced when it's allowed in
+//! other targets, and otherwise as unknown, suggesting the closest allowed name.
+
+use serde_json::Value;
+use z_ast::{Element, Node, Span};
+use z_semantic::SemanticError;
+
+/// Structural errors in `program`, in source order
+pub fn validate(program: &Element, registry: &Value) -> Vec<SemanticError> {
+    let mut errors = Vec::new();
+    scope(program, &registry["targets"]["workspace"], registry, &mut errors);
+    errors
+}
+
+/// The top level or a workspace: targets, and the namespaces the workspace allows
+fn scope(parent: &Element, workspace: &Value, registry: &Value, errors: &mut Vec<SemanticError>) {
+    for node in parent.items() {
+        let Node::Element(element) = node else {
+            continue;
+        };
+        match element.name.split_once(':') {
+            Some(("workspace", _)) => scope(element, workspace, registry, errors),
+            Some((kind, name)) if registry["targets"][kind].is_object() => target(element, kind, name, registry, errors),
+            // Unknown target kinds are reported when compiling
+            Some(_) => {}
+            None if allows(workspace, &element.name) => section(element, registry, errors),
+            // Anything else isn't a well-formed target, which lowering reports
+            None => {}
+        }
+    }
+}
+
+fn target(element: &Element, kind: &str, name: &str, registry: &Value, errors: &mut Vec<SemanticError>) {
+    let target = &registry["targets"][kind];
+    let code = target["mode"] == "code";
+    let allowed = strings(&target["allowedChildren"]);
+    let mut fields = keys(&registry["targetFields"]);
+    fields.extend(keys(&target["fields"]));
+
+    for node in element.items() {
+        match node {
+            Node::Element(child) => match child.name.split_once(':') {
+                Some((declaration, _)) if code => check_declaration(declaration, child.span, kind, &allowed, errors),
+                // Markup targets take any declaration; their sections give them meaning
+                Some(_) => {}
+                None if allowed.contains(&child.name.as_str()) => section(child, registry, errors),
+                // Lowering explains that Links and Meta belong to the workspace, and checks
+                // the variables of `env`, which every target takes
+                None if child.name == "Links" || child.name == "Meta" || child.name == "env" => {}
+                None => errors.push(misplaced(child, kind, name, &allowed, registry)),
+            },
+            Node::KeyValue { key, span, .. } => check_field(key, *span, &format!("{} {}", kind, name), &fields, errors),
+            Node::ChildLine { modifier: Some(declaration), span, .. } if code => {
+                check_declaration(declaration, *span, kind, &allowed, errors);
+            }
+            Node::ChildLine { .. } | Node::Const { .. } | Node::Expansion { .. } => {}
+        }
+    }
+}
+
+/// Check a `kind name` declaration of a code target, such as `fun main`
+fn check_declaration(declaration: &str, span: Span, kind: &str, allowed: &[&str], errors: &mut Vec<SemanticError>) {
+    if !allowed.contains(&declaration) {
+        errors.push(SemanticError {
+            span,
+            message: format!("{} targets have no '{}' declarations{}", kind, declaration, suggestion(declaration, allowed)),
+        });
+    }
+}
+
+/// Check the properties of a section whose namespace declares its `fields`
+fn section(element: &Element, registry: &Value, errors: &mut Vec<SemanticError>) {
+    let namespace = &registry["namespaces"][element.name.as_str()];
+    if !namespace["fields"].is_object() {
+        return;
+    }
+    let fields = keys(&namespace["fields"]);
+    for node in element.items() {
+        if let Node::KeyValue { key, span, .. } = node {
+            check_field(key, *span, &element.name, &fields, errors);
+        }
+    }
+}
+
+fn check_field(key: &str, span: Span, owner: &str, fields: &[&str], errors: &mut Vec<SemanticError>) {
+    if !fields.contains(&key) {
+        errors.push(SemanticError {
+            span,
+            message: format!("unknown field '{}' in {}{}", key, owner, suggestion(key, fields)),
+        });
+    }
+}
+
+/// A section `target` doesn't allow: one other targets use, or a name nothing uses
+fn misplaced(section: &Element, kind: &str, name: &str, allowed: &[&str], registry: &Value) -> SemanticError {
+    let homes: Vec<&str> = registry["targets"].as_object()
+        .map(|targets| {
+            targets.iter()
+                .filter(|(home, target)| home.as_str() != "workspace" && allows(target, &section.name))
+                .map(|(home, _)| home.as_str())
+                .collect()
+        })
+        .unwrap_or_default();
+
+    let message = if homes.is_empty() {
+        format!("unknown section '{}' in {} {}{}", section.name, kind, name, suggestion(&section.name, allowed))
+    } else {
+        format!(
+            "{} isn't available in {} targets; it's used in {} targets{}",
+            section.name,
+            kind,
+            join_or(&homes),
+            if allowed.is_empty() { String::new() } else { format!(" ({} supports {})", kind, allowed.join(", ")) },
+        )
+    };
+    SemanticError { span: section.span, message }
+}
+
+fn allows(target: &Value, child: &str) -> bool {
+    strings(&target["allowedChildren"]).contains(&child)
+}
+
+fn strings(list: &Value) -> Vec<&str> {
+    list.as_array().map(|items| items.iter().filter_map(Value::as_str).collect()).unwrap_or_default()
+}
+
+fn keys(map: &Value) -> Vec<&str> {
+    map.as_object().map(|fields| fields.keys().map(String::as_str).collect()).unwrap_or_default()
+}
+
+/// `; did you mean 'X'?` for the candidate closest to `name`, if any is close enough
+pub(crate) fn suggestion(name: &str, candidates: &[&str]) -> String {
+    let lower = name.to_lowercase();
+    let closest = candidates.iter()
+        .map(|candidate| (distance(&lower, &candidate.to_lowercase()), *candidate))
+        .min();
+    match closest {
+        Some((distance, candidate)) if distance <= (name.chars().count() / 3).max(2) => format!("; did you mean '{}'?", candidate),
+        _ => String::new(),
+    }
+}
+
+/// Levenshtein distance between `a` and `b`
+fn distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, a) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, b) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(a != *b);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+/// `a`, `a or b`, `a, b or c`
+pub(crate) fn join_or(items: &[&str]) -> String {
+    match items {
+        [] => String::new(),
+        [only] => only.to_string(),
+        [init @ .., last] => format!("{} or {}", init.join(", "), last),
+    }
+}
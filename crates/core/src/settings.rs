@@ -0,0 +1,118 @@
+//! How a project is built when the command line doesn't say: the `[project]` table of
+//! `z.toml`, and the `[profile.<name>]` tables that change it for a kind of build.
+//!
+//! ```toml
+//! [project]
+//! entry = "src/app.z"          # instead of main.z
+//! out = "build"                # instead of out/, from the directory of z.toml
+//! targets = ["WebApp", "rust:Api"]
+//! env = "dev"
+//!
+//! [profile.ci]
+//! env = "prod"
+//! strict = true
+//! deny_warnings = true
+//! ```
+//!
+//! The project is the nearest directory from the current one up that has a `z.toml`
+//! (see [`find_root`]), so commands work from anywhere inside it. A profile has the keys
+//! of `[project]` but `entry`, and wins over it; flags on the command line win over both.
+
+use std::path::{Path, PathBuf};
+
+use serde_json::Value;
+
+use crate::toml::CONFIG_FILE;
+use crate::CompileOptions;
+
+/// Key of the project's settings in `z.toml`
+pub const PROJECT: &str = "project";
+
+/// Key of the profiles in `z.toml`
+pub const PROFILE: &str = "profile";
+
+/// Entry file of a project whose `z.toml` doesn't name one
+pub const DEFAULT_ENTRY: &str = "main.z";
+
+/// The settings of a project, with those of the profile chosen applied
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Settings {
+    /// The program to compile, relative to the project's directory
+    pub entry: Option<PathBuf>,
+    /// Where the targets go, relative to the project's directory
+    pub out: Option<PathBuf>,
+    /// The targets to compile, by name or `kind:name`; all of them when empty
+    pub targets: Vec<String>,
+    /// The environment to compile for
+    pub environment: Option<String>,
+    pub strict: bool,
+    pub deny_warnings: bool,
+}
+
+impl Settings {
+    /// Read `[project]` from a parsed `z.toml`, with `[profile.<profile>]` over it. A
+    /// profile that isn't there is an error, naming those that are.
+    pub fn from_config(config: &Value, profile: Option<&str>) -> Result<Self, String> {
+        let mut settings = Self::default();
+        settings.read(&config[PROJECT], PROJECT, true)?;
+        if let Some(name) = profile {
+            let profiles = config[PROFILE].as_object();
+            let Some(table) = profiles.and_then(|profiles| profiles.get(name)) else {
+                let names: Vec<&str> = profiles.map(|profiles| profiles.keys().map(String::as_str).collect()).unwrap_or_default();
+                return Err(match names.is_empty() {
+                    true => format!("no profile named '{}'; declare it as [{}.{}]", name, PROFILE, name),
+                    false => format!("no profile named '{}' (the profiles are {})", name, names.join(", ")),
+                });
+            };
+            settings.read(table, &format!("{}.{}", PROFILE, name), false)?;
+        }
+        Ok(settings)
+    }
+
+    /// The entry file, relative to the project's directory
+    pub fn entry(&self) -> &Path {
+        self.entry.as_deref().unwrap_or(Path::new(DEFAULT_ENTRY))
+    }
+
+    /// `options` with what these settings give and `options` leaves unset
+    pub fn apply(&self, options: CompileOptions) -> CompileOptions {
+        CompileOptions {
+            targets: if options.targets.is_empty() { self.targets.clone() } else { options.targets },
+            environment: options.environment.or_else(|| self.environment.clone()),
+            strict: options.strict || self.strict,
+            deny_warnings: options.deny_warnings || self.deny_warnings,
+            ..options
+        }
+    }
+
+    /// Take the keys `table` has, at `path` in `z.toml`
+    fn read(&mut self, table: &Value, path: &str, with_entry: bool) -> Result<(), String> {
+        let Some(table) = table.as_object() else {
+            return match table {
+                Value::Null => Ok(()),
+                _ => Err(format!("'{}' must be a table", path)),
+            };
+        };
+        for (key, value) in table {
+            let invalid = |expected: &str| format!("'{}.{}' must be {}", path, key, expected);
+            match key.as_str() {
+                "entry" if with_entry => self.entry = Some(value.as_str().ok_or_else(|| invalid("a path"))?.into()),
+                "out" => self.out = Some(value.as_str().ok_or_else(|| invalid("a path"))?.into()),
+                "targets" => self.targets = value.as_array()
+                    .and_then(|targets| targets.iter().map(|target| target.as_str().map(str::to_string)).collect())
+                    .ok_or_else(|| invalid("a list of target names, e.g. [\"WebApp\"]"))?,
+                "env" => self.environment = Some(value.as_str().ok_or_else(|| invalid("an environment name"))?.to_string()),
+                "strict" => self.strict = value.as_bool().ok_or_else(|| invalid("true or false"))?,
+                "deny_warnings" => self.deny_warnings = value.as_bool().ok_or_else(|| invalid("true or false"))?,
+                _ => return Err(format!("unknown key '{}' in [{}]", key, path)),
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The project `dir` is in: the nearest directory from `dir` up with a `z.toml`
+pub fn find_root(dir: &Path) -> Option<PathBuf> {
+    let dir = std::path::absolute(dir).unwrap_or_else(|_| dir.to_path_buf());
+    dir.ancestors().find(|dir| dir.join(CONFIG_FILE).is_file()).map(Path::to_path_buf)
+}
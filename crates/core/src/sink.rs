@@ -0,0 +1,362 @@
+//! Where target compilers put the files of the projects they generate.
+//!
+//! Compilers write through a [`FileSink`] rather than `std::fs`, with paths relative to
+//! the project, so the same compiler can generate a project on disk ([`FsSink`]), in
+//! memory for tests and previews ([`MemorySink`]), or straight into a zip, tar or
+//! `.tar.gz` file for a download ([`ArchiveSink`]).
+//!
+//! Compilers that start from a tool's scaffold, like `cargo init`, need a directory to
+//! run the tool in. [`on_disk`] gives them the sink's own, or a scratch directory whose
+//! files are then written to the sink.
+//!
+//! What every target gets after its compiler runs, such as store metadata, shared types
+//! or stamped versions, is read from and written back to the same sink, so a target
+//! generated in memory or into an archive never goes through the disk.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+use crate::archive::{gzip, Tar, Zip};
+use crate::tr;
+
+/// Destination of a generated project's files. Paths are relative to the project; one that
+/// leaves it, like `../x` or an absolute path, is an error rather than written outside it
+pub trait FileSink: Sync {
+    /// Create the directory `path`, and those it's in, if they aren't there yet
+    fn create_dir(&self, path: &Path) -> Result<(), String>;
+
+    /// Write `contents` to the file `path`, replacing it if it's there, in a directory
+    /// already created with [`create_dir`](Self::create_dir)
+    fn write(&self, path: &Path, contents: &[u8]) -> Result<(), String>;
+
+    /// Contents of the file `path`, or `None` if there's no such file
+    fn read(&self, path: &Path) -> Result<Option<Vec<u8>>, String>;
+
+    /// Every file in the project, in path order
+    fn files(&self) -> Vec<PathBuf>;
+
+    /// The directory on disk that holds the project, for sinks that have one
+    fn directory(&self) -> Option<&Path> {
+        None
+    }
+}
+
+/// Writes the project into a directory
+#[derive(Debug, Clone)]
+pub struct FsSink {
+    root: PathBuf,
+}
+
+impl FsSink {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+}
+
+impl FileSink for FsSink {
+    fn create_dir(&self, path: &Path) -> Result<(), String> {
+        let path = self.root.join(relative(path)?);
+        fs::create_dir_all(&path).map_err(|e| tr!("create-dir-failed", path = path.display(), error = e))
+    }
+
+    fn write(&self, path: &Path, contents: &[u8]) -> Result<(), String> {
+        fs::write(self.root.join(relative(path)?), contents).map_err(|e| tr!("write-failed", path = path.display(), error = e))
+    }
+
+    fn read(&self, path: &Path) -> Result<Option<Vec<u8>>, String> {
+        let path = self.root.join(relative(path)?);
+        match fs::read(&path) {
+            Ok(contents) => Ok(Some(contents)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(tr!("read-failed", path = path.display(), error = e)),
+        }
+    }
+
+    fn files(&self) -> Vec<PathBuf> {
+        let mut files = Vec::new();
+        list(&self.root, Path::new(""), &mut files);
+        files.sort();
+        files
+    }
+
+    fn directory(&self) -> Option<&Path> {
+        Some(&self.root)
+    }
+}
+
+/// Keeps the project in memory
+#[derive(Debug, Default)]
+pub struct MemorySink {
+    files: Mutex<BTreeMap<PathBuf, Vec<u8>>>,
+}
+
+impl MemorySink {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Every file written, by path
+    pub fn files(&self) -> BTreeMap<PathBuf, Vec<u8>> {
+        self.files.lock().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    /// The file at `path` as text, if it was written and is UTF-8
+    pub fn read_text(&self, path: impl AsRef<Path>) -> Option<String> {
+        let files = self.files.lock().unwrap_or_else(|e| e.into_inner());
+        files.get(path.as_ref()).and_then(|contents| String::from_utf8(contents.clone()).ok())
+    }
+
+    /// Every file written, by path, without copying them
+    pub fn into_files(self) -> BTreeMap<PathBuf, Vec<u8>> {
+        self.files.into_inner().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl FileSink for MemorySink {
+    fn create_dir(&self, path: &Path) -> Result<(), String> {
+        relative(path).map(drop)
+    }
+
+    fn write(&self, path: &Path, contents: &[u8]) -> Result<(), String> {
+        let path = relative(path)?;
+        self.files.lock().unwrap_or_else(|e| e.into_inner()).insert(path, contents.to_vec());
+        Ok(())
+    }
+
+    fn read(&self, path: &Path) -> Result<Option<Vec<u8>>, String> {
+        let path = relative(path)?;
+        Ok(self.files.lock().unwrap_or_else(|e| e.into_inner()).get(&path).cloned())
+    }
+
+    fn files(&self) -> Vec<PathBuf> {
+        self.files.lock().unwrap_or_else(|e| e.into_inner()).keys().cloned().collect()
+    }
+}
+
+/// Kind of file an [`ArchiveSink`] makes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArchiveFormat {
+    Zip,
+    Tar,
+    /// A tar compressed with gzip
+    TarGz,
+}
+
+impl ArchiveFormat {
+    /// The format of an archive file named `name`, by its extension
+    pub fn of_file(name: &Path) -> Option<Self> {
+        let name = name.file_name()?.to_str()?.to_ascii_lowercase();
+        [".tar.gz", ".tgz", ".tar", ".zip"].iter()
+            .find(|extension| name.ends_with(*extension))
+            .and_then(|extension| extension.trim_start_matches('.').parse().ok())
+    }
+
+    /// Extension of files in this format, without the dot
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ArchiveFormat::Zip => "zip",
+            ArchiveFormat::Tar => "tar",
+            ArchiveFormat::TarGz => "tar.gz",
+        }
+    }
+}
+
+impl std::str::FromStr for ArchiveFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "zip" => Ok(ArchiveFormat::Zip),
+            "tar" => Ok(ArchiveFormat::Tar),
+            "tar.gz" | "tgz" => Ok(ArchiveFormat::TarGz),
+            _ => Err(format!("invalid archive format '{}' (expected 'zip', 'tar' or 'tar.gz')", s)),
+        }
+    }
+}
+
+/// Packs the project into one archive file, with its files sorted by path under
+/// `prefix` (usually the project's name)
+#[derive(Debug)]
+pub struct ArchiveSink {
+    format: ArchiveFormat,
+    prefix: PathBuf,
+    files: MemorySink,
+}
+
+impl ArchiveSink {
+    pub fn new(format: ArchiveFormat, prefix: impl Into<PathBuf>) -> Self {
+        Self { format, prefix: prefix.into(), files: MemorySink::new() }
+    }
+
+    /// The archive, with every file written so far
+    pub fn finish(self) -> Vec<u8> {
+        let entries = self.files.into_files().into_iter()
+            .map(|(path, contents)| (self.prefix.join(path).to_string_lossy().replace('\\', "/"), contents));
+        match self.format {
+            ArchiveFormat::Zip => {
+                let mut zip = Zip::default();
+                entries.for_each(|(name, contents)| zip.add(&name, &contents));
+                zip.finish()
+            }
+            ArchiveFormat::Tar | ArchiveFormat::TarGz => {
+                let mut tar = Tar::default();
+                entries.for_each(|(name, contents)| tar.add(&name, &contents));
+                let tar = tar.finish();
+                if self.format == ArchiveFormat::TarGz { gzip(&tar) } else { tar }
+            }
+        }
+    }
+}
+
+impl FileSink for ArchiveSink {
+    fn create_dir(&self, path: &Path) -> Result<(), String> {
+        self.files.create_dir(path)
+    }
+
+    fn write(&self, path: &Path, contents: &[u8]) -> Result<(), String> {
+        self.files.write(path, contents)
+    }
+
+    fn read(&self, path: &Path) -> Result<Option<Vec<u8>>, String> {
+        self.files.read(path)
+    }
+
+    fn files(&self) -> Vec<PathBuf> {
+        FileSink::files(&self.files)
+    }
+}
+
+/// Writes into another sink, under one of its directories, e.g. one of the projects in
+/// an archive
+pub(crate) struct Within<'a> {
+    pub dir: &'a Path,
+    pub sink: &'a dyn FileSink,
+}
+
+impl FileSink for Within<'_> {
+    fn create_dir(&self, path: &Path) -> Result<(), String> {
+        self.sink.create_dir(&self.dir.join(path))
+    }
+
+    fn write(&self, path: &Path, contents: &[u8]) -> Result<(), String> {
+        self.sink.write(&self.dir.join(path), contents)
+    }
+
+    fn read(&self, path: &Path) -> Result<Option<Vec<u8>>, String> {
+        self.sink.read(&self.dir.join(path))
+    }
+
+    fn files(&self) -> Vec<PathBuf> {
+        let dir = relative(self.dir).unwrap_or_default();
+        self.sink.files().into_iter().filter_map(|path| path.strip_prefix(&dir).ok().map(Path::to_path_buf)).collect()
+    }
+}
+
+/// Helpers for the steps that update a project after its compiler generated it
+impl dyn FileSink + '_ {
+    /// The file `path` as text, or `None` if there's none
+    pub(crate) fn read_text(&self, path: &Path) -> Result<Option<String>, String> {
+        self.read(path)?
+            .map(|contents| String::from_utf8(contents).map_err(|e| tr!("read-failed", path = path.display(), error = e)))
+            .transpose()
+    }
+
+    /// Write `contents` to the file `path`, creating the directory it's in if needed
+    pub(crate) fn write_text(&self, path: &Path, contents: &str) -> Result<(), String> {
+        self.create_dir(path.parent().unwrap_or(Path::new("")))?;
+        self.write(path, contents.as_bytes())
+    }
+
+    pub(crate) fn exists(&self, path: &Path) -> bool {
+        self.read(path).is_ok_and(|contents| contents.is_some())
+    }
+
+    /// Contents of a JSON file, or an empty object if there's none yet
+    pub(crate) fn read_json(&self, path: &Path) -> Result<serde_json::Value, String> {
+        match self.read_text(path)? {
+            Some(contents) => serde_json::from_str(&contents).map_err(|e| tr!("read-failed", path = path.display(), error = e)),
+            None => Ok(serde_json::json!({})),
+        }
+    }
+
+    /// Write `value` pretty-printed, creating the directory it's in if needed
+    pub(crate) fn write_json(&self, path: &Path, value: &serde_json::Value) -> Result<(), String> {
+        self.write_text(path, &(serde_json::to_string_pretty(value).expect("JSON value") + "\n"))
+    }
+
+    /// Write every file of this project to `sink`
+    pub(crate) fn copy_to(&self, sink: &dyn FileSink) -> Result<(), String> {
+        for path in self.files() {
+            let contents = self.read(&path)?.unwrap_or_default();
+            sink.create_dir(path.parent().unwrap_or(Path::new("")))?;
+            sink.write(&path, &contents)?;
+        }
+        Ok(())
+    }
+}
+
+/// Run `f` with a directory on disk for the project: the sink's own, or else a scratch
+/// directory whose files, but for `.git`, are written to the sink once `f` is done
+pub(crate) fn on_disk<T>(sink: &dyn FileSink, f: impl FnOnce(&Path) -> Result<T, String>) -> Result<T, String> {
+    if let Some(directory) = sink.directory() {
+        return f(directory);
+    }
+    static SCRATCH: AtomicUsize = AtomicUsize::new(0);
+    let scratch = std::env::temp_dir().join(format!("z-scratch-{}-{}", std::process::id(), SCRATCH.fetch_add(1, Ordering::Relaxed)));
+    let project = scratch.join("project");
+    let result = fs::create_dir_all(&project)
+        .map_err(|e| tr!("create-dir-failed", path = project.display(), error = e))
+        .and_then(|_| f(&project))
+        .and_then(|value| copy_into(&project, Path::new(""), sink).map(|_| value));
+    let _ = fs::remove_dir_all(&scratch);
+    result
+}
+
+fn copy_into(root: &Path, relative: &Path, sink: &dyn FileSink) -> Result<(), String> {
+    let dir = root.join(relative);
+    let entries = fs::read_dir(&dir).map_err(|e| tr!("read-dir-failed", path = dir.display(), error = e))?;
+    let mut entries = entries.collect::<Result<Vec<_>, _>>().map_err(|e| tr!("read-dir-failed", path = dir.display(), error = e))?;
+    // In name order, so a sink is handed the files the same way every time
+    entries.sort_by_key(|entry| entry.file_name());
+    for entry in entries {
+        let path = relative.join(entry.file_name());
+        if entry.file_name() == ".git" {
+            continue;
+        }
+        if entry.file_type().is_ok_and(|kind| kind.is_dir()) {
+            sink.create_dir(&path)?;
+            copy_into(root, &path, sink)?;
+        } else {
+            let contents = fs::read(entry.path()).map_err(|e| tr!("read-failed", path = entry.path().display(), error = e))?;
+            sink.write(&path, &contents)?;
+        }
+    }
+    Ok(())
+}
+
+/// The files under `root`'s `relative` directory
+fn list(root: &Path, relative: &Path, files: &mut Vec<PathBuf>) {
+    let Ok(entries) = fs::read_dir(root.join(relative)) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let path = relative.join(entry.file_name());
+        match entry.file_type() {
+            Ok(kind) if kind.is_dir() => list(root, &path, files),
+            Ok(kind) if kind.is_file() => files.push(path),
+            _ => {}
+        }
+    }
+}
+
+/// `path` if it stays inside the project
+fn relative(path: &Path) -> Result<PathBuf, String> {
+    if path.components().all(|component| matches!(component, Component::Normal(_) | Component::CurDir)) {
+        Ok(path.components().filter(|component| matches!(component, Component::Normal(_))).collect())
+    } else {
+        Err(format!("'{}' isn't a path inside the project", path.display()))
+    }
+}
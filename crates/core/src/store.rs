@@ -0,0 +1,113 @@
+//! Release metadata generated from a target's `Store` section.
+//!
+//! - `swift` targets get a fastlane `deliver` layout: `fastlane/metadata` with the
+//!   name, description and categories, and the screenshots under `fastlane/screenshots`
+//! - `tauri` targets get the bundle metadata in `src-tauri/tauri.conf.json` that the
+//!   AppImage, MSI and DMG bundlers read
+//!
+//! Screenshot paths are relative to the directory of the source file.
+
+use std::fs;
+use std::path::Path;
+
+use serde_json::{json, Value};
+use z_semantic::{StoreListing, Target};
+
+use crate::naming::Case;
+use crate::sink::FileSink;
+use crate::tr;
+
+/// Locale the listing is written for; fastlane keeps one directory per locale
+const LOCALE: &str = "en-US";
+
+/// Write the store metadata for `target` into its project, if it has a listing
+pub(crate) fn write_store_metadata(target: &Target, sink: &dyn FileSink, source_dir: Option<&Path>) -> Result<(), String> {
+    let Some(listing) = target.store() else {
+        return Ok(());
+    };
+
+    match target.kind.as_str() {
+        "swift" => write_fastlane(listing, sink, source_dir.unwrap_or(Path::new("."))),
+        "tauri" => write_tauri_bundle(listing, sink),
+        _ => Ok(()),
+    }
+}
+
+fn write_fastlane(listing: &StoreListing, sink: &dyn FileSink, source_dir: &Path) -> Result<(), String> {
+    let metadata = Path::new("fastlane/metadata");
+    let localized = metadata.join(LOCALE);
+    sink.write_text(&localized.join("name.txt"), &format!("{}\n", listing.name))?;
+    sink.write_text(&localized.join("description.txt"), &format!("{}\n", listing.description))?;
+
+    // App Store Connect takes category ids such as PRODUCTIVITY
+    let categories = ["primary_category.txt", "secondary_category.txt"];
+    for (file, category) in categories.iter().zip(&listing.categories) {
+        sink.write_text(&metadata.join(file), &format!("{}\n", Case::Constant.apply(category)))?;
+    }
+
+    if !listing.screenshots.is_empty() {
+        let screenshots = Path::new("fastlane/screenshots").join(LOCALE);
+        sink.create_dir(&screenshots)?;
+        for screenshot in &listing.screenshots {
+            let from = source_dir.join(screenshot);
+            let name = Path::new(screenshot).file_name().unwrap_or_default();
+            let contents = fs::read(&from).map_err(|e| tr!("copy-failed", path = from.display(), error = e))?;
+            sink.write(&screenshots.join(name), &contents)?;
+        }
+    }
+    Ok(())
+}
+
+fn write_tauri_bundle(listing: &StoreListing, sink: &dyn FileSink) -> Result<(), String> {
+    let path = Path::new("src-tauri/tauri.conf.json");
+    let mut config = sink.read_json(path)?;
+
+    let mut bundle = json!({
+        "shortDescription": listing.description.lines().next().unwrap_or_default(),
+        "longDescription": listing.description,
+        "targets": ["appimage", "msi", "dmg"],
+    });
+    // Tauri takes categories such as DeveloperTool
+    if let Some(category) = listing.categories.first() {
+        bundle["category"] = json!(Case::Pascal.apply(category));
+    }
+
+    // Tauri 1 keeps the bundle settings under `tauri`, Tauri 2 at the top level
+    let bundle_parent = if config["tauri"].is_object() {
+        config["package"]["productName"] = json!(listing.name);
+        &mut config["tauri"]["bundle"]
+    } else {
+        config["productName"] = json!(listing.name);
+        &mut config["bundle"]
+    };
+    match (bundle_parent, bundle) {
+        (Value::Object(existing), Value::Object(fields)) => existing.extend(fields),
+        (other, bundle) => *other = bundle,
+    }
+
+    sink.write_json(path, &config)
+}
+
+pub(crate) fn create_dir(path: &Path) -> Result<(), String> {
+    fs::create_dir_all(path).map_err(|e| tr!("create-dir-failed", path = path.display(), error = e))
+}
+
+pub(crate) fn write_file(path: &Path, contents: &str) -> Result<(), String> {
+    fs::write(path, contents).map_err(|e| tr!("write-failed", path = path.display(), error = e))
+}
+
+/// Contents of a JSON file, or an empty object if there's none yet
+pub(crate) fn read_json(path: &Path) -> Result<Value, String> {
+    match fs::read_to_string(path) {
+        Ok(contents) => serde_json::from_str(&contents).map_err(|e| tr!("read-failed", path = path.display(), error = e)),
+        Err(_) => Ok(json!({})),
+    }
+}
+
+/// Write `value` pretty-printed, creating the parent directory if needed
+pub(crate) fn write_json(path: &Path, value: &Value) -> Result<(), String> {
+    if let Some(parent) = path.parent() {
+        create_dir(parent)?;
+    }
+    write_file(path, &(serde_json::to_string_pretty(value).expect("JSON value") + "\n"))
+}
@@ -0,0 +1,288 @@
+//! Pictures of how a program is put together, for `z graph`.
+//!
+//! Each target is a cluster holding its sections, and the sections what they declare:
+//! routes, with the routes under them, components, endpoints, models and environment
+//! variables. Solid arrows go from a block to what's in it, dashed ones from a route,
+//! endpoint or model to the component, model or variable it names, and bold ones from a
+//! target to the targets it `uses`. Components no route renders are drawn in red, dashed,
+//! as they're reported as `unused-component`.
+//!
+//! The picture is written for Graphviz (`dot -Tsvg`) or Mermaid, which GitHub and most
+//! Markdown viewers draw.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+
+use z_ast::Span;
+use z_semantic::{Program, Route, SectionBody};
+
+use crate::graph::Graph;
+use crate::symbols::{self, SymbolKind};
+use crate::{load_registry, parse, CompileOptions};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum GraphFormat {
+    /// Graphviz
+    #[default]
+    Dot,
+    Mermaid,
+}
+
+impl FromStr for GraphFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "dot" => Ok(GraphFormat::Dot),
+            "mermaid" => Ok(GraphFormat::Mermaid),
+            _ => Err(format!("invalid graph format '{}' (expected 'dot' or 'mermaid')", s)),
+        }
+    }
+}
+
+impl fmt::Display for GraphFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            GraphFormat::Dot => "dot",
+            GraphFormat::Mermaid => "mermaid",
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Shape {
+    Target,
+    Section,
+    Route,
+    Component,
+    Endpoint,
+    Model,
+    Variable,
+    Item,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Edge {
+    /// From a block to what's in it
+    Contains,
+    /// From a route, endpoint or model to what it names, with the annotation naming it
+    Names(&'static str),
+    /// From a target to a target it uses
+    Uses,
+}
+
+struct Node {
+    id: String,
+    label: String,
+    shape: Shape,
+    unused: bool,
+}
+
+/// The targets of a program with what's in them, and the arrows between them
+#[derive(Default)]
+struct Picture {
+    /// `kind:name` and nodes of each target, the target's own first
+    clusters: Vec<(String, Vec<Node>)>,
+    edges: Vec<(String, String, Edge)>,
+    /// Node of what's at a span of a target, by `kind:name` and where the span starts and ends
+    at: HashMap<(String, usize, usize), String>,
+}
+
+fn at(scope: &str, span: Span) -> (String, usize, usize) {
+    (scope.to_string(), span.start, span.end)
+}
+
+/// The structure of the program in `source`, as a graph in `format`. What couldn't be
+/// understood is left out, as are references that name nothing.
+pub fn render(source: &str, format: GraphFormat, options: &CompileOptions) -> Result<String, String> {
+    let (ast, _) = parse(source, options, &load_registry(options))?;
+    let (program, _) = z_semantic::build(&ast);
+    let picture = picture(&program);
+    Ok(match format {
+        GraphFormat::Dot => dot(&picture),
+        GraphFormat::Mermaid => mermaid(&picture),
+    })
+}
+
+fn picture(program: &Program) -> Picture {
+    let mut picture = Picture::default();
+    let mut count = 0;
+    let mut node = |nodes: &mut Vec<Node>, label: String, shape: Shape| {
+        count += 1;
+        let id = format!("n{}", count);
+        nodes.push(Node { id: id.clone(), label, shape, unused: false });
+        id
+    };
+
+    for target in &program.targets {
+        let scope = target.qualified_name();
+        let mut nodes = Vec::new();
+        let root = node(&mut nodes, scope.clone(), Shape::Target);
+        picture.at.insert(at(&scope, target.span), root.clone());
+        let contains = |picture: &mut Picture, parent: &str, child: &str, span: Span| {
+            picture.edges.push((parent.to_string(), child.to_string(), Edge::Contains));
+            picture.at.insert(at(&scope, span), child.to_string());
+        };
+
+        for variable in &target.env {
+            let id = node(&mut nodes, variable.name.clone(), Shape::Variable);
+            contains(&mut picture, &root, &id, variable.span);
+        }
+        for section in &target.sections {
+            let id = node(&mut nodes, section.namespace.to_string(), Shape::Section);
+            contains(&mut picture, &root, &id, section.span);
+            match &section.body {
+                SectionBody::Routes(routes) => {
+                    let mut stack: Vec<(&Route, String, String)> = routes.iter().rev().map(|route| (route, String::new(), id.clone())).collect();
+                    while let Some((route, parent_path, parent)) = stack.pop() {
+                        let path = route.path(&parent_path);
+                        let route_id = node(&mut nodes, path.clone(), Shape::Route);
+                        contains(&mut picture, &parent, &route_id, route.span);
+                        stack.extend(route.children.iter().rev().map(|child| (child, path.clone(), route_id.clone())));
+                    }
+                }
+                SectionBody::Components(components) => components.iter().for_each(|component| {
+                    let component_id = node(&mut nodes, component.name.clone(), Shape::Component);
+                    contains(&mut picture, &id, &component_id, component.span);
+                }),
+                SectionBody::Endpoints(endpoints) => endpoints.iter().for_each(|endpoint| {
+                    let path = format!("/api/{}", endpoint.name);
+                    let label = match &endpoint.method {
+                        Some(method) => format!("{} {}", method.to_uppercase(), path),
+                        None => path,
+                    };
+                    let endpoint_id = node(&mut nodes, label, Shape::Endpoint);
+                    contains(&mut picture, &id, &endpoint_id, endpoint.span);
+                }),
+                SectionBody::Models(models) => models.iter().for_each(|model| {
+                    let model_id = node(&mut nodes, model.name.clone(), Shape::Model);
+                    contains(&mut picture, &id, &model_id, model.span);
+                    // Fields refer to models; the arrow starts at the model they're in
+                    for field in &model.fields {
+                        picture.at.insert(at(&scope, field.span), model_id.clone());
+                    }
+                }),
+                SectionBody::Items(items) => items.iter().for_each(|item| {
+                    let item_id = node(&mut nodes, item.name.clone(), Shape::Item);
+                    contains(&mut picture, &id, &item_id, item.span);
+                }),
+                SectionBody::Settings(_) | SectionBody::Store(_) | SectionBody::Deploy(_) => {}
+            }
+        }
+        picture.clusters.push((scope, nodes));
+    }
+
+    let (table, _) = symbols::resolve(program);
+    for reference in &table.references {
+        let Some(symbol) = reference.symbol.map(|symbol| &table.symbols[symbol]) else {
+            continue;
+        };
+        let from = picture.at.get(&at(&reference.target, reference.span));
+        let to = picture.at.get(&at(&symbol.target, symbol.span));
+        if let (Some(from), Some(to)) = (from, to) {
+            let annotation = match reference.kind {
+                SymbolKind::Component => "component",
+                SymbolKind::Model => "model",
+                SymbolKind::EnvVar => "env",
+                SymbolKind::Route | SymbolKind::Endpoint => continue,
+            };
+            let edge = (from.clone(), to.clone(), Edge::Names(annotation));
+            if from != to && !picture.edges.contains(&edge) {
+                picture.edges.push(edge);
+            }
+        }
+    }
+    for symbol in table.unused_components() {
+        // A shared component is drawn in every target it's shared with
+        for (scope, nodes) in &mut picture.clusters {
+            if let Some(id) = picture.at.get(&at(scope, symbol.span)) {
+                nodes.iter_mut().filter(|node| node.id == *id).for_each(|node| node.unused = true);
+            }
+        }
+    }
+
+    let (graph, _) = Graph::build(&program.targets);
+    for (index, target) in program.targets.iter().enumerate() {
+        for &used in graph.uses(index) {
+            let from = picture.at[&at(&target.qualified_name(), target.span)].clone();
+            let to = picture.at[&at(&program.targets[used].qualified_name(), program.targets[used].span)].clone();
+            picture.edges.push((from, to, Edge::Uses));
+        }
+    }
+    picture
+}
+
+fn dot(picture: &Picture) -> String {
+    let quote = |text: &str| format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\""));
+    let mut out = String::from("digraph program {\n  rankdir=LR;\n  node [fontname=\"Helvetica\", fontsize=11];\n  edge [fontname=\"Helvetica\", fontsize=9];\n");
+    for (index, (name, nodes)) in picture.clusters.iter().enumerate() {
+        out.push_str(&format!("\n  subgraph cluster_{} {{\n    label={};\n", index, quote(name)));
+        for node in nodes {
+            let shape = match node.shape {
+                Shape::Target => "box3d",
+                Shape::Section => "folder",
+                Shape::Route => "box",
+                Shape::Component => "component",
+                Shape::Endpoint => "cds",
+                Shape::Model => "cylinder",
+                Shape::Variable => "note",
+                Shape::Item => "ellipse",
+            };
+            let unused = if node.unused { ", color=red, fontcolor=red, style=dashed" } else { "" };
+            out.push_str(&format!("    {} [label={}, shape={}{}];\n", node.id, quote(&node.label), shape, unused));
+        }
+        out.push_str("  }\n");
+    }
+    out.push('\n');
+    for (from, to, edge) in &picture.edges {
+        let style = match edge {
+            Edge::Contains => String::new(),
+            Edge::Names(annotation) => format!(" [style=dashed, label={}]", quote(&format!("@{}", annotation))),
+            Edge::Uses => " [style=bold, label=\"uses\"]".to_string(),
+        };
+        out.push_str(&format!("  {} -> {}{};\n", from, to, style));
+    }
+    out.push_str("}\n");
+    out
+}
+
+fn mermaid(picture: &Picture) -> String {
+    let quote = |text: &str| format!("\"{}\"", text.replace('"', "#quot;"));
+    let mut out = String::from("flowchart LR\n");
+    let mut unused = Vec::new();
+    for (index, (name, nodes)) in picture.clusters.iter().enumerate() {
+        out.push_str(&format!("  subgraph t{}[{}]\n", index, quote(name)));
+        for node in nodes {
+            let label = quote(&node.label);
+            let shape = match node.shape {
+                Shape::Target => format!("([{}])", label),
+                Shape::Section => format!("{{{{{}}}}}", label),
+                Shape::Route => format!("[{}]", label),
+                Shape::Component => format!("[[{}]]", label),
+                Shape::Endpoint => format!(">{}]", label),
+                Shape::Model => format!("[({})]", label),
+                Shape::Variable => format!("[/{}/]", label),
+                Shape::Item => format!("({})", label),
+            };
+            out.push_str(&format!("    {}{}\n", node.id, shape));
+            if node.unused {
+                unused.push(node.id.as_str());
+            }
+        }
+        out.push_str("  end\n");
+    }
+    for (from, to, edge) in &picture.edges {
+        let arrow = match edge {
+            Edge::Contains => "-->".to_string(),
+            Edge::Names(annotation) => format!("-.->|@{}|", annotation),
+            Edge::Uses => "==>|uses|".to_string(),
+        };
+        out.push_str(&format!("  {} {} {}\n", from, arrow, to));
+    }
+    if !unused.is_empty() {
+        out.push_str("  classDef unused stroke:#d33,color:#d33,stroke-dasharray:4\n");
+        out.push_str(&format!("  class {} unused\n", unused.join(",")));
+    }
+    out
+}
@@ -0,0 +1,556 @@
+use std::path::Path;
+use std::str::FromStr;
+
+/// Indentation unit used for generated code
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Indent {
+    Spaces(usize),
+    Tabs,
+}
+
+impl FromStr for Indent {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "tab" | "tabs" => Ok(Indent::Tabs),
+            n => match n.parse::<usize>() {
+                Ok(width) if (1..=8).contains(&width) => Ok(Indent::Spaces(width)),
+                _ => Err(format!("invalid indent '{}' (expected 1-8 or 'tab')", s)),
+            },
+        }
+    }
+}
+
+/// Quote character for string literals in TypeScript/JavaScript output
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Quotes {
+    Single,
+    Double,
+}
+
+impl Quotes {
+    fn char(self) -> u8 {
+        match self {
+            Quotes::Single => b'\'',
+            Quotes::Double => b'"',
+        }
+    }
+}
+
+impl FromStr for Quotes {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "single" => Ok(Quotes::Single),
+            "double" => Ok(Quotes::Double),
+            _ => Err(format!("invalid quote style '{}' (expected 'single' or 'double')", s)),
+        }
+    }
+}
+
+/// Formatting preferences applied to every generated source file at emit time.
+/// A `None` field keeps whatever the emitter produces natively.
+///
+/// Indentation applies to every language; quotes, semicolons and trailing commas
+/// only affect TypeScript/JavaScript, where they are a matter of taste.
+///
+/// The emitters write their own style, and this rewrites their output line by line
+/// without parsing it, so it leaves alone what it can't change safely that way:
+///
+/// - `indent` rescales leading spaces in steps of the emitter's width (2, or 4 for
+///   Rust and Swift), keeping any remainder as spaces, and leaves the lines inside
+///   multi-line template literals and comments as they are.
+/// - `quotes` swaps the quotes of string literals, but not of strings holding the
+///   other quote or an escape, nor those in comments and template literals.
+/// - `semicolons` adds them after imports, declarations, `return`, `throw` and
+///   assignments outside parentheses, on one line or closing a bracket opened on the
+///   first. Other expression statements (`useEffect(...)`), class and interface
+///   members and statements continued on the next line are left as they are. Removing
+///   them keeps those in `for (...)` and before a line starting with `(`, `[` or `` ` ``.
+/// - `trailing_commas` applies to object and array literals whose closing bracket
+///   starts a line, not to parameter and argument lists.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CodeStyle {
+    pub indent: Option<Indent>,
+    pub quotes: Option<Quotes>,
+    pub semicolons: Option<bool>,
+    pub trailing_commas: Option<bool>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Language {
+    Script,
+    Json,
+    Css,
+    Rust,
+    Swift,
+}
+
+impl Language {
+    fn from_path(path: &Path) -> Option<Self> {
+        match path.extension()?.to_str()? {
+            "ts" | "tsx" | "js" | "jsx" | "mjs" | "cjs" => Some(Language::Script),
+            "json" => Some(Language::Json),
+            "css" => Some(Language::Css),
+            "rs" => Some(Language::Rust),
+            "swift" => Some(Language::Swift),
+            _ => None,
+        }
+    }
+
+    /// Indentation width the built-in emitters use for this language
+    fn native_indent(self) -> usize {
+        match self {
+            Language::Script | Language::Json | Language::Css => 2,
+            Language::Rust | Language::Swift => 4,
+        }
+    }
+}
+
+impl CodeStyle {
+    /// Restyle `code` that is about to be written to `path`. Files in languages
+    /// the style doesn't know about are returned unchanged.
+    pub fn apply(&self, path: &Path, code: &str) -> String {
+        let Some(language) = Language::from_path(path) else {
+            return code.to_string();
+        };
+
+        let mut code = code.to_string();
+        if language == Language::Script {
+            if let Some(quotes) = self.quotes {
+                code = requote(&code, quotes);
+            }
+            if let Some(semicolons) = self.semicolons {
+                code = set_semicolons(&code, semicolons);
+            }
+            if let Some(trailing_commas) = self.trailing_commas {
+                code = set_trailing_commas(&code, trailing_commas);
+            }
+        }
+        if let Some(indent) = self.indent {
+            code = reindent(&code, language, indent);
+        }
+        code
+    }
+}
+
+/// Which bytes of a script are code (as opposed to strings, templates and comments),
+/// which are comments, plus the spans of quoted string literals.
+struct Lexed {
+    code: Vec<bool>,
+    comment: Vec<bool>,
+    strings: Vec<(usize, usize)>,
+}
+
+fn lex_script(src: &str) -> Lexed {
+    let bytes = src.as_bytes();
+    let mut code = vec![true; bytes.len()];
+    let mut comment = vec![false; bytes.len()];
+    let mut strings = Vec::new();
+    let mut i = 0;
+
+    while i < bytes.len() {
+        let start = i;
+        let is_comment = bytes[i] == b'/' && matches!(bytes.get(i + 1), Some(b'/' | b'*'));
+        match bytes[i] {
+            b'/' if bytes.get(i + 1) == Some(&b'/') => {
+                while i < bytes.len() && bytes[i] != b'\n' {
+                    i += 1;
+                }
+            }
+            b'/' if bytes.get(i + 1) == Some(&b'*') => {
+                i += 2;
+                while i < bytes.len() && !(bytes[i] == b'*' && bytes.get(i + 1) == Some(&b'/')) {
+                    i += 1;
+                }
+                i = (i + 2).min(bytes.len());
+            }
+            b'`' => {
+                i += 1;
+                while i < bytes.len() && bytes[i] != b'`' {
+                    i += if bytes[i] == b'\\' { 2 } else { 1 };
+                }
+                i = (i + 1).min(bytes.len());
+            }
+            // Only a quote that follows punctuation opens a string; an apostrophe
+            // inside a word ("Don't") is JSX text, not a literal
+            quote @ (b'\'' | b'"') if i == 0 || !bytes[i - 1].is_ascii_alphanumeric() => {
+                let mut end = i + 1;
+                while end < bytes.len() && bytes[end] != quote && bytes[end] != b'\n' {
+                    end += if bytes[end] == b'\\' { 2 } else { 1 };
+                }
+                if end < bytes.len() && bytes[end] == quote {
+                    i = end + 1;
+                    strings.push((start, i));
+                } else {
+                    i += 1;
+                    continue;
+                }
+            }
+            _ => {
+                i += 1;
+                continue;
+            }
+        }
+        let end = i.min(bytes.len());
+        code[start..end].fill(false);
+        comment[start..end].fill(is_comment);
+    }
+
+    Lexed { code, comment, strings }
+}
+
+fn requote(src: &str, quotes: Quotes) -> String {
+    let target = quotes.char();
+    let lexed = lex_script(src);
+    let mut out = src.as_bytes().to_vec();
+
+    for &(start, end) in &lexed.strings {
+        let inner = &src.as_bytes()[start + 1..end - 1];
+        if src.as_bytes()[start] == target || inner.contains(&target) || inner.contains(&b'\\') {
+            continue;
+        }
+        out[start] = target;
+        out[end - 1] = target;
+    }
+
+    String::from_utf8(out).expect("quote swap keeps UTF-8 intact")
+}
+
+/// Byte ranges of each line, excluding the newline
+fn lines(src: &str) -> Vec<(usize, usize)> {
+    let mut ranges = Vec::new();
+    let mut start = 0;
+    for line in src.split_inclusive('\n') {
+        let end = start + line.trim_end_matches(['\n', '\r']).len();
+        ranges.push((start, end));
+        start += line.len();
+    }
+    ranges
+}
+
+/// Position of the last byte on a line that is neither whitespace nor comment
+fn last_code(src: &[u8], lexed: &Lexed, (start, end): (usize, usize)) -> Option<usize> {
+    (start..end).rev().find(|&i| !lexed.comment[i] && !src[i].is_ascii_whitespace())
+}
+
+fn set_semicolons(src: &str, semicolons: bool) -> String {
+    let lexed = lex_script(src);
+    let bytes = src.as_bytes();
+    let mut remove = Vec::new();
+    let mut insert = Vec::new();
+
+    // For every closing bracket, where the bracket it closes is, and for every byte,
+    // whether it's inside parentheses or square brackets, as JSX attributes are
+    let mut opener = vec![None; bytes.len()];
+    let mut in_parens = vec![false; bytes.len()];
+    let mut stack: Vec<usize> = Vec::new();
+    for i in 0..bytes.len() {
+        in_parens[i] = stack.iter().any(|&open| bytes[open] != b'{');
+        if !lexed.code[i] {
+            continue;
+        }
+        match bytes[i] {
+            b'(' | b'[' | b'{' => stack.push(i),
+            b')' | b']' | b'}' => opener[i] = stack.pop(),
+            _ => {}
+        }
+    }
+
+    for (start, end) in lines(src) {
+        let Some(last) = last_code(bytes, &lexed, (start, end)) else {
+            continue;
+        };
+        let next = src[end..].trim_start();
+
+        if !semicolons {
+            // Without it, a next line opening with a bracket or template would continue the statement
+            let text = src[start..end].trim_start();
+            if bytes[last] == b';'
+                && lexed.code[last]
+                && !text.starts_with("for ")
+                && !text.starts_with("for(")
+                && !next.starts_with(['(', '[', '`'])
+            {
+                remove.push(last);
+            }
+            continue;
+        }
+
+        // A line closing a bracket opened on an earlier line ends the statement that line starts
+        let start = match opener[last] {
+            Some(open) if open < start => src[..open].rfind('\n').map_or(0, |newline| newline + 1),
+            _ => start,
+        };
+        let text = src[start..end].trim_start();
+
+        // Adding semicolons is only safe for complete declarations, returns and assignments
+        let declaration = ["import ", "const ", "let ", "var ", "module.exports", "return ", "return(", "throw "]
+            .iter()
+            .any(|keyword| text.starts_with(keyword))
+            || (!in_parens[start] && assigns(text))
+            || (text.starts_with("export ")
+                && !["interface ", "class ", "function ", "enum "].iter().any(|keyword| text.contains(keyword)));
+        let continued = next.starts_with(['.', '?', ':', '+', '-', '*', '/', '|', '&'])
+            && !next.starts_with("//")
+            && !next.starts_with("/*");
+        let balanced = (start..=last).fold(0i32, |depth, i| match bytes[i] {
+            b'(' | b'[' | b'{' if lexed.code[i] => depth + 1,
+            b')' | b']' | b'}' if lexed.code[i] => depth - 1,
+            _ => depth,
+        }) == 0;
+        let ends_value = !lexed.code[last]
+            || bytes[last].is_ascii_alphanumeric()
+            || matches!(bytes[last], b')' | b']' | b'}' | b'_' | b'$');
+        let ends_block = bytes[last] == b'}' && text.contains("function");
+        if declaration && !continued && balanced && ends_value && !ends_block && bytes[last] != b';' {
+            insert.push(last + 1);
+        }
+    }
+
+    let mut out = String::with_capacity(src.len() + insert.len());
+    let (mut remove, mut insert) = (remove.into_iter().peekable(), insert.into_iter().peekable());
+    for (i, ch) in src.char_indices() {
+        if insert.peek() == Some(&i) {
+            insert.next();
+            out.push(';');
+        }
+        if remove.peek() == Some(&i) {
+            remove.next();
+            continue;
+        }
+        out.push(ch);
+    }
+    if insert.peek().is_some() {
+        out.push(';');
+    }
+    out
+}
+
+/// Whether `text` starts with an assignment to a name or property, as in
+/// `Button.displayName = "Button"`
+fn assigns(text: &str) -> bool {
+    let target = text.trim_start_matches(|c: char| c.is_ascii_alphanumeric() || matches!(c, '_' | '$' | '.'));
+    target.len() < text.len() && target.trim_start().strip_prefix('=').is_some_and(|value| !value.starts_with(['=', '>']))
+}
+
+fn set_trailing_commas(src: &str, trailing_commas: bool) -> String {
+    let lexed = lex_script(src);
+    let bytes = src.as_bytes();
+
+    // For every closing brace/bracket, whether it closes an object or array literal
+    let mut literal_closer = vec![false; bytes.len()];
+    let mut stack: Vec<bool> = Vec::new();
+    for i in 0..bytes.len() {
+        if !lexed.code[i] {
+            continue;
+        }
+        match bytes[i] {
+            b'[' => stack.push(true),
+            b'(' => stack.push(false),
+            b'{' => stack.push(opens_literal(src, &lexed, i)),
+            b'}' | b']' | b')' => literal_closer[i] = stack.pop().unwrap_or(false) && bytes[i] != b')',
+            _ => {}
+        }
+    }
+
+    let lines = lines(src);
+    let mut remove = Vec::new();
+    let mut insert = Vec::new();
+    for (index, &line) in lines.iter().enumerate() {
+        let Some(last) = last_code(bytes, &lexed, line) else {
+            continue;
+        };
+        let next_first = lines[index + 1..].iter().find_map(|&(start, end)| {
+            (start..end).find(|&i| !bytes[i].is_ascii_whitespace())
+        });
+        let Some(next_first) = next_first else {
+            continue;
+        };
+        if !literal_closer[next_first] {
+            continue;
+        }
+
+        // A string or template literal ending the line counts as a value
+        let last_byte = if lexed.code[last] { bytes[last] } else { b'"' };
+        match last_byte {
+            b',' if !trailing_commas => remove.push(last),
+            b',' | b'{' | b'[' | b'(' => {}
+            _ if trailing_commas => insert.push(last + 1),
+            _ => {}
+        }
+    }
+
+    let mut out = String::with_capacity(src.len() + insert.len());
+    let (mut remove, mut insert) = (remove.into_iter().peekable(), insert.into_iter().peekable());
+    for (i, ch) in src.char_indices() {
+        if insert.peek() == Some(&i) {
+            insert.next();
+            out.push(',');
+        }
+        if remove.peek() == Some(&i) {
+            remove.next();
+            continue;
+        }
+        out.push(ch);
+    }
+    out
+}
+
+/// Decide whether the `{` at `pos` starts an object literal (rather than a block)
+/// from the code that precedes it
+fn opens_literal(src: &str, lexed: &Lexed, pos: usize) -> bool {
+    let bytes = src.as_bytes();
+    let Some(prev) = (0..pos).rev().find(|&i| lexed.code[i] && !bytes[i].is_ascii_whitespace()) else {
+        return false;
+    };
+
+    match bytes[prev] {
+        b'=' | b'(' | b',' | b':' | b'[' | b'?' => true,
+        // `=> {` is a function body and `>{` a JSX expression container
+        b'>' => false,
+        _ => {
+            let word_start = (0..=prev).rev()
+                .take_while(|&i| bytes[i].is_ascii_alphanumeric())
+                .last()
+                .unwrap_or(prev);
+            matches!(&src[word_start..=prev], "return" | "import" | "export")
+        }
+    }
+}
+
+fn reindent(src: &str, language: Language, indent: Indent) -> String {
+    let native = language.native_indent();
+    let lexed = (language == Language::Script).then(|| lex_script(src));
+    let mut out = String::with_capacity(src.len());
+    let mut offset = 0;
+
+    for line in src.split_inclusive('\n') {
+        let line_start = offset;
+        offset += line.len();
+
+        // Leave the contents of multi-line template literals and comments alone
+        let inside_literal = lexed.as_ref().is_some_and(|lexed| {
+            line_start > 0 && !lexed.code[line_start - 1] && !lexed.code.get(line_start).copied().unwrap_or(true)
+        });
+        let spaces = line.len() - line.trim_start_matches(' ').len();
+        if inside_literal || spaces == 0 {
+            out.push_str(line);
+            continue;
+        }
+
+        let (levels, rest) = (spaces / native, spaces % native);
+        match indent {
+            Indent::Spaces(width) => out.push_str(&" ".repeat(levels * width + rest)),
+            Indent::Tabs => {
+                out.push_str(&"\t".repeat(levels));
+                out.push_str(&" ".repeat(rest));
+            }
+        }
+        out.push_str(&line[spaces..]);
+    }
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn styled(style: CodeStyle, path: &str, code: &str) -> String {
+        style.apply(Path::new(path), code)
+    }
+
+    #[test]
+    fn indent_rescales_leading_spaces() {
+        let style = CodeStyle { indent: Some(Indent::Spaces(4)), ..CodeStyle::default() };
+        assert_eq!(styled(style.clone(), "a.ts", "if (a) {\n  b()\n   c\n}\n"), "if (a) {\n    b()\n     c\n}\n");
+        assert_eq!(styled(style, "a.rs", "fn a() {\n    b();\n}\n"), "fn a() {\n    b();\n}\n");
+
+        let tabs = CodeStyle { indent: Some(Indent::Tabs), ..CodeStyle::default() };
+        assert_eq!(styled(tabs.clone(), "a.swift", "struct A {\n    var b: Int\n}\n"), "struct A {\n\tvar b: Int\n}\n");
+        assert_eq!(styled(tabs, "a.css", "a {\n  color: red;\n}\n"), "a {\n\tcolor: red;\n}\n");
+    }
+
+    #[test]
+    fn indent_leaves_template_literals_and_unknown_files() {
+        let style = CodeStyle { indent: Some(Indent::Spaces(4)), ..CodeStyle::default() };
+        let code = "const a = `\n  kept\n`\n  moved\n";
+        assert_eq!(styled(style.clone(), "a.js", code), "const a = `\n  kept\n`\n    moved\n");
+        assert_eq!(styled(style, "a.md", "  text\n"), "  text\n");
+    }
+
+    #[test]
+    fn quotes_swap_plain_strings_only() {
+        let single = CodeStyle { quotes: Some(Quotes::Single), ..CodeStyle::default() };
+        assert_eq!(
+            styled(single, "a.tsx", "import a from \"a\"\nconst b = [\"it's\", \"say \\\"hi\\\"\", `x`]\n<p>Don't</p>\n"),
+            "import a from 'a'\nconst b = [\"it's\", \"say \\\"hi\\\"\", `x`]\n<p>Don't</p>\n",
+        );
+        let double = CodeStyle { quotes: Some(Quotes::Double), ..CodeStyle::default() };
+        assert_eq!(styled(double.clone(), "a.js", "const a = 'b' // 'c'\n"), "const a = \"b\" // 'c'\n");
+        assert_eq!(styled(double, "a.json", "{ 'a': 1 }"), "{ 'a': 1 }");
+    }
+
+    #[test]
+    fn semicolons_added_to_statements() {
+        let style = CodeStyle { semicolons: Some(true), ..CodeStyle::default() };
+        let code = "\
+import { a } from 'a'
+const b = cva(
+  'x',
+)
+export function c() {
+  return (
+    <div className={a} ref={b} />
+  )
+}
+Button.displayName = \"Button\"
+export const d = {
+  e: 1,
+}
+const f = a
+  .b()
+useEffect(() => {}, [])
+";
+        let expected = "\
+import { a } from 'a';
+const b = cva(
+  'x',
+);
+export function c() {
+  return (
+    <div className={a} ref={b} />
+  );
+}
+Button.displayName = \"Button\";
+export const d = {
+  e: 1,
+};
+const f = a
+  .b()
+useEffect(() => {}, [])
+";
+        assert_eq!(styled(style, "a.tsx", code), expected);
+    }
+
+    #[test]
+    fn semicolons_removed_where_safe() {
+        let style = CodeStyle { semicolons: Some(false), ..CodeStyle::default() };
+        let code = "const a = 1;\nfor (let i = 0; i < 2; i++) {}\nlet b = c;\n[1, 2].map(d);\nconst s = ';'\n";
+        assert_eq!(styled(style, "a.ts", code), "const a = 1\nfor (let i = 0; i < 2; i++) {}\nlet b = c;\n[1, 2].map(d)\nconst s = ';'\n");
+    }
+
+    #[test]
+    fn trailing_commas_in_multi_line_literals() {
+        let add = CodeStyle { trailing_commas: Some(true), ..CodeStyle::default() };
+        let code = "const a = {\n  b: 1,\n  c: [\n    'd'\n  ]\n}\nf(\n  g\n)\nif (h) {\n  i()\n}\n";
+        let expected = "const a = {\n  b: 1,\n  c: [\n    'd',\n  ],\n}\nf(\n  g\n)\nif (h) {\n  i()\n}\n";
+        assert_eq!(styled(add, "a.ts", code), expected);
+
+        let remove = CodeStyle { trailing_commas: Some(false), ..CodeStyle::default() };
+        assert_eq!(styled(remove, "a.ts", expected), "const a = {\n  b: 1,\n  c: [\n    'd'\n  ]\n}\nf(\n  g\n)\nif (h) {\n  i()\n}\n");
+    }
+}
@@ -0,0 +1,260 @@
+//! Name resolution over the semantic model.
+//!
+//! Every target gets its own scope holding its components, models, routes and
+//! endpoints. References between them are resolved within that scope:
+//! - `@component(Name)` on a route names the component that renders it
+//! - `@model(Name)` on an API endpoint names the model it serves
+//! - field types such as `Order[]` refer to a model when one has that name
+//! - `@env(NAME)` on a route or API endpoint names an environment variable it reads,
+//!   declared in an `env` block of the target, its workspace or the file
+//!
+//! Unknown names in annotations and duplicate definitions are errors; a field
+//! type that matches no model is taken to be a built-in or external type, unless
+//! another target defines a model by that name. A name defined only in another
+//! target is reported as such, since generating the reference would import
+//! something the target doesn't have; `Components` and `Schema` sections outside
+//! any target are shared by all the targets next to them instead.
+
+use std::collections::HashMap;
+
+use z_ast::{Annotation, Span};
+use z_semantic::{Program, Route, SectionBody, SemanticError, Target};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SymbolKind {
+    Component,
+    Model,
+    Route,
+    Endpoint,
+    EnvVar,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Symbol {
+    pub kind: SymbolKind,
+    /// Component or model name, route path (`/customers/[id]`), or endpoint
+    /// path with its method if it has one (`GET /api/users`)
+    pub name: String,
+    /// `kind:name` of the target that defines it
+    pub target: String,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Reference {
+    pub kind: SymbolKind,
+    pub name: String,
+    /// `kind:name` of the target the reference appears in
+    pub target: String,
+    /// The route, endpoint or field making the reference
+    pub span: Span,
+    /// Index into [`SymbolTable::symbols`], if the name resolved
+    pub symbol: Option<usize>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct SymbolTable {
+    pub symbols: Vec<Symbol>,
+    pub references: Vec<Reference>,
+    index: HashMap<(String, SymbolKind, String), usize>,
+}
+
+impl SymbolKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SymbolKind::Component => "component",
+            SymbolKind::Model => "model",
+            SymbolKind::Route => "route",
+            SymbolKind::Endpoint => "endpoint",
+            SymbolKind::EnvVar => "environment variable",
+        }
+    }
+}
+
+impl SymbolTable {
+    /// Find a symbol defined in `target` (given as `kind:name`)
+    pub fn lookup(&self, target: &str, kind: SymbolKind, name: &str) -> Option<&Symbol> {
+        self.index.get(&(target.to_string(), kind, name.to_string())).map(|&i| &self.symbols[i])
+    }
+
+    /// Resolved references pointing at the symbol with the given index
+    pub fn references_to(&self, symbol: usize) -> impl Iterator<Item = &Reference> {
+        self.references.iter().filter(move |reference| reference.symbol == Some(symbol))
+    }
+
+    /// Components no route renders, in targets whose routes name the components that
+    /// render them. A shared component counts as used when any target's route uses it,
+    /// and is listed once.
+    pub fn unused_components(&self) -> Vec<&Symbol> {
+        let rendering: Vec<&str> = self.references.iter()
+            .filter(|reference| reference.kind == SymbolKind::Component)
+            .map(|reference| reference.target.as_str())
+            .collect();
+        let used: Vec<Span> = self.references.iter()
+            .filter_map(|reference| reference.symbol)
+            .map(|symbol| self.symbols[symbol].span)
+            .collect();
+        let mut unused: Vec<&Symbol> = self.symbols.iter()
+            .filter(|symbol| symbol.kind == SymbolKind::Component && rendering.contains(&symbol.target.as_str()))
+            .filter(|symbol| !used.contains(&symbol.span))
+            .collect();
+        unused.sort_by_key(|symbol| symbol.span.start);
+        unused.dedup_by_key(|symbol| symbol.span);
+        unused
+    }
+
+    fn define(&mut self, kind: SymbolKind, name: String, target: &str, span: Span, errors: &mut Vec<SemanticError>) {
+        let key = (target.to_string(), kind, name.clone());
+        if self.index.contains_key(&key) {
+            errors.push(SemanticError { message: format!("duplicate {} '{}'", kind.as_str(), name), span });
+            return;
+        }
+        self.index.insert(key, self.symbols.len());
+        self.symbols.push(Symbol { kind, name, target: target.to_string(), span });
+    }
+
+    /// Record a reference; `required` ones that don't resolve are errors, and so are
+    /// others naming a symbol of another target
+    fn refer(&mut self, kind: SymbolKind, name: &str, target: &str, span: Span, required: bool, errors: &mut Vec<SemanticError>) {
+        let symbol = self.index.get(&(target.to_string(), kind, name.to_string())).copied();
+        if symbol.is_none() {
+            let elsewhere: Vec<&str> = self.symbols.iter()
+                .filter(|symbol| symbol.kind == kind && symbol.name == name)
+                .map(|symbol| symbol.target.as_str())
+                .collect();
+            if !elsewhere.is_empty() {
+                let home = match kind {
+                    SymbolKind::Model => "a Schema section",
+                    SymbolKind::EnvVar => "an env block",
+                    _ => "a Components section",
+                };
+                errors.push(SemanticError {
+                    message: format!(
+                        "{} '{}' is defined in {}, not in {}; move it to {} outside the targets to share it",
+                        kind.as_str(), name, elsewhere.join(", "), target, home,
+                    ),
+                    span,
+                });
+            } else if required {
+                errors.push(SemanticError { message: format!("unknown {} '{}'", kind.as_str(), name), span });
+            } else {
+                return;
+            }
+        }
+        self.references.push(Reference { kind, name: name.to_string(), target: target.to_string(), span, symbol });
+    }
+}
+
+/// Build the symbol table for `program` and resolve every reference in it
+pub fn resolve(program: &Program) -> (SymbolTable, Vec<SemanticError>) {
+    let mut table = SymbolTable::default();
+    let mut errors = Vec::new();
+
+    for target in &program.targets {
+        define_target(&mut table, target, &mut errors);
+    }
+    for target in &program.targets {
+        resolve_target(&mut table, target, &mut errors);
+    }
+
+    errors.sort_by_key(|error| error.span.start);
+    // Shared sections are defined once per target; report their problems once
+    errors.dedup();
+    (table, errors)
+}
+
+fn define_target(table: &mut SymbolTable, target: &Target, errors: &mut Vec<SemanticError>) {
+    let scope = target.qualified_name();
+    for variable in &target.env {
+        table.define(SymbolKind::EnvVar, variable.name.clone(), &scope, variable.span, errors);
+    }
+    for section in &target.sections {
+        match &section.body {
+            SectionBody::Components(components) => {
+                for component in components {
+                    table.define(SymbolKind::Component, component.name.clone(), &scope, component.span, errors);
+                }
+            }
+            SectionBody::Models(models) => {
+                for model in models {
+                    table.define(SymbolKind::Model, model.name.clone(), &scope, model.span, errors);
+                }
+            }
+            SectionBody::Routes(routes) => define_routes(table, routes, "", &scope, errors),
+            SectionBody::Endpoints(endpoints) => {
+                for endpoint in endpoints {
+                    let path = format!("/api/{}", endpoint.name);
+                    let name = match &endpoint.method {
+                        Some(method) => format!("{} {}", method.to_uppercase(), path),
+                        None => path,
+                    };
+                    table.define(SymbolKind::Endpoint, name, &scope, endpoint.span, errors);
+                }
+            }
+            SectionBody::Settings(_) | SectionBody::Store(_) | SectionBody::Deploy(_) | SectionBody::Items(_) => {}
+        }
+    }
+}
+
+fn define_routes(table: &mut SymbolTable, routes: &[Route], parent: &str, scope: &str, errors: &mut Vec<SemanticError>) {
+    for route in routes {
+        let path = route.path(parent);
+        table.define(SymbolKind::Route, path.clone(), scope, route.span, errors);
+        define_routes(table, &route.children, &path, scope, errors);
+    }
+}
+
+fn resolve_target(table: &mut SymbolTable, target: &Target, errors: &mut Vec<SemanticError>) {
+    let scope = target.qualified_name();
+    for section in &target.sections {
+        match &section.body {
+            SectionBody::Routes(routes) => resolve_routes(table, routes, &scope, errors),
+            SectionBody::Endpoints(endpoints) => {
+                for endpoint in endpoints {
+                    for name in annotation_names(&endpoint.annotations, "model") {
+                        table.refer(SymbolKind::Model, name, &scope, endpoint.span, true, errors);
+                    }
+                    for name in annotation_names(&endpoint.annotations, "env") {
+                        table.refer(SymbolKind::EnvVar, name, &scope, endpoint.span, true, errors);
+                    }
+                }
+            }
+            SectionBody::Models(models) => {
+                for field in models.iter().flat_map(|model| &model.fields) {
+                    table.refer(SymbolKind::Model, base_type(&field.ty), &scope, field.span, false, errors);
+                }
+            }
+            SectionBody::Components(_)
+            | SectionBody::Settings(_)
+            | SectionBody::Store(_)
+            | SectionBody::Deploy(_)
+            | SectionBody::Items(_) => {}
+        }
+    }
+}
+
+fn resolve_routes(table: &mut SymbolTable, routes: &[Route], scope: &str, errors: &mut Vec<SemanticError>) {
+    for route in routes {
+        for name in annotation_names(&route.annotations, "component") {
+            table.refer(SymbolKind::Component, name, scope, route.span, true, errors);
+        }
+        for name in annotation_names(&route.annotations, "env") {
+            table.refer(SymbolKind::EnvVar, name, scope, route.span, true, errors);
+        }
+        resolve_routes(table, &route.children, scope, errors);
+    }
+}
+
+/// Arguments of every `@annotation(Name)` with the given name
+fn annotation_names<'a>(annotations: &'a [Annotation], annotation: &'a str) -> impl Iterator<Item = &'a str> {
+    annotations.iter()
+        .filter(move |candidate| candidate.name == annotation)
+        .filter_map(|candidate| candidate.args.as_deref())
+        .map(str::trim)
+}
+
+/// `Order` for `Order[]`, `Order?` or `Order | null`
+fn base_type(ty: &str) -> &str {
+    let ty = ty.split('|').next().unwrap_or(ty).trim();
+    ty.trim_end_matches(['[', ']', '?'])
+}
@@ -0,0 +1,219 @@
+//! Options of the target compilers: the choices a compiler makes for the projects it
+//! generates, like the Next.js generation or the runtime, made per target or for
+//! every target of a type.
+//!
+//! The options a target type takes are its `fields` in the registry. A compiler gets
+//! them as [`CompilerOptions`], each from the first place that sets it:
+//!
+//! - a property in the target block, `framework: next15`;
+//! - the `@options(...)` annotation of the target block,
+//!   `next site @options(framework: next15, runtime: bun) { ... }`;
+//! - the `[targets.<target type>]` table of `z.toml`, for every target of the type:
+//!
+//! ```toml
+//! [targets.next]
+//! runtime = "bun"
+//! ```
+//!
+//! Without any, the compiler's default applies.
+
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use serde_json::Value;
+use z_semantic::{SemanticError, Target};
+
+use crate::compilers::Outputs;
+use crate::hooks::AFTER_GENERATE;
+use crate::schema::{join_or, suggestion};
+
+/// The `[targets.<target type>]` tables of `z.toml`
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TargetOptions {
+    by_kind: BTreeMap<String, BTreeMap<String, String>>,
+}
+
+impl TargetOptions {
+    /// Read the `targets` table of a parsed `z.toml`
+    pub fn from_config(config: &Value) -> Result<Self, String> {
+        let mut options = Self::default();
+        let Some(kinds) = config.get("targets") else {
+            return Ok(options);
+        };
+        let kinds = kinds.as_object().ok_or("'targets' must be a table of target types")?;
+        for (kind, table) in kinds {
+            let table = table.as_object()
+                .ok_or_else(|| format!("'targets.{}' must be a table of options", kind))?;
+            let mut values = BTreeMap::new();
+            // Commands, not options; see `hooks`
+            for (key, value) in table.iter().filter(|(key, _)| *key != AFTER_GENERATE) {
+                let value = match value {
+                    Value::String(value) => value.clone(),
+                    Value::Number(_) | Value::Bool(_) => value.to_string(),
+                    _ => return Err(format!("'targets.{}.{}' must be a string, a number or a boolean", kind, key)),
+                };
+                values.insert(key.clone(), value);
+            }
+            options.by_kind.insert(kind.clone(), values);
+        }
+        Ok(options)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.by_kind.values().all(BTreeMap::is_empty)
+    }
+
+    /// Options for every target of type `kind`
+    pub fn for_kind(&self, kind: &str) -> Option<&BTreeMap<String, String>> {
+        self.by_kind.get(kind)
+    }
+
+    /// Options `registry` doesn't list for their target type, one line each
+    pub(crate) fn check(&self, registry: &Value) -> Vec<String> {
+        let mut errors = Vec::new();
+        for (kind, values) in &self.by_kind {
+            if !registry["targets"][kind.as_str()].is_object() {
+                continue;
+            }
+            let known = option_names(kind, registry);
+            for key in values.keys().filter(|key| !known.contains(&key.as_str())) {
+                errors.push(format!("'targets.{}.{}' in z.toml: {}", kind, key, unknown(key, kind, &known)));
+            }
+        }
+        errors
+    }
+}
+
+/// The options one target is compiled with
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CompilerOptions {
+    values: BTreeMap<String, String>,
+    environment: Option<String>,
+    uses: Vec<Prerequisite>,
+    used_by: Vec<String>,
+}
+
+/// A target another one [uses](z_semantic::Target::uses), generated before it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Prerequisite {
+    /// Its qualified name, e.g. `next:Web`
+    pub target: String,
+    /// Where it was generated, relative to the output directory of the target using it,
+    /// e.g. `../Web`
+    pub dir: PathBuf,
+    pub outputs: Outputs,
+}
+
+impl CompilerOptions {
+    /// The options with the target compiled for the [environment](crate::environment) `name`
+    pub(crate) fn in_environment(self, name: Option<&str>) -> Self {
+        Self { environment: name.map(str::to_string), ..self }
+    }
+
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.values.get(key).map(String::as_str)
+    }
+
+    /// The option `key` parsed, or `T`'s default when nothing sets it
+    pub fn parse<T: FromStr<Err = String> + Default>(&self, key: &str) -> Result<T, String> {
+        self.get(key).map_or(Ok(T::default()), str::parse)
+    }
+
+    pub fn values(&self) -> &BTreeMap<String, String> {
+        &self.values
+    }
+
+    /// The [environment](crate::Environments) being compiled for, e.g. `prod`, if any
+    pub fn environment(&self) -> Option<&str> {
+        self.environment.as_deref()
+    }
+
+    /// The options with the types of the targets that use the target
+    pub(crate) fn used_by_kinds(self, used_by: Vec<String>) -> Self {
+        Self { used_by, ..self }
+    }
+
+    /// The options with the targets the target uses, once they were generated
+    pub(crate) fn after(self, uses: Vec<Prerequisite>) -> Self {
+        Self { uses, ..self }
+    }
+
+    /// The targets the target uses, in the order it lists them
+    pub fn uses(&self) -> &[Prerequisite] {
+        &self.uses
+    }
+
+    /// The types of the targets that use this one, e.g. `tauri`
+    pub fn used_by(&self) -> &[String] {
+        &self.used_by
+    }
+}
+
+/// The options of `target`, and the problems with its `@options(...)`
+pub(crate) fn resolve(target: &Target, defaults: &TargetOptions, registry: &Value) -> (CompilerOptions, Vec<SemanticError>) {
+    let known = option_names(&target.kind, registry);
+    let mut errors = Vec::new();
+    let mut values = defaults.for_kind(&target.kind).cloned().unwrap_or_default();
+
+    let annotated = target.annotations.iter().filter(|annotation| annotation.name == "options");
+    for args in annotated.map(|annotation| annotation.args.as_deref().unwrap_or_default()) {
+        for pair in split_args(args).into_iter().filter(|pair| !pair.trim().is_empty()) {
+            let Some((key, value)) = pair.split_once(':') else {
+                errors.push(SemanticError {
+                    message: format!("@options takes `name: value` pairs separated by commas, found '{}'", pair.trim()),
+                    span: target.span,
+                });
+                continue;
+            };
+            let key = key.trim();
+            if !known.contains(&key) {
+                errors.push(SemanticError { message: format!("@options: {}", unknown(key, &target.kind, &known)), span: target.span });
+                continue;
+            }
+            values.insert(key.to_string(), value.trim().trim_matches('"').to_string());
+        }
+    }
+    // Properties of the block are checked against the same fields by the schema check
+    for setting in target.settings.iter().filter(|setting| known.contains(&setting.key.as_str())) {
+        values.insert(setting.key.clone(), setting.value.trim().trim_matches('"').to_string());
+    }
+    (CompilerOptions { values, ..CompilerOptions::default() }, errors)
+}
+
+fn option_names<'a>(kind: &str, registry: &'a Value) -> Vec<&'a str> {
+    registry["targets"][kind]["fields"].as_object()
+        .map(|fields| fields.keys().map(String::as_str).collect())
+        .unwrap_or_default()
+}
+
+fn unknown(key: &str, kind: &str, known: &[&str]) -> String {
+    if known.is_empty() {
+        return format!("{} targets take no options, so '{}' does nothing", kind, key);
+    }
+    let suggested = suggestion(key, known);
+    if suggested.is_empty() {
+        format!("unknown option '{}' for {} targets, which take {}", key, kind, join_or(known))
+    } else {
+        format!("unknown option '{}' for {} targets{}", key, kind, suggested)
+    }
+}
+
+/// `args` split at the commas outside quotes
+fn split_args(args: &str) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut start = 0;
+    let mut quoted = false;
+    for (index, c) in args.char_indices() {
+        match c {
+            '"' => quoted = !quoted,
+            ',' if !quoted => {
+                parts.push(&args[start..index]);
+                start = index + 1;
+            }
+            _ => {}
+        }
+    }
+    parts.push(&args[start..]);
+    parts
+}
@@ -0,0 +1,220 @@
+//! Opt-in usage metrics: which target types are built, how long builds take and
+//! which diagnostics come up, to tell which compilers deserve the most work.
+//!
+//! Nothing is recorded until the user runs `z telemetry on`. Each compile then adds
+//! one [`Event`] to a local queue, `~/.config/z/telemetry-queue.jsonl`, which
+//! `z telemetry show` prints. Events never hold names, paths, source code or
+//! messages: only built-in target types (plugins count as `plugin`), durations,
+//! diagnostic codes, the compiler version and the platform, with the day rather than
+//! the time. The queue is only sent when an endpoint is configured, in batches of
+//! [`BATCH_SIZE`]; see `doc/telemetry.md` for the payload.
+//!
+//! `Z_TELEMETRY=off` or `DO_NOT_TRACK=1` turns recording off whatever the setting.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+use serde::Serialize;
+use serde_json::{json, Value};
+
+use crate::report::Status;
+use crate::sandbox::config_dir;
+use crate::store::{read_json, write_json};
+use crate::{tr, CompileResult};
+
+/// Version of the event and payload layout
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// Environment variable that turns recording off when set to `off`
+pub const TELEMETRY_ENV: &str = "Z_TELEMETRY";
+
+/// Environment variable naming the endpoint, instead of the one in the settings
+pub const ENDPOINT_ENV: &str = "Z_TELEMETRY_ENDPOINT";
+
+/// Events sent at once; fewer wait in the queue
+pub const BATCH_SIZE: usize = 20;
+
+/// Most events kept while they can't be sent; older ones are dropped
+const QUEUE_LIMIT: usize = 500;
+
+/// The user's choice, from `~/.config/z/telemetry.json`
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Settings {
+    pub enabled: bool,
+    /// Where batches are posted; without one, events stay in the queue
+    pub endpoint: Option<String>,
+}
+
+impl Settings {
+    /// The saved settings; off when there are none
+    pub fn load() -> Self {
+        let Some(path) = settings_file() else {
+            return Self::default();
+        };
+        let saved = read_json(&path).unwrap_or_default();
+        Self {
+            enabled: saved["enabled"] == true,
+            endpoint: saved["endpoint"].as_str().map(str::to_string),
+        }
+    }
+
+    pub fn save(&self) -> Result<(), String> {
+        let path = settings_file().ok_or("no home directory to save telemetry settings in")?;
+        write_json(&path, &json!({ "enabled": self.enabled, "endpoint": self.endpoint }))?;
+        // Turning telemetry off drops whatever hadn't been sent
+        if !self.enabled {
+            if let Some(queue) = queue_file() {
+                let _ = fs::remove_file(queue);
+            }
+        }
+        Ok(())
+    }
+
+    /// The endpoint batches go to, if any
+    pub fn endpoint(&self) -> Option<String> {
+        std::env::var(ENDPOINT_ENV).ok().filter(|endpoint| !endpoint.is_empty()).or_else(|| self.endpoint.clone())
+    }
+}
+
+/// Why events aren't recorded even though the user turned telemetry on, if they aren't
+pub fn disabled_by_environment() -> Option<&'static str> {
+    if std::env::var(TELEMETRY_ENV).is_ok_and(|value| value == "off" || value == "0") {
+        return Some(TELEMETRY_ENV);
+    }
+    if std::env::var("DO_NOT_TRACK").is_ok_and(|value| value == "1" || value == "true") {
+        return Some("DO_NOT_TRACK");
+    }
+    None
+}
+
+/// What one compile adds to the queue
+#[derive(Debug, Clone, Serialize)]
+pub struct Event {
+    pub schema_version: u32,
+    /// UTC day of the compile, `YYYY-MM-DD`
+    pub day: String,
+    pub compiler_version: &'static str,
+    /// `linux`, `macos` or `windows`
+    pub os: &'static str,
+    pub arch: &'static str,
+    /// Whether it ran under CI, which builds differently from people
+    pub ci: bool,
+    pub status: Status,
+    pub duration_ms: u64,
+    pub targets: Vec<TargetEvent>,
+    /// How many times each diagnostic code came up
+    pub diagnostics: BTreeMap<&'static str, usize>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TargetEvent {
+    /// Built-in target type, or `plugin`
+    pub kind: String,
+    pub status: Status,
+    pub duration_ms: u64,
+    pub cached: bool,
+    pub unchanged: bool,
+}
+
+impl Event {
+    /// The event for a compile with outcome `result`
+    pub fn of(result: &CompileResult) -> Self {
+        let registry = crate::registry::built_in();
+        let mut diagnostics = BTreeMap::new();
+        for diagnostic in &result.diagnostics {
+            *diagnostics.entry(diagnostic.code).or_insert(0) += 1;
+        }
+        Self {
+            schema_version: SCHEMA_VERSION,
+            day: crate::version::today(),
+            compiler_version: env!("CARGO_PKG_VERSION"),
+            os: std::env::consts::OS,
+            arch: std::env::consts::ARCH,
+            ci: std::env::var_os("CI").is_some(),
+            status: if result.has_errors() { Status::Failed } else { Status::Success },
+            duration_ms: result.duration.as_millis() as u64,
+            targets: result.artifacts.iter()
+                .map(|artifact| TargetEvent {
+                    // Plugin names can say what a project is about
+                    kind: if registry["targets"][artifact.kind.as_str()].is_object() { artifact.kind.clone() } else { "plugin".to_string() },
+                    status: artifact.status,
+                    duration_ms: artifact.duration_ms,
+                    cached: artifact.cached,
+                    unchanged: artifact.unchanged,
+                })
+                .collect(),
+            diagnostics,
+        }
+    }
+}
+
+/// Queue the event for `result` if the user opted in, and send the queue once a batch
+/// is ready. Telemetry never fails a build, so problems are only returned for the
+/// caller to ignore or log.
+pub fn record(result: &CompileResult) -> Result<(), String> {
+    let settings = Settings::load();
+    if !settings.enabled || disabled_by_environment().is_some() {
+        return Ok(());
+    }
+    let queue = queue_file().ok_or("no home directory to queue telemetry in")?;
+    let mut events = queued();
+    events.push(serde_json::to_value(Event::of(result)).expect("events are serializable"));
+    let excess = events.len().saturating_sub(QUEUE_LIMIT);
+    events.drain(..excess);
+
+    if let Some(endpoint) = settings.endpoint().filter(|_| events.len() >= BATCH_SIZE) {
+        if send(&endpoint, &events).is_ok() {
+            events.clear();
+        }
+    }
+    write_queue(&queue, &events)
+}
+
+/// The events waiting to be sent, oldest first
+pub fn queued() -> Vec<Value> {
+    let Some(contents) = queue_file().and_then(|path| fs::read_to_string(path).ok()) else {
+        return Vec::new();
+    };
+    contents.lines().filter_map(|line| serde_json::from_str(line).ok()).collect()
+}
+
+/// Where the events wait
+pub fn queue_file() -> Option<PathBuf> {
+    Some(config_dir()?.join("telemetry-queue.jsonl"))
+}
+
+fn settings_file() -> Option<PathBuf> {
+    Some(config_dir()?.join("telemetry.json"))
+}
+
+fn write_queue(path: &Path, events: &[Value]) -> Result<(), String> {
+    if events.is_empty() {
+        let _ = fs::remove_file(path);
+        return Ok(());
+    }
+    if let Some(parent) = path.parent() {
+        crate::store::create_dir(parent)?;
+    }
+    let lines: String = events.iter().map(|event| format!("{}\n", event)).collect();
+    fs::write(path, lines).map_err(|e| tr!("write-failed", path = path.display(), error = e))
+}
+
+/// Post `events` to `endpoint`, giving up quickly so a slow endpoint doesn't hold up builds
+fn send(endpoint: &str, events: &[Value]) -> Result<(), String> {
+    let payload = json!({ "schema_version": SCHEMA_VERSION, "events": events });
+    let mut child = Command::new("curl")
+        .args(["-sf", "--max-time", "3", "-X", "POST", "-H", "Content-Type: application/json", "--data-binary", "@-", endpoint])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .map_err(|e| tr!("tool-exec-failed", tool = "curl", error = e))?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(payload.to_string().as_bytes()).map_err(|e| tr!("tool-exec-failed", tool = "curl", error = e))?;
+    }
+    let status = child.wait().map_err(|e| tr!("tool-exec-failed", tool = "curl", error = e))?;
+    if status.success() { Ok(()) } else { Err(tr!("tool-failed", tool = "curl")) }
+}
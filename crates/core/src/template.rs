@@ -0,0 +1,272 @@
+//! Reusable blocks: `template Name(params) { ... }` and `use Name(args)`.
+//!
+//! Templates are defined at the top level. A `use` line naming one, anywhere in the
+//! program, becomes a [`Node::Expansion`] holding a copy of the template body in
+//! which every `$param` is replaced by the raw text of its argument, string literals
+//! included. Everything in the copy takes the span of the `use` line, so errors in
+//! it point at the call site.
+//!
+//! Definitions stay in the tree, and lowering skips them. A `use` line naming no
+//! template is left alone, since code targets have `use` declarations of their own.
+
+use std::collections::HashMap;
+
+use z_ast::{assign_ids, Element, Node, Span};
+
+use crate::line_number;
+
+/// Upper bound on the nodes expansions may add, so templates using each other
+/// several times over can't blow up exponentially
+const MAX_EXPANDED_NODES: usize = 200_000;
+
+struct Template {
+    params: Vec<String>,
+    body: Vec<Node>,
+}
+
+struct Expander<'a> {
+    templates: HashMap<String, Template>,
+    source: &'a str,
+    /// Templates being expanded, outermost first, to catch ones that use themselves
+    stack: Vec<String>,
+    expanded_nodes: usize,
+}
+
+/// Expand every `use` of a template in `program`, parsed from `source`
+pub fn expand_templates(program: &mut Element, source: &str) -> Result<(), String> {
+    let mut templates = HashMap::new();
+    for node in &program.children {
+        let Node::Element(element) = node else {
+            continue;
+        };
+        let Some(name) = element.name.strip_prefix("template:") else {
+            continue;
+        };
+        let at = |message: String| format!("line {}: {}", line_number(source, element.span.start), message);
+        let params = parameters(element.signature.as_deref()).map_err(|e| at(format!("template '{}': {}", name, e)))?;
+        let template = Template { params, body: element.children.clone() };
+        if templates.insert(name.to_string(), template).is_some() {
+            return Err(at(format!("template '{}' is defined twice", name)));
+        }
+    }
+    if templates.is_empty() {
+        return Ok(());
+    }
+
+    let mut expander = Expander { templates, source, stack: Vec::new(), expanded_nodes: 0 };
+    for node in &mut program.children {
+        match node {
+            Node::Element(element) if element.name.starts_with("template:") => {}
+            node => expander.node(node)?,
+        }
+    }
+    // Expanded blocks get ids as if they had been written where the template is used
+    assign_ids(program);
+    Ok(())
+}
+
+/// The names in a `(a, b)` parameter list
+fn parameters(signature: Option<&str>) -> Result<Vec<String>, String> {
+    let Some(signature) = signature else {
+        return Ok(Vec::new());
+    };
+    let inner = parenthesized(signature)
+        .ok_or_else(|| format!("expected a parameter list like '(model)', found '{}'", signature.trim()))?;
+    let mut params: Vec<String> = Vec::new();
+    for param in split_arguments(inner) {
+        let valid = param.chars().next().is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+            && param.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+        if !valid {
+            return Err(format!("invalid parameter '{}'", param));
+        }
+        if params.contains(&param) {
+            return Err(format!("parameter '{}' is declared twice", param));
+        }
+        params.push(param);
+    }
+    Ok(params)
+}
+
+/// The text inside `(...)`
+fn parenthesized(text: &str) -> Option<&str> {
+    text.trim().strip_prefix('(')?.strip_suffix(')')
+}
+
+/// Split at commas that aren't inside brackets or string literals, trimming each part
+fn split_arguments(text: &str) -> Vec<String> {
+    let mut parts = Vec::new();
+    let mut depth = 0usize;
+    let mut quote = None;
+    let mut escaped = false;
+    let mut current = String::new();
+    for c in text.chars() {
+        match quote {
+            Some(_) if escaped => escaped = false,
+            Some(_) if c == '\\' => escaped = true,
+            Some(q) if c == q => quote = None,
+            Some(_) => {}
+            None => match c {
+                '"' | '\'' | '`' => quote = Some(c),
+                '(' | '[' | '{' => depth += 1,
+                ')' | ']' | '}' => depth = depth.saturating_sub(1),
+                ',' if depth == 0 => {
+                    parts.push(current.trim().to_string());
+                    current.clear();
+                    continue;
+                }
+                _ => {}
+            },
+        }
+        current.push(c);
+    }
+    if !current.trim().is_empty() || !parts.is_empty() {
+        parts.push(current.trim().to_string());
+    }
+    parts
+}
+
+impl Expander<'_> {
+    fn error(&self, span: Span, message: String) -> String {
+        format!("line {}: {}", line_number(self.source, span.start), message)
+    }
+
+    fn node(&mut self, node: &mut Node) -> Result<(), String> {
+        match node {
+            Node::ChildLine { modifier: Some(modifier), id, signature, annotations, span, trivia }
+                if modifier == "use" && self.templates.contains_key(id.as_str()) =>
+            {
+                if !annotations.is_empty() {
+                    return Err(self.error(*span, format!("'use {}' can't take annotations", id)));
+                }
+                let args = match signature.as_deref() {
+                    Some(signature) => Some(parenthesized(signature).ok_or_else(|| {
+                        self.error(*span, format!("expected arguments like 'use {}(...)'", id))
+                    })?),
+                    None => None,
+                };
+                let children = self.expand(id, args, *span)?;
+                *node = Node::Expansion {
+                    template: std::mem::take(id),
+                    args: args.map(str::to_string),
+                    children,
+                    span: *span,
+                    trivia: std::mem::take(trivia),
+                };
+            }
+            Node::Element(element) => {
+                for child in &mut element.children {
+                    self.node(child)?;
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// The body of `name` for a `use` at `span`, with `args` (the text between the
+    /// parentheses) bound and nested uses expanded
+    fn expand(&mut self, name: &str, args: Option<&str>, span: Span) -> Result<Vec<Node>, String> {
+        if self.stack.iter().any(|open| open == name) {
+            let mut chain = self.stack.clone();
+            chain.push(name.to_string());
+            return Err(self.error(span, format!("template '{}' uses itself ({})", name, chain.join(" > "))));
+        }
+
+        let template = &self.templates[name];
+        let args = args.map(split_arguments).unwrap_or_default();
+        if args.len() != template.params.len() {
+            let expected = match template.params.len() {
+                1 => "1 argument".to_string(),
+                n => format!("{} arguments", n),
+            };
+            return Err(self.error(span, format!("template '{}' takes {}, got {}", name, expected, args.len())));
+        }
+        if args.iter().any(String::is_empty) {
+            return Err(self.error(span, format!("empty argument to template '{}'", name)));
+        }
+
+        let bindings: Vec<(&str, &str)> = template.params.iter().map(String::as_str).zip(args.iter().map(String::as_str)).collect();
+        let mut body = template.body.clone();
+        for node in &mut body {
+            instantiate(node, &bindings, span, &mut self.expanded_nodes);
+        }
+        if self.expanded_nodes > MAX_EXPANDED_NODES {
+            return Err(self.error(span, format!("expanding templates adds more than {} nodes", MAX_EXPANDED_NODES)));
+        }
+
+        self.stack.push(name.to_string());
+        let result = body.iter_mut().try_for_each(|node| self.node(node));
+        self.stack.pop();
+        result.map(|()| body)
+    }
+}
+
+/// Bind the parameters in `node` and move it to `span`, counting the nodes
+fn instantiate(node: &mut Node, bindings: &[(&str, &str)], span: Span, count: &mut usize) {
+    *count += 1;
+    let bind = |text: &mut String| *text = substitute(text, bindings);
+    match node {
+        Node::Element(element) => {
+            bind(&mut element.name);
+            element.signature.iter_mut().for_each(bind);
+            element.annotations.iter_mut().filter_map(|annotation| annotation.args.as_mut()).for_each(bind);
+            element.span = span;
+            for child in &mut element.children {
+                instantiate(child, bindings, span, count);
+            }
+        }
+        Node::ChildLine { modifier, id, signature, annotations, span: line_span, .. } => {
+            modifier.iter_mut().for_each(bind);
+            bind(id);
+            signature.iter_mut().for_each(bind);
+            annotations.iter_mut().filter_map(|annotation| annotation.args.as_mut()).for_each(bind);
+            *line_span = span;
+        }
+        Node::KeyValue { key, value, span: property_span, .. } => {
+            bind(key);
+            bind(value);
+            *property_span = span;
+        }
+        Node::Const { value, span: const_span, .. } => {
+            bind(value);
+            *const_span = span;
+        }
+        Node::Expansion { args, children, span: expansion_span, .. } => {
+            args.iter_mut().for_each(bind);
+            *expansion_span = span;
+            for child in children {
+                instantiate(child, bindings, span, count);
+            }
+        }
+    }
+}
+
+/// Replace `$param` with its argument; other `$name`s are left for constants
+fn substitute(text: &str, bindings: &[(&str, &str)]) -> String {
+    if !text.contains('$') {
+        return text.to_string();
+    }
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(at) = rest.find('$') {
+        out.push_str(&rest[..at]);
+        let after = &rest[at + 1..];
+        let name_len = after.bytes().take_while(|c| c.is_ascii_alphanumeric() || *c == b'_').count();
+        match bindings.iter().find(|(param, _)| *param == &after[..name_len]) {
+            Some((_, value)) if name_len > 0 => out.push_str(value),
+            _ => out.push_str(&rest[at..at + 1 + name_len]),
+        }
+        rest = &after[name_len..];
+    }
+    out.push_str(rest);
+    out
+}
+
+/// The template a diagnostic at `span` came from, when it's in an expansion
+pub(crate) fn expanded_from(program: &Element, span: Span) -> Option<&str> {
+    program.children.iter().find_map(|node| match node {
+        Node::Expansion { template, span: call, .. } if *call == span => Some(template.as_str()),
+        Node::Element(element) => expanded_from(element, span),
+        _ => None,
+    })
+}
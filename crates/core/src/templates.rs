@@ -0,0 +1,377 @@
+//! Templates of the files compilers generate, which projects can replace.
+//!
+//! The fixed parts of the output (layouts, entry points, configuration files) are
+//! rendered from templates under `shared/templates/<target type>/`, embedded in the
+//! compiler. A file at the same path in a templates directory is used instead, so the
+//! output can be changed without rebuilding the compiler:
+//!
+//! - `templates/` in the user's config directory (`~/.config/z/templates`), for every project;
+//! - [`PROJECT_DIR`] next to the source, for one project, over the user's.
+//!
+//! These are unrelated to the `template` blocks of Z sources. The syntax is a small
+//! part of Jinja's:
+//!
+//! - `{{ app.name }}` inserts a value, and `{{ app.name | json }}` inserts it as a
+//!   JSON string, which is a JavaScript string too;
+//! - `{% if app.description %}`, `{% if not ... %}`, `{% else %}` and `{% endif %}`
+//!   keep a part or not; a missing value, `null`, `false`, `""` and `[]` count as false;
+//! - `{% for route in routes %}` ... `{% endfor %}` repeats a part for each item of a list.
+//!
+//! A line holding nothing but a `{% %}` tag is left out of the output. Every template
+//! gets the [`context`] of its target, plus the values its compiler adds.
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use serde_json::{json, Map, Value};
+use z_semantic::{Profile, Target};
+
+use crate::schema::suggestion;
+use crate::tr;
+
+/// Directory next to the source whose templates replace the built-in ones
+pub const PROJECT_DIR: &str = "z-templates";
+
+/// The built-in templates, by name
+pub const BUILT_IN: &[(&str, &str)] = &[
+    ("deno/_404.tsx", include_str!("../../../shared/templates/deno/_404.tsx")),
+    ("deno/_app.tsx", include_str!("../../../shared/templates/deno/_app.tsx")),
+    ("deno/dev.ts", include_str!("../../../shared/templates/deno/dev.ts")),
+    ("deno/main.ts", include_str!("../../../shared/templates/deno/main.ts")),
+    ("next/error.tsx", include_str!("../../../shared/templates/next/error.tsx")),
+    ("next/layout.tsx", include_str!("../../../shared/templates/next/layout.tsx")),
+    ("next/loading.tsx", include_str!("../../../shared/templates/next/loading.tsx")),
+    ("next/not-found.tsx", include_str!("../../../shared/templates/next/not-found.tsx")),
+    ("next/tsconfig.json", include_str!("../../../shared/templates/next/tsconfig.json")),
+    ("rust/main.rs", include_str!("../../../shared/templates/rust/main.rs")),
+    ("swift/Package.swift", include_str!("../../../shared/templates/swift/Package.swift")),
+    ("tauri/main.js", include_str!("../../../shared/templates/tauri/main.js")),
+    ("tauri/main.rs", include_str!("../../../shared/templates/tauri/main.rs")),
+];
+
+/// The built-in template called `name`
+pub fn built_in(name: &str) -> Option<&'static str> {
+    BUILT_IN.iter().find(|(built_in, _)| *built_in == name)
+        .map(|(_, source)| crate::assets::get(&format!("templates/{}", name)).unwrap_or(source))
+}
+
+/// The templates compilers render, with those replacing built-in ones
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Templates {
+    /// Templates replacing built-in ones, by name
+    pub overrides: BTreeMap<String, Override>,
+}
+
+/// A template file replacing a built-in one
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Override {
+    pub path: PathBuf,
+    pub source: String,
+}
+
+impl Templates {
+    /// The templates in the user's templates directory and in `project_dir`, checked
+    pub fn load(project_dir: &Path) -> Result<Self, String> {
+        let user = crate::sandbox::config_dir().map(|dir| dir.join("templates"));
+        let project = Path::new(".").join(project_dir).join(PROJECT_DIR);
+        let names: Vec<&str> = BUILT_IN.iter().map(|(name, _)| *name).collect();
+        let mut templates = Self::default();
+        for dir in user.into_iter().chain([project]) {
+            let mut files = Vec::new();
+            collect_files(&dir, &mut files)?;
+            for path in files {
+                let name = path.strip_prefix(&dir).unwrap_or(&path).components()
+                    .map(|component| component.as_os_str().to_string_lossy())
+                    .collect::<Vec<_>>()
+                    .join("/");
+                if built_in(&name).is_none() {
+                    return Err(format!(
+                        "{}: there's no built-in template {} to replace{}",
+                        path.display(), name, suggestion(&name, &names),
+                    ));
+                }
+                let source = std::fs::read_to_string(&path)
+                    .map_err(|e| tr!("read-failed", path = path.display(), error = e))?;
+                parse(&source).map_err(|error| format!("{}:{}: {}", path.display(), error.line, error.message))?;
+                templates.overrides.insert(name, Override { path, source });
+            }
+        }
+        Ok(templates)
+    }
+
+    /// Render the template called `name` with `context`
+    pub fn render(&self, name: &str, context: &Value) -> Result<String, String> {
+        let (source, origin) = match self.overrides.get(name) {
+            Some(template) => (template.source.as_str(), template.path.display().to_string()),
+            None => (built_in(name).ok_or_else(|| format!("there's no template called {}", name))?, name.to_string()),
+        };
+        render(source, context).map_err(|error| format!("template {}:{}: {}", origin, error.line, error.message))
+    }
+}
+
+/// Values every template gets about its target
+pub(crate) fn context(target: &Target) -> Value {
+    let meta = target.meta.as_ref();
+    let config: Map<String, Value> = target.config.iter()
+        .flat_map(|config| &config.values)
+        .map(|setting| (setting.key.clone(), Value::String(setting.value.clone())))
+        .collect();
+    json!({
+        "target": { "kind": target.kind, "name": target.name },
+        "app": {
+            "name": target.display_name(),
+            "description": meta.and_then(|meta| meta.description.as_deref()),
+            "version": meta.and_then(|meta| meta.version.as_deref()),
+            "locale": target.locale(),
+            "package": crate::identifiers::package(target),
+            "crate": crate::identifiers::crate_name(target),
+            "bundle_id": crate::identifiers::bundle_id(target),
+        },
+        "profile": {
+            "name": match target.profile {
+                Profile::Minimal => "minimal",
+                Profile::Standard => "standard",
+                Profile::Full => "full",
+            },
+            "standard": target.profile >= Profile::Standard,
+            "full": target.profile >= Profile::Full,
+        },
+        "config": config,
+    })
+}
+
+/// Render the template `source` with `context`
+pub fn render(source: &str, context: &Value) -> Result<String, Error> {
+    let parts = parse(source)?;
+    let mut out = String::new();
+    render_parts(&parts, context, &mut Vec::new(), &mut out)?;
+    Ok(out)
+}
+
+/// A problem with a template, at a line of it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Error {
+    pub line: usize,
+    pub message: String,
+}
+
+fn error(line: usize, message: String) -> Error {
+    Error { line, message }
+}
+
+enum Token<'a> {
+    Text(&'a str),
+    /// `{{ ... }}`
+    Value(&'a str, usize),
+    /// `{% ... %}`
+    Tag(&'a str, usize),
+}
+
+enum Part<'a> {
+    Text(&'a str),
+    Value { path: &'a str, json: bool, line: usize },
+    If { path: &'a str, negated: bool, then: Vec<Part<'a>>, otherwise: Vec<Part<'a>> },
+    For { name: &'a str, path: &'a str, line: usize, body: Vec<Part<'a>> },
+}
+
+fn tokenize(source: &str) -> Result<Vec<Token<'_>>, Error> {
+    let mut tokens = Vec::new();
+    let mut offset = 0;
+    while offset < source.len() {
+        let rest = &source[offset..];
+        let Some(start) = [rest.find("{{"), rest.find("{%")].into_iter().flatten().min() else {
+            tokens.push(Token::Text(rest));
+            break;
+        };
+        // `{{{ value }}}` is a value in braces, as JSX attributes have them
+        let start = if rest[start..].starts_with("{{{") { start + 1 } else { start };
+        let line = source[..offset + start].matches('\n').count() + 1;
+        let tag = rest[start..].starts_with("{%");
+        let close = if tag { "%}" } else { "}}" };
+        let end = rest[start + 2..].find(close)
+            .ok_or_else(|| error(line, format!("`{}` isn't closed with `{}`", &rest[start..start + 2], close)))?
+            + start + 2;
+        let inner = rest[start + 2..end].trim();
+        let mut text = &rest[..start];
+        let mut next = end + 2;
+
+        if tag {
+            // A tag alone on its line takes the whole line with it
+            let line_start = text.rfind('\n').map(|i| i + 1);
+            let at_line_start = line_start.is_some() || offset == 0 || source[..offset].ends_with('\n');
+            let indent = &text[line_start.unwrap_or(0)..];
+            let line_end = rest[next..].find('\n').map_or(rest.len(), |i| next + i);
+            if at_line_start && indent.trim().is_empty() && rest[next..line_end].trim().is_empty() {
+                text = &text[..line_start.unwrap_or(0)];
+                next = (line_end + 1).min(rest.len());
+            }
+        }
+        if !text.is_empty() {
+            tokens.push(Token::Text(text));
+        }
+        tokens.push(if tag { Token::Tag(inner, line) } else { Token::Value(inner, line) });
+        offset += next;
+    }
+    Ok(tokens)
+}
+
+fn parse(source: &str) -> Result<Vec<Part<'_>>, Error> {
+    let mut tokens = tokenize(source)?.into_iter();
+    let (parts, end) = parse_parts(&mut tokens, &[])?;
+    match end {
+        Some((tag, line)) => Err(error(line, format!("`{{% {} %}}` doesn't close anything", tag))),
+        None => Ok(parts),
+    }
+}
+
+/// A tag ending a block, with its line
+type Closing<'a> = (&'a str, usize);
+
+/// Parts up to one of the `closing` tags, which is returned with its line; `None`
+/// when the template ends first
+fn parse_parts<'a>(
+    tokens: &mut std::vec::IntoIter<Token<'a>>,
+    closing: &[&str],
+) -> Result<(Vec<Part<'a>>, Option<Closing<'a>>), Error> {
+    let mut parts = Vec::new();
+    while let Some(token) = tokens.next() {
+        match token {
+            Token::Text(text) => parts.push(Part::Text(text)),
+            Token::Value(inner, line) => {
+                let (path, filter) = match inner.split_once('|') {
+                    Some((path, filter)) => (path.trim(), Some(filter.trim())),
+                    None => (inner, None),
+                };
+                if let Some(filter) = filter.filter(|filter| *filter != "json") {
+                    return Err(error(line, format!("unknown filter '{}'; the only one is 'json'", filter)));
+                }
+                check_path(path, line)?;
+                parts.push(Part::Value { path, json: filter.is_some(), line });
+            }
+            Token::Tag(inner, line) => {
+                let words: Vec<&str> = inner.split_whitespace().collect();
+                match words.as_slice() {
+                    ["if", rest @ ..] => {
+                        let (negated, path) = match rest {
+                            ["not", path] => (true, *path),
+                            [path] => (false, *path),
+                            _ => return Err(error(line, "expected `{% if name %}` or `{% if not name %}`".to_string())),
+                        };
+                        check_path(path, line)?;
+                        let (then, end) = parse_parts(tokens, &["else", "endif"])?;
+                        let otherwise = match end {
+                            Some(("else", _)) => match parse_parts(tokens, &["endif"])? {
+                                (otherwise, Some(_)) => otherwise,
+                                (_, None) => return Err(error(line, "`{% if %}` isn't closed with `{% endif %}`".to_string())),
+                            },
+                            Some(_) => Vec::new(),
+                            None => return Err(error(line, "`{% if %}` isn't closed with `{% endif %}`".to_string())),
+                        };
+                        parts.push(Part::If { path, negated, then, otherwise });
+                    }
+                    ["for", name, "in", path] => {
+                        check_path(path, line)?;
+                        let (body, end) = parse_parts(tokens, &["endfor"])?;
+                        if end.is_none() {
+                            return Err(error(line, "`{% for %}` isn't closed with `{% endfor %}`".to_string()));
+                        }
+                        parts.push(Part::For { name, path, line, body });
+                    }
+                    [tag] if closing.contains(tag) => return Ok((parts, Some((tag, line)))),
+                    [tag @ ("else" | "endif" | "endfor")] => {
+                        return Err(error(line, format!("`{{% {} %}}` doesn't close anything", tag)));
+                    }
+                    _ => return Err(error(line, format!("unknown tag `{{% {} %}}`; tags are if, else, endif, for and endfor", inner))),
+                }
+            }
+        }
+    }
+    Ok((parts, None))
+}
+
+fn check_path(path: &str, line: usize) -> Result<(), Error> {
+    let valid = path.split('.').all(|segment| {
+        !segment.is_empty() && segment.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+    });
+    if valid { Ok(()) } else { Err(error(line, format!("'{}' isn't a value name like app.name", path))) }
+}
+
+/// The value at `path`: a loop variable, innermost first, or else one of the context
+fn lookup(path: &str, context: &Value, scope: &[(&str, Value)]) -> Option<Value> {
+    let mut segments = path.split('.');
+    let first = segments.next()?;
+    let mut value = match scope.iter().rev().find(|(name, _)| *name == first) {
+        Some((_, value)) => value,
+        None => context.get(first)?,
+    };
+    for segment in segments {
+        value = match value {
+            Value::Array(items) => items.get(segment.parse::<usize>().ok()?)?,
+            _ => value.get(segment)?,
+        };
+    }
+    Some(value.clone())
+}
+
+fn truthy(value: Option<&Value>) -> bool {
+    match value {
+        None | Some(Value::Null) | Some(Value::Bool(false)) => false,
+        Some(Value::String(text)) => !text.is_empty(),
+        Some(Value::Array(items)) => !items.is_empty(),
+        Some(_) => true,
+    }
+}
+
+fn render_parts<'a>(parts: &[Part<'a>], context: &Value, scope: &mut Vec<(&'a str, Value)>, out: &mut String) -> Result<(), Error> {
+    for part in parts {
+        match part {
+            Part::Text(text) => out.push_str(text),
+            Part::Value { path, json, line } => {
+                let value = lookup(path, context, scope)
+                    .ok_or_else(|| error(*line, format!("there's no value called {}", path)))?;
+                match (&value, json) {
+                    (_, true) => out.push_str(&value.to_string()),
+                    (Value::String(text), false) => out.push_str(text),
+                    (Value::Number(_) | Value::Bool(_), false) => out.push_str(&value.to_string()),
+                    _ => return Err(error(*line, format!("{} isn't text; insert it with `| json`", path))),
+                }
+            }
+            Part::If { path, negated, then, otherwise } => {
+                let kept = if truthy(lookup(path, context, scope).as_ref()) != *negated { then } else { otherwise };
+                render_parts(kept, context, scope, out)?;
+            }
+            Part::For { name, path, line, body } => {
+                let items = match lookup(path, context, scope) {
+                    Some(Value::Array(items)) => items,
+                    None | Some(Value::Null) => Vec::new(),
+                    Some(_) => return Err(error(*line, format!("{} isn't a list", path))),
+                };
+                for item in items {
+                    scope.push((name, item));
+                    render_parts(body, context, scope, out)?;
+                    scope.pop();
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Every file under `dir`, sorted; none when it doesn't exist
+fn collect_files(dir: &Path, files: &mut Vec<PathBuf>) -> Result<(), String> {
+    let entries = match std::fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(tr!("read-dir-failed", path = dir.display(), error = e)),
+    };
+    let mut paths: Vec<PathBuf> = entries.filter_map(|entry| entry.ok().map(|entry| entry.path())).collect();
+    paths.sort();
+    for path in paths {
+        if path.is_dir() {
+            collect_files(&path, files)?;
+        } else {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,116 @@
+//! The test suites of generated projects, for `z test`.
+//!
+//! [`test_command`] finds the runner for a project from its target type and what's in it:
+//!
+//! | Targets         | Runs                                                   | When                                                          |
+//! | --------------- | ------------------------------------------------------ | ------------------------------------------------------------- |
+//! | `next`, `tauri` | the `test` script, with the package manager            | `package.json` has one                                        |
+//! |                 | `vitest run`, with the package manager                 | vitest is a dependency or has a config file                   |
+//! | `deno`          | the `test` task                                        | `deno.json` has one                                           |
+//! |                 | `deno test -A`                                         | there are test modules, e.g. `routes/index_test.ts`           |
+//! | `rust`          | `cargo test`                                           | there's a `Cargo.toml`                                        |
+//! | `swift`         | `swift test`                                           | there's a `Package.swift`                                     |
+//!
+//! Anything else has no test suite, as neither do the projects as first generated, before
+//! tests are added to them.
+
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::runtime::Runtime;
+use crate::store::read_json;
+
+/// Runs a generated project's tests
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TestCommand {
+    pub program: String,
+    pub args: Vec<String>,
+    /// Directory to run in
+    pub dir: PathBuf,
+}
+
+impl TestCommand {
+    fn new(program: &str, args: Vec<String>, dir: &Path) -> Self {
+        Self { program: program.to_string(), args, dir: dir.to_path_buf() }
+    }
+
+    pub fn command(&self) -> Command {
+        let mut command = Command::new(&self.program);
+        command.args(&self.args).current_dir(&self.dir);
+        command
+    }
+}
+
+impl fmt::Display for TestCommand {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.program)?;
+        for arg in &self.args {
+            write!(f, " {}", arg)?;
+        }
+        Ok(())
+    }
+}
+
+/// The command running the tests of the project of a `kind` target in `project_dir`, or
+/// `None` if it has none
+pub fn test_command(kind: &str, project_dir: &Path) -> Option<TestCommand> {
+    match kind {
+        "next" | "tauri" => {
+            let package = read_json(&project_dir.join("package.json")).unwrap_or_default();
+            let runtime = Runtime::detect(project_dir);
+            let args = if package["scripts"].get("test").is_some() {
+                vec!["test".to_string()]
+            } else if has_vitest(project_dir, &package) {
+                vec!["vitest".to_string(), "run".to_string()]
+            } else {
+                return None;
+            };
+            let (program, args) = runtime.command(project_dir, &args);
+            Some(TestCommand::new(program, args, project_dir))
+        }
+        "deno" => {
+            let config = read_json(&project_dir.join("deno.json")).unwrap_or_default();
+            if config["tasks"].get("test").is_some() {
+                Some(TestCommand::new("deno", vec!["task".to_string(), "test".to_string()], project_dir))
+            } else if has_test_modules(project_dir) {
+                Some(TestCommand::new("deno", vec!["test".to_string(), "-A".to_string()], project_dir))
+            } else {
+                None
+            }
+        }
+        "rust" => project_dir.join("Cargo.toml").is_file()
+            .then(|| TestCommand::new("cargo", vec!["test".to_string()], project_dir)),
+        "swift" => project_dir.join("Package.swift").is_file()
+            .then(|| TestCommand::new("swift", vec!["test".to_string()], project_dir)),
+        _ => None,
+    }
+}
+
+fn has_vitest(project_dir: &Path, package: &serde_json::Value) -> bool {
+    let configs = ["vitest.config.ts", "vitest.config.mts", "vitest.config.js", "vitest.config.mjs"];
+    package["devDependencies"].get("vitest").is_some()
+        || package["dependencies"].get("vitest").is_some()
+        || configs.iter().any(|config| project_dir.join(config).is_file())
+}
+
+/// Whether any file under `dir` is one `deno test` runs: `test.ts`, `*_test.ts` or
+/// `*.test.ts`, or the same in JavaScript or TSX
+fn has_test_modules(dir: &Path) -> bool {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return false;
+    };
+    entries.flatten().any(|entry| {
+        let name = entry.file_name().to_string_lossy().to_string();
+        let path = entry.path();
+        if path.is_dir() {
+            // Dependencies and build output aren't the project's tests
+            return !name.starts_with(['.', '_']) && name != "node_modules" && has_test_modules(&path);
+        }
+        let Some((stem, extension)) = name.rsplit_once('.') else {
+            return false;
+        };
+        ["ts", "tsx", "mts", "js", "jsx", "mjs"].contains(&extension)
+            && (stem == "test" || stem.ends_with("_test") || stem.ends_with(".test"))
+    })
+}
@@ -0,0 +1,322 @@
+//! Reading `z.toml` project configuration.
+//!
+//! Supports the part of TOML a project file needs: tables (`[a.b]`), arrays of
+//! tables (`[[a]]`), dotted keys, basic and literal strings, integers, floats,
+//! booleans, arrays and inline tables. Documents come back as JSON values so they're
+//! read the same way as the registry.
+
+use std::path::Path;
+
+use serde_json::{Map, Value};
+
+/// Name of the project configuration file, next to the `.z` source
+pub const CONFIG_FILE: &str = "z.toml";
+
+/// The `z.toml` in `dir`, or an empty table when there's none
+pub fn load_config(dir: &Path) -> Result<Value, String> {
+    let path = dir.join(CONFIG_FILE);
+    match std::fs::read_to_string(&path) {
+        Ok(text) => parse(&text).map_err(|e| format!("{}: {}", path.display(), e)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Value::Object(Map::new())),
+        Err(e) => Err(format!("{}: {}", path.display(), e)),
+    }
+}
+
+/// Parse a TOML document into a JSON object
+pub fn parse(text: &str) -> Result<Value, String> {
+    let mut parser = Parser { chars: text.chars().collect(), pos: 0, line: 1 };
+    let mut root = Map::new();
+    // Path of the table `key = value` lines go into
+    let mut current: Vec<String> = Vec::new();
+
+    loop {
+        parser.skip_blank_lines();
+        let Some(c) = parser.peek() else {
+            break;
+        };
+        if c == '[' {
+            parser.pos += 1;
+            let array = parser.eat('[');
+            let path = parser.key()?;
+            if !parser.eat(']') || (array && !parser.eat(']')) {
+                return Err(parser.error("expected ']' after the table name"));
+            }
+            let (last, parents) = path.split_last().expect("keys have at least one part");
+            let parent = table_at(&mut root, parents).map_err(|e| parser.error(&e))?;
+            if array {
+                let tables = parent.entry(last.clone()).or_insert_with(|| Value::Array(Vec::new()));
+                let Value::Array(tables) = tables else {
+                    return Err(parser.error(&format!("'{}' is already defined", path.join("."))));
+                };
+                tables.push(Value::Object(Map::new()));
+            } else {
+                match parent.entry(last.clone()).or_insert_with(|| Value::Object(Map::new())) {
+                    Value::Object(_) => {}
+                    _ => return Err(parser.error(&format!("'{}' is already defined", path.join(".")))),
+                }
+            }
+            current = path;
+        } else {
+            let path = parser.key()?;
+            parser.skip_spaces();
+            if !parser.eat('=') {
+                return Err(parser.error(&format!("expected '=' after '{}'", path.join("."))));
+            }
+            let value = parser.value()?;
+            let table = table_at(&mut root, &current).map_err(|e| parser.error(&e))?;
+            insert(table, &path, value).map_err(|e| parser.error(&e))?;
+        }
+        parser.end_of_line()?;
+    }
+    Ok(Value::Object(root))
+}
+
+/// The table at `path` below `root`, creating missing ones. An array of tables
+/// stands for its last table.
+fn table_at<'a>(root: &'a mut Map<String, Value>, path: &[String]) -> Result<&'a mut Map<String, Value>, String> {
+    let mut table = root;
+    for (i, key) in path.iter().enumerate() {
+        let mut entry = table.entry(key.clone()).or_insert_with(|| Value::Object(Map::new()));
+        if let Value::Array(tables) = entry {
+            match tables.last_mut() {
+                Some(last) => entry = last,
+                None => return Err(format!("'{}' is not a table", path[..=i].join("."))),
+            }
+        }
+        table = match entry {
+            Value::Object(next) => next,
+            _ => return Err(format!("'{}' is not a table", path[..=i].join("."))),
+        };
+    }
+    Ok(table)
+}
+
+fn insert(table: &mut Map<String, Value>, path: &[String], value: Value) -> Result<(), String> {
+    let (last, parents) = path.split_last().expect("keys have at least one part");
+    let table = table_at(table, parents)?;
+    if table.contains_key(last) {
+        return Err(format!("'{}' is defined twice", path.join(".")));
+    }
+    table.insert(last.clone(), value);
+    Ok(())
+}
+
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+    line: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        self.skip_spaces();
+        if self.peek() == Some(c) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn error(&self, message: &str) -> String {
+        format!("line {}: {}", self.line, message)
+    }
+
+    fn skip_spaces(&mut self) {
+        while matches!(self.peek(), Some(' ' | '\t')) {
+            self.pos += 1;
+        }
+    }
+
+    fn skip_comment(&mut self) {
+        if self.peek() == Some('#') {
+            while !matches!(self.peek(), None | Some('\n')) {
+                self.pos += 1;
+            }
+        }
+    }
+
+    /// Skip whitespace, comments and newlines
+    fn skip_blank_lines(&mut self) {
+        loop {
+            self.skip_spaces();
+            self.skip_comment();
+            match self.peek() {
+                Some('\n') => self.line += 1,
+                Some('\r') => {}
+                _ => return,
+            }
+            self.pos += 1;
+        }
+    }
+
+    fn end_of_line(&mut self) -> Result<(), String> {
+        self.skip_spaces();
+        self.skip_comment();
+        match self.peek() {
+            None | Some('\n' | '\r') => Ok(()),
+            Some(c) => Err(self.error(&format!("unexpected '{}'", c))),
+        }
+    }
+
+    /// A dotted key such as `dependencies.next` or `"quoted.part".bare`
+    fn key(&mut self) -> Result<Vec<String>, String> {
+        let mut parts = Vec::new();
+        loop {
+            self.skip_spaces();
+            let part = match self.peek() {
+                Some('"') => self.basic_string()?,
+                Some('\'') => self.literal_string()?,
+                _ => {
+                    let start = self.pos;
+                    while matches!(self.peek(), Some(c) if c.is_ascii_alphanumeric() || c == '_' || c == '-') {
+                        self.pos += 1;
+                    }
+                    if start == self.pos {
+                        return Err(self.error("expected a key"));
+                    }
+                    self.chars[start..self.pos].iter().collect()
+                }
+            };
+            parts.push(part);
+            self.skip_spaces();
+            if self.peek() != Some('.') {
+                return Ok(parts);
+            }
+            self.pos += 1;
+        }
+    }
+
+    fn value(&mut self) -> Result<Value, String> {
+        self.skip_spaces();
+        match self.peek() {
+            Some('"') if self.chars[self.pos..].starts_with(&['"', '"', '"']) => {
+                Err(self.error("multi-line strings aren't supported"))
+            }
+            Some('"') => self.basic_string().map(Value::String),
+            Some('\'') => self.literal_string().map(Value::String),
+            Some('[') => {
+                self.pos += 1;
+                let mut items = Vec::new();
+                loop {
+                    self.skip_blank_lines();
+                    if self.peek() == Some(']') {
+                        self.pos += 1;
+                        return Ok(Value::Array(items));
+                    }
+                    items.push(self.value()?);
+                    self.skip_blank_lines();
+                    match self.peek() {
+                        Some(',') => self.pos += 1,
+                        Some(']') => {}
+                        _ => return Err(self.error("expected ',' or ']' in array")),
+                    }
+                }
+            }
+            Some('{') => {
+                self.pos += 1;
+                let mut table = Map::new();
+                self.skip_spaces();
+                if self.peek() == Some('}') {
+                    self.pos += 1;
+                    return Ok(Value::Object(table));
+                }
+                loop {
+                    let path = self.key()?;
+                    if !self.eat('=') {
+                        return Err(self.error(&format!("expected '=' after '{}'", path.join("."))));
+                    }
+                    let value = self.value()?;
+                    insert(&mut table, &path, value).map_err(|e| self.error(&e))?;
+                    self.skip_spaces();
+                    match self.peek() {
+                        Some(',') => self.pos += 1,
+                        Some('}') => {
+                            self.pos += 1;
+                            return Ok(Value::Object(table));
+                        }
+                        _ => return Err(self.error("expected ',' or '}' in inline table")),
+                    }
+                }
+            }
+            _ => {
+                let start = self.pos;
+                while matches!(self.peek(), Some(c) if c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '+' | '.' | ':')) {
+                    self.pos += 1;
+                }
+                let word: String = self.chars[start..self.pos].iter().collect();
+                match word.as_str() {
+                    "true" => Ok(Value::Bool(true)),
+                    "false" => Ok(Value::Bool(false)),
+                    "" => Err(self.error("expected a value")),
+                    _ => {
+                        let digits = word.replace('_', "");
+                        if let Ok(integer) = digits.parse::<i64>() {
+                            Ok(Value::from(integer))
+                        } else if let Some(float) = digits.parse::<f64>().ok().and_then(serde_json::Number::from_f64) {
+                            Ok(Value::Number(float))
+                        } else {
+                            Err(self.error(&format!("unsupported value '{}'", word)))
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// `"..."` with escapes
+    fn basic_string(&mut self) -> Result<String, String> {
+        self.pos += 1;
+        let mut text = String::new();
+        loop {
+            match self.peek() {
+                None | Some('\n') => return Err(self.error("unterminated string")),
+                Some('"') => {
+                    self.pos += 1;
+                    return Ok(text);
+                }
+                Some('\\') => {
+                    self.pos += 1;
+                    let escaped = match self.peek() {
+                        Some('n') => '\n',
+                        Some('t') => '\t',
+                        Some('r') => '\r',
+                        Some('"') => '"',
+                        Some('\\') => '\\',
+                        Some('u') => {
+                            let hex: String = self.chars.iter().skip(self.pos + 1).take(4).collect();
+                            self.pos += 4;
+                            u32::from_str_radix(&hex, 16).ok().and_then(char::from_u32)
+                                .ok_or_else(|| self.error(&format!("invalid escape '\\u{}'", hex)))?
+                        }
+                        other => return Err(self.error(&format!("invalid escape '\\{}'", other.unwrap_or(' ')))),
+                    };
+                    text.push(escaped);
+                    self.pos += 1;
+                }
+                Some(c) => {
+                    text.push(c);
+                    self.pos += 1;
+                }
+            }
+        }
+    }
+
+    /// `'...'`, taken as written
+    fn literal_string(&mut self) -> Result<String, String> {
+        self.pos += 1;
+        let start = self.pos;
+        while !matches!(self.peek(), None | Some('\'' | '\n')) {
+            self.pos += 1;
+        }
+        if self.peek() != Some('\'') {
+            return Err(self.error("unterminated string"));
+        }
+        self.pos += 1;
+        Ok(self.chars[start..self.pos - 1].iter().collect())
+    }
+}
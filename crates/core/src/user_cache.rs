@@ -0,0 +1,191 @@
+//! Cache of generated targets shared by every project of the user, in `~/.cache/z`.
+//!
+//! Scaffolding a large target, such as a Next.js app, and running `cargo init` or
+//! `create-tauri-app` for it, takes far longer than restoring the files it generated.
+//! Each target generated is kept under the [remote cache](crate::remote_cache) key, in
+//! the remote cache's entry format, so a target compiled before, in this project or in
+//! another one, is restored rather than generated again. Templates and assets are
+//! embedded in the compiler, so the entries hold the templates already expanded.
+//!
+//! The cache is kept under a size limit: when an entry makes it larger, the entries
+//! restored least recently are deleted until it fits again.
+//!
+//! `Z_CACHE_DIR` moves the cache and `Z_CACHE_MAX_SIZE` sets the limit, e.g. `500M`.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use z_semantic::Target;
+
+use crate::hash::sha256_hex;
+use crate::remote_cache::{CacheMode, RemoteCache};
+use crate::sandbox::home_dir;
+use crate::sink::FileSink;
+use crate::tr;
+
+/// Environment variable holding the directory of the cache
+pub const DIR_ENV: &str = "Z_CACHE_DIR";
+/// Environment variable holding the size limit of the cache
+pub const MAX_SIZE_ENV: &str = "Z_CACHE_MAX_SIZE";
+/// Size the cache is kept under unless told otherwise: 2 GiB
+pub const DEFAULT_MAX_BYTES: u64 = 2 << 30;
+
+/// Entries being written by a compile that never finished are deleted after this long
+const ABANDONED: Duration = Duration::from_secs(24 * 60 * 60);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UserCache {
+    dir: PathBuf,
+    /// Size the entries are kept under, in bytes
+    pub max_bytes: u64,
+}
+
+/// How much a cache holds, or how much was deleted from it
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Usage {
+    pub entries: usize,
+    pub bytes: u64,
+}
+
+impl UserCache {
+    /// A cache kept in `dir`, under `max_bytes`
+    pub fn new(dir: impl Into<PathBuf>, max_bytes: u64) -> Self {
+        Self { dir: dir.into(), max_bytes }
+    }
+
+    /// The cache in `$Z_CACHE_DIR`, or else `~/.cache/z` or its equivalent on the
+    /// platform, under `$Z_CACHE_MAX_SIZE`. None when there's no home directory to put it in.
+    pub fn from_env() -> Result<Option<Self>, String> {
+        let max_bytes = match std::env::var(MAX_SIZE_ENV) {
+            Ok(size) => parse_size(&size).map_err(|e| format!("{}: {}", MAX_SIZE_ENV, e))?,
+            Err(_) => DEFAULT_MAX_BYTES,
+        };
+        let dir = std::env::var_os(DIR_ENV).map(PathBuf::from)
+            .or_else(|| std::env::var_os("XDG_CACHE_HOME").map(|cache| PathBuf::from(cache).join("z")))
+            .or_else(|| std::env::var_os("LOCALAPPDATA").map(|cache| PathBuf::from(cache).join("z")))
+            .or_else(|| home_dir().map(|home| home.join(".cache/z")));
+        Ok(dir.map(|dir| Self::new(dir, max_bytes)))
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Restore the entry for `key` into `sink`, marking it as used. `Ok(false)` is a miss.
+    pub(crate) fn restore(&self, key: &str, sink: &dyn FileSink) -> Result<bool, String> {
+        let hit = self.entries().restore(key, sink)?;
+        if hit {
+            // What was restored recently is what the size limit keeps
+            let _ = fs::File::options().append(true).open(self.targets().join(key))
+                .and_then(|entry| entry.set_modified(SystemTime::now()));
+        }
+        Ok(hit)
+    }
+
+    /// Keep everything generated in `sink` under `key`, then delete the entries
+    /// restored least recently until the cache fits its size limit
+    pub(crate) fn store(&self, key: &str, sink: &dyn FileSink) -> Result<(), String> {
+        self.entries().store(key, sink)?;
+        self.collect_garbage(self.max_bytes).map(|_| ())
+    }
+
+    /// The entries kept, and their size
+    pub fn usage(&self) -> Usage {
+        self.list().iter().fold(Usage::default(), |usage, (_, bytes, _)| Usage { entries: usage.entries + 1, bytes: usage.bytes + bytes })
+    }
+
+    /// Delete the entries restored least recently until the rest take at most `max_bytes`,
+    /// returning what was deleted
+    pub fn collect_garbage(&self, max_bytes: u64) -> Result<Usage, String> {
+        let mut entries = self.list();
+        // Newest first, so the ones past the limit are the oldest
+        entries.sort_by(|a, b| b.2.cmp(&a.2).then_with(|| a.0.cmp(&b.0)));
+        let mut kept = 0;
+        let mut deleted = Usage::default();
+        for (path, bytes, _) in entries {
+            if kept + bytes <= max_bytes {
+                kept += bytes;
+                continue;
+            }
+            fs::remove_file(&path).map_err(|e| tr!("remove-failed", path = path.display(), error = e))?;
+            deleted.entries += 1;
+            deleted.bytes += bytes;
+        }
+
+        let abandoned = self.partial().into_iter()
+            .filter(|(_, modified)| modified.elapsed().is_ok_and(|age| age > ABANDONED));
+        for (path, _) in abandoned {
+            let _ = fs::remove_file(path);
+        }
+        Ok(deleted)
+    }
+
+    /// Delete every entry, returning what was deleted
+    pub fn clean(&self) -> Result<Usage, String> {
+        self.collect_garbage(0)
+    }
+
+    fn targets(&self) -> PathBuf {
+        self.dir.join("targets")
+    }
+
+    fn entries(&self) -> RemoteCache {
+        RemoteCache::directory(self.targets(), CacheMode::ReadWrite)
+    }
+
+    /// Every entry with its size and when it was last used
+    fn list(&self) -> Vec<(PathBuf, u64, SystemTime)> {
+        self.files().filter(|(name, ..)| !name.starts_with('.')).map(|(_, path, bytes, modified)| (path, bytes, modified)).collect()
+    }
+
+    /// Entries still being written, or left half written, with when they were last written to
+    fn partial(&self) -> Vec<(PathBuf, SystemTime)> {
+        self.files().filter(|(name, ..)| name.starts_with('.')).map(|(_, path, _, modified)| (path, modified)).collect()
+    }
+
+    fn files(&self) -> impl Iterator<Item = (String, PathBuf, u64, SystemTime)> {
+        fs::read_dir(self.targets()).into_iter().flatten().flatten().filter_map(|file| {
+            let metadata = file.metadata().ok().filter(|metadata| metadata.is_file())?;
+            let modified = metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH);
+            Some((file.file_name().to_string_lossy().into_owned(), file.path(), metadata.len(), modified))
+        })
+    }
+}
+
+/// The key of `target` in the user cache. Projects sharing the cache can point to
+/// different files by the same path, so the files the target copies from the project,
+/// such as its `Meta` icon, count along with its `key`.
+pub(crate) fn key(key: &str, target: &Target, source_dir: Option<&Path>) -> String {
+    let icon = target.meta.as_ref().and_then(|meta| meta.icon.as_ref());
+    let screenshots = target.store().map(|listing| listing.screenshots.as_slice()).unwrap_or_default();
+    let mut files = icon.into_iter().chain(screenshots).peekable();
+    if files.peek().is_none() {
+        return key.to_string();
+    }
+    let source_dir = source_dir.unwrap_or(Path::new("."));
+    let mut inputs = key.to_string();
+    for file in files {
+        let contents = fs::read(source_dir.join(file)).map(|contents| sha256_hex(&contents)).unwrap_or_default();
+        inputs.push_str(&format!("\n{} {}", file, contents));
+    }
+    sha256_hex(inputs.as_bytes())
+}
+
+/// A size such as `500M` or `2G`, in bytes; K, M and G are powers of 1024
+pub fn parse_size(size: &str) -> Result<u64, String> {
+    let size = size.trim();
+    let digits = size.find(|c: char| !c.is_ascii_digit()).unwrap_or(size.len());
+    let (number, unit) = size.split_at(digits);
+    let shift = match unit.trim().to_ascii_uppercase().trim_end_matches("IB").trim_end_matches('B') {
+        "" => 0,
+        "K" => 10,
+        "M" => 20,
+        "G" => 30,
+        "T" => 40,
+        _ => return Err(format!("invalid size '{}' (expected bytes, or a number ending in K, M or G)", size)),
+    };
+    number.parse::<u64>().ok()
+        .and_then(|number| number.checked_mul(1 << shift))
+        .ok_or_else(|| format!("invalid size '{}' (expected bytes, or a number ending in K, M or G)", size))
+}
@@ -0,0 +1,412 @@
+//! App versions of the generated projects, and releases that bump them.
+//!
+//! The version lives in a `VERSION` file next to the source, since generated
+//! manifests are rewritten on every compilation. [`stamp_version`] writes it into
+//! whichever of these a generated project has:
+//!
+//! - `package.json`: `version`
+//! - `Cargo.toml` and `src-tauri/Cargo.toml`: `version` in `[package]`
+//! - `src-tauri/tauri.conf.json`: `version` (Tauri 2) or `package.version` (Tauri 1)
+//! - `Info.plist` (`swift` targets): `CFBundleShortVersionString` and `CFBundleVersion`
+//!
+//! [`release`] bumps the version, stamps it into the projects already generated,
+//! prepends a changelog entry built from the [conventional commits] since the last
+//! `v*` tag, and commits and tags the release.
+//!
+//! [conventional commits]: https://www.conventionalcommits.org
+
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde_json::json;
+use z_semantic::{Program, Target};
+
+use crate::layout::Layout;
+use crate::links::plist;
+use crate::publish::git;
+use crate::sink::{FileSink, FsSink};
+use crate::store::write_file;
+use crate::tr;
+
+/// File holding the version, in the directory of the source file
+pub const VERSION_FILE: &str = "VERSION";
+/// Changelog the release entries are added to, next to the version file
+pub const CHANGELOG_FILE: &str = "CHANGELOG.md";
+
+/// A `MAJOR.MINOR.PATCH` version
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Version {
+    pub major: u64,
+    pub minor: u64,
+    pub patch: u64,
+}
+
+impl Version {
+    /// The version generated projects start out with
+    pub const INITIAL: Version = Version { major: 0, minor: 1, patch: 0 };
+
+    pub fn bump(self, level: Bump) -> Version {
+        match level {
+            Bump::Major => Version { major: self.major + 1, minor: 0, patch: 0 },
+            Bump::Minor => Version { minor: self.minor + 1, patch: 0, ..self },
+            Bump::Patch => Version { patch: self.patch + 1, ..self },
+        }
+    }
+
+    /// The git tag of this version's release, e.g. `v1.2.0`
+    pub fn tag(&self) -> String {
+        format!("v{}", self)
+    }
+}
+
+impl fmt::Display for Version {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)
+    }
+}
+
+impl FromStr for Version {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("invalid version '{}' (expected MAJOR.MINOR.PATCH)", s);
+        let numbers: Vec<u64> = s.trim().trim_start_matches('v').split('.')
+            .map(|part| part.parse().map_err(|_| invalid()))
+            .collect::<Result<_, _>>()?;
+        match numbers[..] {
+            [major, minor, patch] => Ok(Version { major, minor, patch }),
+            _ => Err(invalid()),
+        }
+    }
+}
+
+/// Which part of the version a release increments
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Bump {
+    Major,
+    Minor,
+    Patch,
+}
+
+impl FromStr for Bump {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "major" => Ok(Bump::Major),
+            "minor" => Ok(Bump::Minor),
+            "patch" => Ok(Bump::Patch),
+            _ => Err(format!("invalid version bump '{}' (expected 'major', 'minor' or 'patch')", s)),
+        }
+    }
+}
+
+/// The version in `source_dir`'s version file, if it has one
+pub fn read_version(source_dir: &Path) -> Result<Option<Version>, String> {
+    let path = source_dir.join(VERSION_FILE);
+    match fs::read_to_string(&path) {
+        Ok(contents) => contents.parse().map(Some).map_err(|e| tr!("read-failed", path = path.display(), error = e)),
+        Err(_) => Ok(None),
+    }
+}
+
+/// Write `version` into the manifests of the project generated for `target`,
+/// returning the files that were updated, relative to the project
+pub fn stamp_version(target: &Target, sink: &dyn FileSink, version: &Version) -> Result<Vec<PathBuf>, String> {
+    let version = version.to_string();
+    let mut updated = Vec::new();
+
+    let package_json = Path::new("package.json");
+    if let Some(contents) = sink.read_text(package_json)? {
+        write_if_changed(sink, package_json, &contents, replace_json_string(&contents, "version", &version), &mut updated)?;
+    }
+
+    for manifest in [Path::new("Cargo.toml"), Path::new("src-tauri/Cargo.toml")] {
+        if let Some(contents) = sink.read_text(manifest)? {
+            write_if_changed(sink, manifest, &contents, set_package_field(&contents, "version", &version), &mut updated)?;
+        }
+    }
+
+    let tauri_conf = Path::new("src-tauri/tauri.conf.json");
+    if sink.exists(tauri_conf) {
+        let mut config = sink.read_json(tauri_conf)?;
+        // Tauri 1 keeps the version under `package`, Tauri 2 at the top level
+        let slot = if config["package"].is_object() { &mut config["package"]["version"] } else { &mut config["version"] };
+        if *slot != json!(version) {
+            *slot = json!(version);
+            sink.write_json(tauri_conf, &config)?;
+            updated.push(tauri_conf.to_path_buf());
+        }
+    }
+
+    if target.kind == "swift" {
+        let info = Path::new("Info.plist");
+        let existing = sink.read_text(info)?;
+        let contents = existing.clone().unwrap_or_else(|| plist(""));
+        let stamped = ["CFBundleShortVersionString", "CFBundleVersion"].iter()
+            .fold(contents.clone(), |plist, key| set_plist_string(&plist, key, &version));
+        if existing.is_none() || stamped != contents {
+            sink.write_text(info, &stamped)?;
+            updated.push(info.to_path_buf());
+        }
+    }
+
+    Ok(updated)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReleaseOptions {
+    pub bump: Bump,
+    /// Directory holding the generated projects to update
+    pub output_dir: PathBuf,
+    /// Where each project is in it
+    pub layout: Layout,
+    /// Commit the version file and changelog and tag the release
+    pub tag: bool,
+}
+
+/// Outcome of [`release`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Release {
+    pub previous: Version,
+    pub version: Version,
+    /// Generated manifests that now carry the new version
+    pub updated: Vec<PathBuf>,
+    /// Commits listed in the changelog entry
+    pub changes: usize,
+    pub changelog: PathBuf,
+    /// The release tag, if one was created
+    pub tag: Option<String>,
+}
+
+/// Bump the version of the apps in `program`, whose source is in `source_dir`
+pub fn release(program: &Program, source_dir: &Path, options: &ReleaseOptions) -> Result<Release, String> {
+    let previous = read_version(source_dir)?.unwrap_or(Version::INITIAL);
+    let version = previous.bump(options.bump);
+    let repository = source_dir.to_string_lossy();
+    let in_repository = |args: &[&str]| {
+        let args: Vec<&str> = ["-C", repository.as_ref()].iter().chain(args).copied().collect();
+        git(&args, &[], None)
+    };
+
+    // Commits since the previous release, oldest first
+    let last_tag = in_repository(&["describe", "--tags", "--abbrev=0", "--match", "v[0-9]*"]).ok();
+    let range = last_tag.map_or_else(|| "HEAD".to_string(), |tag| format!("{}..HEAD", tag));
+    let log = in_repository(&["log", "--reverse", "--format=%h%x1f%s%x1f%b%x1e", &range])?;
+    let commits: Vec<Commit> = log.split('\x1e')
+        .filter_map(|record| {
+            let mut fields = record.trim_start().splitn(3, '\x1f');
+            Commit::parse(fields.next()?, fields.next()?, fields.next().unwrap_or_default())
+        })
+        .collect();
+
+    write_file(&source_dir.join(VERSION_FILE), &format!("{}\n", version))?;
+    let mut updated = Vec::new();
+    for target in &program.targets {
+        let Ok(dir) = options.layout.dir(target) else {
+            continue;
+        };
+        let project = options.output_dir.join(dir);
+        if project.is_dir() {
+            let stamped = stamp_version(target, &FsSink::new(&project), &version)?;
+            updated.extend(stamped.into_iter().map(|path| project.join(path)));
+        }
+    }
+
+    let changelog = source_dir.join(CHANGELOG_FILE);
+    let entry = changelog_entry(&version, &today(), &commits);
+    let existing = read_existing(&changelog)?.unwrap_or_default();
+    let contents = match existing.strip_prefix("# Changelog\n") {
+        Some(rest) => format!("# Changelog\n\n{}{}", entry, rest.trim_start_matches('\n')),
+        None if existing.is_empty() => format!("# Changelog\n\n{}", entry),
+        None => format!("{}\n{}", entry, existing),
+    };
+    write_file(&changelog, &contents)?;
+
+    let tag = if options.tag {
+        let tag = version.tag();
+        in_repository(&["add", VERSION_FILE, CHANGELOG_FILE])?;
+        in_repository(&["commit", "-q", "-m", &format!("chore(release): {}", tag), "--", VERSION_FILE, CHANGELOG_FILE])?;
+        in_repository(&["tag", "-a", &tag, "-m", &tag])?;
+        Some(tag)
+    } else {
+        None
+    };
+
+    let changes = commits.iter().filter(|commit| commit.section().is_some()).count();
+    Ok(Release { previous, version, updated, changes, changelog, tag })
+}
+
+/// A commit message following the conventional commits format, e.g. `feat(api)!: drop v1 routes`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Commit {
+    /// Abbreviated hash
+    pub hash: String,
+    /// `feat`, `fix`, ...
+    pub kind: String,
+    pub scope: Option<String>,
+    pub description: String,
+    /// Marked with `!` or a `BREAKING CHANGE` footer
+    pub breaking: bool,
+}
+
+impl Commit {
+    /// `None` for messages that don't follow the format
+    pub fn parse(hash: &str, subject: &str, body: &str) -> Option<Commit> {
+        let (head, description) = subject.split_once(": ")?;
+        let (head, marked) = match head.strip_suffix('!') {
+            Some(head) => (head, true),
+            None => (head, false),
+        };
+        let (kind, scope) = match head.split_once('(') {
+            Some((kind, scope)) => (kind, Some(scope.strip_suffix(')')?.to_string())),
+            None => (head, None),
+        };
+        if kind.is_empty() || !kind.chars().all(|c| c.is_ascii_alphabetic()) {
+            return None;
+        }
+        let breaking = marked || body.lines().any(|line| line.starts_with("BREAKING CHANGE") || line.starts_with("BREAKING-CHANGE"));
+        Some(Commit {
+            hash: hash.to_string(),
+            kind: kind.to_ascii_lowercase(),
+            scope,
+            description: description.trim().to_string(),
+            breaking,
+        })
+    }
+
+    /// Changelog section listing the commit; other commit types than these are left out
+    pub fn section(&self) -> Option<&'static str> {
+        match self.kind.as_str() {
+            _ if self.breaking => Some(SECTIONS[0]),
+            "feat" => Some(SECTIONS[1]),
+            "fix" => Some(SECTIONS[2]),
+            "perf" => Some(SECTIONS[3]),
+            _ => None,
+        }
+    }
+}
+
+const SECTIONS: [&str; 4] = ["Breaking Changes", "Features", "Bug Fixes", "Performance"];
+
+/// Changelog section for `version`: breaking changes, then features, fixes and
+/// performance improvements
+pub fn changelog_entry(version: &Version, date: &str, commits: &[Commit]) -> String {
+    let mut entry = format!("## {} ({})\n\n", version, date);
+    let mut listed = false;
+    for title in SECTIONS {
+        let items: Vec<String> = commits.iter()
+            .filter(|commit| commit.section() == Some(title))
+            .map(|commit| match &commit.scope {
+                Some(scope) => format!("- **{}:** {} ({})\n", scope, commit.description, commit.hash),
+                None => format!("- {} ({})\n", commit.description, commit.hash),
+            })
+            .collect();
+        if !items.is_empty() {
+            entry.push_str(&format!("### {}\n\n{}\n", title, items.concat()));
+            listed = true;
+        }
+    }
+    if !listed {
+        entry.push_str("No notable changes.\n\n");
+    }
+    entry
+}
+
+fn read_existing(path: &Path) -> Result<Option<String>, String> {
+    if !path.exists() {
+        return Ok(None);
+    }
+    fs::read_to_string(path).map(Some).map_err(|e| tr!("read-failed", path = path.display(), error = e))
+}
+
+fn write_if_changed(sink: &dyn FileSink, path: &Path, old: &str, new: Option<String>, updated: &mut Vec<PathBuf>) -> Result<(), String> {
+    match new {
+        Some(new) if new != old => {
+            sink.write_text(path, &new)?;
+            updated.push(path.to_path_buf());
+            Ok(())
+        }
+        _ => Ok(()),
+    }
+}
+
+/// `json` with the first string value of `key` replaced, keeping the file's layout
+fn replace_json_string(json: &str, key: &str, value: &str) -> Option<String> {
+    let quoted = format!("\"{}\"", key);
+    let at = json.find(&quoted)? + quoted.len();
+    let after_colon = at + json[at..].find(':')? + 1;
+    let start = after_colon + json[after_colon..].find('"')? + 1;
+    let end = start + json[start..].find('"')?;
+    Some(format!("{}{}{}", &json[..start], value, &json[end..]))
+}
+
+/// `toml` with `key` in its `[package]` table set to the string `value`, added at the
+/// end of the table if it isn't there
+pub(crate) fn set_package_field(toml: &str, key: &str, value: &str) -> Option<String> {
+    let entry = format!("{} = {}", key, json!(value));
+    let mut in_package = false;
+    let mut found_package = false;
+    let mut replaced = false;
+    let mut lines: Vec<String> = Vec::new();
+    for line in toml.lines() {
+        let trimmed = line.trim_start();
+        if trimmed.starts_with('[') {
+            if in_package && !replaced {
+                // Before the blank lines separating the next table
+                let at = lines.iter().rposition(|line| !line.trim().is_empty()).map_or(0, |i| i + 1);
+                lines.insert(at, entry.clone());
+                replaced = true;
+            }
+            in_package = trimmed.starts_with("[package]");
+            found_package |= in_package;
+        } else if in_package && !replaced && trimmed.split('=').next().is_some_and(|k| k.trim() == key) {
+            replaced = true;
+            lines.push(entry.clone());
+            continue;
+        }
+        lines.push(line.to_string());
+    }
+    if in_package && !replaced {
+        lines.push(entry);
+    }
+    let trailing_newline = if toml.ends_with('\n') { "\n" } else { "" };
+    found_package.then(|| lines.join("\n") + trailing_newline)
+}
+
+/// `plist` with the string under `key` in its top-level dictionary set to `value`
+pub(crate) fn set_plist_string(plist: &str, key: &str, value: &str) -> String {
+    let key_element = format!("<key>{}</key>", key);
+    if let Some(at) = plist.find(&key_element) {
+        let after_key = at + key_element.len();
+        let value_range = plist[after_key..].find("<string>")
+            .map(|open| after_key + open + "<string>".len())
+            .and_then(|start| plist[start..].find("</string>").map(|end| start..start + end));
+        if let Some(range) = value_range {
+            return format!("{}{}{}", &plist[..range.start], value, &plist[range.end..]);
+        }
+    }
+    match plist.rfind("</dict>") {
+        Some(end) => format!("{}\t{}\n\t<string>{}</string>\n{}", &plist[..end], key_element, value, &plist[end..]),
+        None => plist.to_string(),
+    }
+}
+
+/// Today's date in UTC as `YYYY-MM-DD`
+pub(crate) fn today() -> String {
+    let days = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |elapsed| elapsed.as_secs() / 86_400) as i64;
+    // Civil date from days since 1970-01-01 (Howard Hinnant's algorithm)
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z.rem_euclid(146_097);
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 { shifted_month + 3 } else { shifted_month - 9 };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
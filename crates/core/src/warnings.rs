@@ -0,0 +1,122 @@
+//! Which warnings a compile reports, and whether they fail it.
+//!
+//! `@allow(...)` on a block or a line silences the warnings it names inside it, by
+//! their [code](crate::diagnostic), with `_` or `-` between words:
+//!
+//! ```z
+//! next Web @allow(ignored) {
+//!   Components {
+//!     Spinner @allow(unused_component)
+//!   }
+//! }
+//! ```
+//!
+//! `@allow(warnings)` silences every warning. Warnings about a target as a whole, such
+//! as a dependency override outside the known versions, are silenced on the target's
+//! block. Errors can't be silenced.
+//!
+//! What's left is reported as warnings, except that [`strict`](crate::CompileOptions::strict)
+//! makes errors of those about blocks nothing generates from, and
+//! [`deny_warnings`](crate::CompileOptions::deny_warnings) of all of them.
+
+use z_ast::visit::{self, ChildLine, Visitor};
+use z_ast::{Annotation, Element, Span};
+
+use crate::diagnostic::{self, Diagnostic, Severity};
+use crate::schema::suggestion;
+use crate::{tr, CompileOptions};
+
+/// Name of the annotation silencing warnings
+const ALLOW: &str = "allow";
+/// What `@allow(...)` takes to silence every warning
+const ALL: &str = "warnings";
+
+/// Warnings about blocks nothing generates from, which strict mode makes errors
+const UNHANDLED: &[&str] = &[diagnostic::UNKNOWN_TARGET, diagnostic::EMPTY_TARGET, diagnostic::IGNORED];
+
+/// The parts of a program where `@allow(...)` silences warnings
+#[derive(Debug, Default)]
+pub(crate) struct Allowed {
+    /// Each block or line with the codes it allows
+    regions: Vec<(Span, Vec<String>)>,
+    /// `@allow(...)` naming something that isn't a warning
+    unknown: Vec<Diagnostic>,
+}
+
+impl Allowed {
+    /// The `@allow(...)` annotations of `ast`, whose text is `source`
+    pub(crate) fn collect(ast: &Element, source: &str) -> Self {
+        let mut collector = Collector { allowed: Self::default(), source };
+        visit::walk_element(&mut collector, ast);
+        collector.allowed
+    }
+
+    /// Warnings for the names in `@allow(...)` that aren't warnings
+    pub(crate) fn unknown(&self) -> Vec<Diagnostic> {
+        self.unknown.clone()
+    }
+
+    /// Drop the warnings from `from` on that an `@allow(...)` silences, and make errors of
+    /// those `options` say should fail the build. Warnings without a span are taken to be
+    /// about `target`, the span of the target they belong to, if any.
+    pub(crate) fn settle(&self, diagnostics: &mut Vec<Diagnostic>, from: usize, target: Option<Span>, options: &CompileOptions) {
+        let settled = diagnostics.split_off(from).into_iter()
+            .filter(|diagnostic| diagnostic.is_error() || !self.allows(diagnostic.code, diagnostic.span.or(target)))
+            .map(|diagnostic| {
+                let fails = options.deny_warnings || (options.strict && UNHANDLED.contains(&diagnostic.code));
+                if fails {
+                    Diagnostic { severity: Severity::Error, ..diagnostic }
+                } else {
+                    diagnostic
+                }
+            });
+        diagnostics.extend(settled.collect::<Vec<_>>());
+    }
+
+    fn allows(&self, code: &str, span: Option<Span>) -> bool {
+        let Some(span) = span else {
+            return false;
+        };
+        self.regions.iter()
+            .filter(|(region, _)| region.start <= span.start && span.end <= region.end)
+            .any(|(_, codes)| codes.iter().any(|allowed| allowed == ALL || allowed == code))
+    }
+}
+
+struct Collector<'a> {
+    allowed: Allowed,
+    source: &'a str,
+}
+
+impl Collector<'_> {
+    fn annotations(&mut self, annotations: &[Annotation], span: Span) {
+        for annotation in annotations.iter().filter(|annotation| annotation.name == ALLOW) {
+            let mut codes = Vec::new();
+            for name in annotation.args.as_deref().unwrap_or_default().split(',').map(str::trim).filter(|name| !name.is_empty()) {
+                let code = name.replace('_', "-");
+                if code == ALL || diagnostic::WARNINGS.contains(&code.as_str()) {
+                    codes.push(code);
+                } else {
+                    let names: Vec<String> = diagnostic::WARNINGS.iter().map(|code| code.replace('-', "_")).collect();
+                    let names: Vec<&str> = names.iter().map(String::as_str).collect();
+                    self.allowed.unknown.push(
+                        Diagnostic::warning(diagnostic::INVALID, tr!("allow-unknown", name = name, suggestion = suggestion(name, &names)))
+                            .with_span(span, self.source),
+                    );
+                }
+            }
+            self.allowed.regions.push((span, codes));
+        }
+    }
+}
+
+impl Visitor for Collector<'_> {
+    fn visit_element(&mut self, element: &Element) {
+        self.annotations(&element.annotations, element.span);
+        visit::walk_element(self, element);
+    }
+
+    fn visit_child_line(&mut self, line: ChildLine<'_>) {
+        self.annotations(line.annotations, line.span);
+    }
+}
@@ -0,0 +1,107 @@
+//! Noticing when the files of a program change, so that it can be compiled again.
+//!
+//! The files are polled for their modification time and size, which works the same on
+//! every platform and on network and container mounts, where change notifications often
+//! don't arrive. A program is a handful of files, so looking at them a few times a second
+//! costs next to nothing. Changes are debounced: an editor that saves several files, or
+//! writes one in several steps, causes one compile once it's done.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use crate::toml::CONFIG_FILE;
+use crate::CompileResult;
+
+/// How often the files are looked at
+pub const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// How long the files must stay as they are after a change before it's reported
+pub const DEBOUNCE: Duration = Duration::from_millis(300);
+
+/// What a file looked like when it was last looked at: None when it didn't exist
+type Stamp = Option<(SystemTime, u64)>;
+
+/// The files of a program, and what each looked like
+#[derive(Debug, Clone, Default)]
+pub struct Watcher {
+    files: BTreeMap<PathBuf, Stamp>,
+}
+
+impl Watcher {
+    pub fn new(files: impl IntoIterator<Item = PathBuf>) -> Self {
+        let mut watcher = Self::default();
+        watcher.watch(files);
+        watcher
+    }
+
+    /// Watch `files` from now on instead, as they are now. A compile that couldn't read
+    /// the program has no files to give, so an empty list keeps those watched so far.
+    pub fn watch(&mut self, files: impl IntoIterator<Item = PathBuf>) {
+        let files: BTreeMap<PathBuf, Stamp> = files.into_iter().map(|file| {
+            let stamp = stamp(&file);
+            (file, stamp)
+        }).collect();
+        if !files.is_empty() {
+            self.files = files;
+        }
+    }
+
+    pub fn files(&self) -> impl Iterator<Item = &Path> {
+        self.files.keys().map(PathBuf::as_path)
+    }
+
+    /// Block until a file changes, is created or is deleted, and then stays as it is for
+    /// [`DEBOUNCE`]; returns the files that changed, sorted
+    pub fn wait(&mut self) -> Vec<PathBuf> {
+        let mut changed = Vec::new();
+        let mut quiet = Duration::ZERO;
+        loop {
+            std::thread::sleep(POLL_INTERVAL);
+            let now_changed = self.changed();
+            if now_changed.is_empty() {
+                quiet += POLL_INTERVAL;
+            } else {
+                quiet = Duration::ZERO;
+                changed.extend(now_changed);
+            }
+            if !changed.is_empty() && quiet >= DEBOUNCE {
+                changed.sort();
+                changed.dedup();
+                return changed;
+            }
+        }
+    }
+
+    /// The files that look different from last time, which are recorded as they are now
+    fn changed(&mut self) -> Vec<PathBuf> {
+        self.files.iter_mut()
+            .filter_map(|(file, seen)| {
+                let now = stamp(file);
+                (now != *seen).then(|| {
+                    *seen = now;
+                    file.clone()
+                })
+            })
+            .collect()
+    }
+}
+
+/// The files the compile of `entry` read, to watch for the next one: the entry, the files
+/// it imports and the `z.toml` next to it, whether or not there is one yet. None when the
+/// program couldn't be read, since then it's unknown what it imports.
+pub fn inputs(entry: &Path, result: &CompileResult) -> Vec<PathBuf> {
+    if result.inputs.is_empty() {
+        return Vec::new();
+    }
+    let dir = entry.parent().unwrap_or(Path::new(""));
+    let mut files: Vec<PathBuf> = result.inputs.iter().map(|input| dir.join(&input.path)).collect();
+    files.push(dir.join(CONFIG_FILE));
+    files
+}
+
+fn stamp(file: &Path) -> Stamp {
+    let metadata = fs::metadata(file).ok()?;
+    Some((metadata.modified().ok()?, metadata.len()))
+}
@@ -0,0 +1,28 @@
+//! Every example of the gallery compiles, in memory, without errors.
+
+use std::path::Path;
+use std::process::Command;
+
+use z_compiler_core::gallery::EXAMPLES;
+use z_compiler_core::{compile, CompileOptions};
+
+/// Whether `pnpm` is installed, which `tauri` targets are scaffolded with
+fn has_pnpm() -> bool {
+    Command::new("pnpm").arg("--version").output().is_ok_and(|output| output.status.success())
+}
+
+#[test]
+fn examples_compile_without_errors() {
+    let options = CompileOptions { in_memory: true, ..CompileOptions::default() };
+    let mut failed = Vec::new();
+    for example in EXAMPLES {
+        let result = compile(example.source(), Path::new(example.name), &options);
+        let errors = result.diagnostics.iter()
+            .filter(|diagnostic| diagnostic.is_error())
+            // Without the scaffolding tool, a `tauri` target can't be generated here
+            .filter(|diagnostic| has_pnpm() || !diagnostic.message.contains("create-tauri-app"));
+        failed.extend(errors.map(|diagnostic| format!("{}: {}", example.name, diagnostic)));
+        assert!(!result.files.is_empty() || result.has_errors(), "{} generated no files", example.name);
+    }
+    assert!(failed.is_empty(), "{}", failed.join("\n"));
+}
@@ -0,0 +1,140 @@
+//! Parse throughput on a generated 50k-line `.z` file, building the regular tree and
+//! the interned [`ArenaAst`], and the cost of walking and cloning each.
+//!
+//! Run with `cargo bench -p z-parser`.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+use z_ast::{ArenaAst, Element, Node};
+use z_parser::{lexer::tokenize, parse_arena, parse_source};
+
+/// Counts allocations, to show how much of the difference is the allocator
+struct Counting;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: Counting = Counting;
+
+const TARGET_LINES: usize = 50_000;
+const RUNS: u32 = 20;
+
+/// Build a workspace of many small apps, mixing every construct the parser knows
+fn generate_source() -> String {
+    let mut src = String::from("// Generated benchmark input\nworkspace bench-platform {\n");
+    let mut lines = 2;
+    let mut app = 0;
+    while lines < TARGET_LINES {
+        let block = format!(
+            r#"  @experimental
+  next WebApp{app} {{
+    Routes {{
+      home
+      products
+      [slug] @dynamic
+      "about-us"
+    }}
+
+    // Shared components
+    Components {{
+      Button(label: string, onClick: () => void) @client
+      ProductCard(product: Product)
+    }}
+
+    Config {{
+      title: "Store {app}"
+      theme: {{ primary: "blue", radius: 4 }}
+      pages: [1, 2, 3]
+    }}
+  }}
+
+"#
+        );
+        lines += block.lines().count();
+        src.push_str(&block);
+        app += 1;
+    }
+    src.push_str("}\n");
+    src
+}
+
+fn time<T>(mut f: impl FnMut() -> T) -> Duration {
+    // Warm up once so the first run's allocations don't skew the numbers
+    std::hint::black_box(f());
+    let start = Instant::now();
+    for _ in 0..RUNS {
+        std::hint::black_box(f());
+    }
+    start.elapsed() / RUNS
+}
+
+/// Allocations made by one call of `f`
+fn allocations<T>(f: impl FnOnce() -> T) -> usize {
+    let before = ALLOCATIONS.load(Ordering::Relaxed);
+    std::hint::black_box(f());
+    ALLOCATIONS.load(Ordering::Relaxed) - before
+}
+
+/// Blocks named `name`, comparing strings
+fn count_blocks(element: &Element, name: &str) -> usize {
+    let nested: usize = element.children.iter()
+        .map(|child| match child {
+            Node::Element(child) => count_blocks(child, name),
+            _ => 0,
+        })
+        .sum();
+    nested + usize::from(element.name == name)
+}
+
+/// Blocks named `name`, comparing symbols
+fn count_arena_blocks(ast: &ArenaAst, name: &str) -> usize {
+    let Some(name) = ast.symbol(name) else {
+        return 0;
+    };
+    ast.items().filter(|(_, item)| item.name == name).count()
+}
+
+fn main() {
+    let src = generate_source();
+    let lines = src.lines().count();
+    println!("input: {} lines, {} KiB", lines, src.len() / 1024);
+
+    parse_source(&src).expect("benchmark input should parse");
+
+    let lex = time(|| tokenize(&src));
+    let parse = time(|| parse_source(&src).unwrap());
+    let arena = time(|| parse_arena(&src).unwrap());
+    println!("tokenize     {:>8.2?} / run", lex);
+    println!("parse_source {:>8.2?} / run, {:>7} allocations", parse, allocations(|| parse_source(&src)));
+    println!("parse_arena  {:>8.2?} / run, {:>7} allocations", arena, allocations(|| parse_arena(&src)));
+    println!("throughput   {:>8.0} lines/ms (arena {:.0})", lines as f64 / parse.as_secs_f64() / 1000.0, lines as f64 / arena.as_secs_f64() / 1000.0);
+
+    let (tree, ast) = (parse_source(&src).unwrap(), parse_arena(&src).unwrap());
+    assert_eq!(count_blocks(&tree, "Routes"), count_arena_blocks(&ast, "Routes"));
+    println!("walk         {:>8.2?} / run (arena {:.2?})", time(|| count_blocks(&tree, "Routes")), time(|| count_arena_blocks(&ast, "Routes")));
+    println!(
+        "clone        {:>8.2?} / run, {:>7} allocations (arena {:.2?}, {} allocations)",
+        time(|| tree.clone()),
+        allocations(|| tree.clone()),
+        time(|| ast.clone()),
+        allocations(|| ast.clone()),
+    );
+}
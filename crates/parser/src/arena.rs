@@ -0,0 +1,80 @@
+//! Parsing straight into an [`ArenaAst`], without building [`Element`](z_ast::Element)s first.
+
+use z_ast::arena::{Annotation, ItemId};
+use z_ast::{ArenaAst, Span};
+
+use crate::parser::{parse_items, Body, Build, Header};
+use crate::{check_size, Limits};
+
+/// [`parse_source`](crate::parse_source) into the compact [`ArenaAst`], for large
+/// programs. Comments and blank lines are dropped.
+pub fn parse_arena(src: &str) -> Result<ArenaAst, String> {
+    parse_arena_with(src, &Limits::default())
+}
+
+/// [`parse_arena`] with custom limits
+pub fn parse_arena_with(src: &str, limits: &Limits) -> Result<ArenaAst, String> {
+    check_size(src, limits)?;
+    // About one item per line of typical source
+    let mut ast = ArenaAst::with_capacity(src.len() / 24);
+    let mut build = Arena { ast: &mut ast, name: String::new() };
+    let body = parse_items(src, 0, src.len(), limits, &mut build)?;
+
+    let program = ast.intern("Program");
+    ast.block(program, None, &[], &body.children, Span::new(0, src.len()));
+    Ok(ast)
+}
+
+/// Adds items to an [`ArenaAst`], interning their text
+struct Arena<'t> {
+    ast: &'t mut ArenaAst,
+    /// Reused to put `kind:name` together without allocating for every target
+    name: String,
+}
+
+impl Arena<'_> {
+    fn intern(&mut self, text: Option<&str>) -> Option<z_ast::Symbol> {
+        text.map(|text| self.ast.intern(text))
+    }
+}
+
+impl<'a> Build<'a> for Arena<'_> {
+    type Node = ItemId;
+    type Annotation = Annotation;
+    const TRIVIA: bool = false;
+
+    fn annotation(&mut self, name: &'a str, args: Option<&'a str>) -> Annotation {
+        Annotation { name: self.ast.intern(name), args: self.intern(args) }
+    }
+
+    fn property(&mut self, key: &'a str, value: &'a str, span: Span) -> ItemId {
+        let (key, value) = (self.ast.intern(key), self.ast.intern(value));
+        self.ast.property(key, value, span)
+    }
+
+    fn constant(&mut self, name: &'a str, value: &'a str, span: Span) -> ItemId {
+        let (name, value) = (self.ast.intern(name), self.ast.intern(value));
+        self.ast.constant(name, value, span)
+    }
+
+    fn line(&mut self, header: Header<'a>, annotations: Vec<Annotation>, span: Span) -> ItemId {
+        let (modifier, signature) = (self.intern(header.modifier), self.intern(header.signature));
+        let id = self.ast.intern(header.id);
+        self.ast.line(modifier, id, signature, &annotations, span)
+    }
+
+    fn block(&mut self, header: Header<'a>, annotations: Vec<Annotation>, body: Body<ItemId>, _comment: Option<&'a str>, span: Span) -> ItemId {
+        let name = match header.modifier {
+            Some(modifier) => {
+                self.name.clear();
+                self.name.push_str(modifier);
+                self.name.push(':');
+                self.name.push_str(header.id);
+                self.ast.intern(&self.name)
+            }
+            None => self.ast.intern(header.id),
+        };
+        let signature = self.intern(header.signature);
+        self.ast.block(name, signature, &annotations, &body.children, span)
+    }
+}
@@ -0,0 +1,183 @@
+use z_ast::Span;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TokenKind {
+    /// Identifier, possibly hyphenated (`ecommerce-platform`)
+    Ident,
+    Number,
+    /// Quoted string literal, quotes included
+    Str,
+    /// `// ...` up to (not including) the newline
+    Comment,
+    Newline,
+    At,
+    Colon,
+    Comma,
+    LBrace,
+    RBrace,
+    LParen,
+    RParen,
+    LBracket,
+    RBracket,
+    /// Any other single character (operators, dots, ...)
+    Punct,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Token {
+    pub kind: TokenKind,
+    pub span: Span,
+}
+
+/// Single-pass tokenizer over the source bytes. Spaces and tabs are skipped;
+/// newlines and comments are kept because the grammar is line oriented.
+pub fn tokenize(src: &str) -> Vec<Token> {
+    tokenize_range(src, 0, src.len())
+}
+
+/// Tokenize `src[start..end]`, with spans relative to the whole of `src`
+pub fn tokenize_range(src: &str, start: usize, end: usize) -> Vec<Token> {
+    let bytes = &src.as_bytes()[..end];
+    // Rough guess: one token every four bytes keeps reallocation rare
+    let mut tokens = Vec::with_capacity((end - start) / 4);
+    let mut i = start;
+
+    while i < end {
+        let b = bytes[i];
+        let token_start = i;
+        let kind = match b {
+            b' ' | b'\t' | b'\r' => {
+                i += 1;
+                continue;
+            }
+            b'\n' => {
+                i += 1;
+                TokenKind::Newline
+            }
+            b'/' if bytes.get(i + 1) == Some(&b'/') => {
+                i += 2;
+                while i < end && bytes[i] != b'\n' {
+                    i += 1;
+                }
+                TokenKind::Comment
+            }
+            b'"' | b'\'' | b'`' => {
+                i += 1;
+                while i < end && bytes[i] != b {
+                    // Plain quotes don't span lines; an unterminated one ends at the newline
+                    if bytes[i] == b'\n' && b != b'`' {
+                        break;
+                    }
+                    // An escape takes the next byte with it, except a newline a plain quote stops at
+                    let escape = bytes[i] == b'\\' && (b == b'`' || bytes.get(i + 1) != Some(&b'\n'));
+                    i += if escape { 2 } else { 1 };
+                }
+                i = (i + 1).min(end);
+                if bytes[i - 1] == b'\n' {
+                    i -= 1;
+                }
+                TokenKind::Str
+            }
+            b'0'..=b'9' => {
+                while i < end && (bytes[i].is_ascii_alphanumeric() || bytes[i] == b'.' || bytes[i] == b'_') {
+                    i += 1;
+                }
+                TokenKind::Number
+            }
+            b if b.is_ascii_alphabetic() || b == b'_' || b >= 0x80 => {
+                i += 1;
+                while i < end {
+                    let c = bytes[i];
+                    let hyphen = c == b'-' && bytes.get(i + 1).is_some_and(|n| n.is_ascii_alphanumeric()) && i + 1 < end;
+                    if c.is_ascii_alphanumeric() || c == b'_' || c >= 0x80 || hyphen {
+                        i += 1;
+                    } else {
+                        break;
+                    }
+                }
+                TokenKind::Ident
+            }
+            _ => {
+                i += 1;
+                match b {
+                    b'@' => TokenKind::At,
+                    b':' => TokenKind::Colon,
+                    b',' => TokenKind::Comma,
+                    b'{' => TokenKind::LBrace,
+                    b'}' => TokenKind::RBrace,
+                    b'(' => TokenKind::LParen,
+                    b')' => TokenKind::RParen,
+                    b'[' => TokenKind::LBracket,
+                    b']' => TokenKind::RBracket,
+                    _ => TokenKind::Punct,
+                }
+            }
+        };
+        tokens.push(Token { kind, span: Span::new(token_start, i) });
+    }
+
+    tokens
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tokens(src: &str) -> Vec<(TokenKind, &str)> {
+        tokenize(src).into_iter().map(|token| (token.kind, &src[token.span.start..token.span.end])).collect()
+    }
+
+    fn strings(src: &str) -> Vec<&str> {
+        tokens(src).into_iter().filter(|(kind, _)| *kind == TokenKind::Str).map(|(_, text)| text).collect()
+    }
+
+    #[test]
+    fn strings_keep_their_quotes() {
+        assert_eq!(strings(r#"title: "Hello, world" 'a' `b`"#), [r#""Hello, world""#, "'a'", "`b`"]);
+    }
+
+    #[test]
+    fn escapes_take_the_next_character() {
+        assert_eq!(strings(r#"a: "say \"hi\"" b: "back\\" c"#), [r#""say \"hi\"""#, r#""back\\""#]);
+        assert_eq!(strings(r"a: 'it\'s'"), [r"'it\'s'"]);
+    }
+
+    #[test]
+    fn plain_strings_stop_at_the_newline() {
+        assert_eq!(tokens("a: \"open\nb"), [
+            (TokenKind::Ident, "a"),
+            (TokenKind::Colon, ":"),
+            (TokenKind::Str, "\"open"),
+            (TokenKind::Newline, "\n"),
+            (TokenKind::Ident, "b"),
+        ]);
+    }
+
+    #[test]
+    fn an_escaped_newline_still_ends_a_plain_string() {
+        assert_eq!(strings("a: \"open\\\nb: \"next\""), ["\"open\\", "\"next\""]);
+        assert_eq!(tokens("'x\\\n").last(), Some(&(TokenKind::Newline, "\n")));
+    }
+
+    #[test]
+    fn template_strings_span_lines() {
+        assert_eq!(strings("a: `one\ntwo\\\nthree`\n"), ["`one\ntwo\\\nthree`"]);
+    }
+
+    #[test]
+    fn a_trailing_backslash_ends_at_the_end_of_the_source() {
+        assert_eq!(strings("a: \"x\\"), ["\"x\\"]);
+        assert_eq!(strings("a: `x\\"), ["`x\\"]);
+    }
+
+    #[test]
+    fn comments_and_hyphenated_names() {
+        assert_eq!(tokens("ecommerce-platform - x // note\n"), [
+            (TokenKind::Ident, "ecommerce-platform"),
+            (TokenKind::Punct, "-"),
+            (TokenKind::Ident, "x"),
+            (TokenKind::Comment, "// note"),
+            (TokenKind::Newline, "\n"),
+        ]);
+    }
+}
@@ -1,30 +1,62 @@
-use z_ast::{Element, Node, Annotation};
+use z_ast::{assign_ids, Element, NodeId, Span, Trivia};
 
-// Temporary regex-based parser until PEG is integrated
+mod arena;
+pub mod lexer;
+mod parser;
+mod reparse;
+pub use arena::{parse_arena, parse_arena_with};
+pub use reparse::{reparse, TextEdit};
+
+/// Parse a whole `.z` source into a `Program` element.
+///
+/// The source is tokenized in a single pass and parsed by recursive descent.
+/// Top-level blocks like `next MySite { ... }` are named `"next:MySite"`;
+/// nested blocks keep their plain name.
 pub fn parse_source(src: &str) -> Result<Element, String> {
-    let mut children = Vec::new();
+    parse_source_with(src, &Limits::default())
+}
 
-    // Find top-level blocks like "next MySite {", "swift MyApp {", etc.
-    for line in src.lines() {
-        let trimmed = line.trim();
-        if let Some(caps) = regex::Regex::new(r"^([a-z]+)\s+([A-Za-z0-9_]+)\s*\{")
-            .unwrap()
-            .captures(trimmed)
-        {
-            let target_type = caps[1].to_string();
-            let app_name = caps[2].to_string();
+/// Bounds on a source, so generated or hostile input fails with an error instead of
+/// overflowing the stack or exhausting memory
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Limits {
+    /// Size of the source in bytes
+    pub max_bytes: usize,
+    /// Blocks nested inside each other
+    pub max_depth: usize,
+    /// Blocks, lines, properties and constants in the whole source
+    pub max_nodes: usize,
+}
 
-            children.push(Node::Element(Element {
-                name: format!("{}:{}", target_type, app_name), // Store as "target:name"
-                annotations: Vec::new(),
-                children: Vec::new(),
-            }));
-        }
+impl Default for Limits {
+    fn default() -> Self {
+        Self { max_bytes: 8 * 1024 * 1024, max_depth: 128, max_nodes: 200_000 }
     }
+}
+
+/// [`parse_source`] with custom limits
+pub fn parse_source_with(src: &str, limits: &Limits) -> Result<Element, String> {
+    check_size(src, limits)?;
+    let body = parser::parse_items(src, 0, src.len(), limits, &mut parser::Elements)?;
 
-    Ok(Element {
+    let mut program = Element {
         name: "Program".to_string(),
         annotations: Vec::new(),
-        children,
-    })
-}
\ No newline at end of file
+        children: body.children,
+        signature: None,
+        span: Span::new(0, src.len()),
+        id: NodeId::default(),
+        trivia: Trivia::default(),
+        // Comments and blank lines at the end of the file
+        body_trivia: Trivia { leading: body.dangling, trailing: None },
+    };
+    assign_ids(&mut program);
+    Ok(program)
+}
+
+fn check_size(src: &str, limits: &Limits) -> Result<(), String> {
+    if src.len() > limits.max_bytes {
+        return Err(format!("source is {} bytes, over the limit of {} bytes", src.len(), limits.max_bytes));
+    }
+    Ok(())
+}
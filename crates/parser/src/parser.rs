@@ -0,0 +1,577 @@
+use z_ast::{Annotation, Element, Node, NodeId, Span, Trivia, TriviaPiece};
+
+use crate::lexer::{tokenize_range, Token, TokenKind};
+use crate::Limits;
+
+/// Items of a block or file, plus the comments and blank lines after the last of them
+pub(crate) struct Body<N> {
+    pub children: Vec<N>,
+    pub dangling: Vec<TriviaPiece>,
+}
+
+/// The words an item starts with, as in `page dashboard(id: string)`
+pub(crate) struct Header<'a> {
+    pub modifier: Option<&'a str>,
+    /// The item's id, or its whole text when it isn't a name
+    pub id: &'a str,
+    pub signature: Option<&'a str>,
+}
+
+/// Makes the tree out of the items the parser recognizes, borrowing their text
+/// from the source
+pub(crate) trait Build<'a> {
+    type Node;
+    type Annotation;
+    /// Whether comments and blank lines are kept
+    const TRIVIA: bool;
+
+    fn annotation(&mut self, name: &'a str, args: Option<&'a str>) -> Self::Annotation;
+    fn property(&mut self, key: &'a str, value: &'a str, span: Span) -> Self::Node;
+    fn constant(&mut self, name: &'a str, value: &'a str, span: Span) -> Self::Node;
+    fn line(&mut self, header: Header<'a>, annotations: Vec<Self::Annotation>, span: Span) -> Self::Node;
+    /// A block, named `modifier:id` when it has a modifier; `comment` follows its `{`
+    fn block(
+        &mut self,
+        header: Header<'a>,
+        annotations: Vec<Self::Annotation>,
+        body: Body<Self::Node>,
+        comment: Option<&'a str>,
+        span: Span,
+    ) -> Self::Node;
+
+    /// Comments and blank lines above `node`; only called when keeping [`TRIVIA`](Self::TRIVIA)
+    fn leading(&mut self, _node: &mut Self::Node, _trivia: Vec<TriviaPiece>) {}
+    fn trailing(&mut self, _node: &mut Self::Node, _comment: &'a str) {}
+}
+
+/// Builds [`Node`]s, keeping trivia
+pub(crate) struct Elements;
+
+impl<'a> Build<'a> for Elements {
+    type Node = Node;
+    type Annotation = Annotation;
+    const TRIVIA: bool = true;
+
+    fn annotation(&mut self, name: &'a str, args: Option<&'a str>) -> Annotation {
+        Annotation { name: name.to_string(), args: args.map(str::to_string) }
+    }
+
+    fn property(&mut self, key: &'a str, value: &'a str, span: Span) -> Node {
+        Node::KeyValue { key: key.to_string(), value: value.to_string(), span, trivia: Trivia::default() }
+    }
+
+    fn constant(&mut self, name: &'a str, value: &'a str, span: Span) -> Node {
+        Node::Const { name: name.to_string(), value: value.to_string(), span, trivia: Trivia::default() }
+    }
+
+    fn line(&mut self, header: Header<'a>, annotations: Vec<Annotation>, span: Span) -> Node {
+        Node::ChildLine {
+            modifier: header.modifier.map(str::to_string),
+            id: header.id.to_string(),
+            signature: header.signature.map(str::to_string),
+            annotations,
+            span,
+            trivia: Trivia::default(),
+        }
+    }
+
+    fn block(&mut self, header: Header<'a>, annotations: Vec<Annotation>, body: Body<Node>, comment: Option<&'a str>, span: Span) -> Node {
+        Node::Element(Element {
+            name: block_name(&header),
+            annotations,
+            children: body.children,
+            signature: header.signature.map(str::to_string),
+            span,
+            // Assigned once the whole tree is known
+            id: NodeId::default(),
+            trivia: Trivia::default(),
+            body_trivia: Trivia { leading: body.dangling, trailing: comment.map(str::to_string) },
+        })
+    }
+
+    fn leading(&mut self, node: &mut Node, trivia: Vec<TriviaPiece>) {
+        node.trivia_mut().leading = trivia;
+    }
+
+    fn trailing(&mut self, node: &mut Node, comment: &'a str) {
+        node.trivia_mut().trailing = Some(comment.to_string());
+    }
+}
+
+/// Name of the block `header` opens: `next:WebApp` for a target, `Routes` otherwise
+pub(crate) fn block_name(header: &Header) -> String {
+    match header.modifier {
+        Some(modifier) => format!("{}:{}", modifier, header.id),
+        None => header.id.to_string(),
+    }
+}
+
+/// Parse `src[start..end]` as a sequence of top-level items
+pub(crate) fn parse_items<'a, B: Build<'a>>(
+    src: &'a str,
+    start: usize,
+    end: usize,
+    limits: &Limits,
+    build: &mut B,
+) -> Result<Body<B::Node>, String> {
+    let mut parser = Parser {
+        src,
+        build,
+        tokens: tokenize_range(src, start, end),
+        pos: 0,
+        limits: *limits,
+        depth: 0,
+        nodes: 0,
+    };
+
+    // The lexer ends an unterminated string at the newline, or at the end of the file for a
+    // template string, which would otherwise be taken as the value silently
+    if let Some(token) = parser.tokens.iter()
+        .find(|token| token.kind == TokenKind::Str && !terminated(parser.text(token.span)))
+    {
+        let what = if parser.text(token.span).starts_with('`') { "template string" } else { "string" };
+        return Err(parser.error(token.span.start, &format!("unterminated {}", what)));
+    }
+
+    parser.parse_body(false, start == 0 || src[..start].ends_with('\n'))
+}
+
+/// Whether a string token ends with its opening quote, rather than an escaped one or none
+fn terminated(text: &str) -> bool {
+    let bytes = text.as_bytes();
+    let Some((&quote, rest)) = bytes.split_first() else {
+        return false;
+    };
+    match rest.split_last() {
+        Some((&last, body)) if last == quote => body.iter().rev().take_while(|&&b| b == b'\\').count() % 2 == 0,
+        _ => false,
+    }
+}
+
+struct Parser<'a, 'b, B> {
+    src: &'a str,
+    build: &'b mut B,
+    tokens: Vec<Token>,
+    pos: usize,
+    limits: Limits,
+    /// Blocks open around the current position
+    depth: usize,
+    /// Items parsed so far
+    nodes: usize,
+}
+
+impl<'a, B: Build<'a>> Parser<'a, '_, B> {
+    fn kind(&self, offset: usize) -> Option<TokenKind> {
+        self.tokens.get(self.pos + offset).map(|token| token.kind)
+    }
+
+    fn text(&self, span: Span) -> &'a str {
+        &self.src[span.start..span.end]
+    }
+
+    fn error(&self, offset: usize, message: &str) -> String {
+        let line = self.src[..offset].bytes().filter(|&b| b == b'\n').count() + 1;
+        format!("line {}: {}", line, message)
+    }
+
+    /// Parse items until the closing brace of a `block` (left unconsumed),
+    /// or until the end of input for the top level.
+    ///
+    /// Comments and blank lines become trivia: lines of their own lead the next
+    /// item, and a comment sharing a line with an item trails it.
+    fn parse_body(&mut self, block: bool, mut at_line_start: bool) -> Result<Body<B::Node>, String> {
+        let mut children: Vec<B::Node> = Vec::new();
+        let mut annotations = Vec::new();
+        let mut annotations_start = None;
+        let mut first_annotation = "";
+        let mut trivia = Vec::new();
+
+        while let Some(token) = self.tokens.get(self.pos).copied() {
+            match token.kind {
+                TokenKind::Newline => {
+                    if at_line_start && B::TRIVIA {
+                        trivia.push(TriviaPiece::BlankLine);
+                    }
+                    at_line_start = true;
+                    self.pos += 1;
+                }
+                TokenKind::Comment => {
+                    let comment = self.text(token.span).trim_end();
+                    if !B::TRIVIA {
+                        // Nothing to attach it to
+                    } else if at_line_start || annotations_start.is_some() {
+                        trivia.push(TriviaPiece::Comment(comment.to_string()));
+                    } else if let Some(last) = children.last_mut() {
+                        self.build.trailing(last, comment);
+                    }
+                    // Otherwise it trails something before the parsed range
+                    at_line_start = false;
+                    self.pos += 1;
+                }
+                TokenKind::RBrace if block => break,
+                TokenKind::RBrace => return Err(self.error(token.span.start, "unexpected '}'")),
+                TokenKind::At => {
+                    if annotations_start.is_none() {
+                        annotations_start = Some(token.span.start);
+                        first_annotation = self.tokens.get(self.pos + 1).map_or("", |name| self.text(name.span));
+                    }
+                    annotations.push(self.parse_annotation()?);
+                    at_line_start = false;
+                }
+                _ => {
+                    let start = annotations_start.take().unwrap_or(token.span.start);
+                    self.nodes += 1;
+                    if self.nodes > self.limits.max_nodes {
+                        return Err(self.error(start, &format!("more than {} items in the source", self.limits.max_nodes)));
+                    }
+                    let mut item = self.parse_item(std::mem::take(&mut annotations), start)?;
+                    if !trivia.is_empty() {
+                        self.build.leading(&mut item, std::mem::take(&mut trivia));
+                    }
+                    children.push(item);
+                    at_line_start = false;
+                }
+            }
+        }
+
+        if let Some(start) = annotations_start {
+            return Err(self.error(start, &format!("annotation '@{}' is not attached to anything", first_annotation)));
+        }
+        Ok(Body { children, dangling: trivia })
+    }
+
+    /// `@name` or `@name(args)`
+    fn parse_annotation(&mut self) -> Result<B::Annotation, String> {
+        let at = self.tokens[self.pos];
+        self.pos += 1;
+        if self.kind(0) != Some(TokenKind::Ident) {
+            return Err(self.error(at.span.start, "expected annotation name after '@'"));
+        }
+        let name = self.text(self.tokens[self.pos].span);
+        self.pos += 1;
+
+        let mut args = None;
+        if self.kind(0) == Some(TokenKind::LParen) {
+            let open = self.tokens[self.pos].span;
+            let close = self.skip_balanced()
+                .ok_or_else(|| self.error(open.start, &format!("unclosed '(' in annotation '@{}'", name)))?;
+            args = Some(self.src[open.end..close.start].trim());
+        }
+
+        Ok(self.build.annotation(name, args))
+    }
+
+    /// Skip from an opening bracket to its matching closer, returning the closer's span
+    fn skip_balanced(&mut self) -> Option<Span> {
+        let mut depth = 0usize;
+        while let Some(token) = self.tokens.get(self.pos).copied() {
+            self.pos += 1;
+            match token.kind {
+                TokenKind::LParen | TokenKind::LBracket | TokenKind::LBrace => depth += 1,
+                TokenKind::RParen | TokenKind::RBracket | TokenKind::RBrace => {
+                    depth -= 1;
+                    if depth == 0 {
+                        return Some(token.span);
+                    }
+                }
+                _ => {}
+            }
+        }
+        None
+    }
+
+    fn parse_item(&mut self, annotations: Vec<B::Annotation>, start: usize) -> Result<B::Node, String> {
+        let first = self.tokens[self.pos];
+
+        // `key: value` runs to the end of the line
+        if first.kind == TokenKind::Ident && self.kind(1) == Some(TokenKind::Colon) {
+            let key = self.text(first.span);
+            if !annotations.is_empty() {
+                return Err(self.error(start, &format!("annotations can't be applied to property '{}'", key)));
+            }
+            self.pos += 2;
+            let value_start = self.tokens[self.pos - 1].span.end;
+            let end = self.scan_line(true, key)?.unwrap_or(value_start);
+            let value = self.src[value_start..end].trim();
+            return Ok(self.build.property(key, value, Span::new(first.span.start, end)));
+        }
+
+        // `const name = value` runs to the end of the line
+        if self.text(first.span) == "const"
+            && self.kind(1) == Some(TokenKind::Ident)
+            && self.kind(2) == Some(TokenKind::Punct)
+            && self.text(self.tokens[self.pos + 2].span) == "="
+        {
+            let name = self.text(self.tokens[self.pos + 1].span);
+            if !annotations.is_empty() {
+                return Err(self.error(start, &format!("annotations can't be applied to constant '{}'", name)));
+            }
+            self.pos += 3;
+            let value_start = self.tokens[self.pos - 1].span.end;
+            let Some(end) = self.scan_line(true, name)? else {
+                return Err(self.error(first.span.start, &format!("constant '{}' has no value", name)));
+            };
+            let value = self.src[value_start..end].trim();
+            return Ok(self.build.constant(name, value, Span::new(first.span.start, end)));
+        }
+
+        // Leading words: `Routes`, `next WebApp`, `for product in products`
+        // Only the first two words matter; the rest are part of the signature
+        let mut words = [Span::default(); 2];
+        let mut count = 0;
+        while self.kind(0) == Some(TokenKind::Ident) {
+            if count < words.len() {
+                words[count] = self.tokens[self.pos].span;
+                count += 1;
+            }
+            self.pos += 1;
+        }
+        if count == 0
+            && self.kind(0) == Some(TokenKind::LBracket)
+            && self.kind(1) == Some(TokenKind::Ident)
+            && self.kind(2) == Some(TokenKind::RBracket)
+        {
+            // Dynamic identifier like [slug]
+            words[0] = Span::new(self.tokens[self.pos].span.start, self.tokens[self.pos + 2].span.end);
+            count = 1;
+            self.pos += 3;
+        }
+        let words = &words[..count];
+
+        let (modifier, id) = match words {
+            [] => (None, None),
+            [id] => (None, Some(self.text(*id))),
+            [modifier, id, ..] => (Some(self.text(*modifier)), Some(self.text(*id))),
+        };
+        let signature_start = words.get(1).or(words.first()).map_or(first.span.start, |word| word.end);
+
+        // The rest of the header, up to a newline or the opening brace of a body
+        let rest_start = self.pos;
+        let rest_end = self.scan_line(false, id.unwrap_or("item"))?;
+        let (signature, trailing) = self.split_annotations(rest_start, signature_start);
+        let mut annotations = annotations;
+        annotations.extend(trailing);
+        let header_end = rest_end.unwrap_or(signature_start);
+
+        if self.kind(0) == Some(TokenKind::LBrace) {
+            // Stored as "target:name" when there's a modifier
+            let header = match id {
+                Some(id) => Header { modifier, id, signature },
+                None => Header { modifier: None, id: self.src[first.span.start..header_end].trim(), signature: None },
+            };
+            if header.id.is_empty() {
+                return Err(self.error(first.span.start, "block without a name"));
+            }
+
+            let open = self.tokens[self.pos].span;
+            if self.depth == self.limits.max_depth {
+                return Err(self.error(open.start, &format!("blocks nested more than {} levels deep", self.limits.max_depth)));
+            }
+            self.pos += 1;
+            let mut comment = None;
+            if self.kind(0) == Some(TokenKind::Comment) {
+                comment = Some(self.text(self.tokens[self.pos].span).trim_end());
+                self.pos += 1;
+            }
+            self.depth += 1;
+            let body = self.parse_body(true, false)?;
+            self.depth -= 1;
+            let Some(close) = self.tokens.get(self.pos).copied() else {
+                return Err(self.error(open.start, &format!("unclosed block '{}'", block_name(&header))));
+            };
+            self.pos += 1;
+
+            return Ok(self.build.block(header, annotations, body, comment, Span::new(start, close.span.end)));
+        }
+
+        let end = header_end.max(first.span.end);
+        let header = match id {
+            Some(id) => Header { modifier, id, signature },
+            // Anything else (a string, an expression) is kept verbatim as the id
+            None => Header { modifier: None, id: self.src[first.span.start..end].trim(), signature: None },
+        };
+        Ok(self.build.line(header, annotations, Span::new(start, end)))
+    }
+
+    /// Advance over the rest of a line, stopping at a newline or comment outside
+    /// brackets, or at a `{` / `}` outside brackets. With `braces_inline` a `{ ... }`
+    /// is part of the line (property values) rather than the start of a body.
+    /// Returns the end offset of the last token consumed.
+    fn scan_line(&mut self, braces_inline: bool, item: &str) -> Result<Option<usize>, String> {
+        let mut depth = 0usize;
+        let mut end = None;
+        let mut opened_at = 0;
+
+        while let Some(token) = self.tokens.get(self.pos).copied() {
+            match token.kind {
+                TokenKind::Newline | TokenKind::Comment if depth == 0 => break,
+                TokenKind::LBrace | TokenKind::RBrace if depth == 0 && !braces_inline => break,
+                TokenKind::RBrace if depth == 0 => break,
+                TokenKind::LParen | TokenKind::LBracket | TokenKind::LBrace => {
+                    if depth == 0 {
+                        opened_at = token.span.start;
+                    }
+                    depth += 1;
+                }
+                TokenKind::RParen | TokenKind::RBracket | TokenKind::RBrace => depth = depth.saturating_sub(1),
+                _ => {}
+            }
+            end = Some(token.span.end);
+            self.pos += 1;
+        }
+
+        if depth > 0 {
+            return Err(self.error(opened_at, &format!("unclosed bracket in '{}'", item)));
+        }
+        Ok(end)
+    }
+
+    /// Split the header tokens in `[from, self.pos)` into the signature text and
+    /// trailing `@annotations`. Annotations only count when nothing follows them.
+    fn split_annotations(&mut self, from: usize, signature_start: usize) -> (Option<&'a str>, Vec<B::Annotation>) {
+        let tokens = &self.tokens[from..self.pos];
+        let signature_end = tokens.last().map_or(signature_start, |token| token.span.end);
+
+        let mut annotations = Vec::new();
+        let mut annotations_start = None;
+        let mut i = 0;
+        let mut depth = 0usize;
+        while i < tokens.len() {
+            match tokens[i].kind {
+                TokenKind::At if depth == 0 && tokens.get(i + 1).map(|t| t.kind) == Some(TokenKind::Ident) => {
+                    annotations_start.get_or_insert(tokens[i].span.start);
+                    let name = self.text(tokens[i + 1].span);
+                    i += 2;
+                    let mut args = None;
+                    if tokens.get(i).map(|t| t.kind) == Some(TokenKind::LParen) {
+                        let open = tokens[i].span;
+                        let mut inner = 0usize;
+                        while i < tokens.len() {
+                            match tokens[i].kind {
+                                TokenKind::LParen | TokenKind::LBracket | TokenKind::LBrace => inner += 1,
+                                TokenKind::RParen | TokenKind::RBracket | TokenKind::RBrace => inner -= 1,
+                                _ => {}
+                            }
+                            i += 1;
+                            if inner == 0 {
+                                break;
+                            }
+                        }
+                        args = Some(self.src[open.end..tokens[i - 1].span.start].trim());
+                    }
+                    annotations.push((name, args));
+                    continue;
+                }
+                TokenKind::LParen | TokenKind::LBracket | TokenKind::LBrace => depth += 1,
+                TokenKind::RParen | TokenKind::RBracket | TokenKind::RBrace => depth = depth.saturating_sub(1),
+                _ => {}
+            }
+            if annotations_start.is_some() {
+                // Something other than an annotation follows: it's all signature
+                annotations.clear();
+                annotations_start = None;
+            }
+            i += 1;
+        }
+
+        let signature_end = annotations_start.unwrap_or(signature_end).max(signature_start);
+        let signature = self.src[signature_start..signature_end].trim();
+        let annotations = annotations.into_iter().map(|(name, args)| self.build.annotation(name, args)).collect();
+        ((!signature.is_empty()).then_some(signature), annotations)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use z_ast::{Element, Node};
+
+    use crate::{parse_source, parse_source_with, Limits};
+
+    fn first_item(src: &str) -> Node {
+        parse_source(src).unwrap().children.remove(0)
+    }
+
+    fn annotations(node: &Node) -> Vec<(&str, Option<&str>)> {
+        let annotations = match node {
+            Node::Element(Element { annotations, .. }) | Node::ChildLine { annotations, .. } => annotations,
+            _ => panic!("no annotations on {:?}", node),
+        };
+        annotations.iter().map(|annotation| (annotation.name.as_str(), annotation.args.as_deref())).collect()
+    }
+
+    #[test]
+    fn string_values_keep_quotes_and_escapes() {
+        let Node::KeyValue { key, value, .. } = first_item("title: \"Say \\\"hi\\\", { not a block }\"\n") else {
+            panic!("not a property");
+        };
+        assert_eq!(key, "title");
+        assert_eq!(value, "\"Say \\\"hi\\\", { not a block }\"");
+    }
+
+    #[test]
+    fn template_strings_span_lines() {
+        let Node::KeyValue { value, .. } = first_item("body: `line one\nline two`\n") else {
+            panic!("not a property");
+        };
+        assert_eq!(value, "`line one\nline two`");
+    }
+
+    #[test]
+    fn annotations_before_and_after_an_item() {
+        let block = first_item("@client\nnext Web @experimental @cache(ttl: 60) {\n  home\n}\n");
+        assert_eq!(annotations(&block), [("client", None), ("experimental", None), ("cache", Some("ttl: 60"))]);
+
+        let Node::Element(element) = block else { panic!("not a block") };
+        assert_eq!(annotations(&element.children[0]), []);
+        assert_eq!(annotations(&first_item("page home @auth(role: \"admin\")\n")), [("auth", Some("role: \"admin\""))]);
+    }
+
+    #[test]
+    fn annotations_followed_by_more_text_are_signature() {
+        let Node::ChildLine { signature, annotations, .. } = first_item("page home @auth then more\n") else {
+            panic!("not a line");
+        };
+        assert_eq!(signature.as_deref(), Some("@auth then more"));
+        assert!(annotations.is_empty());
+    }
+
+    #[test]
+    fn errors_for_misplaced_annotations() {
+        assert_eq!(parse_source("home\n@client\n").unwrap_err(), "line 2: annotation '@client' is not attached to anything");
+        assert_eq!(parse_source("@1 home\n").unwrap_err(), "line 1: expected annotation name after '@'");
+        assert_eq!(parse_source("@client title: \"x\"\n").unwrap_err(), "line 1: annotations can't be applied to property 'title'");
+    }
+
+    #[test]
+    fn unterminated_strings() {
+        assert_eq!(parse_source("next Web {\n  title: \"Home\n}\n").unwrap_err(), "line 2: unterminated string");
+        assert_eq!(parse_source("next Web {\n  title: \"Home\\\n}\n").unwrap_err(), "line 2: unterminated string");
+        assert_eq!(parse_source("a: 1\nb: 'x\\'\n").unwrap_err(), "line 2: unterminated string");
+        assert_eq!(parse_source("a: 1\n\nbody: `open\nand on\n").unwrap_err(), "line 3: unterminated template string");
+    }
+
+    #[test]
+    fn errors_name_the_line() {
+        assert_eq!(parse_source("next Web {\n  home\n}\n}\n").unwrap_err(), "line 4: unexpected '}'");
+        assert_eq!(parse_source("// intro\n\nnext Web {\n  Routes {\n    home\n}\n").unwrap_err(), "line 3: unclosed block 'next:Web'");
+        assert_eq!(parse_source("a: 1\nitems: [1, 2\n").unwrap_err(), "line 2: unclosed bracket in 'items'");
+        assert_eq!(parse_source("a: 1\n\nconst X =\n").unwrap_err(), "line 3: constant 'X' has no value");
+    }
+
+    #[test]
+    fn limits() {
+        let limits = Limits { max_bytes: 10, max_depth: 2, max_nodes: 3 };
+        assert_eq!(parse_source_with("next Web { home }", &limits).unwrap_err(), "source is 17 bytes, over the limit of 10 bytes");
+
+        let limits = Limits { max_depth: 2, ..Limits::default() };
+        assert!(parse_source_with("a {\n  b {\n    c\n  }\n}\n", &limits).is_ok());
+        assert_eq!(
+            parse_source_with("a {\n  b {\n    c {\n      d\n    }\n  }\n}\n", &limits).unwrap_err(),
+            "line 3: blocks nested more than 2 levels deep",
+        );
+
+        let limits = Limits { max_nodes: 3, ..Limits::default() };
+        assert!(parse_source_with("a\nb\nc\n", &limits).is_ok());
+        assert_eq!(parse_source_with("a\nb\nc\nd\n", &limits).unwrap_err(), "line 4: more than 3 items in the source");
+    }
+}
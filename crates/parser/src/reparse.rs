@@ -0,0 +1,193 @@
+use z_ast::{assign_ids, Element, Node, NodeId, Span};
+
+use crate::parser::{parse_items, Elements};
+use crate::{check_size, parse_source_with, Limits};
+
+/// A single text change, expressed against the source the old AST was parsed from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TextEdit {
+    /// Byte range in the old source that was replaced
+    pub range: Span,
+    /// Length in bytes of the text that replaced it
+    pub new_len: usize,
+}
+
+impl TextEdit {
+    pub fn new(range: Span, new_len: usize) -> Self {
+        Self { range, new_len }
+    }
+
+    /// Describe the difference between two sources as one edit covering
+    /// everything between their common prefix and common suffix.
+    /// Useful when a watcher only knows the before/after file contents.
+    pub fn between(old: &str, new: &str) -> Option<TextEdit> {
+        if old == new {
+            return None;
+        }
+
+        let (old_bytes, new_bytes) = (old.as_bytes(), new.as_bytes());
+        let mut prefix = old_bytes.iter().zip(new_bytes).take_while(|(a, b)| a == b).count();
+        while !old.is_char_boundary(prefix) || !new.is_char_boundary(prefix) {
+            prefix -= 1;
+        }
+
+        let max_suffix = old.len().min(new.len()) - prefix;
+        let mut suffix = old_bytes.iter().rev().zip(new_bytes.iter().rev())
+            .take(max_suffix)
+            .take_while(|(a, b)| a == b)
+            .count();
+        while !old.is_char_boundary(old.len() - suffix) || !new.is_char_boundary(new.len() - suffix) {
+            suffix -= 1;
+        }
+
+        Some(TextEdit {
+            range: Span::new(prefix, old.len() - suffix),
+            new_len: new.len() - suffix - prefix,
+        })
+    }
+
+    fn delta(&self) -> isize {
+        self.new_len as isize - self.range.len() as isize
+    }
+}
+
+/// Re-parse `new_source` reusing the top-level items of `old_ast` that no edit touched.
+///
+/// Only the lines between the nearest untouched items around the edits are parsed
+/// again; items outside them are moved over with their spans shifted. Falls back to a
+/// full parse when that stretch doesn't parse on its own (e.g. an edit unbalanced
+/// braces) or the edits don't line up with the old AST. `limits` bound the new source
+/// as they do in [`parse_source_with`]; the items kept were counted when `old_ast` was parsed.
+pub fn reparse(old_ast: Element, new_source: &str, edits: &[TextEdit], limits: &Limits) -> Result<Element, String> {
+    let mut edits = edits.to_vec();
+    edits.sort_by_key(|edit| edit.range.start);
+
+    let old_len = old_ast.span.end;
+    let delta: isize = edits.iter().map(TextEdit::delta).sum();
+    let overlapping = edits.windows(2).any(|pair| pair[0].range.end > pair[1].range.start);
+    if edits.is_empty()
+        || overlapping
+        || edits.iter().any(|edit| edit.range.end > old_len)
+        || old_len as isize + delta != new_source.len() as isize
+    {
+        return if edits.is_empty() && old_len == new_source.len() {
+            Ok(old_ast)
+        } else {
+            parse_source_with(new_source, limits)
+        };
+    }
+
+    let first_edit = edits[0].range.start;
+    let last_edit = edits[edits.len() - 1].range.end;
+    let shifted = |offset: usize| (offset as isize + delta) as usize;
+
+    let Element { name, annotations, children, signature, trivia, body_trivia, .. } = old_ast;
+    let items: Vec<Span> = children.iter().map(Node::span).collect();
+
+    // Items ending on a line before the first edit are untouched, so parsing can
+    // resume right after the last of them. Only items with nothing but a comment after
+    // them on their line count, since the rest of the line may have been read with them.
+    let keep_before = items.iter()
+        .take_while(|span| {
+            let rest = new_source.get(span.end..first_edit).and_then(|between| between.split_once('\n'));
+            rest.is_some_and(|(line, _)| line.trim().is_empty() || line.trim_start().starts_with("//"))
+        })
+        .count();
+    let resume = if keep_before == 0 { 0 } else { items[keep_before - 1].end };
+
+    // Likewise the first item starting a line after the last edit anchors the end
+    let new_edit_end = shifted(last_edit);
+    let keep_after = items.iter().position(|span| {
+        span.start >= last_edit
+            && new_source
+                .get(new_edit_end..shifted(span.start))
+                .and_then(|between| between.rsplit_once('\n'))
+                .is_some_and(|(_, indent)| indent.trim().is_empty())
+    });
+    let stop = keep_after.map_or(new_source.len(), |index| shifted(items[index].start));
+
+    if !new_source.is_char_boundary(resume) || !new_source.is_char_boundary(stop) {
+        return parse_source_with(new_source, limits);
+    }
+
+    check_size(new_source, limits)?;
+    let Ok(fresh) = parse_items(new_source, resume, stop, limits, &mut Elements) else {
+        return parse_source_with(new_source, limits);
+    };
+
+    // Comments and blank lines at the end of the stretch lead the first kept item,
+    // or close the file when nothing after the edits was kept
+    let mut body_trivia = body_trivia;
+    let mut dangling = Some(fresh.dangling);
+    if keep_after.is_none() {
+        body_trivia.leading = dangling.take().unwrap_or_default();
+    }
+
+    let keep_after = keep_after.unwrap_or(children.len());
+    let mut reparsed = Vec::with_capacity(keep_before + fresh.children.len() + children.len() - keep_after);
+    let mut children = children.into_iter();
+    reparsed.extend(children.by_ref().take(keep_before));
+    reparsed.extend(fresh.children);
+    reparsed.extend(children.skip(keep_after - keep_before).map(|node| {
+        let mut node = shift(node, delta);
+        if let Some(leading) = dangling.take() {
+            node.trivia_mut().leading = leading;
+        }
+        node
+    }));
+
+    let mut program = Element {
+        name,
+        annotations,
+        children: reparsed,
+        signature,
+        span: Span::new(0, new_source.len()),
+        id: NodeId::default(),
+        trivia,
+        body_trivia,
+    };
+    // Ids only depend on names, so kept items end up with the ids they had
+    assign_ids(&mut program);
+    Ok(program)
+}
+
+fn shift(node: Node, delta: isize) -> Node {
+    match node {
+        Node::Element(mut element) => {
+            element.span = shift_span(element.span, delta);
+            element.children = element.children.into_iter().map(|child| shift(child, delta)).collect();
+            Node::Element(element)
+        }
+        Node::ChildLine { modifier, id, signature, annotations, span, trivia } => Node::ChildLine {
+            modifier,
+            id,
+            signature,
+            annotations,
+            span: shift_span(span, delta),
+            trivia,
+        },
+        Node::KeyValue { key, value, span, trivia } => Node::KeyValue {
+            key,
+            value,
+            span: shift_span(span, delta),
+            trivia,
+        },
+        Node::Const { name, value, span, trivia } => Node::Const {
+            name,
+            value,
+            span: shift_span(span, delta),
+            trivia,
+        },
+        Node::Expansion { template, args, children, span, trivia } => Node::Expansion {
+            template,
+            args,
+            children: children.into_iter().map(|child| shift(child, delta)).collect(),
+            span: shift_span(span, delta),
+            trivia,
+        },
+    }
+}
+
+fn shift_span(span: Span, delta: isize) -> Span {
+    Span::new((span.start as isize + delta) as usize, (span.end as isize + delta) as usize)
+}
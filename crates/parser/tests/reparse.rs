@@ -0,0 +1,82 @@
+//! Reparsing an edited source gives the tree a full parse of it does, for random edits
+//! of the gallery examples.
+
+use z_ast::Span;
+use z_parser::{parse_source, parse_source_with, reparse, Limits, TextEdit};
+
+const SOURCES: &[&str] = &[
+    include_str!("../../../shared/examples/blog.z"),
+    include_str!("../../../shared/examples/todo.z"),
+    include_str!("../../../shared/examples/api.z"),
+    include_str!("../../../shared/examples/workspace.z"),
+];
+
+/// Text an edit puts in, picked to open and close blocks, strings and comments
+const INSERTS: &[&str] = &[
+    "", "\n", "{", "}", "\"", "`", "// note\n", "  home\n", "@client ", "next Extra {\n", "}\n", "x", "\n\n",
+];
+
+/// A small xorshift generator, so the edits are the same on every run
+struct Rng(u64);
+
+impl Rng {
+    fn below(&mut self, n: usize) -> usize {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        (self.0 % n as u64) as usize
+    }
+}
+
+fn boundary(source: &str, mut offset: usize) -> usize {
+    while !source.is_char_boundary(offset) {
+        offset -= 1;
+    }
+    offset
+}
+
+#[test]
+fn reparse_matches_a_full_parse() {
+    let mut rng = Rng(0x5eed);
+    let limits = Limits::default();
+    for original in SOURCES {
+        let mut source = original.to_string();
+        for _ in 0..500 {
+            let start = boundary(&source, rng.below(source.len() + 1));
+            let end = boundary(&source, (start + rng.below(12)).min(source.len()));
+            let mut edited = source.clone();
+            edited.replace_range(start..end, INSERTS[rng.below(INSERTS.len())]);
+
+            let old = parse_source(&source).expect("the source before the edit parses");
+            let edits: Vec<TextEdit> = TextEdit::between(&source, &edited).into_iter().collect();
+            let reparsed = reparse(old, &edited, &edits, &limits);
+            assert_eq!(reparsed, parse_source(&edited), "after replacing {}..{} of:\n{}", start, end, source);
+
+            // Keep editing from the new source while it parses, and start over when it doesn't
+            source = if reparsed.is_ok() { edited } else { original.to_string() };
+        }
+    }
+}
+
+#[test]
+fn reparse_takes_several_edits() {
+    let source = "next Web {\n  Routes {\n    home\n  }\n}\n\nswift App {\n  App {\n    ContentView\n  }\n}\n";
+    let edited = source.replace("home", "about").replace("ContentView", "MainView");
+    let edits = [
+        TextEdit::new(Span::new(26, 30), "about".len()),
+        TextEdit::new(Span::new(62, 73), "MainView".len()),
+    ];
+    let reparsed = reparse(parse_source(source).unwrap(), &edited, &edits, &Limits::default());
+    assert_eq!(reparsed, parse_source(&edited));
+}
+
+#[test]
+fn reparse_keeps_the_callers_limits() {
+    let source = "next Web {\n  Routes {\n    home\n  }\n}\n";
+    let edited = source.replace("home", "home\n    about");
+    let limits = Limits { max_bytes: source.len(), ..Limits::default() };
+    let edits: Vec<TextEdit> = TextEdit::between(source, &edited).into_iter().collect();
+    let reparsed = reparse(parse_source(source).unwrap(), &edited, &edits, &limits);
+    assert_eq!(reparsed, parse_source_with(&edited, &limits));
+    assert!(reparsed.is_err());
+}
@@ -0,0 +1,583 @@
+//! Typed model of a Z program, layered over the generic AST.
+//!
+//! The parser only knows about blocks, lines and properties. This crate gives them
+//! meaning: a [`Program`] holds [`Target`]s (with workspaces flattened), a target
+//! holds [`Section`]s, and each section holds the typed items its namespace is
+//! made of. Compilers match on these types instead of on element names.
+//!
+//! [`build`] never gives up on the first problem: anything that can't be
+//! understood is left out of the model and reported as a [`SemanticError`].
+
+use serde::Serialize;
+use z_ast::{Annotation, Span};
+
+mod lower;
+
+pub use lower::build;
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct Program {
+    /// Every target in source order, including those inside workspaces
+    pub targets: Vec<Target>,
+    /// The top-level `config` block
+    pub config: Option<Config>,
+}
+
+/// One generated application, e.g. `next WebApp { ... }`
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Target {
+    /// Target type from the registry, e.g. `next`
+    pub kind: String,
+    pub name: String,
+    /// Name of the enclosing `workspace`, if any
+    pub workspace: Option<String>,
+    /// How much scaffolding to emit, from `profile: ...` in the target block
+    pub profile: Profile,
+    pub annotations: Vec<Annotation>,
+    /// The `Components` and `Schema` sections its file or workspace shares, then its own
+    pub sections: Vec<Section>,
+    /// Lines directly inside the target rather than in a section
+    pub declarations: Vec<Item>,
+    /// `key: value` properties directly inside the target
+    pub settings: Vec<Setting>,
+    /// The `Links` block of the enclosing workspace, or of the file for top-level targets
+    pub links: Option<DeepLinks>,
+    /// The `Meta` block of the enclosing workspace, or of the file for top-level targets
+    pub meta: Option<Meta>,
+    /// The program's `config` block
+    pub config: Option<Config>,
+    /// Variables of the environment the app runs in, from the `env` blocks of the file,
+    /// the workspace and the target itself
+    pub env: Vec<EnvVar>,
+    /// Targets it's built from, from `uses: ...` in the target block
+    pub uses: Vec<Dependency>,
+    /// Commands run in its output directory once it's generated, from `after_generate: ...`
+    /// in the target block
+    pub after_generate: Vec<Hook>,
+    pub span: Span,
+}
+
+/// Scaffolding levels, each including everything below it
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Profile {
+    /// The framework and nothing else: no UI kit, CSS framework or extra tooling
+    Minimal,
+    /// The usual stack for the target, e.g. Tailwind and shadcn/ui for Next.js
+    #[default]
+    Standard,
+    /// Everything in `standard` plus linting, formatting and other extras
+    Full,
+}
+
+/// A namespace block inside a target, e.g. `Routes { ... }`
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Section {
+    pub namespace: Namespace,
+    /// Annotations on the section block, which its items inherit
+    pub annotations: Vec<Annotation>,
+    pub body: SectionBody,
+    pub span: Span,
+}
+
+/// Namespaces from the registry; anything else is kept as [`Namespace::Other`]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize)]
+pub enum Namespace {
+    Routes,
+    Api,
+    Components,
+    Schema,
+    Config,
+    App,
+    Frontend,
+    Backend,
+    Activities,
+    Services,
+    Pages,
+    Windows,
+    Type,
+    Fun,
+    Mod,
+    Class,
+    Interface,
+    Package,
+    Function,
+    Module,
+    Script,
+    Store,
+    Deploy,
+    Other(String),
+}
+
+/// What a section contains, decided by its namespace
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub enum SectionBody {
+    /// `Routes`
+    Routes(Vec<Route>),
+    /// `API`
+    Endpoints(Vec<Endpoint>),
+    /// `Components` and `Frontend`
+    Components(Vec<Component>),
+    /// `Schema` and `type`
+    Models(Vec<Model>),
+    /// `Config`
+    Settings(Vec<Setting>),
+    /// `Store`
+    Store(StoreListing),
+    /// `Deploy`
+    Deploy(Deployment),
+    /// Every other namespace: a flat list of named items
+    Items(Vec<Item>),
+}
+
+/// A page route; nested routes extend the parent's path
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Route {
+    /// Path segment without brackets, e.g. `id` for `[id]`
+    pub segment: String,
+    /// Written as `[segment]`: matches any value
+    pub dynamic: bool,
+    pub annotations: Vec<Annotation>,
+    /// Annotations of the enclosing section and routes that this one doesn't set itself
+    pub inherited: Vec<Annotation>,
+    pub children: Vec<Route>,
+    pub span: Span,
+}
+
+/// An API route, optionally prefixed with its HTTP method (`get users`)
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Endpoint {
+    pub name: String,
+    pub method: Option<String>,
+    pub signature: Option<String>,
+    pub annotations: Vec<Annotation>,
+    /// Annotations of the enclosing section and routes that this one doesn't set itself
+    pub inherited: Vec<Annotation>,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Component {
+    pub name: String,
+    /// Raw parameter list, e.g. `(title: string)`
+    pub props: Option<String>,
+    pub annotations: Vec<Annotation>,
+    /// Annotations of the enclosing section and routes that this one doesn't set itself
+    pub inherited: Vec<Annotation>,
+    pub span: Span,
+}
+
+/// A `table`, `model`, `enum`, ... in a schema, or a bare name to be filled in later
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Model {
+    /// The keyword in front of the name, e.g. `table`
+    pub kind: Option<String>,
+    pub name: String,
+    pub fields: Vec<Field>,
+    /// Plain lines in the body, e.g. the values of an `enum`
+    pub values: Vec<String>,
+    pub annotations: Vec<Annotation>,
+    /// Annotations of the enclosing section and routes that this one doesn't set itself
+    pub inherited: Vec<Annotation>,
+    pub span: Span,
+}
+
+/// `name: type @annotation ...`
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Field {
+    pub name: String,
+    pub ty: String,
+    /// Annotations after the type, e.g. `primary` and `unique`, with their arguments
+    pub annotations: Vec<Annotation>,
+    pub span: Span,
+}
+
+/// `key: value`, with the value as written
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Setting {
+    pub key: String,
+    pub value: String,
+    pub span: Span,
+}
+
+/// A target another is built from, e.g. the `Web` of `uses: Web`
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Dependency {
+    /// Its name, or `kind:name` when two targets share the name
+    pub target: String,
+    pub span: Span,
+}
+
+/// A command run in a target's output directory once it's generated, e.g. `pnpm install`
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Hook {
+    pub command: String,
+    pub span: Span,
+}
+
+/// A variable of the environment an app runs in, e.g. `DATABASE_URL: secret`
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct EnvVar {
+    pub name: String,
+    pub kind: EnvKind,
+    /// Written with a trailing `?`, e.g. `SENTRY_DSN: string?`
+    pub optional: bool,
+    pub span: Span,
+}
+
+/// What an environment variable holds
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EnvKind {
+    String,
+    Url,
+    Number,
+    Boolean,
+    /// A string that must never be committed or shipped to clients
+    Secret,
+}
+
+/// App store listing, the source of truth for release metadata
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct StoreListing {
+    pub name: String,
+    pub description: String,
+    /// Store categories, most relevant first, e.g. `productivity`
+    pub categories: Vec<String>,
+    /// Image paths, relative to the source file
+    pub screenshots: Vec<String>,
+    pub span: Span,
+}
+
+/// Where and how a target is deployed
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Deployment {
+    pub provider: Provider,
+    /// The provider's id for the app: Vercel project, Netlify site, Fly app,
+    /// Railway service or GitHub repository (`owner/name`)
+    pub project: String,
+    /// Vercel team (org) the project belongs to
+    pub team: Option<String>,
+    pub span: Span,
+}
+
+/// Hosting and release services `z deploy` can drive
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Provider {
+    Vercel,
+    Netlify,
+    Fly,
+    Railway,
+    /// Bundles attached to a GitHub release
+    GitHub,
+}
+
+/// URL scheme and associated domains shared by the apps of a workspace
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DeepLinks {
+    /// Custom scheme, e.g. `notes` for `notes://...`
+    pub scheme: Option<String>,
+    /// Hosts whose `https` links open the apps, e.g. `notes.example.com`
+    pub domains: Vec<String>,
+    /// Paths the apps handle, e.g. `/notes/*`; every path when empty
+    pub paths: Vec<String>,
+    /// `TEAMID.bundle.id` of the Apple app, for `apple-app-site-association`
+    pub apple_app_id: Option<String>,
+    /// Package name of the Android app, for `assetlinks.json`
+    pub android_package: Option<String>,
+    /// SHA-256 fingerprints of the Android signing certificates
+    pub android_fingerprints: Vec<String>,
+    pub span: Span,
+}
+
+/// Name, version and the rest of the metadata the apps of a workspace are published with
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Meta {
+    /// Human-readable name, e.g. `Notes`
+    pub name: Option<String>,
+    /// Semantic version, e.g. `1.2.0`
+    pub version: Option<String>,
+    pub description: Option<String>,
+    /// Image path, relative to the source file
+    pub icon: Option<String>,
+    pub span: Span,
+}
+
+/// Settings of the whole program, from the top-level `config` block, which every
+/// target's generated code can use
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Config {
+    /// Product name, e.g. `Acme`; apps without a `Meta` name are shown with it
+    pub name: Option<String>,
+    /// Domain the product is served from, e.g. `acme.dev`
+    pub domain: Option<String>,
+    /// Language of the content, e.g. `en` or `pt-BR`
+    pub default_locale: Option<String>,
+    /// Every `key: value` of the block, unquoted, those above included
+    pub values: Vec<Setting>,
+    pub span: Span,
+}
+
+impl Config {
+    /// The value of `key`, if the block sets it
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.values.iter().find(|setting| setting.key == key).map(|setting| setting.value.as_str())
+    }
+}
+
+/// A named entry with an optional keyword in front, e.g. `ContentView` or `fun main()`
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Item {
+    pub kind: Option<String>,
+    pub name: String,
+    pub signature: Option<String>,
+    pub annotations: Vec<Annotation>,
+    /// Annotations of the enclosing section and routes that this one doesn't set itself
+    pub inherited: Vec<Annotation>,
+    pub span: Span,
+}
+
+/// Items whose annotations cascade from the blocks around them.
+///
+/// An annotation on a section, or on a route with nested routes, applies to
+/// everything inside unless an item sets the same annotation itself. `@doc` only
+/// describes the block it's written on and isn't inherited.
+pub trait Annotated {
+    /// Annotations written on the item
+    fn own_annotations(&self) -> &[Annotation];
+
+    /// Annotations from enclosing blocks, nearest first
+    fn inherited_annotations(&self) -> &[Annotation];
+
+    /// The annotation `name` that applies to the item, its own or inherited
+    fn annotation(&self, name: &str) -> Option<&Annotation> {
+        self.annotations().find(|annotation| annotation.name == name)
+    }
+
+    /// Every annotation that applies to the item: its own, then inherited ones
+    fn annotations(&self) -> impl Iterator<Item = &Annotation> {
+        self.own_annotations().iter().chain(self.inherited_annotations())
+    }
+}
+
+macro_rules! annotated {
+    ($($ty:ty),*) => {$(
+        impl Annotated for $ty {
+            fn own_annotations(&self) -> &[Annotation] {
+                &self.annotations
+            }
+
+            fn inherited_annotations(&self) -> &[Annotation] {
+                &self.inherited
+            }
+        }
+    )*};
+}
+
+annotated!(Route, Endpoint, Component, Model, Item);
+
+/// Something in the AST that doesn't make sense as a program
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SemanticError {
+    pub message: String,
+    pub span: Span,
+}
+
+impl std::str::FromStr for Profile {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "minimal" => Ok(Profile::Minimal),
+            "standard" => Ok(Profile::Standard),
+            "full" => Ok(Profile::Full),
+            _ => Err(format!("invalid profile '{}' (expected 'minimal', 'standard' or 'full')", s)),
+        }
+    }
+}
+
+impl std::str::FromStr for EnvKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "string" => Ok(EnvKind::String),
+            "url" => Ok(EnvKind::Url),
+            "number" => Ok(EnvKind::Number),
+            "boolean" => Ok(EnvKind::Boolean),
+            "secret" => Ok(EnvKind::Secret),
+            _ => Err(format!("invalid environment variable type '{}' (expected string, url, number, boolean or secret)", s)),
+        }
+    }
+}
+
+impl std::str::FromStr for Provider {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "vercel" => Ok(Provider::Vercel),
+            "netlify" => Ok(Provider::Netlify),
+            "fly" => Ok(Provider::Fly),
+            "railway" => Ok(Provider::Railway),
+            "github" => Ok(Provider::GitHub),
+            _ => Err(format!(
+                "invalid provider '{}' (expected 'vercel', 'netlify', 'fly', 'railway' or 'github')",
+                s
+            )),
+        }
+    }
+}
+
+impl Provider {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Provider::Vercel => "vercel",
+            Provider::Netlify => "netlify",
+            Provider::Fly => "fly",
+            Provider::Railway => "railway",
+            Provider::GitHub => "github",
+        }
+    }
+}
+
+impl Namespace {
+    pub fn from_name(name: &str) -> Self {
+        match name {
+            "Routes" => Namespace::Routes,
+            "API" => Namespace::Api,
+            "Components" => Namespace::Components,
+            "Schema" => Namespace::Schema,
+            "Config" => Namespace::Config,
+            "App" => Namespace::App,
+            "Frontend" => Namespace::Frontend,
+            "Backend" => Namespace::Backend,
+            "Activities" => Namespace::Activities,
+            "Services" => Namespace::Services,
+            "Pages" => Namespace::Pages,
+            "Windows" => Namespace::Windows,
+            "type" => Namespace::Type,
+            "fun" => Namespace::Fun,
+            "mod" => Namespace::Mod,
+            "class" => Namespace::Class,
+            "interface" => Namespace::Interface,
+            "package" => Namespace::Package,
+            "function" => Namespace::Function,
+            "module" => Namespace::Module,
+            "script" => Namespace::Script,
+            "Store" => Namespace::Store,
+            "Deploy" => Namespace::Deploy,
+            other => Namespace::Other(other.to_string()),
+        }
+    }
+
+    /// The name as written in source and in the registry
+    pub fn as_str(&self) -> &str {
+        match self {
+            Namespace::Routes => "Routes",
+            Namespace::Api => "API",
+            Namespace::Components => "Components",
+            Namespace::Schema => "Schema",
+            Namespace::Config => "Config",
+            Namespace::App => "App",
+            Namespace::Frontend => "Frontend",
+            Namespace::Backend => "Backend",
+            Namespace::Activities => "Activities",
+            Namespace::Services => "Services",
+            Namespace::Pages => "Pages",
+            Namespace::Windows => "Windows",
+            Namespace::Type => "type",
+            Namespace::Fun => "fun",
+            Namespace::Mod => "mod",
+            Namespace::Class => "class",
+            Namespace::Interface => "interface",
+            Namespace::Package => "package",
+            Namespace::Function => "function",
+            Namespace::Module => "module",
+            Namespace::Script => "script",
+            Namespace::Store => "Store",
+            Namespace::Deploy => "Deploy",
+            Namespace::Other(name) => name,
+        }
+    }
+}
+
+impl std::fmt::Display for Namespace {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl Target {
+    /// `kind:name`, the form used in diagnostics and reports
+    pub fn qualified_name(&self) -> String {
+        format!("{}:{}", self.kind, self.name)
+    }
+
+    /// The name the app is shown with: its `Meta` name, the program's `config` name,
+    /// or else the target's name
+    pub fn display_name(&self) -> &str {
+        self.meta.as_ref().and_then(|meta| meta.name.as_deref())
+            .or_else(|| self.config.as_ref().and_then(|config| config.name.as_deref()))
+            .unwrap_or(&self.name)
+    }
+
+    /// Language of the generated content: the program's `default_locale`, or else `en`
+    pub fn locale(&self) -> &str {
+        self.config.as_ref().and_then(|config| config.default_locale.as_deref()).unwrap_or("en")
+    }
+
+    /// The first section of the given namespace
+    pub fn section(&self, namespace: &Namespace) -> Option<&Section> {
+        self.sections.iter().find(|section| &section.namespace == namespace)
+    }
+
+    pub fn routes(&self) -> impl Iterator<Item = &Route> {
+        self.sections.iter().flat_map(|section| match &section.body {
+            SectionBody::Routes(routes) => routes.as_slice(),
+            _ => &[],
+        })
+    }
+
+    pub fn endpoints(&self) -> impl Iterator<Item = &Endpoint> {
+        self.sections.iter().flat_map(|section| match &section.body {
+            SectionBody::Endpoints(endpoints) => endpoints.as_slice(),
+            _ => &[],
+        })
+    }
+
+    pub fn models(&self) -> impl Iterator<Item = &Model> {
+        self.sections.iter().flat_map(|section| match &section.body {
+            SectionBody::Models(models) => models.as_slice(),
+            _ => &[],
+        })
+    }
+
+    /// The target's `Deploy` section, if it has one
+    pub fn deployment(&self) -> Option<&Deployment> {
+        self.sections.iter().find_map(|section| match &section.body {
+            SectionBody::Deploy(deployment) => Some(deployment),
+            _ => None,
+        })
+    }
+
+    /// The target's `Store` listing, if it has one
+    pub fn store(&self) -> Option<&StoreListing> {
+        self.sections.iter().find_map(|section| match &section.body {
+            SectionBody::Store(listing) => Some(listing),
+            _ => None,
+        })
+    }
+}
+
+impl Route {
+    /// URL path of this route below its parent, e.g. `/customers/[id]` for a nested route
+    pub fn path(&self, parent: &str) -> String {
+        if self.dynamic {
+            format!("{}/[{}]", parent, self.segment)
+        } else {
+            format!("{}/{}", parent, self.segment)
+        }
+    }
+}
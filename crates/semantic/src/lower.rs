@@ -0,0 +1,791 @@
+//! Lowering of the generic AST into the typed model.
+
+use z_ast::{Annotation, Element, Node, Span};
+
+use crate::{
+    Component, Config, DeepLinks, Dependency, Deployment, Endpoint, EnvVar, Field, Hook, Item, Meta, Model, Namespace, Profile, Program, Route, Section,
+    SectionBody, SemanticError, Setting, StoreListing, Target,
+};
+
+/// Build the typed model of a parsed (and constant-resolved) program.
+///
+/// Parts that fail validation are left out of the model; every problem found is
+/// returned alongside it, in source order.
+pub fn build(program: &Element) -> (Program, Vec<SemanticError>) {
+    let mut lowering = Lowering { errors: Vec::new() };
+    let mut targets = Vec::new();
+    let config = lowering.shared_block(program, "config").and_then(|element| lowering.config(element));
+    let shared = Shared { config: config.as_ref(), ..Shared::default() };
+    lowering.targets(program, None, shared, &mut targets);
+    (Program { targets, config }, lowering.errors)
+}
+
+struct Lowering {
+    errors: Vec<SemanticError>,
+}
+
+/// Blocks a file or workspace shares with the targets inside it
+#[derive(Clone, Copy, Default)]
+struct Shared<'a> {
+    links: Option<&'a DeepLinks>,
+    meta: Option<&'a Meta>,
+    config: Option<&'a Config>,
+    /// `Components` and `Schema` sections, visible to every target as its own
+    sections: &'a [Section],
+    /// Variables of `env` blocks, which every target has on top of its own
+    env: &'a [EnvVar],
+}
+
+/// Sections declared outside any target, which every target below them shares
+const SHARED_SECTIONS: [&str; 2] = ["Components", "Schema"];
+
+const CONFIG_AT_TOP_LEVEL: &str = "config applies to the whole program; declare it at the top level";
+
+/// Split a `modifier:id` element name
+fn split_name(name: &str) -> (Option<&str>, &str) {
+    match name.split_once(':') {
+        Some((modifier, id)) => (Some(modifier), id),
+        None => (None, name),
+    }
+}
+
+impl Lowering {
+    fn error(&mut self, span: Span, message: String) {
+        self.errors.push(SemanticError { message, span });
+    }
+
+    /// Targets declared in `parent`, which share its `Links` and `Meta` blocks or else
+    /// the `inherited` ones, and its `Components` and `Schema` sections on top of the
+    /// `inherited` ones
+    fn targets(&mut self, parent: &Element, workspace: Option<&str>, inherited: Shared, targets: &mut Vec<Target>) {
+        let links = self.shared_block(parent, "Links").map(|element| self.links(element));
+        let links = links.flatten().or_else(|| inherited.links.cloned());
+        let meta = self.shared_block(parent, "Meta").map(|element| self.meta(element));
+        let meta = meta.flatten().or_else(|| inherited.meta.cloned());
+        let mut sections = inherited.sections.to_vec();
+        for node in parent.items() {
+            if let Node::Element(element) = node {
+                if SHARED_SECTIONS.contains(&element.name.as_str()) {
+                    sections.extend(self.section(element));
+                }
+            }
+        }
+        let mut env = inherited.env.to_vec();
+        for node in parent.items() {
+            if let Node::Element(element) = node {
+                if element.name == "env" {
+                    env.extend(self.env(element));
+                }
+            }
+        }
+        let shared = Shared { links: links.as_ref(), meta: meta.as_ref(), sections: &sections, env: &env, ..inherited };
+
+        for node in parent.items() {
+            let Node::Element(element) = node else {
+                continue;
+            };
+            match split_name(&element.name) {
+                (None, "Links" | "Meta" | "Components" | "Schema" | "env") => {}
+                (None, "config") if workspace.is_none() => {}
+                (None, "config") => self.error(element.span, CONFIG_AT_TOP_LEVEL.to_string()),
+                // Definitions only matter where they're used, which expanding templates took care of
+                (Some("template"), _) => {}
+                // What's left of environments after compiling for one: those inside a workspace
+                (Some("env"), _) => self.error(
+                    element.span,
+                    "environments apply to the whole program; declare them at the top level".to_string(),
+                ),
+                // Workspaces group related apps; their blocks are targets in their own right
+                (Some("workspace"), name) => self.targets(element, Some(name), shared, targets),
+                // Target names become type, module and directory names, where `-` can't go
+                (Some(_), name) if !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') => self.error(
+                    element.span,
+                    format!("invalid target name '{}': use letters, digits and underscores", name),
+                ),
+                (Some(kind), name) => {
+                    let mut target = self.target(element, kind, name, workspace);
+                    target.links = links.clone();
+                    target.meta = meta.clone();
+                    target.config = inherited.config.cloned();
+                    target.env.splice(0..0, env.iter().cloned());
+                    target.sections.splice(0..0, sections.iter().cloned());
+                    targets.push(target);
+                }
+                (None, _) => self.error(
+                    element.span,
+                    format!("invalid target format: {} (expected target:name)", element.name),
+                ),
+            }
+        }
+    }
+
+    fn target(&mut self, element: &Element, kind: &str, name: &str, workspace: Option<&str>) -> Target {
+        let mut target = Target {
+            kind: kind.to_string(),
+            name: name.to_string(),
+            workspace: workspace.map(str::to_string),
+            profile: Profile::default(),
+            annotations: element.annotations.clone(),
+            sections: Vec::new(),
+            declarations: Vec::new(),
+            settings: Vec::new(),
+            links: None,
+            meta: None,
+            config: None,
+            env: Vec::new(),
+            uses: Vec::new(),
+            after_generate: Vec::new(),
+            span: element.span,
+        };
+
+        for node in element.items() {
+            match node {
+                // `fun main() { ... }` declares something; a bare name opens a namespace
+                Node::Element(child) if child.name.contains(':') => {
+                    let (kind, name) = split_name(&child.name);
+                    target.declarations.push(Item {
+                        kind: kind.map(str::to_string),
+                        name: name.to_string(),
+                        signature: child.signature.clone(),
+                        annotations: child.annotations.clone(),
+                        inherited: Vec::new(),
+                        span: child.span,
+                    });
+                }
+                Node::Element(child) if child.name == "Links" || child.name == "Meta" => self.error(
+                    child.span,
+                    format!("{} is shared by a workspace; declare it in the workspace or at the top level", child.name),
+                ),
+                Node::Element(child) if child.name == "config" => self.error(child.span, CONFIG_AT_TOP_LEVEL.to_string()),
+                Node::Element(child) if child.name == "env" => {
+                    let env = self.env(child);
+                    target.env.extend(env);
+                }
+                Node::Element(child) => target.sections.extend(self.section(child)),
+                Node::ChildLine { modifier, id, signature, annotations, span, .. } => {
+                    target.declarations.push(Item {
+                        kind: modifier.clone(),
+                        name: id.clone(),
+                        signature: signature.clone(),
+                        annotations: annotations.clone(),
+                        inherited: Vec::new(),
+                        span: *span,
+                    });
+                }
+                Node::KeyValue { key, value, span, .. } if key == "profile" => {
+                    match value.trim().trim_matches('"').parse() {
+                        Ok(profile) => target.profile = profile,
+                        Err(e) => self.error(*span, e),
+                    }
+                }
+                Node::KeyValue { key, value, span, .. } if key == "uses" => {
+                    let names = value.trim().trim_start_matches('[').trim_end_matches(']');
+                    let uses: Vec<Dependency> = names.split(',')
+                        .map(|name| Dependency { target: name.trim().to_string(), span: *span })
+                        .collect();
+                    if uses.iter().any(|dependency| dependency.target.is_empty()) {
+                        self.error(*span, "'uses' takes the names of targets separated by commas, e.g. `uses: Web, Api`".to_string());
+                    } else {
+                        target.uses.extend(uses);
+                    }
+                }
+                Node::KeyValue { key, value, span, .. } if key == "after_generate" => {
+                    let commands = list(value);
+                    if commands.is_empty() {
+                        self.error(*span, "'after_generate' takes the commands to run, e.g. `after_generate: [\"pnpm install\", \"pnpm format\"]`".to_string());
+                    }
+                    target.after_generate.extend(commands.into_iter().map(|command| Hook { command, span: *span }));
+                }
+                Node::KeyValue { key, value, span, .. } => {
+                    if let Some(setting) = self.setting(key, value, *span) {
+                        target.settings.push(setting);
+                    }
+                }
+                Node::Const { .. } | Node::Expansion { .. } => {}
+            }
+        }
+        target
+    }
+
+    fn section(&mut self, element: &Element) -> Option<Section> {
+        let namespace = Namespace::from_name(&element.name);
+        let scope = scope(&element.annotations, &[]);
+        let body = match namespace {
+            Namespace::Routes => SectionBody::Routes(self.routes(element, &scope)),
+            Namespace::Api => {
+                let mut endpoints = Vec::new();
+                self.endpoints(element, "", &scope, &mut endpoints);
+                SectionBody::Endpoints(endpoints)
+            }
+            Namespace::Components | Namespace::Frontend => SectionBody::Components(self.components(element, &scope)),
+            Namespace::Schema | Namespace::Type => SectionBody::Models(self.models(element, &scope)),
+            Namespace::Config => SectionBody::Settings(self.settings(element)),
+            Namespace::Store => SectionBody::Store(self.store(element)?),
+            Namespace::Deploy => SectionBody::Deploy(self.deployment(element)?),
+            _ => SectionBody::Items(self.items(element, &scope)),
+        };
+        Some(Section { namespace, annotations: element.annotations.clone(), body, span: element.span })
+    }
+
+    fn routes(&mut self, parent: &Element, scope: &[Annotation]) -> Vec<Route> {
+        let mut routes = Vec::new();
+        for node in parent.items() {
+            let (modifier, id, annotations, children) = match node {
+                Node::ChildLine { modifier, id, annotations, .. } => (modifier.as_deref(), id.as_str(), annotations, None),
+                Node::Element(element) => {
+                    let (modifier, id) = split_name(&element.name);
+                    (modifier, id, &element.annotations, Some(element))
+                }
+                Node::KeyValue { key, span, .. } => {
+                    self.error(*span, format!("unexpected property '{}' in {}", key, parent.name));
+                    continue;
+                }
+                Node::Const { .. } | Node::Expansion { .. } => continue,
+            };
+            if let Some(modifier) = modifier.filter(|modifier| *modifier != "route") {
+                self.error(node.span(), format!("unexpected '{}' before route '{}'", modifier, id));
+                continue;
+            }
+
+            let (segment, dynamic) = match id.strip_prefix('[').and_then(|id| id.strip_suffix(']')) {
+                Some(segment) => (segment, true),
+                None => (id, false),
+            };
+            if segment.is_empty() {
+                self.error(node.span(), "dynamic route segment needs a name, e.g. [id]".to_string());
+                continue;
+            }
+
+            let inherited = inherit(scope, annotations);
+            let children = children.map(|element| self.routes(element, &self::scope(annotations, &inherited)));
+            routes.push(Route {
+                segment: segment.to_string(),
+                dynamic,
+                annotations: annotations.clone(),
+                inherited,
+                children: children.unwrap_or_default(),
+                span: node.span(),
+            });
+        }
+        routes
+    }
+
+    /// API routes, with nested blocks flattened into `parent/child` names
+    fn endpoints(&mut self, parent: &Element, prefix: &str, scope: &[Annotation], endpoints: &mut Vec<Endpoint>) {
+        for node in parent.items() {
+            match node {
+                Node::ChildLine { modifier, id, signature, annotations, span, .. } => endpoints.push(Endpoint {
+                    name: format!("{}{}", prefix, id),
+                    method: modifier.clone(),
+                    signature: signature.clone(),
+                    annotations: annotations.clone(),
+                    inherited: inherit(scope, annotations),
+                    span: *span,
+                }),
+                Node::Element(element) => {
+                    let (method, id) = split_name(&element.name);
+                    let name = format!("{}{}", prefix, id);
+                    let inherited = inherit(scope, &element.annotations);
+                    let nested = self::scope(&element.annotations, &inherited);
+                    endpoints.push(Endpoint {
+                        name: name.clone(),
+                        method: method.map(str::to_string),
+                        signature: element.signature.clone(),
+                        annotations: element.annotations.clone(),
+                        inherited,
+                        span: element.span,
+                    });
+                    self.endpoints(element, &format!("{}/", name), &nested, endpoints);
+                }
+                Node::KeyValue { key, span, .. } => {
+                    self.error(*span, format!("unexpected property '{}' in {}", key, parent.name));
+                }
+                Node::Const { .. } | Node::Expansion { .. } => {}
+            }
+        }
+    }
+
+    fn components(&mut self, parent: &Element, scope: &[Annotation]) -> Vec<Component> {
+        let mut components = Vec::new();
+        for node in parent.items() {
+            let (modifier, id, props, annotations) = match node {
+                Node::ChildLine { modifier, id, signature, annotations, .. } => {
+                    (modifier.as_deref(), id.as_str(), signature, annotations)
+                }
+                Node::Element(element) => {
+                    let (modifier, id) = split_name(&element.name);
+                    (modifier, id, &element.signature, &element.annotations)
+                }
+                Node::KeyValue { key, span, .. } => {
+                    self.error(*span, format!("unexpected property '{}' in {}", key, parent.name));
+                    continue;
+                }
+                Node::Const { .. } | Node::Expansion { .. } => continue,
+            };
+            if let Some(modifier) = modifier.filter(|modifier| *modifier != "component") {
+                self.error(node.span(), format!("unexpected '{}' before component '{}'", modifier, id));
+                continue;
+            }
+            components.push(Component {
+                name: id.to_string(),
+                props: props.clone(),
+                annotations: annotations.clone(),
+                inherited: inherit(scope, annotations),
+                span: node.span(),
+            });
+        }
+        components
+    }
+
+    fn models(&mut self, parent: &Element, scope: &[Annotation]) -> Vec<Model> {
+        let mut models = Vec::new();
+        for node in parent.items() {
+            match node {
+                // A bare name declares the model without saying what's in it yet
+                Node::ChildLine { modifier, id, annotations, span, .. } => models.push(Model {
+                    kind: modifier.clone(),
+                    name: id.clone(),
+                    fields: Vec::new(),
+                    values: Vec::new(),
+                    annotations: annotations.clone(),
+                    inherited: inherit(scope, annotations),
+                    span: *span,
+                }),
+                Node::Element(element) => {
+                    if let Some(model) = self.model(element, scope) {
+                        models.push(model);
+                    }
+                }
+                Node::KeyValue { key, span, .. } => {
+                    self.error(*span, format!("unexpected property '{}' in {}", key, parent.name));
+                }
+                Node::Const { .. } | Node::Expansion { .. } => {}
+            }
+        }
+        models
+    }
+
+    fn model(&mut self, element: &Element, scope: &[Annotation]) -> Option<Model> {
+        let (kind, name) = split_name(&element.name);
+        let mut model = Model {
+            kind: kind.map(str::to_string),
+            name: name.to_string(),
+            fields: Vec::new(),
+            values: Vec::new(),
+            annotations: element.annotations.clone(),
+            inherited: inherit(scope, &element.annotations),
+            span: element.span,
+        };
+
+        for node in element.items() {
+            match node {
+                Node::KeyValue { key, value, span, .. } => {
+                    let (ty, annotations) = split_field_annotations(value);
+                    if ty.is_empty() {
+                        self.error(*span, format!("field '{}' of '{}' needs a type", key, name));
+                        continue;
+                    }
+                    model.fields.push(Field { name: key.clone(), ty, annotations, span: *span });
+                }
+                Node::ChildLine { id, .. } => model.values.push(id.clone()),
+                Node::Element(nested) => {
+                    self.error(nested.span, format!("unexpected block '{}' in '{}'", nested.name, name));
+                }
+                Node::Const { .. } | Node::Expansion { .. } => {}
+            }
+        }
+
+        if !element.items().any(|node| !matches!(node, Node::Const { .. })) {
+            self.error(element.span, format!("'{}' declares no fields", name));
+            return None;
+        }
+        Some(model)
+    }
+
+    fn settings(&mut self, parent: &Element) -> Vec<Setting> {
+        let mut settings = Vec::new();
+        for node in parent.items() {
+            match node {
+                Node::KeyValue { key, value, span, .. } => settings.extend(self.setting(key, value, *span)),
+                // A bare name switches a flag on
+                Node::ChildLine { modifier: None, id, signature: None, span, .. } => {
+                    settings.push(Setting { key: id.clone(), value: "true".to_string(), span: *span });
+                }
+                other => self.error(other.span(), format!("expected `key: value` in {}", parent.name)),
+            }
+        }
+        settings
+    }
+
+    fn store(&mut self, element: &Element) -> Option<StoreListing> {
+        let mut listing = StoreListing {
+            name: String::new(),
+            description: String::new(),
+            categories: Vec::new(),
+            screenshots: Vec::new(),
+            span: element.span,
+        };
+
+        for node in element.items() {
+            match node {
+                Node::KeyValue { key, value, .. } => match key.as_str() {
+                    "name" => listing.name = unquote(value).to_string(),
+                    "description" => listing.description = unquote(value).to_string(),
+                    "categories" => listing.categories = list(value),
+                    "screenshots" => listing.screenshots = list(value),
+                    // Unknown fields are reported by core, against the registry
+                    _ => {}
+                },
+                Node::Const { .. } | Node::Expansion { .. } => {}
+                other => self.error(other.span(), "expected `key: value` in Store".to_string()),
+            }
+        }
+
+        let missing: Vec<&str> = [("name", &listing.name), ("description", &listing.description)].into_iter()
+            .filter(|(_, value)| value.is_empty())
+            .map(|(field, _)| field)
+            .collect();
+        for field in &missing {
+            self.error(element.span, format!("Store needs a '{}'", field));
+        }
+        missing.is_empty().then_some(listing)
+    }
+
+    fn deployment(&mut self, element: &Element) -> Option<Deployment> {
+        let (mut provider, mut project, mut team) = (None, None, None);
+        let mut valid = true;
+        for node in element.items() {
+            match node {
+                Node::KeyValue { key, value, span, .. } => match key.as_str() {
+                    "provider" => match unquote(value).parse() {
+                        Ok(parsed) => provider = Some(parsed),
+                        Err(e) => {
+                            self.error(*span, e);
+                            valid = false;
+                        }
+                    },
+                    "project" => project = Some(unquote(value).to_string()),
+                    "team" => team = Some(unquote(value).to_string()),
+                    _ => {}
+                },
+                Node::Const { .. } | Node::Expansion { .. } => {}
+                other => self.error(other.span(), "expected `key: value` in Deploy".to_string()),
+            }
+        }
+
+        let project = project.filter(|project| !project.is_empty());
+        for (field, missing) in [("provider", provider.is_none() && valid), ("project", project.is_none())] {
+            if missing {
+                self.error(element.span, format!("Deploy needs a '{}'", field));
+            }
+        }
+        Some(Deployment { provider: provider?, project: project?, team, span: element.span })
+    }
+
+    /// The `name` block directly inside `parent`, reporting any after the first
+    fn shared_block<'a>(&mut self, parent: &'a Element, name: &str) -> Option<&'a Element> {
+        let mut blocks = parent.items().filter_map(|node| match node {
+            Node::Element(element) if element.name == name => Some(element),
+            _ => None,
+        });
+        let first = blocks.next();
+        for element in blocks {
+            self.error(element.span, format!("only one {} block is allowed here", name));
+        }
+        first
+    }
+
+    fn meta(&mut self, element: &Element) -> Option<Meta> {
+        let mut meta = Meta { name: None, version: None, description: None, icon: None, span: element.span };
+
+        for node in element.items() {
+            match node {
+                Node::KeyValue { key, value, .. } => {
+                    let slot = match key.as_str() {
+                        "name" => &mut meta.name,
+                        "version" => &mut meta.version,
+                        "description" => &mut meta.description,
+                        "icon" => &mut meta.icon,
+                        _ => continue,
+                    };
+                    *slot = Some(unquote(value).to_string()).filter(|value| !value.is_empty());
+                }
+                Node::Const { .. } | Node::Expansion { .. } => {}
+                other => self.error(other.span(), "expected `key: value` in Meta".to_string()),
+            }
+        }
+
+        if let Some(version) = meta.version.as_deref().filter(|version| !is_version(version)) {
+            self.error(element.span, format!("Meta version '{}' isn't a version like 1.2.0", version));
+            return None;
+        }
+        Some(meta)
+    }
+
+    /// The variables of an `env` block, `NAME: type` with a trailing `?` when optional
+    fn env(&mut self, element: &Element) -> Vec<EnvVar> {
+        let mut env = Vec::new();
+        for node in element.items() {
+            match node {
+                Node::KeyValue { key, value, span, .. } => {
+                    if !is_env_name(key) {
+                        self.error(*span, format!("environment variable '{}' isn't named like DATABASE_URL", key));
+                        continue;
+                    }
+                    let value = value.trim();
+                    let (kind, optional) = match value.strip_suffix('?') {
+                        Some(kind) => (kind.trim(), true),
+                        None => (value, false),
+                    };
+                    match kind.parse() {
+                        Ok(kind) => env.push(EnvVar { name: key.clone(), kind, optional, span: *span }),
+                        Err(e) => self.error(*span, e),
+                    }
+                }
+                Node::Const { .. } | Node::Expansion { .. } => {}
+                other => self.error(other.span(), "expected `NAME: type` in env".to_string()),
+            }
+        }
+        env
+    }
+
+    fn config(&mut self, element: &Element) -> Option<Config> {
+        let mut config = Config { name: None, domain: None, default_locale: None, values: Vec::new(), span: element.span };
+
+        for node in element.items() {
+            match node {
+                Node::KeyValue { key, value, span, .. } => {
+                    let value = unquote(value).to_string();
+                    let slot = match key.as_str() {
+                        "name" => Some(&mut config.name),
+                        "domain" => Some(&mut config.domain),
+                        "default_locale" => Some(&mut config.default_locale),
+                        _ => None,
+                    };
+                    if let Some(slot) = slot {
+                        *slot = Some(value.clone()).filter(|value| !value.is_empty());
+                    }
+                    config.values.push(Setting { key: key.clone(), value, span: *span });
+                }
+                Node::Const { .. } | Node::Expansion { .. } => {}
+                other => self.error(other.span(), "expected `key: value` in config".to_string()),
+            }
+        }
+
+        if let Some(domain) = config.domain.as_deref().filter(|domain| !is_domain(domain)) {
+            self.error(element.span, format!("config domain '{}' isn't a domain like acme.dev", domain));
+            return None;
+        }
+        if let Some(locale) = config.default_locale.as_deref().filter(|locale| !is_locale(locale)) {
+            self.error(element.span, format!("config default_locale '{}' isn't a language tag like en or pt-BR", locale));
+            return None;
+        }
+        Some(config)
+    }
+
+    fn links(&mut self, element: &Element) -> Option<DeepLinks> {
+        let mut links = DeepLinks {
+            scheme: None,
+            domains: Vec::new(),
+            paths: Vec::new(),
+            apple_app_id: None,
+            android_package: None,
+            android_fingerprints: Vec::new(),
+            span: element.span,
+        };
+
+        for node in element.items() {
+            match node {
+                Node::KeyValue { key, value, .. } => match key.as_str() {
+                    "scheme" => links.scheme = Some(unquote(value).trim_end_matches("://").to_string()),
+                    "domains" => links.domains = list(value),
+                    "paths" => links.paths = list(value),
+                    "apple" => links.apple_app_id = Some(unquote(value).to_string()),
+                    "android" => links.android_package = Some(unquote(value).to_string()),
+                    "fingerprints" => links.android_fingerprints = list(value),
+                    _ => {}
+                },
+                Node::Const { .. } | Node::Expansion { .. } => {}
+                other => self.error(other.span(), "expected `key: value` in Links".to_string()),
+            }
+        }
+
+        if links.scheme.is_none() && links.domains.is_empty() {
+            self.error(element.span, "Links needs a 'scheme' or 'domains'".to_string());
+            return None;
+        }
+        if let Some(path) = links.paths.iter().find(|path| !path.starts_with('/')) {
+            self.error(element.span, format!("link path '{}' must start with '/'", path));
+            return None;
+        }
+        Some(links)
+    }
+
+    fn setting(&mut self, key: &str, value: &str, span: Span) -> Option<Setting> {
+        if value.trim().is_empty() {
+            self.error(span, format!("'{}' needs a value", key));
+            return None;
+        }
+        Some(Setting { key: key.to_string(), value: value.to_string(), span })
+    }
+
+    fn items(&mut self, parent: &Element, scope: &[Annotation]) -> Vec<Item> {
+        let mut items = Vec::new();
+        for node in parent.items() {
+            match node {
+                Node::ChildLine { modifier, id, signature, annotations, span, .. } => items.push(Item {
+                    kind: modifier.clone(),
+                    name: id.clone(),
+                    signature: signature.clone(),
+                    annotations: annotations.clone(),
+                    inherited: inherit(scope, annotations),
+                    span: *span,
+                }),
+                Node::Element(element) => {
+                    let (kind, name) = split_name(&element.name);
+                    items.push(Item {
+                        kind: kind.map(str::to_string),
+                        name: name.to_string(),
+                        signature: element.signature.clone(),
+                        annotations: element.annotations.clone(),
+                        inherited: inherit(scope, &element.annotations),
+                        span: element.span,
+                    });
+                }
+                Node::KeyValue { key, span, .. } => {
+                    self.error(*span, format!("unexpected property '{}' in {}", key, parent.name));
+                }
+                Node::Const { .. } | Node::Expansion { .. } => {}
+            }
+        }
+        items
+    }
+}
+
+/// Annotations that describe the block they're written on rather than its contents
+const NOT_INHERITED: &[&str] = &["doc"];
+
+/// What the children of a block inherit: its own annotations, then the ones it
+/// inherited itself
+fn scope(own: &[Annotation], inherited: &[Annotation]) -> Vec<Annotation> {
+    own.iter()
+        .filter(|annotation| !NOT_INHERITED.contains(&annotation.name.as_str()))
+        .chain(inherited)
+        .cloned()
+        .collect()
+}
+
+/// The annotations in `scope` that a child with `own` annotations doesn't override
+fn inherit(scope: &[Annotation], own: &[Annotation]) -> Vec<Annotation> {
+    scope.iter()
+        .filter(|annotation| !own.iter().any(|mine| mine.name == annotation.name))
+        .cloned()
+        .collect()
+}
+
+/// The text of a string literal, or the value as written if it isn't one
+fn unquote(value: &str) -> &str {
+    let value = value.trim();
+    for quote in ['"', '\'', '`'] {
+        if let Some(inner) = value.strip_prefix(quote).and_then(|value| value.strip_suffix(quote)) {
+            return inner;
+        }
+    }
+    value
+}
+
+/// Items of `[a, "b c"]`; a single value is a list of one. Commas inside quotes
+/// don't separate items.
+fn list(value: &str) -> Vec<String> {
+    let value = value.trim();
+    let inner = value.strip_prefix('[').and_then(|value| value.strip_suffix(']')).unwrap_or(value);
+    let mut items = Vec::new();
+    let (mut start, mut quote) = (0, None);
+    for (index, c) in inner.char_indices() {
+        match (c, quote) {
+            ('"' | '\'' | '`', None) => quote = Some(c),
+            (c, Some(open)) if c == open => quote = None,
+            (',', None) => {
+                items.push(&inner[start..index]);
+                start = index + 1;
+            }
+            _ => {}
+        }
+    }
+    items.push(&inner[start..]);
+    items.into_iter()
+        .map(unquote)
+        .filter(|item| !item.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+/// `major.minor.patch`, optionally followed by a `-prerelease` or `+build` suffix
+fn is_version(version: &str) -> bool {
+    let core = version.split(['-', '+']).next().unwrap_or_default();
+    let parts: Vec<&str> = core.split('.').collect();
+    parts.len() == 3 && parts.iter().all(|part| !part.is_empty() && part.chars().all(|c| c.is_ascii_digit()))
+}
+
+/// A host name such as `acme.dev`: dot-separated labels of letters, digits and `-`
+/// Upper case letters, digits and underscores, not starting with a digit
+fn is_env_name(name: &str) -> bool {
+    !name.starts_with(|c: char| c.is_ascii_digit())
+        && !name.is_empty()
+        && name.chars().all(|c| c.is_ascii_uppercase() || c.is_ascii_digit() || c == '_')
+}
+
+fn is_domain(domain: &str) -> bool {
+    let labels: Vec<&str> = domain.split('.').collect();
+    labels.len() >= 2 && labels.iter().all(|label| {
+        !label.is_empty() && !label.starts_with('-') && !label.ends_with('-')
+            && label.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+    })
+}
+
+/// A language tag such as `en`, `pt-BR` or `zh-Hant`
+fn is_locale(locale: &str) -> bool {
+    let mut parts = locale.split(['-', '_']);
+    let language = parts.next().unwrap_or_default();
+    (2..=3).contains(&language.len()) && language.chars().all(|c| c.is_ascii_lowercase())
+        && parts.all(|part| (2..=8).contains(&part.len()) && part.chars().all(|c| c.is_ascii_alphanumeric()))
+}
+
+/// Split `string @primary @default(now())` into the type and its annotations
+fn split_field_annotations(value: &str) -> (String, Vec<Annotation>) {
+    let Some(at) = value.find(" @").map(|i| i + 1).or_else(|| value.starts_with('@').then_some(0)) else {
+        return (value.trim().to_string(), Vec::new());
+    };
+
+    let mut annotations = Vec::new();
+    let mut rest = &value[at..];
+    while let Some(after_at) = rest.trim_start().strip_prefix('@') {
+        let name_len = after_at
+            .find(|c: char| !(c.is_alphanumeric() || c == '_' || c == '-'))
+            .unwrap_or(after_at.len());
+        let name = after_at[..name_len].to_string();
+        rest = &after_at[name_len..];
+
+        let mut args = None;
+        if rest.starts_with('(') {
+            let mut depth = 0usize;
+            let close = rest.char_indices().find(|&(_, c)| {
+                match c {
+                    '(' => depth += 1,
+                    ')' => depth -= 1,
+                    _ => {}
+                }
+                depth == 0
+            });
+            let close = close.map_or(rest.len(), |(i, _)| i);
+            args = Some(rest[1..close].trim().to_string());
+            rest = rest.get(close + 1..).unwrap_or("");
+        }
+        annotations.push(Annotation { name, args });
+    }
+    if !rest.trim().is_empty() {
+        // Not just annotations after all, e.g. an email address in a default value
+        return (value.trim().to_string(), Vec::new());
+    }
+    (value[..at].trim().to_string(), annotations)
+}
@@ -0,0 +1,7 @@
+export default function Page() {
+  return (
+    <main className="container mx-auto px-4 py-8">
+      <h1 className="text-3xl font-bold">/cart</h1>
+    </main>
+  )
+}
@@ -0,0 +1,55 @@
+import { Button } from '@/components/ui/button'
+
+export default function Home() {
+  return (
+    <div className="min-h-screen bg-gradient-to-br from-slate-50 to-slate-100 dark:from-slate-900 dark:to-slate-800">
+      <div className="container mx-auto px-4 py-8">
+        <div className="text-center mb-12">
+          <h1 className="text-4xl font-bold text-slate-900 dark:text-slate-100 mb-4">
+            Welcome to Shop
+          </h1>
+          <p className="text-xl text-slate-600 dark:text-slate-400">
+            Built with Next.js, Tailwind CSS, and shadcn/ui
+          </p>
+        </div>
+
+        <div className="mb-8">
+          <div className="bg-white dark:bg-slate-800 rounded-lg shadow-md p-6">
+            <h2 className="text-2xl font-semibold text-slate-900 dark:text-slate-100 mb-4">🛣️ Routes</h2>
+            <p className="text-slate-600 dark:text-slate-400 mb-4">Your application routes are ready to be implemented.</p>
+            <Button variant="outline">Explore Routes</Button>
+          </div>
+        </div>
+        <div className="mb-8">
+          <div className="bg-white dark:bg-slate-800 rounded-lg shadow-md p-6">
+            <h2 className="text-2xl font-semibold text-slate-900 dark:text-slate-100 mb-4">🧩 Components</h2>
+            <p className="text-slate-600 dark:text-slate-400 mb-4">Reusable components with shadcn/ui integration.</p>
+            <div className="grid grid-cols-1 md:grid-cols-3 gap-4">
+              <div className="bg-slate-50 dark:bg-slate-700 rounded p-4 text-center">
+                <div className="text-2xl mb-2">📱</div>
+                <p className="text-sm font-medium">Responsive</p>
+              </div>
+              <div className="bg-slate-50 dark:bg-slate-700 rounded p-4 text-center">
+                <div className="text-2xl mb-2">🎨</div>
+                <p className="text-sm font-medium">Styled</p>
+              </div>
+              <div className="bg-slate-50 dark:bg-slate-700 rounded p-4 text-center">
+                <div className="text-2xl mb-2">⚡</div>
+                <p className="text-sm font-medium">Fast</p>
+              </div>
+            </div>
+          </div>
+        </div>
+        <div className="mb-8">
+          <div className="bg-white dark:bg-slate-800 rounded-lg shadow-md p-6">
+            <h2 className="text-2xl font-semibold text-slate-900 dark:text-slate-100 mb-4">🔌 API</h2>
+            <p className="text-slate-600 dark:text-slate-400 mb-4">API endpoints are configured and ready for development.</p>
+            <div className="bg-slate-50 dark:bg-slate-700 rounded p-3">
+              <code className="text-sm text-slate-700 dark:text-slate-300">GET /api/example</code>
+            </div>
+          </div>
+        </div>
+      </div>
+    </div>
+  )
+}
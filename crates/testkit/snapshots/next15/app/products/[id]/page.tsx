@@ -0,0 +1,8 @@
+export default async function Page(props: { params: Promise<{ id: string }> }) {
+  const params = await props.params
+  return (
+    <main className="container mx-auto px-4 py-8">
+      <h1 className="text-3xl font-bold">/products/{params.id}</h1>
+    </main>
+  )
+}
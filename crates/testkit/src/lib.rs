@@ -0,0 +1,233 @@
+//! Conformance checks for target compilers, built-in or plugin.
+//!
+//! [`Conformance`] compiles a set of canonical programs, the [`FIXTURES`], with the
+//! compiler of one target type, in memory, and checks what every compiler should
+//! get right whatever it generates:
+//!
+//! - the program compiles, without the compiler failing or panicking;
+//! - the files the caller [requires](Conformance::require) are there;
+//! - every `.json` and `.toml` file parses, so package managers can read the manifests;
+//! - no file holds an absolute path of the machine it was generated on;
+//! - compiling the program again generates the same files, byte for byte.
+//!
+//! ```no_run
+//! use z_compiler_core::CompileOptions;
+//! use z_compiler_testkit::{assert_conformance, Conformance};
+//!
+//! let options = CompileOptions::default();
+//! assert_conformance(&Conformance::new("next", &options).require("package.json"));
+//! ```
+//!
+//! A compiler that isn't a built-in or a plugin of the options can be checked with
+//! [`Conformance::with_compiler`].
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use z_compiler_core::{compile_target, compiler_for, crash, diagnostic, lower, tr, CompileOptions, MemorySink, TargetCompiler};
+
+/// A canonical program with one target, of type `KIND`
+#[derive(Debug, Clone, Copy)]
+pub struct Fixture {
+    pub name: &'static str,
+    pub source: &'static str,
+}
+
+impl Fixture {
+    /// The program with its target of type `kind`
+    pub fn for_kind(&self, kind: &str) -> String {
+        self.source.replace("KIND", kind)
+    }
+}
+
+/// The programs every compiler is checked with, from the smallest up. A fixture with
+/// sections a target type doesn't take is skipped for it.
+pub const FIXTURES: &[Fixture] = &[
+    Fixture { name: "minimal", source: include_str!("../fixtures/minimal.z") },
+    Fixture { name: "app", source: include_str!("../fixtures/app.z") },
+    Fixture { name: "workspace", source: include_str!("../fixtures/workspace.z") },
+];
+
+/// Something a compiler got wrong with a fixture
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Failure {
+    /// The fixture didn't get past the checks before generating
+    Invalid(String),
+    NoCompiler(String),
+    /// The compiler returned an error
+    Compile(String),
+    Panicked(String),
+    Empty,
+    Missing(PathBuf),
+    /// A `.json` or `.toml` file doesn't parse
+    Manifest { path: PathBuf, error: String },
+    AbsolutePath { path: PathBuf, leaked: String },
+    /// The file is different, or only there, in one of two compiles
+    Nondeterministic(PathBuf),
+}
+
+impl fmt::Display for Failure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let message = match self {
+            Failure::Invalid(error) => tr!("conformance-invalid", error = error),
+            Failure::NoCompiler(kind) => tr!("conformance-no-compiler", kind = kind),
+            Failure::Compile(error) => tr!("conformance-compile-failed", error = error),
+            Failure::Panicked(error) => tr!("conformance-panicked", error = error),
+            Failure::Empty => tr!("conformance-empty"),
+            Failure::Missing(path) => tr!("conformance-missing", path = path.display()),
+            Failure::Manifest { path, error } => tr!("conformance-manifest", path = path.display(), error = error),
+            Failure::AbsolutePath { path, leaked } => tr!("conformance-absolute-path", path = path.display(), leaked = leaked),
+            Failure::Nondeterministic(path) => tr!("conformance-nondeterministic", path = path.display()),
+        };
+        f.write_str(&message)
+    }
+}
+
+/// How a compiler did with one fixture
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Verdict {
+    Passed { files: usize },
+    /// The fixture has sections the target type doesn't take
+    Skipped,
+    Failed(Vec<Failure>),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Outcome {
+    pub fixture: &'static str,
+    pub verdict: Verdict,
+}
+
+/// The conformance checks for the compiler of one target type
+pub struct Conformance<'a> {
+    kind: String,
+    options: &'a CompileOptions,
+    compiler: Option<&'a dyn TargetCompiler>,
+    required: Vec<PathBuf>,
+}
+
+impl<'a> Conformance<'a> {
+    /// Check the compiler `options` have for targets of type `kind`: a built-in one,
+    /// or a plugin's
+    pub fn new(kind: impl Into<String>, options: &'a CompileOptions) -> Self {
+        Self { kind: kind.into(), options, compiler: None, required: Vec::new() }
+    }
+
+    /// Check `compiler` instead
+    pub fn with_compiler(mut self, compiler: &'a dyn TargetCompiler) -> Self {
+        self.compiler = Some(compiler);
+        self
+    }
+
+    /// Fail every fixture that doesn't generate the file `path`, relative to the project
+    pub fn require(mut self, path: impl Into<PathBuf>) -> Self {
+        self.required.push(path.into());
+        self
+    }
+
+    /// Check the compiler with every fixture, in order
+    pub fn run(&self) -> Vec<Outcome> {
+        FIXTURES.iter().map(|fixture| self.run_fixture(fixture)).collect()
+    }
+
+    /// Check the compiler with one program, whose first target is generated
+    pub fn run_fixture(&self, fixture: &Fixture) -> Outcome {
+        let verdict = match self.check(&fixture.for_kind(&self.kind)) {
+            Ok(Some(files)) => Verdict::Passed { files },
+            Ok(None) => Verdict::Skipped,
+            Err(failures) => Verdict::Failed(failures),
+        };
+        Outcome { fixture: fixture.name, verdict }
+    }
+
+    /// How many files the compiler generates for `source`, or None if the target type
+    /// doesn't take its sections
+    fn check(&self, source: &str) -> Result<Option<usize>, Vec<Failure>> {
+        let lowered = match lower(source, self.options) {
+            Ok(lowered) => lowered,
+            Err(errors) if errors.iter().all(|error| error.code == diagnostic::SCHEMA) => return Ok(None),
+            Err(errors) => return Err(errors.iter().map(|error| Failure::Invalid(error.to_string())).collect()),
+        };
+        let Some((target, target_options)) = lowered.targets.first() else {
+            return Err(vec![Failure::Invalid(tr!("no-targets"))]);
+        };
+        let found;
+        let compiler = match self.compiler {
+            Some(compiler) => compiler,
+            None => {
+                found = compiler_for(&self.kind, &lowered.ast, self.options).ok_or_else(|| vec![Failure::NoCompiler(self.kind.clone())])?;
+                &*found
+            }
+        };
+
+        let generate = || {
+            let sink = MemorySink::new();
+            match crash::isolate(|| compile_target(target, compiler, target_options, &sink, self.options)) {
+                Ok(Ok(())) => Ok(sink.files()),
+                Ok(Err(e)) => Err(vec![Failure::Compile(e)]),
+                Err(panic) => Err(vec![Failure::Panicked(panic)]),
+            }
+        };
+        let files = generate()?;
+        let again = generate()?;
+
+        let mut failures = Vec::new();
+        if files.is_empty() {
+            failures.push(Failure::Empty);
+        }
+        failures.extend(self.required.iter().filter(|path| !files.contains_key(*path)).map(|path| Failure::Missing(path.clone())));
+        failures.extend(files.iter().filter_map(|(path, contents)| invalid_manifest(path, contents)));
+        failures.extend(leaked_paths(&files));
+        let paths: BTreeSet<&PathBuf> = files.keys().chain(again.keys()).collect();
+        failures.extend(paths.into_iter().filter(|path| files.get(*path) != again.get(*path)).map(|path| Failure::Nondeterministic(path.clone())));
+        if failures.is_empty() {
+            Ok(Some(files.len()))
+        } else {
+            Err(failures)
+        }
+    }
+}
+
+/// Run the checks, panicking with every failure; for a compiler's `#[test]`
+pub fn assert_conformance(conformance: &Conformance) {
+    let failed: Vec<String> = conformance.run().into_iter()
+        .filter_map(|outcome| match outcome.verdict {
+            Verdict::Failed(failures) => Some(failures.iter().map(|failure| format!("{}: {}", outcome.fixture, failure)).collect::<Vec<_>>()),
+            _ => None,
+        })
+        .flatten()
+        .collect();
+    assert!(failed.is_empty(), "{}", failed.join("\n"));
+}
+
+/// The failure of a `.json` or `.toml` file that doesn't parse
+fn invalid_manifest(path: &Path, contents: &[u8]) -> Option<Failure> {
+    let text = String::from_utf8_lossy(contents);
+    let error = match path.extension().and_then(|extension| extension.to_str()) {
+        Some("json") => serde_json::from_str::<serde_json::Value>(&text).err()?.to_string(),
+        Some("toml") => z_compiler_core::toml::parse(&text).err()?,
+        _ => return None,
+    };
+    Some(Failure::Manifest { path: path.to_path_buf(), error })
+}
+
+/// The directories of this machine a generated file has no business naming: the
+/// scratch directories compilers generate in, the home directory and the working one
+fn leaked_paths(files: &BTreeMap<PathBuf, Vec<u8>>) -> Vec<Failure> {
+    let mut machine: Vec<String> = vec![std::env::temp_dir().join("z-").to_string_lossy().into_owned()];
+    machine.extend(std::env::var("HOME").ok());
+    machine.extend(std::env::current_dir().ok().map(|dir| dir.to_string_lossy().into_owned()));
+    machine.retain(|dir| dir.trim_matches('/').len() > 1);
+
+    let mut failures = Vec::new();
+    for (path, contents) in files {
+        let Ok(text) = std::str::from_utf8(contents) else {
+            continue;
+        };
+        if let Some(leaked) = machine.iter().find(|dir| text.contains(dir.as_str())) {
+            failures.push(Failure::AbsolutePath { path: path.clone(), leaked: leaked.clone() });
+        }
+    }
+    failures
+}
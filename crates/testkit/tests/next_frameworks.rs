@@ -0,0 +1,66 @@
+//! The Next.js project each framework mode generates for the `app` fixture, compared
+//! file by file with the one under `snapshots/<mode>`. Run with `Z_UPDATE_SNAPSHOTS=1`
+//! to write the snapshots again after a deliberate change.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use z_compiler_core::{compile_target, compiler_for, lower, CompileOptions, MemorySink};
+use z_compiler_testkit::FIXTURES;
+
+fn assert_snapshot(framework: &str) {
+    let fixture = FIXTURES.iter().find(|fixture| fixture.name == "app").expect("app fixture");
+    let source = fixture.source.replace("KIND Shop", &format!("next Shop @options(framework: {})", framework));
+    let options = CompileOptions::default();
+    let lowered = lower(&source, &options).unwrap_or_else(|errors| panic!("{:?}", errors));
+    let (target, target_options) = &lowered.targets[0];
+    let compiler = compiler_for("next", &lowered.ast, &options).expect("next compiler");
+    let sink = MemorySink::new();
+    compile_target(target, &*compiler, target_options, &sink, &options).expect("compiles");
+    let files = sink.into_files();
+
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("snapshots").join(framework);
+    if std::env::var_os("Z_UPDATE_SNAPSHOTS").is_some() {
+        let _ = fs::remove_dir_all(&dir);
+        for (path, contents) in &files {
+            let path = dir.join(path);
+            fs::create_dir_all(path.parent().expect("in the snapshot")).expect("snapshot directory");
+            fs::write(path, contents).expect("snapshot written");
+        }
+        return;
+    }
+
+    let mut snapshot = Vec::new();
+    list(&dir, Path::new(""), &mut snapshot);
+    snapshot.sort();
+    assert_eq!(files.keys().cloned().collect::<Vec<_>>(), snapshot, "files generated for {}", framework);
+    for (path, contents) in &files {
+        let expected = fs::read(dir.join(path)).expect("snapshot file");
+        assert!(
+            *contents == expected,
+            "{} differs from snapshots/{}/{}:\n{}",
+            path.display(), framework, path.display(), String::from_utf8_lossy(contents),
+        );
+    }
+}
+
+fn list(root: &Path, relative: &Path, files: &mut Vec<PathBuf>) {
+    for entry in fs::read_dir(root.join(relative)).expect("snapshot directory").flatten() {
+        let path = relative.join(entry.file_name());
+        if entry.file_type().is_ok_and(|kind| kind.is_dir()) {
+            list(root, &path, files);
+        } else {
+            files.push(path);
+        }
+    }
+}
+
+#[test]
+fn next14() {
+    assert_snapshot("next14");
+}
+
+#[test]
+fn next15() {
+    assert_snapshot("next15");
+}
@@ -9,6 +9,7 @@ import lsp from 'vscode-languageserver/node.js';
 import { LspClientLogger } from './utils/logger.js';
 import { LspServer } from './lsp-server.js';
 import { LspClientImpl } from './lsp-client.js';
+import { GeneratePreviewRequest, ListTargetsRequest, ZSources } from './z-preview.js';
 
 export interface LspConnectionOptions {
     showMessageLevel: lsp.MessageType;
@@ -23,14 +24,25 @@ export function createLspConnection(options: LspConnectionOptions): lsp.Connecti
         lspClient,
     });
 
+    const zSources = new ZSources();
+
     connection.onInitialize(server.initialize.bind(server));
     connection.onInitialized(server.initialized.bind(server));
     connection.onDidChangeConfiguration(server.didChangeConfiguration.bind(server));
 
-    connection.onDidOpenTextDocument(server.didOpenTextDocument.bind(server));
+    connection.onDidOpenTextDocument((params) => {
+        zSources.open(params);
+        server.didOpenTextDocument(params);
+    });
     connection.onDidSaveTextDocument(server.didSaveTextDocument.bind(server));
-    connection.onDidCloseTextDocument(server.didCloseTextDocument.bind(server));
-    connection.onDidChangeTextDocument(server.didChangeTextDocument.bind(server));
+    connection.onDidCloseTextDocument((params) => {
+        zSources.close(params);
+        server.didCloseTextDocument(params);
+    });
+    connection.onDidChangeTextDocument((params) => {
+        zSources.change(params);
+        server.didChangeTextDocument(params);
+    });
 
     connection.onCodeAction(server.codeAction.bind(server));
     connection.onCodeLens(server.codeLens.bind(server));
@@ -62,5 +74,8 @@ export function createLspConnection(options: LspConnectionOptions): lsp.Connecti
     connection.languages.semanticTokens.onRange(server.semanticTokensRange.bind(server));
     connection.workspace.onWillRenameFiles(server.willRenameFiles.bind(server));
 
+    connection.onRequest(ListTargetsRequest.type, zSources.listTargets.bind(zSources));
+    connection.onRequest(GeneratePreviewRequest.type, zSources.generatePreview.bind(zSources));
+
     return connection;
 }
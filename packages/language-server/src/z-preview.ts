@@ -0,0 +1,119 @@
+/**
+ * Custom requests for editors that show the code generated from a Z program next to
+ * its source. Both run the Z compiler (`z-cli preview`, or `$Z_CLI` if set) on the
+ * document's current text, saved or not.
+ *
+ * - `z/listTargets`: the targets of the document, with the lines they span and the
+ *   sections their compilers ignore.
+ * - `z/generatePreview`: the files generated for the declaration under the cursor,
+ *   such as a route's page or a model's schema; all the target's files when the
+ *   cursor isn't on a declaration.
+ */
+
+import { execFile } from 'node:child_process';
+import * as path from 'node:path';
+import { RequestType } from 'vscode-languageserver-protocol';
+import type lsp from 'vscode-languageserver-protocol';
+import { TextDocument } from 'vscode-languageserver-textdocument';
+import { URI } from 'vscode-uri';
+
+export interface ZTarget {
+    kind: string;
+    name: string;
+    /** 1-based lines of the target's block */
+    line: number;
+    endLine: number;
+    /** Sections of the target its compiler generates nothing from, e.g. `Schema` */
+    ignored?: string[];
+}
+
+export interface ZPreviewFile {
+    /** Relative to the target's output directory */
+    path: string;
+    contents: string;
+}
+
+export interface ZPreview {
+    /** `kind:name` of the generated target */
+    target: string;
+    /** The declaration the files were picked for, if the cursor was on one */
+    element?: string;
+    files: ZPreviewFile[];
+}
+
+export interface ListTargetsParams {
+    textDocument: lsp.TextDocumentIdentifier;
+}
+
+export interface GeneratePreviewParams extends lsp.TextDocumentPositionParams {
+    /** Target to generate, as `name` or `kind:name`, instead of the one around the position */
+    target?: string;
+}
+
+export namespace ListTargetsRequest {
+    export const type = new RequestType<ListTargetsParams, ZTarget[], void>('z/listTargets');
+}
+
+export namespace GeneratePreviewRequest {
+    export const type = new RequestType<GeneratePreviewParams, ZPreview, void>('z/generatePreview');
+}
+
+/**
+ * Text of the open `.z` documents, which the compiler is given instead of what's on disk
+ */
+export class ZSources {
+    private readonly documents = new Map<string, TextDocument>();
+
+    open(params: lsp.DidOpenTextDocumentParams): void {
+        const { uri, languageId, version, text } = params.textDocument;
+        if (uri.endsWith('.z')) {
+            this.documents.set(uri, TextDocument.create(uri, languageId, version, text));
+        }
+    }
+
+    change(params: lsp.DidChangeTextDocumentParams): void {
+        const document = this.documents.get(params.textDocument.uri);
+        if (document) {
+            TextDocument.update(document, params.contentChanges, params.textDocument.version);
+        }
+    }
+
+    close(params: lsp.DidCloseTextDocumentParams): void {
+        this.documents.delete(params.textDocument.uri);
+    }
+
+    listTargets(params: ListTargetsParams): Promise<ZTarget[]> {
+        return this.preview(params.textDocument.uri, ['--list']).then((output) =>
+            (JSON.parse(output) as Array<ZTarget & { end_line: number; }>).map(({ end_line, ...target }) => ({
+                ...target,
+                endLine: end_line,
+            })),
+        );
+    }
+
+    generatePreview(params: GeneratePreviewParams): Promise<ZPreview> {
+        const args = ['--line', `${params.position.line + 1}`, '--column', `${params.position.character + 1}`];
+        if (params.target) {
+            args.push('--target', params.target);
+        }
+        return this.preview(params.textDocument.uri, args).then((output) => JSON.parse(output) as ZPreview);
+    }
+
+    private preview(uri: string, args: string[]): Promise<string> {
+        const file = URI.parse(uri).fsPath;
+        const text = this.documents.get(uri)?.getText();
+        const compiler = process.env.Z_CLI || 'z-cli';
+        const allArgs = ['preview', file, ...text !== undefined ? ['--stdin'] : [], ...args];
+        return new Promise((resolve, reject) => {
+            const child = execFile(compiler, allArgs, { cwd: path.dirname(file), maxBuffer: 64 * 1024 * 1024 }, (error, stdout, stderr) => {
+                if (error) {
+                    // The compiler explains what went wrong on stderr, after an icon
+                    reject(new Error(stderr.replace(/^\W+/, '').trim() || error.message));
+                } else {
+                    resolve(stdout);
+                }
+            });
+            child.stdin?.end(text ?? '');
+        });
+    }
+}
@@ -14,10 +14,21 @@ export interface TargetInfo {
     description: string;
     mode: 'markup' | 'code';
     allowedChildren: string[];
+    /** Properties this target takes besides the registry's `targetFields`, with their descriptions */
+    fields?: Record<string, string>;
     defaultPackages: Record<string, string>;
+    /** Version range of each package the generated code is known to work with, e.g. `>=14.0.0 <16.0.0` */
+    compatibleDependencies?: Record<string, string>;
     compiler: string;
 }
 
+export interface TargetAlias {
+    /** Target type the alias stands for */
+    target: string;
+    /** An old name, still accepted with a warning */
+    deprecated?: boolean;
+}
+
 export interface NamespaceInfo {
     aliasOf: string;
     description: string;
@@ -25,6 +36,8 @@ export interface NamespaceInfo {
     childMode?: 'single' | 'multiple';
     allowedChildren?: string[];
     role?: string;
+    /** Properties the namespace takes, with their descriptions; any when left out */
+    fields?: Record<string, string>;
     scaffolding?: ScaffoldingConfig;
 }
 
@@ -45,6 +58,10 @@ export interface AnnotationInfo {
 export interface Registry {
     version: string;
     targets: Record<string, TargetInfo>;
+    /** Properties every target takes, with their descriptions */
+    targetFields: Record<string, string>;
+    /** Other names of target types */
+    targetAliases?: Record<string, TargetAlias>;
     namespaces: Record<string, NamespaceInfo>;
     annotations: Record<string, AnnotationInfo>;
     childTypes: Record<string, ChildTypeInfo>;
@@ -104,15 +121,23 @@ export function getTargetCompletions(): Array<{
     detail: string;
 }> {
     const registry = loadRegistry();
-    return Object.entries(registry.targets).map(([name, info]) => ({
+    const targets = Object.entries(registry.targets).map(([name, info]) => ({
         label: name,
         detail: info.description,
     }));
+    // Deprecated names aren't offered; they only keep old sources working
+    const aliases = Object.entries(registry.targetAliases ?? {})
+        .filter(([, alias]) => !alias.deprecated)
+        .map(([name, alias]) => ({
+            label: name,
+            detail: `Same as ${alias.target}: ${registry.targets[alias.target]?.description ?? ''}`,
+        }));
+    return [...targets, ...aliases];
 }
 
 export function getChildrenForTarget(targetName: string): string[] {
     const registry = loadRegistry();
-    const target = registry.targets[targetName];
+    const target = registry.targets[registry.targetAliases?.[targetName]?.target ?? targetName];
     return target ? target.allowedChildren : [];
 }
 
@@ -1,7 +1,13 @@
 import * as path from "path";
 import {
+  commands,
+  window,
   workspace,
+  EventEmitter,
   ExtensionContext,
+  TextDocumentContentProvider,
+  Uri,
+  ViewColumn,
 } from "vscode";
 
 import {
@@ -13,6 +19,43 @@ import {
 
 let client: LanguageClient;
 
+// Generated files shown by "Z: Show Generated Code to the Side"
+const PREVIEW_SCHEME = "z-preview";
+
+interface PreviewFile {
+  path: string;
+  contents: string;
+}
+
+interface Preview {
+  target: string;
+  element?: string;
+  files: PreviewFile[];
+}
+
+class PreviewProvider
+  implements TextDocumentContentProvider
+{
+  private contents = new Map<string, string>();
+  private changes = new EventEmitter<Uri>();
+  readonly onDidChange = this.changes.event;
+
+  // Shows `file` of `target` as a read-only document, e.g. z-preview:/next:web/app/page.tsx
+  show(target: string, file: PreviewFile): Uri {
+    const uri = Uri.from({
+      scheme: PREVIEW_SCHEME,
+      path: `/${target}/${file.path}`,
+    });
+    this.contents.set(uri.toString(), file.contents);
+    this.changes.fire(uri);
+    return uri;
+  }
+
+  provideTextDocumentContent(uri: Uri): string {
+    return this.contents.get(uri.toString()) ?? "";
+  }
+}
+
 export function activate(
   context: ExtensionContext
 ) {
@@ -37,6 +80,13 @@ export function activate(
     ) ||
     "z-language-server"; // Global installation
 
+  // The server runs the compiler for previews of the generated code
+  const compilerPath =
+    config.get<string>("compiler.path");
+  const env = compilerPath
+    ? { ...process.env, Z_CLI: compilerPath }
+    : process.env;
+
   // Server options for the new z-language-server
   const serverOptions: ServerOptions = {
     run: {
@@ -46,6 +96,7 @@ export function activate(
         "--stdio",
       ],
       transport: TransportKind.stdio,
+      options: { env },
     },
     debug: {
       command: "node",
@@ -56,6 +107,7 @@ export function activate(
         "4",
       ],
       transport: TransportKind.stdio,
+      options: { env },
     },
   };
 
@@ -88,6 +140,84 @@ export function activate(
 
   // Start the client. This will also launch the server
   client.start();
+
+  const previews = new PreviewProvider();
+  context.subscriptions.push(
+    workspace.registerTextDocumentContentProvider(
+      PREVIEW_SCHEME,
+      previews
+    ),
+    commands.registerCommand(
+      "z-language.showPreview",
+      () => showPreview(previews)
+    )
+  );
+}
+
+// Generate the code for the declaration under the cursor and open it beside the
+// source, asking which file to show when there are several
+async function showPreview(
+  previews: PreviewProvider
+) {
+  const editor = window.activeTextEditor;
+  if (
+    !editor ||
+    editor.document.languageId !== "z"
+  ) {
+    return;
+  }
+
+  let preview: Preview;
+  try {
+    preview = await client.sendRequest(
+      "z/generatePreview",
+      {
+        textDocument: {
+          uri: editor.document.uri.toString(),
+        },
+        position: editor.selection.active,
+      }
+    );
+  } catch (error) {
+    window.showErrorMessage(
+      `Couldn't generate a preview: ${
+        error instanceof Error
+          ? error.message
+          : error
+      }`
+    );
+    return;
+  }
+
+  const file =
+    preview.files.length === 1
+      ? preview.files[0]
+      : await window
+          .showQuickPick(
+            preview.files.map((file) => ({
+              label: file.path,
+              file,
+            })),
+            {
+              placeHolder: `Files generated for ${
+                preview.element ?? preview.target
+              }`,
+            }
+          )
+          .then((picked) => picked?.file);
+  if (!file) {
+    return;
+  }
+
+  const document =
+    await workspace.openTextDocument(
+      previews.show(preview.target, file)
+    );
+  await window.showTextDocument(document, {
+    viewColumn: ViewColumn.Beside,
+    preserveFocus: true,
+    preview: true,
+  });
 }
 
 export function deactivate():
@@ -0,0 +1,338 @@
+# English messages for the Z compiler and CLI.
+# Every message id used in the code must be defined here; other locales fall
+# back to this file for anything they don't translate.
+
+## Compilation
+
+no-targets = No target blocks found in entry file.
+no-targets-help = Declare one, e.g. `next web {}`
+detected-targets = Detected targets: { $targets }
+unknown-target = { $target } - Unknown target type (not in registry)
+unknown-target-help = Known target types: { $targets }
+selected-target-unknown = No target named { $name } to compile. The targets are: { $targets }
+excluded-target-unknown = No target named { $name } to exclude. The targets are: { $targets }
+deprecated-target = target type { $alias } is deprecated; it's now called { $target }
+deprecated-target-help = Write `{ $target }` instead of `{ $alias }`; the old name will stop working in a future version
+no-compiler = No compiler available for target: { $target }
+empty-target = target { $target } has no content; generated app will be a placeholder
+empty-target-help = Add one of the sections it understands: { $sections }
+section-ignored = { $section } is ignored by the compiler of { $target }; nothing is generated from it
+section-ignored-help = { $kind } targets generate code from { $sections }
+declarations-ignored = lines directly inside { $target } are ignored by its compiler
+declarations-ignored-help = Move them into one of the sections it generates code from: { $sections }
+unused-component = component { $component } is declared but no route renders it
+unused-component-help = Name it in a route's `@component(...)`, remove it, or mark it `@allow(unused_component)`
+allow-unknown = @allow: no warning is called '{ $name }'{ $suggestion }
+compile-success = { $target } { $name } compilation successful ({ $compiler })
+compile-failed = { $target } { $name } compilation failed: { $error }
+target-blocked = { $target } was not generated because { $prerequisite }, which it uses, was not
+hook-failed = `{ $command }` failed: { $error }
+hook-exit-code = it exited with status { $code }
+hook-offline-help = Hooks run without network access. If this one needs it, e.g. to install packages, add `network = true` to [sandbox] in z.toml and approve it with `z trust`
+hook-ran = Ran `{ $command }` ({ $duration } ms)
+span-compile = compiling { $source }
+span-closed = { $name } took { $duration } ms
+compiler-panicked = { $target } { $name } compilation crashed: { $error }
+compiler-panicked-help = This is a bug in the { $compiler } compiler, not in your program; the other targets were still compiled
+parse-error = Parse error: { $error }
+import-missing = cannot import { $path }: { $error }
+import-invalid = `{ $import }` isn't an import; write `import "./file.z"` or `import name from "./file.z"`
+import-top-level = the top level
+import-namespace-conflict = { $path } is imported into both { $first } and { $second }; import it under one name
+import-namespace-workspace = a file imported under a name can't declare workspaces; its targets already go into that name's workspace
+imports-unresolved = imports are only followed when compiling a file; this source has no file to import relative to
+project-created = Project created in: { $path }
+compiled = Compiled { $source } -> { $out }
+compiled-with-errors = { $source } has { $errors ->
+        [one] 1 error
+       *[other] { $errors } errors
+    }; the targets without any were still generated
+target-stats = { $duration } ms, { $files ->
+        [one] 1 file
+       *[other] { $files } files
+    }, { $bytes ->
+        [one] 1 byte
+       *[other] { $bytes } bytes
+    }
+dry-run-done = Dry run of { $source }: { $changes ->
+        [one] 1 file would change
+       *[other] { $changes } files would change
+    } in { $out }; nothing was written
+determinism-ok = Compiling { $source } twice generated the same files
+determinism-differs = { $path } came out different the second time
+determinism-failed = Compiling { $source } twice generated { $count ->
+        [one] 1 file
+       *[other] { $count } files
+    } differently
+strict-failed = { $source } has errors, and strict mode fails the build on them
+archive-format-unknown = { $path } isn't an archive z can write; name it .zip, .tar or .tar.gz
+warnings-denied = { $source } has errors or warnings, and --deny-warnings fails the build on them
+members-unsupported = --archive, --emit, --ui, --interactive and --check-determinism work on one program at a time; compile a single .z file to use them
+members-no-match = { $pattern } matches no file
+members-none = { $dir } has no members to compile; list the entry files in its z.toml, e.g. members = ["apps/*/main.z"]
+member-compiling = ── { $source }
+compiled-members = Compiled { $count ->
+        [one] 1 program
+       *[other] { $count } programs
+    } in { $duration }ms
+dry-run-members-done = Dry run of { $count ->
+        [one] 1 program
+       *[other] { $count } programs
+    }: { $changes ->
+        [one] 1 file would change
+       *[other] { $changes } files would change
+    }; nothing was written
+check-passed = { $source } has no errors: { $targets ->
+        [one] 1 target
+       *[other] { $targets } targets
+    } checked, { $warnings ->
+        [one] 1 warning
+       *[other] { $warnings } warnings
+    }
+check-failed = { $source } has { $errors ->
+        [one] 1 error
+       *[other] { $errors } errors
+    } and { $warnings ->
+        [one] 1 warning
+       *[other] { $warnings } warnings
+    }
+diagnostic-error = error
+diagnostic-warning = warning
+diagnostic-help = help
+clean-target-unknown = No target named { $name } to clean. The targets are: { $targets }
+clean-target = Removed { $files } generated files of { $target } from { $path }
+clean-kept = Kept { $path }, edited since it was generated (--overwrite removes it)
+clean-cache = Removed the compile cache in { $path }
+clean-done = Cleaned { $count ->
+        [one] 1 target
+       *[other] { $count } targets
+    }
+add-target-exists = The program already has a target named { $name }
+add-exists = { $what } is already in { $target }
+add-route-empty = { $path } names no route; write it as a path, e.g. /pricing
+add-no-targets = The program has no targets; add one first, e.g. z add target next WebApp
+add-target-ambiguous = The program has several targets; name the one to add to with --to: { $targets }
+add-section-unsupported = { $target } can't have a { $section } section: { $kind } targets take { $sections }
+add-done = Added to { $path }
+explain-unknown = No diagnostic has the code { $code }; `z explain` lists them
+explain-wrong = For example, this program gets it
+explain-fixed = Fixed
+explain-column-code = CODE
+explain-column-severity = SEVERITY
+explain-column-summary = MEANING
+registry-unknown = { $name } isn't a target type, alias or section{ $suggestion }
+registry-targets = Target types:
+registry-sections = Sections:
+registry-options = Options:
+registry-common-options = Options of every target:
+registry-also = also { $names }
+registry-deprecated = { $name } (deprecated)
+registry-aliases = Also written: { $names }
+registry-generator = Generated by: { $generator }
+registry-built-in = the built-in compiler
+registry-plugin = the plugin { $command }
+registry-not-generated = nothing yet, only checked
+registry-children = Holds: { $names }
+registry-in-targets = In target types: { $names }
+fmt-failed = Couldn't format { $path }: { $error }
+fmt-done = Formatted { $changed } of { $files ->
+        [one] 1 file
+       *[other] { $files } files
+    }
+fmt-checked = { $unformatted } of { $files ->
+        [one] 1 file
+       *[other] { $files } files
+    } { $unformatted ->
+        [one] isn't
+       *[other] aren't
+    } formatted
+dry-run-no-changes = No files would change
+dry-run-create = Would create { $path }
+dry-run-update = Would update { $path }
+dry-run-delete = Would delete { $path }
+
+## File system
+
+create-dir-failed = Failed to create directory { $path }: { $error }
+read-dir-failed = Failed to read directory { $path }: { $error }
+read-failed = Failed to read { $path }: { $error }
+write-failed = Failed to write { $path }: { $error }
+copy-failed = Failed to copy { $path }: { $error }
+remove-failed = Failed to remove { $path }: { $error }
+output-dir-occupied = { $path } already has files z didn't generate; generate the target somewhere else, or pass --overwrite to write into it anyway
+orphan-kept = Kept { $path }: it was generated before but has been edited since; delete it if it is no longer needed
+keep-unplaced = Regions marked z:begin-keep in { $path } no longer had a place in it and were moved to the end of the file
+file-edited = { $path } was edited since it was generated, so it was kept as it is
+file-edited-help = Move your changes into a z:begin-keep region, or compile with --overwrite to regenerate the file
+remove-dir-failed = Failed to remove directory { $path }: { $error }
+read-source-failed = Failed to read source { $path }: { $error }
+source-not-utf8 = { $path } isn't UTF-8 text; save it as UTF-8
+source-too-large = { $path } is { $size } bytes, over the limit of { $limit } bytes (raise it with --max-file-size)
+stdin-too-large = The program on stdin is over the limit of { $limit } bytes (raise it with --max-file-size)
+stdin-unsupported = --ui, --interactive and --check-determinism read the program from its file; they don't work with one piped on stdin
+emit-several-files = --emit - prints a single file, but { $count } were generated: { $files }. Pick a target that generates one, like swift, with --target, or pack them with --archive
+
+## External tools
+
+tool-exec-failed = Failed to execute { $tool }: { $error }
+tool-failed = { $tool } failed
+tool-missing-hint = Make sure { $tool } is installed and available in your PATH
+
+## Project commands
+
+pm-unknown-target = No target named { $name } in { $path } to run the command in; its targets are { $targets }
+project-missing = Project { $name } does not exist yet. Run compilation first.
+no-package-manager = No package manager configured for { $name } ({ $kind })
+no-command = No command provided for { $kind } project { $name }
+running-command = Running { $command } in { $name } ({ $kind })
+command-succeeded = Command completed successfully for { $name }
+command-failed = Command failed for { $name } with exit code: { $code }
+command-exec-failed = Failed to execute { $tool } command for { $name }: { $error }
+legacy-project-command = `z <project> <command>` is deprecated; use: z pm --source { $source } { $command }
+dev-target-unknown = No target named { $name } was generated. The targets are: { $targets }
+dev-no-targets = No target of { $source } was generated successfully
+test-results = Tests:
+test-passed = passed in { $duration }
+test-failed = failed with exit code { $code }
+test-killed = failed, killed by a signal
+test-not-run = not run, the test runner couldn't be started
+test-none = no tests
+test-not-built = not tested, the target failed to compile
+test-summary = { $passed } passed, { $failed } failed, { $skipped } without tests
+dev-starting = Starting { $count } dev servers; press Ctrl-C to stop them
+dev-server-exited = The dev server exited with code { $code }
+dev-stopping = Stopping { $count } dev servers
+watching = Watching { $count ->
+        [one] 1 file
+       *[other] { $count } files
+    } of { $source } for changes
+watch-changed = Changed: { $files }
+
+## Caches
+
+cache-restored = { $target } { $name } restored from the cache
+target-unchanged = { $target } { $name } is unchanged, skipped (--force regenerates it)
+cache-unavailable = Remote cache skipped: { $error }
+cache-corrupt = corrupt cache entry ({ $reason })
+cache-http-failed = cache server answered with HTTP { $code }
+user-cache-unavailable = Cache in { $path } skipped: { $error }
+user-cache-summary = { $entries ->
+        [one] 1 target
+       *[other] { $entries } targets
+    }, { $size } of { $max }, in { $path }
+user-cache-none = There's no home directory to keep the cache in; set { $variable } to use one.
+user-cache-cleaned = Deleted { $entries ->
+        [one] 1 target
+       *[other] { $entries } targets
+    }, { $size }, from { $path }
+
+## Publishing
+
+publish-committed = { $branch }: committed { $files ->
+        [one] 1 file
+       *[other] { $files } files
+    } as { $commit }
+publish-up-to-date = { $branch } is already up to date
+publish-pushed = Pushed { $branch } to { $remote }
+publish-no-output = No generated projects in { $path }. Run compilation first.
+publish-unknown-target = No generated project named { $name } in { $path }
+
+## Deployment
+
+deploy-unknown-target = No target named { $name } in { $path }
+
+## Versioning
+
+version-bumped = Version { $from } → { $to }
+version-updated = Updated { $path }
+version-changelog = Added { $count } commits to { $path }
+version-tagged = Committed and tagged { $tag }
+
+## Crashes
+
+crash-header = The compiler crashed. This is a bug in z, not in your program.
+crash-prompt = Write a crash report to { $path } to attach to a bug report? It holds your .z sources with credentials removed. [Y/n]
+crash-written = Crash report written to { $path }. Please attach it to a new issue at { $url }
+crash-hint = Run with Z_CRASH_REPORT=1 to write a crash report for a bug report.
+
+## Sandbox
+
+trust-nothing = z.toml doesn't ask for any permissions beyond the sandbox defaults.
+trust-already = The permissions in z.toml are already approved.
+trust-grants = z.toml in { $path } asks to let project commands:
+trust-prompt = Approve? [y/N]
+trust-needs-yes = Not running in a terminal; pass --yes to approve.
+trust-recorded = Approved. Changing these permissions in z.toml needs approving again.
+
+## Dependencies
+
+dependency-incompatible = { $package } { $version } is outside the range known to work ({ $range })
+dependency-unchecked = { $package } { $version } can't be checked against the range known to work ({ $range })
+dependency-needs-force = { $problem }. To use it anyway, set force = true for { $package } in [dependencies.{ $kind }] of z.toml
+dependency-not-used = the generated project doesn't depend on { $package }; check its name in z.toml
+
+## Build dashboard
+
+ui-building = Building… { $seconds }s
+ui-done = Done in { $seconds }s
+ui-failed = Failed after { $seconds }s
+ui-targets = Targets
+ui-diagnostics = Diagnostics
+ui-log = Log
+ui-unchanged = unchanged
+ui-skipped = not selected, only checked
+ui-target-started = Generating { $target }
+ui-keys-building = ↑/↓ select · tab diagnostics
+ui-keys-done = ↑/↓ select · tab diagnostics · o open output · d run dev · q quit
+ui-choose = Targets to build ({ $picked } of { $total })
+ui-keys-choose = ↑/↓ select · space pick · a all · enter build · q quit
+ui-choose-none = Pick at least one target to build
+ui-dev-unavailable = Select a target that was generated successfully to run its dev server
+
+## Examples
+
+example-unknown = No example named { $name }. The examples are: { $examples }
+example-exists = { $path } already exists; pass --force to replace it
+example-copied = Copied the { $name } example to { $path }. Compile it with: z build { $path }
+new-created = Started a project from the { $name } example in { $dir }. Build it with: z build { $path }
+example-files = { $count ->
+        [one] 1 file
+       *[other] { $count } files
+    }
+examples-checked = { $passed } of { $total } examples compiled
+
+## Telemetry
+
+telemetry-on = Usage metrics are on. Thank you!
+telemetry-collects = Each compile records the target types built, build times, diagnostic codes, the compiler version and the platform; never names, paths or code. See them with: z-cli telemetry show
+telemetry-off = Usage metrics are off, and the events not sent yet were deleted.
+telemetry-status-on = Usage metrics are on.
+telemetry-status-off = Usage metrics are off. Turn them on with: z-cli telemetry on
+telemetry-status-overridden = Usage metrics are on, but { $variable } turns them off in this environment.
+telemetry-endpoint = Events are sent to { $endpoint } in batches of { $batch }.
+telemetry-no-endpoint = No endpoint is configured, so events stay on this machine.
+telemetry-queue = { $count } events waiting in { $path }:
+telemetry-queue-empty = No events are waiting to be sent.
+
+## Internals
+
+assets-dumped = Wrote { $count } embedded assets to { $path }. Point { $variable } at it to use them instead.
+
+## Conformance checks
+
+conformance-invalid = The fixture doesn't compile: { $error }
+conformance-no-compiler = There's no compiler for '{ $kind }' targets
+conformance-compile-failed = Generating failed: { $error }
+conformance-panicked = The compiler panicked: { $error }
+conformance-empty = No files were generated
+conformance-missing = { $path } wasn't generated
+conformance-manifest = { $path } doesn't parse: { $error }
+conformance-absolute-path = { $path } contains the absolute path { $leaked }
+conformance-nondeterministic = { $path } differs between two compiles of the same program
+conformance-passed = { $fixture }: { $count ->
+        [one] 1 file
+       *[other] { $count } files
+    }
+conformance-skipped = { $fixture }: skipped, { $kind } targets don't take its sections
+conformance-failed = { $fixture }:
+conformance-summary = { $kind }: { $passed } fixtures passed, { $skipped } skipped, { $failed } failed
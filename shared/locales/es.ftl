@@ -0,0 +1,336 @@
+# Mensajes en español del compilador y la CLI de Z.
+
+## Compilación
+
+no-targets = No se encontraron bloques de destino en el archivo de entrada.
+no-targets-help = Declara uno, por ejemplo `next web {}`
+detected-targets = Destinos detectados: { $targets }
+unknown-target = { $target } - Tipo de destino desconocido (no está en el registro)
+unknown-target-help = Tipos de destino conocidos: { $targets }
+selected-target-unknown = No hay ningún destino llamado { $name } que compilar. Los destinos son: { $targets }
+excluded-target-unknown = No hay ningún destino llamado { $name } que excluir. Los destinos son: { $targets }
+deprecated-target = el tipo de target { $alias } está obsoleto; ahora se llama { $target }
+deprecated-target-help = Escribe `{ $target }` en lugar de `{ $alias }`; el nombre antiguo dejará de funcionar en una versión futura
+no-compiler = No hay compilador disponible para el destino: { $target }
+empty-target = el destino { $target } no tiene contenido; la aplicación generada será un marcador de posición
+empty-target-help = Añade alguna de las secciones que entiende: { $sections }
+section-ignored = el compilador de { $target } ignora { $section }; no se genera nada a partir de esa sección
+section-ignored-help = Los destinos { $kind } generan código a partir de { $sections }
+declarations-ignored = el compilador de { $target } ignora las líneas escritas directamente dentro del destino
+declarations-ignored-help = Muévelas a alguna de las secciones a partir de las que genera código: { $sections }
+unused-component = el componente { $component } está declarado pero ninguna ruta lo muestra
+unused-component-help = Nómbralo en el `@component(...)` de una ruta, elimínalo o márcalo con `@allow(unused_component)`
+allow-unknown = @allow: ninguna advertencia se llama '{ $name }'{ $suggestion }
+compile-success = { $target } { $name } compilado correctamente ({ $compiler })
+compile-failed = Falló la compilación de { $target } { $name }: { $error }
+target-blocked = { $target } no se generó porque { $prerequisite }, que usa, no se generó
+hook-failed = `{ $command }` falló: { $error }
+hook-exit-code = terminó con el código { $code }
+hook-offline-help = Los hooks se ejecutan sin acceso a la red. Si este lo necesita, por ejemplo para instalar paquetes, agrega `network = true` a [sandbox] en z.toml y apruébalo con `z trust`
+hook-ran = Se ejecutó `{ $command }` ({ $duration } ms)
+span-compile = compilación de { $source }
+span-closed = { $name } tardó { $duration } ms
+compiler-panicked = La compilación de { $target } { $name } se colgó: { $error }
+compiler-panicked-help = Es un error del compilador { $compiler }, no de tu programa; los demás destinos se compilaron igualmente
+parse-error = Error de sintaxis: { $error }
+import-missing = no se puede importar { $path }: { $error }
+import-invalid = `{ $import }` no es una importación; escribe `import "./archivo.z"` o `import nombre from "./archivo.z"`
+import-top-level = el nivel superior
+import-namespace-conflict = { $path } se importa tanto en { $first } como en { $second }; impórtalo con un solo nombre
+import-namespace-workspace = un archivo importado con nombre no puede declarar workspaces; sus destinos ya van al workspace de ese nombre
+imports-unresolved = las importaciones solo se siguen al compilar un archivo; este código no tiene un archivo desde el que importar
+project-created = Proyecto creado en: { $path }
+compiled = Compilado { $source } -> { $out }
+compiled-with-errors = { $source } tiene { $errors ->
+        [one] 1 error
+       *[other] { $errors } errores
+    }; los destinos sin ninguno se generaron igualmente
+target-stats = { $duration } ms, { $files ->
+        [one] 1 archivo
+       *[other] { $files } archivos
+    }, { $bytes ->
+        [one] 1 byte
+       *[other] { $bytes } bytes
+    }
+dry-run-done = Simulación de { $source }: { $changes ->
+        [one] 1 archivo cambiaría
+       *[other] { $changes } archivos cambiarían
+    } en { $out }; no se escribió nada
+determinism-ok = Compilar { $source } dos veces generó los mismos archivos
+determinism-differs = { $path } salió distinto la segunda vez
+determinism-failed = Compilar { $source } dos veces generó { $count ->
+        [one] 1 archivo
+       *[other] { $count } archivos
+    } de forma distinta
+strict-failed = { $source } tiene errores, y el modo estricto hace fallar la compilación por ellos
+archive-format-unknown = { $path } no es un archivo comprimido que z sepa escribir; usa la extensión .zip, .tar o .tar.gz
+warnings-denied = { $source } tiene errores o advertencias, y --deny-warnings hace fallar la compilación por ellos
+members-unsupported = --archive, --emit, --ui, --interactive y --check-determinism funcionan con un programa a la vez; compila un solo archivo .z para usarlos
+members-no-match = { $pattern } no coincide con ningún archivo
+members-none = { $dir } no tiene miembros que compilar; lista los archivos de entrada en su z.toml, p. ej. members = ["apps/*/main.z"]
+member-compiling = ── { $source }
+compiled-members = { $count ->
+        [one] Compilado 1 programa
+       *[other] Compilados { $count } programas
+    } en { $duration }ms
+dry-run-members-done = Simulación de { $count ->
+        [one] 1 programa
+       *[other] { $count } programas
+    }: { $changes ->
+        [one] cambiaría 1 archivo
+       *[other] cambiarían { $changes } archivos
+    }; no se escribió nada
+check-passed = { $source } no tiene errores: { $targets ->
+        [one] 1 destino comprobado
+       *[other] { $targets } destinos comprobados
+    }, { $warnings ->
+        [one] 1 advertencia
+       *[other] { $warnings } advertencias
+    }
+check-failed = { $source } tiene { $errors ->
+        [one] 1 error
+       *[other] { $errors } errores
+    } y { $warnings ->
+        [one] 1 advertencia
+       *[other] { $warnings } advertencias
+    }
+diagnostic-error = error
+diagnostic-warning = advertencia
+diagnostic-help = ayuda
+clean-target-unknown = No hay ningún destino llamado { $name } que limpiar. Los destinos son: { $targets }
+clean-target = Eliminados { $files } archivos generados de { $target } en { $path }
+clean-kept = Se conservó { $path }, editado después de generarse (--overwrite lo elimina)
+clean-cache = Eliminada la caché de compilación en { $path }
+clean-done = { $count ->
+        [one] Limpiado 1 destino
+       *[other] Limpiados { $count } destinos
+    }
+add-target-exists = El programa ya tiene un destino llamado { $name }
+add-exists = { $what } ya está en { $target }
+add-route-empty = { $path } no nombra ninguna ruta; escríbela como una ruta, p. ej. /pricing
+add-no-targets = El programa no tiene destinos; añade uno primero, p. ej. z add target next WebApp
+add-target-ambiguous = El programa tiene varios destinos; indica con --to a cuál añadir: { $targets }
+add-section-unsupported = { $target } no puede tener una sección { $section }: los destinos { $kind } admiten { $sections }
+add-done = Añadido a { $path }
+explain-unknown = Ningún diagnóstico tiene el código { $code }; `z explain` los lista
+explain-wrong = Por ejemplo, este programa lo recibe
+explain-fixed = Corregido
+explain-column-code = CÓDIGO
+explain-column-severity = GRAVEDAD
+explain-column-summary = SIGNIFICADO
+registry-unknown = { $name } no es un tipo de destino, alias ni sección{ $suggestion }
+registry-targets = Tipos de destino:
+registry-sections = Secciones:
+registry-options = Opciones:
+registry-common-options = Opciones de todos los destinos:
+registry-also = también { $names }
+registry-deprecated = { $name } (obsoleto)
+registry-aliases = También se escribe: { $names }
+registry-generator = Lo genera: { $generator }
+registry-built-in = el compilador incorporado
+registry-plugin = el plugin { $command }
+registry-not-generated = nada aún, solo se comprueba
+registry-children = Contiene: { $names }
+registry-in-targets = En los tipos de destino: { $names }
+fmt-failed = No se pudo formatear { $path }: { $error }
+fmt-done = Formateados { $changed } de { $files ->
+        [one] 1 archivo
+       *[other] { $files } archivos
+    }
+fmt-checked = { $unformatted } de { $files ->
+        [one] 1 archivo
+       *[other] { $files } archivos
+    } { $unformatted ->
+        [one] no está formateado
+       *[other] no están formateados
+    }
+dry-run-no-changes = Ningún archivo cambiaría
+dry-run-create = Se crearía { $path }
+dry-run-update = Se actualizaría { $path }
+dry-run-delete = Se eliminaría { $path }
+
+## Sistema de archivos
+
+create-dir-failed = No se pudo crear el directorio { $path }: { $error }
+read-dir-failed = No se pudo leer el directorio { $path }: { $error }
+read-failed = No se pudo leer { $path }: { $error }
+write-failed = No se pudo escribir { $path }: { $error }
+copy-failed = No se pudo copiar { $path }: { $error }
+remove-failed = No se pudo eliminar { $path }: { $error }
+output-dir-occupied = { $path } ya tiene archivos que z no generó; genera el destino en otro lugar, o usa --overwrite para escribir en él de todos modos
+orphan-kept = Se conservó { $path }: se generó antes pero se ha editado desde entonces; elimínalo si ya no lo necesitas
+keep-unplaced = Las regiones marcadas con z:begin-keep en { $path } ya no tenían lugar en él y se movieron al final del archivo
+file-edited = { $path } se editó después de generarse, así que se dejó como estaba
+file-edited-help = Mueve tus cambios a una región z:begin-keep, o compila con --overwrite para regenerar el archivo
+remove-dir-failed = No se pudo eliminar el directorio { $path }: { $error }
+read-source-failed = No se pudo leer el código fuente { $path }: { $error }
+source-not-utf8 = { $path } no es texto UTF-8; guárdalo como UTF-8
+source-too-large = { $path } ocupa { $size } bytes, más que el límite de { $limit } bytes (auméntalo con --max-file-size)
+stdin-too-large = El programa de stdin supera el límite de { $limit } bytes (auméntalo con --max-file-size)
+stdin-unsupported = --ui, --interactive y --check-determinism leen el programa de su archivo; no funcionan con uno recibido por stdin
+emit-several-files = --emit - imprime un solo archivo, pero se generaron { $count }: { $files }. Elige un destino que genere uno, como swift, con --target, o empaquétalos con --archive
+
+## Herramientas externas
+
+tool-exec-failed = No se pudo ejecutar { $tool }: { $error }
+tool-failed = { $tool } falló
+tool-missing-hint = Asegúrate de que { $tool } esté instalado y disponible en tu PATH
+
+## Comandos de proyecto
+
+pm-unknown-target = No hay ningún destino llamado { $name } en { $path } donde ejecutar el comando; sus destinos son { $targets }
+project-missing = El proyecto { $name } todavía no existe. Compílalo primero.
+no-package-manager = No hay gestor de paquetes configurado para { $name } ({ $kind })
+no-command = No se indicó ningún comando para el proyecto { $kind } { $name }
+running-command = Ejecutando { $command } en { $name } ({ $kind })
+command-succeeded = Comando completado correctamente para { $name }
+command-failed = El comando falló para { $name } con código de salida: { $code }
+command-exec-failed = No se pudo ejecutar el comando { $tool } para { $name }: { $error }
+legacy-project-command = `z <proyecto> <comando>` está obsoleto; usa: z pm --source { $source } { $command }
+dev-target-unknown = No se generó ningún destino llamado { $name }. Los destinos son: { $targets }
+dev-no-targets = No se generó correctamente ningún destino de { $source }
+test-results = Pruebas:
+test-passed = pasaron en { $duration }
+test-failed = fallaron con código de salida { $code }
+test-killed = fallaron, terminadas por una señal
+test-not-run = no se ejecutaron, no se pudo iniciar el ejecutor de pruebas
+test-none = sin pruebas
+test-not-built = sin probar, el destino no compiló
+test-summary = { $passed } pasaron, { $failed } fallaron, { $skipped } sin pruebas
+dev-starting = Iniciando { $count } servidores de desarrollo; pulsa Ctrl-C para detenerlos
+dev-server-exited = El servidor de desarrollo terminó con código { $code }
+dev-stopping = Deteniendo { $count } servidores de desarrollo
+watching = Vigilando { $count ->
+        [one] 1 archivo
+       *[other] { $count } archivos
+    } de { $source } en busca de cambios
+watch-changed = Cambiado: { $files }
+
+## Caché remota
+
+cache-restored = { $target } { $name } restaurado desde la caché
+target-unchanged = { $target } { $name } no ha cambiado, se omitió (--force lo regenera)
+cache-unavailable = Se omitió la caché remota: { $error }
+cache-corrupt = entrada de caché corrupta ({ $reason })
+cache-http-failed = el servidor de caché respondió con HTTP { $code }
+user-cache-unavailable = Se omitió la caché de { $path }: { $error }
+user-cache-summary = { $entries ->
+        [one] 1 target
+       *[other] { $entries } targets
+    }, { $size } de { $max }, en { $path }
+user-cache-none = No hay carpeta personal donde guardar la caché; define { $variable } para usar una.
+user-cache-cleaned = { $entries ->
+        [one] Se borró 1 target
+       *[other] Se borraron { $entries } targets
+    }, { $size }, de { $path }
+
+## Publicación
+
+publish-committed = { $branch }: { $files ->
+        [one] 1 archivo confirmado
+       *[other] { $files } archivos confirmados
+    } como { $commit }
+publish-up-to-date = { $branch } ya está al día
+publish-pushed = { $branch } enviado a { $remote }
+publish-no-output = No hay proyectos generados en { $path }. Compílalos primero.
+publish-unknown-target = No hay ningún proyecto generado llamado { $name } en { $path }
+
+## Despliegue
+
+deploy-unknown-target = No hay ningún destino llamado { $name } en { $path }
+
+## Versiones
+
+version-bumped = Versión { $from } → { $to }
+version-updated = Actualizado { $path }
+version-changelog = { $count } commits añadidos a { $path }
+version-tagged = Confirmado y etiquetado { $tag }
+
+## Fallos
+
+crash-header = El compilador ha fallado. Es un error de z, no de tu programa.
+crash-prompt = ¿Escribir un informe del fallo en { $path } para adjuntarlo a un reporte de error? Incluye tus fuentes .z sin credenciales. [S/n]
+crash-written = Informe del fallo escrito en { $path }. Adjúntalo a una nueva incidencia en { $url }
+crash-hint = Ejecuta con Z_CRASH_REPORT=1 para escribir un informe del fallo para un reporte de error.
+
+## Sandbox
+
+trust-nothing = z.toml no pide permisos además de los del sandbox por defecto.
+trust-already = Los permisos de z.toml ya están aprobados.
+trust-grants = z.toml en { $path } pide que los comandos del proyecto puedan:
+trust-prompt = ¿Aprobar? [s/N]
+trust-needs-yes = No se está ejecutando en una terminal; pasa --yes para aprobar.
+trust-recorded = Aprobado. Cambiar estos permisos en z.toml requerirá aprobarlos de nuevo.
+
+## Dependencias
+
+dependency-incompatible = { $package } { $version } está fuera del rango que se sabe que funciona ({ $range })
+dependency-unchecked = { $package } { $version } no se puede comprobar con el rango que se sabe que funciona ({ $range })
+dependency-needs-force = { $problem }. Para usarlo de todos modos, pon force = true en { $package } dentro de [dependencies.{ $kind }] de z.toml
+dependency-not-used = el proyecto generado no depende de { $package }; revisa su nombre en z.toml
+
+## Panel de compilación
+
+ui-building = Compilando… { $seconds }s
+ui-done = Listo en { $seconds }s
+ui-failed = Falló tras { $seconds }s
+ui-targets = Destinos
+ui-diagnostics = Diagnósticos
+ui-log = Registro
+ui-unchanged = sin cambios
+ui-skipped = no seleccionado, solo verificado
+ui-target-started = Generando { $target }
+ui-keys-building = ↑/↓ seleccionar · tab diagnósticos
+ui-keys-done = ↑/↓ seleccionar · tab diagnósticos · o abrir salida · d ejecutar dev · q salir
+ui-choose = Destinos a compilar ({ $picked } de { $total })
+ui-keys-choose = ↑/↓ seleccionar · espacio elegir · a todos · enter compilar · q salir
+ui-choose-none = Elige al menos un destino para compilar
+ui-dev-unavailable = Selecciona un destino generado correctamente para ejecutar su servidor de desarrollo
+
+## Ejemplos
+
+example-unknown = No hay ningún ejemplo llamado { $name }. Los ejemplos son: { $examples }
+example-exists = { $path } ya existe; usa --force para reemplazarlo
+example-copied = Ejemplo { $name } copiado en { $path }. Compílalo con: z build { $path }
+new-created = Proyecto iniciado a partir del ejemplo { $name } en { $dir }. Compílalo con: z build { $path }
+example-files = { $count ->
+        [one] 1 archivo
+       *[other] { $count } archivos
+    }
+examples-checked = { $passed } de { $total } ejemplos compilados
+
+## Telemetría
+
+telemetry-on = Las métricas de uso están activadas. ¡Gracias!
+telemetry-collects = Cada compilación registra los tipos de target generados, los tiempos de compilación, los códigos de diagnóstico, la versión del compilador y la plataforma; nunca nombres, rutas ni código. Puedes verlas con: z-cli telemetry show
+telemetry-off = Las métricas de uso están desactivadas y se borraron los eventos sin enviar.
+telemetry-status-on = Las métricas de uso están activadas.
+telemetry-status-off = Las métricas de uso están desactivadas. Actívalas con: z-cli telemetry on
+telemetry-status-overridden = Las métricas de uso están activadas, pero { $variable } las desactiva en este entorno.
+telemetry-endpoint = Los eventos se envían a { $endpoint } en lotes de { $batch }.
+telemetry-no-endpoint = No hay ningún endpoint configurado, así que los eventos se quedan en esta máquina.
+telemetry-queue = { $count } eventos esperando en { $path }:
+telemetry-queue-empty = No hay eventos esperando a ser enviados.
+
+## Internals
+
+assets-dumped = Se escribieron { $count } recursos integrados en { $path }. Apunta { $variable } a esa carpeta para usarlos en su lugar.
+
+## Conformance checks
+
+conformance-invalid = El programa de prueba no compila: { $error }
+conformance-no-compiler = No hay compilador para los targets '{ $kind }'
+conformance-compile-failed = La generación falló: { $error }
+conformance-panicked = El compilador entró en pánico: { $error }
+conformance-empty = No se generó ningún archivo
+conformance-missing = No se generó { $path }
+conformance-manifest = { $path } no se puede leer: { $error }
+conformance-absolute-path = { $path } contiene la ruta absoluta { $leaked }
+conformance-nondeterministic = { $path } cambia entre dos compilaciones del mismo programa
+conformance-passed = { $fixture }: { $count ->
+        [one] 1 archivo
+       *[other] { $count } archivos
+    }
+conformance-skipped = { $fixture }: omitido, los targets { $kind } no aceptan sus secciones
+conformance-failed = { $fixture }:
+conformance-summary = { $kind }: { $passed } programas de prueba pasaron, { $skipped } omitidos, { $failed } fallaron
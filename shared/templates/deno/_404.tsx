@@ -0,0 +1,15 @@
+import { Head } from "$fresh/runtime.ts";
+
+export default function NotFound() {
+  return (
+    <>
+      <Head>
+        <title>404 - Page not found</title>
+      </Head>
+      <main>
+        <h1>Page not found</h1>
+        <a href="/">Go home</a>
+      </main>
+    </>
+  );
+}
@@ -0,0 +1,23 @@
+import { type PageProps } from "$fresh/server.ts";
+
+export default function App({ Component }: PageProps) {
+  return (
+    <html lang="{{ app.locale }}">
+      <head>
+        <meta charset="utf-8" />
+        <meta name="viewport" content="width=device-width, initial-scale=1.0" />
+        <title>{{ app.name }}</title>
+{% if app.description %}
+        <meta name="description" content={{{ app.description | json }}} />
+{% endif %}
+{% if favicon %}
+        <link rel="icon" href="{{ favicon }}" />
+{% endif %}
+        <link rel="stylesheet" href="/styles.css" />
+      </head>
+      <body>
+        <Component />
+      </body>
+    </html>
+  );
+}
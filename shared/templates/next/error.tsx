@@ -0,0 +1,19 @@
+'use client'
+
+import { Button } from '@/components/ui/button'
+
+export default function Error({
+  error,
+  reset,
+}: {
+  error: Error & { digest?: string }
+  reset: () => void
+}) {
+  return (
+    <div className="flex min-h-screen flex-col items-center justify-center gap-4">
+      <h2 className="text-2xl font-semibold">Something went wrong</h2>
+      <p className="text-slate-600 dark:text-slate-400">{error.message}</p>
+      <Button onClick={() => reset()}>Try again</Button>
+    </div>
+  )
+}
@@ -0,0 +1,25 @@
+import type { Metadata } from 'next'
+import { Inter } from 'next/font/google'
+{% if trpc %}
+import { Providers } from './providers'
+{% endif %}
+import './globals.css'
+
+const inter = Inter({ subsets: ['latin'] })
+
+export const metadata: Metadata = {
+  title: {{ app.name | json }},
+  description: {% if app.description %}{{ app.description | json }}{% else %}"Generated by Z compiler"{% endif %},
+}
+
+export default function RootLayout({
+  children,
+}: {
+  children: React.ReactNode
+}) {
+  return (
+    <html lang="{{ app.locale }}">
+      <body className={inter.className}>{% if trpc %}<Providers>{children}</Providers>{% else %}{children}{% endif %}</body>
+    </html>
+  )
+}
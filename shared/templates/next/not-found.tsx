@@ -0,0 +1,10 @@
+import Link from 'next/link'
+
+export default function NotFound() {
+  return (
+    <div className="flex min-h-screen flex-col items-center justify-center gap-4">
+      <h2 className="text-2xl font-semibold">Page not found</h2>
+      <Link href="/" className="text-slate-600 underline dark:text-slate-400">Go home</Link>
+    </div>
+  )
+}
@@ -0,0 +1,68 @@
+// Generated by Z compiler for Rust
+{% if profile.standard %}
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+#[cfg(feature = "wasm")]
+#[wasm_bindgen]
+extern "C" {
+    #[wasm_bindgen(js_namespace = console)]
+    fn log(s: &str);
+}
+
+#[cfg(feature = "wasm")]
+macro_rules! console_log {
+    ($($t:tt)*) => (log(&format_args!($($t)*).to_string()))
+}
+
+{{ sections }}#[derive(Debug, Serialize, Deserialize)]
+pub struct ZGeneratedApp {
+    pub name: String,
+    pub version: String,
+}
+
+impl ZGeneratedApp {
+    pub fn new() -> Self {
+        Self {
+            name: {{ app.name | json }}.to_string(),
+            version: env!("CARGO_PKG_VERSION").to_string(),
+        }
+    }
+
+    pub fn run(&self) {
+        println!("Running {} v{}", self.name, self.version);
+        // Application logic here
+    }
+}
+
+#[cfg(not(feature = "wasm"))]
+fn main() {
+{% if profile.full %}
+    tracing_subscriber::fmt::init();
+{% endif %}
+    println!("Welcome to {}!", {{ app.name | json }});
+
+    // Initialize application
+    let app = ZGeneratedApp::new();
+    app.run();
+}
+
+#[cfg(feature = "wasm")]
+#[wasm_bindgen(start)]
+pub fn main() {
+    console_log!("{} WebAssembly module loaded!", {{ app.name | json }});
+}
+
+#[cfg(feature = "wasm")]
+#[wasm_bindgen]
+pub fn create_app() -> JsValue {
+    let app = ZGeneratedApp::new();
+    serde_wasm_bindgen::to_value(&app).unwrap()
+}
+{% else %}
+
+{{ sections }}fn main() {
+    println!("Welcome to {}!", {{ app.name | json }});
+}
+{% endif %}
@@ -0,0 +1,24 @@
+// Package.swift
+// swift-tools-version: 5.9
+import PackageDescription
+
+let package = Package(
+    name: "ZGeneratedApp",
+    platforms: [
+        .iOS(.v{{ platforms.ios }}),
+        .macOS(.v{{ platforms.macos }})
+    ],
+    products: [
+        .executable(
+            name: "ZGeneratedApp",
+            targets: ["ZGeneratedApp"]
+        ),
+    ],
+    dependencies: [],
+    targets: [
+        .executableTarget(
+            name: "ZGeneratedApp",
+            dependencies: []
+        ),
+    ]
+)
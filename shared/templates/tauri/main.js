@@ -0,0 +1,53 @@
+// Generated by Z compiler for Tauri frontend
+import { invoke } from '@tauri-apps/api/tauri';
+
+{% if profile.standard %}
+let counter = 0;
+
+{% endif %}
+{{ sections }}// Initialize the app
+document.addEventListener('DOMContentLoaded', async () => {
+{% if profile.standard %}
+  const counterEl = document.getElementById('counter');
+  const incrementBtn = document.getElementById('increment-btn');
+{% endif %}
+  const greetBtn = document.getElementById('greet-btn');
+  const statusEl = document.getElementById('status');
+
+{% if profile.standard %}
+  // Load initial state
+  try {
+    const state = await invoke('get_app_state');
+    counter = state.counter;
+    if (counterEl) counterEl.textContent = counter;
+    if (statusEl) statusEl.textContent = `${state.name} v${state.version} loaded`;
+  } catch (error) {
+    if (statusEl) statusEl.textContent = `Error: ${error}`;
+  }
+
+  // Increment counter
+  if (incrementBtn) {
+    incrementBtn.addEventListener('click', async () => {
+      try {
+        counter = await invoke('increment_counter');
+        if (counterEl) counterEl.textContent = counter;
+        if (statusEl) statusEl.textContent = `Counter incremented to ${counter}`;
+      } catch (error) {
+        if (statusEl) statusEl.textContent = `Error: ${error}`;
+      }
+    });
+  }
+
+{% endif %}
+  // Greet button
+  if (greetBtn) {
+    greetBtn.addEventListener('click', async () => {
+      try {
+        const greeting = await invoke('greet', { name: 'Z User' });
+        if (statusEl) statusEl.textContent = greeting;
+      } catch (error) {
+        if (statusEl) statusEl.textContent = `Error: ${error}`;
+      }
+    });
+  }
+});
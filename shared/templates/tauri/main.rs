@@ -0,0 +1,64 @@
+// Generated by Z compiler for Tauri backend
+#![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
+
+{% if profile.standard %}
+use tauri::{command, State, Manager};
+use serde::{Deserialize, Serialize};
+use std::sync::Mutex;
+{% else %}
+use tauri::command;
+use serde::{Deserialize, Serialize};
+{% endif %}
+
+{{ sections }}{% if profile.standard %}#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct AppState {
+    pub name: String,
+    pub version: String,
+    pub counter: i32,
+}
+
+impl Default for AppState {
+    fn default() -> Self {
+        Self {
+            name: {{ app.name | json }}.to_string(),
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            counter: 0,
+        }
+    }
+}
+
+#[command]
+async fn get_app_state(state: State<'_, Mutex<AppState>>) -> Result<AppState, String> {
+    let app_state = state.lock().map_err(|e| e.to_string())?;
+    Ok(app_state.clone())
+}
+
+#[command]
+async fn increment_counter(state: State<'_, Mutex<AppState>>) -> Result<i32, String> {
+    let mut app_state = state.lock().map_err(|e| e.to_string())?;
+    app_state.counter += 1;
+    Ok(app_state.counter)
+}
+
+{% endif %}
+#[command]
+async fn greet(name: &str) -> Result<String, String> {
+    Ok(format!("Hello, {}! You've been greeted from Rust!", name))
+}
+
+fn main() {
+    tauri::Builder::default()
+{% if profile.standard %}
+        .manage(Mutex::new(AppState::default()))
+        .invoke_handler(tauri::generate_handler![get_app_state, increment_counter, greet])
+{% else %}
+        .invoke_handler(tauri::generate_handler![greet])
+{% endif %}
+        .setup(|app| {
+            // Additional setup logic here
+            println!("{} started!", {{ app.name | json }});
+            Ok(())
+        })
+        .run(tauri::generate_context!())
+        .expect("error while running tauri application");
+}